@@ -0,0 +1,502 @@
+use crate::entity::{Entities, EntityId};
+use crate::{geom, TagType};
+
+/// Two entities touching, reported to [`crate::Game::handle_collisions`]
+/// and [`crate::Game::handle_triggers`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Contact<T: TagType> {
+    /// The first entity
+    pub a: EntityId,
+    /// The first entity's tag
+    pub tag_a: T,
+    /// The second entity
+    pub b: EntityId,
+    /// The second entity's tag
+    pub tag_b: T,
+    /// For a trigger contact, how far the two boxes overlap on each
+    /// axis; for a collision, how far `a` was pushed out of `b`
+    pub amount: geom::Vec2,
+}
+
+/// Whether a colliding entity pushes others out of itself, can be
+/// pushed out of others, or both; see [`Collision`].
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CollisionFlags(u8);
+
+/// How an entity collides with others.  Only colliding entities
+/// block each other; triggers just report overlaps with each other
+/// and with colliding entities.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Collision {
+    /// Doesn't collide with anything, e.g. decorations
+    None,
+    /// Reports overlaps but doesn't block anything, e.g. pickups
+    Trigger,
+    /// Blocks or is blocked by other colliding entities
+    Colliding(CollisionFlags),
+}
+
+impl Collision {
+    const PUSHABLE: u8 = 0b01;
+    const SOLID: u8 = 0b10;
+    /// Pushes pushable entities out of itself but doesn't move, e.g. walls.
+    pub fn solid() -> Self {
+        Self::Colliding(CollisionFlags(Self::SOLID))
+    }
+    /// Gets pushed out of solid entities, e.g. a player.
+    pub fn pushable() -> Self {
+        Self::Colliding(CollisionFlags(Self::PUSHABLE))
+    }
+    /// Pushes and is pushed by other pushable solid entities, each
+    /// moving half the way, e.g. crates.
+    pub fn pushable_solid() -> Self {
+        Self::Colliding(CollisionFlags(Self::PUSHABLE | Self::SOLID))
+    }
+    /// Doesn't collide.
+    pub fn none() -> Self {
+        Self::None
+    }
+    /// Reports overlaps without blocking.
+    pub fn trigger() -> Self {
+        Self::Trigger
+    }
+    /// Whether this pushes pushable entities out of itself.
+    pub fn is_solid(&self) -> bool {
+        matches!(self, Self::Colliding(flags) if (flags.0 & Self::SOLID) == Self::SOLID)
+    }
+    /// Whether this gets pushed out of solid entities.
+    pub fn is_pushable(&self) -> bool {
+        matches!(self, Self::Colliding(flags) if (flags.0 & Self::PUSHABLE) == Self::PUSHABLE)
+    }
+    /// Whether this is both solid and pushable.
+    pub fn is_pushable_solid(&self) -> bool {
+        self.is_solid() && self.is_pushable()
+    }
+    /// Whether this doesn't collide.
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+    /// Whether this only reports overlaps.
+    pub fn is_trigger(&self) -> bool {
+        matches!(self, Self::Trigger)
+    }
+}
+
+// An entity's box as of the start of a collision pass
+struct Body<T: TagType> {
+    id: EntityId,
+    rect: geom::Rect,
+    collision: Collision,
+    tag: T,
+}
+
+pub(crate) struct Contacts<T: TagType> {
+    pub(crate) triggers: Vec<Contact<T>>,
+    pub(crate) displacements: Vec<Contact<T>>,
+    bodies: Vec<Body<T>>,
+    pairs: Vec<(EntityId, EntityId, geom::Vec2)>,
+}
+
+impl<T: TagType> Contacts<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            triggers: Vec::with_capacity(32),
+            displacements: Vec::with_capacity(32),
+            bodies: Vec::with_capacity(256),
+            pairs: Vec::with_capacity(32),
+        }
+    }
+    // Gathers the active scene's entities which `include` accepts
+    fn gather(
+        &mut self,
+        entities: &Entities<T>,
+        scene: usize,
+        include: impl Fn(Collision) -> bool,
+    ) {
+        self.bodies.clear();
+        self.bodies.extend(
+            entities
+                .iter()
+                .filter(|(_id, e)| e.scene == scene && include(e.collision))
+                .map(|(id, e)| Body {
+                    id,
+                    rect: e.aabb.into(),
+                    collision: e.collision,
+                    tag: e.tag,
+                }),
+        );
+    }
+}
+
+// Calls `overlap` on every overlapping pair of bodies, found by
+// sorting them left to right and sweeping across
+fn sweep<T: TagType>(
+    bodies: &mut [Body<T>],
+    mut overlap: impl FnMut(&Body<T>, &Body<T>, geom::Vec2),
+) {
+    bodies.sort_by(|a, b| a.rect.corner.x.total_cmp(&b.rect.corner.x));
+    for (i, body_i) in bodies.iter().enumerate() {
+        let right = body_i.rect.corner.x + body_i.rect.size.x;
+        for body_j in bodies[i + 1..]
+            .iter()
+            .take_while(|body| body.rect.corner.x <= right)
+        {
+            if let Some(disp) = body_i.rect.displacement(body_j.rect) {
+                overlap(body_i, body_j, disp);
+            }
+        }
+    }
+}
+
+// Pushes overlapping colliding entities of the active scene apart,
+// recording each push in `contacts.displacements`
+pub(crate) fn do_collisions<T: TagType>(
+    entities: &mut Entities<T>,
+    scene: usize,
+    contacts: &mut Contacts<T>,
+) {
+    contacts.gather(entities, scene, |c| matches!(c, Collision::Colliding(_)));
+    let Contacts { bodies, pairs, .. } = contacts;
+    sweep(bodies, |i, j, disp| {
+        if i.tag > j.tag {
+            pairs.push((i.id, j.id, disp));
+        } else {
+            pairs.push((j.id, i.id, disp));
+        }
+    });
+    // Resolve the deepest overlaps first
+    pairs.sort_by(|p1, p2| p2.2.length_squared().total_cmp(&p1.2.length_squared()));
+    for (ci, cj, _contact_disp) in contacts.pairs.drain(..) {
+        let (Some(char_i), Some(char_j)) = (entities.get(ci), entities.get(cj)) else {
+            continue;
+        };
+        let (flags_i, flags_j) = (char_i.collision, char_j.collision);
+        // if neither is solid, there's no actual occlusion
+        if !flags_i.is_solid() && !flags_j.is_solid() {
+            continue;
+        }
+        // if both are impushable, there's nothing to do
+        if !flags_i.is_pushable() && !flags_j.is_pushable() {
+            continue;
+        }
+        // Earlier pushes this step may have separated them already
+        let disp = char_j
+            .aabb
+            .displacement(char_i.aabb)
+            .unwrap_or(geom::Vec2::ZERO);
+        if disp.x.abs() < f32::EPSILON || disp.y.abs() < f32::EPSILON {
+            continue;
+        }
+        let (pos_i, pos_j) = (char_i.aabb.center, char_j.aabb.center);
+        let (disp_i, disp_j) = compute_disp(pos_i, flags_i, pos_j, flags_j, disp);
+        displace(entities, ci, cj, disp_i, &mut contacts.displacements);
+        displace(entities, cj, ci, disp_j, &mut contacts.displacements);
+    }
+}
+
+// Moves `id` by `amt` along whichever axis needs the smaller push
+fn displace<T: TagType>(
+    entities: &mut Entities<T>,
+    id: EntityId,
+    other: EntityId,
+    amt: geom::Vec2,
+    displacements: &mut Vec<Contact<T>>,
+) {
+    if amt == geom::Vec2::ZERO {
+        return;
+    }
+    let Some(tag_b) = entities.get(other).map(|e| e.tag) else {
+        return;
+    };
+    let Some(entity) = entities.get_mut(id) else {
+        return;
+    };
+    let amount = if amt.x.abs() < amt.y.abs() {
+        geom::Vec2 { x: amt.x, y: 0.0 }
+    } else {
+        geom::Vec2 { x: 0.0, y: amt.y }
+    };
+    entity.aabb.center += amount;
+    displacements.push(Contact {
+        a: id,
+        tag_a: entity.tag,
+        b: other,
+        tag_b,
+        amount,
+    });
+}
+
+fn compute_disp(
+    pos_i: geom::Vec2,
+    flags_i: Collision,
+    pos_j: geom::Vec2,
+    flags_j: Collision,
+    mut disp: geom::Vec2,
+) -> (geom::Vec2, geom::Vec2) {
+    // Preconditions: at least one is pushable and at least one is solid
+    assert!(flags_i.is_pushable() || flags_j.is_pushable());
+    assert!(flags_i.is_solid() || flags_j.is_solid());
+    // i is left of j, push i left
+    if pos_i.x < pos_j.x {
+        disp.x *= -1.0;
+    }
+    // i is below j, push i down
+    if pos_i.y < pos_j.y {
+        disp.y *= -1.0;
+    }
+    if flags_i.is_pushable_solid() && flags_j.is_pushable_solid() {
+        // both are pushable and solid, split the push
+        (disp / 2.0, -disp / 2.0)
+    } else if !flags_i.is_pushable() && flags_j.is_pushable() {
+        // only j can move
+        (geom::Vec2::ZERO, -disp)
+    } else {
+        // only i can move
+        (disp, geom::Vec2::ZERO)
+    }
+}
+
+// Records every overlap of the active scene's triggers with each
+// other and with colliding entities in `contacts.triggers`
+pub(crate) fn gather_triggers<T: TagType>(
+    entities: &Entities<T>,
+    scene: usize,
+    contacts: &mut Contacts<T>,
+) {
+    contacts.gather(entities, scene, |c| !c.is_none());
+    let Contacts {
+        bodies, triggers, ..
+    } = contacts;
+    sweep(bodies, |i, j, amount| {
+        if !i.collision.is_trigger() && !j.collision.is_trigger() {
+            return;
+        }
+        let (i, j) = if i.tag > j.tag { (j, i) } else { (i, j) };
+        triggers.push(Contact {
+            a: i.id,
+            tag_a: i.tag,
+            b: j.id,
+            tag_b: j.tag,
+            amount,
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Entity;
+    use crate::Spritesheet;
+    use frenderer::sprites::SheetRegion;
+
+    fn spawn(
+        entities: &mut Entities<u8>,
+        tag: u8,
+        center: [f32; 2],
+        size: [f32; 2],
+        collision: Collision,
+    ) -> EntityId {
+        entities.spawn(Entity {
+            aabb: geom::AABB {
+                center: center.into(),
+                size: size.into(),
+            },
+            vel: geom::Vec2::ZERO,
+            region: SheetRegion::ZERO,
+            sheet: Spritesheet(0),
+            collision,
+            tag,
+            scene: 0,
+        })
+    }
+    fn pos(entities: &Entities<u8>, id: EntityId) -> geom::Vec2 {
+        entities.get(id).unwrap().pos()
+    }
+
+    #[test]
+    fn test_no_entities() {
+        let mut entities = Entities::<u8>::new();
+        let mut contacts = Contacts::new();
+        do_collisions(&mut entities, 0, &mut contacts);
+        gather_triggers(&entities, 0, &mut contacts);
+        assert!(contacts.displacements.is_empty());
+        assert!(contacts.triggers.is_empty());
+    }
+    #[test]
+    fn test_wall_pushes_player() {
+        let mut entities = Entities::new();
+        let wall = spawn(
+            &mut entities,
+            0,
+            [0.0, 0.0],
+            [10.0, 10.0],
+            Collision::solid(),
+        );
+        let player = spawn(
+            &mut entities,
+            1,
+            [7.0, 1.0],
+            [6.0, 6.0],
+            Collision::pushable(),
+        );
+        let mut contacts = Contacts::new();
+        do_collisions(&mut entities, 0, &mut contacts);
+        // The player is pushed out along the axis it overlaps least
+        assert_eq!(pos(&entities, player), geom::Vec2::new(8.0, 1.0));
+        assert_eq!(pos(&entities, wall), geom::Vec2::ZERO);
+        assert_eq!(
+            contacts.displacements,
+            [Contact {
+                a: player,
+                tag_a: 1,
+                b: wall,
+                tag_b: 0,
+                amount: geom::Vec2::new(1.0, 0.0),
+            }]
+        );
+        // Nothing moves once they're apart
+        contacts.displacements.clear();
+        do_collisions(&mut entities, 0, &mut contacts);
+        assert!(contacts.displacements.is_empty());
+    }
+    #[test]
+    fn test_crates_split_push() {
+        let mut entities = Entities::new();
+        let crate_a = spawn(
+            &mut entities,
+            0,
+            [0.0, 0.0],
+            [10.0, 10.0],
+            Collision::pushable_solid(),
+        );
+        let crate_b = spawn(
+            &mut entities,
+            0,
+            [8.0, 0.0],
+            [10.0, 10.0],
+            Collision::pushable_solid(),
+        );
+        let mut contacts = Contacts::new();
+        do_collisions(&mut entities, 0, &mut contacts);
+        assert_eq!(pos(&entities, crate_a), geom::Vec2::new(-1.0, 0.0));
+        assert_eq!(pos(&entities, crate_b), geom::Vec2::new(9.0, 0.0));
+        assert_eq!(contacts.displacements.len(), 2);
+    }
+    #[test]
+    fn test_non_blocking_pairs() {
+        let mut entities = Entities::new();
+        // Two pushables don't block each other, and neither do two walls
+        let a = spawn(
+            &mut entities,
+            0,
+            [0.0, 0.0],
+            [4.0, 4.0],
+            Collision::pushable(),
+        );
+        let b = spawn(
+            &mut entities,
+            0,
+            [1.0, 0.0],
+            [4.0, 4.0],
+            Collision::pushable(),
+        );
+        spawn(
+            &mut entities,
+            0,
+            [20.0, 0.0],
+            [4.0, 4.0],
+            Collision::solid(),
+        );
+        spawn(
+            &mut entities,
+            0,
+            [21.0, 0.0],
+            [4.0, 4.0],
+            Collision::solid(),
+        );
+        let mut contacts = Contacts::new();
+        do_collisions(&mut entities, 0, &mut contacts);
+        assert!(contacts.displacements.is_empty());
+        assert_eq!(pos(&entities, a), geom::Vec2::ZERO);
+        assert_eq!(pos(&entities, b), geom::Vec2::new(1.0, 0.0));
+    }
+    #[test]
+    fn test_triggers() {
+        let mut entities = Entities::new();
+        let coin = spawn(
+            &mut entities,
+            2,
+            [0.0, 0.0],
+            [4.0, 4.0],
+            Collision::trigger(),
+        );
+        let player = spawn(
+            &mut entities,
+            1,
+            [1.0, 1.0],
+            [4.0, 4.0],
+            Collision::pushable(),
+        );
+        spawn(&mut entities, 0, [1.0, 0.0], [4.0, 4.0], Collision::none());
+        let mut contacts = Contacts::new();
+        do_collisions(&mut entities, 0, &mut contacts);
+        gather_triggers(&entities, 0, &mut contacts);
+        assert!(contacts.displacements.is_empty());
+        assert_eq!(pos(&entities, coin), geom::Vec2::ZERO);
+        // Contacts list the lower tag first
+        assert_eq!(
+            contacts.triggers,
+            [Contact {
+                a: player,
+                tag_a: 1,
+                b: coin,
+                tag_b: 2,
+                amount: geom::Vec2::new(3.0, 3.0),
+            }]
+        );
+    }
+    #[test]
+    fn test_other_scenes_ignored() {
+        let mut entities = Entities::new();
+        let wall = spawn(
+            &mut entities,
+            0,
+            [0.0, 0.0],
+            [10.0, 10.0],
+            Collision::solid(),
+        );
+        let player = spawn(
+            &mut entities,
+            1,
+            [7.0, 1.0],
+            [6.0, 6.0],
+            Collision::pushable(),
+        );
+        entities.get_mut(wall).unwrap().scene = 1;
+        let mut contacts = Contacts::new();
+        do_collisions(&mut entities, 0, &mut contacts);
+        gather_triggers(&entities, 0, &mut contacts);
+        assert!(contacts.displacements.is_empty());
+        assert_eq!(pos(&entities, player), geom::Vec2::new(7.0, 1.0));
+        do_collisions(&mut entities, 1, &mut contacts);
+        assert!(contacts.displacements.is_empty());
+    }
+    #[test]
+    fn test_separated_boxes() {
+        let a = geom::AABB {
+            center: geom::Vec2::ZERO,
+            size: geom::Vec2::new(2.0, 2.0),
+        };
+        let b = geom::AABB {
+            center: geom::Vec2::new(3.0, 0.0),
+            ..a
+        };
+        assert_eq!(a.displacement(b), None);
+        let c = geom::AABB {
+            center: geom::Vec2::new(1.5, 0.5),
+            ..a
+        };
+        assert_eq!(a.displacement(c), Some(geom::Vec2::new(0.5, 1.5)));
+    }
+}
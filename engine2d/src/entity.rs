@@ -0,0 +1,165 @@
+use crate::geom;
+use crate::{Collision, Spritesheet, TagType};
+use frenderer::sprites::SheetRegion;
+
+/// Names an entity spawned with [`crate::Engine::spawn`].  Ids are
+/// never reused: once an entity is despawned, looking its id up gives
+/// `None` even if its slot holds a newer entity.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+/// A game object: a box which moves at some velocity, collides with
+/// other entities according to its [`Collision`], and is drawn as a
+/// region of a [`Spritesheet`].
+#[derive(Clone, Debug)]
+pub struct Entity<Tag: TagType> {
+    pub(crate) aabb: geom::AABB,
+    pub(crate) vel: geom::Vec2,
+    pub(crate) region: SheetRegion,
+    pub(crate) sheet: Spritesheet,
+    pub(crate) collision: Collision,
+    pub(crate) tag: Tag,
+    pub(crate) scene: usize,
+}
+
+impl<Tag: TagType> Entity<Tag> {
+    /// The center of the entity's box.
+    pub fn pos(&self) -> geom::Vec2 {
+        self.aabb.center
+    }
+    /// Moves the entity's box to be centered on `p`.
+    pub fn set_pos(&mut self, p: geom::Vec2) {
+        self.aabb.center = p;
+    }
+    /// The entity's box, which is both where it's drawn and what it collides with.
+    pub fn aabb(&self) -> geom::AABB {
+        self.aabb
+    }
+    /// Replaces the entity's box.
+    pub fn set_aabb(&mut self, b: geom::AABB) {
+        self.aabb = b;
+    }
+    /// How far the entity moves each simulation step.
+    pub fn vel(&self) -> geom::Vec2 {
+        self.vel
+    }
+    /// Sets how far the entity moves each simulation step.
+    pub fn set_vel(&mut self, v: geom::Vec2) {
+        self.vel = v;
+    }
+    /// The part of the spritesheet the entity is drawn with.
+    pub fn region(&self) -> SheetRegion {
+        self.region
+    }
+    /// Changes the part of the spritesheet the entity is drawn with,
+    /// e.g. to animate it.
+    pub fn set_region(&mut self, region: SheetRegion) {
+        self.region = region;
+    }
+    /// The spritesheet the entity is drawn from.
+    pub fn spritesheet(&self) -> Spritesheet {
+        self.sheet
+    }
+    /// How the entity collides with others.
+    pub fn collision(&self) -> Collision {
+        self.collision
+    }
+    /// The game-defined kind of entity this is.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+    /// The depth of the scene the entity belongs to, see
+    /// [`crate::Engine::push_scene`].
+    pub fn scene(&self) -> usize {
+        self.scene
+    }
+}
+
+struct Slot<Tag: TagType> {
+    generation: u32,
+    entity: Option<Entity<Tag>>,
+}
+
+// Entities in reusable slots, with generations to tell apart the
+// entities which have lived in each slot
+pub(crate) struct Entities<Tag: TagType> {
+    slots: Vec<Slot<Tag>>,
+    free: Vec<u32>,
+}
+
+impl<Tag: TagType> Entities<Tag> {
+    pub(crate) fn new() -> Self {
+        Self {
+            slots: Vec::with_capacity(256),
+            free: Vec::new(),
+        }
+    }
+    pub(crate) fn spawn(&mut self, entity: Entity<Tag>) -> EntityId {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.entity = Some(entity);
+            EntityId {
+                index,
+                generation: slot.generation,
+            }
+        } else {
+            self.slots.push(Slot {
+                generation: 0,
+                entity: Some(entity),
+            });
+            EntityId {
+                index: self.slots.len() as u32 - 1,
+                generation: 0,
+            }
+        }
+    }
+    pub(crate) fn despawn(&mut self, id: EntityId) -> Option<Entity<Tag>> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let entity = slot.entity.take()?;
+        slot.generation += 1;
+        self.free.push(id.index);
+        Some(entity)
+    }
+    pub(crate) fn get(&self, id: EntityId) -> Option<&Entity<Tag>> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.entity.as_ref())
+    }
+    pub(crate) fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity<Tag>> {
+        self.slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.entity.as_mut())
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (EntityId, &Entity<Tag>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let id = EntityId {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.entity.as_ref().map(|entity| (id, entity))
+        })
+    }
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut Entity<Tag>)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let id = EntityId {
+                    index: index as u32,
+                    generation: slot.generation,
+                };
+                slot.entity.as_mut().map(|entity| (id, entity))
+            })
+    }
+}
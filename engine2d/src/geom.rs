@@ -0,0 +1,111 @@
+//! Rectangles for positioning, collision, and cameras, plus [glam]'s
+//! vector types.
+
+use frenderer::sprites::{Camera2D, Transform};
+pub use glam::*;
+
+/// A rectangle given by its bottom left corner and its size.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, bytemuck::Zeroable, bytemuck::Pod, Debug)]
+pub struct Rect {
+    /// The bottom left corner
+    pub corner: Vec2,
+    /// The width and height
+    pub size: Vec2,
+}
+
+/// An axis-aligned bounding box given by its center and its size.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, bytemuck::Zeroable, bytemuck::Pod, Debug)]
+pub struct AABB {
+    /// The center
+    pub center: Vec2,
+    /// The width and height
+    pub size: Vec2,
+}
+
+impl From<AABB> for Transform {
+    fn from(val: AABB) -> Self {
+        Transform {
+            w: val.size.x as u16,
+            h: val.size.y as u16,
+            x: val.center.x,
+            y: val.center.y,
+            rot: 0.0,
+        }
+    }
+}
+
+impl From<Rect> for Transform {
+    fn from(val: Rect) -> Self {
+        AABB::from(val).into()
+    }
+}
+
+impl From<Rect> for Camera2D {
+    fn from(val: Rect) -> Self {
+        Camera2D {
+            screen_pos: val.corner.into(),
+            screen_size: val.size.into(),
+        }
+    }
+}
+
+impl From<AABB> for Camera2D {
+    fn from(val: AABB) -> Self {
+        Rect::from(val).into()
+    }
+}
+
+impl From<AABB> for Rect {
+    fn from(val: AABB) -> Self {
+        Rect {
+            corner: val.center - val.size / 2.0,
+            size: val.size,
+        }
+    }
+}
+
+impl From<Rect> for AABB {
+    fn from(val: Rect) -> Self {
+        AABB {
+            center: val.corner + val.size / 2.0,
+            size: val.size,
+        }
+    }
+}
+
+impl Rect {
+    /// How far this rectangle overlaps `other` on each axis, or
+    /// `None` if they don't touch.
+    pub fn displacement(&self, other: Rect) -> Option<Vec2> {
+        let x_overlap = (self.corner.x + self.size.x).min(other.corner.x + other.size.x)
+            - self.corner.x.max(other.corner.x);
+        let y_overlap = (self.corner.y + self.size.y).min(other.corner.y + other.size.y)
+            - self.corner.y.max(other.corner.y);
+        if x_overlap >= 0.0 && y_overlap >= 0.0 {
+            Some(Vec2 {
+                x: x_overlap,
+                y: y_overlap,
+            })
+        } else {
+            None
+        }
+    }
+    /// Whether `point` lies inside this rectangle.
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.corner).all() && point.cmple(self.corner + self.size).all()
+    }
+}
+
+impl AABB {
+    /// How far this box overlaps `other` on each axis, or `None` if
+    /// they don't touch.
+    pub fn displacement(&self, other: AABB) -> Option<Vec2> {
+        Rect::from(*self).displacement(Rect::from(other))
+    }
+    /// Whether `point` lies inside this box.
+    pub fn contains(&self, point: Vec2) -> bool {
+        Rect::from(*self).contains(point)
+    }
+}
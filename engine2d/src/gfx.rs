@@ -0,0 +1,39 @@
+use crate::geom;
+
+/// A spritesheet added with [`crate::Engine::add_spritesheet`], from
+/// which entities and text are drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Spritesheet(pub(crate) usize);
+
+/// A bitmap font laid out on a spritesheet, made with
+/// [`crate::Engine::add_font`] and drawn with
+/// [`crate::Engine::draw_string`].
+#[derive(Clone, Copy, Debug)]
+pub struct BitFont {
+    pub(crate) spritesheet: Spritesheet,
+    pub(crate) font: frenderer::bitfont::BitFont,
+}
+
+impl BitFont {
+    /// The spritesheet the font's glyphs are on.
+    pub fn spritesheet(&self) -> Spritesheet {
+        self.spritesheet
+    }
+    /// The same font drawn with a different color modulation, see
+    /// [`frenderer::sprites::SheetRegion::colormod`].
+    pub fn colormod(&self, colormod: [u8; 4]) -> Self {
+        Self {
+            font: self.font.colormod(colormod),
+            ..*self
+        }
+    }
+}
+
+// A string queued for this frame: the font, the text, its top left
+// corner, and the height of each character
+pub(crate) struct TextDraw(
+    pub(crate) BitFont,
+    pub(crate) String,
+    pub(crate) geom::Vec2,
+    pub(crate) f32,
+);
@@ -0,0 +1,409 @@
+//! `engine2d` is a small 2D game engine built on [frenderer]'s
+//! [Renderer].  It brings together the pieces the example engines in
+//! the frenderer repository each had some of:
+//!
+//! - entity storage with generational [EntityId]s, so stale ids never
+//!   alias newer entities;
+//! - axis-aligned box collision with solid, pushable, and trigger
+//!   entities (see [Collision]);
+//! - any number of [Spritesheet]s to draw entities from;
+//! - bitmap text with [BitFont];
+//! - a stack of scenes which own the entities spawned in them (see
+//!   [Engine::push_scene]).
+//!
+//! A game implements [Game] and hands it to [Engine::run]:
+//!
+//! ```no_run
+//! use engine2d::{geom::*, Collision, Engine, Game, SheetRegion};
+//! struct Demo;
+//! impl Game for Demo {
+//!     type Tag = u8;
+//!     fn new(engine: &mut Engine<Self>) -> Self {
+//!         let sheet = engine.add_spritesheet(&[&engine2d::image::RgbaImage::new(16, 16)], None);
+//!         engine.spawn(
+//!             sheet,
+//!             0,
+//!             AABB { center: Vec2::new(32.0, 32.0), size: Vec2::splat(16.0) },
+//!             SheetRegion::rect(0, 0, 16, 16),
+//!             Collision::pushable(),
+//!         );
+//!         Demo
+//!     }
+//!     fn update(&mut self, _engine: &mut Engine<Self>) {}
+//! }
+//! Engine::<Demo>::run(engine2d::winit::window::WindowBuilder::new(), None).unwrap();
+//! ```
+//!
+//! Unlike the example engines, which are teaching material and change
+//! freely, this crate follows semantic versioning: while it's at
+//! 0.x, breaking changes only come with a new minor version.
+
+use std::sync::Arc;
+
+pub use frenderer;
+pub use frenderer::input::{Input, Key};
+use frenderer::{clock::Clock, FrendererEvents};
+pub use frenderer::{
+    sprites::{Camera2D as Camera, SheetRegion, Transform},
+    wgpu, EventPhase, Renderer,
+};
+pub use image;
+pub use winit;
+
+mod collision;
+pub use collision::{Collision, CollisionFlags, Contact};
+mod entity;
+pub use entity::{Entity, EntityId};
+mod gfx;
+use gfx::TextDraw;
+pub use gfx::{BitFont, Spritesheet};
+pub mod geom;
+
+/// How many times per step colliding entities are pushed apart, so
+/// that pushes which cause new overlaps get resolved too
+const COLLISION_STEPS: usize = 3;
+
+/// The kinds of entity a game has, usually a fieldless enum.  Tags
+/// are ordered so that each [Contact] lists the lesser tag first.
+pub trait TagType: Copy + Eq + Ord + std::fmt::Debug {}
+impl<T: Copy + Eq + Ord + std::fmt::Debug> TagType for T {}
+
+/// A game run by an [Engine].  Each simulation step calls
+/// [Game::update], moves the active scene's entities by their
+/// velocities, resolves collisions (calling
+/// [Game::handle_collisions]), and reports trigger overlaps to
+/// [Game::handle_triggers].  Each frame then calls [Game::render]
+/// and draws every entity and string.
+pub trait Game: Sized + 'static {
+    /// The kinds of entity the game has
+    type Tag: TagType;
+    /// Seconds per simulation step
+    const DT: f32 = 1.0 / 60.0;
+    /// Set up the game, e.g. adding spritesheets and spawning entities.
+    fn new(engine: &mut Engine<Self>) -> Self;
+    /// Simulate one step, before entities move.
+    fn update(&mut self, engine: &mut Engine<Self>);
+    /// Respond to colliding entities having been pushed apart; each
+    /// contact's `a` was pushed out of its `b`.  Called up to a few
+    /// times per step.
+    fn handle_collisions(&mut self, _engine: &mut Engine<Self>, _contacts: &[Contact<Self::Tag>]) {}
+    /// Respond to triggers overlapping each other or colliding entities.
+    fn handle_triggers(&mut self, _engine: &mut Engine<Self>, _contacts: &[Contact<Self::Tag>]) {}
+    /// Prepare the frame, e.g. with [Engine::draw_string] or
+    /// [Engine::set_camera], before entities are drawn.
+    fn render(&mut self, _engine: &mut Engine<Self>) {}
+}
+
+/// Owns the window, [Renderer], input, entities, and scenes, and
+/// runs a [Game]; see the [crate] docs.
+pub struct Engine<G: Game> {
+    /// The renderer, for anything beyond what the engine draws
+    pub renderer: Renderer,
+    /// This step's input
+    pub input: Input,
+    camera: Camera,
+    clock: Clock,
+    window: Arc<winit::window::Window>,
+    entities: entity::Entities<G::Tag>,
+    // Each spritesheet's sprite group
+    sheets: Vec<usize>,
+    texts: Vec<TextDraw>,
+    scene: usize,
+}
+
+impl<G: Game> Engine<G> {
+    /// Opens a window built from `builder` (rendering at
+    /// `render_size`, or the window's size if `None`) and runs `G`
+    /// until the window closes.
+    pub fn run(
+        builder: winit::window::WindowBuilder,
+        render_size: Option<(u32, u32)>,
+    ) -> Result<(), frenderer::FrendererError> {
+        let drv = frenderer::Driver::new(builder, render_size);
+        drv.run_event_loop::<(), _>(
+            move |window, renderer| {
+                let (w, h) = renderer.render_size();
+                let mut engine = Self {
+                    renderer,
+                    input: Input::default(),
+                    window,
+                    camera: Camera {
+                        screen_pos: [0.0, 0.0],
+                        screen_size: [w as f32, h as f32],
+                    },
+                    clock: Clock::new(G::DT, 0.0002, 5),
+                    entities: entity::Entities::new(),
+                    sheets: vec![],
+                    texts: Vec::with_capacity(128),
+                    scene: 0,
+                };
+                let game = G::new(&mut engine);
+                (engine, game, collision::Contacts::new())
+            },
+            move |event, target, (engine, game, contacts)| {
+                engine.run_step(event, target, game, contacts);
+            },
+        )
+    }
+    fn run_step(
+        &mut self,
+        event: winit::event::Event<()>,
+        target: &winit::event_loop::EventLoopWindowTarget<()>,
+        game: &mut G,
+        contacts: &mut collision::Contacts<G::Tag>,
+    ) {
+        match self.renderer.handle_event(
+            &mut self.clock,
+            &self.window,
+            &event,
+            target,
+            &mut self.input,
+        ) {
+            EventPhase::Run(steps) => {
+                for _ in 0..steps {
+                    self.simulate(game, contacts);
+                    self.input.next_frame();
+                }
+                game.render(self);
+                self.draw();
+                self.renderer.render();
+                self.texts.clear();
+            }
+            EventPhase::Quit => {
+                target.exit();
+            }
+            EventPhase::Wait => {}
+        }
+    }
+    fn simulate(&mut self, game: &mut G, contacts: &mut collision::Contacts<G::Tag>) {
+        game.update(self);
+        let scene = self.scene;
+        for (_id, entity) in self.entities.iter_mut() {
+            if entity.scene == scene {
+                entity.aabb.center += entity.vel;
+            }
+        }
+        for _iter in 0..COLLISION_STEPS {
+            collision::do_collisions(&mut self.entities, scene, contacts);
+            if !contacts.displacements.is_empty() {
+                game.handle_collisions(self, &contacts.displacements);
+            }
+            contacts.displacements.clear();
+        }
+        collision::gather_triggers(&self.entities, scene, contacts);
+        if !contacts.triggers.is_empty() {
+            game.handle_triggers(self, &contacts.triggers);
+        }
+        contacts.triggers.clear();
+    }
+    // Writes every entity and queued string into its spritesheet's sprite group
+    fn draw(&mut self) {
+        for (sheet, &group) in self.sheets.iter().enumerate() {
+            let sheet = Spritesheet(sheet);
+            let entity_count = self
+                .entities
+                .iter()
+                .filter(|(_id, e)| e.sheet == sheet)
+                .count();
+            // draw_text uses up to one sprite per byte
+            let text_len: usize = self
+                .texts
+                .iter()
+                .filter(|t| t.0.spritesheet == sheet)
+                .map(|t| t.1.len())
+                .sum();
+            self.renderer
+                .sprite_group_resize(group, entity_count + text_len);
+            self.renderer.sprite_group_set_camera(group, self.camera);
+            let (trfs, uvs) = self.renderer.sprites_mut(group, ..);
+            let entities = self.entities.iter().filter(|(_id, e)| e.sheet == sheet);
+            for ((_id, entity), (trf, uv)) in entities.zip(trfs.iter_mut().zip(uvs.iter_mut())) {
+                *trf = entity.aabb.into();
+                *uv = entity.region;
+            }
+            let mut sprite_idx = entity_count;
+            for TextDraw(font, text, pos, size) in self.texts.iter() {
+                if font.spritesheet != sheet {
+                    continue;
+                }
+                font.font.draw_text(
+                    &mut trfs[sprite_idx..],
+                    &mut uvs[sprite_idx..],
+                    text,
+                    (*pos).into(),
+                    0,
+                    *size,
+                );
+                sprite_idx += text.len();
+            }
+        }
+    }
+    /// Adds a spritesheet with one layer per image, which must all be
+    /// the same size.
+    pub fn add_spritesheet(
+        &mut self,
+        imgs: &[&image::RgbaImage],
+        label: Option<&str>,
+    ) -> Spritesheet {
+        let img_bytes: Vec<_> = imgs.iter().map(|img| img.as_raw().as_slice()).collect();
+        let texture = self.renderer.create_array_texture(
+            &img_bytes,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            imgs[0].dimensions(),
+            label,
+        );
+        let group = self.renderer.sprite_group_add(
+            &texture,
+            vec![Transform::ZERO; 16],
+            vec![SheetRegion::ZERO; 16],
+            self.camera,
+        );
+        self.sheets.push(group);
+        Spritesheet(self.sheets.len() - 1)
+    }
+    /// Makes a font whose glyphs for `range` are laid out in rows in
+    /// `region` of `spritesheet`, see
+    /// [frenderer::bitfont::BitFont::with_sheet_region].
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_font<B: std::ops::RangeBounds<char>>(
+        &mut self,
+        spritesheet: Spritesheet,
+        range: B,
+        region: SheetRegion,
+        char_w: u16,
+        char_h: u16,
+        padding_x: u16,
+        padding_y: u16,
+    ) -> BitFont {
+        BitFont {
+            font: frenderer::bitfont::BitFont::with_sheet_region(
+                range, region, char_w, char_h, padding_x, padding_y,
+            ),
+            spritesheet,
+        }
+    }
+    /// Draws `text` in one line this frame with its top left corner
+    /// at `pos`, each character `char_height` tall.
+    pub fn draw_string(
+        &mut self,
+        font: &BitFont,
+        text: impl Into<String>,
+        pos: geom::Vec2,
+        char_height: f32,
+    ) {
+        self.texts
+            .push(TextDraw(*font, text.into(), pos, char_height));
+    }
+    /// Returns the camera every spritesheet is drawn with.
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+    /// Sets the camera every spritesheet is drawn with.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
+    /// Creates an entity in the current scene and returns its id.
+    pub fn spawn(
+        &mut self,
+        spritesheet: Spritesheet,
+        tag: G::Tag,
+        aabb: geom::AABB,
+        region: SheetRegion,
+        collision: Collision,
+    ) -> EntityId {
+        self.entities.spawn(Entity {
+            aabb,
+            vel: geom::Vec2::ZERO,
+            region,
+            sheet: spritesheet,
+            collision,
+            tag,
+            scene: self.scene,
+        })
+    }
+    /// Removes an entity, returning it if it was still alive.
+    pub fn despawn(&mut self, id: EntityId) -> Option<Entity<G::Tag>> {
+        self.entities.despawn(id)
+    }
+    /// Gets a live entity.
+    pub fn entity(&self, id: EntityId) -> Option<&Entity<G::Tag>> {
+        self.entities.get(id)
+    }
+    /// Gets a live entity to modify.
+    pub fn entity_mut(&mut self, id: EntityId) -> Option<&mut Entity<G::Tag>> {
+        self.entities.get_mut(id)
+    }
+    /// How many entities are alive.
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+    /// Every live entity.
+    pub fn entities(&self) -> impl Iterator<Item = (EntityId, &Entity<G::Tag>)> {
+        self.entities.iter()
+    }
+    /// Every live entity, to modify.
+    pub fn entities_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut Entity<G::Tag>)> {
+        self.entities.iter_mut()
+    }
+    /// Every live entity with the given tag.
+    pub fn entities_by_tag(
+        &self,
+        tag: G::Tag,
+    ) -> impl Iterator<Item = (EntityId, &Entity<G::Tag>)> {
+        self.entities().filter(move |(_id, e)| e.tag == tag)
+    }
+    /// Every live entity with the given tag, to modify.
+    pub fn entities_by_tag_mut(
+        &mut self,
+        tag: G::Tag,
+    ) -> impl Iterator<Item = (EntityId, &mut Entity<G::Tag>)> {
+        self.entities_mut().filter(move |(_id, e)| e.tag == tag)
+    }
+    /// The depth of the current scene, starting from 0 for the scene
+    /// the game starts in.
+    pub fn scene(&self) -> usize {
+        self.scene
+    }
+    /// Starts a new scene on top of the current one, e.g. for a pause
+    /// menu.  Entities spawned from now on belong to it, and only its
+    /// entities move and collide; those of the scenes below hold
+    /// still but are still drawn.
+    pub fn push_scene(&mut self) {
+        self.scene += 1;
+    }
+    /// Ends the current scene, despawning its entities, and resumes
+    /// the one below.  Ending the first scene just despawns its
+    /// entities.
+    pub fn pop_scene(&mut self) {
+        self.clear_scene();
+        self.scene = self.scene.saturating_sub(1);
+    }
+    /// Despawns the current scene's entities without ending it, e.g.
+    /// to load the next level.
+    pub fn clear_scene(&mut self) {
+        let scene = self.scene;
+        let doomed: Vec<EntityId> = self
+            .entities
+            .iter()
+            .filter(|(_id, e)| e.scene == scene)
+            .map(|(id, _e)| id)
+            .collect();
+        for id in doomed {
+            self.entities.despawn(id);
+        }
+    }
+}
+
+impl<G: Game> std::ops::Index<EntityId> for Engine<G> {
+    type Output = Entity<G::Tag>;
+
+    fn index(&self, index: EntityId) -> &Self::Output {
+        self.entity(index).expect("Entity was despawned")
+    }
+}
+
+impl<G: Game> std::ops::IndexMut<EntityId> for Engine<G> {
+    fn index_mut(&mut self, index: EntityId) -> &mut Self::Output {
+        self.entity_mut(index).expect("Entity was despawned")
+    }
+}
@@ -3,7 +3,6 @@ use frapp::frenderer::input::Input;
 use frapp::frenderer::sprites::{Camera2D, SheetRegion, Transform};
 use frapp::frenderer::*;
 use frapp::*;
-use rand::{seq::SliceRandom, Rng};
 
 const COUNT: usize = 100;
 const W: f32 = 1024.0;
@@ -13,10 +12,36 @@ struct TestApp {
     #[allow(dead_code)]
     assets: AssetCache,
     sprites: Vec<(f32, f32, f32, SheetRegion)>,
+    rng: TickRng,
+}
+
+fn random_sprite(rng: &mut TickRng) -> (f32, f32, f32, SheetRegion) {
+    (
+        rng.range_f32(0.0..(W - 16.0)),
+        rng.range_f32(0.0..(H - 16.0)),
+        rng.range_f32(0.0..std::f32::consts::TAU),
+        SheetRegion::new(rng.range_i32(0..2) as u16, 0, 16, 8, 11, 16).with_colormod([
+            rng.next_u32() as u8,
+            rng.next_u32() as u8,
+            rng.next_u32() as u8,
+            *rng.choose(&[0, 128, 255]).unwrap(),
+        ]),
+    )
 }
 impl App for TestApp {
     type Renderer = Immediate;
     const DT: f32 = 1.0 / 60.0;
+    fn load(assets: &AssetCache, step: usize) -> LoadStatus {
+        // Decode one image per step behind the loading screen; new() finds them cached
+        const IMAGES: [&str; 2] = ["king", "king_invert"];
+        match IMAGES.get(step) {
+            Some(name) => {
+                assets.load::<Png>(name).expect("Couldn't load image");
+                LoadStatus::Loading((step + 1) as f32 / IMAGES.len() as f32)
+            }
+            None => LoadStatus::Done,
+        }
+    }
     fn new(renderer: &mut Self::Renderer, assets: AssetCache) -> Self {
         let sprite_img_handle = assets.load::<Png>("king").expect("Couldn't load king img");
         let sprite_img = sprite_img_handle.read().0.to_rgba8();
@@ -42,51 +67,32 @@ impl App for TestApp {
             },
         );
 
-        let sprites: Vec<_> = {
-            let mut rng = rand::thread_rng();
-            (0..COUNT)
-                .map(|_| {
-                    (
-                        rng.gen_range(0.0..(W - 16.0)),
-                        rng.gen_range(0.0..(H - 16.0)),
-                        rng.gen_range(0.0..std::f32::consts::TAU),
-                        SheetRegion::new(rng.gen_range(0..2), 0, 16, 8, 11, 16).with_colormod([
-                            rng.gen(),
-                            rng.gen(),
-                            rng.gen(),
-                            *[0, 128, 255].choose(&mut rng).unwrap(),
-                        ]),
-                    )
-                })
-                .collect()
-        };
-        Self { assets, sprites }
+        // Seeded rather than thread_rng, so every run plays out the same way
+        let mut rng = TickRng::new(0x5eed);
+        let sprites: Vec<_> = (0..COUNT).map(|_| random_sprite(&mut rng)).collect();
+        Self {
+            assets,
+            sprites,
+            rng,
+        }
     }
     fn update(&mut self, _renderer: &mut Self::Renderer, _input: &Input) {
-        let mut rng = rand::thread_rng();
+        let rng = &mut self.rng;
         for (x, y, rot, _gfx) in self.sprites.iter_mut() {
-            *x += rng.gen_range((-1.0)..1.0);
-            *y += rng.gen_range((-1.0)..1.0);
-            *rot += rng.gen_range((-0.05)..0.05);
+            *x += rng.range_f32((-1.0)..1.0);
+            *y += rng.range_f32((-1.0)..1.0);
+            *rot += rng.range_f32((-0.05)..0.05);
         }
-        if rng.gen_bool(0.05) && !self.sprites.is_empty() {
-            self.sprites
-                .swap_remove(rng.gen_range(0..self.sprites.len()));
+        if rng.chance(0.05) && !self.sprites.is_empty() {
+            self.sprites.swap_remove(rng.index(self.sprites.len()));
         }
-        if rng.gen_bool(0.01) {
-            self.sprites.push((
-                rng.gen_range(0.0..(W - 16.0)),
-                rng.gen_range(0.0..(H - 16.0)),
-                rng.gen_range(0.0..std::f32::consts::TAU),
-                SheetRegion::new(rng.gen_range(0..2), 0, 16, 8, 11, 16).with_colormod([
-                    rng.gen(),
-                    rng.gen(),
-                    rng.gen(),
-                    *[0, 128, 255].choose(&mut rng).unwrap(),
-                ]),
-            ));
+        if rng.chance(0.01) {
+            self.sprites.push(random_sprite(rng));
         }
     }
+    fn rng(&mut self) -> Option<&mut TickRng> {
+        Some(&mut self.rng)
+    }
     fn render(&mut self, renderer: &mut Self::Renderer, _dt: f32, _input: &Input) {
         for (x, y, rot, uv) in self.sprites.iter() {
             renderer.draw_sprite(
@@ -75,7 +75,8 @@ impl<G: Game> Engine<G> {
             move |event, target, (ref mut engine, ref mut game, ref mut contacts)| {
                 engine.run_step(event, target, game, contacts);
             },
-        )
+        )?;
+        Ok(())
     }
     fn run_step(
         &mut self,
@@ -77,7 +77,8 @@ impl<G: Game> Engine<G> {
                 (this, game, displacements, triggers)
             },
             move |event, target, (ref mut engine, ref mut game, ref mut displacements, ref mut triggers)| engine.run_step(&event, target, game, displacements, triggers)
-    )
+    )?;
+        Ok(())
     }
     pub fn world(&self) -> &hecs::World {
         &self.world
@@ -0,0 +1,190 @@
+use engine2d::{geom::*, Camera, Collision, Contact, EntityId, Key, SheetRegion, Spritesheet};
+use rand::Rng;
+type Engine = engine2d::Engine<Game>;
+
+const W: f32 = 320.0;
+const H: f32 = 240.0;
+const GUY_SPEED: f32 = 4.0;
+const GUY_SIZE: Vec2 = Vec2 { x: 16.0, y: 16.0 };
+const APPLE_SIZE: Vec2 = Vec2 { x: 16.0, y: 16.0 };
+
+const WALL_UVS: SheetRegion = SheetRegion::new(0, 0, 480, 12, 8, 8);
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Tag {
+    Wall,
+    Guy,
+    Apple,
+    Deco,
+}
+
+struct Game {
+    apple_timer: u32,
+    score: u32,
+    paused: bool,
+    guy: EntityId,
+    spritesheet: Spritesheet,
+    font: engine2d::BitFont,
+}
+
+impl engine2d::Game for Game {
+    type Tag = Tag;
+    fn new(engine: &mut Engine) -> Self {
+        engine.set_camera(Camera {
+            screen_pos: [0.0, 0.0],
+            screen_size: [W, H],
+        });
+        #[cfg(target_arch = "wasm32")]
+        let sprite_img = {
+            let img_bytes = include_bytes!("../../../content/demo.png");
+            image::load_from_memory_with_format(img_bytes, image::ImageFormat::Png)
+                .map_err(|e| e.to_string())
+                .unwrap()
+                .into_rgba8()
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let sprite_img = image::open("content/demo.png").unwrap().into_rgba8();
+        let spritesheet = engine.add_spritesheet(&[&sprite_img], Some("demo spritesheet"));
+        engine.spawn(
+            spritesheet,
+            Tag::Deco,
+            AABB {
+                center: Vec2 {
+                    x: W / 2.0,
+                    y: H / 2.0,
+                },
+                size: Vec2 { x: W, y: H },
+            },
+            SheetRegion::new(0, 0, 0, 16, 640, 480),
+            Collision::none(),
+        );
+        let guy = engine.spawn(
+            spritesheet,
+            Tag::Guy,
+            AABB {
+                center: Vec2 {
+                    x: W / 2.0,
+                    y: 24.0,
+                },
+                size: GUY_SIZE,
+            },
+            SheetRegion::new(0, 16, 480, 8, 16, 16),
+            Collision::pushable(),
+        );
+        // floor, left wall, right wall
+        for (center, size) in [
+            (Vec2::new(W / 2.0, 8.0), Vec2::new(W, 16.0)),
+            (Vec2::new(8.0, H / 2.0), Vec2::new(16.0, H)),
+            (Vec2::new(W - 8.0, H / 2.0), Vec2::new(16.0, H)),
+        ] {
+            engine.spawn(
+                spritesheet,
+                Tag::Wall,
+                AABB { center, size },
+                WALL_UVS,
+                Collision::solid(),
+            );
+        }
+        let font = engine.add_font(
+            spritesheet,
+            '0'..='9',
+            SheetRegion::new(0, 0, 512, 0, 80, 8),
+            8,
+            8,
+            0,
+            0,
+        );
+        Game {
+            apple_timer: 0,
+            score: 0,
+            paused: false,
+            font,
+            spritesheet,
+            guy,
+        }
+    }
+    fn update(&mut self, engine: &mut Engine) {
+        // Pausing pushes a scene, which freezes the apples and the guy
+        if engine.input.is_key_pressed(Key::Space) {
+            self.paused = !self.paused;
+            if self.paused {
+                engine.push_scene();
+                engine.spawn(
+                    self.spritesheet,
+                    Tag::Deco,
+                    AABB {
+                        center: Vec2::new(W / 2.0, H / 2.0),
+                        size: Vec2::new(W / 2.0, H / 4.0),
+                    },
+                    WALL_UVS.with_depth(0),
+                    Collision::none(),
+                );
+            } else {
+                engine.pop_scene();
+            }
+        }
+        if self.paused {
+            return;
+        }
+        let dir = engine.input.key_axis(Key::ArrowLeft, Key::ArrowRight);
+        engine[self.guy].set_vel(Vec2 {
+            x: dir * GUY_SPEED,
+            y: 0.0,
+        });
+        let mut rng = rand::thread_rng();
+        if self.apple_timer > 0 {
+            self.apple_timer -= 1;
+        } else if engine.entities_by_tag(Tag::Apple).count() < 8 {
+            let apple = engine.spawn(
+                self.spritesheet,
+                Tag::Apple,
+                AABB {
+                    center: Vec2 {
+                        x: rng.gen_range(8.0..(W - 8.0)),
+                        y: H + 8.0,
+                    },
+                    size: APPLE_SIZE,
+                },
+                SheetRegion::new(0, 0, 496, 4, 16, 16),
+                Collision::trigger(),
+            );
+            engine[apple].set_vel(Vec2 {
+                x: 0.0,
+                y: rng.gen_range((-4.0)..(-1.0)),
+            });
+            self.apple_timer = rng.gen_range(30..90);
+        }
+        let fallen: Vec<EntityId> = engine
+            .entities_by_tag(Tag::Apple)
+            .filter(|(_id, apple)| apple.pos().y < -8.0)
+            .map(|(id, _apple)| id)
+            .collect();
+        for apple in fallen {
+            engine.despawn(apple);
+        }
+    }
+    fn handle_triggers(&mut self, engine: &mut Engine, triggers: &[Contact<Tag>]) {
+        for contact in triggers {
+            // Apple, Guy never happens because Guy comes before Apple in the enum
+            if let (Tag::Guy, Tag::Apple) = (contact.tag_a, contact.tag_b) {
+                if engine.despawn(contact.b).is_some() {
+                    self.score += 1;
+                }
+            }
+        }
+    }
+    fn render(&mut self, engine: &mut Engine) {
+        engine.draw_string(
+            &self.font,
+            self.score.to_string(),
+            Vec2 {
+                x: 16.0,
+                y: H - 16.0,
+            },
+            16.0,
+        );
+    }
+}
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    Engine::run(winit::window::WindowBuilder::new(), Some((1024, 768)))?;
+    Ok(())
+}
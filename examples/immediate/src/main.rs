@@ -112,19 +112,21 @@ fn main() {
                             ));
                         }
                     }
-                    for (x, y, rot, uv) in sprites.iter() {
-                        frend.draw_sprite(
-                            0,
-                            Transform {
-                                x: *x,
-                                y: *y,
-                                w: 44,
-                                h: 64,
-                                rot: *rot,
-                            },
-                            *uv,
-                        );
-                    }
+                    frend.draw_sprites_iter(
+                        0,
+                        sprites.iter().map(|(x, y, rot, uv)| {
+                            (
+                                Transform {
+                                    x: *x,
+                                    y: *y,
+                                    w: 44,
+                                    h: 64,
+                                    rot: *rot,
+                                },
+                                *uv,
+                            )
+                        }),
+                    );
                     // ok now render.
                     {
                         let nine_stretched =
@@ -2,33 +2,108 @@ pub use assets_manager;
 pub use frenderer;
 use frenderer::clock::{Clock, Instant};
 use frenderer::input::Input;
+pub use frenderer::rng::TickRng;
 use frenderer::Frenderer;
 pub use frenderer::FrendererEvents;
 use frenderer::{Driver, EventPhase};
 pub use winit::{self, window::WindowBuilder};
 
+#[cfg(feature = "physics")]
+pub mod physics;
+pub mod profile;
+pub use profile::FrameTimings;
+pub mod loading;
+pub use loading::{LoadStatus, LoadingScreen, ProgressBar};
+
 /// `frapp` exposes an alias for [assets_manager::AssetCache] that uses a different source depending on whether we're targeting native or web.
 #[cfg(not(target_arch = "wasm32"))]
 pub type AssetCache = assets_manager::AssetCache<assets_manager::source::FileSystem>;
 #[cfg(target_arch = "wasm32")]
 pub type AssetCache = assets_manager::AssetCache<assets_manager::source::Embedded<'static>>;
 
+/// A [frenderer::particles::EffectDesc] loaded from a `.ron` file, e.g. with `cache.load::<EffectAsset>("effects.sparks")`.  Edits to the file are picked up by hot-reloading.
+#[cfg(feature = "particles")]
+#[derive(Clone, Debug)]
+pub struct EffectAsset(pub frenderer::particles::EffectDesc);
+#[cfg(feature = "particles")]
+impl From<frenderer::particles::EffectDesc> for EffectAsset {
+    fn from(desc: frenderer::particles::EffectDesc) -> Self {
+        Self(desc)
+    }
+}
+#[cfg(feature = "particles")]
+impl assets_manager::Asset for EffectAsset {
+    const EXTENSION: &'static str = "ron";
+    type Loader = assets_manager::loader::LoadFrom<
+        frenderer::particles::EffectDesc,
+        assets_manager::loader::RonLoader,
+    >;
+}
+
 /// App is the main public trait of `frapp`.  Implementors get a defined new/update/render lifecycle with a choice of frenderer renderers (either [frenderer::Renderer] or [frenderer::Immediate]).
 pub trait App {
     /// Target delta-time for simulation
     const DT: f32;
     /// The renderer type to use
     type Renderer: frenderer::Frenderer;
+    /// Do one step of loading before [App::new], e.g. load the
+    /// `step`th of a list of assets into `assets`, and report how far
+    /// along loading is.  It's called with `step` counting up from 0
+    /// until it returns [LoadStatus::Done], while
+    /// [App::loading_screen] shows the progress; see [loading].  The
+    /// default loads nothing.
+    fn load(_assets: &AssetCache, _step: usize) -> LoadStatus {
+        LoadStatus::Done
+    }
+    /// The screen drawn while [App::load] runs, a [ProgressBar] by default.
+    fn loading_screen() -> Box<dyn LoadingScreen> {
+        Box::new(ProgressBar::default())
+    }
     /// Initialize the app
     fn new(renderer: &mut Self::Renderer, assets: AssetCache) -> Self;
     /// Update (called every DT seconds)
     fn update(&mut self, renderer: &mut Self::Renderer, input: &Input);
+    /// The app's deterministic random number generator, if it has
+    /// one.  It's set to the current simulation tick before each
+    /// call to [App::update], so random choices made there with it
+    /// (rather than e.g. `rand::thread_rng`) are the same every time
+    /// the game is played with the same inputs, as replays and
+    /// lockstep networking require.
+    fn rng(&mut self) -> Option<&mut TickRng> {
+        None
+    }
+    /// The app's frame timings, if it wants them.  When this returns
+    /// `Some`, [AppDriver::run] records how long each frame's
+    /// updates, render, and GPU submission took into it; see
+    /// [profile].
+    fn timings(&mut self) -> Option<&mut FrameTimings> {
+        None
+    }
     /// Render (called once per present cycle)
     fn render(&mut self, renderer: &mut Self::Renderer, dt: f32, input: &Input);
 }
 
 use std::marker::PhantomData;
 
+// How long to spend on App::load steps in each loading screen frame
+const LOAD_BUDGET: f32 = 1.0 / 120.0;
+
+enum Phase<A: App> {
+    Loading {
+        screen: Box<frenderer::Immediate>,
+        loading_screen: Box<dyn LoadingScreen>,
+        cache: AssetCache,
+        step: usize,
+        progress: f32,
+    },
+    Running {
+        app: A,
+        renderer: A::Renderer,
+    },
+    // Only used while switching from loading to running
+    Starting,
+}
+
 /// AppDriver is public, but should only be created from the [app] macro.
 pub struct AppDriver<A: App + 'static>
 where
@@ -47,23 +122,101 @@ where
         let drv = Driver::new(builder, render_dims);
         let mut clock = Clock::new(A::DT, 0.0002, 5);
         let mut last_render = Instant::now();
+        let mut tick: u64 = 0;
         drv.run_event_loop::<(), _>(
             move |window, renderer| {
-                let input = Input::default();
-                let mut rend: A::Renderer = renderer.into();
-                let app = A::new(&mut rend, self.cache);
-                (window, app, rend, input)
+                let phase = Phase::<A>::Loading {
+                    screen: Box::new(frenderer::Immediate::new(renderer)),
+                    loading_screen: A::loading_screen(),
+                    cache: self.cache,
+                    step: 0,
+                    progress: 0.0,
+                };
+                (window, phase, Input::default())
             },
-            move |event, target, (window, ref mut app, ref mut renderer, ref mut input)| {
+            move |event, target, (window, phase, input)| {
+                let (app, renderer) = match phase {
+                    Phase::Running { app, renderer } => (app, renderer),
+                    Phase::Loading {
+                        screen,
+                        loading_screen,
+                        cache,
+                        step,
+                        progress,
+                    } => {
+                        match screen.handle_event(&mut clock, window, &event, target, input) {
+                            EventPhase::Run(_) => {
+                                let start = Instant::now();
+                                let done = loop {
+                                    match A::load(cache, *step) {
+                                        LoadStatus::Done => break true,
+                                        LoadStatus::Loading(fraction) => {
+                                            *step += 1;
+                                            *progress = fraction.clamp(0.0, 1.0);
+                                        }
+                                    }
+                                    if start.elapsed().as_secs_f32() >= LOAD_BUDGET {
+                                        break false;
+                                    }
+                                };
+                                if !done {
+                                    loading_screen.draw(screen, *progress);
+                                    screen.render();
+                                    screen.request_redraw();
+                                    input.next_frame();
+                                    return;
+                                }
+                            }
+                            EventPhase::Quit => {
+                                target.exit();
+                                return;
+                            }
+                            EventPhase::Wait => return,
+                        }
+                        let Phase::Loading {
+                            mut screen,
+                            mut loading_screen,
+                            cache,
+                            ..
+                        } = std::mem::replace(phase, Phase::Starting)
+                        else {
+                            unreachable!()
+                        };
+                        loading_screen.finish(&mut screen);
+                        let mut renderer: A::Renderer = screen.into_renderer().into();
+                        let app = A::new(&mut renderer, cache);
+                        *phase = Phase::Running { app, renderer };
+                        // Don't make up for the time spent loading
+                        clock.set_now(Instant::now());
+                        last_render = Instant::now();
+                        window.request_redraw();
+                        return;
+                    }
+                    Phase::Starting => return,
+                };
                 match renderer.handle_event(&mut clock, window, &event, target, input) {
                     EventPhase::Run(steps) => {
+                        let frame_start = Instant::now();
                         for _ in 0..steps {
+                            if let Some(rng) = app.rng() {
+                                rng.set_tick(tick);
+                            }
+                            tick += 1;
                             app.update(renderer, input);
                             input.next_frame();
                         }
+                        let update_end = Instant::now();
                         app.render(renderer, last_render.elapsed().as_secs_f32(), input);
                         last_render = Instant::now();
                         renderer.render();
+                        if let Some(timings) = app.timings() {
+                            timings.record(
+                                steps,
+                                update_end.duration_since(frame_start).as_secs_f32(),
+                                last_render.duration_since(update_end).as_secs_f32(),
+                                last_render.elapsed().as_secs_f32(),
+                            );
+                        }
                     }
                     EventPhase::Quit => {
                         target.exit();
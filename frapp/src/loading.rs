@@ -0,0 +1,122 @@
+//! A loading phase before [`crate::App::new`], so the window stays
+//! responsive while a game's assets load.  [`crate::App::load`] is
+//! called a step at a time, as many steps per frame as fit in a
+//! short time budget, and in between the app's
+//! [`crate::App::loading_screen`] draws its progress through a
+//! [`frenderer::Immediate`].  This works the same way on native and
+//! on the web, where there are no threads to block.
+//!
+//! Assets loaded into the [`crate::AssetCache`] during this phase
+//! stay cached, so [`crate::App::new`] gets them back from
+//! `cache.load` right away.
+
+use frenderer::sprites::{Camera2D, SheetRegion, Transform};
+use frenderer::Immediate;
+
+/// How far along [`crate::App::load`] is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadStatus {
+    /// There is more to load, and this fraction of it (from 0 to 1)
+    /// is done
+    Loading(f32),
+    /// Everything is loaded and [`crate::App::new`] can run
+    Done,
+}
+
+/// Something drawn while [`crate::App::load`] runs, like the built-in
+/// [`ProgressBar`].
+pub trait LoadingScreen {
+    /// Draw one frame of the loading screen with `progress` (from 0
+    /// to 1) of the loading done.  The frame is rendered afterwards.
+    fn draw(&mut self, screen: &mut Immediate, progress: f32);
+    /// Remove anything the loading screen added to the renderer,
+    /// e.g. its sprite groups, before it's handed to
+    /// [`crate::App::new`].
+    fn finish(&mut self, _screen: &mut Immediate) {}
+}
+
+/// The default loading screen: a bar filling up across the middle
+/// of a solid background.
+#[derive(Clone, Debug)]
+pub struct ProgressBar {
+    /// The background color as RGBA bytes
+    pub background: [u8; 4],
+    /// The color of the bar's unfilled part
+    pub track: [u8; 4],
+    /// The color of the bar's filled part
+    pub fill: [u8; 4],
+    /// The bar's width as a fraction of the rendered image's width
+    pub width: f32,
+    /// The bar's height in rendered pixels
+    pub height: f32,
+    group: Option<usize>,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self {
+            background: [0, 0, 0, 255],
+            track: [48, 48, 48, 255],
+            fill: [224, 224, 224, 255],
+            width: 0.5,
+            height: 8.0,
+            group: None,
+        }
+    }
+}
+
+impl LoadingScreen for ProgressBar {
+    fn draw(&mut self, screen: &mut Immediate, progress: f32) {
+        let (w, h) = screen.render_size();
+        let (w, h) = (w as f32, h as f32);
+        let camera = Camera2D {
+            screen_pos: [0.0, 0.0],
+            screen_size: [w, h],
+        };
+        let group = match self.group {
+            Some(group) => {
+                screen.sprite_group_set_camera(group, camera);
+                group
+            }
+            None => {
+                // Every rectangle is this one white texel tinted by its colormod
+                let white = screen.create_array_texture(
+                    &[&[255; 4]],
+                    frenderer::wgpu::TextureFormat::Rgba8UnormSrgb,
+                    (1, 1),
+                    Some("loading:white"),
+                );
+                let group = screen.sprite_group_add(&white, 3, camera);
+                self.group = Some(group);
+                group
+            }
+        };
+        let rect = |x: f32, width: f32, height: f32, depth: u16, color: [u8; 4]| {
+            (
+                Transform {
+                    w: width.round() as u16,
+                    h: height.round() as u16,
+                    x,
+                    y: h / 2.0,
+                    rot: 0.0,
+                },
+                SheetRegion::new(0, 0, 0, depth, 1, 1).with_colormod(color),
+            )
+        };
+        let bar_w = w * self.width;
+        let fill_w = bar_w * progress.clamp(0.0, 1.0);
+        let left = (w - bar_w) / 2.0;
+        for (trf, region) in [
+            rect(w / 2.0, w, h, 2, self.background),
+            rect(w / 2.0, bar_w, self.height, 1, self.track),
+            rect(left + fill_w / 2.0, fill_w, self.height, 0, self.fill),
+        ] {
+            screen.draw_sprite(group, trf, region);
+        }
+    }
+    fn finish(&mut self, screen: &mut Immediate) {
+        if let Some(group) = self.group.take() {
+            screen.sprite_group_remove(group);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Optional 2D physics through [`rapier2d`], for games which outgrow
+//! axis-aligned box collision.  A [`Physics`] world owns rapier's
+//! body and collider sets and everything needed to step them; call
+//! [`Physics::step`] once per [`crate::App::update`] (it steps by
+//! the app's fixed `DT`), or [`Physics::advance`] with the step
+//! count from a [`frenderer::clock::Clock`] you drive yourself.
+//! Then copy body positions into a sprite group with
+//! [`Physics::sync_sprites`] (or [`Physics::write_transforms`]).
+//!
+//! [`Physics::sync_debug_sprites`] outlines every collider with
+//! sprites, like [`frenderer::gizmos::Gizmo2D`] draws its handles,
+//! which helps when colliders and art don't line up.
+
+pub use rapier2d;
+use rapier2d::prelude::*;
+
+use frenderer::sprites::{SheetRegion, Transform};
+use frenderer::Renderer;
+
+// Line segments per circle outline
+const CIRCLE_SEGMENTS: usize = 16;
+
+/// A rapier physics world stepped at a fixed rate, with links from
+/// rigid bodies to the sprites that show them.
+pub struct Physics {
+    /// The world's rigid bodies
+    pub bodies: RigidBodySet,
+    /// The world's colliders
+    pub colliders: ColliderSet,
+    /// The world's impulse joints
+    pub impulse_joints: ImpulseJointSet,
+    /// The world's multibody joints
+    pub multibody_joints: MultibodyJointSet,
+    /// Acceleration due to gravity
+    pub gravity: Vector<Real>,
+    /// Settings for each step; `dt` is the fixed step length
+    pub integration: IntegrationParameters,
+    /// Scene queries (ray casts, shape casts, intersections), kept up
+    /// to date by [`Physics::step`]
+    pub query: QueryPipeline,
+    pipeline: PhysicsPipeline,
+    islands: IslandManager,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    ccd: CCDSolver,
+    // Which sprite index shows each body
+    links: Vec<(RigidBodyHandle, usize)>,
+}
+
+impl Physics {
+    /// Creates an empty world stepped by `dt` seconds at a time,
+    /// usually the app's [`crate::App::DT`], with the given gravity.
+    pub fn new(dt: f32, gravity: [f32; 2]) -> Self {
+        Self {
+            bodies: RigidBodySet::new(),
+            colliders: ColliderSet::new(),
+            impulse_joints: ImpulseJointSet::new(),
+            multibody_joints: MultibodyJointSet::new(),
+            gravity: vector![gravity[0], gravity[1]],
+            integration: IntegrationParameters {
+                dt,
+                ..IntegrationParameters::default()
+            },
+            query: QueryPipeline::new(),
+            pipeline: PhysicsPipeline::new(),
+            islands: IslandManager::new(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            ccd: CCDSolver::new(),
+            links: vec![],
+        }
+    }
+    /// Adds a body with its colliders, returning its handle.
+    pub fn add(&mut self, body: impl Into<RigidBody>, colliders: Vec<Collider>) -> RigidBodyHandle {
+        let handle = self.bodies.insert(body);
+        for collider in colliders {
+            self.colliders
+                .insert_with_parent(collider, handle, &mut self.bodies);
+        }
+        handle
+    }
+    /// Removes a body along with its colliders, joints, and sprite link.
+    pub fn remove(&mut self, body: RigidBodyHandle) -> Option<RigidBody> {
+        self.links.retain(|(linked, _)| *linked != body);
+        self.bodies.remove(
+            body,
+            &mut self.islands,
+            &mut self.colliders,
+            &mut self.impulse_joints,
+            &mut self.multibody_joints,
+            true,
+        )
+    }
+    /// Shows `body` with the sprite at index `sprite` of whatever
+    /// group [`Physics::write_transforms`] writes into, replacing any
+    /// sprite it was linked to before.
+    pub fn link(&mut self, body: RigidBodyHandle, sprite: usize) {
+        self.unlink(body);
+        self.links.push((body, sprite));
+    }
+    /// Stops showing `body` with a sprite.
+    pub fn unlink(&mut self, body: RigidBodyHandle) {
+        self.links.retain(|(linked, _)| *linked != body);
+    }
+    /// Advances the world by one fixed step of `integration.dt` seconds.
+    pub fn step(&mut self) {
+        self.pipeline.step(
+            &self.gravity,
+            &self.integration,
+            &mut self.islands,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.bodies,
+            &mut self.colliders,
+            &mut self.impulse_joints,
+            &mut self.multibody_joints,
+            &mut self.ccd,
+            Some(&mut self.query),
+            &(),
+            &(),
+        );
+    }
+    /// Advances the world by `steps` fixed steps, e.g. the count
+    /// returned by [`frenderer::clock::Clock::tick`] for a clock with
+    /// the same timestep.
+    pub fn advance(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+    // The lowest and highest linked sprite indices below `len`
+    fn linked_range(&self, len: usize) -> Option<(usize, usize)> {
+        let mut sprites = self
+            .links
+            .iter()
+            .filter(|(handle, sprite)| *sprite < len && self.bodies.contains(*handle))
+            .map(|&(_, sprite)| sprite);
+        let first = sprites.next()?;
+        Some(sprites.fold((first, first), |(lo, hi), s| (lo.min(s), hi.max(s))))
+    }
+    /// Moves and rotates each linked sprite in `trfs` to match its
+    /// body, leaving its size alone.  Links to bodies which no longer
+    /// exist or sprites past the end of `trfs` are skipped.  Returns
+    /// the lowest and highest sprite indices written, to e.g. upload
+    /// just that range, or `None` if none were.
+    pub fn write_transforms(&self, trfs: &mut [Transform]) -> Option<(usize, usize)> {
+        let range = self.linked_range(trfs.len())?;
+        self.write_linked(&mut trfs[range.0..=range.1], range.0);
+        Some(range)
+    }
+    // Writes linked bodies' positions into `trfs`, which starts at sprite index `first`
+    fn write_linked(&self, trfs: &mut [Transform], first: usize) {
+        for &(handle, sprite) in self.links.iter() {
+            let Some(body) = self.bodies.get(handle) else {
+                continue;
+            };
+            let Some(trf) = sprite.checked_sub(first).and_then(|idx| trfs.get_mut(idx)) else {
+                continue;
+            };
+            let pos = body.position();
+            trf.x = pos.translation.vector.x;
+            trf.y = pos.translation.vector.y;
+            trf.rot = pos.rotation.angle();
+        }
+    }
+    /// Moves and rotates the linked sprites of sprite group `group`
+    /// to match their bodies, like [`Physics::write_transforms`],
+    /// marking only the range of sprites it touches for upload.
+    pub fn sync_sprites(&self, renderer: &mut Renderer, group: usize) {
+        let Some((lo, hi)) = self.linked_range(renderer.sprite_group_size(group)) else {
+            return;
+        };
+        let (trfs, _) = renderer.sprites_mut(group, lo..=hi);
+        self.write_linked(trfs, lo);
+    }
+    /// How many sprites [`Physics::write_debug_sprites`] needs to
+    /// outline every collider.
+    pub fn debug_sprite_count(&self) -> usize {
+        self.colliders
+            .iter()
+            .map(|(_, collider)| {
+                if collider.shape().as_ball().is_some() {
+                    CIRCLE_SEGMENTS
+                } else {
+                    4
+                }
+            })
+            .sum()
+    }
+    /// Writes outlines of every collider into `trfs` and `uvs`,
+    /// returning how many sprites were written.  Balls are drawn as
+    /// circles and cuboids as boxes; other shapes are drawn as their
+    /// bounding boxes.  `white` should be a region of plain white
+    /// texels; outlines are colored with its
+    /// [`SheetRegion::colormod`] and drawn at its depth, `thickness`
+    /// units wide.
+    pub fn write_debug_sprites(
+        &self,
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+        white: SheetRegion,
+        thickness: f32,
+    ) -> usize {
+        let mut lines: Vec<([f32; 2], [f32; 2])> = Vec::with_capacity(self.debug_sprite_count());
+        for (_, collider) in self.colliders.iter() {
+            let pos = collider.position();
+            if let Some(ball) = collider.shape().as_ball() {
+                let center = pos.translation.vector;
+                let step = std::f32::consts::TAU / CIRCLE_SEGMENTS as f32;
+                let point = |i: usize| {
+                    let angle = i as f32 * step;
+                    [
+                        center.x + angle.cos() * ball.radius,
+                        center.y + angle.sin() * ball.radius,
+                    ]
+                };
+                lines.extend((0..CIRCLE_SEGMENTS).map(|i| (point(i), point(i + 1))));
+                continue;
+            }
+            let corners = if let Some(cuboid) = collider.shape().as_cuboid() {
+                let [hw, hh] = [cuboid.half_extents.x, cuboid.half_extents.y];
+                [[-hw, -hh], [hw, -hh], [hw, hh], [-hw, hh]].map(|[x, y]| {
+                    let p = pos * point![x, y];
+                    [p.x, p.y]
+                })
+            } else {
+                let aabb = collider.compute_aabb();
+                let (lo, hi) = (aabb.mins, aabb.maxs);
+                [[lo.x, lo.y], [hi.x, lo.y], [hi.x, hi.y], [lo.x, hi.y]]
+            };
+            lines.extend((0..4).map(|i| (corners[i], corners[(i + 1) % 4])));
+        }
+        let mut used = 0;
+        for ((trf, uv), (a, b)) in trfs.iter_mut().zip(uvs.iter_mut()).zip(lines) {
+            let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+            *trf = Transform {
+                // Overlap segments slightly so corners have no gaps
+                w: (dx.hypot(dy) + thickness).round().max(1.0) as u16,
+                h: thickness.round().max(1.0) as u16,
+                x: (a[0] + b[0]) / 2.0,
+                y: (a[1] + b[1]) / 2.0,
+                rot: dy.atan2(dx),
+            };
+            *uv = white;
+            used += 1;
+        }
+        used
+    }
+    /// Resizes sprite group `group` to fit every collider's outline
+    /// and fills it with them using [`Physics::write_debug_sprites`].
+    /// Draw this group above the rest of the scene.
+    pub fn sync_debug_sprites(
+        &self,
+        renderer: &mut Renderer,
+        group: usize,
+        white: SheetRegion,
+        thickness: f32,
+    ) {
+        let count = self.debug_sprite_count();
+        renderer.sprite_group_resize(group, count);
+        let (trfs, uvs) = renderer.sprites_mut(group, ..);
+        self.write_debug_sprites(trfs, uvs, white, thickness);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> (Physics, RigidBodyHandle) {
+        let mut physics = Physics::new(1.0 / 60.0, [0.0, -100.0]);
+        physics.add(
+            RigidBodyBuilder::fixed(),
+            vec![ColliderBuilder::cuboid(50.0, 5.0).build()],
+        );
+        let ball = physics.add(
+            RigidBodyBuilder::dynamic().translation(vector![0.0, 50.0]),
+            vec![ColliderBuilder::ball(8.0).build()],
+        );
+        (physics, ball)
+    }
+
+    #[test]
+    fn test_write_transforms() {
+        let (mut physics, ball) = world();
+        let mut trfs = [Transform::ZERO; 4];
+        assert_eq!(physics.write_transforms(&mut trfs), None);
+        physics.link(ball, 2);
+        physics.advance(10);
+        assert_eq!(physics.write_transforms(&mut trfs), Some((2, 2)));
+        // The ball has fallen, and other sprites are untouched
+        assert!(trfs[2].y < 50.0 && trfs[2].y > 13.0);
+        assert_eq!(trfs[1].y, 0.0);
+        // It comes to rest on the ground
+        physics.advance(300);
+        physics.write_transforms(&mut trfs);
+        assert!((trfs[2].y - 13.0).abs() < 0.5, "{}", trfs[2].y);
+        // Links past the end of the slice or to removed bodies are skipped
+        assert_eq!(physics.write_transforms(&mut trfs[..2]), None);
+        physics.remove(ball);
+        physics.link(ball, 0);
+        assert_eq!(physics.write_transforms(&mut trfs), None);
+    }
+    #[test]
+    fn test_debug_sprites() {
+        let (physics, _) = world();
+        assert_eq!(physics.debug_sprite_count(), 4 + CIRCLE_SEGMENTS);
+        let mut trfs = [Transform::ZERO; 24];
+        let mut uvs = [SheetRegion::ZERO; 24];
+        let white = SheetRegion::rect(0, 0, 1, 1).with_depth(1);
+        let used = physics.write_debug_sprites(&mut trfs, &mut uvs, white, 2.0);
+        assert_eq!(used, 20);
+        // The ground's top edge, overlapping the corners by the thickness
+        let top = trfs[..4].iter().find(|t| t.y == 5.0).unwrap();
+        assert_eq!((top.x, top.w, top.h), (0.0, 102, 2));
+        assert!(uvs[..used].iter().all(|uv| uv.depth == 1));
+        assert_eq!(uvs[used].depth, 0);
+        // Too few sprites just draws fewer lines
+        assert_eq!(
+            physics.write_debug_sprites(&mut trfs[..3], &mut uvs[..3], white, 2.0),
+            3
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Built-in timing of the [`crate::App`] lifecycle.  An app which
+//! returns a [`FrameTimings`] from [`crate::App::timings`] has each
+//! frame's time split into its [`crate::App::update`] steps, its
+//! [`crate::App::render`] call, and the renderer's submission to the
+//! GPU, with rolling averages and percentiles over recent frames.
+//! Apps which return `None` (the default) aren't timed.
+
+use std::collections::VecDeque;
+
+/// Rolling statistics of one part of recent frames, in seconds.
+#[derive(Clone, Debug)]
+pub struct TimingStats {
+    samples: VecDeque<f32>,
+    window: usize,
+}
+
+impl TimingStats {
+    fn new(window: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window: window.max(1),
+        }
+    }
+    fn push(&mut self, seconds: f32) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(seconds);
+    }
+    /// How many frames these statistics cover.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    /// Whether no frames have been timed yet.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+    /// The most recent frame's time, or 0 if there is none.
+    pub fn last(&self) -> f32 {
+        self.samples.back().copied().unwrap_or(0.0)
+    }
+    /// The mean time over recent frames, or 0 if there are none.
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+    /// The longest time over recent frames, or 0 if there are none.
+    pub fn max(&self) -> f32 {
+        self.samples.iter().copied().fold(0.0, f32::max)
+    }
+    /// The time which `percent` percent of recent frames took at most
+    /// (nearest rank), e.g. 99.0 for the slow frames behind hitches,
+    /// or 0 if there are none.
+    pub fn percentile(&self, percent: f32) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f32).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
+
+impl std::fmt::Display for TimingStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "avg {:.2} ms, p99 {:.2} ms, max {:.2} ms",
+            self.average() * 1000.0,
+            self.percentile(99.0) * 1000.0,
+            self.max() * 1000.0
+        )
+    }
+}
+
+/// Timings of an app's recent frames, filled in by
+/// [`crate::AppDriver::run`] for apps which return one from
+/// [`crate::App::timings`].  Its `Display` form only uses ASCII, so
+/// it can be drawn with a [`frenderer::bitfont::BitFont`] as a debug
+/// overlay.
+#[derive(Clone, Debug)]
+pub struct FrameTimings {
+    frame: TimingStats,
+    update: TimingStats,
+    render: TimingStats,
+    submit: TimingStats,
+    last_steps: usize,
+}
+
+impl FrameTimings {
+    /// Creates timings covering the most recent `window` frames.
+    pub fn new(window: usize) -> Self {
+        Self {
+            frame: TimingStats::new(window),
+            update: TimingStats::new(window),
+            render: TimingStats::new(window),
+            submit: TimingStats::new(window),
+            last_steps: 0,
+        }
+    }
+    /// Whole frames: updates, rendering, and submission together.
+    pub fn frame(&self) -> &TimingStats {
+        &self.frame
+    }
+    /// All of each frame's [`crate::App::update`] steps together,
+    /// including frames with no steps.  Divide by
+    /// [`FrameTimings::last_steps`] for the time of one step.
+    pub fn update(&self) -> &TimingStats {
+        &self.update
+    }
+    /// Each frame's [`crate::App::render`] call.
+    pub fn render(&self) -> &TimingStats {
+        &self.render
+    }
+    /// Encoding, submitting, and presenting each frame with
+    /// [`frenderer::Frenderer::render`], including any wait for the
+    /// next swapchain image.  See [`frenderer::Renderer::gpu_timings`]
+    /// for time spent on the GPU itself.
+    pub fn submit(&self) -> &TimingStats {
+        &self.submit
+    }
+    /// How many update steps the most recent frame ran.
+    pub fn last_steps(&self) -> usize {
+        self.last_steps
+    }
+    /// Forgets all timed frames.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.frame.window);
+    }
+    pub(crate) fn record(&mut self, steps: usize, update: f32, render: f32, submit: f32) {
+        self.frame.push(update + render + submit);
+        self.update.push(update);
+        self.render.push(render);
+        self.submit.push(submit);
+        self.last_steps = steps;
+    }
+}
+
+impl Default for FrameTimings {
+    /// Timings of the last 120 frames.
+    fn default() -> Self {
+        Self::new(120)
+    }
+}
+
+impl std::fmt::Display for FrameTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "frame: {}", self.frame)?;
+        writeln!(f, "update: {} ({} steps)", self.update, self.last_steps)?;
+        writeln!(f, "render: {}", self.render)?;
+        write!(f, "submit: {}", self.submit)
+    }
+}
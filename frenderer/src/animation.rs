@@ -0,0 +1,721 @@
+//! Skeletal animation: [`AnimationClip`]s of keyframed joint
+//! translations, rotations, and scales, played on a [`Skeleton`] to
+//! get the joint matrices a skinned mesh is drawn with.
+//!
+//! Clips and skeletons are shared between every instance that uses
+//! them; each instance only needs a [`ClipState`] saying which clip
+//! it's playing and how far along it is.  Tick the states every
+//! frame, then have a [`PoseSampler`] write every instance's joint
+//! matrices in one go with [`PoseSampler::write_instances`].
+//!
+//! With the `gltf` feature, skeletons and clips can be imported from
+//! a glTF file's skins and animations with [`Skeleton::from_gltf`]
+//! and [`AnimationClip::from_gltf`].
+
+use ultraviolet::{Mat4, Rotor3, Vec3};
+
+/// A joint's transform relative to its parent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointPose {
+    /// The joint's translation
+    pub translation: [f32; 3],
+    /// The joint's rotation as a quaternion `[x, y, z, w]`
+    pub rotation: [f32; 4],
+    /// The joint's scale along each axis
+    pub scale: [f32; 3],
+}
+
+impl JointPose {
+    /// The pose which leaves a joint where its parent is.
+    pub const IDENTITY: Self = Self {
+        translation: [0.0; 3],
+        rotation: [0.0, 0.0, 0.0, 1.0],
+        scale: [1.0; 3],
+    };
+    fn matrix(&self) -> Mat4 {
+        Mat4::from_translation(Vec3::from(self.translation))
+            * Rotor3::from_quaternion_array(self.rotation)
+                .into_matrix()
+                .into_homogeneous()
+            * Mat4::from_nonuniform_scale(Vec3::from(self.scale))
+    }
+}
+
+/// The joints of a skinned mesh: their hierarchy, their rest pose,
+/// and the inverse bind matrices taking the mesh into each joint's
+/// space.  Joint indices match the mesh's joint attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Skeleton {
+    names: Vec<String>,
+    parents: Vec<Option<usize>>,
+    rest: Vec<JointPose>,
+    inverse_bind: Vec<Mat4>,
+    // Joint indices with every parent before its children
+    order: Vec<usize>,
+}
+
+impl Skeleton {
+    /// Creates a skeleton from each joint's name, parent joint, rest
+    /// pose, and column-major inverse bind matrix.  Fails if the
+    /// lists' lengths differ, a parent is out of range, or the
+    /// parents form a cycle.
+    pub fn new(
+        names: Vec<String>,
+        parents: Vec<Option<usize>>,
+        rest: Vec<JointPose>,
+        inverse_bind: Vec<[[f32; 4]; 4]>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let count = names.len();
+        if parents.len() != count || rest.len() != count || inverse_bind.len() != count {
+            return Err(format!(
+                "Skeleton has {count} names but {} parents, {} rest poses, and {} inverse bind matrices",
+                parents.len(),
+                rest.len(),
+                inverse_bind.len()
+            )
+            .into());
+        }
+        if let Some(joint) = parents.iter().position(|p| p.is_some_and(|p| p >= count)) {
+            return Err(format!("Joint {joint}'s parent is out of range").into());
+        }
+        let mut order = Vec::with_capacity(count);
+        let mut placed = vec![false; count];
+        while order.len() < count {
+            let before = order.len();
+            for joint in 0..count {
+                if !placed[joint] && parents[joint].map_or(true, |p| placed[p]) {
+                    placed[joint] = true;
+                    order.push(joint);
+                }
+            }
+            if order.len() == before {
+                return Err("Skeleton's joint parents form a cycle".into());
+            }
+        }
+        Ok(Self {
+            names,
+            parents,
+            rest,
+            inverse_bind: inverse_bind.into_iter().map(Mat4::from).collect(),
+            order,
+        })
+    }
+    /// How many joints the skeleton has.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+    /// Whether the skeleton has no joints.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+    /// The index of the joint with the given name, if any.
+    pub fn joint(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+    /// Each joint's parent, if it has one.
+    pub fn parents(&self) -> &[Option<usize>] {
+        &self.parents
+    }
+    /// Each joint's pose when no animation moves it.
+    pub fn rest_pose(&self) -> &[JointPose] {
+        &self.rest
+    }
+}
+
+/// How a [`Channel`] gets from one keyframe to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Each key's value holds until the next key
+    Step,
+    /// Values are blended linearly (spherically for rotations)
+    Linear,
+    /// Values follow a cubic Hermite spline; each key has an
+    /// in-tangent, a value, and an out-tangent, in that order
+    CubicSpline,
+}
+
+/// Which part of a joint's pose a [`Channel`] animates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+    /// [`JointPose::translation`]
+    Translation,
+    /// [`JointPose::rotation`]
+    Rotation,
+    /// [`JointPose::scale`]
+    Scale,
+}
+
+/// Keyframes for one property of one joint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    /// The joint being animated
+    pub joint: usize,
+    /// The property being animated
+    pub property: Property,
+    /// How to blend between keys
+    pub interpolation: Interpolation,
+    /// Each key's time in seconds, in increasing order
+    pub times: Vec<f32>,
+    /// Each key's value (three per key for
+    /// [`Interpolation::CubicSpline`]); translations and scales leave
+    /// the last component unused
+    pub values: Vec<[f32; 4]>,
+}
+
+impl Channel {
+    /// The channel's value at `time` seconds, holding the first and
+    /// last keys' values outside their range.  Panics if the channel
+    /// has no keys.
+    pub fn sample(&self, time: f32) -> [f32; 4] {
+        let stride = if self.interpolation == Interpolation::CubicSpline {
+            3
+        } else {
+            1
+        };
+        // The value of key `k` (skipping cubic tangents)
+        let value = |k: usize| self.values[k * stride + stride / 2];
+        let next = self.times.partition_point(|&t| t <= time);
+        if next == 0 {
+            return value(0);
+        }
+        if next == self.times.len() {
+            return value(next - 1);
+        }
+        let prev = next - 1;
+        let span = self.times[next] - self.times[prev];
+        let t = (time - self.times[prev]) / span;
+        let rotation = self.property == Property::Rotation;
+        match self.interpolation {
+            Interpolation::Step => value(prev),
+            Interpolation::Linear if rotation => slerp(value(prev), value(next), t),
+            Interpolation::Linear => {
+                let (a, b) = (value(prev), value(next));
+                std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+            }
+            Interpolation::CubicSpline => {
+                let (t2, t3) = (t * t, t * t * t);
+                let (v0, out0) = (self.values[prev * 3 + 1], self.values[prev * 3 + 2]);
+                let (in1, v1) = (self.values[next * 3], self.values[next * 3 + 1]);
+                let out: [f32; 4] = std::array::from_fn(|i| {
+                    (2.0 * t3 - 3.0 * t2 + 1.0) * v0[i]
+                        + (t3 - 2.0 * t2 + t) * span * out0[i]
+                        + (-2.0 * t3 + 3.0 * t2) * v1[i]
+                        + (t3 - t2) * span * in1[i]
+                });
+                if rotation {
+                    normalize(out)
+                } else {
+                    out
+                }
+            }
+        }
+    }
+}
+
+fn normalize(q: [f32; 4]) -> [f32; 4] {
+    let len = q.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if len > 0.0 {
+        q.map(|c| c / len)
+    } else {
+        JointPose::IDENTITY.rotation
+    }
+}
+
+// Spherical interpolation between quaternions along the shorter arc
+fn slerp(a: [f32; 4], mut b: [f32; 4], t: f32) -> [f32; 4] {
+    let mut dot: f32 = (0..4).map(|i| a[i] * b[i]).sum();
+    if dot < 0.0 {
+        b = b.map(|c| -c);
+        dot = -dot;
+    }
+    let (wa, wb) = if dot > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let theta = dot.acos();
+        let sin = theta.sin();
+        (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+    };
+    normalize(std::array::from_fn(|i| a[i] * wa + b[i] * wb))
+}
+
+/// A named animation made of [`Channel`]s, shared by every instance playing it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    name: String,
+    duration: f32,
+    channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    /// Creates a clip from its channels; it lasts until the last key
+    /// of any channel.
+    pub fn new(name: impl Into<String>, channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|c| c.times.last().copied())
+            .fold(0.0, f32::max);
+        Self {
+            name: name.into(),
+            duration,
+            channels,
+        }
+    }
+    /// The clip's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// How long the clip lasts in seconds.
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+    /// The clip's channels.
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+    /// Overwrites the properties this clip animates in `pose` with
+    /// their values at `time` seconds, leaving the rest alone.
+    /// Channels for joints past the end of `pose` are ignored.
+    pub fn sample(&self, time: f32, pose: &mut [JointPose]) {
+        for channel in self.channels.iter().filter(|c| !c.times.is_empty()) {
+            let Some(joint) = pose.get_mut(channel.joint) else {
+                continue;
+            };
+            let [x, y, z, w] = channel.sample(time);
+            match channel.property {
+                Property::Translation => joint.translation = [x, y, z],
+                Property::Rotation => joint.rotation = [x, y, z, w],
+                Property::Scale => joint.scale = [x, y, z],
+            }
+        }
+    }
+}
+
+/// One instance's playback of an [`AnimationClip`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipState {
+    /// Which clip is playing, as an index into the clips given to
+    /// [`PoseSampler::write_instances`]
+    pub clip: usize,
+    /// How far into the clip playback is, in seconds
+    pub time: f32,
+    /// How fast the clip plays, where 1.0 is normal speed
+    pub speed: f32,
+    /// Whether the clip starts over when it ends (otherwise it holds its last pose)
+    pub looping: bool,
+}
+
+impl ClipState {
+    /// Starts looping `clip` from the beginning at normal speed.
+    pub fn new(clip: usize) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+        }
+    }
+    /// Advances playback by `dt` seconds of a clip lasting `duration` seconds.
+    pub fn tick(&mut self, dt: f32, duration: f32) {
+        self.time += dt * self.speed;
+        if self.looping && duration > 0.0 {
+            self.time = self.time.rem_euclid(duration);
+        } else {
+            self.time = self.time.clamp(0.0, duration);
+        }
+    }
+    /// Whether a non-looping clip lasting `duration` seconds has played to its end.
+    pub fn is_finished(&self, duration: f32) -> bool {
+        !self.looping && self.time >= duration
+    }
+}
+
+/// Turns clips and playback times into joint matrices, reusing its
+/// scratch space between instances.
+#[derive(Clone, Debug, Default)]
+pub struct PoseSampler {
+    pose: Vec<JointPose>,
+    globals: Vec<Mat4>,
+}
+
+impl PoseSampler {
+    /// Creates a sampler; its scratch space grows to fit the largest
+    /// skeleton it's used with.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Writes the column-major joint matrices (each joint's global
+    /// transform times its inverse bind matrix) of `skeleton` posed by
+    /// `clip` at `time` seconds into the first [`Skeleton::len`]
+    /// entries of `out`.  Panics if `out` is too short.
+    pub fn write_joints(
+        &mut self,
+        skeleton: &Skeleton,
+        clip: &AnimationClip,
+        time: f32,
+        out: &mut [[[f32; 4]; 4]],
+    ) {
+        let count = skeleton.len();
+        assert!(out.len() >= count, "Not enough room for joint matrices");
+        self.pose.clear();
+        self.pose.extend_from_slice(&skeleton.rest);
+        clip.sample(time, &mut self.pose);
+        self.globals.resize(count, Mat4::identity());
+        for &joint in skeleton.order.iter() {
+            let local = self.pose[joint].matrix();
+            self.globals[joint] = match skeleton.parents[joint] {
+                Some(parent) => self.globals[parent] * local,
+                None => local,
+            };
+            out[joint] = (self.globals[joint] * skeleton.inverse_bind[joint]).into();
+        }
+    }
+    /// Writes [`Skeleton::len`] joint matrices per instance into
+    /// `out`, one instance after another, with each instance playing
+    /// `clips[state.clip]` at its own time.  Returns how many instances
+    /// fit in `out`.  Panics if a state's clip is out of range.
+    pub fn write_instances(
+        &mut self,
+        skeleton: &Skeleton,
+        clips: &[AnimationClip],
+        states: &[ClipState],
+        out: &mut [[[f32; 4]; 4]],
+    ) -> usize {
+        let count = skeleton.len().max(1);
+        let mut written = 0;
+        for (state, joints) in states.iter().zip(out.chunks_exact_mut(count)) {
+            self.write_joints(skeleton, &clips[state.clip], state.time, joints);
+            written += 1;
+        }
+        written
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl Skeleton {
+    /// Imports a glTF skin's joints, using each joint node's
+    /// transform as its rest pose.  `buffers` are the file's buffer
+    /// contents, e.g. from `gltf::import`.  Transforms of nodes above
+    /// the skin's top joints are not included.
+    pub fn from_gltf<B: std::ops::Deref<Target = [u8]>>(
+        skin: &gltf::Skin,
+        buffers: &[B],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let nodes: Vec<_> = skin.joints().collect();
+        let joint_of = |node: usize| nodes.iter().position(|n| n.index() == node);
+        let mut parents = vec![None; nodes.len()];
+        for (joint, node) in nodes.iter().enumerate() {
+            for child in node.children() {
+                if let Some(child) = joint_of(child.index()) {
+                    parents[child] = Some(joint);
+                }
+            }
+        }
+        let rest = nodes
+            .iter()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+                JointPose {
+                    translation,
+                    rotation,
+                    scale,
+                }
+            })
+            .collect();
+        let names = nodes
+            .iter()
+            .map(|node| {
+                node.name()
+                    .map_or_else(|| format!("joint{}", node.index()), str::to_string)
+            })
+            .collect();
+        let reader = skin.reader(|buffer| buffers.get(buffer.index()).map(|data| &**data));
+        let inverse_bind = match reader.read_inverse_bind_matrices() {
+            Some(matrices) => matrices.collect(),
+            None => vec![Mat4::identity().into(); nodes.len()],
+        };
+        Self::new(names, parents, rest, inverse_bind)
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl AnimationClip {
+    /// Imports a glTF animation's channels which target joints of
+    /// `skin`; channels for other nodes and morph target weights are
+    /// skipped.  `buffers` are the file's buffer contents, as for
+    /// [`Skeleton::from_gltf`].
+    pub fn from_gltf<B: std::ops::Deref<Target = [u8]>>(
+        animation: &gltf::Animation,
+        skin: &gltf::Skin,
+        buffers: &[B],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        use gltf::animation::util::ReadOutputs;
+        let name = animation
+            .name()
+            .map_or_else(|| format!("animation{}", animation.index()), str::to_string);
+        let joints: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+        let mut channels = vec![];
+        for channel in animation.channels() {
+            let node = channel.target().node().index();
+            let Some(joint) = joints.iter().position(|&n| n == node) else {
+                continue;
+            };
+            let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(|data| &**data));
+            let times: Vec<f32> = reader
+                .read_inputs()
+                .ok_or_else(|| format!("Animation {name:?} is missing keyframe times"))?
+                .collect();
+            let (property, values): (_, Vec<[f32; 4]>) = match reader.read_outputs() {
+                Some(ReadOutputs::Translations(t)) => (
+                    Property::Translation,
+                    t.map(|[x, y, z]| [x, y, z, 0.0]).collect(),
+                ),
+                Some(ReadOutputs::Rotations(r)) => (Property::Rotation, r.into_f32().collect()),
+                Some(ReadOutputs::Scales(s)) => {
+                    (Property::Scale, s.map(|[x, y, z]| [x, y, z, 0.0]).collect())
+                }
+                Some(ReadOutputs::MorphTargetWeights(_)) => continue,
+                None => return Err(format!("Animation {name:?} is missing keyframe values").into()),
+            };
+            let interpolation = match channel.sampler().interpolation() {
+                gltf::animation::Interpolation::Step => Interpolation::Step,
+                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+            };
+            let stride = if interpolation == Interpolation::CubicSpline {
+                3
+            } else {
+                1
+            };
+            if values.len() != times.len() * stride {
+                return Err(format!(
+                    "Animation {name:?} has {} keyframe times but {} values",
+                    times.len(),
+                    values.len()
+                )
+                .into());
+            }
+            channels.push(Channel {
+                joint,
+                property,
+                interpolation,
+                times,
+                values,
+            });
+        }
+        Ok(Self::new(name, channels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: [[f32; 4]; 4] = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+
+    fn channel(
+        property: Property,
+        interpolation: Interpolation,
+        keys: &[(f32, [f32; 4])],
+    ) -> Channel {
+        Channel {
+            joint: 0,
+            property,
+            interpolation,
+            times: keys.iter().map(|k| k.0).collect(),
+            values: keys.iter().map(|k| k.1).collect(),
+        }
+    }
+    fn close(a: [f32; 4], b: [f32; 4]) -> bool {
+        (0..4).all(|i| (a[i] - b[i]).abs() < 1e-5)
+    }
+    // A rotation of `angle` radians about the z axis
+    fn about_z(angle: f32) -> [f32; 4] {
+        [0.0, 0.0, (angle / 2.0).sin(), (angle / 2.0).cos()]
+    }
+    fn pose(translation: [f32; 3]) -> JointPose {
+        JointPose {
+            translation,
+            ..JointPose::IDENTITY
+        }
+    }
+    // A root joint with a child two units above it
+    fn arm() -> Skeleton {
+        Skeleton::new(
+            vec!["hand".into(), "shoulder".into()],
+            vec![Some(1), None],
+            vec![pose([0.0, 2.0, 0.0]), pose([1.0, 0.0, 0.0])],
+            vec![IDENTITY; 2],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_linear_sampling() {
+        let keys = [(1.0, [0.0, 0.0, 0.0, 0.0]), (3.0, [4.0, -2.0, 8.0, 0.0])];
+        let linear = channel(Property::Translation, Interpolation::Linear, &keys);
+        // The ends hold outside the keys' range
+        assert_eq!(linear.sample(-5.0), keys[0].1);
+        assert_eq!(linear.sample(1.0), keys[0].1);
+        assert_eq!(linear.sample(2.0), [2.0, -1.0, 4.0, 0.0]);
+        assert_eq!(linear.sample(3.0), keys[1].1);
+        assert_eq!(linear.sample(10.0), keys[1].1);
+        let step = channel(Property::Translation, Interpolation::Step, &keys);
+        assert_eq!(step.sample(2.9), keys[0].1);
+        assert_eq!(step.sample(3.0), keys[1].1);
+        let single = channel(Property::Scale, Interpolation::Linear, &keys[1..]);
+        assert_eq!(single.sample(0.0), keys[1].1);
+    }
+    #[test]
+    fn test_rotation_sampling() {
+        let quarter = std::f32::consts::FRAC_PI_2;
+        let keys = [(0.0, about_z(0.0)), (1.0, about_z(quarter))];
+        let rotation = channel(Property::Rotation, Interpolation::Linear, &keys);
+        assert!(close(rotation.sample(0.5), about_z(quarter / 2.0)));
+        assert!(close(rotation.sample(1.0), about_z(quarter)));
+        // Blending takes the shorter way around
+        let negated = about_z(quarter).map(|c| -c);
+        let rotation = channel(
+            Property::Rotation,
+            Interpolation::Linear,
+            &[keys[0], (1.0, negated)],
+        );
+        assert!(close(rotation.sample(0.5), about_z(quarter / 2.0)));
+    }
+    #[test]
+    fn test_cubic_sampling() {
+        let flat = [0.0; 4];
+        let cubic = Channel {
+            values: vec![
+                flat,
+                [0.0, 0.0, 0.0, 0.0],
+                flat,
+                flat,
+                [2.0, 4.0, 6.0, 0.0],
+                flat,
+            ],
+            ..channel(
+                Property::Translation,
+                Interpolation::CubicSpline,
+                &[(0.0, flat), (2.0, flat)],
+            )
+        };
+        // Values come from the middle of each triple, not the tangents
+        assert_eq!(cubic.sample(0.0), [0.0; 4]);
+        assert_eq!(cubic.sample(2.0), [2.0, 4.0, 6.0, 0.0]);
+        assert_eq!(cubic.sample(3.0), [2.0, 4.0, 6.0, 0.0]);
+        // With flat tangents the curve eases through the middle
+        assert!(close(cubic.sample(1.0), [1.0, 2.0, 3.0, 0.0]));
+        assert!(cubic.sample(0.5)[0] < 0.5);
+    }
+    #[test]
+    fn test_clip_sample() {
+        let moved = Channel {
+            joint: 1,
+            ..channel(
+                Property::Translation,
+                Interpolation::Linear,
+                &[(0.0, [0.0; 4]), (2.0, [4.0, 0.0, 0.0, 0.0])],
+            )
+        };
+        let out_of_range = Channel {
+            joint: 5,
+            ..moved.clone()
+        };
+        let empty = channel(Property::Scale, Interpolation::Linear, &[]);
+        let clip = AnimationClip::new("walk", vec![moved, out_of_range, empty]);
+        assert_eq!(clip.name(), "walk");
+        assert_eq!(clip.duration(), 2.0);
+        let mut pose = arm().rest_pose().to_vec();
+        clip.sample(1.0, &mut pose);
+        assert_eq!(pose[1].translation, [2.0, 0.0, 0.0]);
+        assert_eq!(pose[0], arm().rest_pose()[0]);
+        assert_eq!(AnimationClip::new("still", vec![]).duration(), 0.0);
+    }
+    #[test]
+    fn test_clip_state() {
+        let mut state = ClipState::new(0);
+        state.tick(2.5, 2.0);
+        assert_eq!(state.time, 0.5);
+        state.speed = -1.0;
+        state.tick(1.0, 2.0);
+        assert_eq!(state.time, 1.5);
+        state.looping = false;
+        state.speed = 1.0;
+        assert!(!state.is_finished(2.0));
+        state.tick(5.0, 2.0);
+        assert_eq!(state.time, 2.0);
+        assert!(state.is_finished(2.0));
+        // Looping a clip with no length doesn't divide by zero
+        let mut state = ClipState::new(0);
+        state.tick(1.0, 0.0);
+        assert_eq!(state.time, 0.0);
+    }
+    #[test]
+    fn test_skeleton_errors() {
+        let new = |parents: Vec<Option<usize>>| {
+            let count = parents.len();
+            Skeleton::new(
+                (0..count).map(|i| i.to_string()).collect(),
+                parents,
+                vec![JointPose::IDENTITY; count],
+                vec![IDENTITY; count],
+            )
+        };
+        assert!(new(vec![None, Some(0)]).is_ok());
+        assert!(new(vec![None, Some(2)]).is_err());
+        assert!(new(vec![Some(1), Some(0)]).is_err());
+        assert!(new(vec![Some(0)]).is_err());
+        assert!(Skeleton::new(vec!["a".into()], vec![None], vec![], vec![IDENTITY]).is_err());
+        assert!(new(vec![]).unwrap().is_empty());
+        assert_eq!(arm().joint("shoulder"), Some(1));
+        assert_eq!(arm().joint("elbow"), None);
+    }
+    #[test]
+    fn test_write_joints() {
+        let skeleton = arm();
+        let clips = [
+            AnimationClip::new("still", vec![]),
+            AnimationClip::new(
+                "raise",
+                vec![Channel {
+                    joint: 1,
+                    ..channel(
+                        Property::Rotation,
+                        Interpolation::Linear,
+                        &[
+                            (0.0, about_z(0.0)),
+                            (1.0, about_z(std::f32::consts::FRAC_PI_2)),
+                        ],
+                    )
+                }],
+            ),
+        ];
+        let mut sampler = PoseSampler::new();
+        let mut out = [IDENTITY; 5];
+        let states = [
+            ClipState::new(0),
+            ClipState {
+                time: 1.0,
+                ..ClipState::new(1)
+            },
+            ClipState::new(0),
+        ];
+        // Only whole instances are written
+        assert_eq!(
+            sampler.write_instances(&skeleton, &clips, &states, &mut out),
+            2
+        );
+        // Children follow their parents
+        let translation = |m: [[f32; 4]; 4]| [m[3][0], m[3][1], m[3][2], m[3][3]];
+        assert!(close(translation(out[0]), [1.0, 2.0, 0.0, 1.0]));
+        assert!(close(translation(out[1]), [1.0, 0.0, 0.0, 1.0]));
+        // Turning the shoulder a quarter turn swings the hand to its left
+        assert!(close(translation(out[2]), [-1.0, 0.0, 0.0, 1.0]));
+        assert!(close(translation(out[3]), [1.0, 0.0, 0.0, 1.0]));
+    }
+}
@@ -0,0 +1,766 @@
+//! Autotiling for square [`Tilemap`]s: paint a grid of terrain kinds
+//! and let an [`Autotiler`] pick each tile's variant from a 47-tile
+//! "blob" set, so edges, corners, and inner corners between terrains
+//! come out right without placing them by hand.
+//!
+//! A tile's variant depends on which of its eight neighbors share its
+//! terrain.  Diagonal neighbors only matter when both of the edges
+//! beside them match too, which leaves the 47 distinct cases listed
+//! in [`BLOB_MASKS`]; variant `i` is the tile for `BLOB_MASKS[i]`.
+//! Changing terrain marks the surrounding area dirty, and
+//! [`Autotiler::update`] only recomputes tiles there, so editing a
+//! big map stays cheap.
+//!
+//! For maps too big to retile on the CPU, e.g. in editors, an
+//! [`AutotileSprites`] writes tiles straight into a sprite group
+//! with one sprite per tile, running the same rules in a compute
+//! pass where the GPU supports it and on the CPU otherwise.
+
+use crate::sprites::{SheetRegion, SpriteRenderer, Transform};
+use crate::tilemap::{TileGrid, TileLayout, Tilemap};
+use crate::WGPU;
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+/// Neighbor bit for the tile above (row + 1)
+pub const NORTH: u8 = 1;
+/// Neighbor bit for the tile above and to the right
+pub const NORTH_EAST: u8 = 2;
+/// Neighbor bit for the tile to the right (column + 1)
+pub const EAST: u8 = 4;
+/// Neighbor bit for the tile below and to the right
+pub const SOUTH_EAST: u8 = 8;
+/// Neighbor bit for the tile below (row - 1)
+pub const SOUTH: u8 = 16;
+/// Neighbor bit for the tile below and to the left
+pub const SOUTH_WEST: u8 = 32;
+/// Neighbor bit for the tile to the left (column - 1)
+pub const WEST: u8 = 64;
+/// Neighbor bit for the tile above and to the left
+pub const NORTH_WEST: u8 = 128;
+
+// Each neighbor's bit and offset, clockwise from north
+const NEIGHBORS: [(u8, [i32; 2]); 8] = [
+    (NORTH, [0, 1]),
+    (NORTH_EAST, [1, 1]),
+    (EAST, [1, 0]),
+    (SOUTH_EAST, [1, -1]),
+    (SOUTH, [0, -1]),
+    (SOUTH_WEST, [-1, -1]),
+    (WEST, [-1, 0]),
+    (NORTH_WEST, [-1, 1]),
+];
+
+// Drops the diagonal bits whose two neighboring edges aren't both set
+const fn reduce_mask(mask: u8) -> u8 {
+    let mut out = mask & (NORTH | EAST | SOUTH | WEST);
+    let corners = [
+        (NORTH_EAST, NORTH | EAST),
+        (SOUTH_EAST, SOUTH | EAST),
+        (SOUTH_WEST, SOUTH | WEST),
+        (NORTH_WEST, NORTH | WEST),
+    ];
+    let mut i = 0;
+    while i < corners.len() {
+        let (corner, edges) = corners[i];
+        if mask & edges == edges {
+            out |= mask & corner;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// The neighbor masks (built from [`NORTH`], [`NORTH_EAST`], and so
+/// on) which the 47 blob variants stand for, in variant order.
+pub const BLOB_MASKS: [u8; 47] = {
+    let mut masks = [0; 47];
+    let (mut mask, mut count) = (0, 0);
+    while mask < 256 {
+        if reduce_mask(mask as u8) == mask as u8 {
+            masks[count] = mask as u8;
+            count += 1;
+        }
+        mask += 1;
+    }
+    masks
+};
+
+// The variant for each reduced mask
+const BLOB_VARIANTS: [u8; 256] = {
+    let mut variants = [0; 256];
+    let mut i = 0;
+    while i < BLOB_MASKS.len() {
+        variants[BLOB_MASKS[i] as usize] = i as u8;
+        i += 1;
+    }
+    variants
+};
+
+/// The blob variant (an index into [`BLOB_MASKS`]) for a tile whose
+/// matching neighbors are the set bits of `mask`.
+pub fn blob_variant(mask: u8) -> u8 {
+    BLOB_VARIANTS[reduce_mask(mask) as usize]
+}
+
+/// A grid of terrain kinds which picks the tiles of a [`Tilemap`]
+/// of the same size.  Terrain `0` is empty and leaves its tiles
+/// clear; tiles of other terrains connect to neighbors of the same
+/// terrain.  Cells past the map's edges count as matching, so
+/// terrain runs off the edges seamlessly.
+#[derive(Clone, Debug)]
+pub struct Autotiler {
+    width: usize,
+    height: usize,
+    terrain: Vec<u16>,
+    // The inclusive [min col, min row, max col, max row] needing update
+    dirty: Option<[i32; 4]>,
+}
+
+impl Autotiler {
+    /// Creates an empty terrain grid with the given size in tiles;
+    /// the first [`Autotiler::update`] clears the whole tilemap.
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut tiler = Self {
+            width,
+            height,
+            terrain: vec![0; width * height],
+            dirty: None,
+        };
+        tiler.mark_all_dirty();
+        tiler
+    }
+    /// The grid's width and height in tiles.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    /// The terrain at the given position, if it's in bounds.
+    pub fn terrain(&self, [col, row]: [i32; 2]) -> Option<u16> {
+        self.index(col, row).map(|idx| self.terrain[idx])
+    }
+    /// Sets the terrain at the given position, marking it and its
+    /// neighbors for update.  Panics if the position is out of bounds.
+    pub fn set_terrain(&mut self, [col, row]: [i32; 2], terrain: u16) {
+        let idx = self
+            .index(col, row)
+            .expect("Terrain position out of bounds");
+        if self.terrain[idx] != terrain {
+            self.terrain[idx] = terrain;
+            self.mark_dirty([col - 1, row - 1], [col + 1, row + 1]);
+        }
+    }
+    /// Sets the terrain of every position from `min` to `max`
+    /// inclusive which is in bounds.
+    pub fn fill_terrain(&mut self, min: [i32; 2], max: [i32; 2], terrain: u16) {
+        for row in min[1].max(0)..=max[1].min(self.height as i32 - 1) {
+            for col in min[0].max(0)..=max[0].min(self.width as i32 - 1) {
+                self.set_terrain([col, row], terrain);
+            }
+        }
+    }
+    /// Marks the whole map for update, e.g. after changing which
+    /// regions the tiles come from.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = Some([0, 0, self.width as i32 - 1, self.height as i32 - 1]);
+    }
+    /// Whether some tiles need to be recomputed by [`Autotiler::update`].
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.is_some()
+    }
+    /// The blob variant of the given position, or `None` if it's out
+    /// of bounds or empty.
+    pub fn variant(&self, [col, row]: [i32; 2]) -> Option<u8> {
+        let terrain = self.terrain([col, row]).filter(|&t| t != 0)?;
+        let mask = NEIGHBORS
+            .iter()
+            .filter(|(_, [dc, dr])| {
+                self.terrain([col + dc, row + dr])
+                    .map_or(true, |other| other == terrain)
+            })
+            .fold(0, |mask, (bit, _)| mask | bit);
+        Some(blob_variant(mask))
+    }
+    /// Writes the tiles of the area changed since the last update into
+    /// `tilemap`, getting each one's region from `tile` given its
+    /// terrain and blob variant.  Returns whether anything was
+    /// written.  Panics if `tilemap` isn't the same size as the grid.
+    pub fn update(
+        &mut self,
+        tilemap: &mut Tilemap,
+        tile: impl Fn(u16, u8) -> Option<SheetRegion>,
+    ) -> bool {
+        assert_eq!(
+            tilemap.size(),
+            self.size(),
+            "Tilemap and terrain sizes differ"
+        );
+        let Some([min_col, min_row, max_col, max_row]) = self.take_dirty() else {
+            return false;
+        };
+        for row in min_row..=max_row {
+            for col in min_col..=max_col {
+                let terrain = self.terrain[self.index(col, row).unwrap()];
+                let region = self
+                    .variant([col, row])
+                    .and_then(|variant| tile(terrain, variant));
+                tilemap.set([col, row], region);
+            }
+        }
+        true
+    }
+    // Takes the dirty area, clipped to the map, if there is one
+    fn take_dirty(&mut self) -> Option<[i32; 4]> {
+        let [min_col, min_row, max_col, max_row] = self.dirty.take()?;
+        let dirty = [
+            min_col.max(0),
+            min_row.max(0),
+            max_col.min(self.width as i32 - 1),
+            max_row.min(self.height as i32 - 1),
+        ];
+        (dirty[0] <= dirty[2] && dirty[1] <= dirty[3]).then_some(dirty)
+    }
+    fn mark_dirty(&mut self, min: [i32; 2], max: [i32; 2]) {
+        self.dirty = Some(match self.dirty {
+            None => [min[0], min[1], max[0], max[1]],
+            Some([c0, r0, c1, r1]) => [
+                c0.min(min[0]),
+                r0.min(min[1]),
+                c1.max(max[0]),
+                r1.max(max[1]),
+            ],
+        });
+    }
+    fn index(&self, col: i32, row: i32) -> Option<usize> {
+        let (col, row) = (usize::try_from(col).ok()?, usize::try_from(row).ok()?);
+        (col < self.width && row < self.height).then_some(row * self.width + col)
+    }
+}
+
+/// Writes an [`Autotiler`]'s tiles into a sprite group with one
+/// sprite per tile, row by row from `[0, 0]`, so the group needs
+/// exactly as many sprites as the map has tiles.  Empty tiles get
+/// zero-sized sprites.  Like [`Autotiler::update`],
+/// [`AutotileSprites::update`] only recomputes the area changed since
+/// the last update.
+///
+/// By default the tiles are computed on the CPU; with
+/// [`AutotileSprites::with_compute`] they're computed in a compute
+/// pass writing straight into the group's GPU buffers instead, if
+/// the GPU supports it.
+pub struct AutotileSprites {
+    tiler: Autotiler,
+    grid: TileGrid,
+    terrains: u16,
+    // Each terrain's 47 variants' regions from terrain 1 up, ZERO for no tile
+    regions: Vec<SheetRegion>,
+    compute: Option<AutotileCompute>,
+}
+
+struct AutotileCompute {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    // The variant for each neighbor mask, then the regions
+    table_buffer: wgpu::Buffer,
+    // Each tile's terrain as a u32
+    terrain_buffer: wgpu::Buffer,
+}
+
+// Matches Params in autotile.wgsl
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+struct AutotileParams {
+    size: [u32; 2],
+    min: [u32; 2],
+    extent: [u32; 2],
+    origin: [f32; 2],
+    tile_size: [f32; 2],
+    terrains: u32,
+    padding: u32,
+}
+
+// Matches the workgroup size in autotile.wgsl
+const WORKGROUP_SIZE: u32 = 8;
+
+impl AutotileSprites {
+    /// Tiles `tiler`'s terrain on `grid`, which must be a square grid.
+    /// Each terrain from `1` to `terrains` gets its tiles' regions
+    /// from `tile` given its blob variant, as in [`Autotiler::update`];
+    /// tiles of other terrains are left empty.
+    pub fn new(
+        tiler: Autotiler,
+        grid: TileGrid,
+        terrains: u16,
+        tile: impl Fn(u16, u8) -> Option<SheetRegion>,
+    ) -> Self {
+        assert_eq!(
+            grid.layout,
+            TileLayout::Square,
+            "Autotiling needs a square grid"
+        );
+        let regions = (1..=terrains)
+            .flat_map(|terrain| (0..BLOB_MASKS.len() as u8).map(move |variant| (terrain, variant)))
+            .map(|(terrain, variant)| tile(terrain, variant).unwrap_or(SheetRegion::ZERO))
+            .collect();
+        Self {
+            tiler,
+            grid,
+            terrains,
+            regions,
+            compute: None,
+        }
+    }
+    /// Computes tiles in a compute pass on `gpu` if it
+    /// [supports compute shaders](WGPU::supports_compute), falling
+    /// back to the CPU otherwise.
+    pub fn with_compute(mut self, gpu: &WGPU) -> Self {
+        let limits = gpu.device().limits();
+        if gpu.supports_compute()
+            && limits.max_compute_invocations_per_workgroup >= WORKGROUP_SIZE * WORKGROUP_SIZE
+        {
+            self.compute = Some(AutotileCompute::new(gpu, &self.tiler, &self.regions));
+            // The GPU's copy of the terrain starts out complete
+            self.tiler.mark_all_dirty();
+        }
+        self
+    }
+    /// Whether tiles are computed in a compute pass.
+    pub fn uses_compute(&self) -> bool {
+        self.compute.is_some()
+    }
+    /// The terrain being tiled.
+    pub fn tiler(&self) -> &Autotiler {
+        &self.tiler
+    }
+    /// The terrain being tiled, to paint it; changes show up after
+    /// the next [`AutotileSprites::update`].
+    pub fn tiler_mut(&mut self) -> &mut Autotiler {
+        &mut self.tiler
+    }
+    /// How many sprites the sprite group needs: one per tile.
+    pub fn sprite_count(&self) -> usize {
+        let (width, height) = self.tiler.size();
+        width * height
+    }
+    /// Writes the tiles of the area changed since the last update into
+    /// sprite group `which` of `sprites`, returning whether anything
+    /// was written.  Panics if the group is compact or doesn't have
+    /// [`AutotileSprites::sprite_count`] sprites.
+    ///
+    /// The compute pass writes only to the group's GPU buffers, so
+    /// its sprites on the CPU (e.g. from
+    /// [`SpriteRenderer::get_sprites`]) are left alone.  If they're
+    /// uploaded again, for instance when the group is resized or
+    /// reallocated, call [`Autotiler::mark_all_dirty`] to restore the
+    /// tiles.
+    pub fn update(&mut self, gpu: &WGPU, sprites: &mut SpriteRenderer, which: usize) -> bool {
+        assert_eq!(
+            sprites.sprite_group_size(which),
+            self.sprite_count(),
+            "Sprite group and terrain sizes differ"
+        );
+        let Some(dirty) = self.tiler.take_dirty() else {
+            return false;
+        };
+        if let Some(compute) = self.compute.as_ref() {
+            let (world, sheet) = sprites
+                .storage_buffers(which)
+                .expect("Autotiled sprite groups can't be compact");
+            compute.run(gpu, self, dirty, world, sheet);
+        } else {
+            let width = self.tiler.width;
+            let rows = dirty[1] as usize * width..(dirty[3] as usize + 1) * width;
+            let (trfs, uvs) = sprites.get_sprites_mut(which);
+            assert_eq!(
+                trfs.len(),
+                self.sprite_count(),
+                "Autotiled sprite groups can't be compact"
+            );
+            self.write_tiles(dirty, trfs, uvs);
+            sprites.upload_sprites(gpu, which, rows);
+        }
+        true
+    }
+    // The region for a tile, ZERO if it has none
+    fn region(&self, terrain: u16, variant: u8) -> SheetRegion {
+        if terrain == 0 || terrain > self.terrains {
+            return SheetRegion::ZERO;
+        }
+        self.regions[(terrain as usize - 1) * BLOB_MASKS.len() + variant as usize]
+    }
+    // Writes the sprites of the tiles from [min col, min row] to
+    // [max col, max row] inclusive, like the compute pass does
+    fn write_tiles(&self, dirty: [i32; 4], trfs: &mut [Transform], uvs: &mut [SheetRegion]) {
+        let [min_col, min_row, max_col, max_row] = dirty;
+        for row in min_row..=max_row {
+            for col in min_col..=max_col {
+                let idx = row as usize * self.tiler.width + col as usize;
+                let terrain = self.tiler.terrain[idx];
+                let region = self
+                    .tiler
+                    .variant([col, row])
+                    .map_or(SheetRegion::ZERO, |variant| self.region(terrain, variant));
+                if region.w == 0 && region.h == 0 {
+                    trfs[idx] = Transform::ZERO;
+                    uvs[idx] = SheetRegion::ZERO;
+                    continue;
+                }
+                let [x, y] = self.grid.tile_to_world([col, row]);
+                trfs[idx] = Transform {
+                    w: self.grid.tile_w as u16,
+                    h: self.grid.tile_h as u16,
+                    x,
+                    y,
+                    rot: 0.0,
+                };
+                uvs[idx] = region;
+            }
+        }
+    }
+}
+
+impl AutotileCompute {
+    fn new(gpu: &WGPU, tiler: &Autotiler, regions: &[SheetRegion]) -> Self {
+        let device = gpu.device();
+        let shader = gpu.create_shader(wgpu::ShaderModuleDescriptor {
+            label: Some("autotile:shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("autotile.wgsl"))),
+        });
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage = |read_only| wgpu::BufferBindingType::Storage { read_only };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("autotile:bgl"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                // The terrain and the variant and region table
+                buffer_entry(1, storage(true)),
+                buffer_entry(2, storage(true)),
+                // The sprite group's transforms and regions
+                buffer_entry(3, storage(false)),
+                buffer_entry(4, storage(false)),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("autotile:pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("autotile:pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("autotile:params_buffer"),
+            size: std::mem::size_of::<AutotileParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let variants = (0..=255).map(|mask| blob_variant(mask) as u32);
+        let table: Vec<u32> = variants
+            .chain(
+                bytemuck::cast_slice::<SheetRegion, u32>(regions)
+                    .iter()
+                    .copied(),
+            )
+            .collect();
+        let table_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("autotile:table_buffer"),
+            contents: bytemuck::cast_slice(&table),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let terrain_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("autotile:terrain_buffer"),
+            // Storage bindings can't be empty
+            size: (tiler.terrain.len().max(1) * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            pipeline,
+            bind_group_layout,
+            params_buffer,
+            table_buffer,
+            terrain_buffer,
+        }
+    }
+    fn run(
+        &self,
+        gpu: &WGPU,
+        sprites: &AutotileSprites,
+        dirty: [i32; 4],
+        world: &wgpu::Buffer,
+        sheet: &wgpu::Buffer,
+    ) {
+        let [min_col, min_row, max_col, max_row] = dirty.map(|x| x as u32);
+        let tiler = &sprites.tiler;
+        // Every change is inside the dirty area, so its rows hold
+        // all the terrain the GPU hasn't seen yet
+        let width = tiler.width;
+        let rows = min_row as usize * width..(max_row as usize + 1) * width;
+        let terrain: Vec<u32> = tiler.terrain[rows.clone()]
+            .iter()
+            .map(|&t| t as u32)
+            .collect();
+        gpu.queue().write_buffer(
+            &self.terrain_buffer,
+            (rows.start * std::mem::size_of::<u32>()) as u64,
+            bytemuck::cast_slice(&terrain),
+        );
+        let extent = [max_col - min_col + 1, max_row - min_row + 1];
+        let params = AutotileParams {
+            size: [width as u32, tiler.height as u32],
+            min: [min_col, min_row],
+            extent,
+            origin: sprites.grid.origin,
+            tile_size: [sprites.grid.tile_w, sprites.grid.tile_h],
+            terrains: sprites.terrains as u32,
+            padding: 0,
+        };
+        gpu.queue()
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+        let bind_group = gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("autotile:bg"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.terrain_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.table_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: world.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: sheet.as_entire_binding(),
+                },
+            ],
+        });
+        let mut encoder = gpu
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("autotile:encoder"),
+            });
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("autotile:pass"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(
+                extent[0].div_ceil(WORKGROUP_SIZE),
+                extent[1].div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        gpu.queue().submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::{TileGrid, TileLayout};
+
+    fn tilemap(width: usize, height: usize) -> Tilemap {
+        let grid = TileGrid {
+            layout: TileLayout::Square,
+            tile_w: 16.0,
+            tile_h: 16.0,
+            origin: [0.0, 0.0],
+        };
+        Tilemap::new(grid, width, height)
+    }
+    // Encodes the terrain and variant in the region so tests can read them back
+    fn region(terrain: u16, variant: u8) -> Option<SheetRegion> {
+        Some(SheetRegion::rect(variant as u16, terrain, 16, 16))
+    }
+
+    #[test]
+    fn test_blob_masks() {
+        for (idx, &mask) in BLOB_MASKS.iter().enumerate() {
+            assert_eq!(blob_variant(mask) as usize, idx);
+            assert!(!BLOB_MASKS[..idx].contains(&mask));
+        }
+        // A lone corner bit doesn't change anything without its edges
+        assert_eq!(blob_variant(NORTH_EAST), blob_variant(0));
+        assert_eq!(
+            blob_variant(NORTH | EAST | NORTH_EAST),
+            BLOB_MASKS
+                .iter()
+                .position(|&m| m == NORTH | EAST | NORTH_EAST)
+                .unwrap() as u8
+        );
+    }
+    #[test]
+    fn test_variants() {
+        let mut tiler = Autotiler::new(5, 5);
+        tiler.set_terrain([2, 2], 1);
+        assert_eq!(tiler.variant([2, 2]), Some(blob_variant(0)));
+        // Empty and out of bounds positions have no variant
+        assert_eq!(tiler.variant([0, 0]), None);
+        assert_eq!(tiler.variant([-1, 2]), None);
+        assert_eq!(tiler.variant([5, 2]), None);
+        assert_eq!(tiler.terrain([2, 5]), None);
+        // Cells past the edge count as matching
+        tiler.set_terrain([0, 0], 2);
+        assert_eq!(
+            tiler.variant([0, 0]),
+            Some(blob_variant(SOUTH | SOUTH_WEST | WEST | NORTH_WEST))
+        );
+        tiler.fill_terrain([-3, -3], [10, 10], 1);
+        assert_eq!(tiler.variant([2, 2]), Some(blob_variant(0xff)));
+    }
+    #[test]
+    fn test_update() {
+        let mut tiler = Autotiler::new(4, 3);
+        let mut map = tilemap(4, 3);
+        map.set([3, 2], region(9, 9));
+        assert!(tiler.update(&mut map, region));
+        // The first update clears the whole map
+        assert_eq!(map.sprite_count(), 0);
+        assert!(!tiler.is_dirty());
+        assert!(!tiler.update(&mut map, region));
+
+        tiler.set_terrain([1, 1], 3);
+        assert!(tiler.is_dirty());
+        assert!(tiler.update(&mut map, region));
+        let tile = map.get([1, 1]).unwrap();
+        assert_eq!(tile.y, 3);
+        assert_eq!(tile.x, blob_variant(0) as u16);
+        assert_eq!(map.sprite_count(), 1);
+        assert!(!tiler.update(&mut map, region));
+    }
+    #[test]
+    fn test_empty() {
+        let mut tiler = Autotiler::new(0, 0);
+        let mut map = tilemap(0, 0);
+        assert_eq!(tiler.terrain([0, 0]), None);
+        tiler.fill_terrain([0, 0], [3, 3], 1);
+        tiler.update(&mut map, region);
+        assert!(!tiler.is_dirty());
+    }
+    // A region for the first two terrains only
+    fn two_terrains(terrain: u16, variant: u8) -> Option<SheetRegion> {
+        (terrain <= 2).then(|| region(terrain, variant).unwrap())
+    }
+    fn painted() -> Autotiler {
+        let mut tiler = Autotiler::new(6, 5);
+        tiler.fill_terrain([0, 0], [3, 2], 1);
+        tiler.fill_terrain([2, 1], [5, 4], 2);
+        tiler.set_terrain([0, 4], 3);
+        tiler.set_terrain([4, 0], 1);
+        tiler
+    }
+    #[test]
+    fn test_autotile_sprites_match_tilemap() {
+        let mut tiler = painted();
+        let mut map = tilemap(6, 5);
+        tiler.update(&mut map, two_terrains);
+        let mut sprites = AutotileSprites::new(painted(), map.grid, 2, two_terrains);
+        assert!(!sprites.uses_compute());
+        let mut trfs = vec![Transform::ZERO; sprites.sprite_count()];
+        let mut uvs = vec![SheetRegion::ZERO; sprites.sprite_count()];
+        let dirty = sprites.tiler.take_dirty().unwrap();
+        assert_eq!(dirty, [0, 0, 5, 4]);
+        sprites.write_tiles(dirty, &mut trfs, &mut uvs);
+        for (idx, (trf, uv)) in trfs.iter().zip(&uvs).enumerate() {
+            let pos = [(idx % 6) as i32, (idx / 6) as i32];
+            match map.get(pos) {
+                Some(tile) => {
+                    assert_eq!((uv.x, uv.y), (tile.x, tile.y), "{pos:?}");
+                    assert_eq!(trf.translation(), map.grid.tile_to_world(pos));
+                    assert_eq!((trf.w, trf.h), (16, 16));
+                }
+                // Empty tiles and terrains without regions are hidden
+                None => assert_eq!((trf.w, trf.h, uv.w, uv.h), (0, 0, 0, 0), "{pos:?}"),
+            }
+        }
+        assert_eq!(trfs.iter().filter(|t| t.w > 0).count(), map.sprite_count());
+    }
+    #[test]
+    fn test_autotile_sprites_dirty_area() {
+        let grid = tilemap(6, 5).grid;
+        let mut sprites = AutotileSprites::new(painted(), grid, 2, two_terrains);
+        let dirty = sprites.tiler.take_dirty().unwrap();
+        let mut trfs = vec![Transform::ZERO; 30];
+        let mut uvs = vec![SheetRegion::ZERO; 30];
+        sprites.write_tiles(dirty, &mut trfs, &mut uvs);
+        // Only the changed tile and its neighbors are rewritten
+        sprites.tiler_mut().set_terrain([5, 0], 2);
+        let dirty = sprites.tiler.take_dirty().unwrap();
+        assert_eq!(dirty, [4, 0, 5, 1]);
+        let marker = SheetRegion::rect(999, 999, 1, 1);
+        let mut new_uvs = vec![marker; 30];
+        sprites.write_tiles(dirty, &mut trfs, &mut new_uvs);
+        // Retiling everything changes nothing outside that area
+        let mut all_uvs = vec![SheetRegion::ZERO; 30];
+        sprites.write_tiles([0, 0, 5, 4], &mut trfs, &mut all_uvs);
+        for (idx, ((old, new), all)) in uvs.iter().zip(&new_uvs).zip(&all_uvs).enumerate() {
+            if idx % 6 >= 4 && idx / 6 <= 1 {
+                assert_eq!(new.x, all.x);
+            } else {
+                assert_eq!(new.x, 999);
+                assert_eq!(old.x, all.x);
+            }
+        }
+        assert_eq!(new_uvs[5].y, 2);
+        assert!(sprites.tiler.take_dirty().is_none());
+    }
+    #[test]
+    fn test_compute_layout() {
+        // The shader reads regions as four words with the width and
+        // height in the third, and its parameters fill 48 bytes
+        let region = SheetRegion::new(1, 2, 3, 4, -5, 6);
+        let words: [u32; 4] = bytemuck::cast(region);
+        assert_eq!(words[2], (-5i16 as u16 as u32) | (6 << 16));
+        assert_eq!(bytemuck::cast::<_, [u32; 4]>(SheetRegion::ZERO)[2], 0);
+        assert_eq!(std::mem::size_of::<AutotileParams>(), 48);
+        let trf = Transform {
+            w: 16,
+            h: 8,
+            x: 1.5,
+            y: -2.0,
+            rot: 0.0,
+        };
+        let words: [u32; 4] = bytemuck::cast(trf);
+        assert_eq!(
+            words,
+            [16 | (8 << 16), 1.5f32.to_bits(), (-2.0f32).to_bits(), 0]
+        );
+    }
+    #[test]
+    #[should_panic]
+    fn test_autotile_sprites_need_square_grid() {
+        let grid = TileGrid {
+            layout: TileLayout::Isometric,
+            ..tilemap(1, 1).grid
+        };
+        AutotileSprites::new(Autotiler::new(2, 2), grid, 1, region);
+    }
+    #[test]
+    #[should_panic]
+    fn test_size_mismatch() {
+        Autotiler::new(2, 2).update(&mut tilemap(3, 2), region);
+    }
+}
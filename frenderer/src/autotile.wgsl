@@ -0,0 +1,78 @@
+// Picks the blob variant of each tile in the dirty area of an
+// autotiled map and writes its sprite, one sprite per tile
+struct Params {
+    // The map's width and height in tiles
+    size: vec2<u32>,
+    // The first column and row of the dirty area, and its width and height
+    min: vec2<u32>,
+    extent: vec2<u32>,
+    // The world space center of tile [0, 0], and the tile size
+    origin: vec2<f32>,
+    tile_size: vec2<f32>,
+    // How many terrains have regions in the table, then padding
+    terrains: u32,
+    padding: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+// Each tile's terrain, row by row
+@group(0) @binding(1)
+var<storage, read> terrain: array<u32>;
+// The blob variant for each of the 256 neighbor masks, then each
+// terrain's 47 variants' sheet regions, four words apiece
+@group(0) @binding(2)
+var<storage, read> table: array<u32>;
+// The sprite group's transforms and sheet regions, as raw words
+@group(0) @binding(3)
+var<storage, read_write> world: array<vec4<u32>>;
+@group(0) @binding(4)
+var<storage, read_write> sheet: array<vec4<u32>>;
+
+// Each neighbor's offset, clockwise from north like the mask bits
+var<private> NEIGHBORS: array<vec2<i32>, 8> = array<vec2<i32>, 8>(
+    vec2<i32>(0, 1),
+    vec2<i32>(1, 1),
+    vec2<i32>(1, 0),
+    vec2<i32>(1, -1),
+    vec2<i32>(0, -1),
+    vec2<i32>(-1, -1),
+    vec2<i32>(-1, 0),
+    vec2<i32>(-1, 1)
+);
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.extent.x || id.y >= params.extent.y) {
+        return;
+    }
+    let tile = params.min + id.xy;
+    let idx = tile.y * params.size.x + tile.x;
+    let kind = terrain[idx];
+    var region = vec4<u32>(0u);
+    if (kind != 0u && kind <= params.terrains) {
+        let size = vec2<i32>(params.size);
+        var mask = 0u;
+        for (var i = 0u; i < 8u; i++) {
+            let pos = vec2<i32>(tile) + NEIGHBORS[i];
+            // Cells past the map's edges count as matching
+            if (any(pos < vec2<i32>(0)) || any(pos >= size)
+                || terrain[u32(pos.y) * params.size.x + u32(pos.x)] == kind) {
+                mask |= 1u << i;
+            }
+        }
+        let start = 256u + ((kind - 1u) * 47u + table[mask]) * 4u;
+        region = vec4<u32>(table[start], table[start + 1u], table[start + 2u], table[start + 3u]);
+    }
+    // Tiles without a region (zero width and height) are hidden
+    if (region.z == 0u) {
+        world[idx] = vec4<u32>(0u);
+        sheet[idx] = vec4<u32>(0u);
+        return;
+    }
+    let pos = params.origin + vec2<f32>(tile) * params.tile_size;
+    let w = u32(params.tile_size.x) & 0xffffu;
+    let h = u32(params.tile_size.y) & 0xffffu;
+    world[idx] = vec4<u32>(w | (h << 16u), bitcast<u32>(pos.x), bitcast<u32>(pos.y), 0u);
+    sheet[idx] = region;
+}
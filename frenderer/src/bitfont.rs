@@ -1,7 +1,155 @@
 use std::ops::RangeBounds;
 
+use crate::rng::TickRng;
 use crate::sprites::{SheetRegion, Transform};
 
+/// An animated effect applied to the characters of a [`RichSpan`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextEffect {
+    /// Characters sit still
+    #[default]
+    None,
+    /// Characters bob up and down in a wave running along the text
+    Wave,
+    /// Characters jitter randomly
+    Shake,
+}
+
+impl TextEffect {
+    // How far the `glyph`th sprite of some text is displaced at the given time
+    fn offset(self, glyph: usize, time: f32, char_height: f32) -> [f32; 2] {
+        match self {
+            TextEffect::None => [0.0, 0.0],
+            TextEffect::Wave => [
+                0.0,
+                (time * 8.0 - glyph as f32 * 0.6).sin() * char_height * 0.15,
+            ],
+            TextEffect::Shake => {
+                // A new random offset 30 times a second, different for each glyph
+                let mut rng = TickRng::new(glyph as u64);
+                rng.set_tick((time * 30.0) as u64);
+                let amount = char_height * 0.06;
+                [
+                    rng.range_f32(-amount..amount),
+                    rng.range_f32(-amount..amount),
+                ]
+            }
+        }
+    }
+}
+
+/// A piece of a line of rich text drawn with [`BitFont::draw_rich_text`].
+#[derive(Clone, Copy, Debug)]
+pub enum RichSpan<'a> {
+    /// Characters of the font
+    Text {
+        /// The characters to draw
+        text: &'a str,
+        /// The characters' colormod, or `None` for the font's own
+        colormod: Option<[u8; 4]>,
+        /// How the characters move
+        effect: TextEffect,
+    },
+    /// A sprite drawn inline, as tall as a character and as wide as
+    /// its aspect ratio requires
+    Icon {
+        /// The sprite's region; its depth is replaced by the text's
+        region: SheetRegion,
+        /// How the sprite moves
+        effect: TextEffect,
+    },
+}
+
+impl<'a> RichSpan<'a> {
+    /// Plain text in the font's own color.
+    pub fn text(text: &'a str) -> Self {
+        Self::Text {
+            text,
+            colormod: None,
+            effect: TextEffect::None,
+        }
+    }
+}
+
+/// Parses a line of text with inline markup into [`RichSpan`]s.
+/// The supported tags are:
+///
+/// * `[color=rrggbb]` or `[color=rrggbbaa]` ... `[/color]` (with an optional `#`) for colored text
+/// * `[wave]` ... `[/wave]` and `[shake]` ... `[/shake]` for animated text
+/// * `[icon=name]` for a sprite looked up by name with `icon`
+///
+/// Tags may be nested, and `[[` stands for a literal `[`.
+pub fn parse_markup<'a>(
+    markup: &'a str,
+    icon: impl Fn(&str) -> Option<SheetRegion>,
+) -> Result<Vec<RichSpan<'a>>, Box<dyn std::error::Error>> {
+    let mut spans = vec![];
+    let mut colors: Vec<[u8; 4]> = vec![];
+    let mut effects: Vec<TextEffect> = vec![];
+    let text = |text: &'a str, colors: &[[u8; 4]], effects: &[TextEffect]| RichSpan::Text {
+        text,
+        colormod: colors.last().copied(),
+        effect: effects.last().copied().unwrap_or_default(),
+    };
+    let mut rest = markup;
+    while let Some(open) = rest.find('[') {
+        if open > 0 {
+            spans.push(text(&rest[..open], &colors, &effects));
+        }
+        rest = &rest[open..];
+        if let Some(after) = rest.strip_prefix("[[") {
+            spans.push(text(&rest[..1], &colors, &effects));
+            rest = after;
+            continue;
+        }
+        let close = rest
+            .find(']')
+            .ok_or_else(|| format!("Unclosed tag in {markup:?}"))?;
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+        match tag.split_once('=') {
+            Some(("color", hex)) => colors.push(parse_color(hex)?),
+            Some(("icon", name)) => spans.push(RichSpan::Icon {
+                region: icon(name).ok_or_else(|| format!("Unknown icon {name:?}"))?,
+                effect: effects.last().copied().unwrap_or_default(),
+            }),
+            Some(_) => return Err(format!("Unknown tag [{tag}]").into()),
+            None => match tag {
+                "wave" => effects.push(TextEffect::Wave),
+                "shake" => effects.push(TextEffect::Shake),
+                "/color" => {
+                    colors.pop().ok_or("[/color] without [color]")?;
+                }
+                "/wave" | "/shake" => {
+                    let expected = if tag == "/wave" {
+                        TextEffect::Wave
+                    } else {
+                        TextEffect::Shake
+                    };
+                    if effects.pop() != Some(expected) {
+                        return Err(format!("[{tag}] doesn't match the open effect tag").into());
+                    }
+                }
+                _ => return Err(format!("Unknown tag [{tag}]").into()),
+            },
+        }
+    }
+    if !rest.is_empty() {
+        spans.push(text(rest, &colors, &effects));
+    }
+    Ok(spans)
+}
+
+fn parse_color(hex: &str) -> Result<[u8; 4], Box<dyn std::error::Error>> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    let value = u32::from_str_radix(hex, 16).map_err(|_| format!("Bad color {hex:?}"))?;
+    match hex.len() {
+        6 => Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255]),
+        8 => Ok(value.to_be_bytes()),
+        _ => Err(format!("Bad color {hex:?}").into()),
+    }
+}
+
 /// A bitmapped font helper described as a rectangular area of a spritesheet.
 #[derive(Clone, Copy, Debug)]
 pub struct BitFont {
@@ -79,6 +227,10 @@ impl BitFont {
             end_char,
         }
     }
+    /// The width and height of each glyph in spritesheet pixels.
+    pub fn char_size(&self) -> (u16, u16) {
+        (self.char_w, self.char_h)
+    }
     /// Returns a `BitFont` which is the same in every way except that a different colormod is used.
     pub fn colormod(&self, cmod: [u8; 4]) -> Self {
         let mut copy = *self;
@@ -98,10 +250,8 @@ impl BitFont {
         depth: u16,
         char_height: f32,
     ) -> ([f32; 2], usize) {
-        let start_char: u32 = self.start_char;
         trfs[0..text.len()].fill(Transform::ZERO);
         uvs[0..text.len()].fill(SheetRegion::ZERO);
-        let chars_per_row = self.region.w as u16 / (self.char_w + self.padding_x);
         let aspect = self.char_w as f32 / self.char_h as f32;
         let char_width = aspect * char_height;
         screen_pos[0] += char_width / 2.0;
@@ -112,9 +262,6 @@ impl BitFont {
             if chara.is_whitespace() {
                 screen_pos[0] += char_width;
             }
-            if !(self.start_char..self.end_char).contains(&u32::from(chara)) {
-                panic!("Drawing outside of font character range");
-            }
             *trf = Transform {
                 w: char_width as u16,
                 h: char_height as u16,
@@ -122,18 +269,7 @@ impl BitFont {
                 y: screen_pos[1],
                 rot: 0.0,
             };
-            let chara = u32::from(chara) - start_char;
-            let which_row = chara / chars_per_row as u32;
-            let which_col = chara % chars_per_row as u32;
-            *uv = SheetRegion::new(
-                self.region.sheet,
-                self.region.x + (which_col as u16) * (self.char_w + self.padding_x),
-                self.region.y + (which_row as u16) * (self.char_h + self.padding_y),
-                depth,
-                self.char_w as i16,
-                self.char_h as i16,
-            )
-            .with_colormod(self.region.colormod);
+            *uv = self.glyph(chara, depth, self.region.colormod);
             used += 1;
             screen_pos[0] += char_width;
         }
@@ -145,4 +281,468 @@ impl BitFont {
             used,
         )
     }
+    /// How many sprites [`BitFont::draw_rich_text`] uses for the
+    /// given spans: one per icon and non-whitespace character.
+    pub fn rich_sprite_count(spans: &[RichSpan]) -> usize {
+        spans
+            .iter()
+            .map(|span| match span {
+                RichSpan::Text { text, .. } => text.chars().filter(|c| !c.is_whitespace()).count(),
+                RichSpan::Icon { .. } => 1,
+            })
+            .sum()
+    }
+    /// Like [`BitFont::draw_text`], but draws a line of [`RichSpan`]s
+    /// (e.g. from [`parse_markup`]) with their own colors, effects,
+    /// and inline icons.  `time` in seconds drives the animated
+    /// effects.  Whitespace takes up space but uses no sprites.
+    /// Returns the bottom right corner of the rendered line and how
+    /// many sprites were used.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_rich_text(
+        &self,
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+        spans: &[RichSpan],
+        screen_pos: [f32; 2],
+        depth: u16,
+        char_height: f32,
+        time: f32,
+    ) -> ([f32; 2], usize) {
+        let count = Self::rich_sprite_count(spans);
+        trfs[0..count].fill(Transform::ZERO);
+        uvs[0..count].fill(SheetRegion::ZERO);
+        let char_width = self.char_w as f32 / self.char_h as f32 * char_height;
+        let [mut x, top] = screen_pos;
+        let mut used = 0;
+        for span in spans {
+            match *span {
+                RichSpan::Text {
+                    text,
+                    colormod,
+                    effect,
+                } => {
+                    for chara in text.chars() {
+                        if !chara.is_whitespace() {
+                            let [dx, dy] = effect.offset(used, time, char_height);
+                            trfs[used] = Transform {
+                                w: char_width as u16,
+                                h: char_height as u16,
+                                x: x + char_width / 2.0 + dx,
+                                y: top - char_height / 2.0 + dy,
+                                rot: 0.0,
+                            };
+                            uvs[used] =
+                                self.glyph(chara, depth, colormod.unwrap_or(self.region.colormod));
+                            used += 1;
+                        }
+                        x += char_width;
+                    }
+                }
+                RichSpan::Icon { region, effect } => {
+                    let icon_width = char_height * region.w.unsigned_abs() as f32
+                        / region.h.unsigned_abs().max(1) as f32;
+                    let [dx, dy] = effect.offset(used, time, char_height);
+                    trfs[used] = Transform {
+                        w: icon_width as u16,
+                        h: char_height as u16,
+                        x: x + icon_width / 2.0 + dx,
+                        y: top - char_height / 2.0 + dy,
+                        rot: 0.0,
+                    };
+                    uvs[used] = SheetRegion { depth, ..region };
+                    used += 1;
+                    x += icon_width;
+                }
+            }
+        }
+        ([x, top - char_height], used)
+    }
+    /// Whether the font has a glyph for the given character.
+    pub fn has_char(&self, chara: char) -> bool {
+        (self.start_char..self.end_char).contains(&u32::from(chara))
+    }
+    // The region of the given character in the font's spritesheet
+    fn glyph(&self, chara: char, depth: u16, colormod: [u8; 4]) -> SheetRegion {
+        if !(self.start_char..self.end_char).contains(&u32::from(chara)) {
+            panic!("Drawing outside of font character range");
+        }
+        let chars_per_row = (self.region.w as u16 / (self.char_w + self.padding_x)) as u32;
+        let chara = u32::from(chara) - self.start_char;
+        let which_row = chara / chars_per_row;
+        let which_col = chara % chars_per_row;
+        SheetRegion::new(
+            self.region.sheet,
+            self.region.x + (which_col as u16) * (self.char_w + self.padding_x),
+            self.region.y + (which_row as u16) * (self.char_h + self.padding_y),
+            depth,
+            self.char_w as i16,
+            self.char_h as i16,
+        )
+        .with_colormod(colormod)
+    }
+}
+
+/// A list of [`BitFont`]s tried in order for each character, so
+/// text mixing scripts (e.g. Latin and CJK) can be drawn from a few
+/// small sheets rather than one enormous one.  Ranges of characters
+/// can be sent to a particular font first with
+/// [`FontChain::with_override`], e.g. so that Han characters in
+/// Japanese text use the Japanese font's forms.
+///
+/// To draw a chain's text in one sprite group, put its fonts on
+/// different layers (see [`SheetRegion::sheet`]) of the group's
+/// spritesheet.
+#[derive(Clone, Debug)]
+pub struct FontChain {
+    fonts: Vec<BitFont>,
+    overrides: Vec<(std::ops::RangeInclusive<char>, usize)>,
+}
+
+impl FontChain {
+    /// The character drawn in place of ones no font has, if some
+    /// font has it; otherwise `'?'` is tried before leaving a blank.
+    pub const REPLACEMENT: char = '\u{FFFD}';
+    /// Creates a chain which tries `fonts` in order.  The first font
+    /// sets the width of whitespace.  Panics if `fonts` is empty.
+    pub fn new(fonts: impl IntoIterator<Item = BitFont>) -> Self {
+        let fonts: Vec<BitFont> = fonts.into_iter().collect();
+        assert!(!fonts.is_empty(), "A font chain needs at least one font");
+        Self {
+            fonts,
+            overrides: vec![],
+        }
+    }
+    /// Tries the `font`th font first for characters in `chars`,
+    /// falling back to the usual order for ones it lacks.  Later
+    /// overrides take precedence over earlier ones.  Panics if
+    /// `font` is out of range.
+    pub fn with_override(mut self, chars: std::ops::RangeInclusive<char>, font: usize) -> Self {
+        assert!(font < self.fonts.len(), "No font {font} in chain");
+        self.overrides.push((chars, font));
+        self
+    }
+    /// The fonts of the chain in fallback order.
+    pub fn fonts(&self) -> &[BitFont] {
+        &self.fonts
+    }
+    /// The font used to draw the given character, or `None` if no
+    /// font has it.
+    pub fn font_for(&self, chara: char) -> Option<&BitFont> {
+        self.overrides
+            .iter()
+            .rev()
+            .filter(|(chars, _)| chars.contains(&chara))
+            .map(|&(_, font)| &self.fonts[font])
+            .chain(&self.fonts)
+            .find(|font| font.has_char(chara))
+    }
+    /// How many sprites [`FontChain::draw_text`] uses for `text`: one
+    /// per non-whitespace character.
+    pub fn sprite_count(text: &str) -> usize {
+        text.chars().filter(|c| !c.is_whitespace()).count()
+    }
+    /// Like [`BitFont::draw_text`], but drawing each character with
+    /// the first font of the chain which has it, at that font's
+    /// aspect ratio.  Characters no font has are drawn as
+    /// [`FontChain::REPLACEMENT`] or `'?'` if possible and left blank
+    /// otherwise.  Whitespace takes up space but uses no sprites.
+    /// Returns the bottom right corner of the rendered line and how
+    /// many sprites were used.
+    pub fn draw_text(
+        &self,
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+        text: &str,
+        screen_pos: [f32; 2],
+        depth: u16,
+        char_height: f32,
+    ) -> ([f32; 2], usize) {
+        let count = Self::sprite_count(text);
+        trfs[0..count].fill(Transform::ZERO);
+        uvs[0..count].fill(SheetRegion::ZERO);
+        let width = |font: &BitFont| font.char_w as f32 / font.char_h as f32 * char_height;
+        let space_width = width(&self.fonts[0]);
+        let [mut x, top] = screen_pos;
+        let mut used = 0;
+        for chara in text.chars() {
+            if chara.is_whitespace() {
+                x += space_width;
+                continue;
+            }
+            let found = [chara, Self::REPLACEMENT, '?']
+                .into_iter()
+                .find_map(|chara| Some((chara, self.font_for(chara)?)));
+            let Some((chara, font)) = found else {
+                // Leave a blank the size of the first font's characters
+                x += space_width;
+                used += 1;
+                continue;
+            };
+            let char_width = width(font);
+            trfs[used] = Transform {
+                w: char_width as u16,
+                h: char_height as u16,
+                x: x + char_width / 2.0,
+                y: top - char_height / 2.0,
+                rot: 0.0,
+            };
+            uvs[used] = font.glyph(chara, depth, font.region.colormod);
+            used += 1;
+            x += char_width;
+        }
+        ([x, top - char_height], used)
+    }
+}
+
+// Rows of each glyph of the built-in font from top to bottom, with
+// the high bit as the leftmost pixel, for ' ' through '~'
+#[cfg(feature = "bundled-assets")]
+const DEFAULT_GLYPHS: [[u8; 5]; 95] = [
+    [0b000, 0b000, 0b000, 0b000, 0b000], //
+    [0b010, 0b010, 0b010, 0b000, 0b010], // !
+    [0b101, 0b101, 0b000, 0b000, 0b000], // "
+    [0b101, 0b111, 0b101, 0b111, 0b101], // #
+    [0b011, 0b110, 0b010, 0b011, 0b110], // $
+    [0b101, 0b001, 0b010, 0b100, 0b101], // %
+    [0b010, 0b101, 0b010, 0b101, 0b011], // &
+    [0b010, 0b010, 0b000, 0b000, 0b000], // "'"
+    [0b001, 0b010, 0b010, 0b010, 0b001], // (
+    [0b100, 0b010, 0b010, 0b010, 0b100], // )
+    [0b000, 0b101, 0b010, 0b101, 0b000], // *
+    [0b000, 0b010, 0b111, 0b010, 0b000], // +
+    [0b000, 0b000, 0b000, 0b010, 0b100], // ,
+    [0b000, 0b000, 0b111, 0b000, 0b000], // -
+    [0b000, 0b000, 0b000, 0b000, 0b010], // .
+    [0b001, 0b001, 0b010, 0b100, 0b100], // /
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b110, 0b001, 0b010, 0b100, 0b111], // 2
+    [0b110, 0b001, 0b010, 0b001, 0b110], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b110, 0b001, 0b110], // 5
+    [0b011, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b010, 0b010, 0b010], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b110], // 9
+    [0b000, 0b010, 0b000, 0b010, 0b000], // :
+    [0b000, 0b010, 0b000, 0b010, 0b100], // ;
+    [0b001, 0b010, 0b100, 0b010, 0b001], // <
+    [0b000, 0b111, 0b000, 0b111, 0b000], // =
+    [0b100, 0b010, 0b001, 0b010, 0b100], // >
+    [0b110, 0b001, 0b010, 0b000, 0b010], // ?
+    [0b010, 0b101, 0b111, 0b100, 0b011], // @
+    [0b010, 0b101, 0b111, 0b101, 0b101], // A
+    [0b110, 0b101, 0b110, 0b101, 0b110], // B
+    [0b011, 0b100, 0b100, 0b100, 0b011], // C
+    [0b110, 0b101, 0b101, 0b101, 0b110], // D
+    [0b111, 0b100, 0b110, 0b100, 0b111], // E
+    [0b111, 0b100, 0b110, 0b100, 0b100], // F
+    [0b011, 0b100, 0b101, 0b101, 0b011], // G
+    [0b101, 0b101, 0b111, 0b101, 0b101], // H
+    [0b111, 0b010, 0b010, 0b010, 0b111], // I
+    [0b001, 0b001, 0b001, 0b101, 0b010], // J
+    [0b101, 0b101, 0b110, 0b101, 0b101], // K
+    [0b100, 0b100, 0b100, 0b100, 0b111], // L
+    [0b101, 0b111, 0b111, 0b101, 0b101], // M
+    [0b110, 0b101, 0b101, 0b101, 0b101], // N
+    [0b010, 0b101, 0b101, 0b101, 0b010], // O
+    [0b110, 0b101, 0b110, 0b100, 0b100], // P
+    [0b010, 0b101, 0b101, 0b110, 0b011], // Q
+    [0b110, 0b101, 0b110, 0b101, 0b101], // R
+    [0b011, 0b100, 0b010, 0b001, 0b110], // S
+    [0b111, 0b010, 0b010, 0b010, 0b010], // T
+    [0b101, 0b101, 0b101, 0b101, 0b011], // U
+    [0b101, 0b101, 0b101, 0b010, 0b010], // V
+    [0b101, 0b101, 0b111, 0b111, 0b101], // W
+    [0b101, 0b101, 0b010, 0b101, 0b101], // X
+    [0b101, 0b101, 0b010, 0b010, 0b010], // Y
+    [0b111, 0b001, 0b010, 0b100, 0b111], // Z
+    [0b011, 0b010, 0b010, 0b010, 0b011], // [
+    [0b100, 0b100, 0b010, 0b001, 0b001], // \
+    [0b110, 0b010, 0b010, 0b010, 0b110], // ]
+    [0b010, 0b101, 0b000, 0b000, 0b000], // ^
+    [0b000, 0b000, 0b000, 0b000, 0b111], // _
+    [0b100, 0b010, 0b000, 0b000, 0b000], // `
+    [0b000, 0b011, 0b101, 0b101, 0b011], // a
+    [0b100, 0b110, 0b101, 0b101, 0b110], // b
+    [0b000, 0b011, 0b100, 0b100, 0b011], // c
+    [0b001, 0b011, 0b101, 0b101, 0b011], // d
+    [0b000, 0b010, 0b111, 0b100, 0b011], // e
+    [0b001, 0b010, 0b111, 0b010, 0b010], // f
+    [0b011, 0b101, 0b011, 0b001, 0b110], // g
+    [0b100, 0b110, 0b101, 0b101, 0b101], // h
+    [0b010, 0b000, 0b110, 0b010, 0b111], // i
+    [0b001, 0b000, 0b001, 0b101, 0b010], // j
+    [0b100, 0b101, 0b110, 0b110, 0b101], // k
+    [0b110, 0b010, 0b010, 0b010, 0b111], // l
+    [0b000, 0b111, 0b111, 0b101, 0b101], // m
+    [0b000, 0b110, 0b101, 0b101, 0b101], // n
+    [0b000, 0b010, 0b101, 0b101, 0b010], // o
+    [0b000, 0b110, 0b101, 0b110, 0b100], // p
+    [0b000, 0b011, 0b101, 0b011, 0b001], // q
+    [0b000, 0b011, 0b100, 0b100, 0b100], // r
+    [0b000, 0b011, 0b110, 0b011, 0b110], // s
+    [0b010, 0b111, 0b010, 0b010, 0b001], // t
+    [0b000, 0b101, 0b101, 0b101, 0b011], // u
+    [0b000, 0b101, 0b101, 0b010, 0b010], // v
+    [0b000, 0b101, 0b111, 0b111, 0b101], // w
+    [0b000, 0b101, 0b010, 0b010, 0b101], // x
+    [0b000, 0b101, 0b011, 0b001, 0b110], // y
+    [0b000, 0b111, 0b011, 0b100, 0b111], // z
+    [0b011, 0b010, 0b100, 0b010, 0b011], // {
+    [0b010, 0b010, 0b010, 0b010, 0b010], // |
+    [0b110, 0b010, 0b001, 0b010, 0b110], // }
+    [0b000, 0b001, 0b111, 0b100, 0b000], // ~
+];
+
+#[cfg(feature = "bundled-assets")]
+impl BitFont {
+    /// Makes a texture holding a tiny built-in 3x5 pixel font for
+    /// printable ASCII (`' '` through `'~'`) and returns it with a
+    /// [`BitFont`] reading its first layer, so prototypes can draw
+    /// text before they have a font sheet.  Glyphs are white on a
+    /// transparent background, for tinting with
+    /// [`BitFont::colormod`].  The font was drawn for frenderer and
+    /// is in the public domain.
+    pub fn default_font(renderer: &crate::Renderer) -> (wgpu::Texture, Self) {
+        const PER_ROW: usize = 16;
+        let (cell_w, cell_h) = (4, 6);
+        let rows = DEFAULT_GLYPHS.len().div_ceil(PER_ROW);
+        let (width, height) = (PER_ROW * cell_w, rows * cell_h);
+        let mut image = vec![0_u8; width * height * 4];
+        for (idx, glyph) in DEFAULT_GLYPHS.iter().enumerate() {
+            let (x0, y0) = ((idx % PER_ROW) * cell_w, (idx / PER_ROW) * cell_h);
+            for (y, bits) in glyph.iter().enumerate() {
+                for x in 0..3 {
+                    if bits & (0b100 >> x) != 0 {
+                        let px = ((y0 + y) * width + x0 + x) * 4;
+                        image[px..px + 4].fill(255);
+                    }
+                }
+            }
+        }
+        let texture = renderer.create_texture(
+            &image,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            (width as u32, height as u32),
+            Some("bitfont:default"),
+        );
+        let font = Self::with_sheet_region(
+            ' '..='~',
+            SheetRegion::new(0, 0, 0, 0, width as i16, height as i16),
+            3,
+            5,
+            1,
+            1,
+        );
+        (texture, font)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Option<[u8; 4]> = Some([255, 0, 0, 255]);
+
+    // Each span's text (or icon x position), color, and effect
+    type Span = (String, Option<[u8; 4]>, TextEffect);
+    fn parse(markup: &str) -> Result<Vec<Span>, String> {
+        let icon = |name: &str| (name == "coin").then(|| SheetRegion::rect(48, 0, 16, 8));
+        let spans = parse_markup(markup, icon).map_err(|err| err.to_string())?;
+        Ok(spans
+            .iter()
+            .map(|span| match *span {
+                RichSpan::Text {
+                    text,
+                    colormod,
+                    effect,
+                } => (text.to_string(), colormod, effect),
+                RichSpan::Icon { region, effect } => (format!("icon {}", region.x), None, effect),
+            })
+            .collect())
+    }
+    fn plain(text: &str) -> Span {
+        (text.to_string(), None, TextEffect::None)
+    }
+
+    #[test]
+    fn test_plain_markup() {
+        assert_eq!(parse("hello world").unwrap(), [plain("hello world")]);
+        assert!(parse("").unwrap().is_empty());
+        // Closing brackets on their own are just text
+        assert_eq!(parse("a]b").unwrap(), [plain("a]b")]);
+    }
+    #[test]
+    fn test_nested_tags() {
+        let spans = parse("a[color=#ff0000]b[wave]c[icon=coin][/wave]d[/color]e").unwrap();
+        assert_eq!(
+            spans,
+            [
+                plain("a"),
+                ("b".into(), RED, TextEffect::None),
+                ("c".into(), RED, TextEffect::Wave),
+                ("icon 48".into(), None, TextEffect::Wave),
+                ("d".into(), RED, TextEffect::None),
+                plain("e"),
+            ]
+        );
+        let spans = parse("[color=00ff0080][shake]x").unwrap();
+        assert_eq!(
+            spans,
+            [("x".into(), Some([0, 255, 0, 128]), TextEffect::Shake)]
+        );
+    }
+    #[test]
+    fn test_escaped_brackets() {
+        assert_eq!(parse("[[wave]").unwrap(), [plain("["), plain("wave]")]);
+        assert_eq!(
+            parse("a[[[color=ff0000]b").unwrap(),
+            [plain("a"), plain("["), ("b".into(), RED, TextEffect::None)]
+        );
+    }
+    #[test]
+    fn test_bad_markup() {
+        // Unclosed brackets and mismatched or unknown tags are errors
+        assert!(parse("a[color=ff0000").is_err());
+        assert!(parse("a[").is_err());
+        assert!(parse("[wave]a[/shake]").is_err());
+        assert!(parse("a[/color]").is_err());
+        assert!(parse("[/wave]").is_err());
+        assert!(parse("[bold]a").is_err());
+        assert!(parse("[size=3]a").is_err());
+        assert!(parse("[color=red]a").is_err());
+        assert!(parse("[color=fff]a").is_err());
+        assert!(parse("[icon=gem]").is_err());
+        // Tags left open run to the end of the line
+        assert_eq!(
+            parse("[wave]a").unwrap(),
+            [("a".into(), None, TextEffect::Wave)]
+        );
+    }
+    #[test]
+    fn test_draw_rich_text() {
+        let font =
+            BitFont::with_sheet_region('A'..='Z', SheetRegion::rect(0, 0, 26 * 8, 8), 8, 8, 0, 0);
+        let spans = [
+            RichSpan::text("AB C"),
+            RichSpan::Icon {
+                region: SheetRegion::rect(0, 16, 16, 8),
+                effect: TextEffect::None,
+            },
+        ];
+        assert_eq!(BitFont::rich_sprite_count(&spans), 4);
+        let mut trfs = [Transform::ZERO; 4];
+        let mut uvs = [SheetRegion::ZERO; 4];
+        let (corner, used) =
+            font.draw_rich_text(&mut trfs, &mut uvs, &spans, [0.0, 16.0], 3, 16.0, 0.0);
+        assert_eq!(used, 4);
+        // Whitespace advances without a sprite; icons keep their aspect ratio
+        assert_eq!(corner, [96.0, 0.0]);
+        assert_eq!(trfs[2].translation(), [56.0, 8.0]);
+        assert_eq!((trfs[3].w, trfs[3].h), (32, 16));
+        assert_eq!((uvs[1].x, uvs[1].depth), (8, 3));
+        assert_eq!((uvs[3].y, uvs[3].depth), (16, 3));
+    }
 }
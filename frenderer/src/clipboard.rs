@@ -0,0 +1,122 @@
+//! Reading and writing the system clipboard as text, and copying
+//! images to it with the `screenshot` feature.
+//!
+//! On native platforms this needs the `clipboard` feature, which uses
+//! [arboard](https://docs.rs/arboard); without it the clipboard
+//! always reads as empty and writes are ignored.
+//!
+//! On the web, browsers only hand over clipboard contents inside
+//! paste events, so [`Clipboard::text`] is always `None` there.
+//! Call [`crate::input::Input::listen_for_paste`] to receive pasted
+//! text through [`crate::input::Input::typed_text`] instead.  Writes
+//! use `navigator.clipboard.writeText`, which browsers only allow
+//! while handling user input like a key press.
+//!
+//! Most games use the clipboard through [`crate::input::Input`]'s
+//! text entry mode rather than directly.
+
+/// A connection to the system clipboard.
+pub struct Clipboard {
+    #[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clipboard {
+    /// Connects to the system clipboard.  If that fails (e.g. with no
+    /// display server), the clipboard acts as if it were empty.
+    pub fn new() -> Self {
+        Self {
+            #[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+            inner: arboard::Clipboard::new()
+                .map_err(|err| log::warn!("clipboard: couldn't connect: {err}"))
+                .ok(),
+        }
+    }
+    /// The text on the clipboard, if it holds any.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+    pub fn text(&mut self) -> Option<String> {
+        self.inner.as_mut()?.get_text().ok()
+    }
+    /// The text on the clipboard, if it holds any.
+    #[cfg(any(target_arch = "wasm32", not(feature = "clipboard")))]
+    pub fn text(&mut self) -> Option<String> {
+        None
+    }
+    /// Puts `text` on the clipboard, returning whether that worked.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+    pub fn set_text(&mut self, text: &str) -> bool {
+        let Some(inner) = self.inner.as_mut() else {
+            return false;
+        };
+        inner
+            .set_text(text)
+            .map_err(|err| log::warn!("clipboard: couldn't copy: {err}"))
+            .is_ok()
+    }
+    /// Puts `text` on the clipboard, returning whether that worked.
+    /// On the web the write finishes asynchronously, so this only
+    /// says whether it was started.
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_text(&mut self, text: &str) -> bool {
+        use wasm_bindgen::{JsCast, JsValue};
+        // navigator.clipboard is missing outside of secure contexts,
+        // so look it up dynamically rather than through web-sys
+        let Some(window) = web_sys::window() else {
+            return false;
+        };
+        let navigator = window.navigator();
+        let Ok(clipboard) = js_sys::Reflect::get(&navigator, &JsValue::from_str("clipboard"))
+        else {
+            return false;
+        };
+        let Some(write) = js_sys::Reflect::get(&clipboard, &JsValue::from_str("writeText"))
+            .ok()
+            .and_then(|write| write.dyn_into::<js_sys::Function>().ok())
+        else {
+            log::warn!("clipboard: navigator.clipboard.writeText is unavailable");
+            return false;
+        };
+        write.call1(&clipboard, &JsValue::from_str(text)).is_ok()
+    }
+    /// Puts `text` on the clipboard, returning whether that worked.
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "clipboard")))]
+    pub fn set_text(&mut self, _text: &str) -> bool {
+        false
+    }
+    /// Puts an image of tightly packed RGBA8 pixels on the clipboard,
+    /// e.g. a [`crate::CapturedFrame`], returning whether that worked.
+    #[cfg(all(
+        not(target_arch = "wasm32"),
+        feature = "clipboard",
+        feature = "screenshot"
+    ))]
+    pub fn set_image(&mut self, width: u32, height: u32, rgba: &[u8]) -> bool {
+        let Some(inner) = self.inner.as_mut() else {
+            return false;
+        };
+        inner
+            .set_image(arboard::ImageData {
+                width: width as usize,
+                height: height as usize,
+                bytes: rgba.into(),
+            })
+            .map_err(|err| log::warn!("clipboard: couldn't copy image: {err}"))
+            .is_ok()
+    }
+    /// Puts an image of tightly packed RGBA8 pixels on the clipboard,
+    /// returning whether that worked.  Copying images isn't supported
+    /// on the web.
+    #[cfg(all(
+        feature = "screenshot",
+        any(target_arch = "wasm32", not(feature = "clipboard"))
+    ))]
+    pub fn set_image(&mut self, _width: u32, _height: u32, _rgba: &[u8]) -> bool {
+        false
+    }
+}
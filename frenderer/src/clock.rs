@@ -22,6 +22,7 @@ pub struct Clock {
     fudge_amount: f32,
     max_frames_per_tick: usize,
     last_t: Instant,
+    steps: u64,
 }
 
 impl Clock {
@@ -37,6 +38,7 @@ impl Clock {
             fudge_amount,
             max_frames_per_tick,
             last_t: Instant::now(),
+            steps: 0,
         }
     }
     /// Re-initialize the last-ticked time to the given instant and
@@ -47,6 +49,25 @@ impl Clock {
         self.last_t = instant;
         self.acc = 0.0;
     }
+    /// Returns how far the clock is between the most recent
+    /// simulation step and the next one, from 0.0 to 1.0.  This is
+    /// useful for interpolating between the previous and current
+    /// simulation states when rendering, e.g. with
+    /// [`crate::sprites::TransformHistory`] or
+    /// [`crate::Renderer::mesh_interpolate`].
+    pub fn alpha(&self) -> f32 {
+        (self.acc / self.dt).clamp(0.0, 1.0)
+    }
+    /// Returns the simulation timestep in seconds
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+    /// Returns how many simulation steps [`Clock::tick`] has asked
+    /// for in total.  This makes a good tick number for a
+    /// [`crate::rng::TickRng`], as long as every step is simulated.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
     /// Returns the current time on the clock
     pub fn now(&self) -> Instant {
         self.last_t
@@ -75,6 +96,7 @@ impl Clock {
 
         let steps = (self.acc / self.dt) as usize;
         self.acc -= steps as f32 * self.dt;
+        self.steps += steps as u64;
         steps
     }
 }
@@ -5,14 +5,31 @@ use std::borrow::Cow;
 use crate::gpu::WGPU;
 use wgpu::util::DeviceExt;
 
+/// The bind group layouts of a [`ColorGeo`]'s pipeline, from
+/// [`ColorGeo::layouts`], for custom passes which bind its bind
+/// groups (see [`ColorGeo::bind_groups`]) or make compatible ones.
+#[derive(Clone, Copy, Debug)]
+pub struct ColorGeoLayouts<'a> {
+    /// Group 0: the geometry transform uniform at binding 0
+    pub transform: &'a wgpu::BindGroupLayout,
+    /// Group 1: the color transform uniform at binding 0, the color
+    /// texture and its sampler at bindings 1 and 2, the 3D LUT
+    /// texture and its sampler at bindings 3 and 4, and the
+    /// reference frame, transition frame, selection mask, and
+    /// previous frame textures at bindings 5, 6, 7, and 8, and the
+    /// 3D LUT being blended towards at binding 9
+    pub inputs: &'a wgpu::BindGroupLayout,
+}
+
 /// Includes a 4x4 homogeneous geometry transformation, a 4x4
-/// homogenous color transformation, a saturation modifier, and a
-/// color lookup table (LUT).
+/// homogenous color transformation, a saturation modifier,
+/// per-channel color curves, and a color lookup table (LUT).
 pub struct ColorGeo {
     shader: wgpu::ShaderModule,
     pipeline: wgpu::RenderPipeline,
     pipeline_layout: wgpu::PipelineLayout,
     transform_bind_group: wgpu::BindGroup,
+    transform_bind_group_layout: wgpu::BindGroupLayout,
     texture_bind_group: wgpu::BindGroup,
     texture_bind_group_layout: wgpu::BindGroupLayout,
     transform: Transform,
@@ -21,18 +38,561 @@ pub struct ColorGeo {
     colormod_buf: wgpu::Buffer,
     color_texture_view: wgpu::TextureView,
     lut_texture_view: wgpu::TextureView,
+    // The LUT blended towards by set_lut_blend, if any
+    next_lut_texture_view: Option<wgpu::TextureView>,
+    filter: UpscaleFilter,
+    // The reference frame for CompareMode, and a view of it
+    reference: Option<(wgpu::Texture, wgpu::TextureView)>,
+    compare: CompareMode,
+    // The frame being transitioned away from, and a view of it
+    transition_frame: Option<(wgpu::Texture, wgpu::TextureView)>,
+    transition: Option<Transition>,
+    // The selection mask outlined by Outline, and a view of it
+    mask_texture_view: Option<wgpu::TextureView>,
+    outline: Option<Outline>,
+    // The previous frame blended in by Feedback, and a view of it
+    history_texture_view: Option<wgpu::TextureView>,
+    feedback: Option<Feedback>,
+    curves: ColorCurves,
+    rotation: SurfaceRotation,
+    color_space: ColorSpace,
+    target_format: wgpu::TextureFormat,
+    output_alpha: OutputAlpha,
+    anaglyph: bool,
 }
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
 struct Transform {
     mat: [f32; 16],
+    // The SurfaceRotation applied after mat, as a 2x2 matrix
+    rotation: [f32; 4],
 }
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
 struct ColorTransform {
     mat: [f32; 16],
+    // The saturation, the upscale filter's index, the compare mode's index, and the split position
     saturation_padding: [f32; 4],
+    // How to convert the output for the color target (see ColorSpace::conversion),
+    // the OutputAlpha's index, 1 to combine side-by-side halves into an anaglyph, then padding
+    conversion: [f32; 4],
+    // The transition's kind (0 for none), its progress, and the wipe direction
+    transition: [f32; 4],
+    // The color faded through
+    transition_color: [f32; 4],
+    // The outline's width in texels (0 for none), then padding
+    outline: [f32; 4],
+    outline_color: [f32; 4],
+    // Each curve parameter for red, green, and blue, then 1 in
+    // curve_in_black's last entry if the curves aren't the identity
+    curve_in_black: [f32; 4],
+    curve_in_white: [f32; 4],
+    curve_gamma: [f32; 4],
+    curve_contrast: [f32; 4],
+    curve_out_black: [f32; 4],
+    curve_out_white: [f32; 4],
+    // How much of the previous frame to blend in (0 for none), its
+    // scale, and its offset
+    feedback: [f32; 4],
+    // How much of the next LUT to use instead of the current one, then padding
+    lut_blend: [f32; 4],
+}
+
+/// What the postprocessing step writes to its color target's alpha
+/// channel, see [`ColorGeo::set_output_alpha`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum OutputAlpha {
+    /// Always fully opaque (the default)
+    #[default]
+    Opaque,
+    /// The rendered image's alpha, with colors multiplied by it, for
+    /// surfaces using [`wgpu::CompositeAlphaMode::PreMultiplied`]
+    Premultiplied,
+    /// The rendered image's alpha, with colors left as they are, for
+    /// surfaces using [`wgpu::CompositeAlphaMode::PostMultiplied`]
+    Straight,
+}
+
+/// A transition from a captured frame to the live one, see
+/// [`ColorGeo::set_transition`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transition {
+    /// Fade the old frame out to this color over the first half,
+    /// then fade the live frame in from it over the second half.
+    /// The color is in the same color space as the rendered image.
+    Fade([f32; 4]),
+    /// Sweep the live frame in over the old one, moving in the given
+    /// direction (e.g. `[1.0, 0.0]` for left to right, with y up)
+    Wipe([f32; 2]),
+    /// Blend smoothly from the old frame to the live one
+    Crossfade,
+}
+
+/// An outline drawn around the nonzero texels of a selection mask,
+/// see [`ColorGeo::set_outline`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    /// The outline's color; its alpha blends it over the frame.
+    /// The color is in the same color space as the rendered image,
+    /// and skips the color transform and LUT.
+    pub color: [f32; 4],
+    /// How many texels of the source image the outline extends
+    /// outward, up to [`Outline::MAX_WIDTH`]
+    pub width: u32,
+}
+
+impl Outline {
+    /// The widest supported outline, since each pixel checks every
+    /// mask texel within this distance
+    pub const MAX_WIDTH: u32 = 8;
+}
+
+/// The previous frame blended into the live one before color
+/// grading, for trails and feedback zooms; see
+/// [`ColorGeo::set_feedback`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Feedback {
+    /// How much of the previous frame shows through, from 0.0 (none)
+    /// to 1.0 (only the previous frame)
+    pub amount: f32,
+    /// How much larger the previous frame is drawn, around the center
+    pub scale: f32,
+    /// How far the previous frame is moved, in fractions of the
+    /// frame's size with y up
+    pub offset: [f32; 2],
+}
+
+impl Default for Feedback {
+    /// Half of the previous frame, unmoved
+    fn default() -> Self {
+        Self {
+            amount: 0.5,
+            scale: 1.0,
+            offset: [0.0; 2],
+        }
+    }
+}
+
+impl Default for Outline {
+    /// A two-texel orange outline
+    fn default() -> Self {
+        Self {
+            color: [1.0, 0.6, 0.1, 1.0],
+            width: 2,
+        }
+    }
+}
+
+/// A color transform for a single sprite, mesh, or flat group,
+/// applied in the group's own shader before the global
+/// postprocessing step, e.g. to tint the world for nighttime while
+/// UI groups stay neutral.  Groups start out with
+/// [`ColorGrade::IDENTITY`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorGrade {
+    /// A 4x4 homogeneous column-major matrix applied to each
+    /// fragment's RGB color, like [`ColorGeo::set_color_transform`]
+    pub matrix: [f32; 16],
+}
+
+impl ColorGrade {
+    /// The grade which leaves colors alone.
+    pub const IDENTITY: Self = Self {
+        matrix: [
+            1.0, 0.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 0.0, //
+            0.0, 0.0, 1.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0,
+        ],
+    };
+    /// A grade which multiplies each channel by the given factor.
+    pub fn tint([r, g, b]: [f32; 3]) -> Self {
+        let mut grade = Self::IDENTITY;
+        grade.matrix[0] = r;
+        grade.matrix[5] = g;
+        grade.matrix[10] = b;
+        grade
+    }
+}
+
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// A parametric tone curve for one color channel, applied after the
+/// color transform and saturation but before the LUT; see
+/// [`ColorCurves`].  Each value is remapped by levels
+/// (`in_black..in_white` stretched to `0..1`), then gamma, then
+/// contrast about the midpoint, then output levels (`0..1` squeezed
+/// into `out_black..out_white`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelCurve {
+    /// The input value which becomes black
+    pub in_black: f32,
+    /// The input value which becomes white
+    pub in_white: f32,
+    /// Gamma correction: above 1 brightens midtones, below 1 darkens them
+    pub gamma: f32,
+    /// Contrast about 0.5: above 1 increases it, below 1 flattens it
+    pub contrast: f32,
+    /// The output value for black
+    pub out_black: f32,
+    /// The output value for white
+    pub out_white: f32,
+}
+
+impl ChannelCurve {
+    /// The curve which leaves values alone.
+    pub const IDENTITY: Self = Self {
+        in_black: 0.0,
+        in_white: 1.0,
+        gamma: 1.0,
+        contrast: 1.0,
+        out_black: 0.0,
+        out_white: 1.0,
+    };
+    /// Applies the curve to one channel value, just as the
+    /// postprocessing shader does, e.g. to preview a curve on the CPU.
+    pub fn apply(&self, value: f32) -> f32 {
+        let range = (self.in_white - self.in_black).max(0.0001);
+        let x = ((value - self.in_black) / range).clamp(0.0, 1.0);
+        let x = x.powf(1.0 / self.gamma.max(0.0001));
+        let x = ((x - 0.5) * self.contrast + 0.5).clamp(0.0, 1.0);
+        self.out_black + (self.out_white - self.out_black) * x
+    }
+}
+
+impl Default for ChannelCurve {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Per-channel tone curves for the postprocessing step, a cheap
+/// alternative to authoring a LUT for small adjustments like gamma,
+/// contrast, or levels which a color matrix can't express; see
+/// [`ColorGeo::set_curves`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorCurves {
+    /// The red channel's curve
+    pub red: ChannelCurve,
+    /// The green channel's curve
+    pub green: ChannelCurve,
+    /// The blue channel's curve
+    pub blue: ChannelCurve,
+}
+
+impl ColorCurves {
+    /// The curves which leave colors alone.
+    pub const IDENTITY: Self = Self::uniform(ChannelCurve::IDENTITY);
+    /// The same curve for every channel.
+    pub const fn uniform(curve: ChannelCurve) -> Self {
+        Self {
+            red: curve,
+            green: curve,
+            blue: curve,
+        }
+    }
+    /// Applies each channel's curve to a color, just as the
+    /// postprocessing shader does.
+    pub fn apply(&self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        [self.red.apply(r), self.green.apply(g), self.blue.apply(b)]
+    }
+    /// Sets each channel's gamma.
+    pub fn with_gamma(mut self, [r, g, b]: [f32; 3]) -> Self {
+        self.red.gamma = r;
+        self.green.gamma = g;
+        self.blue.gamma = b;
+        self
+    }
+    /// Sets each channel's contrast.
+    pub fn with_contrast(mut self, [r, g, b]: [f32; 3]) -> Self {
+        self.red.contrast = r;
+        self.green.contrast = g;
+        self.blue.contrast = b;
+        self
+    }
+    /// Sets every channel's input levels.
+    pub fn with_levels(mut self, black: f32, white: f32) -> Self {
+        for curve in [&mut self.red, &mut self.green, &mut self.blue] {
+            curve.in_black = black;
+            curve.in_white = white;
+        }
+        self
+    }
+    /// Sets every channel's output levels.
+    pub fn with_output_levels(mut self, black: f32, white: f32) -> Self {
+        for curve in [&mut self.red, &mut self.green, &mut self.blue] {
+            curve.out_black = black;
+            curve.out_white = white;
+        }
+        self
+    }
+}
+
+impl Default for ColorCurves {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// How far the final image is turned clockwise as it's drawn onto the
+/// surface, to match a display which is rotated relative to the
+/// surface's natural orientation (e.g. a phone held sideways whose
+/// swapchain isn't rotated by the compositor).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum SurfaceRotation {
+    /// No rotation (the default)
+    #[default]
+    Identity,
+    /// A quarter turn clockwise
+    Rotate90,
+    /// A half turn
+    Rotate180,
+    /// Three quarter turns clockwise
+    Rotate270,
+}
+
+impl SurfaceRotation {
+    /// The clockwise rotation in degrees.
+    pub fn degrees(self) -> u32 {
+        match self {
+            Self::Identity => 0,
+            Self::Rotate90 => 90,
+            Self::Rotate180 => 180,
+            Self::Rotate270 => 270,
+        }
+    }
+    /// Whether this turns the image on its side, swapping the
+    /// surface's width and height as the user sees them.
+    pub fn is_quarter_turn(self) -> bool {
+        matches!(self, Self::Rotate90 | Self::Rotate270)
+    }
+    /// Turns a point in normalized device coordinates the way the
+    /// final image is turned.
+    pub fn rotate_ndc(self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let [a, b, c, d] = self.matrix();
+        [a * x + c * y, b * x + d * y]
+    }
+    // The clockwise rotation in clip space, as a column-major 2x2 matrix
+    fn matrix(self) -> [f32; 4] {
+        match self {
+            Self::Identity => [1.0, 0.0, 0.0, 1.0],
+            Self::Rotate90 => [0.0, -1.0, 1.0, 0.0],
+            Self::Rotate180 => [-1.0, 0.0, 0.0, -1.0],
+            Self::Rotate270 => [0.0, 1.0, -1.0, 0.0],
+        }
+    }
+}
+
+/// Which color space the renderers blend in, i.e. what the values
+/// in the offscreen color texture mean.  The postprocessing step
+/// converts them to suit whatever format it draws into, so either
+/// workflow looks the same on every platform as long as textures are
+/// created with [`ColorSpace::texture_format`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum ColorSpace {
+    /// Physically correct blending and filtering (the default).
+    /// Textures from ordinary images should use sRGB formats like
+    /// `Rgba8UnormSrgb`, so they're decoded to linear values when
+    /// sampled; the result is encoded back to sRGB on output.
+    #[default]
+    Linear,
+    /// Blend sRGB-encoded values directly, as most image editors and
+    /// many 2D engines do.  Textures from ordinary images should use
+    /// plain formats like `Rgba8Unorm`, and colors pass through to
+    /// the output unchanged.
+    Srgb,
+}
+
+impl ColorSpace {
+    /// The format to create textures from ordinary (sRGB-encoded)
+    /// 8-bit RGBA images with in this color space.
+    pub fn texture_format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::Linear => wgpu::TextureFormat::Rgba8UnormSrgb,
+            Self::Srgb => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+    /// The view format postprocessing should draw into on a surface
+    /// of the given format, letting the hardware do the sRGB
+    /// encoding in the linear workflow.
+    ///
+    /// ```
+    /// use frenderer::colorgeo::ColorSpace;
+    /// use frenderer::wgpu::TextureFormat;
+    /// assert_eq!(
+    ///     ColorSpace::Linear.output_format(TextureFormat::Bgra8Unorm),
+    ///     TextureFormat::Bgra8UnormSrgb
+    /// );
+    /// assert_eq!(
+    ///     ColorSpace::Srgb.output_format(TextureFormat::Rgba8UnormSrgb),
+    ///     TextureFormat::Rgba8Unorm
+    /// );
+    /// // Float surfaces have no sRGB variant
+    /// assert_eq!(
+    ///     ColorSpace::Linear.output_format(TextureFormat::Rgba16Float),
+    ///     TextureFormat::Rgba16Float
+    /// );
+    /// ```
+    pub fn output_format(self, surface_format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        match self {
+            Self::Linear => surface_format.add_srgb_suffix(),
+            Self::Srgb => surface_format.remove_srgb_suffix(),
+        }
+    }
+    // What the shader must do to colors in this space so they display
+    // correctly from a target of the given format: 0 for nothing, 1
+    // to encode linear values to sRGB, or 2 to decode sRGB values.
+    // sRGB and float formats expect linear values; others expect encoded ones.
+    fn conversion(self, target: wgpu::TextureFormat) -> f32 {
+        let expects_linear = target.is_srgb()
+            || matches!(
+                target,
+                wgpu::TextureFormat::Rgba16Float
+                    | wgpu::TextureFormat::Rgba32Float
+                    | wgpu::TextureFormat::Rg11b10Float
+            );
+        match (self, expects_linear) {
+            (Self::Linear, false) => 1.0,
+            (Self::Srgb, true) => 2.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// How the postprocessing step stretches the rendered image to fit
+/// its color target, see [`ColorGeo::set_filter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum UpscaleFilter {
+    /// Square, sharp-edged pixels (the default)
+    #[default]
+    Nearest,
+    /// Bilinear interpolation, which blurs pixel edges
+    Bilinear,
+    /// A lightweight xBR-style filter which rounds off the
+    /// staircases along diagonal edges.  Like [`UpscaleFilter::Nearest`],
+    /// it only ever outputs colors from the source image, so it's
+    /// safe to use with palette-limited art.
+    Xbr,
+    /// An imitation of a CRT monitor, with scanlines, an aperture
+    /// grille, and soft horizontal blending.
+    Crt,
+}
+
+/// A debugging view which compares the live frame against a
+/// reference frame captured with [`ColorGeo::capture_reference`],
+/// e.g. to check a shader change for regressions.  Both frames go
+/// through the same color transform and LUT; the upscale filter is
+/// skipped while comparing so that only the frames' contents differ.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum CompareMode {
+    /// Show only the live frame (the default)
+    #[default]
+    Off,
+    /// Show the reference frame to the left of this fraction of the
+    /// width and the live frame to its right
+    Split(f32),
+    /// Show a heatmap of how much each pixel differs, from black
+    /// through red and yellow to white, over a dimmed live frame
+    Difference,
+}
+
+impl UpscaleFilter {
+    fn mag_filter(self) -> wgpu::FilterMode {
+        match self {
+            Self::Bilinear => wgpu::FilterMode::Linear,
+            // Xbr and Crt read texels directly
+            _ => wgpu::FilterMode::Nearest,
+        }
+    }
+}
+
+/// A target value for an automated postprocessing parameter, see [`PostAutomation`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostTarget<T> {
+    /// The value the parameter moves towards
+    pub target: T,
+    /// Roughly how many seconds the parameter takes to get most of
+    /// the way (about 63%) to the target; zero or less means it jumps
+    /// there immediately.
+    pub smoothing: f32,
+}
+
+impl<T> PostTarget<T> {
+    // How far to move towards the target over `dt` seconds
+    fn factor(&self, dt: f32) -> f32 {
+        if self.smoothing <= 0.0 {
+            1.0
+        } else {
+            1.0 - (-dt / self.smoothing).exp()
+        }
+    }
+}
+
+/// Target values for [`ColorGeo`]'s parameters, which
+/// [`ColorGeo::automate`] eases towards every frame so that changing
+/// them (e.g. from an audio signal) doesn't pop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostAutomation {
+    /// The geometric transform's target
+    pub transform: PostTarget<[f32; 16]>,
+    /// The color transform's target
+    pub color_transform: PostTarget<[f32; 16]>,
+    /// The saturation modifier's target
+    pub saturation: PostTarget<f32>,
+}
+
+impl PostAutomation {
+    /// Creates an automation whose targets are `post`'s current
+    /// values, with the same smoothing time for every parameter.
+    pub fn from_current(post: &ColorGeo, smoothing: f32) -> Self {
+        Self {
+            transform: PostTarget {
+                target: post.transform(),
+                smoothing,
+            },
+            color_transform: PostTarget {
+                target: post.color_transform(),
+                smoothing,
+            },
+            saturation: PostTarget {
+                target: post.saturation(),
+                smoothing,
+            },
+        }
+    }
+}
+
+/// How a timed change such as [`crate::Renderer::post_fade_to`]
+/// moves from its start to its end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Easing {
+    /// A constant rate of change
+    #[default]
+    Linear,
+    /// Starts slowly and speeds up
+    EaseIn,
+    /// Starts quickly and slows down
+    EaseOut,
+    /// Starts and ends slowly
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress `t` from 0.0 to 1.0 onto eased progress.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
 }
 
 /// Returns an identity lut, for convenience in constructing a [`ColorGeo`].
@@ -73,6 +633,70 @@ pub fn lut_identity(gpu: &WGPU) -> wgpu::Texture {
     )
 }
 
+/// Returns a lut which snaps every color to the nearest color of
+/// `palette` (ignoring alpha), for a consistent indexed-color look
+/// across sprites and meshes; see [`crate::Renderer::post_set_lut`].
+pub fn lut_from_palette(gpu: &WGPU, palette: &[[u8; 4]]) -> wgpu::Texture {
+    const CUBE: u32 = 32;
+    assert!(
+        !palette.is_empty(),
+        "Can't build a lut from an empty palette"
+    );
+    // The lut is indexed by and produces linear colors, but palettes are sRGB
+    let to_linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let linear_palette: Vec<[f32; 3]> = palette
+        .iter()
+        .map(|[r, g, b, _]| [to_linear(*r), to_linear(*g), to_linear(*b)])
+        .collect();
+    gpu.device().create_texture_with_data(
+        gpu.queue(),
+        &wgpu::TextureDescriptor {
+            label: Some("lut:palette"),
+            size: wgpu::Extent3d {
+                width: CUBE,
+                height: CUBE,
+                depth_or_array_layers: CUBE,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        // red horizontal
+        // green vertical
+        // blue depth
+        &(0..CUBE)
+            .flat_map(|z| {
+                let linear_palette = &linear_palette;
+                (0..CUBE).flat_map(move |y| {
+                    (0..CUBE).flat_map(move |x| {
+                        let rgb = [x, y, z].map(|c| c as f32 / (CUBE - 1) as f32);
+                        let dist = |color: &[f32; 3]| -> f32 {
+                            (0..3).map(|i| (color[i] - rgb[i]).powi(2)).sum()
+                        };
+                        let [r, g, b] = linear_palette
+                            .iter()
+                            .min_by(|c1, c2| dist(c1).total_cmp(&dist(c2)))
+                            .unwrap()
+                            .map(|c| (c * 255.0).round() as u8);
+                        [r, g, b, 255]
+                    })
+                })
+            })
+            .collect::<Vec<u8>>(),
+    )
+}
+
 impl ColorGeo {
     /// Creates a new [`ColorGeo`] phase.
     pub fn new(
@@ -81,12 +705,10 @@ impl ColorGeo {
         lut_texture: &wgpu::Texture,
         color_target: wgpu::ColorTargetState,
     ) -> Self {
-        let shader = gpu
-            .device()
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("post:shader"),
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("colorgeo.wgsl"))),
-            });
+        let shader = gpu.create_shader(wgpu::ShaderModuleDescriptor {
+            label: Some("post:shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("colorgeo.wgsl"))),
+        });
         let transform_bind_group_layout =
             gpu.device()
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -179,6 +801,61 @@ impl ColorGeo {
                             // No count
                             count: None,
                         },
+                        // Reference frame texture binding, sampled like the color texture
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 5,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        // Transition frame texture binding, sampled like the color texture
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 6,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        // Selection mask texture binding, read texel by texel
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 7,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        // Previous frame texture binding, sampled like the color texture
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 8,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        // Next LUT texture binding, sampled like the LUT
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 9,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D3,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
                     ],
                 });
         let pipeline_layout =
@@ -192,12 +869,31 @@ impl ColorGeo {
             mat: [
                 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
             ],
+            rotation: SurfaceRotation::Identity.matrix(),
         };
         let colormod = ColorTransform {
             mat: [
                 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
             ],
             saturation_padding: [0.0; 4],
+            conversion: [
+                ColorSpace::default().conversion(color_target.format),
+                0.0,
+                0.0,
+                0.0,
+            ],
+            transition: [0.0; 4],
+            transition_color: [0.0; 4],
+            outline: [0.0; 4],
+            outline_color: [0.0; 4],
+            curve_in_black: [0.0; 4],
+            curve_in_white: [0.0; 4],
+            curve_gamma: [0.0; 4],
+            curve_contrast: [0.0; 4],
+            curve_out_black: [0.0; 4],
+            curve_out_white: [0.0; 4],
+            feedback: [0.0; 4],
+            lut_blend: [0.0; 4],
         };
         let transform_buf = gpu
             .device()
@@ -234,9 +930,16 @@ impl ColorGeo {
             &colormod_buf,
             &color_texture_view,
             &lut_texture_view,
+            None,
+            None,
+            None,
+            None,
+            None,
+            UpscaleFilter::default(),
             gpu,
         );
 
+        let target_format = color_target.format;
         let pipeline = gpu
             .device()
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -267,15 +970,38 @@ impl ColorGeo {
             transform_buf,
             colormod_buf,
             transform_bind_group,
+            transform_bind_group_layout,
             texture_bind_group_layout,
             texture_bind_group,
             color_texture_view,
             lut_texture_view,
+            next_lut_texture_view: None,
+            filter: UpscaleFilter::default(),
+            reference: None,
+            compare: CompareMode::Off,
+            transition_frame: None,
+            transition: None,
+            mask_texture_view: None,
+            outline: None,
+            history_texture_view: None,
+            feedback: None,
+            curves: ColorCurves::IDENTITY,
+            rotation: SurfaceRotation::Identity,
+            color_space: ColorSpace::default(),
+            target_format,
+            output_alpha: OutputAlpha::default(),
+            anaglyph: false,
         }
     }
 
+    /// Returns the format of the postprocessing phase's color target.
+    pub fn color_target_format(&self) -> wgpu::TextureFormat {
+        self.target_format
+    }
     /// Changes the postprocessing phase's color target, re-creating the pipeline if needed
     pub fn set_color_target(&mut self, gpu: &WGPU, color_target: wgpu::ColorTargetState) {
+        self.target_format = color_target.format;
+        self.update_conversion(gpu);
         self.pipeline = gpu
             .device()
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -297,12 +1023,53 @@ impl ColorGeo {
                 multiview: None,
             });
     }
+    /// Returns the color space of the source texture.
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+    /// Sets the color space of the source texture, so that it's
+    /// converted correctly for the color target.
+    pub fn set_color_space(&mut self, gpu: &WGPU, color_space: ColorSpace) {
+        self.color_space = color_space;
+        self.update_conversion(gpu);
+    }
+    /// Returns what's written to the color target's alpha channel.
+    pub fn output_alpha(&self) -> OutputAlpha {
+        self.output_alpha
+    }
+    /// Sets what's written to the color target's alpha channel.
+    /// With anything but [`OutputAlpha::Opaque`], parts of the
+    /// source texture which were cleared to transparent and never
+    /// drawn over stay transparent, e.g. for overlay windows.
+    pub fn set_output_alpha(&mut self, gpu: &WGPU, output_alpha: OutputAlpha) {
+        self.output_alpha = output_alpha;
+        self.update_conversion(gpu);
+    }
+    /// Returns whether the source texture's halves are combined into an anaglyph.
+    pub fn anaglyph(&self) -> bool {
+        self.anaglyph
+    }
+    /// Treats the source texture as a side-by-side stereo pair and
+    /// combines its halves into a red/cyan anaglyph, the left half
+    /// in red and the right half in green and blue.  The upscale
+    /// filter and compare mode are skipped while this is on.
+    pub fn set_anaglyph(&mut self, gpu: &WGPU, anaglyph: bool) {
+        self.anaglyph = anaglyph;
+        self.update_conversion(gpu);
+    }
+    fn update_conversion(&mut self, gpu: &WGPU) {
+        self.colormod.conversion[0] = self.color_space.conversion(self.target_format);
+        self.colormod.conversion[1] = self.output_alpha as u32 as f32;
+        self.colormod.conversion[2] = if self.anaglyph { 1.0 } else { 0.0 };
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+    }
     /// Updates simple parameters for color-geometry transforms.  To replace the lut, call [`ColorGeo::replace_lut`].
     pub fn set_post(&mut self, gpu: &WGPU, trf: [f32; 16], color_trf: [f32; 16], sat: f32) {
         // update buffers
         self.transform.mat = trf;
         self.colormod.mat = color_trf;
-        self.colormod.saturation_padding = [sat, 0.0, 0.0, 0.0];
+        self.colormod.saturation_padding[0] = sat;
         gpu.queue()
             .write_buffer(&self.transform_buf, 0, bytemuck::bytes_of(&self.transform));
         gpu.queue()
@@ -313,31 +1080,307 @@ impl ColorGeo {
     /// has changed size).
     pub fn replace_color_texture(&mut self, gpu: &WGPU, color: &wgpu::Texture) {
         self.color_texture_view = color.create_view(&wgpu::TextureViewDescriptor::default());
-        self.texture_bind_group = Self::create_bind_group(
-            &self.texture_bind_group_layout,
-            &self.colormod_buf,
-            &self.color_texture_view,
-            &self.lut_texture_view,
-            gpu,
-        );
+        self.rebuild_texture_bind_group(gpu);
+    }
+    /// Returns the current upscale filter.
+    pub fn filter(&self) -> UpscaleFilter {
+        self.filter
     }
-    /// Replaces the lookup table used by this postprocessing stage.
+    /// Changes how the source image is stretched to fit the color target.
+    pub fn set_filter(&mut self, gpu: &WGPU, filter: UpscaleFilter) {
+        self.filter = filter;
+        self.colormod.saturation_padding[1] = filter as u32 as f32;
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+        self.rebuild_texture_bind_group(gpu);
+    }
+    /// Replaces the lookup table used by this postprocessing stage,
+    /// forgetting any LUT it was blending towards.
     /// The LUT should be a 3D texture.
     pub fn replace_lut(&mut self, gpu: &WGPU, lut: &wgpu::Texture) {
         self.lut_texture_view = lut.create_view(&wgpu::TextureViewDescriptor::default());
+        self.next_lut_texture_view = None;
+        self.set_lut_blend(gpu, 0.0);
+        self.rebuild_texture_bind_group(gpu);
+    }
+    /// Whether there's a LUT to blend towards, see [`ColorGeo::set_next_lut`].
+    pub fn has_next_lut(&self) -> bool {
+        self.next_lut_texture_view.is_some()
+    }
+    /// Sets the lookup table which [`ColorGeo::set_lut_blend`]
+    /// blends towards, or removes it.  The LUT should be a 3D texture.
+    pub fn set_next_lut(&mut self, gpu: &WGPU, lut: Option<&wgpu::Texture>) {
+        self.next_lut_texture_view =
+            lut.map(|lut| lut.create_view(&wgpu::TextureViewDescriptor::default()));
+        self.rebuild_texture_bind_group(gpu);
+        self.set_lut_blend(gpu, self.lut_blend());
+    }
+    /// Returns how much of the next LUT is used instead of the current one.
+    pub fn lut_blend(&self) -> f32 {
+        self.colormod.lut_blend[0]
+    }
+    /// Grades with a mix of the current LUT and the one given to
+    /// [`ColorGeo::set_next_lut`], from 0.0 (all current) to 1.0
+    /// (all next).  Without a next LUT this has no effect.
+    pub fn set_lut_blend(&mut self, gpu: &WGPU, blend: f32) {
+        self.colormod.lut_blend[0] = if self.next_lut_texture_view.is_some() {
+            blend.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+    }
+    /// Makes the next LUT the current one, e.g. once a blend towards
+    /// it is finished.  Does nothing without a next LUT.
+    pub fn commit_next_lut(&mut self, gpu: &WGPU) {
+        if let Some(next) = self.next_lut_texture_view.take() {
+            self.lut_texture_view = next;
+            self.set_lut_blend(gpu, 0.0);
+            self.rebuild_texture_bind_group(gpu);
+        }
+    }
+    /// Copies `source` (e.g. the texture being postprocessed) into
+    /// the reference frame for [`CompareMode`].
+    pub fn capture_reference(&mut self, gpu: &WGPU, source: &wgpu::Texture) {
+        self.reference = Some(Self::copy_texture(gpu, source, "post:reference_texture"));
+        self.rebuild_texture_bind_group(gpu);
+    }
+    // A copy of `source` which can be sampled, and a view of it
+    fn copy_texture(
+        gpu: &WGPU,
+        source: &wgpu::Texture,
+        label: &str,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = gpu.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: source.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: source.format(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let mut encoder = gpu
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("post:copy_texture"),
+            });
+        encoder.copy_texture_to_texture(
+            source.as_image_copy(),
+            texture.as_image_copy(),
+            source.size(),
+        );
+        gpu.queue().submit(Some(encoder.finish()));
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+    /// Forgets the reference frame.  Until another is captured, the
+    /// live frame is compared against itself.
+    pub fn clear_reference(&mut self, gpu: &WGPU) {
+        self.reference = None;
+        self.rebuild_texture_bind_group(gpu);
+    }
+    /// Returns the current [`CompareMode`].
+    pub fn compare(&self) -> CompareMode {
+        self.compare
+    }
+    /// Sets how the live frame is compared against the reference frame.
+    pub fn set_compare(&mut self, gpu: &WGPU, compare: CompareMode) {
+        self.compare = compare;
+        let (mode, split) = match compare {
+            CompareMode::Off => (0.0, 0.0),
+            CompareMode::Split(split) => (1.0, split),
+            CompareMode::Difference => (2.0, 0.0),
+        };
+        self.colormod.saturation_padding[2] = mode;
+        self.colormod.saturation_padding[3] = split;
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+    }
+    /// Copies `source` (e.g. the texture being postprocessed) into
+    /// the frame which [`ColorGeo::set_transition`] transitions from.
+    pub fn capture_transition(&mut self, gpu: &WGPU, source: &wgpu::Texture) {
+        self.transition_frame = Some(Self::copy_texture(gpu, source, "post:transition_texture"));
+        self.rebuild_texture_bind_group(gpu);
+    }
+    /// Returns the current transition, if any.
+    pub fn transition(&self) -> Option<Transition> {
+        self.transition
+    }
+    /// Shows `transition` from the frame captured with
+    /// [`ColorGeo::capture_transition`] to the live frame, `progress`
+    /// of the way from 0.0 (all old frame) to 1.0 (all live frame).
+    /// `None` shows just the live frame and forgets the captured one.
+    pub fn set_transition(&mut self, gpu: &WGPU, transition: Option<Transition>, progress: f32) {
+        self.transition = transition;
+        let (kind, dir, color) = match transition {
+            None => (0.0, [0.0; 2], [0.0; 4]),
+            Some(Transition::Fade(color)) => (1.0, [0.0; 2], color),
+            Some(Transition::Wipe(dir)) => (2.0, dir, [0.0; 4]),
+            Some(Transition::Crossfade) => (3.0, [0.0; 2], [0.0; 4]),
+        };
+        self.colormod.transition = [kind, progress.clamp(0.0, 1.0), dir[0], dir[1]];
+        self.colormod.transition_color = color;
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+        if transition.is_none() && self.transition_frame.take().is_some() {
+            self.rebuild_texture_bind_group(gpu);
+        }
+    }
+    /// Returns the current outline, if any.
+    pub fn outline(&self) -> Option<Outline> {
+        self.outline
+    }
+    /// Draws `outline` around the nonzero parts of the mask given to
+    /// [`ColorGeo::replace_mask_texture`], just outside them, or
+    /// stops outlining if `outline` is `None`.
+    pub fn set_outline(&mut self, gpu: &WGPU, outline: Option<Outline>) {
+        self.outline = outline;
+        let (width, color) = match outline {
+            // Without a mask there's nothing to outline
+            Some(outline) if self.mask_texture_view.is_some() => {
+                (outline.width.min(Outline::MAX_WIDTH) as f32, outline.color)
+            }
+            _ => (0.0, [0.0; 4]),
+        };
+        self.colormod.outline = [width, 0.0, 0.0, 0.0];
+        self.colormod.outline_color = color;
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+    }
+    /// Returns the current per-channel color curves.
+    pub fn curves(&self) -> ColorCurves {
+        self.curves
+    }
+    /// Sets the per-channel color curves applied after the color
+    /// transform and saturation, before the LUT.
+    pub fn set_curves(&mut self, gpu: &WGPU, curves: ColorCurves) {
+        self.curves = curves;
+        let param =
+            |f: fn(&ChannelCurve) -> f32| [f(&curves.red), f(&curves.green), f(&curves.blue), 0.0];
+        self.colormod.curve_in_black = param(|c| c.in_black);
+        // The shader skips identity curves entirely
+        self.colormod.curve_in_black[3] = if curves == ColorCurves::IDENTITY {
+            0.0
+        } else {
+            1.0
+        };
+        self.colormod.curve_in_white = param(|c| c.in_white);
+        self.colormod.curve_gamma = param(|c| c.gamma);
+        self.colormod.curve_contrast = param(|c| c.contrast);
+        self.colormod.curve_out_black = param(|c| c.out_black);
+        self.colormod.curve_out_white = param(|c| c.out_white);
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+    }
+    /// Returns how far the final image is turned as it's drawn.
+    pub fn rotation(&self) -> SurfaceRotation {
+        self.rotation
+    }
+    /// Turns the final image as it's drawn, after the geometric
+    /// transform, e.g. to match a rotated display.
+    pub fn set_rotation(&mut self, gpu: &WGPU, rotation: SurfaceRotation) {
+        self.rotation = rotation;
+        self.transform.rotation = rotation.matrix();
+        gpu.queue()
+            .write_buffer(&self.transform_buf, 0, bytemuck::bytes_of(&self.transform));
+    }
+    /// Replaces the selection mask which [`ColorGeo::set_outline`]
+    /// outlines, or removes it.  The mask should be the same size as
+    /// the color texture, with its red channel nonzero wherever
+    /// something is selected.
+    pub fn replace_mask_texture(&mut self, gpu: &WGPU, mask: Option<&wgpu::Texture>) {
+        self.mask_texture_view =
+            mask.map(|mask| mask.create_view(&wgpu::TextureViewDescriptor::default()));
+        self.rebuild_texture_bind_group(gpu);
+        self.set_outline(gpu, self.outline);
+    }
+    /// Returns the current feedback, if any.
+    pub fn feedback(&self) -> Option<Feedback> {
+        self.feedback
+    }
+    /// Blends the previous frame given to
+    /// [`ColorGeo::replace_history_texture`] into the live one
+    /// before color grading, or stops if `feedback` is `None`.
+    pub fn set_feedback(&mut self, gpu: &WGPU, feedback: Option<Feedback>) {
+        self.feedback = feedback;
+        self.colormod.feedback = match feedback {
+            // Without a previous frame there's nothing to blend in
+            Some(feedback) if self.history_texture_view.is_some() => [
+                feedback.amount.clamp(0.0, 1.0),
+                feedback.scale.max(0.0001),
+                feedback.offset[0],
+                feedback.offset[1],
+            ],
+            _ => [0.0; 4],
+        };
+        gpu.queue()
+            .write_buffer(&self.colormod_buf, 0, bytemuck::bytes_of(&self.colormod));
+    }
+    /// Replaces the previous frame which [`ColorGeo::set_feedback`]
+    /// blends in, or removes it.  The texture should be the same
+    /// size and format as the color texture; only its first layer
+    /// is used.
+    pub fn replace_history_texture(&mut self, gpu: &WGPU, history: Option<&wgpu::Texture>) {
+        self.history_texture_view = history.map(|history| {
+            history.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        });
+        self.rebuild_texture_bind_group(gpu);
+        self.set_feedback(gpu, self.feedback);
+    }
+    // Lists the uniform buffers and captured frames
+    pub(crate) fn collect_resources(&self, out: &mut Vec<crate::resources::GpuResource>) {
+        use crate::resources::GpuResource;
+        out.push(GpuResource::buffer(
+            Some("post:transform_buffer".to_string()),
+            &self.transform_buf,
+        ));
+        out.push(GpuResource::buffer(
+            Some("post:colormod_buffer".to_string()),
+            &self.colormod_buf,
+        ));
+        for (label, frame) in [
+            ("post:reference_texture", &self.reference),
+            ("post:transition_texture", &self.transition_frame),
+        ] {
+            if let Some((texture, _)) = frame {
+                out.push(GpuResource::texture(Some(label.to_string()), texture));
+            }
+        }
+    }
+    fn rebuild_texture_bind_group(&mut self, gpu: &WGPU) {
         self.texture_bind_group = Self::create_bind_group(
             &self.texture_bind_group_layout,
             &self.colormod_buf,
             &self.color_texture_view,
             &self.lut_texture_view,
+            self.next_lut_texture_view.as_ref(),
+            self.reference.as_ref().map(|(_, view)| view),
+            self.transition_frame.as_ref().map(|(_, view)| view),
+            self.mask_texture_view.as_ref(),
+            self.history_texture_view.as_ref(),
+            self.filter,
             gpu,
         );
     }
+    #[allow(clippy::too_many_arguments)]
     fn create_bind_group(
         texture_bind_group_layout: &wgpu::BindGroupLayout,
         colormod_buf: &wgpu::Buffer,
         color_texture_view: &wgpu::TextureView,
         lut_texture_view: &wgpu::TextureView,
+        next_lut_texture_view: Option<&wgpu::TextureView>,
+        reference_texture_view: Option<&wgpu::TextureView>,
+        transition_texture_view: Option<&wgpu::TextureView>,
+        mask_texture_view: Option<&wgpu::TextureView>,
+        history_texture_view: Option<&wgpu::TextureView>,
+        filter: UpscaleFilter,
         gpu: &WGPU,
     ) -> wgpu::BindGroup {
         gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
@@ -363,8 +1406,8 @@ impl ColorGeo {
                             label: Some("post:color_sampler"),
                             address_mode_u: wgpu::AddressMode::ClampToEdge,
                             address_mode_v: wgpu::AddressMode::ClampToEdge,
-                            mag_filter: wgpu::FilterMode::Nearest,
-                            min_filter: wgpu::FilterMode::Nearest,
+                            mag_filter: filter.mag_filter(),
+                            min_filter: filter.mag_filter(),
                             ..Default::default()
                         },
                     )),
@@ -387,6 +1430,36 @@ impl ColorGeo {
                         },
                     )),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(
+                        reference_texture_view.unwrap_or(color_texture_view),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(
+                        transition_texture_view.unwrap_or(color_texture_view),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::TextureView(
+                        mask_texture_view.unwrap_or(color_texture_view),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: wgpu::BindingResource::TextureView(
+                        history_texture_view.unwrap_or(color_texture_view),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: wgpu::BindingResource::TextureView(
+                        next_lut_texture_view.unwrap_or(lut_texture_view),
+                    ),
+                },
             ],
         })
     }
@@ -401,6 +1474,17 @@ impl ColorGeo {
         rpass.set_bind_group(1, &self.texture_bind_group, &[]);
         rpass.draw(0..6, 0..1);
     }
+    /// Returns the bind group layouts this phase's pipeline uses.
+    pub fn layouts(&self) -> ColorGeoLayouts<'_> {
+        ColorGeoLayouts {
+            transform: &self.transform_bind_group_layout,
+            inputs: &self.texture_bind_group_layout,
+        }
+    }
+    /// Returns the bind groups for groups 0 and 1 of [`ColorGeoLayouts`].
+    pub fn bind_groups(&self) -> (&wgpu::BindGroup, &wgpu::BindGroup) {
+        (&self.transform_bind_group, &self.texture_bind_group)
+    }
     /// Returns the current geometric transform (a 4x4 homogeneous column-major matrix).
     pub fn transform(&self) -> [f32; 16] {
         self.transform.mat
@@ -435,4 +1519,98 @@ impl ColorGeo {
     pub fn set_saturation(&mut self, gpu: &WGPU, sat: f32) {
         self.set_post(gpu, self.transform.mat, self.colormod.mat, sat);
     }
+    /// Moves each parameter towards its target in `automation`,
+    /// given that `dt` seconds have passed since the last call.
+    /// Call this once per frame.
+    pub fn automate(&mut self, gpu: &WGPU, automation: &PostAutomation, dt: f32) {
+        let mut trf = self.transform.mat;
+        let t = automation.transform.factor(dt);
+        for (cur, target) in trf.iter_mut().zip(automation.transform.target) {
+            *cur += (target - *cur) * t;
+        }
+        let mut color_trf = self.colormod.mat;
+        let t = automation.color_transform.factor(dt);
+        for (cur, target) in color_trf.iter_mut().zip(automation.color_transform.target) {
+            *cur += (target - *cur) * t;
+        }
+        let sat = self.saturation();
+        let sat = sat + (automation.saturation.target - sat) * automation.saturation.factor(dt);
+        self.set_post(gpu, trf, color_trf, sat);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_identity_curve() {
+        for x in [0.0, 0.25, 0.5, 1.0] {
+            assert!(close(ChannelCurve::IDENTITY.apply(x), x));
+        }
+        // Out of range values are clamped
+        assert_eq!(ChannelCurve::IDENTITY.apply(-0.5), 0.0);
+        assert_eq!(ChannelCurve::IDENTITY.apply(2.0), 1.0);
+    }
+    #[test]
+    fn test_curve_endpoints() {
+        let curves = ColorCurves::IDENTITY
+            .with_levels(0.1, 0.9)
+            .with_output_levels(0.2, 0.7);
+        // Gamma and increased contrast don't move black or white, so
+        // the input levels land exactly on the output levels
+        for curves in [
+            curves,
+            curves.with_gamma([2.2, 0.5, 1.0]),
+            curves.with_contrast([1.5, 3.0, 1.0]),
+        ] {
+            for x in [0.0, 0.1] {
+                assert!(curves.apply([x; 3]).iter().all(|&c| close(c, 0.2)));
+            }
+            for x in [0.9, 1.0] {
+                assert!(curves.apply([x; 3]).iter().all(|&c| close(c, 0.7)));
+            }
+        }
+        // Inverted output levels flip the image
+        let negative = ColorCurves::IDENTITY.with_output_levels(1.0, 0.0);
+        assert_eq!(negative.apply([0.0, 1.0, 0.25]), [1.0, 0.0, 0.75]);
+        // Reduced contrast lifts black and lowers white
+        let flat = ColorCurves::IDENTITY.with_contrast([0.5; 3]);
+        assert_eq!(flat.apply([0.0, 1.0, 0.5]), [0.25, 0.75, 0.5]);
+    }
+    #[test]
+    fn test_curve_midtones() {
+        let gamma = ColorCurves::IDENTITY.with_gamma([2.0, 0.5, 1.0]);
+        let [r, g, b] = gamma.apply([0.25; 3]);
+        assert!(close(r, 0.5) && close(g, 0.0625) && close(b, 0.25));
+        // Contrast pivots around the midpoint
+        let contrast = ChannelCurve {
+            contrast: 2.0,
+            ..ChannelCurve::IDENTITY
+        };
+        assert!(close(contrast.apply(0.5), 0.5));
+        assert!(close(contrast.apply(0.6), 0.7));
+        assert_eq!(contrast.apply(0.8), 1.0);
+        assert_eq!(contrast.apply(0.2), 0.0);
+    }
+    #[test]
+    fn test_degenerate_curve() {
+        // Empty input levels and zero gamma give a hard threshold, not NaN
+        let curve = ChannelCurve {
+            in_black: 0.5,
+            in_white: 0.5,
+            gamma: 0.0,
+            ..ChannelCurve::IDENTITY
+        };
+        for x in [0.0, 0.5, 0.51, 1.0] {
+            let y = curve.apply(x);
+            assert!(y == 0.0 || y == 1.0, "{x} -> {y}");
+        }
+        assert_eq!(curve.apply(1.0), 1.0);
+        assert_eq!(curve.apply(0.0), 0.0);
+    }
 }
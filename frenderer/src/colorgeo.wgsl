@@ -20,6 +20,8 @@ struct Transform {
    b: vec4<f32>,
    c: vec4<f32>,
    d: vec4<f32>,
+   // The surface rotation applied after the transform, a column-major 2x2 matrix
+   rotation: vec4<f32>,
 }
 
 struct ColorTransform {
@@ -28,6 +30,32 @@ struct ColorTransform {
    c: vec4<f32>,
    d: vec4<f32>,
    saturation_padding:vec4<f32>,
+   // x: 0 for no conversion, 1 to encode linear to sRGB, 2 to decode sRGB to linear
+   // y: 0 for opaque output, 1 for premultiplied alpha, 2 for straight alpha
+   // z: 1 to combine side-by-side stereo halves into a red/cyan anaglyph
+   conversion:vec4<f32>,
+   // x: 0 for no transition, 1 to fade through a color, 2 to wipe, 3 to crossfade
+   // y: how far along the transition is, from 0 to 1
+   // zw: the wipe's direction
+   transition:vec4<f32>,
+   transition_color:vec4<f32>,
+   // x: the outline's width in texels, 0 for no outline
+   outline:vec4<f32>,
+   outline_color:vec4<f32>,
+   // xyz: each curve parameter for red, green, and blue
+   // curve_in_black.w: 1 to apply the curves, 0 to skip them
+   curve_in_black:vec4<f32>,
+   curve_in_white:vec4<f32>,
+   curve_gamma:vec4<f32>,
+   curve_contrast:vec4<f32>,
+   curve_out_black:vec4<f32>,
+   curve_out_white:vec4<f32>,
+   // x: how much of the previous frame to blend in, 0 for none
+   // y: how much larger the previous frame is drawn
+   // zw: how far the previous frame is moved, with y up
+   feedback:vec4<f32>,
+   // x: how much of the next LUT to use instead of the current one
+   lut_blend:vec4<f32>,
 }
 
 @group(0) @binding(0)
@@ -42,7 +70,9 @@ struct VertexOutput {
 fn vs_vbuf_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
   let trf_mat = mat4x4<f32>(u_transform.a, u_transform.b, u_transform.c, u_transform.d);
   var out:VertexOutput;
-  out.clip_position = trf_mat * VERTICES[in_vertex_index];
+  let pos = trf_mat * VERTICES[in_vertex_index];
+  let rot_mat = mat2x2<f32>(u_transform.rotation.xy, u_transform.rotation.zw);
+  out.clip_position = vec4<f32>(rot_mat * pos.xy, pos.zw);
   out.tex_coords = TEX_COORDS[in_vertex_index];
   return out;
 }
@@ -63,9 +93,71 @@ var t_lut: texture_3d<f32>;
 // And a sampler.
 @group(1) @binding(4)
 var s_lut: sampler;
-@fragment
-fn fs_main(in:VertexOutput) -> @location(0) vec4<f32> {
-    var color:vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+// The LUT being blended towards, sampled with s_lut
+@group(1) @binding(9)
+var t_next_lut: texture_3d<f32>;
+
+fn load_texel(texel:vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    return textureLoad(t_diffuse, clamp(texel, vec2<i32>(0), size - 1), 0);
+}
+fn similar(a:vec4<f32>, b:vec4<f32>) -> bool {
+    let d = abs(a.xyz - b.xyz);
+    return d.x + d.y + d.z < 0.1;
+}
+// Scale2x-style corner rounding at any scale: each quarter of a
+// texel takes the color of its two outer neighbors if they match
+// each other (and not their opposites) and the fragment lies past the
+// diagonal between them.
+fn sample_xbr(uv:vec2<f32>) -> vec4<f32> {
+    let pos = uv * vec2<f32>(textureDimensions(t_diffuse));
+    let texel = vec2<i32>(floor(pos));
+    let f = fract(pos);
+    let dir = vec2<i32>(select(-1, 1, f.x >= 0.5), select(-1, 1, f.y >= 0.5));
+    let c = load_texel(texel);
+    let h = load_texel(texel + vec2<i32>(dir.x, 0));
+    let v = load_texel(texel + vec2<i32>(0, dir.y));
+    let h_opp = load_texel(texel - vec2<i32>(dir.x, 0));
+    let v_opp = load_texel(texel - vec2<i32>(0, dir.y));
+    // 0 at the texel's center, 1 at its edges
+    let q = abs(f - 0.5) * 2.0;
+    if (q.x + q.y > 1.0 && similar(h, v) && !similar(h, v_opp) && !similar(v, h_opp)) {
+        return h;
+    }
+    return c;
+}
+// Soft horizontal blending, scanlines between texel rows, and an
+// aperture grille across output pixels.
+fn sample_crt(uv:vec2<f32>, frag:vec2<f32>) -> vec4<f32> {
+    let pos = uv * vec2<f32>(textureDimensions(t_diffuse)) - vec2<f32>(0.5, 0.0);
+    let texel = vec2<i32>(floor(pos));
+    let f = fract(pos);
+    let blend = smoothstep(0.2, 0.8, f.x);
+    var color = mix(load_texel(texel), load_texel(texel + vec2<i32>(1, 0)), blend);
+    let alpha = color.w;
+    let row = f.y - 0.5;
+    color *= 1.3 * exp(-row * row * 8.0);
+    let grille = u32(frag.x) % 3u;
+    var mask = vec3<f32>(0.8, 0.8, 1.1);
+    if (grille == 0u) {
+        mask = vec3<f32>(1.1, 0.8, 0.8);
+    } else if (grille == 1u) {
+        mask = vec3<f32>(0.8, 1.1, 0.8);
+    }
+    return vec4<f32>(clamp(color.xyz * mask, vec3<f32>(0.0), vec3<f32>(1.0)), alpha);
+}
+// The reference frame for A/B comparisons
+@group(1) @binding(5)
+var t_reference: texture_2d<f32>;
+
+fn grade(in_color:vec4<f32>) -> vec4<f32> {
+    var color = in_color;
+    let transparent = u32(u_color.conversion.y) != 0u;
+    let alpha = select(1.0, in_color.w, transparent);
+    if (transparent) {
+        // Blending over a transparent clear leaves colors premultiplied by alpha
+        color = vec4<f32>(color.xyz / max(alpha, 0.0001), 1.0);
+    }
     color.w = 1.0;
     // apply colormod matrix
     color = mat4x4<f32>(u_color.a, u_color.b, u_color.c, u_color.d) * color;
@@ -73,6 +165,163 @@ fn fs_main(in:VertexOutput) -> @location(0) vec4<f32> {
     let intensity:f32 = (color.x + color.y + color.z) / 3.0;
     let dev:vec4<f32> = vec4<f32>(intensity-color.x, intensity-color.y, intensity-color.z, 1.0);
     color += dev * -u_color.saturation_padding.x;
-    // apply LUT
-    return textureSample(t_lut, s_lut, color.xyz);
+    color = vec4<f32>(curves(color.xyz), color.w);
+    // apply LUT, blending towards the next one; sampled by level so
+    // grading works in non-uniform control flow
+    let graded = textureSampleLevel(t_lut, s_lut, color.xyz, 0.0).xyz;
+    let blend = u_color.lut_blend.x;
+    if (blend > 0.0) {
+        let next = textureSampleLevel(t_next_lut, s_lut, color.xyz, 0.0).xyz;
+        return vec4<f32>(mix(graded, next, blend), alpha);
+    }
+    return vec4<f32>(graded, alpha);
+}
+// Levels, gamma, contrast, and output levels for each channel
+fn curves(c:vec3<f32>) -> vec3<f32> {
+    if (u_color.curve_in_black.w == 0.0) {
+        return c;
+    }
+    let black = u_color.curve_in_black.xyz;
+    let range = max(u_color.curve_in_white.xyz - black, vec3<f32>(0.0001));
+    var x = clamp((c - black) / range, vec3<f32>(0.0), vec3<f32>(1.0));
+    x = pow(x, 1.0 / max(u_color.curve_gamma.xyz, vec3<f32>(0.0001)));
+    x = clamp((x - 0.5) * u_color.curve_contrast.xyz + 0.5, vec3<f32>(0.0), vec3<f32>(1.0));
+    return mix(u_color.curve_out_black.xyz, u_color.curve_out_white.xyz, x);
+}
+fn linear_to_srgb(c:vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+fn srgb_to_linear(c:vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+@fragment
+fn fs_main(in:VertexOutput) -> @location(0) vec4<f32> {
+    var color = transition(in, outline(in, composite(in)));
+    let conversion = u32(u_color.conversion.x);
+    if (conversion == 1u) {
+        color = vec4<f32>(linear_to_srgb(color.xyz), color.w);
+    } else if (conversion == 2u) {
+        color = vec4<f32>(srgb_to_linear(color.xyz), color.w);
+    }
+    if (u32(u_color.conversion.y) == 1u) {
+        color = vec4<f32>(color.xyz * color.w, color.w);
+    }
+    return color;
+}
+// The frame captured at the start of a scene transition
+@group(1) @binding(6)
+var t_transition: texture_2d<f32>;
+
+// Blends from the captured frame to the live one as the transition progresses
+fn transition(in:VertexOutput, live:vec4<f32>) -> vec4<f32> {
+    let kind = u32(u_color.transition.x);
+    if (kind == 0u) {
+        return live;
+    }
+    let t = u_color.transition.y;
+    // Only sampled while a transition runs, by level since grading
+    // may happen in non-uniform control flow
+    let old = grade(textureSampleLevel(t_transition, s_diffuse, in.tex_coords, 0.0));
+    if (kind == 1u) {
+        let color = u_color.transition_color;
+        if (t < 0.5) {
+            return mix(old, color, t * 2.0);
+        }
+        return mix(color, live, t * 2.0 - 1.0);
+    } else if (kind == 2u) {
+        // How far across the screen the pixel is in the wipe's direction, from 0 to 1
+        let dir = u_color.transition.zw;
+        let pos = vec2<f32>(in.tex_coords.x, 1.0 - in.tex_coords.y) - 0.5;
+        let along = dot(pos, dir) / max(abs(dir.x) + abs(dir.y), 0.0001) + 0.5;
+        return select(old, live, along < t);
+    } else if (kind == 3u) {
+        return mix(old, live, t);
+    }
+    return live;
+}
+// The graded reference frame, only sampled while comparing, by level
+// since that can be in non-uniform control flow
+fn graded_reference(in:VertexOutput) -> vec4<f32> {
+    return grade(textureSampleLevel(t_reference, s_diffuse, in.tex_coords, 0.0));
+}
+// The graded, filtered, and possibly compared color of a pixel
+fn composite(in:VertexOutput) -> vec4<f32> {
+    // 0 and 1 are nearest and bilinear, which differ only in the sampler
+    let upscale = u32(u_color.saturation_padding.y);
+    // 0 is off, 1 is split, 2 is difference
+    let compare = u32(u_color.saturation_padding.z);
+    let split = u_color.saturation_padding.w;
+    let sampled = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let line_width = fwidth(in.tex_coords.x);
+    if (u32(u_color.conversion.z) == 1u) {
+        let uv = vec2<f32>(in.tex_coords.x * 0.5, in.tex_coords.y);
+        let left = grade(textureSample(t_diffuse, s_diffuse, uv));
+        let right = grade(textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(0.5, 0.0)));
+        return vec4<f32>(left.x, right.y, right.z, max(left.w, right.w));
+    }
+    var color:vec4<f32> = sampled;
+    if (compare == 0u && upscale == 2u) {
+        color = sample_xbr(in.tex_coords);
+    } else if (compare == 0u && upscale == 3u) {
+        color = sample_crt(in.tex_coords, in.clip_position.xy);
+    }
+    let live = grade(feedback(in, color));
+    if (compare == 1u) {
+        if (abs(in.tex_coords.x - split) < line_width) {
+            return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+        }
+        if (in.tex_coords.x < split) {
+            return graded_reference(in);
+        }
+        return live;
+    } else if (compare == 2u) {
+        let reference = graded_reference(in);
+        // Exaggerate small differences so they're easy to spot
+        let diff = clamp(length(live.xyz - reference.xyz) * 8.0, 0.0, 1.0);
+        let heat = clamp(vec3<f32>(diff * 3.0, diff * 3.0 - 1.0, diff * 3.0 - 2.0), vec3<f32>(0.0), vec3<f32>(1.0));
+        return vec4<f32>(clamp(live.xyz * 0.2 + heat, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+    }
+    return live;
+}
+// The selection mask, nonzero wherever something is selected
+@group(1) @binding(7)
+var t_mask: texture_2d<f32>;
+
+// Draws the outline color over pixels just outside the selection mask
+fn outline(in:VertexOutput, color:vec4<f32>) -> vec4<f32> {
+    let width = i32(u_color.outline.x);
+    if (width == 0) {
+        return color;
+    }
+    let size = vec2<i32>(textureDimensions(t_mask));
+    let texel = clamp(vec2<i32>(in.tex_coords * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    if (textureLoad(t_mask, texel, 0).x > 0.5) {
+        return color;
+    }
+    // Dilate the mask by checking every texel within the outline's width
+    for (var dy = -width; dy <= width; dy++) {
+        for (var dx = -width; dx <= width; dx++) {
+            let neighbor = texel + vec2<i32>(dx, dy);
+            if (dx * dx + dy * dy > width * width || any(neighbor < vec2<i32>(0)) || any(neighbor >= size)) {
+                continue;
+            }
+            if (textureLoad(t_mask, neighbor, 0).x > 0.5) {
+                let edge = u_color.outline_color;
+                return vec4<f32>(mix(color.xyz, edge.xyz, edge.w), max(color.w, edge.w));
+            }
+        }
+    }
+    return color;
+}
+// The previous frame, for feedback effects
+@group(1) @binding(8)
+var t_history: texture_2d<f32>;
+
+// Blends the scaled and moved previous frame into the ungraded color
+fn feedback(in:VertexOutput, color:vec4<f32>) -> vec4<f32> {
+    let fb = u_color.feedback;
+    let offset = vec2<f32>(-fb.z, fb.w);
+    let uv = (in.tex_coords - 0.5) / max(fb.y, 0.0001) + 0.5 + offset;
+    let previous = textureSample(t_history, s_diffuse, uv);
+    return mix(color, previous, fb.x);
 }
@@ -0,0 +1,320 @@
+//! Conversions between frenderer's plain-array types and the math
+//! libraries games tend to use.
+//!
+//! Conversions to and from [ultraviolet](https://docs.rs/ultraviolet)
+//! types are always available.  With the `glam` feature, the same
+//! conversions exist for [glam](https://docs.rs/glam) types.
+//!
+//! Each of [`Transform`], [`Transform3D`], [`Camera2D`], and
+//! [`Camera3D`] converts into a 4x4 matrix: transforms become the
+//! model matrix the renderer draws instances with, and cameras become
+//! the matrix taking world space to clip space.  [`Transform3D`] also
+//! converts to and from a translation, rotation, and uniform scale
+//! ([`ultraviolet::Similarity3`], or a `(Vec3, Quat, f32)` tuple for
+//! glam).  Vectors and quaternions already convert from the arrays
+//! these types store, e.g. `Vec3::from(trf.translation)`.
+
+use crate::meshes::{Camera3D, Transform3D};
+use crate::sprites::{Camera2D, Transform};
+use ultraviolet::{Isometry3, Mat4, Rotor3, Similarity3, Vec3};
+
+impl From<Transform> for Mat4 {
+    /// The matrix taking the unit square centered on the origin to
+    /// this sprite's corners in world space.
+    fn from(trf: Transform) -> Self {
+        Mat4::from_translation(Vec3::new(trf.x, trf.y, 0.0))
+            * Mat4::from_rotation_z(trf.rot)
+            * Mat4::from_nonuniform_scale(Vec3::new(trf.w as f32, trf.h as f32, 1.0))
+    }
+}
+
+impl From<Camera2D> for Mat4 {
+    /// The matrix taking world space to clip space for sprites drawn
+    /// with this camera.
+    fn from(camera: Camera2D) -> Self {
+        let [x, y] = camera.screen_pos;
+        let [w, h] = camera.screen_size;
+        Mat4::from_translation(Vec3::new(-2.0 * x / w - 1.0, -2.0 * y / h - 1.0, 0.0))
+            * Mat4::from_nonuniform_scale(Vec3::new(2.0 / w, 2.0 / h, 1.0))
+    }
+}
+
+impl From<Transform3D> for Similarity3 {
+    fn from(trf: Transform3D) -> Self {
+        Similarity3::new(
+            Vec3::from(trf.translation),
+            Rotor3::from_quaternion_array(trf.rotation),
+            trf.scale,
+        )
+    }
+}
+
+impl From<Similarity3> for Transform3D {
+    fn from(sim: Similarity3) -> Self {
+        Self {
+            translation: sim.translation.into(),
+            scale: sim.scale,
+            rotation: sim.rotation.into_quaternion_array(),
+        }
+    }
+}
+
+impl From<Isometry3> for Transform3D {
+    /// An unscaled transform.
+    fn from(iso: Isometry3) -> Self {
+        Self {
+            translation: iso.translation.into(),
+            scale: 1.0,
+            rotation: iso.rotation.into_quaternion_array(),
+        }
+    }
+}
+
+impl From<Transform3D> for Mat4 {
+    /// The model matrix: scale, then rotate, then translate.
+    fn from(trf: Transform3D) -> Self {
+        Similarity3::from(trf).into_homogeneous_matrix()
+    }
+}
+
+impl From<Camera3D> for Mat4 {
+    /// The camera's view-projection matrix, the one the renderer
+    /// uploads for mesh groups.
+    fn from(camera: Camera3D) -> Self {
+        let view = Isometry3::new(
+            Vec3::from(camera.translation),
+            Rotor3::from_quaternion_array(camera.rotation),
+        )
+        .inversed()
+        .into_homogeneous_matrix();
+        let proj = ultraviolet::projection::rh_yup::perspective_wgpu_dx(
+            camera.fov,
+            camera.aspect,
+            camera.near,
+            camera.far,
+        );
+        proj * view
+    }
+}
+
+#[cfg(feature = "glam")]
+mod glam_conversions {
+    use super::*;
+
+    fn to_glam(mat: Mat4) -> glam::Mat4 {
+        glam::Mat4::from_cols_array(mat.as_array())
+    }
+
+    impl From<Transform> for glam::Mat4 {
+        /// The same matrix as the ultraviolet conversion.
+        fn from(trf: Transform) -> Self {
+            to_glam(trf.into())
+        }
+    }
+
+    impl From<Camera2D> for glam::Mat4 {
+        /// The same matrix as the ultraviolet conversion.
+        fn from(camera: Camera2D) -> Self {
+            to_glam(camera.into())
+        }
+    }
+
+    impl From<Transform3D> for glam::Mat4 {
+        /// The model matrix: scale, then rotate, then translate.
+        fn from(trf: Transform3D) -> Self {
+            let (translation, rotation, scale) = trf.into();
+            glam::Mat4::from_scale_rotation_translation(
+                glam::Vec3::splat(scale),
+                rotation,
+                translation,
+            )
+        }
+    }
+
+    impl From<Camera3D> for glam::Mat4 {
+        /// The same matrix as the ultraviolet conversion.
+        fn from(camera: Camera3D) -> Self {
+            to_glam(camera.into())
+        }
+    }
+
+    impl From<(glam::Vec3, glam::Quat, f32)> for Transform3D {
+        /// A transform from a translation, rotation, and uniform scale.
+        fn from((translation, rotation, scale): (glam::Vec3, glam::Quat, f32)) -> Self {
+            Self {
+                translation: translation.to_array(),
+                scale,
+                rotation: rotation.to_array(),
+            }
+        }
+    }
+
+    impl From<Transform3D> for (glam::Vec3, glam::Quat, f32) {
+        /// The transform's translation, rotation, and uniform scale.
+        fn from(trf: Transform3D) -> Self {
+            (
+                glam::Vec3::from_array(trf.translation),
+                glam::Quat::from_array(trf.rotation),
+                trf.scale,
+            )
+        }
+    }
+
+    impl From<glam::Affine3A> for Transform3D {
+        /// Takes the translation and rotation of `affine` along with
+        /// the average of its scale along each axis, since
+        /// [`Transform3D`] only supports uniform scales.
+        fn from(affine: glam::Affine3A) -> Self {
+            let (scale, rotation, translation) = affine.to_scale_rotation_translation();
+            (translation, rotation, (scale.x + scale.y + scale.z) / 3.0).into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ultraviolet::Vec4;
+
+    fn close(a: impl Into<Vec4>, b: impl Into<Vec4>) -> bool {
+        (a.into() - b.into()).mag() < 1e-4
+    }
+    fn close3(a: [f32; 3], b: [f32; 3]) -> bool {
+        (Vec3::from(a) - Vec3::from(b)).mag() < 1e-4
+    }
+    fn transform3d() -> Transform3D {
+        Transform3D {
+            translation: [1.0, -2.0, 3.0],
+            scale: 2.5,
+            rotation: Rotor3::from_euler_angles(0.3, -0.7, 1.1).into_quaternion_array(),
+        }
+    }
+    fn camera3d() -> Camera3D {
+        Camera3D {
+            translation: [0.0, 2.0, 5.0],
+            near: 0.1,
+            far: 100.0,
+            rotation: Rotor3::from_rotation_xz(0.4).into_quaternion_array(),
+            aspect: 16.0 / 9.0,
+            fov: std::f32::consts::FRAC_PI_3,
+        }
+    }
+
+    #[test]
+    fn test_similarity_round_trip() {
+        let trf = transform3d();
+        let back = Transform3D::from(Similarity3::from(trf));
+        assert!(close3(back.translation, trf.translation));
+        assert!(close(back.rotation, trf.rotation));
+        assert_eq!(back.scale, trf.scale);
+        let unscaled = Transform3D::from(Isometry3::new(
+            Vec3::from(trf.translation),
+            Rotor3::from_quaternion_array(trf.rotation),
+        ));
+        assert_eq!(unscaled.scale, 1.0);
+    }
+    #[test]
+    fn test_transform3d_matrix() {
+        let trf = transform3d();
+        let point = Vec3::new(0.5, -1.0, 2.0);
+        let expected = Rotor3::from_quaternion_array(trf.rotation) * (point * trf.scale)
+            + Vec3::from(trf.translation);
+        let moved = Mat4::from(trf) * point.into_homogeneous_point();
+        assert!(close(moved, expected.into_homogeneous_point()));
+    }
+    #[test]
+    fn test_sprite_matrix() {
+        let trf = Transform {
+            w: 10,
+            h: 4,
+            x: 20.0,
+            y: 30.0,
+            rot: std::f32::consts::FRAC_PI_2,
+        };
+        let corner = Mat4::from(trf) * Vec4::new(0.5, 0.5, 0.0, 1.0);
+        assert!(close(corner, Vec4::new(18.0, 35.0, 0.0, 1.0)));
+    }
+    #[test]
+    fn test_camera2d_matrix() {
+        let camera = Camera2D {
+            screen_pos: [-40.0, 25.0],
+            screen_size: [320.0, 240.0],
+        };
+        let mat = Mat4::from(camera);
+        let [x, y] = camera.screen_pos;
+        let [w, h] = camera.screen_size;
+        assert!(close(
+            mat * Vec4::new(x, y, 0.0, 1.0),
+            Vec4::new(-1.0, -1.0, 0.0, 1.0)
+        ));
+        assert!(close(
+            mat * Vec4::new(x + w, y + h, 0.0, 1.0),
+            Vec4::new(1.0, 1.0, 0.0, 1.0)
+        ));
+        assert!(close(
+            mat * Vec4::new(x + w / 2.0, y + h / 2.0, 0.0, 1.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0)
+        ));
+    }
+    #[test]
+    fn test_camera3d_matrix() {
+        let camera = camera3d();
+        let mat = Mat4::from(camera);
+        let eye = Vec3::from(camera.translation);
+        let forward = Rotor3::from_quaternion_array(camera.rotation) * -Vec3::unit_z();
+        for distance in [1.0, 10.0, 50.0] {
+            let clip = mat * (eye + forward * distance).into_homogeneous_point();
+            let ndc = clip.truncated() / clip.w;
+            // Points straight ahead land in the middle of the screen
+            assert!(ndc.x.abs() < 1e-4 && ndc.y.abs() < 1e-4);
+            assert!(ndc.z > 0.0 && ndc.z < 1.0);
+        }
+        let behind = mat * (eye - forward).into_homogeneous_point();
+        assert!(behind.w < 0.0);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn test_glam_matches_ultraviolet() {
+        let same = |uv: Mat4, glam: glam::Mat4| {
+            uv.as_array()
+                .iter()
+                .zip(glam.to_cols_array())
+                .all(|(a, b)| (a - b).abs() < 1e-4)
+        };
+        let sprite = Transform {
+            w: 10,
+            h: 4,
+            x: 20.0,
+            y: 30.0,
+            rot: 0.6,
+        };
+        assert!(same(sprite.into(), sprite.into()));
+        let camera = Camera2D {
+            screen_pos: [-40.0, 25.0],
+            screen_size: [320.0, 240.0],
+        };
+        assert!(same(camera.into(), camera.into()));
+        assert!(same(transform3d().into(), transform3d().into()));
+        assert!(same(camera3d().into(), camera3d().into()));
+    }
+    #[cfg(feature = "glam")]
+    #[test]
+    fn test_glam_transforms() {
+        let trf = transform3d();
+        let parts: (glam::Vec3, glam::Quat, f32) = trf.into();
+        assert_eq!(Transform3D::from(parts), trf);
+        // Nonuniform scales are averaged
+        let affine = glam::Affine3A::from_scale_rotation_translation(
+            glam::Vec3::new(1.0, 2.0, 6.0),
+            parts.1,
+            parts.0,
+        );
+        let averaged = Transform3D::from(affine);
+        assert!((averaged.scale - 3.0).abs() < 1e-4);
+        assert!(close3(averaged.translation, trf.translation));
+        let rotation = Vec4::from(averaged.rotation);
+        // q and -q are the same rotation
+        assert!(close(rotation, trf.rotation) || close(-rotation, trf.rotation));
+    }
+}
@@ -5,18 +5,9 @@
 //! event loop and initializes both a window and the graphics context
 //! once the proper winit events have arrived.
 
+use crate::{EventPhase, PlatformEvent};
 use std::sync::Arc;
 
-/// Phase in the game event loop
-pub enum EventPhase {
-    /// The game should simulate time forward by the given number of steps and then render.  Typically the caller of [`FrendererEvents::handle_event`] should respond to this by calling `render` on the [`crate::frenderer::Renderer`].
-    Run(usize),
-    /// The game should terminate as quickly as possible and close the window.
-    Quit,
-    /// There's nothing in particular the game should do right now.
-    Wait,
-}
-
 /// This extension trait is used under the `winit` feature to simplify event-loop handling.
 pub trait FrendererEvents<T> {
     /// Call `handle_event` on your [`crate::frenderer::Renderer`]
@@ -45,19 +36,34 @@ impl<T> FrendererEvents<T> for crate::Renderer {
         match evt {
             Event::Resumed if self.surface().is_none() => {
                 self.create_surface(Arc::clone(window));
+                self.set_scale_factor(window.scale_factor() as f32);
+                EventPhase::Wait
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { scale_factor, .. },
+                ..
+            } => {
+                self.set_scale_factor(*scale_factor as f32);
+                window.request_redraw();
                 EventPhase::Wait
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => EventPhase::Quit,
+            } => self.handle_platform_event(clock, PlatformEvent::CloseRequested),
             winit::event::Event::WindowEvent {
                 event: winit::event::WindowEvent::Resized(size),
                 ..
             } => {
                 // For some reason on web this causes repeated increases in size.
                 if !self.gpu.is_web() {
-                    self.resize_surface(size.width, size.height);
+                    self.handle_platform_event(
+                        clock,
+                        PlatformEvent::Resized {
+                            width: size.width,
+                            height: size.height,
+                        },
+                    );
                 }
                 window.request_redraw();
                 EventPhase::Wait
@@ -65,12 +71,20 @@ impl<T> FrendererEvents<T> for crate::Renderer {
             Event::WindowEvent {
                 event: WindowEvent::RedrawRequested,
                 ..
-            } => {
-                let steps = clock.tick();
-                window.request_redraw();
-                EventPhase::Run(steps)
+            } => self.handle_platform_event(clock, PlatformEvent::RedrawRequested),
+            Event::AboutToWait => {
+                // By now the game has handled this batch of events
+                // and may have asked for another frame
+                if self.take_redraw_request() {
+                    window.request_redraw();
+                }
+                EventPhase::Wait
             }
             event => {
+                if matches!(event, Event::WindowEvent { .. }) {
+                    // Input (or e.g. focus changes) may change what's on screen
+                    self.request_redraw();
+                }
                 input.process_input_event(event);
                 EventPhase::Wait
             }
@@ -91,9 +105,34 @@ impl<T> FrendererEvents<T> for crate::Immediate {
     }
 }
 /// Driver takes ownership of winit's event loop and creates a window and graphics context when possible.
+///
+/// It's configured builder-style: start from [`Driver::new`] and
+/// chain `with_` methods to choose e.g. the GPU backends, power
+/// preference, present mode, and logging before calling
+/// [`Driver::run_event_loop`].
 pub struct Driver {
     builder: winit::window::WindowBuilder,
     render_size: Option<(u32, u32)>,
+    request: crate::DeviceRequest,
+    backends: wgpu::Backends,
+    present_mode: Option<wgpu::PresentMode>,
+    max_frame_latency: Option<u32>,
+    #[allow(clippy::type_complexity)]
+    logger: Option<fn() -> Result<(), crate::FrendererError>>,
+    overlay: OverlayOptions,
+    render_on_demand: bool,
+}
+
+/// Window options for desktop overlays and widgets, see [`Driver::with_overlay`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OverlayOptions {
+    /// Show whatever is behind the window wherever nothing is drawn;
+    /// see [`crate::Renderer::set_transparent`]
+    pub transparent: bool,
+    /// Keep the window above other windows
+    pub always_on_top: bool,
+    /// Let mouse clicks pass through the window to whatever is behind it
+    pub click_through: bool,
 }
 #[cfg(all(target_arch = "wasm32", feature = "winit"))]
 pub mod web_error {
@@ -124,6 +163,87 @@ impl Driver {
         Self {
             builder,
             render_size,
+            request: crate::DeviceRequest::default(),
+            backends: wgpu::Backends::all(),
+            present_mode: None,
+            max_frame_latency: None,
+            logger: Some(prepare_logging),
+            overlay: OverlayOptions::default(),
+            render_on_demand: false,
+        }
+    }
+    /// Render at the given size rather than the window's inner size.
+    pub fn with_render_size(self, render_size: (u32, u32)) -> Self {
+        Self {
+            render_size: Some(render_size),
+            ..self
+        }
+    }
+    /// Only consider adapters from the given backends, e.g.
+    /// [`wgpu::Backends::VULKAN`] or [`wgpu::Backends::GL`].  All
+    /// backends are considered by default.
+    pub fn with_backends(self, backends: wgpu::Backends) -> Self {
+        Self { backends, ..self }
+    }
+    /// Prefer a low-power or high-performance adapter; shorthand for
+    /// setting [`crate::DeviceRequest::power_preference`].
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.request.power_preference = power_preference;
+        self
+    }
+    /// Start the renderer with the given present mode instead of
+    /// [`wgpu::PresentMode::AutoVsync`], see
+    /// [`crate::Renderer::set_present_mode`].
+    pub fn with_present_mode(self, present_mode: wgpu::PresentMode) -> Self {
+        Self {
+            present_mode: Some(present_mode),
+            ..self
+        }
+    }
+    /// Start the renderer letting at most `frames` frames queue up
+    /// ahead of the display instead of 2, see
+    /// [`crate::Renderer::set_max_frame_latency`].
+    pub fn with_max_frame_latency(self, frames: u32) -> Self {
+        Self {
+            max_frame_latency: Some(frames),
+            ..self
+        }
+    }
+    /// Set up logging with `logger` when the event loop starts,
+    /// instead of [`prepare_logging`].  Pass `None` if the
+    /// application has already installed its own logger.
+    #[allow(clippy::type_complexity)]
+    pub fn with_logger(self, logger: Option<fn() -> Result<(), crate::FrendererError>>) -> Self {
+        Self { logger, ..self }
+    }
+    /// Request the given GPU features and limits when creating the
+    /// renderer; check [`crate::WGPU::device_report`] to see what was granted.
+    pub fn with_device_request(self, request: crate::DeviceRequest) -> Self {
+        Self { request, ..self }
+    }
+    /// Build the window as an overlay with the given options.  Not
+    /// every platform supports every option; unsupported ones are
+    /// logged as warnings and ignored.
+    pub fn with_overlay(self, overlay: OverlayOptions) -> Self {
+        let mut builder = self.builder;
+        if overlay.transparent {
+            builder = builder.with_transparent(true);
+        }
+        if overlay.always_on_top {
+            builder = builder.with_window_level(winit::window::WindowLevel::AlwaysOnTop);
+        }
+        Self {
+            builder,
+            overlay,
+            ..self
+        }
+    }
+    /// Start the renderer in render-on-demand mode, see
+    /// [`crate::Renderer::set_render_on_demand`].
+    pub fn with_render_on_demand(self, render_on_demand: bool) -> Self {
+        Self {
+            render_on_demand,
+            ..self
         }
     }
     /// Kick off the event loop. Once the driver receives the
@@ -158,7 +278,7 @@ impl Driver {
         init_cb: impl FnOnce(std::sync::Arc<winit::window::Window>, crate::Renderer) -> U + 'static,
         mut handler: impl FnMut(winit::event::Event<T>, &winit::event_loop::EventLoopWindowTarget<T>, &mut U)
             + 'static,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), crate::FrendererError> {
         enum DriverState<U: 'static> {
             WaitingForResume(winit::window::WindowBuilder),
             PollingFuture(
@@ -167,7 +287,7 @@ impl Driver {
                 std::pin::Pin<
                     Box<
                         dyn std::future::Future<
-                            Output = Result<crate::Renderer, Box<dyn std::error::Error>>,
+                            Output = Result<crate::Renderer, crate::FrendererError>,
                         >,
                     >,
                 >,
@@ -180,11 +300,23 @@ impl Driver {
         let Self {
             builder,
             render_size,
+            request,
+            backends,
+            present_mode,
+            max_frame_latency,
+            logger,
+            overlay,
+            render_on_demand,
         } = self;
-        prepare_logging()?;
+        if let Some(logger) = logger {
+            logger()?;
+        }
         let event_loop: EventLoop<T> =
             winit::event_loop::EventLoopBuilder::with_user_event().build()?;
-        let instance = Arc::new(wgpu::Instance::default());
+        let instance = Arc::new(wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        }));
         let waker = Arc::new(NoopWaker()).into();
         let mut init_cb = Some(init_cb);
         let driver_state = std::cell::Cell::new(DriverState::WaitingForResume(builder));
@@ -195,17 +327,28 @@ impl Driver {
                     if let winit::event::Event::Resumed = event {
                         let window = Arc::new(builder.build(target).unwrap());
                         prepare_window(&window);
+                        if overlay.click_through {
+                            if let Err(err) = window.set_cursor_hittest(false) {
+                                log::warn!("Couldn't make the window click-through: {err}");
+                            }
+                        }
                         let surface = instance.create_surface(Arc::clone(&window)).unwrap();
                         let wsz = window.inner_size();
                         let sz = render_size.unwrap_or((wsz.width, wsz.height));
-                        let future = Box::pin(crate::Renderer::with_surface(
-                            sz.0,
-                            sz.1,
-                            wsz.width,
-                            wsz.height,
-                            Arc::clone(&instance),
-                            Some(surface),
-                        ));
+                        let request = request.clone();
+                        let instance = Arc::clone(&instance);
+                        let future = Box::pin(async move {
+                            crate::Renderer::with_request(
+                                sz.0,
+                                sz.1,
+                                wsz.width,
+                                wsz.height,
+                                instance,
+                                Some(surface),
+                                &request,
+                            )
+                            .await
+                        });
                         DriverState::PollingFuture(window, future)
                     } else {
                         DriverState::WaitingForResume(builder)
@@ -214,7 +357,18 @@ impl Driver {
                 DriverState::PollingFuture(window, mut future) => {
                     let mut cx = std::task::Context::from_waker(&waker);
                     if let std::task::Poll::Ready(frend) = future.as_mut().poll(&mut cx) {
-                        let frenderer = frend.unwrap();
+                        let mut frenderer = frend.unwrap();
+                        if overlay.transparent && !frenderer.set_transparent(true) {
+                            log::warn!("The surface doesn't support transparency");
+                        }
+                        if let Some(mode) = present_mode {
+                            frenderer.set_present_mode(mode);
+                        }
+                        if let Some(frames) = max_frame_latency {
+                            frenderer.set_max_frame_latency(frames);
+                        }
+                        frenderer.set_render_on_demand(render_on_demand);
+                        frenderer.set_scale_factor(window.scale_factor() as f32);
                         let userdata = init_cb.take().unwrap()(Arc::clone(&window), frenderer);
                         DriverState::Running(userdata)
                     } else {
@@ -272,16 +426,15 @@ pub fn prepare_window(window: &winit::window::Window) {
 /// If you don't use [`Driver`], it may still be convenient to call
 /// `prepare_logging` to set up `env_logger` or `console_log`
 /// appropriately for the platform.
-pub fn prepare_logging() -> Result<(), Box<dyn std::error::Error>> {
+pub fn prepare_logging() -> Result<(), crate::FrendererError> {
     #[cfg(not(target_arch = "wasm32"))]
     {
-        env_logger::init();
-        Ok(())
+        env_logger::try_init().map_err(|err| crate::FrendererError::Logging(err.to_string()))
     }
     #[cfg(target_arch = "wasm32")]
     {
         std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-        console_log::init_with_level(log::Level::Warn)?;
-        Ok(())
+        console_log::init_with_level(log::Level::Warn)
+            .map_err(|err| crate::FrendererError::Logging(err.to_string()))
     }
 }
@@ -0,0 +1,279 @@
+//! Exporting the 3D scene to a glTF file for debugging, under the
+//! `debug` feature.  [`scene_gltf`] (or
+//! [`crate::Renderer::export_scene_gltf`]) writes every mesh and flat
+//! group's geometry, each visible instance as a node with its
+//! transform, and the renderers' cameras, so a scene can be attached
+//! to a bug report and inspected in any glTF viewer.  Textures aren't
+//! exported, but textured meshes keep their UVs and flat meshes get
+//! their material colors as vertex colors.
+//!
+//! Groups keep a copy of their geometry on the CPU for this when the
+//! `debug` feature is enabled.
+
+use crate::meshes::{Camera3D, FlatRenderer, MeshRenderer, SubmeshData, Transform3D};
+use std::fmt::Write;
+
+/// Returns a self-contained glTF 2.0 document (JSON with an embedded
+/// buffer) of the given renderers' groups, instances, and cameras.
+/// Write it to a `.gltf` file to view it.
+pub fn scene_gltf(meshes: Option<&MeshRenderer>, flats: Option<&FlatRenderer>) -> String {
+    let mut writer = GltfWriter::default();
+    if let Some(meshes) = meshes {
+        writer.add_camera("mesh camera", &meshes.camera());
+        meshes.write_gltf(&mut writer);
+    }
+    if let Some(flats) = flats {
+        writer.add_camera("flat camera", &flats.camera());
+        flats.write_gltf(&mut writer);
+    }
+    writer.finish()
+}
+
+// A vertex as it's exported
+pub(crate) struct ExportVertex {
+    pub(crate) position: [f32; 3],
+    pub(crate) uv: Option<[f32; 2]>,
+    pub(crate) color: Option<[f32; 4]>,
+}
+
+// One mesh of a group: its submeshes and its visible instances'
+// indices, transforms, and per-axis scales
+pub(crate) struct ExportMesh<'a> {
+    pub(crate) submeshes: &'a [SubmeshData],
+    pub(crate) instances: Vec<(usize, Transform3D, [f32; 3])>,
+}
+
+// Accumulates the JSON objects and binary data of a glTF document
+#[derive(Default)]
+pub(crate) struct GltfWriter {
+    buffer: Vec<u8>,
+    buffer_views: Vec<String>,
+    accessors: Vec<String>,
+    meshes: Vec<String>,
+    nodes: Vec<String>,
+    cameras: Vec<String>,
+    // Nodes at the top of the scene
+    roots: Vec<usize>,
+}
+
+impl GltfWriter {
+    // Appends `data` to the buffer as a new buffer view, returning its index
+    fn buffer_view(&mut self, data: &[u8], target: u32) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+        self.buffer_views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{offset},"byteLength":{},"target":{target}}}"#,
+            data.len()
+        ));
+        self.buffer_views.len() - 1
+    }
+    // Adds an accessor of float vectors with `N` components, returning its index
+    fn float_accessor<const N: usize>(&mut self, data: &[[f32; N]], with_bounds: bool) -> usize {
+        const ARRAY_BUFFER: u32 = 34962;
+        let bytes: Vec<u8> = data
+            .iter()
+            .flatten()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+        let view = self.buffer_view(&bytes, ARRAY_BUFFER);
+        let kind = ["SCALAR", "VEC2", "VEC3", "VEC4"][N - 1];
+        let mut accessor = format!(
+            r#"{{"bufferView":{view},"componentType":5126,"count":{},"type":"{kind}""#,
+            data.len()
+        );
+        if with_bounds {
+            let (min, max) = data.iter().fold(
+                ([f32::INFINITY; N], [f32::NEG_INFINITY; N]),
+                |(mut min, mut max), v| {
+                    for i in 0..N {
+                        min[i] = min[i].min(v[i]);
+                        max[i] = max[i].max(v[i]);
+                    }
+                    (min, max)
+                },
+            );
+            write!(
+                accessor,
+                r#","min":{},"max":{}"#,
+                json_floats(&min),
+                json_floats(&max)
+            )
+            .unwrap();
+        }
+        accessor.push('}');
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+    // Adds an accessor of vertex indices, returning its index
+    fn index_accessor(&mut self, indices: &[u32]) -> usize {
+        const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+        let view = self.buffer_view(bytemuck::cast_slice(indices), ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(format!(
+            r#"{{"bufferView":{view},"componentType":5125,"count":{},"type":"SCALAR"}}"#,
+            indices.len()
+        ));
+        self.accessors.len() - 1
+    }
+    fn node(&mut self, json: String) -> usize {
+        self.nodes.push(json);
+        self.nodes.len() - 1
+    }
+    // Adds a group's geometry as one glTF mesh per mesh and a node per
+    // visible instance, all under a node named `name`
+    pub(crate) fn add_group(
+        &mut self,
+        name: &str,
+        vertices: &[ExportVertex],
+        indices: &[u32],
+        meshes: &[ExportMesh],
+    ) {
+        if vertices.is_empty() {
+            return;
+        }
+        let positions: Vec<_> = vertices.iter().map(|v| v.position).collect();
+        let mut attributes = format!(r#""POSITION":{}"#, self.float_accessor(&positions, true));
+        if let Some(uvs) = vertices.iter().map(|v| v.uv).collect::<Option<Vec<_>>>() {
+            let uvs = self.float_accessor(&uvs, false);
+            write!(attributes, r#","TEXCOORD_0":{uvs}"#).unwrap();
+        }
+        if let Some(colors) = vertices.iter().map(|v| v.color).collect::<Option<Vec<_>>>() {
+            let colors = self.float_accessor(&colors, false);
+            write!(attributes, r#","COLOR_0":{colors}"#).unwrap();
+        }
+        let mut children = vec![];
+        for (mesh_idx, mesh) in meshes.iter().enumerate() {
+            if mesh.instances.is_empty() {
+                continue;
+            }
+            let primitives: Vec<_> = mesh
+                .submeshes
+                .iter()
+                .filter(|sm| !sm.indices.is_empty())
+                .map(|sm| {
+                    // glTF has no base vertex, so rebase the indices themselves
+                    let rebased: Vec<u32> = indices
+                        [sm.indices.start as usize..sm.indices.end as usize]
+                        .iter()
+                        .map(|&idx| (idx as i64 + sm.vertex_base as i64) as u32)
+                        .collect();
+                    let accessor = self.index_accessor(&rebased);
+                    format!(r#"{{"attributes":{{{attributes}}},"indices":{accessor},"mode":4}}"#)
+                })
+                .collect();
+            if primitives.is_empty() {
+                continue;
+            }
+            self.meshes.push(format!(
+                r#"{{"name":"{name} mesh {mesh_idx}","primitives":[{}]}}"#,
+                primitives.join(",")
+            ));
+            let gltf_mesh = self.meshes.len() - 1;
+            for (inst_idx, trf, scale) in mesh.instances.iter() {
+                let scale = scale.map(|s| s * trf.scale);
+                let node = self.node(format!(
+                    r#"{{"name":"{name} mesh {mesh_idx} instance {inst_idx}","mesh":{gltf_mesh},"translation":{},"rotation":{},"scale":{}}}"#,
+                    json_floats(&trf.translation),
+                    json_floats(&unit_quaternion(trf.rotation)),
+                    json_floats(&scale),
+                ));
+                children.push(node);
+            }
+        }
+        // glTF doesn't allow empty lists of children
+        let root = if children.is_empty() {
+            self.node(format!(r#"{{"name":"{name}"}}"#))
+        } else {
+            let children: Vec<_> = children.iter().map(usize::to_string).collect();
+            self.node(format!(
+                r#"{{"name":"{name}","children":[{}]}}"#,
+                children.join(",")
+            ))
+        };
+        self.roots.push(root);
+    }
+    // Adds a perspective camera node, unless the camera is degenerate
+    fn add_camera(&mut self, name: &str, camera: &Camera3D) {
+        if !(camera.fov > 0.0 && camera.aspect > 0.0 && camera.near > 0.0)
+            || camera.far <= camera.near
+        {
+            return;
+        }
+        self.cameras.push(format!(
+            r#"{{"name":"{name}","type":"perspective","perspective":{{"yfov":{:?},"aspectRatio":{:?},"znear":{:?},"zfar":{:?}}}}}"#,
+            camera.fov, camera.aspect, camera.near, camera.far
+        ));
+        let node = self.node(format!(
+            r#"{{"name":"{name}","camera":{},"translation":{},"rotation":{}}}"#,
+            self.cameras.len() - 1,
+            json_floats(&camera.translation),
+            json_floats(&unit_quaternion(camera.rotation)),
+        ));
+        self.roots.push(node);
+    }
+    fn finish(self) -> String {
+        let roots: Vec<_> = self.roots.iter().map(usize::to_string).collect();
+        let mut json = format!(
+            r#"{{"asset":{{"version":"2.0","generator":"frenderer"}},"scene":0,"scenes":[{{"nodes":[{}]}}]"#,
+            roots.join(",")
+        );
+        for (key, items) in [
+            ("nodes", &self.nodes),
+            ("meshes", &self.meshes),
+            ("cameras", &self.cameras),
+            ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+        ] {
+            if !items.is_empty() {
+                write!(json, r#","{key}":[{}]"#, items.join(",")).unwrap();
+            }
+        }
+        if !self.buffer.is_empty() {
+            write!(
+                json,
+                r#","buffers":[{{"byteLength":{},"uri":"data:application/octet-stream;base64,{}"}}]"#,
+                self.buffer.len(),
+                base64(&self.buffer)
+            )
+            .unwrap();
+        }
+        json.push('}');
+        json
+    }
+}
+
+// A JSON array of floats
+fn json_floats(values: &[f32]) -> String {
+    let values: Vec<_> = values.iter().map(|v| format!("{v:?}")).collect();
+    format!("[{}]", values.join(","))
+}
+
+// glTF requires unit rotations; zeroed ones become the identity
+fn unit_quaternion(q: [f32; 4]) -> [f32; 4] {
+    let len = q.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if len > 0.0 {
+        q.map(|c| c / len)
+    } else {
+        [0.0, 0.0, 0.0, 1.0]
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
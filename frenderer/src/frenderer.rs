@@ -28,12 +28,10 @@
 use crate::{
     colorgeo::{self, ColorGeo},
     sprites::SpriteRenderer,
+    units::{RenderPx, SurfacePx, WorldPos},
     WGPU,
 };
-use std::{
-    ops::{Range, RangeBounds},
-    sync::Arc,
-};
+use std::ops::{Range, RangeBounds};
 
 pub use crate::meshes::{FlatRenderer, MeshRenderer};
 
@@ -41,6 +39,45 @@ pub trait Frenderer {
     fn render(&mut self);
 }
 
+/// Disjoint mutable views of a [`Renderer`]'s sprite, mesh, and flat
+/// mesh groups, obtained via [`Renderer::groups_mut`].  Each view can
+/// be sent to a different thread.
+pub struct GroupsMut<'a> {
+    /// Each populated sprite group's index, world transforms, and sheet regions
+    pub sprites: Vec<(
+        usize,
+        &'a mut [crate::sprites::Transform],
+        &'a mut [crate::sprites::SheetRegion],
+    )>,
+    /// Each populated mesh group and the instance transforms of each of its meshes
+    pub meshes: Vec<(
+        crate::meshes::MeshGroup,
+        Vec<&'a mut [crate::meshes::Transform3D]>,
+    )>,
+    /// Each populated flat mesh group and the instance transforms of each of its meshes
+    pub flats: Vec<(
+        crate::meshes::MeshGroup,
+        Vec<&'a mut [crate::meshes::Transform3D]>,
+    )>,
+}
+
+/// A rendered frame read back from the GPU with [`Renderer::capture_frame`].
+pub struct CapturedFrame {
+    /// Width of the frame in pixels
+    pub width: u32,
+    /// Height of the frame in pixels
+    pub height: u32,
+    /// Tightly packed RGBA8 pixels, row by row from the top
+    pub rgba: Vec<u8>,
+}
+
+/// A function called on a sprite group's transforms and sheet
+/// regions with the seconds elapsed since the last frame; see
+/// [`Renderer::sprite_group_set_callback`].
+pub type SpriteGroupCallback = Box<
+    dyn FnMut(&mut [crate::sprites::Transform], &mut [crate::sprites::SheetRegion], f32) + Send,
+>;
+
 /// A wrapper over GPU state, surface, depth texture, and some renderers.
 #[allow(dead_code)]
 pub struct Renderer {
@@ -53,13 +90,80 @@ pub struct Renderer {
     depth_texture_view: wgpu::TextureView,
     color_texture: wgpu::Texture,
     color_texture_view: wgpu::TextureView,
+    // The selection mask outlined in postprocessing, while there's an outline
+    mask_texture: Option<(wgpu::Texture, wgpu::TextureView)>,
+    // A copy of the last frame drawn, while frame history is on, and
+    // the sprite groups drawing from it
+    history: Option<wgpu::Texture>,
+    history_groups: Vec<usize>,
     // These ones are tracked for auto uploading of assets and automatic rendering.
     // You can make your own renderers and use them for more control.
     sprites: SpriteRenderer,
     meshes: MeshRenderer,
     flats: FlatRenderer,
+    // Drawn before the mesh groups through the textured mesh camera
+    terrain: Option<crate::terrain::TerrainRenderer>,
+    // The scene mirrored for water groups, and how long their ripples have scrolled
+    reflection: Option<Reflection>,
+    reflection_time: f32,
     postprocess: ColorGeo,
     queued_uploads: Vec<Upload>,
+    sprite_reclaim: Option<crate::sprites::ReclaimPolicy>,
+    sprite_sparse_warning: Option<crate::sprites::SparseWarning>,
+    sprite_callbacks: Vec<Option<SpriteGroupCallback>>,
+    last_frame: Option<crate::clock::Instant>,
+    scale_mode: ScaleMode,
+    clear_color: wgpu::Color,
+    transparent: bool,
+    stereo: Option<Stereo>,
+    scale_factor: f32,
+    pub(crate) render_on_demand: bool,
+    pub(crate) redraw_requested: bool,
+    // Reused by SpriteGroupWriter to avoid reallocating every frame
+    sprite_staging: (
+        Vec<crate::sprites::Transform>,
+        Vec<crate::sprites::SheetRegion>,
+    ),
+    gpu_timer: Option<timing::GpuTimer>,
+    transition: Option<ActiveTransition>,
+    post_fade: Option<PostFade>,
+    upload_fences: Vec<UploadFence>,
+    // The format render_with postprocesses into, see set_postprocess_format
+    target_format: Option<wgpu::TextureFormat>,
+    // The frame being captured since capture_frame_graph, and the last one captured
+    frame_graph_recording: Option<FrameGraph>,
+    frame_graph: Option<FrameGraph>,
+    latency: std::sync::Arc<latency::LatencyTracker>,
+}
+
+// The scene mirrored about a plane for water groups, see Renderer::reflection_set
+struct Reflection {
+    plane: crate::meshes::ReflectionPlane,
+    color: (wgpu::Texture, wgpu::TextureView),
+    depth: (wgpu::Texture, wgpu::TextureView),
+}
+
+// A scene transition in progress, see Renderer::start_transition
+#[derive(Clone, Copy, Debug)]
+struct ActiveTransition {
+    transition: crate::colorgeo::Transition,
+    seconds: f32,
+    // None until the first frame of the transition is drawn
+    elapsed: Option<f32>,
+}
+
+// A postprocessing fade in progress, see Renderer::post_fade_to
+#[derive(Clone, Copy, Debug)]
+struct PostFade {
+    // The transform, color transform, and saturation at each end
+    from: ([f32; 16], [f32; 16], f32),
+    to: ([f32; 16], [f32; 16], f32),
+    // The LUT blend at the start, if the fade is blending towards a new LUT
+    lut_from: Option<f32>,
+    seconds: f32,
+    easing: crate::colorgeo::Easing,
+    // None until the first frame of the fade is drawn
+    elapsed: Option<f32>,
 }
 
 #[derive(Debug)]
@@ -69,7 +173,116 @@ enum Upload {
     Sprite(usize, Range<usize>),
 }
 
+/// How the rendered image is fit to the surface when their sizes
+/// or aspect ratios differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ScaleMode {
+    /// Stretch the image to fill the surface (the default)
+    #[default]
+    Stretch,
+    /// Scale the image up or down as far as it fits while keeping
+    /// its aspect ratio, leaving black bars around it
+    Fit,
+    /// Like [`ScaleMode::Fit`], but only scale up by whole-number
+    /// factors so that every pixel is the same size.  Falls back to
+    /// [`ScaleMode::Fit`] if the surface is smaller than the image.
+    IntegerFit,
+}
+
+/// Every bind group layout used by a [`Renderer`]'s pipelines, from
+/// [`Renderer::bind_group_layouts`], for custom pipelines which
+/// consume frenderer's cameras, textures, or postprocessing inputs.
+#[derive(Clone, Copy, Debug)]
+pub struct BindGroupLayouts<'a> {
+    /// The sprite renderer's layouts
+    pub sprites: crate::sprites::SpriteLayouts<'a>,
+    /// The textured mesh renderer's layouts
+    pub meshes: crate::meshes::MeshLayouts<'a>,
+    /// The flat mesh renderer's layouts
+    pub flats: crate::meshes::MeshLayouts<'a>,
+    /// The postprocessing phase's layouts
+    pub post: colorgeo::ColorGeoLayouts<'a>,
+}
+
+/// A rectangle in pixels with its origin at the top left, e.g. the
+/// part of the surface the rendered image is drawn into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    /// The left edge
+    pub x: f32,
+    /// The top edge
+    pub y: f32,
+    /// The width in pixels
+    pub width: f32,
+    /// The height in pixels
+    pub height: f32,
+}
+
+/// Whether the surface, as the user sees it, is wider or taller; see
+/// [`Renderer::orientation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// At least as wide as it is tall
+    Landscape,
+    /// Taller than it is wide
+    Portrait,
+}
+
+/// How [`Renderer::render`] lays out the two eyes' views when
+/// drawing in stereo, see [`Renderer::set_stereo`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum StereoLayout {
+    /// The left eye in the left half of the image and the right eye
+    /// in the right half, each squeezed to half width, e.g. for VR
+    /// headset previews or 3D TVs (the default)
+    #[default]
+    SideBySide,
+    /// A red/cyan anaglyph, with the left eye in the red channel and
+    /// the right eye in the green and blue channels
+    Anaglyph,
+}
+
+/// How one eye's view differs from the cameras set on the renderer.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct StereoEye {
+    /// Moves the mesh and flat camera, in the camera's own space
+    /// (so `x` is always towards the camera's right)
+    pub offset: [f32; 3],
+    /// Moves every sprite group's camera, in world space
+    pub sprite_offset: [f32; 2],
+}
+
+/// Settings for drawing the scene once per eye, see [`Renderer::set_stereo`].
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Stereo {
+    /// How the two views are combined into one image
+    pub layout: StereoLayout,
+    /// The left eye's camera offsets
+    pub left: StereoEye,
+    /// The right eye's camera offsets
+    pub right: StereoEye,
+}
+
+impl Stereo {
+    /// Eyes `eye_separation` world units apart in 3D and
+    /// `sprite_separation` world units apart in 2D, centered on the
+    /// renderer's cameras.
+    pub fn new(layout: StereoLayout, eye_separation: f32, sprite_separation: f32) -> Self {
+        let eye = |sign: f32| StereoEye {
+            offset: [sign * eye_separation / 2.0, 0.0, 0.0],
+            sprite_offset: [sign * sprite_separation / 2.0, 0.0],
+        };
+        Self {
+            layout,
+            left: eye(-1.0),
+            right: eye(1.0),
+        }
+    }
+}
+
 impl Renderer {
+    /// The format of the color texture the built-in renderers draw into.
+    pub const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
     /// The format used for depth textures within frenderer.
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
     /// Creates a [Renderer] and its internal [crate::gpu::WGPU] using a wgpu [wgpu::Instance] and [wgpu::Surface], along with the rendering resolution (`w`, `h`) and surface dimensions.
@@ -80,8 +293,63 @@ impl Renderer {
         surf_height: u32,
         instance: std::sync::Arc<wgpu::Instance>,
         surface: Option<wgpu::Surface<'static>>,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let gpu = WGPU::new(instance, surface.as_ref()).await?;
+    ) -> Result<Self, crate::FrendererError> {
+        Self::with_request(
+            width,
+            height,
+            surf_width,
+            surf_height,
+            instance,
+            surface,
+            &crate::DeviceRequest::default(),
+        )
+        .await
+    }
+    /// Like [`Renderer::with_surface`], but creates the instance and a
+    /// surface from raw window and display handles, for hosts which
+    /// don't expose a winit window or an existing [`wgpu::Surface`];
+    /// see [`Renderer::create_surface_from_raw`].
+    ///
+    /// # Safety
+    ///
+    /// The handles must be valid, and the window and display they
+    /// refer to must outlive the renderer's surface.
+    pub async unsafe fn with_raw_handles(
+        width: u32,
+        height: u32,
+        surf_width: u32,
+        surf_height: u32,
+        display: wgpu::rwh::RawDisplayHandle,
+        window: wgpu::rwh::RawWindowHandle,
+    ) -> Result<Self, crate::FrendererError> {
+        let instance = std::sync::Arc::new(wgpu::Instance::default());
+        let surface = instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+            raw_display_handle: display,
+            raw_window_handle: window,
+        })?;
+        Self::with_surface(
+            width,
+            height,
+            surf_width,
+            surf_height,
+            instance,
+            Some(surface),
+        )
+        .await
+    }
+    /// Like [`Renderer::with_surface`], but requesting the given GPU
+    /// features and limits; see [`WGPU::with_request`] and
+    /// [`WGPU::device_report`].
+    pub async fn with_request(
+        width: u32,
+        height: u32,
+        surf_width: u32,
+        surf_height: u32,
+        instance: std::sync::Arc<wgpu::Instance>,
+        surface: Option<wgpu::Surface<'static>>,
+        request: &crate::DeviceRequest,
+    ) -> Result<Self, crate::FrendererError> {
+        let gpu = WGPU::with_request(instance, surface.as_ref(), request).await?;
         Ok(Self::with_gpu(
             width,
             height,
@@ -111,7 +379,7 @@ impl Renderer {
             .formats
             .first()
             .unwrap_or(&wgpu::TextureFormat::Rgba8Unorm);
-        let swapchain_format_srgb = swapchain_format.add_srgb_suffix();
+        let output_format = crate::colorgeo::ColorSpace::default().output_format(*swapchain_format);
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -124,21 +392,17 @@ impl Renderer {
             },
             present_mode: wgpu::PresentMode::AutoVsync,
             alpha_mode: swapchain_capabilities.alpha_modes[0],
-            view_formats: vec![*swapchain_format, swapchain_format_srgb],
+            view_formats: surface_view_formats(*swapchain_format),
             desired_maximum_frame_latency: 2,
         };
 
         if let Some(surface) = surface.as_ref() {
             surface.configure(gpu.device(), &config)
         };
-        let (color_texture, color_texture_view) = Self::create_color_texture(
-            gpu.device(),
-            width,
-            height,
-            wgpu::TextureFormat::Rgba8Unorm,
-        );
+        let (color_texture, color_texture_view) =
+            Self::create_color_texture(gpu.device(), width, height, Self::COLOR_FORMAT, "color");
         let lut = colorgeo::lut_identity(&gpu);
-        let postprocess = ColorGeo::new(&gpu, &color_texture, &lut, swapchain_format_srgb.into());
+        let postprocess = ColorGeo::new(&gpu, &color_texture, &lut, output_format.into());
         let (depth_texture, depth_texture_view) =
             Self::create_depth_texture(gpu.device(), width, height);
 
@@ -173,9 +437,35 @@ impl Renderer {
             sprites,
             meshes,
             flats,
+            terrain: None,
+            reflection: None,
+            reflection_time: 0.0,
             queued_uploads: Vec::with_capacity(16),
+            sprite_reclaim: None,
+            sprite_sparse_warning: None,
+            sprite_callbacks: vec![],
+            last_frame: None,
+            scale_mode: ScaleMode::default(),
+            clear_color: wgpu::Color::BLACK,
+            transparent: false,
+            stereo: None,
+            scale_factor: 1.0,
+            render_on_demand: false,
+            redraw_requested: false,
+            sprite_staging: (vec![], vec![]),
+            gpu_timer: None,
+            transition: None,
+            post_fade: None,
+            upload_fences: vec![],
+            target_format: None,
+            frame_graph_recording: None,
+            frame_graph: None,
+            latency: Default::default(),
             color_texture,
             color_texture_view,
+            history: None,
+            history_groups: vec![],
+            mask_texture: None,
         }
     }
     /// Change the presentation mode used by the swapchain
@@ -187,26 +477,131 @@ impl Renderer {
     pub fn surface(&self) -> Option<&wgpu::Surface<'static>> {
         self.surface.as_ref()
     }
-    /// Creates a new surface for this renderer
-    pub fn create_surface(&mut self, window: Arc<winit::window::Window>) {
-        let surface = self.gpu.instance().create_surface(window).unwrap();
+    /// Creates a new surface for this renderer.  `target` is usually
+    /// an `Arc` of a window from winit, SDL2, tao, or any other
+    /// windowing library implementing `raw-window-handle`'s traits.
+    pub fn create_surface(&mut self, target: impl Into<wgpu::SurfaceTarget<'static>>) {
+        let surface = self.gpu.instance().create_surface(target).unwrap();
+        self.install_surface(surface);
+    }
+    /// Like [`Renderer::create_surface`], but for hosts (e.g. plugin
+    /// APIs) which only hand out raw window and display handles.
+    /// The handle types are re-exported as `frenderer::wgpu::rwh`.
+    /// Fails if no backend supports the handles.
+    ///
+    /// # Safety
+    ///
+    /// The handles must be valid, and the window and display they
+    /// refer to must outlive the renderer's surface.
+    pub unsafe fn create_surface_from_raw(
+        &mut self,
+        display: wgpu::rwh::RawDisplayHandle,
+        window: wgpu::rwh::RawWindowHandle,
+    ) -> Result<(), crate::FrendererError> {
+        let surface =
+            self.gpu
+                .instance()
+                .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+                    raw_display_handle: display,
+                    raw_window_handle: window,
+                })?;
+        self.install_surface(surface);
+        Ok(())
+    }
+    fn install_surface(&mut self, surface: wgpu::Surface<'static>) {
         let swapchain_capabilities = surface.get_capabilities(self.gpu.adapter());
         let swapchain_format = swapchain_capabilities.formats[0];
-        let swapchain_format_srgb = swapchain_format.add_srgb_suffix();
 
         self.config = wgpu::SurfaceConfiguration {
             format: swapchain_format,
-            alpha_mode: swapchain_capabilities.alpha_modes[0],
-            view_formats: vec![swapchain_format, swapchain_format_srgb],
+            alpha_mode: self
+                .transparent_alpha_mode(&swapchain_capabilities)
+                .unwrap_or(swapchain_capabilities.alpha_modes[0]),
+            view_formats: surface_view_formats(swapchain_format),
             ..self.config
         };
-        self.postprocess.set_color_target(
-            &self.gpu,
-            (*self.config.view_formats.last().unwrap()).into(),
-        );
+        self.postprocess
+            .set_color_target(&self.gpu, self.output_format().into());
         self.surface = Some(surface);
         self.configure_surface();
     }
+    /// Returns the color space the renderers blend in; see [`crate::colorgeo::ColorSpace`].
+    pub fn color_space(&self) -> crate::colorgeo::ColorSpace {
+        self.postprocess.color_space()
+    }
+    /// Sets the color space the renderers blend in.  Textures created
+    /// from images should use [`Renderer::texture_format`] to match.
+    pub fn set_color_space(&mut self, color_space: crate::colorgeo::ColorSpace) {
+        self.postprocess.set_color_space(&self.gpu, color_space);
+        if self.surface.is_some() {
+            self.postprocess
+                .set_color_target(&self.gpu, self.output_format().into());
+        }
+    }
+    /// Returns the color the rendered image is cleared to before drawing.
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+    /// Sets the color the rendered image is cleared to before
+    /// drawing (black by default).  Its alpha only matters when the
+    /// renderer is transparent; see [`Renderer::set_transparent`].
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+    }
+    /// Returns whether undrawn parts of the surface are transparent.
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+    /// Makes undrawn parts of the surface transparent (e.g. for a
+    /// desktop overlay in a window built with
+    /// `WindowBuilder::with_transparent`) or opaque again, setting
+    /// the clear color's alpha to match.  Returns false and stays
+    /// opaque if the surface can't be composited with alpha.
+    pub fn set_transparent(&mut self, transparent: bool) -> bool {
+        let caps = self
+            .surface
+            .as_ref()
+            .map(|s| s.get_capabilities(self.gpu.adapter()))
+            .unwrap_or_default();
+        self.transparent = transparent;
+        let alpha_mode = self.transparent_alpha_mode(&caps);
+        self.transparent = alpha_mode.is_some();
+        let output_alpha = match alpha_mode {
+            Some(wgpu::CompositeAlphaMode::PreMultiplied) => colorgeo::OutputAlpha::Premultiplied,
+            Some(_) => colorgeo::OutputAlpha::Straight,
+            None => colorgeo::OutputAlpha::Opaque,
+        };
+        self.postprocess.set_output_alpha(&self.gpu, output_alpha);
+        self.clear_color.a = if self.transparent { 0.0 } else { 1.0 };
+        if let Some(&mode) = alpha_mode.as_ref().or(caps.alpha_modes.first()) {
+            self.config.alpha_mode = mode;
+            self.configure_surface();
+        }
+        self.transparent == transparent
+    }
+    // The surface alpha mode to use if the renderer is transparent and the surface supports one
+    fn transparent_alpha_mode(
+        &self,
+        caps: &wgpu::SurfaceCapabilities,
+    ) -> Option<wgpu::CompositeAlphaMode> {
+        let modes = [
+            wgpu::CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied,
+        ];
+        modes
+            .into_iter()
+            .find(|mode| self.transparent && caps.alpha_modes.contains(mode))
+    }
+    /// The format to create textures from ordinary 8-bit RGBA images
+    /// with in the current color space, e.g. for
+    /// [`Renderer::create_array_texture`].
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        self.color_space().texture_format()
+    }
+    // The format of the surface view postprocessing draws into
+    fn output_format(&self) -> wgpu::TextureFormat {
+        self.color_space().output_format(self.config.format)
+    }
     fn configure_surface(&mut self) {
         if let Some(surface) = self.surface.as_ref() {
             surface.configure(self.gpu.device(), &self.config);
@@ -223,14 +618,33 @@ impl Renderer {
         self.render_width = w;
         self.render_height = h;
         let (color_texture, color_texture_view) =
-            Self::create_color_texture(self.gpu.device(), w, h, self.config.format);
+            Self::create_color_texture(self.gpu.device(), w, h, Self::COLOR_FORMAT, "color");
         self.color_texture = color_texture;
         self.color_texture_view = color_texture_view;
         self.postprocess
             .replace_color_texture(&self.gpu, &self.color_texture);
+        if self.history.is_some() {
+            self.replace_history(Some(self.create_history_texture()));
+        }
+        if self.mask_texture.is_some() {
+            self.mask_texture = Some(Self::create_color_texture(
+                self.gpu.device(),
+                w,
+                h,
+                crate::meshes::MASK_FORMAT,
+                "selection_mask",
+            ));
+            self.postprocess.replace_mask_texture(
+                &self.gpu,
+                self.mask_texture.as_ref().map(|(texture, _)| texture),
+            );
+        }
         let (depth_tex, depth_view) = Self::create_depth_texture(self.gpu.device(), w, h);
         self.depth_texture = depth_tex;
         self.depth_texture_view = depth_view;
+        if let Some(reflection) = self.reflection.as_ref() {
+            self.reflection_set(Some(reflection.plane));
+        }
     }
     fn create_depth_texture(
         device: &wgpu::Device,
@@ -261,6 +675,7 @@ impl Renderer {
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
+        label: &str,
     ) -> (wgpu::Texture, wgpu::TextureView) {
         let size = wgpu::Extent3d {
             width,
@@ -268,13 +683,15 @@ impl Renderer {
             depth_or_array_layers: 1,
         };
         let desc = wgpu::TextureDescriptor {
-            label: Some("color"),
+            label: Some(label),
             size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[format],
         };
         let texture = device.create_texture(&desc);
@@ -286,14 +703,21 @@ impl Renderer {
     /// time [`Renderer::do_uploads`] was called.  Call this manually if you
     /// want, or let [`Renderer::render`] call it automatically.
     pub fn do_uploads(&mut self) {
-        for upload in self.queued_uploads.drain(..) {
+        trace_span!("frenderer:uploads", count = self.queued_uploads.len());
+        // Taken out so uploads can be recorded while draining, then
+        // put back to keep its allocation
+        self.sprites.upload_cameras(&self.gpu);
+        let mut uploads = std::mem::take(&mut self.queued_uploads);
+        for upload in uploads.drain(..) {
             log::info!("upload: {upload:?}");
+            self.record_upload(&upload);
             match upload {
                 Upload::Mesh(mg, m, r) => self.meshes.upload_meshes(&self.gpu, mg, m, r),
                 Upload::Flat(mg, m, r) => self.flats.upload_meshes(&self.gpu, mg, m, r),
                 Upload::Sprite(s, r) => self.sprites.upload_sprites(&self.gpu, s, r),
             }
         }
+        self.queued_uploads = uploads;
     }
 
     /// Acquire the next frame, create a [`wgpu::RenderPass`], draw
@@ -302,50 +726,392 @@ impl Renderer {
     /// [`Renderer::render`] in your code be sure to call [`Renderer::do_uploads`] if you're
     /// using the built-in mesh, flat, or sprite renderers.
     pub fn render(&mut self) {
-        self.do_uploads();
+        trace_span!("frenderer:frame");
+        self.prepare_frame();
         let Some((frame, view, mut encoder)) = self.render_setup() else {
             return;
         };
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.color_texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
+        self.reflection_pass();
+        // Taken out for the frame so the passes can borrow it while
+        // the stereo passes change cameras
+        let mut timer = self.gpu_timer.take();
+        let mut queries = timer
+            .as_mut()
+            .and_then(|timer| timer.begin_frame(&self.gpu));
+        match self.stereo {
+            Some(stereo) => self.stereo_passes(stereo, queries.as_mut()),
+            None => self.main_pass(
+                &mut encoder,
+                &self.color_texture_view,
+                &self.depth_texture_view,
+                queries.as_mut(),
+            ),
+        }
+        self.mask_pass(&mut encoder);
+        self.use_post_format(self.output_format());
+        self.post_pass(&mut encoder, &view, Some(self.viewport()), queries.as_mut());
+        self.history_copy(&mut encoder);
+        let spans = queries.map(|queries| queries.resolve(&mut encoder));
+        self.finish_frame_graph(false, "surface");
+        self.render_finish(frame, encoder);
+        if let (Some(timer), Some(spans)) = (timer.as_mut(), spans) {
+            timer.read_back(spans);
+        }
+        self.gpu_timer = timer;
+    }
+    /// Like [`Renderer::render`], but draws into caller-supplied
+    /// targets using the caller's encoder (e.g. for an OpenXR
+    /// compositor) instead of the surface.  The encoder is neither
+    /// submitted nor is any frame presented.
+    ///
+    /// If `depth_view` is given, the built-in renderers draw
+    /// directly into `color_view` and `depth_view` without
+    /// postprocessing; the views must have the formats
+    /// [`Renderer::COLOR_FORMAT`] and [`Renderer::DEPTH_FORMAT`].
+    /// Otherwise they draw into the renderer's own color and depth
+    /// textures, which are then postprocessed into `color_view`; see
+    /// [`Renderer::set_postprocess_format`] to match its format.
+    /// Frame history is only kept in the latter case.
+    pub fn render_with(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        depth_view: Option<&wgpu::TextureView>,
+    ) {
+        self.prepare_frame();
+        self.reflection_pass();
+        match depth_view {
+            Some(depth_view) => self.main_pass(encoder, color_view, depth_view, None),
+            None => {
+                self.main_pass(
+                    encoder,
+                    &self.color_texture_view,
+                    &self.depth_texture_view,
+                    None,
+                );
+                self.mask_pass(encoder);
+                self.use_post_format(self.target_format.unwrap_or(self.output_format()));
+                self.post_pass(encoder, color_view, None, None);
+                self.history_copy(encoder);
+            }
+        }
+        self.finish_frame_graph(depth_view.is_some(), "target");
+    }
+    /// Set the color format [`Renderer::render_with`]'s
+    /// postprocessing pass draws into when it isn't given a depth
+    /// view.  [`Renderer::render`] always draws into the surface's
+    /// format, and the renderers' own color and depth textures keep
+    /// their formats either way.
+    ///
+    /// The postprocessing pipeline (just that one pipeline) is
+    /// remade whenever a frame's target format differs from the last
+    /// frame's, so alternating [`Renderer::render`] and
+    /// [`Renderer::render_with`] every frame with different formats
+    /// remakes it twice a frame.
+    pub fn set_postprocess_format(&mut self, format: wgpu::TextureFormat) {
+        self.target_format = Some(format);
+    }
+    // Remakes the postprocessing pipeline if it draws into another format
+    fn use_post_format(&mut self, format: wgpu::TextureFormat) {
+        if self.postprocess.color_target_format() != format {
+            self.postprocess.set_color_target(&self.gpu, format.into());
+        }
+    }
+    fn prepare_frame(&mut self) {
+        let now = crate::clock::Instant::now();
+        let dt = self
+            .last_frame
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        self.last_frame = Some(now);
+        self.advance_transition(dt);
+        self.reflection_time += dt;
+        self.meshes
+            .set_reflection_time(&self.gpu, self.reflection_time);
+        self.flats
+            .set_reflection_time(&self.gpu, self.reflection_time);
+        self.advance_post_fade(dt);
+        self.poll_upload_fences();
+        for (which, callback) in self.sprite_callbacks.iter_mut().enumerate() {
+            if let Some(callback) = callback {
+                let (trfs, uvs) = self.sprites.get_sprites_mut(which);
+                callback(trfs, uvs, dt);
+                self.queued_uploads
+                    .push(Upload::Sprite(which, 0..trfs.len()));
+            }
+        }
+        self.do_uploads();
+        if let Some(policy) = self.sprite_reclaim {
+            self.sprites.reclaim(&self.gpu, policy);
+        }
+        if let Some(warning) = self.sprite_sparse_warning {
+            self.sprites.warn_sparse_groups(warning);
+        }
+        self.meshes.maintain_pool(&self.gpu);
+        self.flats.maintain_pool(&self.gpu);
+    }
+    fn advance_transition(&mut self, dt: f32) {
+        let Some(active) = self.transition.as_mut() else {
+            return;
+        };
+        // Start counting from the first frame, however long ago the last one was
+        let elapsed = active.elapsed.map_or(0.0, |elapsed| elapsed + dt);
+        active.elapsed = Some(elapsed);
+        let progress = if active.seconds > 0.0 {
+            elapsed / active.seconds
+        } else {
+            1.0
+        };
+        if progress >= 1.0 {
+            self.postprocess.set_transition(&self.gpu, None, 1.0);
+            self.transition = None;
+        } else {
+            self.postprocess
+                .set_transition(&self.gpu, Some(active.transition), progress);
+            // Keep animating even when only rendering on demand
+            self.redraw_requested = true;
+        }
+    }
+    fn advance_post_fade(&mut self, dt: f32) {
+        let Some(fade) = self.post_fade.as_mut() else {
+            return;
+        };
+        let elapsed = fade.elapsed.map_or(0.0, |elapsed| elapsed + dt);
+        fade.elapsed = Some(elapsed);
+        let progress = if fade.seconds > 0.0 {
+            elapsed / fade.seconds
+        } else {
+            1.0
+        };
+        let t = fade.easing.apply(progress);
+        let lerp16 = |a: [f32; 16], b: [f32; 16]| std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t);
+        let fade = *fade;
+        let (from, to) = (fade.from, fade.to);
+        self.postprocess.set_post(
+            &self.gpu,
+            lerp16(from.0, to.0),
+            lerp16(from.1, to.1),
+            from.2 + (to.2 - from.2) * t,
+        );
+        if let Some(lut_from) = fade.lut_from {
+            self.postprocess
+                .set_lut_blend(&self.gpu, lut_from + (1.0 - lut_from) * t);
+        }
+        if progress >= 1.0 {
+            if fade.lut_from.is_some() {
+                self.postprocess.commit_next_lut(&self.gpu);
+            }
+            self.post_fade = None;
+        } else {
+            // Keep animating even when only rendering on demand
+            self.redraw_requested = true;
+        }
+    }
+    fn main_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        mut queries: Option<&mut FrameQueries>,
+    ) {
+        trace_span!("frenderer:main_pass");
+        let timestamp_writes = queries
+            .as_mut()
+            .and_then(|queries| queries.pass("main pass"));
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("frenderer:main_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
                 }),
-                ..Default::default()
-            });
-            self.render_into(&mut rpass);
+                stencil_ops: None,
+            }),
+            timestamp_writes,
+            ..Default::default()
+        });
+        self.render_into_timed(&mut rpass, queries);
+    }
+    // Draws each eye into its half of the color texture.  Camera
+    // uploads take effect at the next submit, so each eye gets its
+    // own encoder; the cameras are restored afterwards.
+    fn stereo_passes(&mut self, stereo: Stereo, mut queries: Option<&mut FrameQueries>) {
+        let sprite_cameras: Vec<_> = (0..self.sprites.sprite_group_count())
+            .map(|which| self.sprites.camera(which))
+            .collect();
+        let mesh_camera = self.meshes.camera();
+        let flat_camera = self.flats.camera();
+        let half_width = self.render_width as f32 / 2.0;
+        for (idx, eye) in [stereo.left, stereo.right].into_iter().enumerate() {
+            trace_span!("frenderer:stereo_pass", eye = idx);
+            for (which, camera) in sprite_cameras.iter().enumerate() {
+                if let Some(mut camera) = *camera {
+                    camera.screen_pos[0] += eye.sprite_offset[0];
+                    camera.screen_pos[1] += eye.sprite_offset[1];
+                    self.sprites.set_camera(&self.gpu, which, camera);
+                }
+            }
+            self.meshes
+                .set_camera(&self.gpu, offset_camera(mesh_camera, eye.offset));
+            self.flats
+                .set_camera(&self.gpu, offset_camera(flat_camera, eye.offset));
+            if let Some(terrain) = self.terrain.as_mut() {
+                terrain.set_camera(&self.gpu, offset_camera(mesh_camera, eye.offset));
+            }
+            let mut encoder =
+                self.gpu
+                    .device()
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("frenderer:stereo_encoder"),
+                    });
+            {
+                let eye_label = ["left eye pass", "right eye pass"][idx];
+                let timestamp_writes = queries
+                    .as_deref_mut()
+                    .and_then(|queries| queries.pass(eye_label));
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("frenderer:stereo_pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &self.color_texture_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            // Keep the left eye when drawing the right
+                            load: if idx == 0 {
+                                wgpu::LoadOp::Clear(self.clear_color)
+                            } else {
+                                wgpu::LoadOp::Load
+                            },
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.depth_texture_view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes,
+                    ..Default::default()
+                });
+                rpass.set_viewport(
+                    idx as f32 * half_width,
+                    0.0,
+                    half_width,
+                    self.render_height as f32,
+                    0.0,
+                    1.0,
+                );
+                self.render_into_timed(&mut rpass, queries.as_deref_mut());
+            }
+            self.gpu.queue().submit(Some(encoder.finish()));
         }
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
+        for (which, camera) in sprite_cameras.into_iter().enumerate() {
+            if let Some(camera) = camera {
+                self.sprites.set_camera(&self.gpu, which, camera);
+            }
+        }
+        self.meshes.set_camera(&self.gpu, mesh_camera);
+        self.flats.set_camera(&self.gpu, flat_camera);
+        if let Some(terrain) = self.terrain.as_mut() {
+            terrain.set_camera(&self.gpu, mesh_camera);
+        }
+    }
+    // Draws selected mesh and flat instances into the selection
+    // mask, if there's an outline.  Stereo frames leave it empty,
+    // since the mask covers the whole frame rather than each eye.
+    fn mask_pass(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some((_, mask_view)) = self.mask_texture.as_ref() else {
+            return;
+        };
+        trace_span!("frenderer:mask_pass");
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("frenderer:mask_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: mask_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        if self.stereo.is_none() {
+            self.meshes.render_selection_mask(&mut rpass, ..);
+            self.flats.render_selection_mask(&mut rpass, ..);
+        }
+    }
+    // Turns a viewport on the upright surface into the part of the
+    // actual surface it covers once the image is rotated
+    fn rotate_viewport(&self, vp: Viewport) -> Viewport {
+        let rotation = self.surface_rotation();
+        let (uw, uh) = self.upright_surface_size();
+        let (sw, sh) = self.surface_size();
+        let corner = |x: f32, y: f32| {
+            let ndc = [x / uw as f32 * 2.0 - 1.0, 1.0 - y / uh as f32 * 2.0];
+            let [rx, ry] = rotation.rotate_ndc(ndc);
+            [(rx + 1.0) / 2.0 * sw as f32, (1.0 - ry) / 2.0 * sh as f32]
+        };
+        let [x0, y0] = corner(vp.x, vp.y);
+        let [x1, y1] = corner(vp.x + vp.width, vp.y + vp.height);
+        Viewport {
+            x: x0.min(x1),
+            y: y0.min(y1),
+            width: (x1 - x0).abs(),
+            height: (y1 - y0).abs(),
+        }
+    }
+    fn post_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        viewport: Option<Viewport>,
+        queries: Option<&mut FrameQueries>,
+    ) {
+        // Only letterboxing modes leave parts of the target uncovered
+        let viewport = viewport.filter(|_| self.scale_mode != ScaleMode::Stretch);
+        trace_span!("frenderer:post_pass", letterboxed = viewport.is_some());
+        let timestamp_writes = queries.and_then(|queries| queries.pass("post pass"));
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("frenderer:post_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if viewport.is_some() {
+                        wgpu::LoadOp::Clear(if self.transparent {
+                            wgpu::Color::TRANSPARENT
+                        } else {
+                            wgpu::Color::BLACK
+                        })
+                    } else {
+                        wgpu::LoadOp::Load
                     },
-                })],
-                depth_stencil_attachment: None,
-                ..Default::default()
-            });
-            self.postprocess.render(&mut rpass);
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            ..Default::default()
+        });
+        if let Some(vp) = viewport.map(|vp| self.rotate_viewport(vp)) {
+            rpass.set_viewport(vp.x, vp.y, vp.width, vp.height, 0.0, 1.0);
         }
-        self.render_finish(frame, encoder);
+        #[cfg(feature = "debug")]
+        rpass.push_debug_group("postprocess");
+        self.postprocess.render(&mut rpass);
+        #[cfg(feature = "debug")]
+        rpass.pop_debug_group();
     }
     /// Renders all the frenderer stuff into a given
     /// [`wgpu::RenderPass`].  Just does rendering of the built-in
@@ -355,9 +1121,77 @@ impl Renderer {
     where
         's: 'pass,
     {
+        if let Some(terrain) = self.terrain.as_ref() {
+            #[cfg(feature = "debug")]
+            rpass.push_debug_group("terrain");
+            terrain.render(rpass);
+            #[cfg(feature = "debug")]
+            rpass.pop_debug_group();
+        }
+        #[cfg(feature = "debug")]
+        rpass.push_debug_group("meshes");
         self.meshes.render(rpass, ..);
+        #[cfg(feature = "debug")]
+        {
+            rpass.pop_debug_group();
+            rpass.push_debug_group("flats");
+        }
         self.flats.render(rpass, ..);
+        #[cfg(feature = "debug")]
+        {
+            rpass.pop_debug_group();
+            rpass.push_debug_group("sprites");
+        }
         self.sprites.render(rpass, ..);
+        #[cfg(feature = "debug")]
+        rpass.pop_debug_group();
+    }
+    /// Renders just the given sprite groups, in the order listed,
+    /// into a [`wgpu::RenderPass`], e.g. to draw world sprites and
+    /// UI sprites on either side of custom draws.  Like
+    /// [`Renderer::render_into`], this does no uploads.
+    pub fn sprite_render_groups<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        which: &[usize],
+    ) where
+        's: 'pass,
+    {
+        self.sprites.render_groups(rpass, which);
+    }
+    // Like render_into, but timing groups individually if queries are given
+    fn render_into_timed<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        queries: Option<&mut FrameQueries>,
+    ) where
+        's: 'pass,
+    {
+        let Some(queries) = queries else {
+            self.render_into(rpass);
+            return;
+        };
+        if let Some(terrain) = self.terrain.as_ref() {
+            terrain.render(rpass);
+        }
+        queries.groups(
+            rpass,
+            "mesh group",
+            self.meshes.mesh_group_count(),
+            |rpass, which| self.meshes.render(rpass, which),
+        );
+        queries.groups(
+            rpass,
+            "flat group",
+            self.flats.mesh_group_count(),
+            |rpass, which| self.flats.render(rpass, which),
+        );
+        queries.groups(
+            rpass,
+            "sprite group",
+            self.sprites.sprite_group_count(),
+            |rpass, which| self.sprites.render(rpass, which),
+        );
     }
     /// Convenience method for acquiring a surface texture, view, and
     /// command encoder.  If this returns `None` it means the surface isn't ready yet.
@@ -376,153 +1210,442 @@ impl Renderer {
             .get_current_texture()
             .expect("Failed to acquire next swap chain texture");
         let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
-            format: Some(self.config.view_formats[1]),
+            format: Some(self.output_format()),
             ..Default::default()
         });
         let encoder = self
             .gpu
             .device()
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("frenderer:frame"),
+            });
         Some((frame, view, encoder))
     }
     /// Convenience method for submitting a command encoder and
     /// presenting the swapchain image.
     pub fn render_finish(&self, frame: wgpu::SurfaceTexture, encoder: wgpu::CommandEncoder) {
+        trace_span!("frenderer:present");
         self.gpu.queue().submit(Some(encoder.finish()));
+        self.latency.submitted(self.gpu.queue());
         frame.present();
     }
+    /// Reads back the most recently rendered frame at the rendering
+    /// resolution, before postprocessing is applied, with sRGB-encoded
+    /// colors in either [`crate::colorgeo::ColorSpace`].  This blocks
+    /// until the GPU has finished all submitted work, so it's best
+    /// used for occasional screenshots rather than every frame.
+    /// Returns [`crate::FrendererError::Readback`] if the frame couldn't be
+    /// read back, e.g. because the device was lost.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn capture_frame(&self) -> Result<CapturedFrame, crate::FrendererError> {
+        let (width, height) = (self.render_width, self.render_height);
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frenderer:capture_buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder =
+            self.gpu
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("frenderer:capture"),
+                });
+        encoder.copy_texture_to_buffer(
+            self.color_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            self.color_texture.size(),
+        );
+        self.gpu.queue().submit(Some(encoder.finish()));
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            // The receiver is still waiting below, so this can't fail
+            let _ = sender.send(result);
+        });
+        self.gpu.device().poll(wgpu::Maintain::Wait);
+        receiver
+            .try_recv()
+            .unwrap_or(Err(wgpu::BufferAsyncError))
+            .map_err(crate::FrendererError::Readback)?;
+        let mut rgba = Vec::with_capacity((row_bytes * height) as usize);
+        for row in slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_bytes as usize)
+        {
+            rgba.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        buffer.unmap();
+        if matches!(
+            self.color_texture.format(),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for px in rgba.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+        }
+        if self.color_space() == crate::colorgeo::ColorSpace::Linear {
+            // Image files expect sRGB-encoded colors
+            for px in rgba.chunks_exact_mut(4) {
+                for c in &mut px[..3] {
+                    let linear = *c as f32 / 255.0;
+                    let encoded = if linear <= 0.0031308 {
+                        linear * 12.92
+                    } else {
+                        1.055 * linear.powf(1.0 / 2.4) - 0.055
+                    };
+                    *c = (encoded * 255.0).round() as u8;
+                }
+            }
+        }
+        Ok(CapturedFrame {
+            width,
+            height,
+            rgba,
+        })
+    }
     /// Returns the size of the surface onto which the rendered image is stretched
     pub fn surface_size(&self) -> (u32, u32) {
         (self.config.width, self.config.height)
     }
+    /// Returns the size of the surface as the user sees it: like
+    /// [`Renderer::surface_size`], but with the width and height
+    /// swapped if the surface rotation is a quarter turn.
+    pub fn upright_surface_size(&self) -> (u32, u32) {
+        let (w, h) = self.surface_size();
+        if self.surface_rotation().is_quarter_turn() {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    }
+    /// Returns whether the surface, as the user sees it, is wider or
+    /// taller, e.g. to lay out a game's interface differently when a
+    /// phone is turned.
+    pub fn orientation(&self) -> Orientation {
+        let (w, h) = self.upright_surface_size();
+        if w >= h {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        }
+    }
+    /// Returns how far the final image is turned as it's drawn onto
+    /// the surface.
+    pub fn surface_rotation(&self) -> colorgeo::SurfaceRotation {
+        self.postprocess.rotation()
+    }
+    /// Turns the final image as it's drawn onto the surface, to
+    /// respect a display's pre-rotation (e.g. the current transform
+    /// reported for an Android swapchain).  The surface keeps its
+    /// natural size, while [`Renderer::viewport`],
+    /// [`Renderer::orientation`], and the surface position
+    /// conversions like [`Renderer::surface_to_render`] work in the
+    /// upright surface the user sees.  This applies to the
+    /// postprocessing step of [`Renderer::render_with`] too.
+    pub fn set_surface_rotation(&mut self, rotation: colorgeo::SurfaceRotation) {
+        self.postprocess.set_rotation(&self.gpu, rotation);
+    }
     /// Returns the size of the internal rendering texture (i.e., the rendering resolution)
     pub fn render_size(&self) -> (u32, u32) {
         (self.render_width, self.render_height)
     }
-    /// Creates an array texture on the renderer's GPU.
-    pub fn create_array_texture(
-        &self,
-        images: &[&[u8]],
-        format: wgpu::TextureFormat,
-        (width, height): (u32, u32),
-        label: Option<&str>,
-    ) -> wgpu::Texture {
-        let size = wgpu::Extent3d {
+    /// Returns how the rendered image is fit to the surface
+    pub fn scale_mode(&self) -> ScaleMode {
+        self.scale_mode
+    }
+    /// Sets how the rendered image is fit to the surface
+    pub fn set_scale_mode(&mut self, mode: ScaleMode) {
+        self.scale_mode = mode;
+    }
+    /// Returns the stereo settings, if [`Renderer::render`] is drawing in stereo.
+    pub fn stereo(&self) -> Option<Stereo> {
+        self.stereo
+    }
+    /// Makes [`Renderer::render`] draw the scene twice, once per eye
+    /// with that eye's camera offsets, into the two halves of the
+    /// render texture; `None` goes back to drawing it once.  With
+    /// [`StereoLayout::Anaglyph`], the postprocessing step then
+    /// recombines the halves.  The cameras set on the renderer are
+    /// left unchanged.  [`Renderer::render_with`] always draws once.
+    pub fn set_stereo(&mut self, stereo: Option<Stereo>) {
+        self.stereo = stereo;
+        let anaglyph = stereo.is_some_and(|s| s.layout == StereoLayout::Anaglyph);
+        self.postprocess.set_anaglyph(&self.gpu, anaglyph);
+    }
+    /// Returns the part of the surface the rendered image is drawn
+    /// into by [`Renderer::render`], according to the scale mode,
+    /// within the upright surface (see [`Renderer::upright_surface_size`]).
+    pub fn viewport(&self) -> Viewport {
+        let (sw, sh) = self.upright_surface_size();
+        let (sw, sh) = (sw as f32, sh as f32);
+        let (rw, rh) = (self.render_width as f32, self.render_height as f32);
+        let fit = (sw / rw).min(sh / rh);
+        let scale = match self.scale_mode {
+            ScaleMode::Stretch => {
+                return Viewport {
+                    x: 0.0,
+                    y: 0.0,
+                    width: sw,
+                    height: sh,
+                }
+            }
+            ScaleMode::Fit => fit,
+            ScaleMode::IntegerFit if fit >= 1.0 => fit.floor(),
+            ScaleMode::IntegerFit => fit,
+        };
+        let (width, height) = (rw * scale, rh * scale);
+        Viewport {
+            x: ((sw - width) / 2.0).floor(),
+            y: ((sh - height) / 2.0).floor(),
             width,
             height,
-            depth_or_array_layers: if self.gpu.is_gl() {
-                // Workaround for opengl: If len is 1, this array texture is just initialized and treated as a regular single texture.  So we lie and say we have at least two (and if we have 6, we lie and say we have 7 so it isn't treated as a cubemap)
-                match images.len() {
-                    1 => 2,
-                    6 => 7,
-                    l => l,
+        }
+    }
+    /// Converts a position on the surface in pixels (e.g. the mouse
+    /// position) to a position in the rendered image in pixels, both
+    /// with their origin at the top left.  This accounts for the
+    /// scale mode and the 2D part of the postprocessing transform.
+    /// Positions outside the viewport map to positions outside the
+    /// image.  Use the result with e.g.
+    /// [`crate::sprites::Camera2D::screen_to_world`] and
+    /// [`Renderer::render_size`], or see
+    /// [`Renderer::surface_px_to_world`].
+    pub fn surface_to_render(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let vp = self.viewport();
+        let ndc_x = (x - vp.x) / vp.width * 2.0 - 1.0;
+        let ndc_y = 1.0 - (y - vp.y) / vp.height * 2.0;
+        // Undo the postprocessing quad's transform (column-major)
+        let m = self.postprocess.transform();
+        let (px, py) = (ndc_x - m[12], ndc_y - m[13]);
+        let det = m[0] * m[5] - m[4] * m[1];
+        let qx = (m[5] * px - m[4] * py) / det;
+        let qy = (m[0] * py - m[1] * px) / det;
+        [
+            (qx + 1.0) / 2.0 * self.render_width as f32,
+            (1.0 - qy) / 2.0 * self.render_height as f32,
+        ]
+    }
+    /// The inverse of [`Renderer::surface_to_render`]: converts a
+    /// position in the rendered image in pixels to where it appears
+    /// on the surface.
+    pub fn render_to_surface(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let vp = self.viewport();
+        let qx = x / self.render_width as f32 * 2.0 - 1.0;
+        let qy = 1.0 - y / self.render_height as f32 * 2.0;
+        let m = self.postprocess.transform();
+        let ndc_x = m[0] * qx + m[4] * qy + m[12];
+        let ndc_y = m[1] * qx + m[5] * qy + m[13];
+        [
+            vp.x + (ndc_x + 1.0) / 2.0 * vp.width,
+            vp.y + (1.0 - ndc_y) / 2.0 * vp.height,
+        ]
+    }
+    /// [`Renderer::surface_to_render`] for typed positions.
+    pub fn surface_px_to_render(&self, px: SurfacePx) -> RenderPx {
+        RenderPx(self.surface_to_render(px.0))
+    }
+    /// [`Renderer::render_to_surface`] for typed positions.
+    pub fn render_px_to_surface(&self, px: RenderPx) -> SurfacePx {
+        SurfacePx(self.render_to_surface(px.0))
+    }
+    /// Converts a position on the surface (e.g. the mouse position)
+    /// to world space as seen through `camera`, going through the
+    /// rendered image like [`Renderer::render`] does.
+    pub fn surface_px_to_world(
+        &self,
+        camera: &crate::sprites::Camera2D,
+        px: SurfacePx,
+    ) -> WorldPos {
+        camera.render_to_world(self.surface_px_to_render(px), self.render_size_f32())
+    }
+    /// Converts a world space position seen through `camera` to where
+    /// it appears on the surface.
+    pub fn world_to_surface_px(
+        &self,
+        camera: &crate::sprites::Camera2D,
+        pos: WorldPos,
+    ) -> SurfacePx {
+        self.render_px_to_surface(camera.world_to_render(pos, self.render_size_f32()))
+    }
+    /// Returns the topmost sprite of any sprite group under a
+    /// position on the surface (e.g. the mouse position), picked on
+    /// the CPU so it works on every backend; see [`crate::pick`].
+    /// Groups are considered in drawing order, with depth tested
+    /// groups only drawing over nearer sprites, so the result is the
+    /// sprite whose rectangle would be visible there.
+    pub fn sprite_pick(&self, px: SurfacePx) -> Option<crate::pick::SpriteHit> {
+        let mut best = None;
+        // The depth the depth buffer would hold at px
+        let mut buffer_depth = f32::INFINITY;
+        for group in 0..self.sprites.sprite_group_count() {
+            let Some(camera) = self.sprites.camera(group) else {
+                continue;
+            };
+            let world = self.surface_px_to_world(&camera, px);
+            let Some((index, depth)) = self.sprites.pick_with_depth(group, world.0) else {
+                continue;
+            };
+            let mode = self.sprites.depth_mode(group);
+            if !mode.test || depth < buffer_depth {
+                best = Some(crate::pick::SpriteHit { group, index });
+                if mode.write {
+                    buffer_depth = depth;
                 }
-            } else {
-                images.len()
-            } as u32,
-        };
-        let texture = self.gpu.device().create_texture(&wgpu::TextureDescriptor {
-            label,
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        for (layer, img) in images.iter().enumerate() {
-            assert_eq!(
-                img.len(),
-                images[0].len(),
-                "Can't create an array texture with images of different dimensions"
-            );
-            self.gpu.queue().write_texture(
-                wgpu::ImageCopyTexture {
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d {
-                        x: 0,
-                        y: 0,
-                        z: layer as u32,
-                    },
-                    aspect: wgpu::TextureAspect::All,
-                },
-                img,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * width),
-                    rows_per_image: Some(height),
-                },
-                wgpu::Extent3d {
-                    width,
-                    height,
-                    depth_or_array_layers: 1,
-                },
-            );
+            }
         }
-        // again, if it's opengl we may need to copy our first texture again to the last (bonus) layer index.
-        if size.depth_or_array_layers > images.len() as u32 {
-            self.gpu.queue().write_texture(
-                wgpu::ImageCopyTexture {
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d {
-                        x: 0,
-                        y: 0,
-                        z: images.len() as u32,
-                    },
-                    aspect: wgpu::TextureAspect::All,
-                },
-                images[0],
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * width),
-                    rows_per_image: Some(height),
-                },
-                wgpu::Extent3d {
-                    width,
-                    height,
-                    depth_or_array_layers: 1,
-                },
-            );
+        best
+    }
+    /// Returns the index of the topmost sprite of a specific sprite
+    /// group under a position on the surface, see
+    /// [`crate::sprites::SpriteRenderer::pick`].
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_pick(&self, which: usize, px: SurfacePx) -> Option<usize> {
+        let camera = self.sprites.camera(which).unwrap();
+        self.sprites
+            .pick(which, self.surface_px_to_world(&camera, px).0)
+    }
+    fn render_size_f32(&self) -> [f32; 2] {
+        [self.render_width as f32, self.render_height as f32]
+    }
+    /// Returns the window's scale factor: physical pixels per
+    /// logical pixel, e.g. 2.0 on many high-DPI displays.  The
+    /// [`crate::Driver`] keeps this up to date; otherwise set it with
+    /// [`Renderer::set_scale_factor`].  Defaults to 1.0.
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+    /// Sets the window's scale factor, e.g. from winit's
+    /// `Window::scale_factor`; see [`Renderer::scale_factor`].
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        if scale_factor > 0.0 {
+            self.scale_factor = scale_factor;
         }
-        texture
     }
-    /// Creates a single texture on the renderer's GPU.
-    pub fn create_texture(
+    /// Converts a size in points (logical pixels, as UI text is
+    /// usually sized) to pixels of the rendered image, accounting for
+    /// the scale factor and for how the rendered image is scaled onto
+    /// the surface.  Text sized this way stays the same physical size
+    /// on every display, however the render resolution is set.
+    pub fn points_to_render_pixels(&self, points: f32) -> f32 {
+        let surface_per_render = self.viewport().height / self.render_height as f32;
+        points * self.scale_factor / surface_per_render
+    }
+    /// The inverse of [`Renderer::points_to_render_pixels`].
+    pub fn render_pixels_to_points(&self, pixels: f32) -> f32 {
+        let surface_per_render = self.viewport().height / self.render_height as f32;
+        pixels * surface_per_render / self.scale_factor
+    }
+    /// A character height in render pixels for drawing `font` (e.g.
+    /// with [`crate::bitfont::BitFont::draw_text`]) at roughly
+    /// `points` points.  Heights of at least one glyph are rounded
+    /// down to whole multiples of the font's glyph height so its
+    /// pixels stay crisp, and smaller ones to whole pixels.
+    pub fn text_height(&self, font: &crate::bitfont::BitFont, points: f32) -> f32 {
+        let pixels = self.points_to_render_pixels(points);
+        let glyph = font.char_size().1 as f32;
+        if pixels >= glyph {
+            (pixels / glyph).floor() * glyph
+        } else {
+            pixels.round().max(1.0)
+        }
+    }
+    /// Creates an array texture on the renderer's GPU, see [`WGPU::create_array_texture`].
+    pub fn create_array_texture(
         &self,
-        image: &[u8],
+        images: &[&[u8]],
         format: wgpu::TextureFormat,
+        size: (u32, u32),
+        label: Option<&str>,
+    ) -> wgpu::Texture {
+        self.gpu.create_array_texture(images, format, size, label)
+    }
+    /// Hot-reloads an array texture made from `old` with the images in
+    /// `new`, uploading only the rectangles of each layer which
+    /// changed; see [`crate::reload`].  Returns those rectangles.
+    /// `new` must have the same number of layers as `old`, and the
+    /// same size and format.
+    pub fn reload_array_texture(
+        &self,
+        texture: &wgpu::Texture,
+        old: &[&[u8]],
+        new: &[&[u8]],
+    ) -> Vec<crate::reload::DirtyRect> {
+        let bytes_per_pixel = texture.format().block_copy_size(None).unwrap_or(4);
+        let rects = crate::reload::diff_layers(
+            old,
+            new,
+            (texture.width(), texture.height()),
+            bytes_per_pixel,
+        );
+        trace_span!("textures:reload", layers = new.len(), rects = rects.len());
+        self.gpu.write_texture_rects(texture, new, &rects);
+        rects
+    }
+    /// Creates the signed distance field texture of a spritesheet's
+    /// RGBA8 layers for [`Renderer::sprite_group_set_sdf_effect`],
+    /// see [`crate::sdf::alpha_sdf`].  This runs on the CPU, so do it
+    /// when loading the sheet.
+    pub fn create_sdf_texture(
+        &self,
+        images: &[&[u8]],
         (width, height): (u32, u32),
+        spread: u32,
         label: Option<&str>,
     ) -> wgpu::Texture {
-        let size = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
-        let texture = self.gpu.device().create_texture(&wgpu::TextureDescriptor {
+        let fields: Vec<Vec<u8>> = images
+            .iter()
+            .map(|image| crate::sdf::alpha_sdf(image, width, height, spread))
+            .collect();
+        let fields: Vec<&[u8]> = fields.iter().map(Vec::as_slice).collect();
+        self.create_array_texture(
+            &fields,
+            wgpu::TextureFormat::R8Unorm,
+            (width, height),
             label,
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        self.gpu.queue().write_texture(
-            texture.as_image_copy(),
-            image,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * width),
-                rows_per_image: Some(height),
-            },
-            size,
-        );
-        texture
+        )
+    }
+    /// Creates a single texture on the renderer's GPU, see [`WGPU::create_texture`].
+    pub fn create_texture(
+        &self,
+        image: &[u8],
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+        label: Option<&str>,
+    ) -> wgpu::Texture {
+        self.gpu.create_texture(image, format, size, label)
+    }
+    /// Makes a 64x64 magenta and black checkerboard texture, a
+    /// stand-in for art which doesn't exist yet or failed to load.
+    #[cfg(feature = "bundled-assets")]
+    pub fn placeholder_texture(&self) -> wgpu::Texture {
+        const SIZE: usize = 64;
+        const CHECK: usize = 8;
+        let image: Vec<u8> = (0..SIZE * SIZE)
+            .flat_map(|px| {
+                let (x, y) = (px % SIZE, px / SIZE);
+                if (x / CHECK + y / CHECK) % 2 == 0 {
+                    [255, 0, 255, 255]
+                } else {
+                    [0, 0, 0, 255]
+                }
+            })
+            .collect();
+        self.create_texture(
+            &image,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            (SIZE as u32, SIZE as u32),
+            Some("frenderer:placeholder"),
+        )
     }
     /// Create a new sprite group sized to fit `world_transforms` and
     /// `sheet_regions`, which should be the same length.  Returns the
@@ -537,18 +1660,166 @@ impl Renderer {
         self.sprites
             .add_sprite_group(&self.gpu, tex, world_transforms, sheet_regions, camera)
     }
+    /// Like [`Renderer::sprite_group_add`], but drawing the group
+    /// with the named shader variant; see
+    /// [`Renderer::sprite_group_set_shader`].
+    pub fn sprite_group_add_with_shader(
+        &mut self,
+        tex: &wgpu::Texture,
+        shader: &str,
+        world_transforms: Vec<crate::sprites::Transform>,
+        sheet_regions: Vec<crate::sprites::SheetRegion>,
+        camera: crate::sprites::Camera2D,
+    ) -> usize {
+        let which = self.sprite_group_add(tex, world_transforms, sheet_regions, camera);
+        self.sprite_group_set_shader(which, shader);
+        which
+    }
+    /// Like [`Renderer::sprite_group_add`], but sampling `tex`
+    /// through a custom view; see [`SpriteRenderer::add_sprite_group_with_view`].
+    pub fn sprite_group_add_with_view(
+        &mut self,
+        tex: &wgpu::Texture,
+        view: &wgpu::TextureViewDescriptor,
+        world_transforms: Vec<crate::sprites::Transform>,
+        sheet_regions: Vec<crate::sprites::SheetRegion>,
+        camera: crate::sprites::Camera2D,
+    ) -> usize {
+        self.sprites.add_sprite_group_with_view(
+            &self.gpu,
+            tex,
+            view,
+            world_transforms,
+            sheet_regions,
+            camera,
+        )
+    }
+    /// Create a sprite group of `capacity` hidden sprites managed by a
+    /// [`crate::sprites::SpritePool`], e.g. for projectiles.
+    pub fn sprite_pool_add(
+        &mut self,
+        tex: &wgpu::Texture,
+        capacity: usize,
+        camera: crate::sprites::Camera2D,
+    ) -> crate::sprites::SpritePool {
+        crate::sprites::SpritePool::new(&mut self.sprites, &self.gpu, tex, capacity, camera)
+    }
+    /// Spawns a sprite in a free slot of `pool`, see
+    /// [`crate::sprites::SpritePool::spawn`].  Marks it for later upload.
+    pub fn sprite_pool_spawn(
+        &mut self,
+        pool: &mut crate::sprites::SpritePool,
+        trf: crate::sprites::Transform,
+        uv: crate::sprites::SheetRegion,
+    ) -> Option<usize> {
+        let slot = pool.spawn(&mut self.sprites, trf, uv);
+        self.queue_sprite_pool(pool);
+        slot
+    }
+    /// Despawns the sprite in a slot of `pool`, see
+    /// [`crate::sprites::SpritePool::despawn`].  Marks it for later upload.
+    pub fn sprite_pool_despawn(
+        &mut self,
+        pool: &mut crate::sprites::SpritePool,
+        slot: usize,
+    ) -> bool {
+        let despawned = pool.despawn(&mut self.sprites, slot);
+        self.queue_sprite_pool(pool);
+        despawned
+    }
+    /// Changes the sprite in a spawned slot of `pool`, see
+    /// [`crate::sprites::SpritePool::set`].  Marks it for later upload.
+    pub fn sprite_pool_set(
+        &mut self,
+        pool: &mut crate::sprites::SpritePool,
+        slot: usize,
+        trf: crate::sprites::Transform,
+        uv: crate::sprites::SheetRegion,
+    ) {
+        pool.set(&mut self.sprites, slot, trf, uv);
+        self.queue_sprite_pool(pool);
+    }
+    /// Despawns every sprite in `pool` and marks them for later upload.
+    pub fn sprite_pool_clear(&mut self, pool: &mut crate::sprites::SpritePool) {
+        pool.clear(&mut self.sprites);
+        self.queue_sprite_pool(pool);
+    }
+    fn queue_sprite_pool(&mut self, pool: &mut crate::sprites::SpritePool) {
+        if let Some(range) = pool.take_dirty() {
+            self.queued_uploads
+                .push(Upload::Sprite(pool.group(), range));
+        }
+    }
+    /// Create a compact sprite group holding `sprites`, for very large
+    /// groups of static decoration; see
+    /// [`SpriteRenderer::add_compact_sprite_group`].
+    pub fn sprite_group_add_compact(
+        &mut self,
+        tex: &wgpu::Texture,
+        sprites: Vec<crate::sprites::CompactSprite>,
+        layout: crate::sprites::CompactLayout,
+        camera: crate::sprites::Camera2D,
+    ) -> usize {
+        self.sprites
+            .add_compact_sprite_group(&self.gpu, tex, sprites, layout, camera)
+    }
+    /// Changes the [`crate::sprites::CompactLayout`] of a compact sprite group.
+    /// Panics if the given sprite group is not a populated compact group.
+    pub fn sprite_group_set_compact_layout(
+        &mut self,
+        which: usize,
+        layout: crate::sprites::CompactLayout,
+    ) {
+        self.sprites.set_compact_layout(&self.gpu, which, layout)
+    }
     /// Returns the number of sprite groups (including placeholders for removed groups).
     pub fn sprite_group_count(&self) -> usize {
         self.sprites.sprite_group_count()
     }
     /// Deletes a sprite group, leaving an empty group slot behind (this might get recycled later).
     pub fn sprite_group_remove(&mut self, which: usize) {
-        self.sprites.remove_sprite_group(which)
+        self.sprites.remove_sprite_group(which);
+        self.history_groups.retain(|&group| group != which);
+        if let Some(callback) = self.sprite_callbacks.get_mut(which) {
+            *callback = None;
+        }
+    }
+    /// Register a function to be called on a specific sprite group's
+    /// transforms and sheet regions (along with the seconds since the
+    /// last frame) every time the renderer draws a frame, just before
+    /// uploading sprite data.  This is handy for idle animations or
+    /// scrolling backgrounds.  The whole group is uploaded every
+    /// frame while a callback is set.  Replaces any previous callback.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_callback(
+        &mut self,
+        which: usize,
+        callback: impl FnMut(&mut [crate::sprites::Transform], &mut [crate::sprites::SheetRegion], f32)
+            + Send
+            + 'static,
+    ) {
+        // Panics if the group isn't populated
+        self.sprites.sprite_group_size(which);
+        if self.sprite_callbacks.len() <= which {
+            self.sprite_callbacks.resize_with(which + 1, || None);
+        }
+        self.sprite_callbacks[which] = Some(Box::new(callback));
+    }
+    /// Remove a specific sprite group's callback, if it has one.
+    pub fn sprite_group_clear_callback(&mut self, which: usize) {
+        if let Some(callback) = self.sprite_callbacks.get_mut(which) {
+            *callback = None;
+        }
     }
     /// Reports the size of the given sprite group.  Panics if the given sprite group is not populated.
     pub fn sprite_group_size(&self, which: usize) -> usize {
         self.sprites.sprite_group_size(which)
     }
+    /// Describes the texture the given sprite group draws from.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_texture_info(&self, which: usize) -> crate::resources::TextureInfo {
+        self.sprites.texture_info(which)
+    }
     /// Resizes a sprite group.  If the new size is smaller, this is
     /// very cheap; if it's larger than it's ever been before, it
     /// might involve reallocating the [`Vec<Transform>`],
@@ -559,17 +1830,278 @@ impl Renderer {
     pub fn sprite_group_resize(&mut self, which: usize, len: usize) -> usize {
         self.sprites.resize_sprite_group(&self.gpu, which, len)
     }
+    /// Shrink a sprite group's buffers to fit its current size (see
+    /// [`SpriteRenderer::shrink_sprite_group_to_fit`]).
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_shrink_to_fit(&mut self, which: usize) {
+        self.sprites.shrink_sprite_group_to_fit(&self.gpu, which)
+    }
+    /// Set whether a specific sprite group's buffers may be
+    /// automatically shrunk when it's been underused for a while.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_reclaimable(&mut self, which: usize, reclaimable: bool) {
+        self.sprites.set_reclaimable(which, reclaimable)
+    }
+    /// Set the policy [`Renderer::render`] uses to shrink underused
+    /// sprite groups, or `None` to never shrink them automatically.
+    /// Defaults to `None`, so a group's capacity only changes when
+    /// it's resized; pass e.g.
+    /// `Some(`[`crate::sprites::ReclaimPolicy::default`]`())` to opt in.
+    pub fn set_sprite_reclaim_policy(&mut self, policy: Option<crate::sprites::ReclaimPolicy>) {
+        self.sprite_reclaim = policy;
+    }
+    /// Counts the given sprite group's visible, allocated, and
+    /// GPU-side sprites; see [`SpriteRenderer::group_stats`].
+    pub fn sprite_group_stats(&self, which: usize) -> crate::sprites::SpriteGroupStats {
+        self.sprites.group_stats(which)
+    }
+    /// Sets when to log warnings about sprite groups which stay
+    /// mostly empty, or `None` (the default) to not check; see
+    /// [`SpriteRenderer::warn_sparse_groups`].
+    pub fn set_sprite_sparse_warning(&mut self, warning: Option<crate::sprites::SparseWarning>) {
+        self.sprite_sparse_warning = warning;
+    }
     /// Set the given camera transform on a specific sprite group.  Uploads to the GPU.
     /// Panics if the given sprite group is not populated.
     pub fn sprite_group_set_camera(&mut self, which: usize, camera: crate::sprites::Camera2D) {
         self.sprites.set_camera(&self.gpu, which, camera)
     }
+    /// Sets the given camera on several sprite groups at once.  The
+    /// cameras are uploaded together during the next
+    /// [`Renderer::do_uploads`], so setting them repeatedly within a
+    /// frame costs one write per group.
+    /// Panics if any of the given sprite groups is not populated.
+    pub fn sprite_groups_set_camera(
+        &mut self,
+        groups: impl IntoIterator<Item = usize>,
+        camera: crate::sprites::Camera2D,
+    ) {
+        for which in groups {
+            self.sprites.stage_camera(which, camera);
+        }
+    }
+    /// Sets the given camera on every populated sprite group, like
+    /// [`Renderer::sprite_groups_set_camera`].
+    pub fn sprite_set_camera_all(&mut self, camera: crate::sprites::Camera2D) {
+        let groups: Vec<_> = (0..self.sprite_group_count())
+            .filter(|&which| self.sprites.sprite_group_populated(which))
+            .collect();
+        self.sprite_groups_set_camera(groups, camera);
+    }
+    /// Returns the camera of a specific sprite group, or `None` if
+    /// the group has been removed.
+    pub fn sprite_group_camera(&self, which: usize) -> Option<crate::sprites::Camera2D> {
+        self.sprites.camera(which)
+    }
+    /// Set a specific sprite group's camera blended between the
+    /// previous and current simulation steps' cameras by `alpha`
+    /// (e.g. [`crate::clock::Clock::alpha`]) to avoid stutter when
+    /// rendering and simulation rates differ.  Uploads to the GPU.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_camera_interpolated(
+        &mut self,
+        which: usize,
+        prev: crate::sprites::Camera2D,
+        current: crate::sprites::Camera2D,
+        alpha: f32,
+    ) {
+        self.sprites
+            .set_camera_interpolated(&self.gpu, which, prev, current, alpha)
+    }
+    /// Set how a specific sprite group interacts with the depth
+    /// buffer, e.g. [`crate::sprites::DepthMode::OVERLAY`] to draw UI
+    /// on top of everything else.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_depth_mode(&mut self, which: usize, mode: crate::sprites::DepthMode) {
+        self.sprites.set_depth_mode(which, mode)
+    }
+    /// Registers a named sprite shader variant, see
+    /// [`SpriteRenderer::register_shader_variant`].
+    pub fn sprite_shader_register(&mut self, name: &str, source: &str, entry_point: &str) {
+        self.sprites
+            .register_shader_variant(&self.gpu, name, source, entry_point)
+    }
+    /// Returns the names of the registered sprite shader variants.
+    pub fn sprite_shader_variants(&self) -> impl Iterator<Item = &str> + '_ {
+        self.sprites.shader_variants()
+    }
+    /// Draws a specific sprite group with the named shader variant, see
+    /// [`SpriteRenderer::set_shader_variant`].
+    pub fn sprite_group_set_shader(&mut self, which: usize, name: &str) {
+        self.sprites.set_shader_variant(&self.gpu, which, name)
+    }
+    /// Returns the name of the shader variant a specific sprite group is drawn with.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_shader(&self, which: usize) -> &str {
+        self.sprites.shader_variant(which)
+    }
+    /// Set or clear the [`crate::sprites::YSort`] which derives a specific sprite group's depths from its sprites' positions.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_y_sort(&mut self, which: usize, y_sort: Option<crate::sprites::YSort>) {
+        self.sprites.set_y_sort(&self.gpu, which, y_sort)
+    }
+    /// Set or clear the [`crate::sprites::WorldDepth`] which places a specific sprite group's sprites at distances from the mesh camera, so they and meshes occlude each other.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_world_depth(
+        &mut self,
+        which: usize,
+        world_depth: Option<crate::sprites::WorldDepth>,
+    ) {
+        self.sprites.set_world_depth(&self.gpu, which, world_depth)
+    }
+    /// Set the [`crate::sprites::SpriteShape`]s a specific sprite
+    /// group draws instead of quads, to reduce overdraw.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_shapes(
+        &mut self,
+        which: usize,
+        shapes: Vec<crate::sprites::SpriteShape>,
+    ) {
+        self.sprites.set_sprite_shapes(&self.gpu, which, shapes)
+    }
+    /// Turn pixel-perfect drawing on or off for a specific sprite
+    /// group, snapping its sprites to whole pixels; see
+    /// [`SpriteRenderer::set_pixel_perfect`].
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_pixel_perfect(&mut self, which: usize, pixel_perfect: bool) {
+        self.sprites
+            .set_pixel_perfect(&self.gpu, which, pixel_perfect)
+    }
+    /// Set how a specific sprite group samples its texture; see
+    /// [`SpriteRenderer::set_sampler`].
+    /// Panics if the given sprite group is not populated or is indexed.
+    pub fn sprite_group_set_sampler(
+        &mut self,
+        which: usize,
+        sampler: crate::sprites::SpriteSampler,
+    ) {
+        self.sprites.set_sampler(&self.gpu, which, sampler)
+    }
+    /// Get how a specific sprite group samples its texture.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_sampler(&self, which: usize) -> crate::sprites::SpriteSampler {
+        self.sprites.sampler(which)
+    }
+    /// Create a sprite group holding a single
+    /// [`crate::sprites::TiledBackground`] sprite covering `camera`'s
+    /// view, sampling `tex` with [`crate::sprites::SpriteSampler::REPEAT`].
+    /// Returns the sprite group index.
+    pub fn sprite_group_add_tiled_background(
+        &mut self,
+        tex: &wgpu::Texture,
+        background: crate::sprites::TiledBackground,
+        camera: crate::sprites::Camera2D,
+    ) -> usize {
+        let (trf, uv) = background.sprite(&camera, [tex.width(), tex.height()]);
+        let which = self.sprite_group_add(tex, vec![trf], vec![uv], camera);
+        self.sprite_group_set_sampler(which, crate::sprites::SpriteSampler::REPEAT);
+        which
+    }
+    /// Scroll a group made with
+    /// [`Renderer::sprite_group_add_tiled_background`] by rewriting its
+    /// sprite for `background` and the group's current camera.  Call
+    /// this whenever the camera moves or the background scrolls.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_tiled_background_set(
+        &mut self,
+        which: usize,
+        background: crate::sprites::TiledBackground,
+    ) {
+        let camera = self.sprites.camera(which).unwrap();
+        let info = self.sprites.texture_info(which);
+        let (trfs, uvs) = self.sprites_mut(which, 0..1);
+        (trfs[0], uvs[0]) = background.sprite(&camera, [info.width, info.height]);
+    }
+    /// Set the [`crate::colorgeo::ColorGrade`] applied to a specific
+    /// sprite group's colors before postprocessing, e.g. to tint the
+    /// world but not the UI.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_color_grade(
+        &mut self,
+        which: usize,
+        grade: crate::colorgeo::ColorGrade,
+    ) {
+        self.sprites.set_color_grade(&self.gpu, which, grade)
+    }
+    /// Gets the [`crate::colorgeo::ColorGrade`] of a specific sprite group.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_color_grade(&self, which: usize) -> crate::colorgeo::ColorGrade {
+        self.sprites.color_grade(which)
+    }
+    /// Set or clear the [`crate::sprites::Trail`] drawn behind a specific sprite group's sprites.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_trail(&mut self, which: usize, trail: Option<crate::sprites::Trail>) {
+        self.sprites.set_trail(&self.gpu, which, trail)
+    }
+    /// Sets or clears a sprite group's soft shadow, glow, and outline
+    /// effects, see [`crate::sprites::SpriteRenderer::set_sdf_effect`]
+    /// and [`Renderer::create_sdf_texture`].
+    pub fn sprite_group_set_sdf_effect(
+        &mut self,
+        which: usize,
+        effect: Option<(crate::sprites::SdfEffect, &wgpu::Texture)>,
+    ) {
+        self.sprites.set_sdf_effect(&self.gpu, which, effect)
+    }
+    /// Changes a sprite group's existing SDF effect, keeping its
+    /// distance field; see [`crate::sprites::SpriteRenderer::update_sdf_effect`].
+    pub fn sprite_group_update_sdf_effect(
+        &mut self,
+        which: usize,
+        effect: crate::sprites::SdfEffect,
+    ) {
+        self.sprites.update_sdf_effect(&self.gpu, which, effect)
+    }
+    /// Returns a sprite group's SDF effect, if it has one.
+    pub fn sprite_group_sdf_effect(&self, which: usize) -> Option<crate::sprites::SdfEffect> {
+        self.sprites.sdf_effect(which)
+    }
+    /// Create an indexed-color sprite group whose spritesheet `tex`
+    /// is an [`wgpu::TextureFormat::R8Uint`] array texture of indices
+    /// into `palette` (see [`Renderer::create_array_texture`]).
+    pub fn sprite_group_add_indexed(
+        &mut self,
+        tex: &wgpu::Texture,
+        palette: &[[u8; 4]],
+        world_transforms: Vec<crate::sprites::Transform>,
+        sheet_regions: Vec<crate::sprites::SheetRegion>,
+        camera: crate::sprites::Camera2D,
+    ) -> usize {
+        self.sprites.add_indexed_sprite_group(
+            &self.gpu,
+            tex,
+            palette,
+            world_transforms,
+            sheet_regions,
+            camera,
+        )
+    }
+    /// Replaces the palette of an indexed-color sprite group.
+    pub fn sprite_group_set_palette(&mut self, which: usize, palette: &[[u8; 4]]) {
+        self.sprites.set_palette(&self.gpu, which, palette)
+    }
+    /// Returns the palette of the given sprite group, if it uses indexed color.
+    pub fn sprite_group_palette(&self, which: usize) -> Option<&crate::sprites::Palette> {
+        self.sprites.palette(which)
+    }
+    /// Rotates a range of an indexed-color sprite group's palette by `amount` places.
+    pub fn sprite_group_cycle_palette_range(
+        &mut self,
+        which: usize,
+        range: Range<usize>,
+        amount: isize,
+    ) {
+        self.sprites
+            .cycle_palette_range(&self.gpu, which, range, amount)
+    }
     /// Get a mutable slice of a specified sprite group's world transforms and texture regions.
     /// Marks these sprites for later upload.
     /// Since this causes an upload later on, call it as few times as possible per frame.
     /// Most importantly, don't call it with lots of tiny or overlapped regions.
     ///
-    /// Panics if the given sprite group is not populated or the range is out of bounds.
+    /// Panics if the given sprite group is not populated or is a
+    /// compact group (see [`Renderer::compact_sprites_mut`]), or if
+    /// the range is out of bounds.
     pub fn sprites_mut(
         &mut self,
         which: usize,
@@ -586,10 +2118,96 @@ impl Renderer {
         let (trfs, uvs) = self.sprites.get_sprites_mut(which);
         (&mut trfs[range.clone()], &mut uvs[range])
     }
+    /// Writes the tiles of an autotiled map changed since the last
+    /// update into the given sprite group, which needs one sprite per
+    /// tile; see [`crate::autotile::AutotileSprites::update`].
+    pub fn sprite_group_autotile(
+        &mut self,
+        which: usize,
+        autotile: &mut crate::autotile::AutotileSprites,
+    ) -> bool {
+        autotile.update(&self.gpu, &mut self.sprites, which)
+    }
+    /// Get a mutable slice of a compact sprite group's sprites, like
+    /// [`Renderer::sprites_mut`] for other groups.  Marks these
+    /// sprites for later upload.
+    ///
+    /// Panics if the given sprite group is not a populated compact
+    /// group or the range is out of bounds.
+    pub fn compact_sprites_mut(
+        &mut self,
+        which: usize,
+        range: impl RangeBounds<usize>,
+    ) -> &mut [crate::sprites::CompactSprite] {
+        let count = self.sprite_group_size(which);
+        let range = crate::range(range, count);
+        self.queued_uploads
+            .push(Upload::Sprite(which, range.clone()));
+        &mut self.sprites.get_compact_sprites_mut(which)[range]
+    }
 
-    /// Sets the given camera for all textured mesh groups.
+    /// Sets the given camera for all textured mesh groups and the terrain, if any.
     pub fn mesh_set_camera(&mut self, camera: crate::meshes::Camera3D) {
-        self.meshes.set_camera(&self.gpu, camera)
+        self.meshes.set_camera(&self.gpu, camera);
+        if let Some(terrain) = self.terrain.as_mut() {
+            terrain.set_camera(&self.gpu, camera);
+        }
+    }
+    /// Returns the camera used for all textured mesh groups.
+    pub fn mesh_camera(&self) -> crate::meshes::Camera3D {
+        self.meshes.camera()
+    }
+    /// Builds a terrain from a heightmap, replacing any previous one.
+    /// It's drawn before the mesh groups, through the textured mesh
+    /// camera; see [`crate::terrain::TerrainRenderer::new`] for the
+    /// textures it needs.
+    pub fn terrain_set(
+        &mut self,
+        heightmap: crate::terrain::Heightmap,
+        settings: crate::terrain::TerrainSettings,
+        textures: &wgpu::Texture,
+        weights: &wgpu::Texture,
+    ) {
+        let mut terrain = crate::terrain::TerrainRenderer::new(
+            &self.gpu,
+            wgpu::ColorTargetState {
+                format: self.color_texture.format(),
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            self.depth_texture.format(),
+            heightmap,
+            settings,
+            textures,
+            weights,
+        );
+        terrain.set_camera(&self.gpu, self.meshes.camera());
+        self.terrain = Some(terrain);
+    }
+    /// Removes the terrain, if there is one.
+    pub fn terrain_remove(&mut self) {
+        self.terrain = None;
+    }
+    /// Returns the terrain, if there is one, e.g. to find the ground's
+    /// height with [`crate::terrain::TerrainRenderer::height_at`].
+    pub fn terrain(&self) -> Option<&crate::terrain::TerrainRenderer> {
+        self.terrain.as_ref()
+    }
+    /// Sets whether textured mesh groups added from now on share pooled
+    /// vertex and index buffers, which are compacted automatically
+    /// during rendering; see [`crate::meshes::MeshRenderer::set_pooled`].
+    pub fn mesh_set_pooled(&mut self, pooled: bool) {
+        self.meshes.set_pooled(pooled)
+    }
+    /// Sets whether textured mesh groups added from now on keep their
+    /// triangles for exact raycasts; see [`crate::raycast`].
+    pub fn mesh_set_pick_triangles(&mut self, pick_triangles: bool) {
+        self.meshes.set_pick_triangles(pick_triangles)
+    }
+    /// Finds the closest textured mesh instance hit by `ray`, e.g. from
+    /// [`crate::meshes::Camera3D::screen_ray`].
+    pub fn mesh_raycast(&self, ray: &crate::meshes::Ray) -> Option<crate::raycast::RayHit> {
+        self.meshes.raycast(ray)
     }
     /// Add a mesh group with the given array texture.  All meshes in
     /// the group pull from the same vertex buffer, and each submesh
@@ -612,52 +2230,337 @@ impl Renderer {
     pub fn mesh_group_remove(&mut self, which: crate::meshes::MeshGroup) {
         self.meshes.remove_mesh_group(which)
     }
-    /// Returns how many mesh groups there are.
-    pub fn mesh_group_count(&self) -> usize {
-        self.meshes.mesh_group_count()
+    /// Returns how many mesh groups there are.
+    pub fn mesh_group_count(&self) -> usize {
+        self.meshes.mesh_group_count()
+    }
+    /// Returns how many meshes there are in the given mesh group.
+    pub fn mesh_group_size(&self, which: crate::meshes::MeshGroup) -> usize {
+        self.meshes.mesh_count(which)
+    }
+    /// Describes the given mesh group, see
+    /// [`crate::meshes::MeshRenderer::group_info`].
+    pub fn mesh_group_info(&self, which: crate::meshes::MeshGroup) -> crate::meshes::MeshGroupInfo {
+        self.meshes.group_info(which)
+    }
+    /// Returns how many mesh instances there are in the given mesh of the given mesh group.
+    pub fn mesh_instance_count(
+        &self,
+        which: crate::meshes::MeshGroup,
+        mesh_number: usize,
+    ) -> usize {
+        self.meshes.mesh_instance_count(which, mesh_number)
+    }
+    /// Change the number of instances of the given mesh of the given mesh group.
+    pub fn mesh_instance_resize(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        len: usize,
+    ) -> usize {
+        self.meshes.resize_group_mesh(&self.gpu, which, idx, len)
+    }
+    /// Adds an instance of the given mesh of the given mesh group,
+    /// returning a handle which stays valid until it's removed; see
+    /// [`crate::meshes::MeshRenderer::add_instance`].
+    pub fn mesh_instance_add(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        trf: crate::meshes::Transform3D,
+    ) -> crate::meshes::InstanceHandle {
+        self.meshes.add_instance(&self.gpu, which, idx, trf)
+    }
+    /// Removes the instance with the given handle, freeing its slot for reuse.
+    pub fn mesh_instance_remove(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        handle: crate::meshes::InstanceHandle,
+    ) {
+        self.meshes.remove_instance(&self.gpu, which, handle)
+    }
+    /// Sets whether the given mesh group blends its instance
+    /// transforms between simulation steps, see
+    /// [`crate::meshes::MeshRenderer::set_interpolated`].
+    pub fn mesh_group_set_interpolated(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        interpolated: bool,
+    ) {
+        self.meshes.set_interpolated(which, interpolated)
+    }
+    /// Returns whether the given mesh group is interpolated.
+    pub fn mesh_group_interpolated(&self, which: crate::meshes::MeshGroup) -> bool {
+        self.meshes.interpolated(which)
+    }
+    /// Remembers every interpolated mesh group's current instance
+    /// transforms as the previous ones; call this at the start of
+    /// each simulation step.
+    pub fn mesh_step_interpolation(&mut self) {
+        self.meshes.step_interpolation()
+    }
+    /// Moves instance `index` of mesh `idx` of the given mesh group
+    /// straight to its current transform without interpolating.
+    pub fn mesh_instance_snap(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+    ) {
+        self.meshes.snap_instance(which, idx, index)
+    }
+    /// Uploads every interpolated mesh group's instances blended
+    /// between the previous and current simulation steps by `alpha`,
+    /// typically [`crate::clock::Clock::alpha`].
+    pub fn mesh_interpolate(&mut self, alpha: f32) {
+        self.meshes.interpolate(&self.gpu, alpha)
+    }
+    /// Shows or hides instance `index` of mesh `idx` of the given
+    /// mesh group without reordering instances; see
+    /// [`crate::meshes::MeshRenderer::set_instance_visible`].
+    pub fn mesh_instance_set_visible(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+        visible: bool,
+    ) {
+        self.meshes
+            .set_instance_visible(&self.gpu, which, idx, index, visible)
+    }
+    /// Returns whether instance `index` of mesh `idx` of the given mesh group is visible.
+    pub fn mesh_instance_visible(
+        &self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+    ) -> bool {
+        self.meshes.instance_visible(which, idx, index)
+    }
+    /// Selects or deselects instance `index` of mesh `idx` of the
+    /// given mesh group for outlining; see [`Renderer::post_set_outline`].
+    pub fn mesh_instance_set_selected(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+        selected: bool,
+    ) {
+        self.meshes
+            .set_instance_selected(which, idx, index, selected)
+    }
+    /// Returns whether instance `index` of mesh `idx` of the given mesh group is selected.
+    pub fn mesh_instance_selected(
+        &self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+    ) -> bool {
+        self.meshes.instance_selected(which, idx, index)
+    }
+    /// Deselects every mesh and flat instance.
+    pub fn clear_selection(&mut self) {
+        self.meshes.clear_selection();
+        self.flats.clear_selection();
+    }
+    /// Gets the (mutable) transform of the instance with the given handle.
+    pub fn mesh_instance_mut(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        handle: crate::meshes::InstanceHandle,
+    ) -> &mut crate::meshes::Transform3D {
+        let (idx, index) = (handle.mesh(), handle.index());
+        self.queued_uploads
+            .push(Upload::Mesh(which, idx, index..index + 1));
+        &mut self.meshes.get_meshes_mut(which, idx)[index]
+    }
+    /// Gets the (mutable) transforms of every instance of the given mesh of a mesh group.
+    /// Since this causes an upload later on, call it as few times as possible per frame.
+    /// Most importantly, don't call it with lots of tiny regions or overlapped regions.
+    pub fn meshes_mut(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        range: impl RangeBounds<usize>,
+    ) -> &mut [crate::meshes::Transform3D] {
+        let count = self.meshes.mesh_instance_count(which, idx);
+        let range = crate::range(range, count);
+        self.queued_uploads
+            .push(Upload::Mesh(which, idx, range.clone()));
+        let trfs = self.meshes.get_meshes_mut(which, idx);
+        &mut trfs[range]
+    }
+    /// Gets disjoint mutable views of every populated sprite group,
+    /// mesh group, and flat mesh group at once.  These views can be
+    /// handed off to different threads (e.g. parallel ECS systems)
+    /// and written concurrently; everything is uploaded in a single
+    /// step during the next [`Renderer::do_uploads`].
+    pub fn groups_mut(&mut self) -> GroupsMut<'_> {
+        let sprites: Vec<_> = self.sprites.get_groups_mut().collect();
+        let meshes: Vec<_> = self.meshes.get_groups_mut().collect();
+        let flats: Vec<_> = self.flats.get_groups_mut().collect();
+        for (which, trfs, _) in sprites.iter() {
+            self.queued_uploads
+                .push(Upload::Sprite(*which, 0..trfs.len()));
+        }
+        for (which, instances) in meshes.iter() {
+            for (idx, trfs) in instances.iter().enumerate() {
+                self.queued_uploads
+                    .push(Upload::Mesh(*which, idx, 0..trfs.len()));
+            }
+        }
+        for (which, instances) in flats.iter() {
+            for (idx, trfs) in instances.iter().enumerate() {
+                self.queued_uploads
+                    .push(Upload::Flat(*which, idx, 0..trfs.len()));
+            }
+        }
+        GroupsMut {
+            sprites,
+            meshes,
+            flats,
+        }
+    }
+    /// Enables or disables non-uniform scaling for the given mesh group's instances.
+    pub fn mesh_group_set_nonuniform_scale(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        enabled: bool,
+    ) {
+        self.meshes.set_nonuniform_scale(&self.gpu, which, enabled)
+    }
+    /// Gets the (mutable) non-uniform scale factors of every instance of the given mesh of a mesh group.
+    /// These are uploaded along with the instances' transforms.
+    /// Panics if the mesh group does not use non-uniform scaling.
+    pub fn mesh_scales_mut(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        range: impl RangeBounds<usize>,
+    ) -> &mut [[f32; 3]] {
+        let count = self.meshes.mesh_instance_count(which, idx);
+        let range = crate::range(range, count);
+        self.queued_uploads
+            .push(Upload::Mesh(which, idx, range.clone()));
+        let scales = self.meshes.get_scales_mut(which, idx);
+        &mut scales[range]
+    }
+    /// Routes `instances` of a logical mesh with several levels of
+    /// detail to the meshes of a mesh group according to their distance
+    /// from the current camera; see [`crate::meshes::Lod`].  This
+    /// resizes each level's mesh to fit its instances.
+    pub fn mesh_group_apply_lod(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        lod: &mut crate::meshes::Lod,
+        instances: &[crate::meshes::Transform3D],
+    ) {
+        let camera_pos = self.meshes.camera().translation;
+        lod.assign(camera_pos, instances, |mesh, level_instances| {
+            self.meshes
+                .resize_group_mesh(&self.gpu, which, mesh, level_instances.len());
+            self.queued_uploads
+                .push(Upload::Mesh(which, mesh, 0..level_instances.len()));
+            self.meshes
+                .get_meshes_mut(which, mesh)
+                .copy_from_slice(level_instances);
+        });
+    }
+    /// Sets the [`crate::colorgeo::ColorGrade`] applied to the given mesh group's colors before postprocessing.
+    pub fn mesh_group_set_color_grade(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        grade: crate::colorgeo::ColorGrade,
+    ) {
+        self.meshes.set_color_grade(&self.gpu, which, grade)
+    }
+    /// Gets the [`crate::colorgeo::ColorGrade`] of the given mesh group.
+    pub fn mesh_group_color_grade(
+        &self,
+        which: crate::meshes::MeshGroup,
+    ) -> crate::colorgeo::ColorGrade {
+        self.meshes.color_grade(which)
+    }
+    /// Sets or clears the [`crate::meshes::MeshOutline`] drawn around every instance of the given mesh group.
+    pub fn mesh_group_set_outline(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        outline: Option<crate::meshes::MeshOutline>,
+    ) {
+        self.meshes.set_outline(&self.gpu, which, outline)
+    }
+    /// Gets the [`crate::meshes::MeshOutline`] of the given mesh group, if it has one.
+    pub fn mesh_group_outline(
+        &self,
+        which: crate::meshes::MeshGroup,
+    ) -> Option<crate::meshes::MeshOutline> {
+        self.meshes.outline(which)
     }
-    /// Returns how many meshes there are in the given mesh group.
-    pub fn mesh_group_size(&self, which: crate::meshes::MeshGroup) -> usize {
-        self.meshes.mesh_count(which)
+    /// Sets how the given mesh group is lit, e.g. with [`crate::meshes::ToonShading`].
+    pub fn mesh_group_set_shading(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        shading: crate::meshes::MeshShading,
+    ) {
+        self.meshes.set_shading(&self.gpu, which, shading)
     }
-    /// Returns how many mesh instances there are in the given mesh of the given mesh group.
-    pub fn mesh_instance_count(
+    /// Gets the [`crate::meshes::MeshShading`] of the given mesh group.
+    pub fn mesh_group_shading(
         &self,
         which: crate::meshes::MeshGroup,
-        mesh_number: usize,
-    ) -> usize {
-        self.meshes.mesh_instance_count(which, mesh_number)
+    ) -> crate::meshes::MeshShading {
+        self.meshes.shading(which)
     }
-    /// Change the number of instances of the given mesh of the given mesh group.
-    pub fn mesh_instance_resize(
+    /// Enables or disables per-instance material overrides for the given mesh group's instances.
+    pub fn mesh_group_set_material_overrides(
         &mut self,
         which: crate::meshes::MeshGroup,
-        idx: usize,
-        len: usize,
-    ) -> usize {
-        self.meshes.resize_group_mesh(&self.gpu, which, idx, len)
+        enabled: bool,
+    ) {
+        self.meshes
+            .set_material_overrides(&self.gpu, which, enabled)
     }
-    /// Gets the (mutable) transforms of every instance of the given mesh of a mesh group.
-    /// Since this causes an upload later on, call it as few times as possible per frame.
-    /// Most importantly, don't call it with lots of tiny regions or overlapped regions.
-    pub fn meshes_mut(
+    /// Gets the (mutable) material overrides of every instance of the given mesh of a mesh group.
+    /// These are uploaded along with the instances' transforms.
+    /// Panics if the mesh group does not use material overrides.
+    pub fn mesh_material_overrides_mut(
         &mut self,
         which: crate::meshes::MeshGroup,
         idx: usize,
         range: impl RangeBounds<usize>,
-    ) -> &mut [crate::meshes::Transform3D] {
+    ) -> &mut [crate::meshes::MaterialOverride] {
         let count = self.meshes.mesh_instance_count(which, idx);
         let range = crate::range(range, count);
         self.queued_uploads
             .push(Upload::Mesh(which, idx, range.clone()));
-        let trfs = self.meshes.get_meshes_mut(which, idx);
-        &mut trfs[range]
+        let materials = self.meshes.get_material_overrides_mut(which, idx);
+        &mut materials[range]
     }
 
     /// Sets the given camera for all flat mesh groups.
     pub fn flat_set_camera(&mut self, camera: crate::meshes::Camera3D) {
         self.flats.set_camera(&self.gpu, camera)
     }
+    /// Returns the camera used for all flat mesh groups.
+    pub fn flat_camera(&self) -> crate::meshes::Camera3D {
+        self.flats.camera()
+    }
+    /// Sets whether flat mesh groups added from now on share pooled
+    /// vertex and index buffers, which are compacted automatically
+    /// during rendering; see [`crate::meshes::MeshRenderer::set_pooled`].
+    pub fn flat_set_pooled(&mut self, pooled: bool) {
+        self.flats.set_pooled(pooled)
+    }
+    /// Sets whether flat mesh groups added from now on keep their
+    /// triangles for exact raycasts; see [`crate::raycast`].
+    pub fn flat_set_pick_triangles(&mut self, pick_triangles: bool) {
+        self.flats.set_pick_triangles(pick_triangles)
+    }
+    /// Finds the closest flat mesh instance hit by `ray`, e.g. from
+    /// [`crate::meshes::Camera3D::screen_ray`].
+    pub fn flat_raycast(&self, ray: &crate::meshes::Ray) -> Option<crate::raycast::RayHit> {
+        self.flats.raycast(ray)
+    }
     /// Add a flat mesh group with the given color materials.  All
     /// meshes in the group pull from the same vertex buffer, and each
     /// submesh is defined in terms of a range of indices within that
@@ -687,6 +2590,11 @@ impl Renderer {
     pub fn flat_group_size(&self, which: crate::meshes::MeshGroup) -> usize {
         self.flats.mesh_count(which)
     }
+    /// Describes the given flat mesh group, see
+    /// [`crate::meshes::FlatRenderer::group_info`].
+    pub fn flat_group_info(&self, which: crate::meshes::MeshGroup) -> crate::meshes::MeshGroupInfo {
+        self.flats.group_info(which)
+    }
     /// Returns how many mesh instances there are in the given mesh of the given mesh group.
     pub fn flat_instance_count(
         &self,
@@ -704,6 +2612,115 @@ impl Renderer {
     ) -> usize {
         self.flats.resize_group_mesh(&self.gpu, which, idx, len)
     }
+    /// Adds an instance of the given mesh of the given mesh group,
+    /// returning a handle which stays valid until it's removed; see
+    /// [`crate::meshes::MeshRenderer::add_instance`].
+    pub fn flat_instance_add(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        trf: crate::meshes::Transform3D,
+    ) -> crate::meshes::InstanceHandle {
+        self.flats.add_instance(&self.gpu, which, idx, trf)
+    }
+    /// Removes the instance with the given handle, freeing its slot for reuse.
+    pub fn flat_instance_remove(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        handle: crate::meshes::InstanceHandle,
+    ) {
+        self.flats.remove_instance(&self.gpu, which, handle)
+    }
+    /// Sets whether the given flat group blends its instance
+    /// transforms between simulation steps, see
+    /// [`crate::meshes::FlatRenderer::set_interpolated`].
+    pub fn flat_group_set_interpolated(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        interpolated: bool,
+    ) {
+        self.flats.set_interpolated(which, interpolated)
+    }
+    /// Returns whether the given flat group is interpolated.
+    pub fn flat_group_interpolated(&self, which: crate::meshes::MeshGroup) -> bool {
+        self.flats.interpolated(which)
+    }
+    /// Remembers every interpolated flat group's current instance
+    /// transforms as the previous ones; call this at the start of
+    /// each simulation step.
+    pub fn flat_step_interpolation(&mut self) {
+        self.flats.step_interpolation()
+    }
+    /// Moves instance `index` of mesh `idx` of the given flat group
+    /// straight to its current transform without interpolating.
+    pub fn flat_instance_snap(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+    ) {
+        self.flats.snap_instance(which, idx, index)
+    }
+    /// Uploads every interpolated flat group's instances blended
+    /// between the previous and current simulation steps by `alpha`,
+    /// typically [`crate::clock::Clock::alpha`].
+    pub fn flat_interpolate(&mut self, alpha: f32) {
+        self.flats.interpolate(&self.gpu, alpha)
+    }
+    /// Shows or hides instance `index` of mesh `idx` of the given
+    /// flat group without reordering instances; see
+    /// [`crate::meshes::FlatRenderer::set_instance_visible`].
+    pub fn flat_instance_set_visible(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+        visible: bool,
+    ) {
+        self.flats
+            .set_instance_visible(&self.gpu, which, idx, index, visible)
+    }
+    /// Returns whether instance `index` of mesh `idx` of the given flat group is visible.
+    pub fn flat_instance_visible(
+        &self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+    ) -> bool {
+        self.flats.instance_visible(which, idx, index)
+    }
+    /// Selects or deselects instance `index` of mesh `idx` of the
+    /// given flat group for outlining; see [`Renderer::post_set_outline`].
+    pub fn flat_instance_set_selected(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+        selected: bool,
+    ) {
+        self.flats
+            .set_instance_selected(which, idx, index, selected)
+    }
+    /// Returns whether instance `index` of mesh `idx` of the given flat group is selected.
+    pub fn flat_instance_selected(
+        &self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        index: usize,
+    ) -> bool {
+        self.flats.instance_selected(which, idx, index)
+    }
+    /// Gets the (mutable) transform of the instance with the given handle.
+    pub fn flat_instance_mut(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        handle: crate::meshes::InstanceHandle,
+    ) -> &mut crate::meshes::Transform3D {
+        let (idx, index) = (handle.mesh(), handle.index());
+        self.queued_uploads
+            .push(Upload::Flat(which, idx, index..index + 1));
+        &mut self.flats.get_meshes_mut(which, idx)[index]
+    }
     /// Gets the (mutable) transforms of every instance of the given mesh of a mesh group.
     /// Since this causes an upload later on, call it as few times as possible per frame.
     /// Most importantly, don't call it with lots of tiny regions or overlapped regions.
@@ -720,6 +2737,120 @@ impl Renderer {
         let trfs = self.flats.get_meshes_mut(which, idx);
         &mut trfs[range]
     }
+    /// Enables or disables non-uniform scaling for the given flat mesh group's instances.
+    pub fn flat_group_set_nonuniform_scale(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        enabled: bool,
+    ) {
+        self.flats.set_nonuniform_scale(&self.gpu, which, enabled)
+    }
+    /// Gets the (mutable) non-uniform scale factors of every instance of the given mesh of a flat mesh group.
+    /// These are uploaded along with the instances' transforms.
+    /// Panics if the mesh group does not use non-uniform scaling.
+    pub fn flat_scales_mut(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        range: impl RangeBounds<usize>,
+    ) -> &mut [[f32; 3]] {
+        let count = self.flats.mesh_instance_count(which, idx);
+        let range = crate::range(range, count);
+        self.queued_uploads
+            .push(Upload::Flat(which, idx, range.clone()));
+        let scales = self.flats.get_scales_mut(which, idx);
+        &mut scales[range]
+    }
+    /// Routes `instances` of a logical mesh with several levels of
+    /// detail to the meshes of a flat mesh group according to their distance
+    /// from the current camera; see [`crate::meshes::Lod`].  This
+    /// resizes each level's mesh to fit its instances.
+    pub fn flat_group_apply_lod(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        lod: &mut crate::meshes::Lod,
+        instances: &[crate::meshes::Transform3D],
+    ) {
+        let camera_pos = self.flats.camera().translation;
+        lod.assign(camera_pos, instances, |mesh, level_instances| {
+            self.flats
+                .resize_group_mesh(&self.gpu, which, mesh, level_instances.len());
+            self.queued_uploads
+                .push(Upload::Flat(which, mesh, 0..level_instances.len()));
+            self.flats
+                .get_meshes_mut(which, mesh)
+                .copy_from_slice(level_instances);
+        });
+    }
+    /// Sets the [`crate::colorgeo::ColorGrade`] applied to the given flat mesh group's colors before postprocessing.
+    pub fn flat_group_set_color_grade(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        grade: crate::colorgeo::ColorGrade,
+    ) {
+        self.flats.set_color_grade(&self.gpu, which, grade)
+    }
+    /// Gets the [`crate::colorgeo::ColorGrade`] of the given flat mesh group.
+    pub fn flat_group_color_grade(
+        &self,
+        which: crate::meshes::MeshGroup,
+    ) -> crate::colorgeo::ColorGrade {
+        self.flats.color_grade(which)
+    }
+    /// Sets or clears the [`crate::meshes::MeshOutline`] drawn around every instance of the given flat mesh group.
+    pub fn flat_group_set_outline(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        outline: Option<crate::meshes::MeshOutline>,
+    ) {
+        self.flats.set_outline(&self.gpu, which, outline)
+    }
+    /// Gets the [`crate::meshes::MeshOutline`] of the given flat mesh group, if it has one.
+    pub fn flat_group_outline(
+        &self,
+        which: crate::meshes::MeshGroup,
+    ) -> Option<crate::meshes::MeshOutline> {
+        self.flats.outline(which)
+    }
+    /// Sets how the given flat mesh group is lit, e.g. with [`crate::meshes::ToonShading`].
+    pub fn flat_group_set_shading(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        shading: crate::meshes::MeshShading,
+    ) {
+        self.flats.set_shading(&self.gpu, which, shading)
+    }
+    /// Gets the [`crate::meshes::MeshShading`] of the given flat mesh group.
+    pub fn flat_group_shading(
+        &self,
+        which: crate::meshes::MeshGroup,
+    ) -> crate::meshes::MeshShading {
+        self.flats.shading(which)
+    }
+    /// Enables or disables per-instance material overrides for the given flat mesh group's instances.
+    pub fn flat_group_set_material_overrides(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        enabled: bool,
+    ) {
+        self.flats.set_material_overrides(&self.gpu, which, enabled)
+    }
+    /// Gets the (mutable) material overrides of every instance of the given mesh of a flat mesh group.
+    /// These are uploaded along with the instances' transforms.
+    /// Panics if the mesh group does not use material overrides.
+    pub fn flat_material_overrides_mut(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        idx: usize,
+        range: impl RangeBounds<usize>,
+    ) -> &mut [crate::meshes::MaterialOverride] {
+        let count = self.flats.mesh_instance_count(which, idx);
+        let range = crate::range(range, count);
+        self.queued_uploads
+            .push(Upload::Flat(which, idx, range.clone()));
+        let materials = self.flats.get_material_overrides_mut(which, idx);
+        &mut materials[range]
+    }
     /// Returns the current geometric transform used in postprocessing (a 4x4 column-major homogeneous matrix)
     pub fn post_transform(&self) -> [f32; 16] {
         self.postprocess.transform()
@@ -732,26 +2863,537 @@ impl Renderer {
     pub fn post_saturation(&self) -> f32 {
         self.postprocess.saturation()
     }
-    /// Sets all postprocessing parameters
+    /// Sets all postprocessing parameters, stopping any fade in progress
     pub fn post_set(&mut self, trf: [f32; 16], color_trf: [f32; 16], sat: f32) {
+        self.post_fade = None;
         self.postprocess.set_post(&self.gpu, trf, color_trf, sat);
     }
-    /// Sets the postprocessing geometric transform (a 4x4 column-major homogeneous matrix)
+    /// Sets the postprocessing geometric transform (a 4x4 column-major homogeneous matrix), stopping any fade in progress
     pub fn post_set_transform(&mut self, trf: [f32; 16]) {
+        self.post_fade = None;
         self.postprocess.set_transform(&self.gpu, trf);
     }
-    /// Sets the postprocessing color transform (a 4x4 column-major homogeneous matrix)
+    /// Sets the postprocessing color transform (a 4x4 column-major homogeneous matrix), stopping any fade in progress
     pub fn post_set_color_transform(&mut self, trf: [f32; 16]) {
+        self.post_fade = None;
         self.postprocess.set_color_transform(&self.gpu, trf);
     }
-    /// Sets the postprocessing saturation value (a number between -1 and 1, with 0.0 meaning an identity transformation)
+    /// Sets the postprocessing saturation value (a number between -1 and 1, with 0.0 meaning an identity transformation), stopping any fade in progress
     pub fn post_set_saturation(&mut self, sat: f32) {
+        self.post_fade = None;
         self.postprocess.set_saturation(&self.gpu, sat);
     }
-    /// Sets the postprocessing color lookup table texture
+    /// Moves all postprocessing parameters from their current values
+    /// to the given ones over `seconds` seconds of rendered frames,
+    /// following `easing`, e.g. for day/night cycles or damage
+    /// flashes.  With a `lut`, the color lookup table cross-fades
+    /// to it too, and it becomes the lookup table once the fade ends.
+    /// Starting a new fade replaces any in progress, starting from
+    /// wherever it had got to; a lookup table which was still fading
+    /// in keeps fading in, unless the new fade brings its own, in
+    /// which case the older table snaps to whichever of its two
+    /// tables it was closer to.  Calling [`Renderer::post_automate`]
+    /// stops the fade.
+    pub fn post_fade_to(
+        &mut self,
+        trf: [f32; 16],
+        color_trf: [f32; 16],
+        sat: f32,
+        lut: Option<&wgpu::Texture>,
+        seconds: f32,
+        easing: crate::colorgeo::Easing,
+    ) {
+        let lut_from = match lut {
+            Some(lut) => {
+                if self.postprocess.lut_blend() >= 0.5 {
+                    self.postprocess.commit_next_lut(&self.gpu);
+                }
+                self.postprocess.set_next_lut(&self.gpu, Some(lut));
+                self.postprocess.set_lut_blend(&self.gpu, 0.0);
+                Some(0.0)
+            }
+            None => self
+                .postprocess
+                .has_next_lut()
+                .then(|| self.postprocess.lut_blend()),
+        };
+        self.post_fade = Some(PostFade {
+            from: (
+                self.post_transform(),
+                self.post_color_transform(),
+                self.post_saturation(),
+            ),
+            to: (trf, color_trf, sat),
+            lut_from,
+            seconds,
+            easing,
+            elapsed: None,
+        });
+        self.redraw_requested = true;
+    }
+    /// How far the current postprocessing fade has progressed from
+    /// 0.0 to 1.0 (before easing), or `None` if there isn't one.
+    pub fn post_fade_progress(&self) -> Option<f32> {
+        self.post_fade.map(|fade| match fade.elapsed {
+            Some(elapsed) if fade.seconds > 0.0 => (elapsed / fade.seconds).min(1.0),
+            Some(_) => 1.0,
+            None => 0.0,
+        })
+    }
+    /// Stops the current postprocessing fade, leaving the parameters
+    /// (including any partly faded lookup table) wherever it had got to.
+    pub fn post_cancel_fade(&mut self) {
+        self.post_fade = None;
+    }
+    /// Creates a [`crate::colorgeo::PostAutomation`] targeting the
+    /// current postprocessing parameters, to be modified and passed
+    /// to [`Renderer::post_automate`] each frame.
+    pub fn post_automation(&self, smoothing: f32) -> crate::colorgeo::PostAutomation {
+        crate::colorgeo::PostAutomation::from_current(&self.postprocess, smoothing)
+    }
+    /// Eases the postprocessing parameters towards `automation`'s
+    /// targets, given that `dt` seconds have passed since the last
+    /// call.  Like setting a parameter, this stops any fade in
+    /// progress (see [`Renderer::post_cancel_fade`]), so automation
+    /// wins over [`Renderer::post_fade_to`]; to fade while
+    /// automating, move the automation's targets instead.
+    pub fn post_automate(&mut self, automation: &crate::colorgeo::PostAutomation, dt: f32) {
+        self.post_fade = None;
+        self.postprocess.automate(&self.gpu, automation, dt);
+    }
+    /// Gets the per-channel color curves used in postprocessing
+    pub fn post_curves(&self) -> crate::colorgeo::ColorCurves {
+        self.postprocess.curves()
+    }
+    /// Sets per-channel gamma, contrast, and levels curves for
+    /// postprocessing, applied after the color transform and before
+    /// the lookup table
+    pub fn post_set_curves(&mut self, curves: crate::colorgeo::ColorCurves) {
+        self.postprocess.set_curves(&self.gpu, curves);
+    }
+    /// Sets the postprocessing color lookup table texture, dropping
+    /// any lookup table a fade was blending towards
     pub fn post_set_lut(&mut self, lut: &wgpu::Texture) {
         self.postprocess.replace_lut(&self.gpu, lut);
     }
+    /// Captures the most recently rendered frame (before
+    /// postprocessing) as the reference for [`Renderer::post_set_compare`]
+    pub fn post_capture_reference(&mut self) {
+        self.postprocess
+            .capture_reference(&self.gpu, &self.color_texture);
+    }
+    /// Forgets the reference frame captured by [`Renderer::post_capture_reference`]
+    pub fn post_clear_reference(&mut self) {
+        self.postprocess.clear_reference(&self.gpu);
+    }
+    /// Gets how the live frame is compared against the reference frame
+    pub fn post_compare(&self) -> crate::colorgeo::CompareMode {
+        self.postprocess.compare()
+    }
+    /// Sets how the live frame is compared against the reference frame, e.g. to look for regressions while working on shaders
+    pub fn post_set_compare(&mut self, compare: crate::colorgeo::CompareMode) {
+        self.postprocess.set_compare(&self.gpu, compare);
+    }
+    /// Gets the filter used to stretch the rendered image to the surface
+    pub fn post_filter(&self) -> crate::colorgeo::UpscaleFilter {
+        self.postprocess.filter()
+    }
+    /// Sets the filter used to stretch the rendered image to the surface
+    pub fn post_set_filter(&mut self, filter: crate::colorgeo::UpscaleFilter) {
+        self.postprocess.set_filter(&self.gpu, filter);
+    }
+    /// Gets the outline drawn around selected instances, if any
+    pub fn post_outline(&self) -> Option<crate::colorgeo::Outline> {
+        self.postprocess.outline()
+    }
+    /// Outlines the mesh and flat instances selected with
+    /// [`Renderer::mesh_instance_set_selected`] and
+    /// [`Renderer::flat_instance_set_selected`], e.g. to highlight
+    /// an editor's selection, or stops outlining if `outline` is
+    /// `None`.  Each frame then draws the selected instances into a
+    /// mask the size of the render target, and postprocessing draws
+    /// the outline just outside the mask.  Selected instances are
+    /// outlined even behind other geometry, but not in stereo.
+    pub fn post_set_outline(&mut self, outline: Option<crate::colorgeo::Outline>) {
+        match (outline.is_some(), self.mask_texture.is_some()) {
+            (true, false) => {
+                self.mask_texture = Some(Self::create_color_texture(
+                    self.gpu.device(),
+                    self.render_width,
+                    self.render_height,
+                    crate::meshes::MASK_FORMAT,
+                    "selection_mask",
+                ));
+            }
+            (false, true) => self.mask_texture = None,
+            _ => {}
+        }
+        self.postprocess.replace_mask_texture(
+            &self.gpu,
+            self.mask_texture.as_ref().map(|(texture, _)| texture),
+        );
+        self.postprocess.set_outline(&self.gpu, outline);
+    }
+    /// Captures the most recently rendered frame and transitions from
+    /// it to whatever is rendered next over the given number of
+    /// seconds, e.g. right after switching scenes.  Starting a new
+    /// transition replaces any in progress.
+    pub fn start_transition(&mut self, transition: crate::colorgeo::Transition, seconds: f32) {
+        self.postprocess
+            .capture_transition(&self.gpu, &self.color_texture);
+        self.transition = Some(ActiveTransition {
+            transition,
+            seconds,
+            elapsed: None,
+        });
+        self.redraw_requested = true;
+    }
+    /// How far the current transition has progressed from 0.0 to
+    /// 1.0, or `None` if there isn't one.
+    pub fn transition_progress(&self) -> Option<f32> {
+        self.transition.map(|active| match active.elapsed {
+            Some(elapsed) if active.seconds > 0.0 => (elapsed / active.seconds).min(1.0),
+            Some(_) => 1.0,
+            None => 0.0,
+        })
+    }
+    /// Ends the current transition immediately, if there is one.
+    pub fn cancel_transition(&mut self) {
+        if self.transition.take().is_some() {
+            self.postprocess.set_transition(&self.gpu, None, 1.0);
+        }
+    }
+    /// Returns whether a copy of each frame is being kept; see
+    /// [`Renderer::set_frame_history`].
+    pub fn frame_history_enabled(&self) -> bool {
+        self.history.is_some()
+    }
+    /// Starts or stops keeping a copy of each frame after it's
+    /// postprocessed, so the next frame can draw from it for effects
+    /// like motion trails and feedback zooms.  The copy is the
+    /// render-resolution image postprocessing read, before color
+    /// grading, so feeding it back doesn't grade it over and over.
+    /// It starts out transparent black and is cleared again whenever
+    /// [`Renderer::resize_render`] changes the render size.
+    ///
+    /// [`Renderer::post_set_feedback`] blends it in during
+    /// postprocessing, and [`Renderer::sprite_group_add_frame_history`]
+    /// draws it with sprites.  Drawing a faded or scaled copy of it
+    /// behind the scene accumulates trails from frame to frame.
+    /// Turning history off stops any feedback, and sprite groups
+    /// drawing it keep the last frame copied.
+    pub fn set_frame_history(&mut self, enabled: bool) {
+        if enabled == self.history.is_some() {
+            return;
+        }
+        let history = enabled.then(|| self.create_history_texture());
+        self.replace_history(history);
+        if !enabled {
+            self.history_groups.clear();
+        }
+    }
+    /// Returns the copy of the last frame while frame history is on,
+    /// e.g. for binding in custom passes.  It's an array texture in
+    /// [`Renderer::COLOR_FORMAT`] at the render size with the frame
+    /// in layer 0 (GL backends get a second, unused layer).  The
+    /// texture is replaced when the render size changes.
+    pub fn frame_history(&self) -> Option<&wgpu::Texture> {
+        self.history.as_ref()
+    }
+    /// Like [`Renderer::sprite_group_add`], but drawing from the copy
+    /// of the last frame, turning frame history on if needed; see
+    /// [`Renderer::set_frame_history`].  Sheet regions are in pixels
+    /// of the render size on layer 0, so
+    /// `SheetRegion::new(0, 0, 0, depth, w, h)` with `(w, h)` from
+    /// [`Renderer::render_size`] covers the whole frame.  The group
+    /// follows the history texture across resizes, but its sheet
+    /// regions don't change.
+    pub fn sprite_group_add_frame_history(
+        &mut self,
+        world_transforms: Vec<crate::sprites::Transform>,
+        sheet_regions: Vec<crate::sprites::SheetRegion>,
+        camera: crate::sprites::Camera2D,
+    ) -> usize {
+        self.set_frame_history(true);
+        let history = self.history.as_ref().unwrap();
+        let which = self.sprites.add_sprite_group(
+            &self.gpu,
+            history,
+            world_transforms,
+            sheet_regions,
+            camera,
+        );
+        self.history_groups.push(which);
+        which
+    }
+    /// Gets the feedback blending the last frame in during
+    /// postprocessing, if any
+    pub fn post_feedback(&self) -> Option<crate::colorgeo::Feedback> {
+        self.postprocess.feedback()
+    }
+    /// Blends the last frame into each new one before color grading,
+    /// turning frame history on if needed, or stops if `feedback`
+    /// is `None`; see [`Renderer::set_frame_history`].
+    pub fn post_set_feedback(&mut self, feedback: Option<crate::colorgeo::Feedback>) {
+        if feedback.is_some() {
+            self.set_frame_history(true);
+        }
+        self.postprocess.set_feedback(&self.gpu, feedback);
+    }
+    fn create_history_texture(&self) -> wgpu::Texture {
+        self.gpu.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame_history"),
+            size: wgpu::Extent3d {
+                width: self.render_width,
+                height: self.render_height,
+                // GL treats single-layer array textures as plain 2D ones
+                depth_or_array_layers: if self.gpu.is_gl() { 2 } else { 1 },
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::COLOR_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+    // Swaps in a new history texture everywhere the old one was bound
+    fn replace_history(&mut self, history: Option<wgpu::Texture>) {
+        self.history = history;
+        self.postprocess
+            .replace_history_texture(&self.gpu, self.history.as_ref());
+        if let Some(history) = self.history.as_ref() {
+            for &which in self.history_groups.iter() {
+                self.sprites.replace_texture(&self.gpu, which, history);
+            }
+        }
+    }
+    // Copies the frame just drawn into the history, after
+    // postprocessing has read the previous one
+    fn history_copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(history) = self.history.as_ref() else {
+            return;
+        };
+        encoder.copy_texture_to_texture(
+            self.color_texture.as_image_copy(),
+            history.as_image_copy(),
+            self.color_texture.size(),
+        );
+    }
+    /// Starts or stops drawing the mesh scene mirrored about `plane`
+    /// for groups with [`crate::meshes::MeshShading::Water`], e.g. a
+    /// flat group of water tiles.  Before each frame's main pass, the
+    /// terrain and every non-water mesh and flat group are drawn
+    /// again through the mesh and flat cameras mirrored with
+    /// [`crate::meshes::Camera3D::mirrored`] into a texture at the
+    /// render size.  Sprites aren't reflected, nor is anything
+    /// clipped away, so keep things under the water out of view.
+    /// Stereo frames reflect the scene as the unshifted cameras see it.
+    pub fn reflection_set(&mut self, plane: Option<crate::meshes::ReflectionPlane>) {
+        let Some(plane) = plane else {
+            self.reflection = None;
+            self.meshes.set_reflection(&self.gpu, None);
+            self.flats.set_reflection(&self.gpu, None);
+            return;
+        };
+        let (w, h) = (self.render_width, self.render_height);
+        let reflection = Reflection {
+            plane,
+            color: Self::create_color_texture(
+                self.gpu.device(),
+                w,
+                h,
+                Self::COLOR_FORMAT,
+                "reflection",
+            ),
+            depth: Self::create_depth_texture(self.gpu.device(), w, h),
+        };
+        self.meshes
+            .set_reflection(&self.gpu, Some(&reflection.color.1));
+        self.flats
+            .set_reflection(&self.gpu, Some(&reflection.color.1));
+        self.reflection = Some(reflection);
+    }
+    /// Returns the plane the scene is mirrored about for water
+    /// groups, if any; see [`Renderer::reflection_set`].
+    pub fn reflection_plane(&self) -> Option<crate::meshes::ReflectionPlane> {
+        self.reflection.as_ref().map(|reflection| reflection.plane)
+    }
+    /// Returns the mirrored scene drawn for water groups, if any,
+    /// e.g. for binding in custom passes.  Like the color texture,
+    /// it's replaced when the render size changes.
+    pub fn reflection_texture(&self) -> Option<&wgpu::Texture> {
+        self.reflection
+            .as_ref()
+            .map(|reflection| &reflection.color.0)
+    }
+    // Draws the mirrored scene for water groups, if there's a
+    // reflection.  Like the stereo passes, this gets its own encoder
+    // so the mirrored cameras' uploads take effect, and restores the
+    // cameras afterwards.
+    fn reflection_pass(&mut self) {
+        let Some(plane) = self.reflection.as_ref().map(|reflection| reflection.plane) else {
+            return;
+        };
+        trace_span!("frenderer:reflection_pass");
+        let mesh_camera = self.meshes.camera();
+        let flat_camera = self.flats.camera();
+        self.meshes
+            .set_camera(&self.gpu, mesh_camera.mirrored(plane));
+        self.flats
+            .set_camera(&self.gpu, flat_camera.mirrored(plane));
+        if let Some(terrain) = self.terrain.as_mut() {
+            terrain.set_camera(&self.gpu, mesh_camera.mirrored(plane));
+        }
+        let mut encoder =
+            self.gpu
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("frenderer:reflection_encoder"),
+                });
+        {
+            let reflection = self.reflection.as_ref().unwrap();
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("frenderer:reflection_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &reflection.color.1,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &reflection.depth.1,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                ..Default::default()
+            });
+            if let Some(terrain) = self.terrain.as_ref() {
+                terrain.render(&mut rpass);
+            }
+            self.meshes.render_reflection(&mut rpass, ..);
+            self.flats.render_reflection(&mut rpass, ..);
+        }
+        self.gpu.queue().submit(Some(encoder.finish()));
+        self.meshes.set_camera(&self.gpu, mesh_camera);
+        self.flats.set_camera(&self.gpu, flat_camera);
+        if let Some(terrain) = self.terrain.as_mut() {
+            terrain.set_camera(&self.gpu, mesh_camera);
+        }
+    }
+    /// Returns the mesh and flat groups, their instances, and the 3D
+    /// cameras as a self-contained glTF document for debugging; see
+    /// [`crate::export`].
+    #[cfg(feature = "debug")]
+    pub fn export_scene_gltf(&self) -> String {
+        crate::export::scene_gltf(Some(&self.meshes), Some(&self.flats))
+    }
+    /// Names sprite group `which`, or removes its name; see
+    /// [`crate::sprites::SpriteRenderer::set_group_name`].
+    pub fn sprite_group_set_name(&mut self, which: usize, name: Option<&str>) {
+        self.sprites.set_group_name(&self.gpu, which, name)
+    }
+    /// Returns the name of sprite group `which`, if it has one.
+    pub fn sprite_group_name(&self, which: usize) -> Option<&str> {
+        self.sprites.group_name(which)
+    }
+    /// Names the given mesh group, or removes its name; see
+    /// [`crate::meshes::MeshRenderer::set_group_name`].
+    pub fn mesh_group_set_name(&mut self, which: crate::meshes::MeshGroup, name: Option<&str>) {
+        self.meshes.set_group_name(&self.gpu, which, name)
+    }
+    /// Returns the name of the given mesh group, if it has one.
+    pub fn mesh_group_name(&self, which: crate::meshes::MeshGroup) -> Option<&str> {
+        self.meshes.group_name(which)
+    }
+    /// Names the given flat group, or removes its name; see
+    /// [`crate::meshes::FlatRenderer::set_group_name`].
+    pub fn flat_group_set_name(&mut self, which: crate::meshes::MeshGroup, name: Option<&str>) {
+        self.flats.set_group_name(&self.gpu, which, name)
+    }
+    /// Returns the name of the given flat group, if it has one.
+    pub fn flat_group_name(&self, which: crate::meshes::MeshGroup) -> Option<&str> {
+        self.flats.group_name(which)
+    }
+    /// Returns the bind group layouts of the built-in renderers and
+    /// postprocessing, so custom pipelines can bind their bind groups
+    /// (e.g. [`crate::sprites::SpriteRenderer::group_bind_groups`])
+    /// or make compatible ones.
+    pub fn bind_group_layouts(&self) -> BindGroupLayouts<'_> {
+        BindGroupLayouts {
+            sprites: self.sprites.layouts(),
+            meshes: self.meshes.layouts(),
+            flats: self.flats.layouts(),
+            post: self.postprocess.layouts(),
+        }
+    }
+    /// Lists the GPU buffers and textures the renderer and its
+    /// built-in renderers keep alive, with their labels and sizes;
+    /// see [`crate::resources`].  Textures passed in by the caller
+    /// (e.g. spritesheets) and buffers only reachable through bind
+    /// groups aren't included.
+    pub fn live_resources(&self) -> Vec<crate::resources::GpuResource> {
+        use crate::resources::GpuResource;
+        let mut resources = vec![
+            GpuResource::texture(Some("color".to_string()), &self.color_texture),
+            GpuResource::texture(Some("depth".to_string()), &self.depth_texture),
+        ];
+        if let Some((mask, _)) = self.mask_texture.as_ref() {
+            resources.push(GpuResource::texture(
+                Some("selection_mask".to_string()),
+                mask,
+            ));
+        }
+        if let Some(history) = self.history.as_ref() {
+            resources.push(GpuResource::texture(
+                Some("frame_history".to_string()),
+                history,
+            ));
+        }
+        self.postprocess.collect_resources(&mut resources);
+        self.sprites.collect_resources(&mut resources);
+        self.meshes.collect_resources(&mut resources);
+        self.flats.collect_resources(&mut resources);
+        if let Some(terrain) = self.terrain.as_ref() {
+            terrain.collect_resources(&mut resources);
+        }
+        if let Some(reflection) = self.reflection.as_ref() {
+            resources.push(GpuResource::texture(
+                Some("reflection".to_string()),
+                &reflection.color.0,
+            ));
+            resources.push(GpuResource::texture(
+                Some("reflection_depth".to_string()),
+                &reflection.depth.0,
+            ));
+        }
+        resources
+    }
+    /// Returns [`Renderer::live_resources`] as a table, one resource
+    /// per line, largest first, followed by the total size; handy
+    /// for logging.
+    pub fn resource_report(&self) -> String {
+        use std::fmt::Write;
+        let mut resources = self.live_resources();
+        resources.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.label.cmp(&b.label)));
+        let mut report = String::new();
+        for resource in resources.iter() {
+            writeln!(report, "{resource}").unwrap();
+        }
+        write!(
+            report,
+            "{} resources, {} bytes",
+            resources.len(),
+            resources.iter().map(|r| r.bytes).sum::<u64>()
+        )
+        .unwrap();
+        report
+    }
     /// Gets the surface configuration
     pub fn config(&self) -> &wgpu::SurfaceConfiguration {
         &self.config
@@ -774,3 +3416,37 @@ impl Frenderer for Renderer {
 
 mod immediate;
 pub use immediate::Immediate;
+mod writer;
+pub use writer::SpriteGroupWriter;
+mod timing;
+use timing::FrameQueries;
+pub use timing::GpuTiming;
+mod fence;
+pub use fence::UploadFence;
+mod bake;
+mod graph;
+pub use graph::{FrameDraw, FrameGraph, FramePass, FrameUpload};
+mod latency;
+pub use latency::FrameLatency;
+mod parallel;
+pub use parallel::{SpriteChunk, PAR_CHUNK_SIZE};
+
+// The surface format with and without its sRGB suffix, so either
+// color space's output view can be created
+fn surface_view_formats(format: wgpu::TextureFormat) -> Vec<wgpu::TextureFormat> {
+    let mut formats = vec![format];
+    for view in [format.add_srgb_suffix(), format.remove_srgb_suffix()] {
+        if !formats.contains(&view) {
+            formats.push(view);
+        }
+    }
+    formats
+}
+
+// The camera moved by `offset` in its own space, e.g. for one eye
+fn offset_camera(mut camera: crate::meshes::Camera3D, offset: [f32; 3]) -> crate::meshes::Camera3D {
+    let rot = ultraviolet::Rotor3::from_quaternion_array(camera.rotation);
+    let offset = rot * ultraviolet::Vec3::from(offset);
+    camera.translation = (ultraviolet::Vec3::from(camera.translation) + offset).into();
+    camera
+}
@@ -0,0 +1,128 @@
+use super::*;
+use crate::sprites::SheetRegion;
+
+impl Renderer {
+    /// Creates an array texture of `layers` layers which the built-in
+    /// renderers can draw into with [`Renderer::render_to_layer`] and
+    /// [`Renderer::render_to_region`], and which sprite groups can
+    /// then use as their spritesheet (e.g. for baked imposters or
+    /// cached UI panels).  Its format is [`Renderer::COLOR_FORMAT`].
+    pub fn create_render_array_texture(
+        &self,
+        (width, height): (u32, u32),
+        layers: u32,
+        label: Option<&str>,
+    ) -> wgpu::Texture {
+        self.gpu.device().create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers.max(1),
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::COLOR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+    /// Sends queued uploads, then clears array layer `layer` of
+    /// `texture` to `clear` and calls `draw` with a render pass
+    /// targeting it, e.g. to call [`Renderer::render_into`] or
+    /// [`Renderer::sprite_render_groups`].  The pass is submitted
+    /// right away.  `texture` needs the
+    /// [`wgpu::TextureUsages::RENDER_ATTACHMENT`] usage and
+    /// [`Renderer::COLOR_FORMAT`] as its format or one of its view
+    /// formats, like textures from
+    /// [`Renderer::create_render_array_texture`].  Cameras map onto
+    /// the whole layer, so a sprite group's camera should usually
+    /// have the layer's size as its `screen_size`.
+    ///
+    /// Each call makes a depth buffer the size of the layer, so this
+    /// suits occasional baking better than every frame.
+    pub fn render_to_layer(
+        &mut self,
+        texture: &wgpu::Texture,
+        layer: u32,
+        clear: wgpu::Color,
+        draw: impl for<'a> FnOnce(&'a Renderer, &mut wgpu::RenderPass<'a>),
+    ) {
+        let region = SheetRegion::layer(texture, layer as u16, 0);
+        self.bake_pass(texture, region, wgpu::LoadOp::Clear(clear), draw);
+    }
+    /// Like [`Renderer::render_to_layer`], but draws only into the
+    /// rectangle `region` of layer `region.sheet`, keeping what's
+    /// already there.  Cameras map onto just the rectangle, so the
+    /// same `region` can later be drawn by sprites to show what was
+    /// baked, e.g. cells from [`SheetRegion::grid_cell`].
+    pub fn render_to_region(
+        &mut self,
+        texture: &wgpu::Texture,
+        region: SheetRegion,
+        draw: impl for<'a> FnOnce(&'a Renderer, &mut wgpu::RenderPass<'a>),
+    ) {
+        self.bake_pass(texture, region, wgpu::LoadOp::Load, draw);
+    }
+    fn bake_pass(
+        &mut self,
+        texture: &wgpu::Texture,
+        region: SheetRegion,
+        load: wgpu::LoadOp<wgpu::Color>,
+        draw: impl for<'a> FnOnce(&'a Renderer, &mut wgpu::RenderPass<'a>),
+    ) {
+        trace_span!("frenderer:bake_pass", layer = region.sheet);
+        self.do_uploads();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("frenderer:bake_layer"),
+            format: Some(Self::COLOR_FORMAT),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: region.sheet as u32,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+        let (_depth, depth_view) =
+            Self::create_depth_texture(self.gpu.device(), texture.width(), texture.height());
+        let mut encoder =
+            self.gpu
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("frenderer:bake_encoder"),
+                });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("frenderer:bake_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                ..Default::default()
+            });
+            let (x, y) = (region.x as u32, region.y as u32);
+            let (w, h) = (
+                region.w.unsigned_abs() as u32,
+                region.h.unsigned_abs() as u32,
+            );
+            rpass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0.0, 1.0);
+            rpass.set_scissor_rect(x, y, w, h);
+            draw(self, &mut rpass);
+        }
+        self.gpu.queue().submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,73 @@
+use super::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Whether the GPU has finished the uploads queued before it was
+/// made, from [`Renderer::upload_fence`].  Clones share the same
+/// fence, so one can be handed to e.g. a loading screen.
+#[derive(Clone, Debug)]
+pub struct UploadFence {
+    done: Arc<AtomicBool>,
+}
+
+impl UploadFence {
+    /// Whether every upload queued before this fence has finished.
+    /// The renderer checks outstanding fences at the start of each
+    /// frame, so this becomes true a frame or so after the GPU is done.
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::Acquire)
+    }
+}
+
+impl Renderer {
+    /// How many sprite, mesh, and flat uploads are waiting for the
+    /// next [`Renderer::do_uploads`] (or frame).
+    pub fn pending_uploads(&self) -> usize {
+        self.queued_uploads.len()
+    }
+    /// How many fences from [`Renderer::upload_fence`] haven't been
+    /// signaled yet.
+    pub fn pending_upload_fences(&self) -> usize {
+        self.upload_fences.len()
+    }
+    /// Sends all queued uploads to the GPU now and returns a fence
+    /// which is signaled once they, and everything else written so
+    /// far (e.g. textures from [`Renderer::create_array_texture`] and
+    /// new groups' buffers), are resident.  To show a loading screen,
+    /// create a level's resources, take a fence, and keep drawing the
+    /// loading screen until [`UploadFence::is_done`].
+    pub fn upload_fence(&mut self) -> UploadFence {
+        self.do_uploads();
+        // Queued writes only go out with a submission, so don't wait for the next frame
+        self.gpu.queue().submit(std::iter::empty());
+        let done = Arc::new(AtomicBool::new(false));
+        let signal = Arc::clone(&done);
+        self.gpu
+            .queue()
+            .on_submitted_work_done(move || signal.store(true, Ordering::Release));
+        let fence = UploadFence { done };
+        self.upload_fences.push(fence.clone());
+        fence
+    }
+    /// Blocks until `fence` is signaled.  On the web, where the GPU
+    /// can't be waited on, this only checks without blocking.
+    pub fn wait_for_uploads(&mut self, fence: &UploadFence) {
+        #[cfg(not(target_arch = "wasm32"))]
+        while !fence.is_done() {
+            self.gpu.device().poll(wgpu::Maintain::Wait);
+        }
+        self.poll_upload_fences();
+    }
+    // Drives fence callbacks and forgets signaled fences; while any
+    // are left, keeps frames coming so they're checked again
+    pub(super) fn poll_upload_fences(&mut self) {
+        if self.upload_fences.is_empty() {
+            return;
+        }
+        self.gpu.device().poll(wgpu::Maintain::Poll);
+        self.upload_fences.retain(|fence| !fence.is_done());
+        if !self.upload_fences.is_empty() {
+            self.redraw_requested = true;
+        }
+    }
+}
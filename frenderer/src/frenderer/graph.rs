@@ -0,0 +1,374 @@
+use super::*;
+use std::fmt::Write;
+
+/// The structure of one frame drawn by [`Renderer::render`] or
+/// [`Renderer::render_with`], from [`Renderer::capture_frame_graph`]:
+/// the uploads sent before drawing, then each pass in order with the
+/// targets it reads and writes and the groups it draws.  Write it
+/// out with [`FrameGraph::to_dot`] for Graphviz or
+/// [`FrameGraph::to_json`] for other tools, e.g. to see where a
+/// custom pass would fit or why a group isn't showing up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameGraph {
+    /// The uploads sent to the GPU before the passes, in order
+    pub uploads: Vec<FrameUpload>,
+    /// The passes, in the order they were encoded
+    pub passes: Vec<FramePass>,
+}
+
+/// Instance data sent to the GPU during a captured frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameUpload {
+    /// `sprites`, `meshes`, or `flats`
+    pub renderer: &'static str,
+    /// The group's index
+    pub group: usize,
+    /// The mesh number within mesh and flat groups
+    pub mesh: Option<usize>,
+    /// Which sprites or instances were uploaded
+    pub range: Range<usize>,
+}
+
+/// One render pass of a captured frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FramePass {
+    /// The pass's name, e.g. `main pass`
+    pub name: String,
+    /// The textures the pass samples
+    pub reads: Vec<String>,
+    /// The textures the pass draws into
+    pub writes: Vec<String>,
+    /// What the pass draws, in order
+    pub draws: Vec<FrameDraw>,
+}
+
+/// One group drawn by a pass of a captured frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameDraw {
+    /// `sprites`, `meshes`, `flats`, or `postprocess`
+    pub renderer: &'static str,
+    /// The group's index
+    pub group: usize,
+    /// The group's name, if it has one
+    pub name: Option<String>,
+    /// How many sprites or visible instances were drawn; a group
+    /// with none is listed so that it's clear it was skipped
+    pub count: usize,
+}
+
+impl FrameDraw {
+    fn label(&self) -> String {
+        let mut label = format!("{}[{}]", self.renderer, self.group);
+        if let Some(name) = self.name.as_deref() {
+            write!(label, " {name}").unwrap();
+        }
+        write!(label, ": {}", self.count).unwrap();
+        if self.count == 0 {
+            label.push_str(" (skipped)");
+        }
+        label
+    }
+}
+
+impl FrameUpload {
+    fn label(&self) -> String {
+        match self.mesh {
+            Some(mesh) => format!(
+                "{}[{}] mesh {mesh}: {:?}",
+                self.renderer, self.group, self.range
+            ),
+            None => format!("{}[{}]: {:?}", self.renderer, self.group, self.range),
+        }
+    }
+}
+
+impl FrameGraph {
+    /// A Graphviz DOT digraph of the frame: uploads, passes, and the
+    /// textures linking them.  Render it with e.g. `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph frame {\n  rankdir=LR;\n");
+        let mut upload_lines = format!("uploads ({})", self.uploads.len());
+        for upload in self.uploads.iter() {
+            write!(upload_lines, "\\l{}", dot_escape(&upload.label())).unwrap();
+        }
+        writeln!(dot, "  uploads [shape=note, label=\"{upload_lines}\\l\"];").unwrap();
+        let mut targets: Vec<&str> = vec![];
+        for (idx, pass) in self.passes.iter().enumerate() {
+            let mut lines = dot_escape(&pass.name);
+            for draw in pass.draws.iter() {
+                write!(lines, "\\l{}", dot_escape(&draw.label())).unwrap();
+            }
+            writeln!(dot, "  pass{idx} [shape=box, label=\"{lines}\\l\"];").unwrap();
+            if idx == 0 {
+                writeln!(dot, "  uploads -> pass{idx} [style=dashed];").unwrap();
+            } else {
+                writeln!(dot, "  pass{} -> pass{idx} [style=dotted];", idx - 1).unwrap();
+            }
+            for target in pass.reads.iter().chain(pass.writes.iter()) {
+                if !targets.contains(&target.as_str()) {
+                    targets.push(target);
+                    writeln!(
+                        dot,
+                        "  \"{0}\" [shape=ellipse, label=\"{0}\"];",
+                        dot_escape(target)
+                    )
+                    .unwrap();
+                }
+            }
+            for read in pass.reads.iter() {
+                writeln!(dot, "  \"{}\" -> pass{idx};", dot_escape(read)).unwrap();
+            }
+            for write in pass.writes.iter() {
+                writeln!(dot, "  pass{idx} -> \"{}\";", dot_escape(write)).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+    /// A JSON object with `uploads` and `passes` arrays mirroring
+    /// the fields of [`FrameGraph`].
+    pub fn to_json(&self) -> String {
+        let uploads: Vec<_> = self
+            .uploads
+            .iter()
+            .map(|upload| {
+                format!(
+                    r#"{{"renderer":{},"group":{},"mesh":{},"start":{},"end":{}}}"#,
+                    json_string(upload.renderer),
+                    upload.group,
+                    upload
+                        .mesh
+                        .map_or("null".to_string(), |mesh| mesh.to_string()),
+                    upload.range.start,
+                    upload.range.end
+                )
+            })
+            .collect();
+        let passes: Vec<_> = self
+            .passes
+            .iter()
+            .map(|pass| {
+                let strings = |items: &[String]| {
+                    let items: Vec<_> = items.iter().map(|item| json_string(item)).collect();
+                    format!("[{}]", items.join(","))
+                };
+                let draws: Vec<_> = pass
+                    .draws
+                    .iter()
+                    .map(|draw| {
+                        format!(
+                            r#"{{"renderer":{},"group":{},"name":{},"count":{}}}"#,
+                            json_string(draw.renderer),
+                            draw.group,
+                            draw.name.as_deref().map_or("null".to_string(), json_string),
+                            draw.count
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"{{"name":{},"reads":{},"writes":{},"draws":[{}]}}"#,
+                    json_string(&pass.name),
+                    strings(&pass.reads),
+                    strings(&pass.writes),
+                    draws.join(",")
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"uploads":[{}],"passes":[{}]}}"#,
+            uploads.join(","),
+            passes.join(",")
+        )
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+impl Renderer {
+    /// Records the structure of the next frame drawn by
+    /// [`Renderer::render`] or [`Renderer::render_with`], to be
+    /// picked up afterwards with [`Renderer::take_frame_graph`].
+    pub fn capture_frame_graph(&mut self) {
+        self.frame_graph_recording = Some(FrameGraph::default());
+    }
+    /// Returns the most recently captured [`FrameGraph`], if a frame
+    /// has been drawn since [`Renderer::capture_frame_graph`].
+    pub fn take_frame_graph(&mut self) -> Option<FrameGraph> {
+        self.frame_graph.take()
+    }
+    // Notes uploads sent while a frame is being captured
+    pub(super) fn record_upload(&mut self, upload: &Upload) {
+        let Some(graph) = self.frame_graph_recording.as_mut() else {
+            return;
+        };
+        graph.uploads.push(match upload {
+            Upload::Mesh(group, mesh, range) => FrameUpload {
+                renderer: "meshes",
+                group: group.index(),
+                mesh: Some(*mesh),
+                range: range.clone(),
+            },
+            Upload::Flat(group, mesh, range) => FrameUpload {
+                renderer: "flats",
+                group: group.index(),
+                mesh: Some(*mesh),
+                range: range.clone(),
+            },
+            Upload::Sprite(group, range) => FrameUpload {
+                renderer: "sprites",
+                group: *group,
+                mesh: None,
+                range: range.clone(),
+            },
+        });
+    }
+    // Finishes capturing a frame given where it was drawn: the
+    // caller's targets if `direct`, otherwise the renderer's own
+    // textures followed by postprocessing into `output`
+    pub(super) fn finish_frame_graph(&mut self, direct: bool, output: &str) {
+        let Some(mut graph) = self.frame_graph_recording.take() else {
+            return;
+        };
+        let draws = self.frame_graph_draws();
+        let (color, depth) = if direct {
+            (output, "target depth")
+        } else {
+            ("color", "depth")
+        };
+        let eyes: &[&str] = match self.stereo {
+            Some(_) if !direct => &["left eye pass", "right eye pass"],
+            _ => &["main pass"],
+        };
+        let mut main_reads = vec![];
+        if self.reflection.is_some() {
+            graph.passes.push(FramePass {
+                name: "reflection pass".to_string(),
+                reads: vec![],
+                writes: vec!["reflection".to_string(), "reflection_depth".to_string()],
+                draws: draws
+                    .iter()
+                    .filter(|draw| draw.renderer != "sprites")
+                    .cloned()
+                    .collect(),
+            });
+            main_reads.push("reflection".to_string());
+        }
+        for eye in eyes {
+            graph.passes.push(FramePass {
+                name: eye.to_string(),
+                reads: main_reads.clone(),
+                writes: vec![color.to_string(), depth.to_string()],
+                draws: draws.clone(),
+            });
+        }
+        if !direct {
+            let mut post_reads = vec!["color".to_string()];
+            if self.mask_texture.is_some() {
+                let selected = if self.stereo.is_some() {
+                    vec![]
+                } else {
+                    self.frame_graph_selected()
+                };
+                graph.passes.push(FramePass {
+                    name: "mask pass".to_string(),
+                    reads: vec![],
+                    writes: vec!["selection_mask".to_string()],
+                    draws: selected,
+                });
+                post_reads.push("selection_mask".to_string());
+            }
+            graph.passes.push(FramePass {
+                name: "post pass".to_string(),
+                reads: post_reads,
+                writes: vec![output.to_string()],
+                draws: vec![FrameDraw {
+                    renderer: "postprocess",
+                    group: 0,
+                    name: None,
+                    count: 1,
+                }],
+            });
+        }
+        self.frame_graph = Some(graph);
+    }
+    // The groups drawn by render_into, in order
+    fn frame_graph_draws(&self) -> Vec<FrameDraw> {
+        let mut draws = vec![];
+        for (renderer, summaries) in [
+            ("meshes", self.meshes.group_summaries()),
+            ("flats", self.flats.group_summaries()),
+        ] {
+            draws.extend(
+                summaries
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(group, summary)| {
+                        let (visible, _, name) = summary?;
+                        Some(FrameDraw {
+                            renderer,
+                            group,
+                            name: name.map(str::to_string),
+                            count: visible,
+                        })
+                    }),
+            );
+        }
+        draws.extend(
+            self.sprites
+                .group_summaries()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(group, summary)| {
+                    let (count, name) = summary?;
+                    Some(FrameDraw {
+                        renderer: "sprites",
+                        group,
+                        name: name.map(str::to_string),
+                        count,
+                    })
+                }),
+        );
+        draws
+    }
+    // The groups with selected instances drawn into the selection mask
+    fn frame_graph_selected(&self) -> Vec<FrameDraw> {
+        let mut draws = vec![];
+        for (renderer, summaries) in [
+            ("meshes", self.meshes.group_summaries()),
+            ("flats", self.flats.group_summaries()),
+        ] {
+            draws.extend(
+                summaries
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(group, summary)| {
+                        let (_, selected, name) = summary?;
+                        (selected > 0).then(|| FrameDraw {
+                            renderer,
+                            group,
+                            name: name.map(str::to_string),
+                            count: selected,
+                        })
+                    }),
+            );
+        }
+        draws
+    }
+}
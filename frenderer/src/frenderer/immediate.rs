@@ -9,10 +9,12 @@ pub struct Immediate {
     flats_used: Vec<Vec<usize>>,
     meshes_used: Vec<Vec<usize>>,
     sprites_used: Vec<usize>,
+    // Sprites from draw_sprites_iter which didn't fit the space reserved for them
+    sprite_staging: Vec<(crate::sprites::Transform, crate::sprites::SheetRegion)>,
     auto_clear: bool,
 }
 impl Immediate {
-    /// Permanently converts a [Renderer] into an [Immediate].
+    /// Converts a [Renderer] into an [Immediate].
     pub fn new(renderer: Renderer) -> Self {
         Self {
             auto_clear: true,
@@ -23,9 +25,16 @@ impl Immediate {
                 .map(|mg| vec![0; renderer.mesh_group_size(mg.into())])
                 .collect(),
             sprites_used: vec![0; renderer.sprite_group_count()],
+            sprite_staging: vec![],
             renderer,
         }
     }
+    /// Converts back into the wrapped [Renderer], e.g. to hand it to
+    /// something else once a loading screen is done with it.  Sprites
+    /// and meshes drawn since the last frame stay in their groups.
+    pub fn into_renderer(self) -> Renderer {
+        self.renderer
+    }
     /// Whether this renderer should clear its counters/state during rendering.  If set to false, it will accumulate drawing commands from multiple frames until [Immediate::clear] is called.
     pub fn auto_clear(&mut self, c: bool) {
         self.auto_clear = c;
@@ -47,13 +56,53 @@ impl Immediate {
     pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
         self.renderer.set_present_mode(mode)
     }
+    /// Returns the most frames queued ahead of the display, see [`Renderer::max_frame_latency`]
+    pub fn max_frame_latency(&self) -> u32 {
+        self.renderer.max_frame_latency()
+    }
+    /// Sets the most frames queued ahead of the display, see [`Renderer::set_max_frame_latency`]
+    pub fn set_max_frame_latency(&mut self, frames: u32) {
+        self.renderer.set_max_frame_latency(frames)
+    }
+    /// Measures how far the GPU is running behind, see [`Renderer::frame_latency`]
+    pub fn frame_latency(&self) -> super::FrameLatency {
+        self.renderer.frame_latency()
+    }
+    /// Returns whether frames are only drawn on demand
+    pub fn render_on_demand(&self) -> bool {
+        self.renderer.render_on_demand()
+    }
+    /// Only draw frames when input arrives or one is requested, see [`Renderer::set_render_on_demand`]
+    pub fn set_render_on_demand(&mut self, on_demand: bool) {
+        self.renderer.set_render_on_demand(on_demand)
+    }
+    /// Asks for another frame to be drawn in render-on-demand mode
+    pub fn request_redraw(&mut self) {
+        self.renderer.request_redraw()
+    }
+    /// Returns how many uploads are queued, see [`Renderer::pending_uploads`]
+    pub fn pending_uploads(&self) -> usize {
+        self.renderer.pending_uploads()
+    }
+    /// Sends queued uploads now and returns a fence for them, see [`Renderer::upload_fence`]
+    pub fn upload_fence(&mut self) -> super::UploadFence {
+        self.renderer.upload_fence()
+    }
+    /// Turns GPU timing on or off, see [`Renderer::set_gpu_timing`]
+    pub fn set_gpu_timing(&mut self, enabled: bool) {
+        self.renderer.set_gpu_timing(enabled)
+    }
+    /// Returns the most recent per-pass and per-group GPU timings
+    pub fn gpu_timings(&self) -> &[super::GpuTiming] {
+        self.renderer.gpu_timings()
+    }
     /// Returns the current surface
     pub fn surface(&self) -> Option<&wgpu::Surface<'static>> {
         self.renderer.surface()
     }
     /// Creates a new surface for this renderer
-    pub fn create_surface(&mut self, window: Arc<winit::window::Window>) {
-        self.renderer.create_surface(window)
+    pub fn create_surface(&mut self, target: impl Into<wgpu::SurfaceTarget<'static>>) {
+        self.renderer.create_surface(target)
     }
     /// Resize the internal surface texture (typically called when the window or canvas size changes).
     pub fn resize_surface(&mut self, w: u32, h: u32) {
@@ -71,6 +120,10 @@ impl Immediate {
     pub fn render(&mut self) {
         // upload affected ranges
         for (sg, used) in self.sprites_used.iter_mut().enumerate() {
+            // Skip the slots of removed groups
+            if !self.renderer.sprites.sprite_group_populated(sg) {
+                continue;
+            }
             self.renderer
                 .sprites
                 .resize_sprite_group(&self.renderer.gpu, sg, *used);
@@ -123,6 +176,30 @@ impl Immediate {
     pub fn render_size(&self) -> (u32, u32) {
         self.renderer.render_size()
     }
+    /// Returns the surface size as the user sees it, see [`Renderer::upright_surface_size`]
+    pub fn upright_surface_size(&self) -> (u32, u32) {
+        self.renderer.upright_surface_size()
+    }
+    /// Returns whether the surface is wider or taller, see [`Renderer::orientation`]
+    pub fn orientation(&self) -> super::Orientation {
+        self.renderer.orientation()
+    }
+    /// Returns how far the final image is turned, see [`Renderer::surface_rotation`]
+    pub fn surface_rotation(&self) -> crate::colorgeo::SurfaceRotation {
+        self.renderer.surface_rotation()
+    }
+    /// Turns the final image to match the display, see [`Renderer::set_surface_rotation`]
+    pub fn set_surface_rotation(&mut self, rotation: crate::colorgeo::SurfaceRotation) {
+        self.renderer.set_surface_rotation(rotation)
+    }
+    /// Returns the window's scale factor, see [`Renderer::scale_factor`].
+    pub fn scale_factor(&self) -> f32 {
+        self.renderer.scale_factor()
+    }
+    /// Converts points to render pixels, see [`Renderer::points_to_render_pixels`].
+    pub fn points_to_render_pixels(&self, points: f32) -> f32 {
+        self.renderer.points_to_render_pixels(points)
+    }
     /// Creates an array texture on the renderer's GPU.
     pub fn create_array_texture(
         &self,
@@ -145,6 +222,11 @@ impl Immediate {
         self.renderer
             .create_texture(image, format, (width, height), label)
     }
+    /// Makes a magenta and black checkerboard stand-in texture.
+    #[cfg(feature = "bundled-assets")]
+    pub fn placeholder_texture(&self) -> wgpu::Texture {
+        self.renderer.placeholder_texture()
+    }
     /// Create a new sprite group sized to fit `count_estimate`.
     /// Returns the sprite group index corresponding to this group.
     pub fn sprite_group_add(
@@ -162,6 +244,24 @@ impl Immediate {
         self.sprites_used.resize(group_count + 1, 0);
         group_count
     }
+    /// Like [`Immediate::sprite_group_add`], but sampling `tex` through a custom view.
+    pub fn sprite_group_add_with_view(
+        &mut self,
+        tex: &wgpu::Texture,
+        view: &wgpu::TextureViewDescriptor,
+        count_estimate: usize,
+        camera: crate::sprites::Camera2D,
+    ) -> usize {
+        let group_count = self.renderer.sprite_group_add_with_view(
+            tex,
+            view,
+            vec![crate::sprites::Transform::ZERO; count_estimate],
+            vec![crate::sprites::SheetRegion::ZERO; count_estimate],
+            camera,
+        );
+        self.sprites_used.resize(group_count + 1, 0);
+        group_count
+    }
     /// Returns the number of sprite groups (including placeholders for removed groups).
     pub fn sprite_group_count(&self) -> usize {
         self.renderer.sprite_group_count()
@@ -174,6 +274,10 @@ impl Immediate {
     pub fn sprite_group_size(&self, which: usize) -> usize {
         self.renderer.sprite_group_size(which)
     }
+    /// Describes a sprite group's texture, see [`Renderer::sprite_group_texture_info`]
+    pub fn sprite_group_texture_info(&self, which: usize) -> crate::resources::TextureInfo {
+        self.renderer.sprite_group_texture_info(which)
+    }
     /// Makes sure that the size of the given sprite group is at least as large as num.
     pub fn ensure_sprites_size(&mut self, which: usize, num: usize) {
         if self.renderer.sprites.sprite_group_size(which) <= num {
@@ -189,6 +293,43 @@ impl Immediate {
     pub fn sprite_group_set_camera(&mut self, which: usize, camera: crate::sprites::Camera2D) {
         self.renderer.sprite_group_set_camera(which, camera)
     }
+    /// Sets the camera of several sprite groups, see [`Renderer::sprite_groups_set_camera`]
+    pub fn sprite_groups_set_camera(
+        &mut self,
+        groups: impl IntoIterator<Item = usize>,
+        camera: crate::sprites::Camera2D,
+    ) {
+        self.renderer.sprite_groups_set_camera(groups, camera)
+    }
+    /// Sets the camera of every sprite group, see [`Renderer::sprite_set_camera_all`]
+    pub fn sprite_set_camera_all(&mut self, camera: crate::sprites::Camera2D) {
+        self.renderer.sprite_set_camera_all(camera)
+    }
+    /// Returns a sprite group's camera, see [`Renderer::sprite_group_camera`]
+    pub fn sprite_group_camera(&self, which: usize) -> Option<crate::sprites::Camera2D> {
+        self.renderer.sprite_group_camera(which)
+    }
+    /// Set a sprite group's camera blended between two cameras by `alpha`.  Uploads to the GPU.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_camera_interpolated(
+        &mut self,
+        which: usize,
+        prev: crate::sprites::Camera2D,
+        current: crate::sprites::Camera2D,
+        alpha: f32,
+    ) {
+        self.renderer
+            .sprite_group_set_camera_interpolated(which, prev, current, alpha)
+    }
+    /// Set the color grade applied to a specific sprite group before postprocessing.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_set_color_grade(
+        &mut self,
+        which: usize,
+        grade: crate::colorgeo::ColorGrade,
+    ) {
+        self.renderer.sprite_group_set_color_grade(which, grade)
+    }
     /// Draws a sprite with the given transform and sheet region
     pub fn draw_sprite(
         &mut self,
@@ -222,6 +363,51 @@ impl Immediate {
         self.sprites_used[group] += howmany;
         (trfs, uvs)
     }
+    /// Draws every sprite from `sprites`, returning how many were
+    /// drawn.  This is much faster than calling
+    /// [`Immediate::draw_sprite`] for each one (e.g. for thousands of
+    /// particles): space is reserved once, using the iterator's size
+    /// hint, and the sprites are written in one pass.  Sprites beyond
+    /// the hint are staged and copied in together at the end.
+    pub fn draw_sprites_iter<I>(&mut self, group: usize, sprites: I) -> usize
+    where
+        I: IntoIterator<Item = (crate::sprites::Transform, crate::sprites::SheetRegion)>,
+    {
+        let start = self.sprites_used[group];
+        let mut sprites = sprites.into_iter();
+        let (lower, upper) = sprites.size_hint();
+        self.ensure_sprites_size(group, start + upper.unwrap_or(lower));
+        let (trfs, uvs) = self.renderer.sprites.get_sprites_mut(group);
+        let mut end = start;
+        // Zip the slots first so that no sprite is taken without a slot for it
+        for ((trf, uv), (new_trf, new_uv)) in trfs[start..]
+            .iter_mut()
+            .zip(uvs[start..].iter_mut())
+            .zip(sprites.by_ref())
+        {
+            *trf = new_trf;
+            *uv = new_uv;
+            end += 1;
+        }
+        self.sprite_staging.clear();
+        self.sprite_staging.extend(sprites);
+        if !self.sprite_staging.is_empty() {
+            let extra = self.sprite_staging.len();
+            self.ensure_sprites_size(group, end + extra);
+            let (trfs, uvs) = self.renderer.sprites.get_sprites_mut(group);
+            for ((trf, uv), (new_trf, new_uv)) in trfs[end..end + extra]
+                .iter_mut()
+                .zip(uvs[end..end + extra].iter_mut())
+                .zip(self.sprite_staging.drain(..))
+            {
+                *trf = new_trf;
+                *uv = new_uv;
+            }
+            end += extra;
+        }
+        self.sprites_used[group] = end;
+        end - start
+    }
 
     /// Draws a line of text with the given [`crate::bitfont::BitFont`].
     pub fn draw_text(
@@ -237,6 +423,58 @@ impl Immediate {
         let (corner, used) = bitfont.draw_text(trfs, uvs, text, screen_pos, depth, char_height);
         (corner, used)
     }
+    /// Like [`Immediate::draw_text`], but with characters about
+    /// `points` points tall whatever the display's DPI, see
+    /// [`Renderer::text_height`].
+    pub fn draw_text_points(
+        &mut self,
+        group: usize,
+        bitfont: &crate::bitfont::BitFont,
+        text: &str,
+        screen_pos: [f32; 2],
+        depth: u16,
+        points: f32,
+    ) -> ([f32; 2], usize) {
+        let char_height = self.renderer.text_height(bitfont, points);
+        self.draw_text(group, bitfont, text, screen_pos, depth, char_height)
+    }
+    /// Draws a line of rich text with the given
+    /// [`crate::bitfont::BitFont`]; see
+    /// [`crate::bitfont::BitFont::draw_rich_text`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_rich_text(
+        &mut self,
+        group: usize,
+        bitfont: &crate::bitfont::BitFont,
+        spans: &[crate::bitfont::RichSpan],
+        screen_pos: [f32; 2],
+        depth: u16,
+        char_height: f32,
+        time: f32,
+    ) -> ([f32; 2], usize) {
+        let count = crate::bitfont::BitFont::rich_sprite_count(spans);
+        let (trfs, uvs) = self.draw_sprites(group, count);
+        bitfont.draw_rich_text(trfs, uvs, spans, screen_pos, depth, char_height, time)
+    }
+    /// Shapes and draws a line of text with the given
+    /// [`crate::shaping::TtfFont`]; see
+    /// [`crate::shaping::TtfFont::draw_text`].  Call
+    /// [`crate::shaping::TtfFont::upload`] with the group's
+    /// spritesheet before rendering.
+    #[cfg(feature = "shaping")]
+    pub fn draw_shaped_text(
+        &mut self,
+        group: usize,
+        font: &mut crate::shaping::TtfFont,
+        text: &str,
+        screen_pos: [f32; 2],
+        depth: u16,
+        char_height: f32,
+    ) -> ([f32; 2], usize) {
+        let shaped = font.shape(text);
+        let (trfs, uvs) = self.draw_sprites(group, shaped.sprite_count());
+        font.draw_shaped(trfs, uvs, &shaped, screen_pos, depth, char_height)
+    }
     /// Draws the sprites of a [`crate::nineslice::NineSlice`].
     #[allow(clippy::too_many_arguments)]
     pub fn draw_nineslice(
@@ -257,6 +495,10 @@ impl Immediate {
     pub fn mesh_set_camera(&mut self, camera: crate::meshes::Camera3D) {
         self.renderer.mesh_set_camera(camera)
     }
+    /// Returns the camera for all textured mesh groups, see [`Renderer::mesh_camera`]
+    pub fn mesh_camera(&self) -> crate::meshes::Camera3D {
+        self.renderer.mesh_camera()
+    }
     /// Add a mesh group with the given array texture.  All meshes in
     /// the group pull from the same vertex buffer, and each submesh
     /// is defined in terms of a range of indices within that buffer.
@@ -284,6 +526,14 @@ impl Immediate {
     pub fn mesh_group_remove(&mut self, which: crate::meshes::MeshGroup) {
         self.renderer.mesh_group_remove(which)
     }
+    /// Set the color grade applied to a mesh group before postprocessing.
+    pub fn mesh_group_set_color_grade(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        grade: crate::colorgeo::ColorGrade,
+    ) {
+        self.renderer.mesh_group_set_color_grade(which, grade)
+    }
     /// Returns how many mesh groups there are.
     pub fn mesh_group_count(&self) -> usize {
         self.renderer.mesh_group_count()
@@ -292,6 +542,10 @@ impl Immediate {
     pub fn mesh_group_size(&self, which: crate::meshes::MeshGroup) -> usize {
         self.renderer.mesh_group_size(which)
     }
+    /// Describes a mesh group, see [`Renderer::mesh_group_info`]
+    pub fn mesh_group_info(&self, which: crate::meshes::MeshGroup) -> crate::meshes::MeshGroupInfo {
+        self.renderer.mesh_group_info(which)
+    }
     /// Makes sure that the mesh instance slice for the given mesh group and index is at least big enough to hold `num`.
     pub fn ensure_meshes_size(&mut self, which: crate::meshes::MeshGroup, idx: usize, num: usize) {
         if self.renderer.meshes.mesh_instance_count(which, idx) <= num {
@@ -335,6 +589,10 @@ impl Immediate {
     pub fn flat_set_camera(&mut self, camera: crate::meshes::Camera3D) {
         self.renderer.flat_set_camera(camera)
     }
+    /// Returns the camera for all flat mesh groups, see [`Renderer::flat_camera`]
+    pub fn flat_camera(&self) -> crate::meshes::Camera3D {
+        self.renderer.flat_camera()
+    }
     /// Add a flat mesh group with the given color materials.  All
     /// meshes in the group pull from the same vertex buffer, and each
     /// submesh is defined in terms of a range of indices within that
@@ -361,6 +619,14 @@ impl Immediate {
     pub fn flat_group_remove(&mut self, which: crate::meshes::MeshGroup) {
         self.renderer.flat_group_remove(which)
     }
+    /// Set the color grade applied to a flat mesh group before postprocessing.
+    pub fn flat_group_set_color_grade(
+        &mut self,
+        which: crate::meshes::MeshGroup,
+        grade: crate::colorgeo::ColorGrade,
+    ) {
+        self.renderer.flat_group_set_color_grade(which, grade)
+    }
     /// Returns how many mesh groups there are.
     pub fn flat_group_count(&self) -> usize {
         self.renderer.flat_group_count()
@@ -369,6 +635,10 @@ impl Immediate {
     pub fn flat_group_size(&self, which: crate::meshes::MeshGroup) -> usize {
         self.renderer.flat_group_size(which)
     }
+    /// Describes a flat mesh group, see [`Renderer::flat_group_info`]
+    pub fn flat_group_info(&self, which: crate::meshes::MeshGroup) -> crate::meshes::MeshGroupInfo {
+        self.renderer.flat_group_info(which)
+    }
     /// Makes sure that the flats instance slice for the given mesh group and index is at least big enough to hold `num`.
     pub fn ensure_flats_size(&mut self, which: crate::meshes::MeshGroup, idx: usize, num: usize) {
         if self.renderer.flats.mesh_instance_count(which, idx) <= num {
@@ -436,10 +706,39 @@ impl Immediate {
     pub fn post_set_saturation(&mut self, sat: f32) {
         self.renderer.post_set_saturation(sat)
     }
+    /// Fades the postprocessing parameters to new values, see [`Renderer::post_fade_to`]
+    pub fn post_fade_to(
+        &mut self,
+        trf: [f32; 16],
+        color_trf: [f32; 16],
+        sat: f32,
+        lut: Option<&wgpu::Texture>,
+        seconds: f32,
+        easing: crate::colorgeo::Easing,
+    ) {
+        self.renderer
+            .post_fade_to(trf, color_trf, sat, lut, seconds, easing)
+    }
     /// Sets the postprocessing color lookup table texture
     pub fn post_set_lut(&mut self, lut: &wgpu::Texture) {
         self.renderer.post_set_lut(lut)
     }
+    /// Gets the postprocessing color curves, see [`Renderer::post_curves`]
+    pub fn post_curves(&self) -> crate::colorgeo::ColorCurves {
+        self.renderer.post_curves()
+    }
+    /// Sets the postprocessing color curves, see [`Renderer::post_set_curves`]
+    pub fn post_set_curves(&mut self, curves: crate::colorgeo::ColorCurves) {
+        self.renderer.post_set_curves(curves)
+    }
+    /// Transitions from the last frame to the next ones, see [`Renderer::start_transition`]
+    pub fn start_transition(&mut self, transition: crate::colorgeo::Transition, seconds: f32) {
+        self.renderer.start_transition(transition, seconds)
+    }
+    /// Returns how far the current transition has progressed, if there is one
+    pub fn transition_progress(&self) -> Option<f32> {
+        self.renderer.transition_progress()
+    }
     /// Gets the surface configuration
     pub fn config(&self) -> &wgpu::SurfaceConfiguration {
         self.renderer.config()
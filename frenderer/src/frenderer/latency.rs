@@ -0,0 +1,83 @@
+use super::*;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// How far the GPU has recently been running behind the CPU, from
+/// [`Renderer::frame_latency`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameLatency {
+    /// Frames presented by [`Renderer::render_finish`] whose GPU work
+    /// hasn't finished yet
+    pub frames_in_flight: usize,
+    /// The mean time in seconds from submitting a frame to the GPU
+    /// finishing it, over recent frames
+    pub average_seconds: f32,
+    /// The longest such time over recent frames
+    pub max_seconds: f32,
+}
+
+// Shared with the queue's work-done callbacks
+#[derive(Debug, Default)]
+pub(super) struct LatencyTracker {
+    in_flight: AtomicUsize,
+    recent: Mutex<VecDeque<f32>>,
+}
+
+impl LatencyTracker {
+    // How many frames the averages cover
+    const WINDOW: usize = 60;
+    // Notes a frame's submission, to be marked done once the GPU finishes it
+    pub(super) fn submitted(self: &Arc<Self>, queue: &wgpu::Queue) {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        let start = crate::clock::Instant::now();
+        let tracker = Arc::clone(self);
+        queue.on_submitted_work_done(move || {
+            tracker.in_flight.fetch_sub(1, Ordering::AcqRel);
+            let mut recent = tracker.recent.lock().unwrap();
+            if recent.len() == Self::WINDOW {
+                recent.pop_front();
+            }
+            recent.push_back(start.elapsed().as_secs_f32());
+        });
+    }
+}
+
+impl Renderer {
+    /// Returns the most frames the surface lets the CPU queue up
+    /// ahead of the display, see [`Renderer::set_max_frame_latency`].
+    pub fn max_frame_latency(&self) -> u32 {
+        self.config.desired_maximum_frame_latency
+    }
+    /// Sets the most frames the surface lets the CPU queue up ahead
+    /// of the display (at least 1, and 2 by default) and reconfigures
+    /// the surface.  Fewer frames means less input lag, e.g. 1 for
+    /// competitive games; more smooths over uneven frame times at
+    /// the cost of lag.  This is a hint the platform may not follow
+    /// exactly; see [`Renderer::frame_latency`] for what's achieved.
+    pub fn set_max_frame_latency(&mut self, frames: u32) {
+        self.config.desired_maximum_frame_latency = frames.max(1);
+        self.configure_surface();
+    }
+    /// Returns the presentation mode used by the swapchain.
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+    /// Measures how far the GPU is running behind: frames still in
+    /// flight, and how long recent frames took from submission to
+    /// the GPU finishing them.
+    pub fn frame_latency(&self) -> FrameLatency {
+        // Run any work-done callbacks which are ready
+        self.gpu.device().poll(wgpu::Maintain::Poll);
+        let recent = self.latency.recent.lock().unwrap();
+        FrameLatency {
+            frames_in_flight: self.latency.in_flight.load(Ordering::Acquire),
+            average_seconds: if recent.is_empty() {
+                0.0
+            } else {
+                recent.iter().sum::<f32>() / recent.len() as f32
+            },
+            max_seconds: recent.iter().copied().fold(0.0, f32::max),
+        }
+    }
+}
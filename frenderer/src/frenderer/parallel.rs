@@ -0,0 +1,143 @@
+use super::*;
+use crate::sprites::{SheetRegion, Transform};
+
+/// A run of neighboring sprites from one sprite group, handed out by
+/// [`Renderer::sprites_par_chunks_mut`].
+pub struct SpriteChunk<'a> {
+    /// The index within the group of the chunk's first sprite
+    pub start: usize,
+    /// The chunk's world transforms
+    pub transforms: &'a mut [Transform],
+    /// The chunk's texture regions
+    pub regions: &'a mut [SheetRegion],
+}
+
+impl SpriteChunk<'_> {
+    /// The sprites of the group this chunk covers.
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.start + self.transforms.len()
+    }
+}
+
+/// How many sprites [`Renderer::sprites_par_fill`] gives each task:
+/// enough that scheduling costs little next to filling them.
+pub const PAR_CHUNK_SIZE: usize = 4096;
+
+// Calls `fill` on each chunk, on rayon's thread pool if the
+// `parallel` feature is on (except on wasm), and gives back whether
+// each chunk changed
+fn fill_chunks(
+    trfs: &mut [Transform],
+    uvs: &mut [SheetRegion],
+    start: usize,
+    chunk_size: usize,
+    fill: impl Fn(SpriteChunk) -> bool + Send + Sync,
+) -> Vec<bool> {
+    let chunk = |(idx, (transforms, regions)): (usize, (&mut [Transform], &mut [SheetRegion]))| {
+        fill(SpriteChunk {
+            start: start + idx * chunk_size,
+            transforms,
+            regions,
+        })
+    };
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+        trfs.par_chunks_mut(chunk_size)
+            .zip(uvs.par_chunks_mut(chunk_size))
+            .enumerate()
+            .map(chunk)
+            .collect()
+    }
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    {
+        trfs.chunks_mut(chunk_size)
+            .zip(uvs.chunks_mut(chunk_size))
+            .enumerate()
+            .map(chunk)
+            .collect()
+    }
+}
+
+impl Renderer {
+    /// Splits a range of a sprite group's sprites into chunks of at
+    /// most `chunk_size` sprites and calls `fill` on each chunk.
+    /// With the `parallel` feature, chunks are filled at the same
+    /// time on rayon's thread pool; without it, or on the web, they
+    /// are filled one after another.
+    ///
+    /// `fill` returns whether it changed its chunk, and only changed
+    /// chunks are marked for upload (neighboring ones together), as
+    /// with [`Renderer::sprites_mut`].
+    ///
+    /// Panics if the given sprite group is not populated or is a
+    /// compact group, if the range is out of bounds, or if
+    /// `chunk_size` is zero.
+    pub fn sprites_par_chunks_mut(
+        &mut self,
+        which: usize,
+        range: impl RangeBounds<usize>,
+        chunk_size: usize,
+        fill: impl Fn(SpriteChunk) -> bool + Send + Sync,
+    ) {
+        assert!(chunk_size > 0, "Sprite chunks must not be empty");
+        let count = self.sprite_group_size(which);
+        let range = crate::range(range, count);
+        trace_span!(
+            "sprites:par_fill",
+            group = which,
+            count = range.len(),
+            chunk_size = chunk_size
+        );
+        let (trfs, uvs) = self.sprites.get_sprites_mut(which);
+        let changed = fill_chunks(
+            &mut trfs[range.clone()],
+            &mut uvs[range.clone()],
+            range.start,
+            chunk_size,
+            fill,
+        );
+        let mut dirty: Option<std::ops::Range<usize>> = None;
+        for (idx, changed) in changed.into_iter().enumerate() {
+            let start = range.start + idx * chunk_size;
+            let chunk = start..(start + chunk_size).min(range.end);
+            dirty = match dirty {
+                Some(run) if changed && run.end == chunk.start => Some(run.start..chunk.end),
+                Some(run) => {
+                    self.queued_uploads.push(Upload::Sprite(which, run));
+                    changed.then_some(chunk)
+                }
+                None => changed.then_some(chunk),
+            };
+        }
+        if let Some(run) = dirty {
+            self.queued_uploads.push(Upload::Sprite(which, run));
+        }
+    }
+    /// Calls `fill` on every sprite in a range of a sprite group,
+    /// along with the sprite's index in the group, splitting the work
+    /// up as [`Renderer::sprites_par_chunks_mut`] does.  The whole
+    /// range is marked for upload.
+    ///
+    /// Panics if the given sprite group is not populated or is a
+    /// compact group, or if the range is out of bounds.
+    pub fn sprites_par_fill(
+        &mut self,
+        which: usize,
+        range: impl RangeBounds<usize>,
+        fill: impl Fn(usize, &mut Transform, &mut SheetRegion) + Send + Sync,
+    ) {
+        self.sprites_par_chunks_mut(which, range, PAR_CHUNK_SIZE, |chunk| {
+            let start = chunk.start;
+            for (idx, (trf, uv)) in chunk
+                .transforms
+                .iter_mut()
+                .zip(chunk.regions.iter_mut())
+                .enumerate()
+            {
+                fill(start + idx, trf, uv);
+            }
+            true
+        });
+    }
+}
@@ -0,0 +1,265 @@
+use super::*;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// How long one pass or group of a frame took on the GPU, from
+/// [`Renderer::gpu_timings`].  Its `Display` form (e.g. `sprite
+/// group 3: 120.5 us`) only uses ASCII, so it can be drawn with a
+/// [`crate::bitfont::BitFont`] as part of a debug overlay.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuTiming {
+    /// What was timed, e.g. `post pass` or `mesh group 2`
+    pub label: String,
+    /// The time it took in microseconds
+    pub micros: f32,
+}
+
+impl std::fmt::Display for GpuTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:.1} us", self.label, self.micros)
+    }
+}
+
+// Timestamps available per frame; groups past this are timed together
+const MAX_QUERIES: u32 = 256;
+
+// How reading back the timestamps of the frame in flight is going
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+// Timestamp queries for frames drawn by `Renderer::render`, read back
+// a frame or two later without stalling
+pub(super) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    read_buffer: wgpu::Buffer,
+    inside_passes: bool,
+    // The spans of the frame being read back, if any
+    in_flight: Option<Vec<(String, u32, u32)>>,
+    // One of the MAP_ constants
+    map_state: Arc<AtomicU8>,
+    timings: Vec<GpuTiming>,
+}
+
+// The timestamps written while encoding one frame
+pub(super) struct FrameQueries<'t> {
+    timer: &'t GpuTimer,
+    next: u32,
+    spans: Vec<(String, u32, u32)>,
+}
+
+impl Renderer {
+    /// Turns GPU timing of frames drawn by [`Renderer::render`] on or
+    /// off.  Each pass is timed, and if the device also supports
+    /// [`wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES`] so is each
+    /// mesh, flat, and sprite group.  This needs
+    /// [`wgpu::Features::TIMESTAMP_QUERY`], which can be asked for
+    /// with [`crate::DeviceRequest::optional_features`]; without it
+    /// this does nothing.
+    pub fn set_gpu_timing(&mut self, enabled: bool) {
+        if !enabled {
+            self.gpu_timer = None;
+        } else if self.gpu_timer.is_none() {
+            if !self
+                .gpu
+                .device()
+                .features()
+                .contains(wgpu::Features::TIMESTAMP_QUERY)
+            {
+                log::warn!("GPU timing needs the TIMESTAMP_QUERY feature");
+                return;
+            }
+            self.gpu_timer = Some(GpuTimer::new(&self.gpu));
+        }
+    }
+    /// Whether GPU timing is on, see [`Renderer::set_gpu_timing`].
+    pub fn gpu_timing(&self) -> bool {
+        self.gpu_timer.is_some()
+    }
+    /// The most recent GPU timings in the order they were drawn, a
+    /// frame or two behind since they're read back without waiting
+    /// on the GPU.  Empty unless [`Renderer::set_gpu_timing`] is on.
+    pub fn gpu_timings(&self) -> &[GpuTiming] {
+        self.gpu_timer.as_ref().map_or(&[], |timer| timer.timings())
+    }
+}
+
+impl GpuTimer {
+    pub(super) fn new(gpu: &WGPU) -> Self {
+        let size = MAX_QUERIES as u64 * wgpu::QUERY_SIZE as u64;
+        Self {
+            query_set: gpu.device().create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("frenderer:timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_QUERIES,
+            }),
+            resolve_buffer: gpu.device().create_buffer(&wgpu::BufferDescriptor {
+                label: Some("frenderer:timestamp_resolve"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            read_buffer: gpu.device().create_buffer(&wgpu::BufferDescriptor {
+                label: Some("frenderer:timestamp_read"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            inside_passes: gpu
+                .device()
+                .features()
+                .contains(wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES),
+            in_flight: None,
+            map_state: Arc::new(AtomicU8::new(MAP_PENDING)),
+            timings: vec![],
+        }
+    }
+    pub(super) fn timings(&self) -> &[GpuTiming] {
+        &self.timings
+    }
+    // Collects the previous frame's timings if they're ready and
+    // starts a new frame, or returns `None` while still waiting
+    pub(super) fn begin_frame(&mut self, gpu: &WGPU) -> Option<FrameQueries<'_>> {
+        if let Some(spans) = self.in_flight.as_ref() {
+            gpu.device().poll(wgpu::Maintain::Poll);
+            match self.map_state.load(Ordering::Acquire) {
+                MAP_PENDING => return None,
+                MAP_FAILED => {
+                    log::warn!("Couldn't read back GPU timestamps; dropping a frame of timings");
+                    // A failed map usually leaves the buffer unmapped
+                    // already, which makes unmapping it a harmless
+                    // validation error
+                    gpu.device().push_error_scope(wgpu::ErrorFilter::Validation);
+                    self.read_buffer.unmap();
+                    drop(gpu.device().pop_error_scope());
+                }
+                _ => {
+                    let period = gpu.queue().get_timestamp_period();
+                    {
+                        let data = self.read_buffer.slice(..).get_mapped_range();
+                        let stamps: &[u64] = bytemuck::cast_slice(&data);
+                        self.timings = spans
+                            .iter()
+                            .map(|(label, start, end)| GpuTiming {
+                                label: label.clone(),
+                                micros: stamps[*end as usize]
+                                    .saturating_sub(stamps[*start as usize])
+                                    as f32
+                                    * period
+                                    / 1000.0,
+                            })
+                            .collect();
+                    }
+                    self.read_buffer.unmap();
+                }
+            }
+            self.map_state.store(MAP_PENDING, Ordering::Release);
+            self.in_flight = None;
+        }
+        Some(FrameQueries {
+            timer: self,
+            next: 0,
+            spans: vec![],
+        })
+    }
+    // Starts reading back the timestamps of a frame whose encoder has
+    // been submitted
+    pub(super) fn read_back(&mut self, spans: Vec<(String, u32, u32)>) {
+        if spans.is_empty() {
+            return;
+        }
+        let map_state = Arc::clone(&self.map_state);
+        self.read_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let state = if result.is_ok() { MAP_DONE } else { MAP_FAILED };
+                map_state.store(state, Ordering::Release)
+            });
+        self.in_flight = Some(spans);
+    }
+}
+
+impl<'t> FrameQueries<'t> {
+    fn take(&mut self, count: u32) -> Option<u32> {
+        let first = self.next;
+        (first + count <= MAX_QUERIES).then(|| {
+            self.next += count;
+            first
+        })
+    }
+    // Timestamp writes for the start and end of a whole pass
+    pub(super) fn pass(&mut self, label: &str) -> Option<wgpu::RenderPassTimestampWrites<'t>> {
+        let first = self.take(2)?;
+        self.spans.push((label.to_string(), first, first + 1));
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.timer.query_set,
+            beginning_of_pass_write_index: Some(first),
+            end_of_pass_write_index: Some(first + 1),
+        })
+    }
+    fn stamp(&mut self, rpass: &mut wgpu::RenderPass) -> Option<u32> {
+        if !self.timer.inside_passes {
+            return None;
+        }
+        let idx = self.take(1)?;
+        rpass.write_timestamp(&self.timer.query_set, idx);
+        Some(idx)
+    }
+    // Renders `count` groups with `render`, timing each one on its
+    // own while there are queries left and the rest together
+    pub(super) fn groups<'pass>(
+        &mut self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        label: &str,
+        count: usize,
+        render: impl Fn(&mut wgpu::RenderPass<'pass>, Range<usize>),
+    ) {
+        let Some(mut start) = self.stamp(rpass) else {
+            render(rpass, 0..count);
+            return;
+        };
+        for which in 0..count {
+            // Keep one query back to close off the remaining groups
+            let range = if self.next + 1 < MAX_QUERIES {
+                which..which + 1
+            } else {
+                which..count
+            };
+            render(rpass, range.clone());
+            let Some(end) = self.stamp(rpass) else {
+                return;
+            };
+            let label = if range.len() == 1 {
+                format!("{label} {which}")
+            } else {
+                format!("{label}s {range:?}")
+            };
+            self.spans.push((label, start, end));
+            start = end;
+            if range.end == count {
+                break;
+            }
+        }
+    }
+    // Resolves the frame's timestamps for reading back once the
+    // encoder is submitted, returning what each span measured
+    pub(super) fn resolve(self, encoder: &mut wgpu::CommandEncoder) -> Vec<(String, u32, u32)> {
+        if self.next > 0 {
+            encoder.resolve_query_set(
+                &self.timer.query_set,
+                0..self.next,
+                &self.timer.resolve_buffer,
+                0,
+            );
+            encoder.copy_buffer_to_buffer(
+                &self.timer.resolve_buffer,
+                0,
+                &self.timer.read_buffer,
+                0,
+                self.next as u64 * wgpu::QUERY_SIZE as u64,
+            );
+        }
+        self.spans
+    }
+}
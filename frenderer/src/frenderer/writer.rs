@@ -0,0 +1,113 @@
+use super::*;
+use crate::sprites::{SheetRegion, Transform};
+use bytemuck::Zeroable;
+
+/// A guard for rebuilding a sprite group's contents, from
+/// [`Renderer::sprite_group_writer`].  Sprites are written into a
+/// staging area which grows as needed, and when the writer is
+/// committed or dropped the group is resized to fit and its contents
+/// replaced and queued for upload in one step.  This avoids the
+/// resize-then-write dance when several systems each contribute
+/// sprites to the same group: pass the writer along and let each one
+/// append what it needs.
+pub struct SpriteGroupWriter<'r> {
+    renderer: &'r mut Renderer,
+    which: usize,
+    trfs: Vec<Transform>,
+    uvs: Vec<SheetRegion>,
+}
+
+impl Renderer {
+    /// Starts rebuilding the contents of a sprite group, see
+    /// [`SpriteGroupWriter`].  The writer starts out empty; anything
+    /// not written again is gone once it's committed.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_group_writer(&mut self, which: usize) -> SpriteGroupWriter<'_> {
+        // Panic now rather than when the writer is dropped
+        self.sprite_group_size(which);
+        let (mut trfs, mut uvs) = std::mem::take(&mut self.sprite_staging);
+        trfs.clear();
+        uvs.clear();
+        SpriteGroupWriter {
+            renderer: self,
+            which,
+            trfs,
+            uvs,
+        }
+    }
+}
+
+impl SpriteGroupWriter<'_> {
+    /// The sprite group being written
+    pub fn group(&self) -> usize {
+        self.which
+    }
+    /// The number of sprites written so far
+    pub fn len(&self) -> usize {
+        self.trfs.len()
+    }
+    /// Whether no sprites have been written yet
+    pub fn is_empty(&self) -> bool {
+        self.trfs.is_empty()
+    }
+    /// Adds a sprite after the ones written so far, returning its index.
+    pub fn push(&mut self, trf: Transform, uv: SheetRegion) -> usize {
+        self.trfs.push(trf);
+        self.uvs.push(uv);
+        self.trfs.len() - 1
+    }
+    /// Adds several sprites after the ones written so far.
+    pub fn extend(&mut self, sprites: impl IntoIterator<Item = (Transform, SheetRegion)>) {
+        for (trf, uv) in sprites {
+            self.push(trf, uv);
+        }
+    }
+    /// Writes a sprite at a specific index, growing the group with
+    /// invisible sprites if it's past the end.
+    pub fn set(&mut self, index: usize, trf: Transform, uv: SheetRegion) {
+        if index >= self.len() {
+            self.trfs.resize(index + 1, Transform::zeroed());
+            self.uvs.resize(index + 1, SheetRegion::zeroed());
+        }
+        self.trfs[index] = trf;
+        self.uvs[index] = uv;
+    }
+    /// Makes room for up to `count` sprites after the ones written so
+    /// far and lets `write` fill them in, keeping as many as it says
+    /// it wrote.  This suits helpers like
+    /// [`crate::tilemap::Tilemap::write_sprites`].
+    pub fn append_with(
+        &mut self,
+        count: usize,
+        write: impl FnOnce(&mut [Transform], &mut [SheetRegion]) -> usize,
+    ) -> std::ops::Range<usize> {
+        let start = self.len();
+        self.trfs.resize(start + count, Transform::zeroed());
+        self.uvs.resize(start + count, SheetRegion::zeroed());
+        let written = write(&mut self.trfs[start..], &mut self.uvs[start..]).min(count);
+        self.trfs.truncate(start + written);
+        self.uvs.truncate(start + written);
+        start..start + written
+    }
+    /// The sprites written so far, for adjusting them in place.
+    pub fn sprites_mut(&mut self) -> (&mut [Transform], &mut [SheetRegion]) {
+        (&mut self.trfs, &mut self.uvs)
+    }
+    /// Resizes the group to fit what's been written and replaces its
+    /// contents.  Dropping the writer does the same thing.
+    pub fn commit(self) {}
+}
+
+impl Drop for SpriteGroupWriter<'_> {
+    fn drop(&mut self) {
+        let len = self.trfs.len();
+        self.renderer.sprite_group_resize(self.which, len);
+        let (trfs, uvs) = self.renderer.sprites_mut(self.which, ..);
+        trfs.copy_from_slice(&self.trfs);
+        uvs.copy_from_slice(&self.uvs);
+        self.renderer.sprite_staging = (
+            std::mem::take(&mut self.trfs),
+            std::mem::take(&mut self.uvs),
+        );
+    }
+}
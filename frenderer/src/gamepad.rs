@@ -0,0 +1,151 @@
+//! An on-screen virtual gamepad for touch devices.  A
+//! [`VirtualGamepad`] turns touches into virtual key presses on an
+//! [`Input`], so games written against the keyboard queries (e.g.
+//! [`Input::is_key_down`] and [`Input::key_axis`]) are playable on
+//! phones and tablets without changes.  It can also write sprites to
+//! draw itself as an overlay.
+//!
+//! Gamepad controls are positioned in physical pixels with the
+//! origin at the top left, like touch and mouse positions.
+
+use crate::input::{Input, Key};
+use crate::sprites::{SheetRegion, Transform};
+
+/// A round on-screen button which holds a key while touched.
+#[derive(Clone, Copy, Debug)]
+pub struct VirtualButton {
+    /// The center of the button in physical pixels
+    pub center: [f32; 2],
+    /// The radius of the button in physical pixels
+    pub radius: f32,
+    /// The key held down while the button is touched
+    pub key: Key,
+    /// How to draw the button
+    pub region: SheetRegion,
+}
+
+/// An on-screen directional pad which holds up to two of its four
+/// keys depending on where it's touched relative to its center.
+#[derive(Clone, Copy, Debug)]
+pub struct VirtualDpad {
+    /// The center of the dpad in physical pixels
+    pub center: [f32; 2],
+    /// The radius of the dpad in physical pixels; touches a little
+    /// outside of this are still counted so thumbs can drift.
+    pub radius: f32,
+    /// Touches closer than this to the center don't press any direction
+    pub dead_zone: f32,
+    /// The keys for left, right, up, and down
+    pub keys: [Key; 4],
+    /// How to draw the dpad
+    pub region: SheetRegion,
+}
+
+impl VirtualDpad {
+    fn held_keys(&self, x: f32, y: f32) -> impl Iterator<Item = Key> + '_ {
+        let (dx, dy) = (x - self.center[0], y - self.center[1]);
+        let dist = (dx * dx + dy * dy).sqrt();
+        let active = dist >= self.dead_zone && dist <= self.radius * 1.5;
+        // Directions within about 67 degrees of the touch are held, allowing diagonals
+        let threshold = dist * 0.38;
+        [
+            dx < -threshold,
+            dx > threshold,
+            dy < -threshold,
+            dy > threshold,
+        ]
+        .into_iter()
+        .zip(self.keys)
+        .filter_map(move |(held, key)| (active && held).then_some(key))
+    }
+}
+
+/// A set of on-screen controls which press virtual keys on an [`Input`].
+#[derive(Clone, Debug, Default)]
+pub struct VirtualGamepad {
+    /// The directional pad, if any
+    pub dpad: Option<VirtualDpad>,
+    /// The buttons
+    pub buttons: Vec<VirtualButton>,
+}
+
+impl VirtualGamepad {
+    /// Sets `input`'s virtual keys according to its current
+    /// touches.  Call this after processing a frame's events and
+    /// before querying `input`.
+    pub fn update(&self, input: &mut Input) {
+        let mut held: Vec<Key> = vec![];
+        for touch in input.touches() {
+            let (x, y) = (touch.pos.x as f32, touch.pos.y as f32);
+            let dpad_keys = self.dpad.iter().flat_map(|dpad| dpad.held_keys(x, y));
+            let button_keys = self
+                .buttons
+                .iter()
+                .filter(|b| Self::touches_circle(b.center, b.radius, x, y))
+                .map(|b| b.key);
+            for key in dpad_keys.chain(button_keys) {
+                if !held.contains(&key) {
+                    held.push(key);
+                }
+            }
+        }
+        input.set_virtual_keys(held);
+    }
+    fn touches_circle(center: [f32; 2], radius: f32, x: f32, y: f32) -> bool {
+        let (dx, dy) = (x - center[0], y - center[1]);
+        dx * dx + dy * dy <= radius * radius
+    }
+    /// How many sprites [`VirtualGamepad::write_sprites`] needs.
+    pub fn sprite_count(&self) -> usize {
+        self.dpad.iter().count() + self.buttons.len()
+    }
+    /// Writes sprites for the gamepad's controls into `trfs` and
+    /// `uvs`, which must have room for at least
+    /// [`VirtualGamepad::sprite_count`] sprites.  Held buttons are
+    /// tinted with `held_colormod`.  The sprites are meant for a
+    /// sprite group whose camera covers the surface in physical
+    /// pixels, i.e. with `screen_pos` at the origin and `screen_size`
+    /// equal to the surface size, so `surface_height` is needed to
+    /// flip the y axis.
+    pub fn write_sprites(
+        &self,
+        input: &Input,
+        surface_height: f32,
+        held_colormod: [u8; 4],
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+    ) {
+        let controls = self
+            .dpad
+            .iter()
+            .map(|d| {
+                (
+                    d.center,
+                    d.radius,
+                    d.region,
+                    d.keys.iter().any(|k| input.is_key_down(*k)),
+                )
+            })
+            .chain(
+                self.buttons
+                    .iter()
+                    .map(|b| (b.center, b.radius, b.region, input.is_key_down(b.key))),
+            );
+        for (((center, radius, region, held), trf), uv) in
+            controls.zip(trfs.iter_mut()).zip(uvs.iter_mut())
+        {
+            *trf = Transform {
+                x: center[0],
+                y: surface_height - center[1],
+                w: (radius * 2.0) as u16,
+                h: (radius * 2.0) as u16,
+                rot: 0.0,
+            };
+            *uv = if held {
+                region.with_colormod(held_colormod)
+            } else {
+                region
+            };
+        }
+    }
+}
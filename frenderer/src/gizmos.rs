@@ -0,0 +1,681 @@
+//! Gizmos for editor tooling: handles for moving, rotating, and
+//! scaling things by dragging them with the mouse.
+//!
+//! A [`Gizmo3D`] draws arrows, rings, or box-tipped handles along its
+//! three axes with its own [`FlatRenderer`] and hit-tests them against
+//! a mouse [`Ray`] from [`Camera3D::screen_ray`].  Render it after the
+//! rest of the scene in a pass with a freshly cleared depth buffer so
+//! the objects it's editing never hide it.
+//!
+//! A [`Gizmo2D`] does the same in a 2D world given the mouse's world
+//! position (see [`crate::sprites::Camera2D::screen_to_world`]),
+//! writing its handles as sprites like [`crate::tilemap::Tilemap`]
+//! does.
+//!
+//! Both report drags as changes since their previous update, to be
+//! applied to whatever is being edited.  Neither moves itself, so
+//! set their position and rotation to match the edited object
+//! every frame.
+
+use crate::meshes::{
+    Camera3D, FlatRenderer, FlatVertex, MeshEntry, MeshGroup, Ray, SubmeshEntry, Transform3D,
+};
+use crate::sprites::{SheetRegion, Transform};
+use crate::WGPU;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+use ultraviolet::{Rotor3, Vec2, Vec3};
+
+/// Which handles a gizmo shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum GizmoMode {
+    /// Arrows for moving along each axis
+    #[default]
+    Translate,
+    /// Rings for rotating about each axis
+    Rotate,
+    /// Box-tipped handles for scaling along each axis
+    Scale,
+}
+
+/// A change made by dragging a [`Gizmo3D`] handle since its last update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GizmoDrag {
+    /// Move by this world space offset
+    Translate([f32; 3]),
+    /// Rotate counterclockwise (by the right-hand rule) about a world space axis
+    Rotate {
+        /// The axis of rotation, of length 1
+        axis: [f32; 3],
+        /// The angle in radians
+        angle: f32,
+    },
+    /// Multiply the scale along each of the gizmo's axes by these factors
+    Scale([f32; 3]),
+}
+
+/// A change made by dragging a [`Gizmo2D`] handle since its last update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GizmoDrag2D {
+    /// Move by this world space offset
+    Translate([f32; 2]),
+    /// Rotate counterclockwise by this many radians
+    Rotate(f32),
+    /// Multiply the scale along each of the gizmo's axes by these factors
+    Scale([f32; 2]),
+}
+
+// RGBA colors of the x, y, and z handles, then of the hovered or dragged handle
+const COLORS: [[f32; 4]; 4] = [
+    [0.9, 0.2, 0.2, 1.0],
+    [0.2, 0.8, 0.2, 1.0],
+    [0.2, 0.4, 0.95, 1.0],
+    [1.0, 0.85, 0.1, 1.0],
+];
+// How close the mouse must be to a handle to grab it, relative to the handle length
+const PICK_RADIUS: f32 = 0.08;
+
+/// Move, rotate, and scale handles for objects in a 3D scene.
+pub struct Gizmo3D {
+    /// The world space position of the gizmo's center
+    pub position: [f32; 3],
+    /// The orientation of the gizmo's axes (a quaternion): the
+    /// identity for world space axes, or the edited object's rotation
+    /// for local ones
+    pub rotation: [f32; 4],
+    /// Which handles are shown
+    pub mode: GizmoMode,
+    /// The length of the handles as a fraction of the view's height,
+    /// so the gizmo stays the same size on screen at any distance
+    pub size: f32,
+    flats: FlatRenderer,
+    group: MeshGroup,
+    hovered: Option<usize>,
+    drag: Option<Drag3D>,
+    was_down: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Drag3D {
+    mode: GizmoMode,
+    axis: usize,
+    // The handle's world space position, direction, and length when the drag started
+    origin: Vec3,
+    dir: Vec3,
+    length: f32,
+    // Where along the axis (or at what angle around it) the drag started and last was
+    start: f32,
+    last: f32,
+}
+
+impl Drag3D {
+    // Where the ray points along the axis, or at what angle around it
+    fn value(&self, ray_origin: Vec3, ray_dir: Vec3) -> Option<f32> {
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                closest_on_axis(self.origin, self.dir, ray_origin, ray_dir).map(|(s, _t)| s)
+            }
+            GizmoMode::Rotate => {
+                let v = hit_plane(self.origin, self.dir, ray_origin, ray_dir)?;
+                let (u, w) = plane_basis(self.dir);
+                Some(v.dot(w).atan2(v.dot(u)))
+            }
+        }
+    }
+    fn change(&self, value: f32) -> GizmoDrag {
+        match self.mode {
+            GizmoMode::Translate => GizmoDrag::Translate((self.dir * (value - self.last)).into()),
+            GizmoMode::Rotate => GizmoDrag::Rotate {
+                axis: self.dir.into(),
+                angle: wrap_angle(value - self.last),
+            },
+            GizmoMode::Scale => {
+                let mut factors = [1.0; 3];
+                factors[self.axis] = scale_factor(self.length, self.start, self.last, value);
+                GizmoDrag::Scale(factors)
+            }
+        }
+    }
+}
+
+impl Gizmo3D {
+    /// Creates a gizmo at the origin, drawing into the given color
+    /// target state and depth texture format.
+    pub fn new(
+        gpu: &WGPU,
+        color_target: wgpu::ColorTargetState,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        let mut flats = FlatRenderer::new(gpu, color_target, depth_format);
+        let mut meshes = GizmoMeshes::default();
+        // Every mesh points along +x and is rotated onto each axis
+        let (shaft, tip) = (0.015, 0.06);
+        meshes.cuboid(Vec3::new(0.0, -shaft, -shaft), Vec3::new(0.8, shaft, shaft));
+        meshes.cone(0.78, 1.0, tip, 12);
+        meshes.finish_mesh();
+        meshes.torus(1.0, shaft, 48, 8);
+        meshes.finish_mesh();
+        meshes.cuboid(
+            Vec3::new(0.0, -shaft, -shaft),
+            Vec3::new(0.88, shaft, shaft),
+        );
+        meshes.cuboid(Vec3::new(0.88, -tip, -tip), Vec3::new(1.0, tip, tip));
+        meshes.finish_mesh();
+        let group = flats.add_mesh_group(
+            gpu,
+            &COLORS,
+            meshes.vertices,
+            meshes.indices,
+            meshes.entries,
+        );
+        flats.set_material_overrides(gpu, group, true);
+        Self {
+            position: [0.0; 3],
+            rotation: Rotor3::identity().into_quaternion_array(),
+            mode: GizmoMode::Translate,
+            size: 0.15,
+            flats,
+            group,
+            hovered: None,
+            drag: None,
+            was_down: false,
+        }
+    }
+    /// Which axis handle (0 for x, 1 for y, 2 for z) is under the
+    /// mouse or being dragged.
+    pub fn hovered(&self) -> Option<usize> {
+        self.drag.map(|drag| drag.axis).or(self.hovered)
+    }
+    /// Whether a handle is being dragged.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+    /// Hit-tests the handles against `ray` (e.g. from
+    /// [`Camera3D::screen_ray`] at the mouse position), starts or
+    /// ends drags as `mouse_down` changes, and uploads the gizmo's
+    /// appearance for `camera`.  Returns the change made by the
+    /// current drag since the last update, if any.
+    pub fn update(
+        &mut self,
+        gpu: &WGPU,
+        camera: &Camera3D,
+        ray: Ray,
+        mouse_down: bool,
+    ) -> Option<GizmoDrag> {
+        let pressed = mouse_down && !self.was_down;
+        self.was_down = mouse_down;
+        let (ray_origin, ray_dir) = (Vec3::from(ray.origin), Vec3::from(ray.dir));
+        let length = self.handle_length(camera);
+        let mut change = None;
+        match self.drag {
+            Some(mut drag) if mouse_down => {
+                if let Some(value) = drag.value(ray_origin, ray_dir) {
+                    change = Some(drag.change(value));
+                    drag.last = value;
+                }
+                self.drag = Some(drag);
+            }
+            _ => {
+                self.drag = None;
+                self.hovered = self.pick(ray_origin, ray_dir, length);
+                if let (true, Some(axis)) = (pressed, self.hovered) {
+                    let mut drag = Drag3D {
+                        mode: self.mode,
+                        axis,
+                        origin: self.position.into(),
+                        dir: self.axes()[axis],
+                        length,
+                        start: 0.0,
+                        last: 0.0,
+                    };
+                    if let Some(value) = drag.value(ray_origin, ray_dir) {
+                        drag.start = value;
+                        drag.last = value;
+                        self.drag = Some(drag);
+                    }
+                }
+            }
+        }
+        self.write_instances(gpu, camera, length);
+        change
+    }
+    /// Draws the gizmo into the given [`wgpu::RenderPass`].
+    pub fn render<'s, 'pass>(&'s self, rpass: &mut wgpu::RenderPass<'pass>)
+    where
+        's: 'pass,
+    {
+        self.flats.render(rpass, ..);
+    }
+    // The world space length of the handles which makes them `size` of the view's height
+    fn handle_length(&self, camera: &Camera3D) -> f32 {
+        let dist = (Vec3::from(self.position) - Vec3::from(camera.translation)).mag();
+        self.size * 2.0 * dist * (camera.fov / 2.0).tan()
+    }
+    fn axes(&self) -> [Vec3; 3] {
+        let rot = Rotor3::from_quaternion_array(self.rotation);
+        [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()].map(|axis| rot * axis)
+    }
+    // The handle closest to the ray, if any are close enough
+    fn pick(&self, ray_origin: Vec3, ray_dir: Vec3, length: f32) -> Option<usize> {
+        let center = Vec3::from(self.position);
+        let axes = self.axes();
+        let miss = (0..3).filter_map(|axis| {
+            let dir = axes[axis];
+            let miss = match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    let (s, t) = closest_on_axis(center, dir, ray_origin, ray_dir)?;
+                    if t < 0.0 || !(0.0..=length).contains(&s) {
+                        return None;
+                    }
+                    (center + dir * s - (ray_origin + ray_dir * t)).mag()
+                }
+                GizmoMode::Rotate => {
+                    let v = hit_plane(center, dir, ray_origin, ray_dir)?;
+                    (v.mag() - length).abs()
+                }
+            };
+            (miss < length * PICK_RADIUS).then_some((axis, miss))
+        });
+        miss.min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(axis, _)| axis)
+    }
+    fn write_instances(&mut self, gpu: &WGPU, camera: &Camera3D, length: f32) {
+        self.flats.set_camera(gpu, *camera);
+        let rot = Rotor3::from_quaternion_array(self.rotation);
+        let shown = match self.mode {
+            GizmoMode::Translate => 0,
+            GizmoMode::Rotate => 1,
+            GizmoMode::Scale => 2,
+        };
+        let hovered = self.hovered();
+        let local_axes = [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()];
+        for mesh in 0..3 {
+            let trfs = self.flats.get_meshes_mut(self.group, mesh);
+            for (trf, axis) in trfs.iter_mut().zip(local_axes) {
+                *trf = Transform3D {
+                    translation: self.position,
+                    // Hidden handles are scaled to nothing
+                    scale: if mesh == shown { length } else { 0.0 },
+                    rotation: (rot * Rotor3::from_rotation_between(Vec3::unit_x(), axis))
+                        .into_quaternion_array(),
+                };
+            }
+            let overrides = self.flats.get_material_overrides_mut(self.group, mesh);
+            for (axis, material) in overrides.iter_mut().enumerate() {
+                material.material_offset = if hovered == Some(axis) {
+                    3
+                } else {
+                    axis as u32
+                };
+            }
+        }
+        self.flats.upload_meshes_group(gpu, self.group);
+    }
+}
+
+// The parameters of the closest points between the line `origin + s * dir`
+// and the ray `ray_origin + t * ray_dir`, or None if they're parallel
+fn closest_on_axis(origin: Vec3, dir: Vec3, ray_origin: Vec3, ray_dir: Vec3) -> Option<(f32, f32)> {
+    let w = origin - ray_origin;
+    let b = dir.dot(ray_dir);
+    let denom = 1.0 - b * b;
+    if denom < 1e-6 {
+        return None;
+    }
+    let (d, e) = (dir.dot(w), ray_dir.dot(w));
+    Some(((b * e - d) / denom, (e - b * d) / denom))
+}
+
+// Where the ray hits the plane through `origin` with the given normal, relative to `origin`
+fn hit_plane(origin: Vec3, normal: Vec3, ray_origin: Vec3, ray_dir: Vec3) -> Option<Vec3> {
+    let denom = ray_dir.dot(normal);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let t = (origin - ray_origin).dot(normal) / denom;
+    (t >= 0.0).then(|| ray_origin + ray_dir * t - origin)
+}
+
+// Two perpendicular unit vectors in the plane with the given normal,
+// ordered so that angles measured from the first increase counterclockwise
+fn plane_basis(normal: Vec3) -> (Vec3, Vec3) {
+    let guess = if normal.x.abs() < 0.9 {
+        Vec3::unit_x()
+    } else {
+        Vec3::unit_y()
+    };
+    let u = (guess - normal * guess.dot(normal)).normalized();
+    (u, normal.cross(u))
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+// How much to scale by when a handle of the given length moves from
+// `last` to `value`, having been grabbed at `start`: dragging the
+// handle out by its own length doubles the scale
+fn scale_factor(length: f32, start: f32, last: f32, value: f32) -> f32 {
+    let min = length * 0.01;
+    (length + value - start).max(min) / (length + last - start).max(min)
+}
+
+// Builds the gizmo's meshes out of flat-shaded triangles, one mesh at a time
+#[derive(Default)]
+struct GizmoMeshes {
+    vertices: Vec<FlatVertex>,
+    indices: Vec<u32>,
+    entries: Vec<MeshEntry>,
+    mesh_start: u32,
+}
+
+impl GizmoMeshes {
+    // Adds a triangle wound to face away from the point `inside`
+    fn tri(&mut self, mut corners: [Vec3; 3], inside: Vec3) {
+        let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+        if normal.dot(corners[0] - inside) < 0.0 {
+            corners.swap(1, 2);
+        }
+        for corner in corners {
+            self.indices.push(self.vertices.len() as u32);
+            self.vertices.push(FlatVertex::new(corner.into(), 0));
+        }
+    }
+    fn cuboid(&mut self, min: Vec3, max: Vec3) {
+        let center = (min + max) * 0.5;
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        // Each face is the four corners which agree on one coordinate
+        for bit in [1, 2, 4] {
+            for side in [0, bit] {
+                let face: Vec<Vec3> = (0..8).filter(|i| i & bit == side).map(corner).collect();
+                self.tri([face[0], face[1], face[3]], center);
+                self.tri([face[0], face[3], face[2]], center);
+            }
+        }
+    }
+    // A cone along +x from a base of the given radius at `base` to a point at `tip`
+    fn cone(&mut self, base: f32, tip: f32, radius: f32, segments: usize) {
+        let inside = Vec3::new(base + (tip - base) / 4.0, 0.0, 0.0);
+        let rim = |i: usize| {
+            let angle = i as f32 / segments as f32 * TAU;
+            Vec3::new(base, angle.cos() * radius, angle.sin() * radius)
+        };
+        for i in 0..segments {
+            let (a, b) = (rim(i), rim(i + 1));
+            self.tri([a, b, Vec3::new(tip, 0.0, 0.0)], inside);
+            self.tri([a, b, Vec3::new(base, 0.0, 0.0)], inside);
+        }
+    }
+    // A ring of the given radius around the x axis
+    fn torus(&mut self, radius: f32, thickness: f32, segments: usize, sides: usize) {
+        let point = |i: usize, j: usize| {
+            let around = i as f32 / segments as f32 * TAU;
+            let out = Vec3::new(0.0, around.cos(), around.sin());
+            let turn = j as f32 / sides as f32 * TAU;
+            out * radius + (out * turn.cos() + Vec3::unit_x() * turn.sin()) * thickness
+        };
+        for i in 0..segments {
+            let around = (i as f32 + 0.5) / segments as f32 * TAU;
+            let inside = Vec3::new(0.0, around.cos(), around.sin()) * radius;
+            for j in 0..sides {
+                let (a, b) = (point(i, j), point(i + 1, j));
+                let (c, d) = (point(i + 1, j + 1), point(i, j + 1));
+                self.tri([a, b, c], inside);
+                self.tri([a, c, d], inside);
+            }
+        }
+    }
+    // Ends the current mesh, which gets one instance per axis
+    fn finish_mesh(&mut self) {
+        let end = self.indices.len() as u32;
+        self.entries.push(MeshEntry {
+            instance_count: 3,
+            submeshes: vec![SubmeshEntry {
+                indices: self.mesh_start..end,
+                vertex_base: 0,
+            }],
+        });
+        self.mesh_start = end;
+    }
+}
+
+// RGBA color modulation of the x and y handles, the center handle,
+// and the hovered or dragged handle
+const COLORMODS_2D: [[u8; 4]; 4] = [
+    [230, 50, 50, 255],
+    [50, 200, 50, 255],
+    [230, 230, 230, 255],
+    [255, 215, 25, 255],
+];
+// How many sprites make up the rotation ring of a Gizmo2D
+const RING_SEGMENTS: usize = 32;
+
+/// Move, rotate, and scale handles for sprites or other objects in a
+/// 2D world.  Handles 0 and 1 are the x and y axis handles, and
+/// handle 2 is the center square (which moves freely or scales
+/// uniformly) or the rotation ring.
+pub struct Gizmo2D {
+    /// The world space position of the gizmo's center
+    pub position: [f32; 2],
+    /// The rotation of the gizmo's axes in radians counterclockwise
+    pub rotation: f32,
+    /// Which handles are shown
+    pub mode: GizmoMode,
+    /// The length of the handles in world units
+    pub size: f32,
+    hovered: Option<usize>,
+    drag: Option<Drag2D>,
+    was_down: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Drag2D {
+    mode: GizmoMode,
+    handle: usize,
+    // The gizmo's position, axes, and size when the drag started
+    origin: Vec2,
+    axes: [Vec2; 2],
+    size: f32,
+    // Where the mouse was when the drag started and at the last update
+    start: Vec2,
+    last: Vec2,
+}
+
+impl Drag2D {
+    // The direction a handle moves in: an axis, or the diagonal between them for the center
+    fn dir(&self) -> Option<Vec2> {
+        match self.handle {
+            0 | 1 => Some(self.axes[self.handle]),
+            _ if self.mode == GizmoMode::Scale => Some((self.axes[0] + self.axes[1]).normalized()),
+            _ => None,
+        }
+    }
+    fn change(&self, mouse: Vec2) -> GizmoDrag2D {
+        match self.mode {
+            GizmoMode::Translate => {
+                let delta = mouse - self.last;
+                let delta = match self.dir() {
+                    Some(dir) => dir * delta.dot(dir),
+                    None => delta,
+                };
+                GizmoDrag2D::Translate(delta.into())
+            }
+            GizmoMode::Rotate => {
+                let angle = |v: Vec2| v.y.atan2(v.x);
+                let (now, last) = (mouse - self.origin, self.last - self.origin);
+                GizmoDrag2D::Rotate(wrap_angle(angle(now) - angle(last)))
+            }
+            GizmoMode::Scale => {
+                let dir = self.dir().unwrap();
+                let along = |v: Vec2| (v - self.origin).dot(dir);
+                let factor =
+                    scale_factor(self.size, along(self.start), along(self.last), along(mouse));
+                match self.handle {
+                    0 => GizmoDrag2D::Scale([factor, 1.0]),
+                    1 => GizmoDrag2D::Scale([1.0, factor]),
+                    _ => GizmoDrag2D::Scale([factor, factor]),
+                }
+            }
+        }
+    }
+}
+
+impl Gizmo2D {
+    /// Creates a gizmo at the given position with handles of the given length.
+    pub fn new(position: [f32; 2], size: f32) -> Self {
+        Self {
+            position,
+            rotation: 0.0,
+            mode: GizmoMode::Translate,
+            size,
+            hovered: None,
+            drag: None,
+            was_down: false,
+        }
+    }
+    /// Which handle is under the mouse or being dragged.
+    pub fn hovered(&self) -> Option<usize> {
+        self.drag.map(|drag| drag.handle).or(self.hovered)
+    }
+    /// Whether a handle is being dragged.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+    /// Hit-tests the handles against the mouse's world space
+    /// position and starts or ends drags as `mouse_down` changes.
+    /// Returns the change made by the current drag since the last
+    /// update, if any.
+    pub fn update(&mut self, mouse: [f32; 2], mouse_down: bool) -> Option<GizmoDrag2D> {
+        let pressed = mouse_down && !self.was_down;
+        self.was_down = mouse_down;
+        let mouse = Vec2::from(mouse);
+        match self.drag {
+            Some(mut drag) if mouse_down => {
+                let change = drag.change(mouse);
+                drag.last = mouse;
+                self.drag = Some(drag);
+                Some(change)
+            }
+            _ => {
+                self.drag = None;
+                self.hovered = self.pick(mouse);
+                if let (true, Some(handle)) = (pressed, self.hovered) {
+                    self.drag = Some(Drag2D {
+                        mode: self.mode,
+                        handle,
+                        origin: self.position.into(),
+                        axes: self.axes(),
+                        size: self.size,
+                        start: mouse,
+                        last: mouse,
+                    });
+                }
+                None
+            }
+        }
+    }
+    fn axes(&self) -> [Vec2; 2] {
+        let (sin, cos) = self.rotation.sin_cos();
+        [Vec2::new(cos, sin), Vec2::new(-sin, cos)]
+    }
+    fn pick(&self, mouse: Vec2) -> Option<usize> {
+        let [x_axis, y_axis] = self.axes();
+        let rel = mouse - Vec2::from(self.position);
+        let (x, y) = (rel.dot(x_axis), rel.dot(y_axis));
+        let tolerance = self.size * PICK_RADIUS * 1.5;
+        let along = |along: f32, across: f32| {
+            (-tolerance..=self.size + tolerance).contains(&along) && across.abs() <= tolerance
+        };
+        match self.mode {
+            GizmoMode::Rotate => ((rel.mag() - self.size).abs() <= tolerance).then_some(2),
+            _ if x.abs().max(y.abs()) <= self.size * 0.1 => Some(2),
+            _ if along(x, y) => Some(0),
+            _ if along(y, x) => Some(1),
+            _ => None,
+        }
+    }
+    /// How many sprites [`Gizmo2D::write_sprites`] needs in the current mode.
+    pub fn sprite_count(&self) -> usize {
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => 5,
+            GizmoMode::Rotate => RING_SEGMENTS,
+        }
+    }
+    /// Writes the gizmo's handles into `trfs` and `uvs`, returning
+    /// how many sprites were written.  `white` should be a region
+    /// of plain white texels; the handles are colored with its
+    /// [`SheetRegion::colormod`] and drawn at its depth.
+    pub fn write_sprites(
+        &self,
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+        white: SheetRegion,
+    ) -> usize {
+        let hovered = self.hovered();
+        let colored = |handle: usize| SheetRegion {
+            colormod: COLORMODS_2D[if hovered == Some(handle) { 3 } else { handle }],
+            ..white
+        };
+        let center = Vec2::from(self.position);
+        let thickness = (self.size / 24.0).max(1.0);
+        let mut sprites: Vec<(Transform, SheetRegion)> = Vec::with_capacity(self.sprite_count());
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                let tip = self.size * 0.15;
+                for (handle, axis) in self.axes().into_iter().enumerate() {
+                    let angle = self.rotation + handle as f32 * FRAC_PI_2;
+                    let shaft = self.size - tip;
+                    sprites.push((
+                        sprite(center + axis * (shaft / 2.0), shaft, thickness, angle),
+                        colored(handle),
+                    ));
+                    // Diamond tips for moving, square ones for scaling
+                    let tip_angle = if self.mode == GizmoMode::Translate {
+                        angle + FRAC_PI_4
+                    } else {
+                        angle
+                    };
+                    sprites.push((
+                        sprite(center + axis * (self.size - tip / 2.0), tip, tip, tip_angle),
+                        colored(handle),
+                    ));
+                }
+                let square = self.size * 0.2;
+                sprites.push((sprite(center, square, square, self.rotation), colored(2)));
+            }
+            GizmoMode::Rotate => {
+                let step = TAU / RING_SEGMENTS as f32;
+                // Chords overlap slightly so the ring has no gaps
+                let chord = 2.0 * self.size * (step / 2.0).sin() + thickness;
+                for i in 0..RING_SEGMENTS {
+                    let angle = self.rotation + (i as f32 + 0.5) * step;
+                    let pos = center + Vec2::new(angle.cos(), angle.sin()) * self.size;
+                    sprites.push((sprite(pos, chord, thickness, angle + FRAC_PI_2), colored(2)));
+                }
+            }
+        }
+        let mut used = 0;
+        for ((trf, uv), (sprite_trf, sprite_uv)) in trfs.iter_mut().zip(uvs.iter_mut()).zip(sprites)
+        {
+            *trf = sprite_trf;
+            *uv = sprite_uv;
+            used += 1;
+        }
+        used
+    }
+}
+
+fn sprite(center: Vec2, w: f32, h: f32, rot: f32) -> Transform {
+    Transform {
+        w: w.round().max(1.0) as u16,
+        h: h.round().max(1.0) as u16,
+        x: center.x,
+        y: center.y,
+        rot,
+    }
+}
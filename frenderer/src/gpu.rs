@@ -1,29 +1,183 @@
 //! A wrapper for WGPU state.
 
+use std::future::Future;
 use std::sync::Arc;
 
+/// What went wrong while setting up the GPU, a surface, or the event
+/// loop, returned by e.g. [`WGPU::with_request`],
+/// [`crate::Renderer::with_surface`], and `Driver::run_event_loop`.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum FrendererError {
-    NoUsableAdapter,
+    /// No adapter matched the request, or none could present to the surface
+    AdapterRequest,
+    /// The adapter lacks these required features
+    MissingFeatures(wgpu::Features),
+    /// The adapter couldn't create a device
+    DeviceRequest(wgpu::RequestDeviceError),
+    /// A surface couldn't be created or configured for the window
+    SurfaceConfig(String),
+    /// A shader failed to compile or validate, see [`WGPU::create_shader_checked`]
+    ShaderCompile(String),
+    /// The windowing event loop couldn't be created or failed while running
+    #[cfg(feature = "winit")]
+    EventLoop(winit::error::EventLoopError),
+    /// A logger couldn't be installed
+    Logging(String),
+    /// A GPU buffer couldn't be mapped to read it back, e.g. because
+    /// the device was lost; see [`crate::Renderer::capture_frame`]
+    Readback(wgpu::BufferAsyncError),
 }
 impl std::fmt::Display for FrendererError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            FrendererError::NoUsableAdapter => {
+            FrendererError::AdapterRequest => {
                 f.write_str("No valid adapter found for GPU requirements")
             }
+            FrendererError::MissingFeatures(features) => {
+                write!(f, "Adapter lacks required features {features:?}")
+            }
+            FrendererError::DeviceRequest(err) => write!(f, "Couldn't create GPU device: {err}"),
+            FrendererError::SurfaceConfig(msg) => write!(f, "Couldn't set up surface: {msg}"),
+            FrendererError::ShaderCompile(msg) => write!(f, "Shader failed to compile: {msg}"),
+            #[cfg(feature = "winit")]
+            FrendererError::EventLoop(err) => write!(f, "Event loop failed: {err}"),
+            FrendererError::Logging(msg) => write!(f, "Couldn't set up logging: {msg}"),
+            FrendererError::Readback(err) => write!(f, "Couldn't read back from the GPU: {err}"),
+        }
+    }
+}
+impl std::error::Error for FrendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrendererError::DeviceRequest(err) => Some(err),
+            #[cfg(feature = "winit")]
+            FrendererError::EventLoop(err) => Some(err),
+            FrendererError::Readback(err) => Some(err),
+            _ => None,
         }
     }
 }
-impl std::error::Error for FrendererError {}
+impl From<wgpu::RequestDeviceError> for FrendererError {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        FrendererError::DeviceRequest(err)
+    }
+}
+impl From<wgpu::CreateSurfaceError> for FrendererError {
+    fn from(err: wgpu::CreateSurfaceError) -> Self {
+        FrendererError::SurfaceConfig(err.to_string())
+    }
+}
+#[cfg(feature = "winit")]
+impl From<winit::error::EventLoopError> for FrendererError {
+    fn from(err: winit::error::EventLoopError) -> Self {
+        FrendererError::EventLoop(err)
+    }
+}
+
+/// What to ask of the GPU when creating a [`WGPU`] with
+/// [`WGPU::with_request`].  The default asks for the same things as
+/// [`WGPU::new`].
+#[derive(Clone, Debug, Default)]
+pub struct DeviceRequest {
+    /// Which kind of adapter to prefer
+    pub power_preference: wgpu::PowerPreference,
+    /// Features which must be supported, or device creation fails
+    pub required_features: wgpu::Features,
+    /// Features which are enabled if the adapter supports them
+    pub optional_features: wgpu::Features,
+    /// Limits to request.  Any the adapter can't meet are lowered
+    /// (or for alignments, raised) to what it supports.  If absent,
+    /// frenderer's usual downlevel limits are used.
+    pub limits: Option<wgpu::Limits>,
+}
+
+/// What a [`DeviceRequest`] got, see [`WGPU::device_report`].
+#[derive(Clone, Debug, Default)]
+pub struct DeviceReport {
+    /// The optional features which were enabled
+    pub enabled_features: wgpu::Features,
+    /// The optional features the adapter doesn't support
+    pub missing_features: wgpu::Features,
+    /// The requested limits which had to be adjusted, with the
+    /// requested and granted values
+    pub adjusted_limits: Vec<(&'static str, u64, u64)>,
+}
+
+// Adjust each limit of `requested` which `allowed` can't meet to `allowed`'s value
+fn fit_limits(requested: &wgpu::Limits, allowed: &wgpu::Limits) -> wgpu::Limits {
+    let mut limits = requested.clone();
+    macro_rules! fit {
+        ($($name:ident),* ; $($align:ident),*) => {
+            $(limits.$name = limits.$name.min(allowed.$name);)*
+            $(limits.$align = limits.$align.max(allowed.$align);)*
+        };
+    }
+    fit!(
+        max_texture_dimension_1d,
+        max_texture_dimension_2d,
+        max_texture_dimension_3d,
+        max_texture_array_layers,
+        max_bind_groups,
+        max_bindings_per_bind_group,
+        max_dynamic_uniform_buffers_per_pipeline_layout,
+        max_dynamic_storage_buffers_per_pipeline_layout,
+        max_sampled_textures_per_shader_stage,
+        max_samplers_per_shader_stage,
+        max_storage_buffers_per_shader_stage,
+        max_storage_textures_per_shader_stage,
+        max_uniform_buffers_per_shader_stage,
+        max_uniform_buffer_binding_size,
+        max_storage_buffer_binding_size,
+        max_vertex_buffers,
+        max_buffer_size,
+        max_vertex_attributes,
+        max_vertex_buffer_array_stride,
+        max_push_constant_size,
+        max_inter_stage_shader_components,
+        max_compute_workgroup_storage_size,
+        max_compute_invocations_per_workgroup,
+        max_compute_workgroup_size_x,
+        max_compute_workgroup_size_y,
+        max_compute_workgroup_size_z,
+        max_compute_workgroups_per_dimension,
+        max_non_sampler_bindings;
+        min_uniform_buffer_offset_alignment,
+        min_storage_buffer_offset_alignment
+    );
+    limits
+}
 
 /// A wrapper for a WGPU instance, surface, adapter, device, queue, and surface configuration.
+///
+/// Cloning a `WGPU` is cheap and shares the same device and queue.
+/// On native targets clones can be sent to worker threads, e.g. to
+/// decode images and create textures in the background while the
+/// renderer keeps drawing on the main thread.
 #[allow(dead_code)]
+#[derive(Clone)]
 pub struct WGPU {
     instance: Arc<wgpu::Instance>,
     adapter: Arc<wgpu::Adapter>,
     device: Arc<wgpu::Device>,
     queue: Arc<wgpu::Queue>,
+    report: DeviceReport,
+}
+
+// Worker threads rely on this, so keep it from regressing
+#[cfg(not(target_arch = "wasm32"))]
+const _: fn() = || {
+    fn send_sync<T: Send + Sync>() {}
+    send_sync::<WGPU>();
+    send_sync::<wgpu::Texture>();
+};
+
+// Wakes nothing, for polling futures that are already finished like
+// native error scopes
+struct NoopWaker;
+
+impl std::task::Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
 }
 
 impl WGPU {
@@ -39,22 +193,33 @@ impl WGPU {
             adapter,
             device,
             queue,
+            report: DeviceReport::default(),
         }
     }
     /// Create a WGPU structure by initializing WGPU for display onto the given surface.
     pub async fn new(
         instance: Arc<wgpu::Instance>,
         surface: Option<&wgpu::Surface<'static>>,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+    ) -> Result<Self, FrendererError> {
+        Self::with_request(instance, surface, &DeviceRequest::default()).await
+    }
+    /// Like [`WGPU::new`], but requesting the given features and
+    /// limits.  Whatever optional features or limits couldn't be
+    /// provided are recorded in [`WGPU::device_report`].
+    pub async fn with_request(
+        instance: Arc<wgpu::Instance>,
+        surface: Option<&wgpu::Surface<'static>>,
+        request: &DeviceRequest,
+    ) -> Result<Self, FrendererError> {
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
+                power_preference: request.power_preference,
                 force_fallback_adapter: false,
                 // Request an adapter which can render to our surface
                 compatible_surface: surface,
             })
             .await
-            .ok_or(FrendererError::NoUsableAdapter)?;
+            .ok_or(FrendererError::AdapterRequest)?;
         let is_gl = adapter.get_info().backend == wgpu::Backend::Gl;
         #[cfg(not(target_arch = "wasm32"))]
         let is_web = false;
@@ -62,28 +227,111 @@ impl WGPU {
         let is_web = true;
         let use_storage = !(is_web && is_gl);
 
+        let supported = adapter.features();
+        if !supported.contains(request.required_features) {
+            return Err(FrendererError::MissingFeatures(
+                request.required_features - supported,
+            ));
+        }
+        let mut report = DeviceReport {
+            enabled_features: request.optional_features & supported,
+            missing_features: request.optional_features - supported,
+            adjusted_limits: vec![],
+        };
+        let limits = match &request.limits {
+            Some(limits) => {
+                limits.check_limits_with_fail_fn(&adapter.limits(), false, |name, want, got| {
+                    report.adjusted_limits.push((name, want, got))
+                });
+                fit_limits(limits, &adapter.limits())
+            }
+            None => if use_storage {
+                wgpu::Limits::downlevel_defaults()
+            } else {
+                wgpu::Limits::downlevel_webgl2_defaults()
+            }
+            .using_resolution(adapter.limits()),
+        };
+        for feature in report.missing_features.iter() {
+            log::warn!("Optional GPU feature {feature:?} is unavailable");
+        }
+        for (name, want, got) in report.adjusted_limits.iter() {
+            log::warn!("GPU limit {name} lowered from {want} to {got}");
+        }
+
+        // Record an API trace if requested (native only)
+        #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+        let trace_path = std::env::var_os("FRENDERER_TRACE_DIR").map(std::path::PathBuf::from);
+        #[cfg(not(all(feature = "debug", not(target_arch = "wasm32"))))]
+        let trace_path: Option<std::path::PathBuf> = None;
+
         // Create the logical device and command queue
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    required_limits: if use_storage {
-                        wgpu::Limits::downlevel_defaults()
-                    } else {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    }
-                    .using_resolution(adapter.limits()),
+                    label: Some("frenderer:device"),
+                    required_features: request.required_features | report.enabled_features,
+                    required_limits: limits,
                 },
-                None,
+                trace_path.as_deref(),
             )
             .await?;
-        Ok(Self::with_resources(
-            instance,
-            Arc::new(adapter),
-            Arc::new(device),
-            Arc::new(queue),
-        ))
+        Ok(Self {
+            report,
+            ..Self::with_resources(
+                instance,
+                Arc::new(adapter),
+                Arc::new(device),
+                Arc::new(queue),
+            )
+        })
+    }
+    /// Creates a shader module, reporting compilation and validation
+    /// errors as [`FrendererError::ShaderCompile`] rather than
+    /// panicking, e.g. for custom renderers' or user-written shaders.
+    pub async fn create_shader_checked(
+        &self,
+        desc: wgpu::ShaderModuleDescriptor<'_>,
+    ) -> Result<wgpu::ShaderModule, FrendererError> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self.device.create_shader_module(desc);
+        match self.device.pop_error_scope().await {
+            Some(err) => Err(FrendererError::ShaderCompile(err.to_string())),
+            None => Ok(module),
+        }
+    }
+    // Creates one of frenderer's own shader modules.  Like
+    // create_shader_checked it catches validation errors in an error
+    // scope, but since renderers are made synchronously a bad shader
+    // (e.g. a broken sprite shader variant) panics with its label and
+    // the error.  Native and WebGL backends report errors right away;
+    // the browser's WebGPU reports them later, so there they go to
+    // wgpu's usual uncaptured error handler instead.
+    pub(crate) fn create_shader(
+        &self,
+        desc: wgpu::ShaderModuleDescriptor<'_>,
+    ) -> wgpu::ShaderModule {
+        if self.adapter.get_info().backend == wgpu::Backend::BrowserWebGpu {
+            return self.device.create_shader_module(desc);
+        }
+        let label = desc.label.unwrap_or("frenderer:shader").to_string();
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self.device.create_shader_module(desc);
+        let error = std::pin::pin!(self.device.pop_error_scope());
+        let waker = std::task::Waker::from(Arc::new(NoopWaker));
+        let mut cx = std::task::Context::from_waker(&waker);
+        if let std::task::Poll::Ready(Some(err)) = error.poll(&mut cx) {
+            panic!(
+                "{}",
+                FrendererError::ShaderCompile(format!("{label}: {err}"))
+            );
+        }
+        module
+    }
+    /// Which optional features were enabled and which limits were
+    /// adjusted when this was created with [`WGPU::with_request`].
+    pub fn device_report(&self) -> &DeviceReport {
+        &self.report
     }
     /// Returns true if this GPU interface is using a GL backend, important to work around some bugs
     pub fn is_gl(&self) -> bool {
@@ -108,6 +356,17 @@ impl WGPU {
                 .contains(wgpu::DownlevelFlags::VERTEX_STORAGE)
             && self.device.limits().max_storage_buffers_per_shader_stage > 0
     }
+    /// Whether this GPU can run compute shaders writing to storage
+    /// buffers, as [`crate::autotile::AutotileSprites`] does
+    pub fn supports_compute(&self) -> bool {
+        self.supports_storage()
+            && self
+                .adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            && self.device.limits().max_compute_invocations_per_workgroup > 0
+    }
     /// Returns this GPU wrapper's [`wgpu::Instance`].
     pub fn instance(&self) -> &wgpu::Instance {
         &self.instance
@@ -125,4 +384,179 @@ impl WGPU {
     pub fn queue(&self) -> &wgpu::Queue {
         &self.queue
     }
+    /// Creates an array texture with a layer for each of `images`,
+    /// which must all have the given size.  This, like the rest of
+    /// `WGPU`, can be called on a clone of the renderer's [`WGPU`] on a
+    /// worker thread; the texture can be used in groups as soon as
+    /// it's handed back, since its upload goes out with the next
+    /// frame's commands.
+    pub fn create_array_texture(
+        &self,
+        images: &[&[u8]],
+        format: wgpu::TextureFormat,
+        (width, height): (u32, u32),
+        label: Option<&str>,
+    ) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: if self.is_gl() {
+                // Workaround for opengl: If len is 1, this array texture is just initialized and treated as a regular single texture.  So we lie and say we have at least two (and if we have 6, we lie and say we have 7 so it isn't treated as a cubemap)
+                match images.len() {
+                    1 => 2,
+                    6 => 7,
+                    l => l,
+                }
+            } else {
+                images.len()
+            } as u32,
+        };
+        let texture = self.device().create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        for (layer, img) in images.iter().enumerate() {
+            assert_eq!(
+                img.len(),
+                images[0].len(),
+                "Can't create an array texture with images of different dimensions"
+            );
+            self.queue().write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                img,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(format.block_copy_size(None).unwrap_or(4) * width),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        // again, if it's opengl we may need to copy our first texture again to the last (bonus) layer index.
+        if size.depth_or_array_layers > images.len() as u32 {
+            self.queue().write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: images.len() as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                images[0],
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(format.block_copy_size(None).unwrap_or(4) * width),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        texture
+    }
+    /// Creates a single texture of the given size, which can be done
+    /// on a worker thread like [`WGPU::create_array_texture`].
+    pub fn create_texture(
+        &self,
+        image: &[u8],
+        format: wgpu::TextureFormat,
+        (width, height): (u32, u32),
+        label: Option<&str>,
+    ) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device().create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        self.queue().write_texture(
+            texture.as_image_copy(),
+            image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        texture
+    }
+    /// Uploads the given rectangles of `images` into the texture
+    /// made from them with [`WGPU::create_array_texture`] or
+    /// [`WGPU::create_texture`], leaving the rest of the texture alone.
+    /// The rectangles typically come from
+    /// [`crate::reload::diff_layers`] when hot-reloading the images.
+    pub fn write_texture_rects(
+        &self,
+        texture: &wgpu::Texture,
+        images: &[&[u8]],
+        rects: &[crate::reload::DirtyRect],
+    ) {
+        let bytes_per_pixel = texture.format().block_copy_size(None).unwrap_or(4);
+        let width = texture.width();
+        for rect in rects {
+            // create_array_texture may have copied layer 0 into a bonus layer on GL
+            let bonus = (rect.layer == 0 && texture.depth_or_array_layers() > images.len() as u32)
+                .then_some(images.len() as u32);
+            for z in std::iter::once(rect.layer).chain(bonus) {
+                self.queue().write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d {
+                            x: rect.x,
+                            y: rect.y,
+                            z,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    images[rect.layer as usize],
+                    wgpu::ImageDataLayout {
+                        offset: ((rect.y * width + rect.x) * bytes_per_pixel) as u64,
+                        bytes_per_row: Some(bytes_per_pixel * width),
+                        rows_per_image: Some(texture.height()),
+                    },
+                    wgpu::Extent3d {
+                        width: rect.width,
+                        height: rect.height,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+    }
 }
@@ -1,13 +1,146 @@
-//! A wrapper for a current and previous input button/mouse state.
+//! A wrapper for a current and previous input button/mouse/touch state.
+//!
+//! Keys, mouse buttons, and positions are frenderer's own types, so
+//! the same code compiles with or without the `winit` feature.  With
+//! it, [`Input::process_input_event`] consumes winit events directly
+//! (and winit's types convert with `From`).  Without it, other
+//! windowing libraries (e.g. SDL2 or tao) can feed an [`Input`]
+//! through methods like [`Input::press_key`] and
+//! [`Input::move_mouse`], passing their own key codes as
+//! [`Key::Other`].
+//!
+//! Files dragged over and dropped onto the window are reported
+//! through [`Input::dropped_files`] and [`Input::is_file_hovering`].
+//! winit reports them on native platforms; on the web, call
+//! [`Input::listen_for_file_drops`] with the window's canvas.
+//!
+//! For text fields, [`Input::start_text_entry`] collects typed
+//! characters into [`Input::typed_text`].  While text entry is on,
+//! Ctrl+V (or Cmd+V) pastes from the [`crate::clipboard`] into the
+//! typed text, and Ctrl/Cmd+C and Ctrl/Cmd+X show up as
+//! [`Input::is_copy_requested`] and [`Input::is_cut_requested`]; the
+//! game answers those by passing its selection to
+//! [`Input::copy_text`].  On the web, pasting goes through the
+//! browser instead: call [`Input::listen_for_paste`] with the
+//! window's canvas.
 
-pub use winit::dpi::PhysicalPosition as MousePos;
-use winit::event::{ElementState, Event, MouseButton, WindowEvent};
-pub use winit::keyboard::KeyCode as Key;
+mod keys;
+pub use keys::Key;
+#[cfg(feature = "winit")]
+use winit::event::{ElementState, Event, TouchPhase, WindowEvent};
+
+/// A mouse button (these are the same as winit's `MouseButton`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MouseButton {
+    /// The left (primary) button
+    Left,
+    /// The right (secondary) button
+    Right,
+    /// The middle button or wheel click
+    Middle,
+    /// The "back" side button
+    Back,
+    /// The "forward" side button
+    Forward,
+    /// Any other button, by the windowing library's number for it
+    Other(u16),
+}
+
+#[cfg(feature = "winit")]
+impl From<winit::event::MouseButton> for MouseButton {
+    fn from(button: winit::event::MouseButton) -> Self {
+        use winit::event::MouseButton as WB;
+        match button {
+            WB::Left => Self::Left,
+            WB::Right => Self::Right,
+            WB::Middle => Self::Middle,
+            WB::Back => Self::Back,
+            WB::Forward => Self::Forward,
+            WB::Other(n) => Self::Other(n),
+        }
+    }
+}
+
+/// A position in physical pixels with the origin at the top left.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MousePos<T> {
+    /// The horizontal position
+    pub x: T,
+    /// The vertical position
+    pub y: T,
+}
+
+#[cfg(feature = "winit")]
+impl<T> From<winit::dpi::PhysicalPosition<T>> for MousePos<T> {
+    fn from(pos: winit::dpi::PhysicalPosition<T>) -> Self {
+        Self { x: pos.x, y: pos.y }
+    }
+}
+
+/// How far (in physical pixels) a touch can move between starting and
+/// ending and still count as a tap.
+pub const TAP_SLOP: f64 = 16.0;
+
+/// A file dragged over or dropped onto the window.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DroppedFile {
+    /// A file on disk, on native platforms
+    Path(std::path::PathBuf),
+    /// A file from the browser, on the web.  It's a `Blob`, so read
+    /// its bytes with e.g. `File::array_buffer` and
+    /// `wasm-bindgen-futures`.
+    #[cfg(target_arch = "wasm32")]
+    Web(web_sys::File),
+}
+
+impl DroppedFile {
+    /// The file's name, without any directories.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Path(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            #[cfg(target_arch = "wasm32")]
+            Self::Web(file) => file.name(),
+        }
+    }
+}
+
+// File drag events from the browser, queued by listen_for_file_drops
+#[cfg(all(target_arch = "wasm32", feature = "winit"))]
+enum WebDrop {
+    Hover,
+    Cancel,
+    Drop(web_sys::File),
+}
+
+/// A finger (or stylus) touching the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Touch {
+    /// Identifies this touch for as long as it lasts
+    pub id: u64,
+    /// Where the touch is now
+    pub pos: MousePos<f64>,
+    /// Where the touch began
+    pub start_pos: MousePos<f64>,
+}
+
+impl Touch {
+    /// Did this touch stay close enough to where it started to count as a tap?
+    pub fn is_tap(&self) -> bool {
+        let dx = self.pos.x - self.start_pos.x;
+        let dy = self.pos.y - self.start_pos.y;
+        dx * dx + dy * dy <= TAP_SLOP * TAP_SLOP
+    }
+}
 
 /// `Input` wraps a current and previous input state.  When window
-/// events arrive from [`winit`], you should call
-/// [`Input::process_input_event()`]; later (e.g. when handling
-/// [`winit::event::Event::AboutToWait`] and simulating a single game
+/// events arrive from `winit`, you should call
+/// `Input::process_input_event()` (or [`Input::press_key()`] and
+/// friends for other windowing libraries); later (e.g. when handling
+/// `winit::event::Event::AboutToWait` and simulating a single game
 /// step), you can make queries like [`Input::is_key_down()`], and
 /// when you've finished processing events for a frame you can call
 /// [`Input::next_frame()`] to cycle the new state to the old state.
@@ -18,6 +151,31 @@ pub struct Input {
     prev_mouse: Vec<MouseButton>,
     now_mouse_pos: MousePos<f64>,
     prev_mouse_pos: MousePos<f64>,
+    now_touches: Vec<Touch>,
+    prev_touches: Vec<Touch>,
+    // Touches which ended since the last frame
+    ended_touches: Vec<Touch>,
+    // Keys held by e.g. a virtual gamepad rather than the keyboard
+    now_virtual_keys: Vec<Key>,
+    prev_virtual_keys: Vec<Key>,
+    // Files being dragged over the window, if any are
+    hovered_files: Option<Vec<DroppedFile>>,
+    // Files dropped since the last frame
+    dropped_files: Vec<DroppedFile>,
+    #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+    web_drops: std::rc::Rc<std::cell::RefCell<Vec<WebDrop>>>,
+    // Whether typed characters are collected, see start_text_entry
+    text_entry: bool,
+    // Text typed or pasted since the last frame
+    typed_text: String,
+    copy_requested: bool,
+    cut_requested: bool,
+    #[cfg(feature = "winit")]
+    modifiers: winit::keyboard::ModifiersState,
+    clipboard: crate::clipboard::Clipboard,
+    // Text pasted in the browser, queued by listen_for_paste
+    #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+    web_pastes: std::rc::Rc<std::cell::RefCell<Vec<String>>>,
 }
 impl Default for Input {
     fn default() -> Self {
@@ -28,13 +186,37 @@ impl Default for Input {
             prev_mouse: vec![],
             now_mouse_pos: MousePos { x: 0.0, y: 0.0 },
             prev_mouse_pos: MousePos { x: 0.0, y: 0.0 },
+            now_touches: vec![],
+            prev_touches: vec![],
+            ended_touches: vec![],
+            now_virtual_keys: vec![],
+            prev_virtual_keys: vec![],
+            hovered_files: None,
+            dropped_files: vec![],
+            #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+            web_drops: Default::default(),
+            text_entry: false,
+            typed_text: String::new(),
+            copy_requested: false,
+            cut_requested: false,
+            #[cfg(feature = "winit")]
+            modifiers: Default::default(),
+            clipboard: crate::clipboard::Clipboard::new(),
+            #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+            web_pastes: Default::default(),
         }
     }
 }
 #[allow(dead_code)]
 impl Input {
     /// Process a [`winit`] event and update the current keys/mouse position.
+    #[cfg(feature = "winit")]
     pub fn process_input_event<T>(&mut self, ev: &Event<T>) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.take_web_drops();
+            self.take_web_pastes();
+        }
         match ev {
             // WindowEvent->KeyboardInput: Keyboard input!
             Event::WindowEvent {
@@ -44,36 +226,94 @@ impl Input {
             } => {
                 self.handle_key_event(key_ev);
             }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(modifiers),
+                ..
+            } => {
+                self.modifiers = modifiers.state();
+            }
             Event::WindowEvent {
                 event: WindowEvent::MouseInput { state, button, .. },
                 ..
             } => {
-                self.handle_mouse_button(*state, *button);
+                self.handle_mouse_button(*state, (*button).into());
             }
             Event::WindowEvent {
                 event: WindowEvent::CursorMoved { position, .. },
                 ..
             } => {
-                self.handle_mouse_move(*position);
+                self.move_mouse((*position).into());
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Touch(touch),
+                ..
+            } => {
+                self.handle_touch(touch);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::HoveredFile(path),
+                ..
+            } => {
+                self.hover_file(DroppedFile::Path(path.clone()));
+            }
+            Event::WindowEvent {
+                event: WindowEvent::HoveredFileCancelled,
+                ..
+            } => {
+                self.cancel_file_hover();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
+                ..
+            } => {
+                self.drop_file(DroppedFile::Path(path.clone()));
             }
             _ => (),
         }
     }
-    /// Is this key currently down?
+    /// Is this key currently down (on the keyboard or virtually)?
     pub fn is_key_down(&self, kc: Key) -> bool {
-        self.now_keys.contains(&kc)
+        self.now_keys.contains(&kc) || self.now_virtual_keys.contains(&kc)
     }
     /// Is this key currently up?
     pub fn is_key_up(&self, kc: Key) -> bool {
-        !self.now_keys.contains(&kc)
+        !self.is_key_down(kc)
     }
     /// Was this key just pressed on this frame?
     pub fn is_key_pressed(&self, kc: Key) -> bool {
-        self.now_keys.contains(&kc) && !self.prev_keys.contains(&kc)
+        self.is_key_down(kc) && !self.was_key_down(kc)
     }
     /// Was this key just released on this frame?
     pub fn is_key_released(&self, kc: Key) -> bool {
-        !self.now_keys.contains(&kc) && self.prev_keys.contains(&kc)
+        !self.is_key_down(kc) && self.was_key_down(kc)
+    }
+    fn was_key_down(&self, kc: Key) -> bool {
+        self.prev_keys.contains(&kc) || self.prev_virtual_keys.contains(&kc)
+    }
+    /// Replace the set of keys held down virtually, e.g. by a
+    /// [`crate::gamepad::VirtualGamepad`].  Virtual keys count as
+    /// held in every key query alongside real keyboard keys.
+    pub fn set_virtual_keys(&mut self, keys: impl IntoIterator<Item = Key>) {
+        self.now_virtual_keys.clear();
+        self.now_virtual_keys.extend(keys);
+    }
+    /// Which touches are currently on the screen?
+    pub fn touches(&self) -> &[Touch] {
+        &self.now_touches
+    }
+    /// Which touches began this frame?
+    pub fn touches_started(&self) -> impl Iterator<Item = &Touch> {
+        self.now_touches
+            .iter()
+            .filter(|t| !self.prev_touches.iter().any(|p| p.id == t.id))
+    }
+    /// Which touches ended this frame?
+    pub fn touches_ended(&self) -> &[Touch] {
+        &self.ended_touches
+    }
+    /// Which touches ended this frame without moving much?
+    pub fn taps(&self) -> impl Iterator<Item = &Touch> {
+        self.ended_touches.iter().filter(|t| t.is_tap())
     }
     /// Is this mouse button currently held?
     pub fn is_mouse_down(&self, button: MouseButton) -> bool {
@@ -95,6 +335,25 @@ impl Input {
     pub fn mouse_pos(&self) -> MousePos<f64> {
         self.now_mouse_pos
     }
+    /// Where is the mouse in the rendered image, in render pixels
+    /// from its top left?  This accounts for how `renderer` fits
+    /// the image to the surface; see [`crate::Renderer::surface_to_render`].
+    pub fn mouse_render_pos(&self, renderer: &crate::Renderer) -> [f32; 2] {
+        renderer.surface_to_render([self.now_mouse_pos.x as f32, self.now_mouse_pos.y as f32])
+    }
+    /// Where is the mouse on the surface, in physical pixels?
+    pub fn mouse_surface_px(&self) -> crate::SurfacePx {
+        crate::SurfacePx::new(self.now_mouse_pos.x as f32, self.now_mouse_pos.y as f32)
+    }
+    /// Where is the mouse in world space as seen through `camera`,
+    /// in the image drawn by `renderer`?
+    pub fn mouse_world_pos(
+        &self,
+        renderer: &crate::Renderer,
+        camera: &crate::sprites::Camera2D,
+    ) -> crate::WorldPos {
+        renderer.surface_px_to_world(camera, self.mouse_surface_px())
+    }
     /// How much has the mouse moved this frame?
     pub fn mouse_delta(&self) -> MousePos<f64> {
         MousePos {
@@ -116,7 +375,275 @@ impl Input {
         self.prev_mouse.extend_from_slice(&self.now_mouse);
 
         self.prev_mouse_pos = self.now_mouse_pos;
+        self.prev_touches.clear();
+        self.prev_touches.extend_from_slice(&self.now_touches);
+        self.ended_touches.clear();
+        self.dropped_files.clear();
+        self.typed_text.clear();
+        self.copy_requested = false;
+        self.cut_requested = false;
+        self.prev_virtual_keys.clear();
+        self.prev_virtual_keys
+            .extend_from_slice(&self.now_virtual_keys);
+    }
+    /// Record that a key was pressed.
+    pub fn press_key(&mut self, kc: Key) {
+        if !self.now_keys.contains(&kc) {
+            self.now_keys.push(kc);
+        }
+    }
+    /// Record that a key was released.
+    pub fn release_key(&mut self, kc: Key) {
+        if let Some(idx) = self.now_keys.iter().position(|k| *k == kc) {
+            self.now_keys.swap_remove(idx);
+        }
+    }
+    /// Record that a mouse button was pressed.
+    pub fn press_mouse(&mut self, button: MouseButton) {
+        if !self.now_mouse.contains(&button) {
+            self.now_mouse.push(button);
+        }
+    }
+    /// Record that a mouse button was released.
+    pub fn release_mouse(&mut self, button: MouseButton) {
+        if let Some(idx) = self.now_mouse.iter().position(|m| *m == button) {
+            self.now_mouse.swap_remove(idx);
+        }
+    }
+    /// Record that the mouse moved to `position`.
+    pub fn move_mouse(&mut self, position: MousePos<f64>) {
+        self.now_mouse_pos = position;
+    }
+    /// Record that touch `id` began (or moved) at `pos`.
+    pub fn touch_started(&mut self, id: u64, pos: MousePos<f64>) {
+        match self.now_touches.iter_mut().find(|t| t.id == id) {
+            Some(touch) => touch.pos = pos,
+            None => self.now_touches.push(Touch {
+                id,
+                pos,
+                start_pos: pos,
+            }),
+        }
+    }
+    /// Record that touch `id` moved to `pos`.
+    pub fn touch_moved(&mut self, id: u64, pos: MousePos<f64>) {
+        if let Some(touch) = self.now_touches.iter_mut().find(|t| t.id == id) {
+            touch.pos = pos;
+        }
+    }
+    /// Record that touch `id` lifted off at `pos`.
+    pub fn touch_ended(&mut self, id: u64, pos: MousePos<f64>) {
+        if let Some(idx) = self.now_touches.iter().position(|t| t.id == id) {
+            let mut ended = self.now_touches.swap_remove(idx);
+            ended.pos = pos;
+            self.ended_touches.push(ended);
+        }
+    }
+    /// Which files were dropped onto the window this frame?  Use
+    /// [`Input::mouse_pos`] to see where they were dropped.
+    pub fn dropped_files(&self) -> &[DroppedFile] {
+        &self.dropped_files
+    }
+    /// Are files being dragged over the window?
+    pub fn is_file_hovering(&self) -> bool {
+        self.hovered_files.is_some()
+    }
+    /// Which files are being dragged over the window?  Browsers
+    /// don't say until they're dropped, so on the web this is empty
+    /// even while [`Input::is_file_hovering`].
+    pub fn hovered_files(&self) -> &[DroppedFile] {
+        self.hovered_files.as_deref().unwrap_or(&[])
+    }
+    /// Record that a file is being dragged over the window.
+    pub fn hover_file(&mut self, file: DroppedFile) {
+        let hovered = self.hovered_files.get_or_insert_with(Vec::new);
+        if !hovered.contains(&file) {
+            hovered.push(file);
+        }
+    }
+    /// Record that files stopped being dragged over the window
+    /// without being dropped.
+    pub fn cancel_file_hover(&mut self) {
+        self.hovered_files = None;
+    }
+    /// Record that a file was dropped onto the window.
+    pub fn drop_file(&mut self, file: DroppedFile) {
+        self.hovered_files = None;
+        self.dropped_files.push(file);
     }
+    /// Listens for files dragged over and dropped onto `canvas` (on
+    /// the web, where winit doesn't report them), e.g. the canvas
+    /// from winit's `WindowExtWebSys::canvas`.  They're reported from
+    /// the next call to [`Input::process_input_event`] on.
+    #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+    pub fn listen_for_file_drops(&mut self, canvas: &web_sys::HtmlCanvasElement) {
+        use wasm_bindgen::{closure::Closure, JsCast};
+        let listen = |name: &str, handler: Box<dyn FnMut(web_sys::DragEvent)>| {
+            let handler = Closure::wrap(handler);
+            canvas
+                .add_event_listener_with_callback(name, handler.as_ref().unchecked_ref())
+                .unwrap();
+            // The listener lives as long as the canvas
+            handler.forget();
+        };
+        let queue = self.web_drops.clone();
+        listen(
+            "dragover",
+            Box::new(move |ev: web_sys::DragEvent| {
+                // Without this the browser opens the file itself on drop
+                ev.prevent_default();
+                queue.borrow_mut().push(WebDrop::Hover);
+            }),
+        );
+        let queue = self.web_drops.clone();
+        listen(
+            "dragleave",
+            Box::new(move |_ev: web_sys::DragEvent| {
+                queue.borrow_mut().push(WebDrop::Cancel);
+            }),
+        );
+        let queue = self.web_drops.clone();
+        listen(
+            "drop",
+            Box::new(move |ev: web_sys::DragEvent| {
+                ev.prevent_default();
+                let Some(files) = ev.data_transfer().and_then(|dt| dt.files()) else {
+                    return;
+                };
+                let mut queue = queue.borrow_mut();
+                for idx in 0..files.length() {
+                    if let Some(file) = files.get(idx) {
+                        queue.push(WebDrop::Drop(file));
+                    }
+                }
+            }),
+        );
+    }
+    #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+    fn take_web_drops(&mut self) {
+        let drops = std::mem::take(&mut *self.web_drops.borrow_mut());
+        for drop in drops {
+            match drop {
+                WebDrop::Hover => {
+                    self.hovered_files.get_or_insert_with(Vec::new);
+                }
+                WebDrop::Cancel => self.cancel_file_hover(),
+                WebDrop::Drop(file) => self.drop_file(DroppedFile::Web(file)),
+            }
+        }
+    }
+    /// Starts collecting typed and pasted text into
+    /// [`Input::typed_text`], e.g. when a text field gains focus.
+    /// Key presses are still reported as usual.
+    pub fn start_text_entry(&mut self) {
+        self.text_entry = true;
+    }
+    /// Stops collecting typed text.
+    pub fn stop_text_entry(&mut self) {
+        self.text_entry = false;
+        self.typed_text.clear();
+    }
+    /// Is text entry on?
+    pub fn is_text_entry(&self) -> bool {
+        self.text_entry
+    }
+    /// Text typed or pasted on this frame while text entry was on.
+    /// Control characters are left out, so check e.g. backspace and
+    /// enter with [`Input::is_key_pressed`].
+    pub fn typed_text(&self) -> &str {
+        &self.typed_text
+    }
+    /// Was copying (Ctrl/Cmd+C) asked for on this frame, during text entry?
+    pub fn is_copy_requested(&self) -> bool {
+        self.copy_requested
+    }
+    /// Was cutting (Ctrl/Cmd+X) asked for on this frame, during text entry?
+    pub fn is_cut_requested(&self) -> bool {
+        self.cut_requested
+    }
+    /// Puts `text` on the system clipboard, e.g. a text field's
+    /// selection when [`Input::is_copy_requested`].  Returns whether
+    /// that worked.
+    pub fn copy_text(&mut self, text: &str) -> bool {
+        self.clipboard.set_text(text)
+    }
+    /// Puts a captured frame on the system clipboard, e.g. from
+    /// [`crate::Renderer::capture_frame`] when a screenshot key is
+    /// pressed.  Returns whether that worked.
+    #[cfg(feature = "screenshot")]
+    pub fn copy_image(&mut self, frame: &crate::CapturedFrame) -> bool {
+        frame.copy_to_clipboard(&mut self.clipboard)
+    }
+    /// The text on the system clipboard, if any; always `None` on the
+    /// web (see [`crate::clipboard`]).
+    pub fn clipboard_text(&mut self) -> Option<String> {
+        self.clipboard.text()
+    }
+    /// Record that `text` was typed.  It's ignored unless text entry
+    /// is on.
+    pub fn type_text(&mut self, text: &str) {
+        if self.text_entry {
+            self.typed_text
+                .extend(text.chars().filter(|c| !c.is_control()));
+        }
+    }
+    /// Record that pasting was asked for, typing the clipboard's
+    /// text if text entry is on.
+    pub fn paste(&mut self) {
+        if !self.text_entry {
+            return;
+        }
+        if let Some(text) = self.clipboard.text() {
+            self.type_text(&text);
+        }
+    }
+    /// Record that copying was asked for, if text entry is on.
+    pub fn request_copy(&mut self) {
+        self.copy_requested |= self.text_entry;
+    }
+    /// Record that cutting was asked for, if text entry is on.
+    pub fn request_cut(&mut self) {
+        self.cut_requested |= self.text_entry;
+    }
+    /// Listens for text pasted into `canvas` (on the web, where the
+    /// clipboard can't be read directly), e.g. the canvas from
+    /// winit's `WindowExtWebSys::canvas`.  Pasted text is typed from
+    /// the next call to [`Input::process_input_event`] on.  winit
+    /// stops the browser from handling key presses by default, which
+    /// also stops it from pasting, so turn that off with
+    /// `WindowExtWebSys::set_prevent_default(false)`.
+    #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+    pub fn listen_for_paste(&mut self, canvas: &web_sys::HtmlCanvasElement) {
+        use wasm_bindgen::{closure::Closure, JsCast};
+        let queue = self.web_pastes.clone();
+        let handler = Closure::wrap(Box::new(move |ev: web_sys::ClipboardEvent| {
+            let Some(text) = ev.clipboard_data().and_then(|dt| dt.get_data("text").ok()) else {
+                return;
+            };
+            ev.prevent_default();
+            queue.borrow_mut().push(text);
+        }) as Box<dyn FnMut(web_sys::ClipboardEvent)>);
+        canvas
+            .add_event_listener_with_callback("paste", handler.as_ref().unchecked_ref())
+            .unwrap();
+        // The listener lives as long as the canvas
+        handler.forget();
+    }
+    #[cfg(all(target_arch = "wasm32", feature = "winit"))]
+    fn take_web_pastes(&mut self) {
+        let pastes = std::mem::take(&mut *self.web_pastes.borrow_mut());
+        for text in pastes {
+            self.type_text(&text);
+        }
+    }
+    /// Record that touch `id` was cancelled.  Cancelled touches just
+    /// disappear, so they never count as taps.
+    pub fn touch_cancelled(&mut self, id: u64) {
+        if let Some(idx) = self.now_touches.iter().position(|t| t.id == id) {
+            self.now_touches.swap_remove(idx);
+        }
+    }
+    #[cfg(feature = "winit")]
     fn handle_key_event(&mut self, ke: &winit::event::KeyEvent) {
         if let winit::event::KeyEvent {
             physical_key: winit::keyboard::PhysicalKey::Code(keycode),
@@ -125,34 +652,312 @@ impl Input {
         } = ke
         {
             match state {
-                winit::event::ElementState::Pressed => {
-                    if !self.now_keys.contains(keycode) {
-                        self.now_keys.push(*keycode);
-                    }
-                }
-                winit::event::ElementState::Released => {
-                    if let Some(idx) = self.now_keys.iter().position(|k| k == keycode) {
-                        self.now_keys.swap_remove(idx);
-                    }
+                ElementState::Pressed => self.press_key((*keycode).into()),
+                ElementState::Released => self.release_key((*keycode).into()),
+            }
+        }
+        if !self.text_entry || ke.state != ElementState::Pressed {
+            return;
+        }
+        // AltGr shows up as Ctrl+Alt on some platforms, and types text
+        let shortcut = (self.modifiers.control_key() || self.modifiers.super_key())
+            && !self.modifiers.alt_key();
+        if shortcut {
+            // Shortcuts go by the character on the key, so they follow
+            // the keyboard layout
+            if let winit::keyboard::Key::Character(ch) = &ke.logical_key {
+                match ch.to_lowercase().as_str() {
+                    "v" => self.paste(),
+                    "c" => self.request_copy(),
+                    "x" => self.request_cut(),
+                    _ => (),
                 }
             }
+        } else if let Some(text) = &ke.text {
+            self.type_text(text);
         }
     }
+    #[cfg(feature = "winit")]
     fn handle_mouse_button(&mut self, state: ElementState, button: MouseButton) {
         match state {
-            ElementState::Pressed => {
-                if !self.now_mouse.contains(&button) {
-                    self.now_mouse.push(button);
+            ElementState::Pressed => self.press_mouse(button),
+            ElementState::Released => self.release_mouse(button),
+        }
+    }
+    #[cfg(feature = "winit")]
+    fn handle_touch(&mut self, touch: &winit::event::Touch) {
+        match touch.phase {
+            TouchPhase::Started => self.touch_started(touch.id, touch.location.into()),
+            TouchPhase::Moved => self.touch_moved(touch.id, touch.location.into()),
+            TouchPhase::Ended => self.touch_ended(touch.id, touch.location.into()),
+            TouchPhase::Cancelled => self.touch_cancelled(touch.id),
+        }
+    }
+}
+
+/// How an axis's magnitude, after its dead zone is removed, maps onto
+/// its output magnitude.  Both range from 0 to 1.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResponseCurve {
+    /// Output is proportional to input.
+    #[default]
+    Linear,
+    /// Output is the square of the input, giving finer control near the center.
+    Squared,
+    /// Output is the input raised to this power.
+    Power(f32),
+    /// Output is linearly interpolated between these `[input, output]`
+    /// points, which should be sorted by input with no input repeated;
+    /// [`ResponseCurve::points`] makes sure of both.
+    Points(Vec<[f32; 2]>),
+}
+
+impl ResponseCurve {
+    /// A [`ResponseCurve::Points`] curve through `points`, sorted by
+    /// input.  Points with the same input as an earlier point are
+    /// dropped, and so are points with a NaN input.
+    pub fn points(points: impl IntoIterator<Item = [f32; 2]>) -> Self {
+        let mut points: Vec<_> = points.into_iter().filter(|p| !p[0].is_nan()).collect();
+        // A stable sort keeps the first of each repeated input first
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        points.dedup_by(|later, earlier| later[0] == earlier[0]);
+        Self::Points(points)
+    }
+    /// Map a magnitude between 0 and 1 through the curve.
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::Squared => t * t,
+            Self::Power(p) => t.powf(*p),
+            Self::Points(points) => {
+                let Some(last) = points.iter().position(|p| p[0] >= t) else {
+                    return points.last().map_or(t, |p| p[1]);
+                };
+                if last == 0 {
+                    return points[0][1];
                 }
-            }
-            ElementState::Released => {
-                if let Some(idx) = self.now_mouse.iter().position(|m| *m == button) {
-                    self.now_mouse.swap_remove(idx);
+                let ([x0, y0], [x1, y1]) = (points[last - 1], points[last]);
+                // Unsorted or repeated points can leave a segment with
+                // no width, which would divide by zero
+                if x1 <= x0 {
+                    return y1;
                 }
+                y0 + (y1 - y0) * (t - x0) / (x1 - x0)
             }
         }
     }
-    fn handle_mouse_move(&mut self, position: MousePos<f64>) {
-        self.now_mouse_pos = position;
+}
+
+/// Calibration and response settings for a single analog axis.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisSettings {
+    /// The raw value the axis reports at rest
+    pub center: f32,
+    /// The smallest raw value the axis reports
+    pub min: f32,
+    /// The largest raw value the axis reports
+    pub max: f32,
+    /// Calibrated magnitudes below this are treated as zero
+    pub dead_zone: f32,
+    /// Calibrated magnitudes above this are treated as one
+    pub saturation: f32,
+    /// The response curve applied after the dead zone
+    pub curve: ResponseCurve,
+    /// Whether to flip the axis's direction
+    pub invert: bool,
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        Self {
+            center: 0.0,
+            min: -1.0,
+            max: 1.0,
+            dead_zone: 0.15,
+            saturation: 1.0,
+            curve: ResponseCurve::Linear,
+            invert: false,
+        }
+    }
+}
+
+impl AxisSettings {
+    /// Widen the calibrated range to include this raw value, e.g.
+    /// while the player rotates a stick all the way around.
+    pub fn calibrate(&mut self, raw: f32) {
+        self.min = self.min.min(raw);
+        self.max = self.max.max(raw);
+    }
+    /// Map a raw value onto -1..=1 using the calibrated center and
+    /// range, without applying the dead zone or curve.
+    pub fn normalize(&self, raw: f32) -> f32 {
+        let offset = raw - self.center;
+        let extent = if offset >= 0.0 {
+            self.max - self.center
+        } else {
+            self.center - self.min
+        };
+        let value = if extent > 0.0 {
+            (offset / extent).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        if self.invert {
+            -value
+        } else {
+            value
+        }
+    }
+    /// Apply the dead zone, saturation, and curve to a normalized
+    /// magnitude between 0 and 1.
+    pub fn respond(&self, magnitude: f32) -> f32 {
+        if magnitude < self.dead_zone {
+            return 0.0;
+        }
+        let span = self.saturation - self.dead_zone;
+        let t = if span > 0.0 {
+            ((magnitude - self.dead_zone) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        self.curve.apply(t).clamp(0.0, 1.0)
+    }
+    /// Turn a raw axis value into a conditioned value between -1 and 1.
+    pub fn condition(&self, raw: f32) -> f32 {
+        let value = self.normalize(raw);
+        self.respond(value.abs()).copysign(value)
+    }
+}
+
+/// Per-device, per-axis [`AxisSettings`], with a fallback for
+/// devices or axes that haven't been configured.  Devices are named
+/// by whatever stable identifier the gamepad library provides
+/// (e.g. a name or UUID), so settings can be saved and loaded with
+/// the `serde` feature.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisConditioning {
+    /// Settings for axes without device-specific settings
+    pub default: AxisSettings,
+    /// Settings for each device's axes, indexed by axis number
+    pub devices: std::collections::BTreeMap<String, Vec<Option<AxisSettings>>>,
+}
+
+impl AxisConditioning {
+    /// The settings used for this device's axis.
+    pub fn settings(&self, device: &str, axis: usize) -> &AxisSettings {
+        self.devices
+            .get(device)
+            .and_then(|axes| axes.get(axis))
+            .and_then(Option::as_ref)
+            .unwrap_or(&self.default)
+    }
+    /// Mutable settings for this device's axis, copied from the
+    /// default settings if the axis hasn't been configured yet.
+    pub fn settings_mut(&mut self, device: &str, axis: usize) -> &mut AxisSettings {
+        let axes = self.devices.entry(device.to_string()).or_default();
+        if axes.len() <= axis {
+            axes.resize(axis + 1, None);
+        }
+        axes[axis].get_or_insert_with(|| self.default.clone())
+    }
+    /// Turn a raw value from this device's axis into a conditioned
+    /// value between -1 and 1.
+    pub fn condition(&self, device: &str, axis: usize, raw: f32) -> f32 {
+        self.settings(device, axis).condition(raw)
+    }
+    /// Condition a two-axis stick together, so the dead zone is a
+    /// circle rather than a cross and diagonals aren't exaggerated.
+    /// Each axis is normalized with its own calibration; the dead
+    /// zone, saturation, and curve come from the `x_axis` settings.
+    pub fn condition_stick(
+        &self,
+        device: &str,
+        (x_axis, y_axis): (usize, usize),
+        raw: [f32; 2],
+    ) -> [f32; 2] {
+        let x_settings = self.settings(device, x_axis);
+        let x = x_settings.normalize(raw[0]);
+        let y = self.settings(device, y_axis).normalize(raw[1]);
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude == 0.0 {
+            return [0.0, 0.0];
+        }
+        let scale = x_settings.respond(magnitude.min(1.0)) / magnitude;
+        [x * scale, y * scale]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_dead_zone_and_saturation() {
+        let settings = AxisSettings {
+            dead_zone: 0.2,
+            saturation: 0.8,
+            ..Default::default()
+        };
+        assert_eq!(settings.condition(0.1), 0.0);
+        assert_eq!(settings.condition(-0.19), 0.0);
+        assert!((settings.condition(0.5) - 0.5).abs() < 1e-6);
+        assert!((settings.condition(-0.5) + 0.5).abs() < 1e-6);
+        assert_eq!(settings.condition(0.9), 1.0);
+        assert_eq!(settings.condition(-2.0), -1.0);
+    }
+    #[test]
+    fn test_calibration() {
+        let mut settings = AxisSettings {
+            center: 0.1,
+            min: 0.0,
+            max: 0.0,
+            dead_zone: 0.0,
+            ..Default::default()
+        };
+        // An uncalibrated side reads as centered rather than dividing by zero
+        assert_eq!(settings.normalize(0.5), 0.0);
+        settings.calibrate(-0.9);
+        settings.calibrate(0.6);
+        assert!((settings.normalize(0.6) - 1.0).abs() < 1e-6);
+        assert!((settings.normalize(-0.4) + 0.5).abs() < 1e-6);
+        settings.invert = true;
+        assert!((settings.normalize(0.6) + 1.0).abs() < 1e-6);
+    }
+    #[test]
+    fn test_curves() {
+        assert_eq!(ResponseCurve::Squared.apply(0.5), 0.25);
+        assert_eq!(ResponseCurve::Power(3.0).apply(0.5), 0.125);
+        let curve = ResponseCurve::points([[0.0, 0.0], [0.5, 0.2], [1.0, 1.0]]);
+        assert!((curve.apply(0.25) - 0.1).abs() < 1e-6);
+        assert!((curve.apply(0.75) - 0.6).abs() < 1e-6);
+        assert_eq!(ResponseCurve::Points(vec![]).apply(0.3), 0.3);
+    }
+    #[test]
+    fn test_duplicate_curve_points() {
+        let curve = ResponseCurve::points([
+            [1.0, 1.0],
+            [0.5, 0.2],
+            [0.0, 0.0],
+            [0.5, 0.9],
+            [f32::NAN, 0.5],
+        ]);
+        assert_eq!(
+            curve,
+            ResponseCurve::Points(vec![[0.0, 0.0], [0.5, 0.2], [1.0, 1.0]])
+        );
+        // Curves built by hand with repeated or unsorted inputs still
+        // give numbers rather than NaN
+        for points in [
+            vec![[0.0, 0.0], [0.5, 0.2], [0.5, 0.8], [1.0, 1.0]],
+            vec![[0.5, 0.5], [0.5, 0.7]],
+            vec![[1.0, 1.0], [0.0, 0.0]],
+        ] {
+            let curve = ResponseCurve::Points(points);
+            for t in [0.0, 0.25, 0.5, 0.6, 1.0] {
+                assert!(!curve.apply(t).is_nan(), "{curve:?} at {t}");
+            }
+        }
     }
 }
@@ -19,7 +19,9 @@
 //! feature flag).  Finally, in use case (3), you'll use
 //! [`clock::Clock`], the extension trait in
 //! [`events::FrendererEvents`], and the [`input::Input`] struct to
-//! simplify your game loop's lifecycle.
+//! simplify your game loop's lifecycle.  Without `winit`, the same
+//! lifecycle is available to other windowing libraries through
+//! [`PlatformEvent`] and [`Renderer::handle_platform_event`].
 //!
 //! frenderer is highly modular, especially in case (1); in
 //! particular, frenderer does not need to take control of the event
@@ -41,19 +43,66 @@
 //! buffers (where available), array textures, and packing multiple
 //! meshes into a single buffer.
 //!
+//! To debug rendering problems, enable the `debug` feature: every GPU
+//! resource will be labeled with its renderer, purpose, and group
+//! index, each render pass will be wrapped in debug groups for tools
+//! like RenderDoc or Xcode, and on native targets an API trace will be
+//! recorded into the directory named by the `FRENDERER_TRACE_DIR`
+//! environment variable (if it's set).
+//!
+//! To profile frame structure, enable the `tracing` feature: frames,
+//! uploads, and render passes are wrapped in [`tracing`] spans
+//! recording e.g. instance counts and bytes uploaded, and buffer
+//! reallocations are recorded as events, for any `tracing`
+//! subscriber to collect.
+//!
+//! To fill large sprite groups on several CPU cores, enable the
+//! `parallel` feature: [`frenderer::Renderer::sprites_par_chunks_mut`]
+//! and [`frenderer::Renderer::sprites_par_fill`] then split the work
+//! across rayon's thread pool.  Without it, or on the web, they run
+//! on the calling thread.
+//!
+//! Frenderer's transforms and cameras are plain arrays, but
+//! [`convert`] gives them `From` conversions to and from ultraviolet
+//! matrices, rotors, and similarities, and to and from glam types
+//! with the `glam` feature.
+//!
+//! [`bitfont::BitFont`] only draws one sprite per character, left to
+//! right.  To localize into right-to-left or complex scripts, enable
+//! the `shaping` feature and draw text with [`shaping::TtfFont`].
+//!
 //! Frenderer works in retained mode, but the "engine-immediate"
 //! example shows how an immediate-mode render API could be built on
 //! top of it.
 
 mod gpu;
-pub use gpu::WGPU;
+pub use gpu::{DeviceReport, DeviceRequest, FrendererError, WGPU};
 pub use wgpu;
 
+// Enters a tracing span for the rest of the enclosing block when the
+// `tracing` feature is on; otherwise the fields aren't even evaluated.
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($($args)*).entered();
+    };
+}
+// Records a tracing event when the `tracing` feature is on.
+macro_rules! trace_event {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($args)*);
+    };
+}
+
 pub mod colorgeo;
 pub mod frenderer;
 pub mod meshes;
+pub mod resources;
 pub mod sprites;
+pub mod units;
 pub use frenderer::*;
+pub use units::{RenderPx, SurfacePx, WorldPos};
 
 fn range<R: std::ops::RangeBounds<usize>>(r: R, hi: usize) -> std::ops::Range<usize> {
     let low = match r.start_bound() {
@@ -69,14 +118,92 @@ fn range<R: std::ops::RangeBounds<usize>>(r: R, hi: usize) -> std::ops::Range<us
     low..high
 }
 
+// Label for a GPU resource belonging to a particular group, which
+// includes the group's name if it has one.  Otherwise group indices
+// are only included with the `debug` feature, and without it the
+// label is just `kind`, borrowed rather than formatted.
+#[cfg(feature = "debug")]
+fn group_label<'k>(
+    kind: &'k str,
+    idx: usize,
+    name: Option<&str>,
+) -> Option<std::borrow::Cow<'k, str>> {
+    Some(std::borrow::Cow::Owned(match name {
+        Some(name) => format!("{kind}[{name}]"),
+        None => format!("{kind}[{idx}]"),
+    }))
+}
+#[cfg(not(feature = "debug"))]
+fn group_label<'k>(
+    kind: &'k str,
+    _idx: usize,
+    name: Option<&str>,
+) -> Option<std::borrow::Cow<'k, str>> {
+    Some(match name {
+        Some(name) => std::borrow::Cow::Owned(format!("{kind}[{name}]")),
+        None => std::borrow::Cow::Borrowed(kind),
+    })
+}
+// An encoder for the copies relabeling a group's buffers, submitted
+// once they're all recorded
+fn relabel_encoder(gpu: &WGPU) -> wgpu::CommandEncoder {
+    gpu.device()
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frenderer:relabel_encoder"),
+        })
+}
+// Records copying `buffer` (which needs the COPY_SRC usage) into a
+// new buffer with the same size and usage but the given label, since
+// wgpu can't rename resources
+fn relabel_buffer(
+    gpu: &WGPU,
+    encoder: &mut wgpu::CommandEncoder,
+    buffer: &wgpu::Buffer,
+    label: Option<&str>,
+) -> wgpu::Buffer {
+    let relabeled = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+        label,
+        size: buffer.size(),
+        usage: buffer.usage(),
+        mapped_at_creation: false,
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &relabeled, 0, buffer.size());
+    relabeled
+}
+
 #[cfg(feature = "winit")]
 mod events;
-#[cfg(feature = "winit")]
+pub mod gamepad;
 pub mod input;
 #[cfg(feature = "winit")]
 pub use events::*;
+mod platform;
+pub use platform::*;
 
+pub mod animation;
+pub mod autotile;
 pub mod bitfont;
+pub mod clipboard;
+pub mod convert;
+#[cfg(feature = "debug")]
+pub mod export;
+pub mod gizmos;
 pub mod nineslice;
+pub mod nodes;
+pub mod particles;
+pub mod pick;
+pub mod raycast;
+pub mod reload;
+#[cfg(feature = "screenshot")]
+pub mod screenshot;
+pub mod sdf;
+#[cfg(feature = "shaping")]
+pub mod shaping;
+pub mod streaming;
+pub mod terrain;
+pub mod textcache;
+pub mod tilemap;
+pub mod ui;
 
 pub mod clock;
+pub mod rng;
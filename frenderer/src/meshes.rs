@@ -9,7 +9,18 @@
 //! of instance data to the GPU are separated like they are for
 //! sprites.  The only instance data is a 3D transform (translation,
 //! rotation, and a uniform scaling factor (so it fits neatly into 8
-//! floats).  Rotations are defined as quaternions.
+//! floats).  Rotations are defined as quaternions.  Mesh groups which
+//! need non-uniform scaling (e.g. stretched crates or walls) can opt
+//! into an extra per-instance scale vector which is multiplied by the
+//! uniform scale, and mesh groups which need per-instance materials
+//! (e.g. team colors or damage states) can opt into a per-instance
+//! [`MaterialOverride`].
+//!
+//! By default each mesh group has its own vertex and index buffers.
+//! Applications which add and remove many mesh groups over time
+//! (e.g. editors) can instead have the renderers suballocate them
+//! from large shared buffers which are compacted when they get
+//! fragmented; see [`MeshRenderer::set_pooled`].
 //!
 //! This module defines two renderers: the textured renderer
 //! [`MeshRenderer`] and the flat-colored renderer [`FlatRenderer`].
@@ -18,10 +29,18 @@
 //!
 //! 3D graphics in frenderer use a right-handed, y-up coordinate system.
 
+use crate::colorgeo::ColorGrade;
+use crate::raycast::{MeshCollider, RayHit};
+use crate::units::RenderPx;
 use bytemuck::Zeroable;
 use std::{borrow::Cow, marker::PhantomData, ops::Range};
 use wgpu::util::{self as wutil, DeviceExt};
 
+/// The format of the selection masks drawn by
+/// [`MeshRenderer::render_selection_mask`] and
+/// [`FlatRenderer::render_selection_mask`].
+pub const MASK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
 /// A vertex for meshes in the [`MeshRenderer`].
 #[repr(C)]
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, PartialEq, Debug)]
@@ -60,14 +79,59 @@ impl FlatVertex {
     }
 }
 
+/// The bind group layouts of a [`MeshRenderer`]'s or
+/// [`FlatRenderer`]'s pipelines, from e.g. [`MeshRenderer::layouts`].
+/// Custom pipelines can use them to bind the renderer's camera bind
+/// group and groups' bind groups, or to make their own bind groups
+/// which frenderer's pipelines accept, without copying the layouts'
+/// definitions.
+#[derive(Clone, Copy, Debug)]
+pub struct MeshLayouts<'a> {
+    /// Group 0: the view-projection matrix followed by the camera's
+    /// position (padded to four floats) in a uniform at binding 0
+    pub camera: &'a wgpu::BindGroupLayout,
+    /// Group 1: for textured meshes, a filterable float `D2Array`
+    /// texture at binding 0 and a filtering sampler at binding 1; for
+    /// flat meshes, the material colors uniform at binding 0; and for
+    /// both, the color grade uniform at binding 2, the
+    /// [`MeshOutline`] uniform at binding 3, and the [`MeshShading`]
+    /// uniform at binding 4
+    pub group: &'a wgpu::BindGroupLayout,
+    /// Group 2 of water groups' pipelines: the reflection texture at
+    /// binding 0, its filtering sampler at binding 1, and a uniform
+    /// with the ripples' time in seconds at binding 2
+    pub reflection: &'a wgpu::BindGroupLayout,
+}
+
 struct MeshRendererInner<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> {
+    name: &'static str,
     groups: Vec<Option<MeshGroupData>>,
     free_groups: Vec<usize>,
     bind_group_layout: wgpu::BindGroupLayout,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
     camera_bind_group: wgpu::BindGroup,
     camera_buffer: wgpu::Buffer,
     camera: Camera3D,
-    pipeline: wgpu::RenderPipeline,
+    // Indexed by which optional instance streams are in use; see `MeshGroupData::pipeline_index`
+    pipelines: [wgpu::RenderPipeline; 4],
+    // Like `pipelines`, but drawing selected instances into a selection mask
+    mask_pipelines: [wgpu::RenderPipeline; 4],
+    // Like `pipelines`, but drawing outlined groups' inverted hulls
+    outline_pipelines: [wgpu::RenderPipeline; 4],
+    // Like `pipelines`, but for water groups, which also take the
+    // reflection bind group
+    water_pipelines: [wgpu::RenderPipeline; 4],
+    reflection_bind_group_layout: wgpu::BindGroupLayout,
+    reflection_bind_group: wgpu::BindGroup,
+    // Seconds elapsed for scrolling water ripples
+    reflection_time_buffer: wgpu::Buffer,
+    // Shown by water groups while there's no reflection
+    blank_reflection: wgpu::TextureView,
+    // Whether new groups' geometry is suballocated from `pool`
+    pooled: bool,
+    pool: Option<MeshPool>,
+    // Whether new groups keep triangle hierarchies for raycasts
+    pick_triangles: bool,
     _vertex_data: PhantomData<Vtx>,
 }
 
@@ -82,16 +146,565 @@ pub struct FlatRenderer {
 struct MeshGroupData {
     instance_data: Vec<Transform3D>,
     instance_buffer: wgpu::Buffer,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
+    geometry: GroupGeometry,
+    binding: GroupBinding,
     bind_group: wgpu::BindGroup,
     meshes: Vec<MeshData>,
+    scales: Option<InstanceStream<[f32; 3]>>,
+    materials: Option<InstanceStream<MaterialOverride>>,
+    colliders: Vec<MeshCollider>,
+    color_grade: ColorGrade,
+    color_grade_buffer: wgpu::Buffer,
+    outline: Option<MeshOutline>,
+    outline_buffer: wgpu::Buffer,
+    shading: MeshShading,
+    shading_buffer: wgpu::Buffer,
+    // A name to include in the group's resource labels, see set_group_name
+    name: Option<String>,
+    // The texture textured groups draw from
+    texture: Option<crate::resources::TextureInfo>,
+    interpolation: Option<Interpolation>,
+    #[cfg(feature = "debug")]
+    cpu_geometry: CpuGeometry,
+}
+
+// A copy of a group's geometry kept for exporting, see crate::export
+#[cfg(feature = "debug")]
+struct CpuGeometry {
+    vertices: Vec<u8>,
+    indices: Vec<u32>,
+    // Flat groups' material colors
+    colors: Vec<[f32; 4]>,
+}
+
+// The instance transforms as of the previous simulation step, and
+// how far uploads blend from them to the current ones; see
+// MeshRenderer::set_interpolated
+struct Interpolation {
+    previous: Vec<Transform3D>,
+    alpha: f32,
+}
+
+impl MeshGroupData {
+    fn pipeline_index(&self) -> usize {
+        (self.scales.is_some() as usize) | ((self.materials.is_some() as usize) << 1)
+    }
+    // The instance transforms in `range` as they're uploaded,
+    // interpolated if the group is, with a zero scale for hidden
+    // instances
+    fn uploaded_instances(&self, range: Range<usize>) -> Cow<'_, [Transform3D]> {
+        let data = &self.instance_data[range.clone()];
+        let data = match self.interpolation.as_ref() {
+            Some(interp) => Cow::Owned(
+                interp.previous[range.clone()]
+                    .iter()
+                    .zip(data)
+                    .map(|(prev, current)| prev.lerp(current, interp.alpha))
+                    .collect(),
+            ),
+            None => Cow::Borrowed(data),
+        };
+        if self.meshes.iter().all(|mesh| mesh.hidden.is_empty()) {
+            return data;
+        }
+        let mut data = data.into_owned();
+        for mesh in self.meshes.iter() {
+            // Shrinking a mesh may leave hidden indices past its end
+            for &idx in mesh
+                .hidden
+                .iter()
+                .filter(|&&idx| idx < mesh.instances.len() as u32)
+            {
+                let instance = mesh.instances.start as usize + idx as usize;
+                if range.contains(&instance) {
+                    data[instance - range.start] = Transform3D::ZERO;
+                }
+            }
+        }
+        Cow::Owned(data)
+    }
+}
+
+// Which of a mesh renderer's pipeline sets MeshRendererInner::new is building
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PipelineKind {
+    Color,
+    Mask,
+    Outline,
+    Water,
+}
+
+// There's one of these per group, so the size difference doesn't matter
+#[allow(clippy::large_enum_variant)]
+enum GroupGeometry {
+    // The group's own vertex and index buffers
+    Dedicated {
+        vertex_buffer: wgpu::Buffer,
+        index_buffer: wgpu::Buffer,
+    },
+    // Byte ranges of the renderer's pooled vertex and index buffers
+    Pooled {
+        vertices: Range<u64>,
+        indices: Range<u64>,
+    },
+}
+
+impl GroupGeometry {
+    fn pooled_range_mut(&mut self, indices: bool) -> Option<&mut Range<u64>> {
+        match self {
+            Self::Dedicated { .. } => None,
+            Self::Pooled {
+                vertices,
+                indices: idx,
+            } => Some(if indices { idx } else { vertices }),
+        }
+    }
+}
+
+// Shared vertex and index buffers which pooled mesh groups suballocate from
+struct MeshPool {
+    vertices: BufferPool,
+    indices: BufferPool,
+    // Whether groups were added to or removed from the pool since the last maintain_pool
+    changed: bool,
+}
+
+struct BufferPool {
+    label: String,
+    usage: wgpu::BufferUsages,
+    buffer: wgpu::Buffer,
+}
+
+impl BufferPool {
+    const MIN_SIZE: u64 = 64 * 1024;
+    fn new(gpu: &crate::WGPU, label: String, usage: wgpu::BufferUsages) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+        let buffer = Self::create_buffer(gpu, &label, usage, Self::MIN_SIZE);
+        Self {
+            label,
+            usage,
+            buffer,
+        }
+    }
+    fn create_buffer(
+        gpu: &crate::WGPU,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        size: u64,
+    ) -> wgpu::Buffer {
+        gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+    // Finds room for `size` bytes (a multiple of 4) among the `live`
+    // allocations, first in the gaps between them, then by
+    // compacting them into a bigger buffer.
+    fn alloc(
+        &mut self,
+        gpu: &crate::WGPU,
+        mut live: Vec<&mut Range<u64>>,
+        size: u64,
+    ) -> Range<u64> {
+        live.sort_by_key(|r| r.start);
+        let mut cursor = 0;
+        for r in live.iter() {
+            if r.start - cursor >= size {
+                return cursor..cursor + size;
+            }
+            cursor = r.end;
+        }
+        if self.buffer.size() - cursor >= size {
+            return cursor..cursor + size;
+        }
+        let used: u64 = live.iter().map(|r| r.end - r.start).sum();
+        let size_needed = (self.buffer.size() * 2).max(used + size);
+        self.compact(gpu, live, size_needed);
+        used..used + size
+    }
+    // Whether the gaps between `live` allocations or the space after
+    // them are big enough that compacting would be worthwhile.
+    fn fragmented(&self, live: &[&mut Range<u64>]) -> bool {
+        let used: u64 = live.iter().map(|r| r.end - r.start).sum();
+        let high_water = live.iter().map(|r| r.end).max().unwrap_or(0);
+        high_water - used > high_water / 2
+            || (self.buffer.size() > Self::MIN_SIZE && self.buffer.size() / 4 > used)
+    }
+    // Copies the `live` allocations to the front of a new buffer of
+    // `size` bytes, updating their ranges.
+    fn compact(&mut self, gpu: &crate::WGPU, mut live: Vec<&mut Range<u64>>, size: u64) {
+        trace_event!(
+            pool = self.label.as_str(),
+            bytes = size,
+            "meshes: compacted buffer pool"
+        );
+        let buffer = Self::create_buffer(gpu, &self.label, self.usage, size.max(Self::MIN_SIZE));
+        let mut encoder = gpu
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some(&self.label),
+            });
+        live.sort_by_key(|r| r.start);
+        let mut cursor = 0;
+        for r in live {
+            let len = r.end - r.start;
+            if len > 0 {
+                encoder.copy_buffer_to_buffer(&self.buffer, r.start, &buffer, cursor, len);
+            }
+            *r = cursor..cursor + len;
+            cursor += len;
+        }
+        gpu.queue().submit(Some(encoder.finish()));
+        self.buffer = buffer;
+    }
+}
+
+// The pooled vertex (or index, if `indices`) ranges of every group
+fn pooled_ranges(groups: &mut [Option<MeshGroupData>], indices: bool) -> Vec<&mut Range<u64>> {
+    groups
+        .iter_mut()
+        .filter_map(|g| g.as_mut()?.geometry.pooled_range_mut(indices))
+        .collect()
+}
+
+// The label for one of a renderer's group resources, see crate::group_label
+fn group_label(renderer: &str, kind: &str, idx: usize, name: Option<&str>) -> Option<String> {
+    crate::group_label(&format!("{renderer}:{kind}"), idx, name).map(std::borrow::Cow::into_owned)
+}
+
+// A group's uniform buffer, e.g. its color grade
+fn uniform_buffer(gpu: &crate::WGPU, label: Option<&str>, contents: &[u8]) -> wgpu::Buffer {
+    gpu.device()
+        .create_buffer_init(&wutil::BufferInitDescriptor {
+            label,
+            contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+}
+
+// A group's bind group: its texture and sampler or material colors,
+// then its color grade, outline, and shading uniforms
+fn create_group_bind_group(
+    gpu: &crate::WGPU,
+    layout: &wgpu::BindGroupLayout,
+    label: Option<&str>,
+    binding: &GroupBinding,
+    [color_grade, outline, shading]: [&wgpu::Buffer; 3],
+) -> wgpu::BindGroup {
+    let mut entries = match binding {
+        GroupBinding::Texture(view, sampler) => vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+        GroupBinding::Materials(colors) => vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: colors.as_entire_binding(),
+        }],
+    };
+    entries.extend([
+        wgpu::BindGroupEntry {
+            binding: 2,
+            resource: color_grade.as_entire_binding(),
+        },
+        wgpu::BindGroupEntry {
+            binding: 3,
+            resource: outline.as_entire_binding(),
+        },
+        wgpu::BindGroupEntry {
+            binding: 4,
+            resource: shading.as_entire_binding(),
+        },
+    ]);
+    gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+        label,
+        layout,
+        entries: &entries,
+    })
+}
+
+// What a group's bind group binds besides its uniforms, kept to
+// remake it when renaming
+enum GroupBinding {
+    // A textured group's texture array and sampler
+    Texture(wgpu::TextureView, wgpu::Sampler),
+    // A flat group's material colors
+    Materials(wgpu::Buffer),
+}
+
+// Optional per-instance data parallel to a group's instance transforms
+struct InstanceStream<T: bytemuck::Pod> {
+    label: Option<String>,
+    data: Vec<T>,
+    buffer: wgpu::Buffer,
+}
+
+impl<T: bytemuck::Pod> InstanceStream<T> {
+    fn new(gpu: &crate::WGPU, label: Option<String>, data: Vec<T>) -> Self {
+        let buffer = Self::create_buffer(gpu, label.as_deref(), &data);
+        Self {
+            label,
+            data,
+            buffer,
+        }
+    }
+    fn create_buffer(gpu: &crate::WGPU, label: Option<&str>, data: &[T]) -> wgpu::Buffer {
+        gpu.device()
+            .create_buffer_init(&wutil::BufferInitDescriptor {
+                label,
+                contents: bytemuck::cast_slice(data),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            })
+    }
+    fn realloc(&mut self, gpu: &crate::WGPU) {
+        self.buffer = Self::create_buffer(gpu, self.label.as_deref(), &self.data);
+    }
+    // The data is all on the CPU, so a new buffer is filled from it
+    fn relabel(&mut self, gpu: &crate::WGPU, label: Option<String>) {
+        self.label = label;
+        self.realloc(gpu);
+    }
+    fn upload(&self, gpu: &crate::WGPU, range: Range<usize>) {
+        gpu.queue().write_buffer(
+            &self.buffer,
+            (range.start * std::mem::size_of::<T>()) as u64,
+            bytemuck::cast_slice(&self.data[range]),
+        );
+    }
+}
+
+/// Per-instance material settings for mesh groups which use them.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MaterialOverride {
+    /// Added to the material index of every vertex of the instance:
+    /// the texture array layer for textured meshes, or the index
+    /// into the material colors for flat meshes.
+    pub material_offset: u32,
+    /// RGBA bytes where alpha indicates intensity of color modulation
+    pub colormod: [u8; 4],
+}
+
+/// An inverted-hull outline drawn around every instance of a mesh
+/// group for a cel-shaded look; see [`MeshRenderer::set_outline`].
+/// The outline is a second draw of the group's meshes with their
+/// front faces culled, pushed outward so its back faces show around
+/// the silhouette.
+///
+/// Mesh vertices don't have normals yet, so vertices are pushed
+/// directly away from their mesh's origin.  This works best for
+/// roughly convex meshes centered on their origin; concave parts and
+/// flat faces far from the origin get uneven outlines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshOutline {
+    /// The outline's color.  It skips the group's color grade.
+    pub color: [f32; 4],
+    /// How far vertices are pushed outward, in world units
+    pub thickness: f32,
+}
+
+impl Default for MeshOutline {
+    fn default() -> Self {
+        Self {
+            color: [0.0, 0.0, 0.0, 1.0],
+            thickness: 0.02,
+        }
+    }
+}
+
+impl MeshOutline {
+    // The outline uniform's contents: the color, then the thickness
+    // padded out to a vec4
+    fn uniform(outline: Option<Self>) -> [f32; 8] {
+        let outline = outline.unwrap_or(Self {
+            color: [0.0; 4],
+            thickness: 0.0,
+        });
+        let [r, g, b, a] = outline.color;
+        [r, g, b, a, outline.thickness, 0.0, 0.0, 0.0]
+    }
+}
+
+/// How a mesh group's colors are lit; see [`MeshRenderer::set_shading`].
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum MeshShading {
+    /// Colors are drawn as they are, with no lighting
+    #[default]
+    Unlit,
+    /// Cel/toon shading from a single directional light
+    Toon(ToonShading),
+    /// Water showing the scene mirrored about a plane; see
+    /// [`crate::Renderer::reflection_set`]
+    Water(Water),
+}
+
+/// Settings for [`MeshShading::Water`]: the reflection is shifted
+/// around by scrolling ripples and tinted.  Like the rest of a
+/// group's colors, water goes through its color grade.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Water {
+    /// The water's own color; its alpha is how much it covers the reflection
+    pub tint: [f32; 4],
+    /// How far ripples shift the reflection, as a fraction of the screen
+    pub distortion: f32,
+    /// How far apart ripples are, in world units
+    pub ripple_size: f32,
+    /// How fast ripples move along the world x and z axes, in world
+    /// units per second
+    pub scroll: [f32; 2],
+}
+
+impl Default for Water {
+    fn default() -> Self {
+        Self {
+            tint: [0.1, 0.3, 0.4, 0.3],
+            distortion: 0.01,
+            ripple_size: 0.5,
+            scroll: [0.3, 0.1],
+        }
+    }
+}
+
+/// A plane the scene is mirrored about for [`MeshShading::Water`]:
+/// the points `p` where `dot(p, normal) == distance`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReflectionPlane {
+    /// The plane's normal, of length 1, pointing to the side the
+    /// camera looks from
+    pub normal: [f32; 3],
+    /// How far the plane is from the origin along its normal
+    pub distance: f32,
+}
+
+impl ReflectionPlane {
+    /// A horizontal plane at the given height, e.g. a water surface.
+    pub fn horizontal(height: f32) -> Self {
+        Self {
+            normal: [0.0, 1.0, 0.0],
+            distance: height,
+        }
+    }
+}
+
+/// Toon shading settings: diffuse lighting quantized into a few flat
+/// bands, plus a hard-edged rim light around silhouettes.
+///
+/// Mesh vertices don't have normals yet, so each triangle is lit by
+/// its face normal, for a faceted low-poly look.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToonShading {
+    /// The direction the light travels, in world space
+    pub light_dir: [f32; 3],
+    /// How many brightness levels the lighting snaps to, at least 1
+    pub bands: u32,
+    /// The brightness of the darkest band, from 0.0 (black) to 1.0
+    pub ambient: f32,
+    /// The rim light's color; its alpha is the rim light's strength,
+    /// with 0.0 turning it off
+    pub rim_color: [f32; 4],
+    /// How far the rim light reaches in from silhouettes, from 0.0
+    /// (not at all) to 1.0 (across the whole surface)
+    pub rim_width: f32,
+}
+
+impl Default for ToonShading {
+    fn default() -> Self {
+        Self {
+            light_dir: [-0.5, -1.0, -0.3],
+            bands: 3,
+            ambient: 0.4,
+            rim_color: [1.0, 1.0, 1.0, 0.0],
+            rim_width: 0.3,
+        }
+    }
+}
+
+impl MeshShading {
+    // The shading uniform's contents: the light direction and shading
+    // mode, then the band count, ambient level, and rim width (or the
+    // water's distortion, ripple size, and scrolling), then the rim
+    // color (or water tint)
+    fn uniform(&self) -> [f32; 12] {
+        match self {
+            Self::Unlit => [0.0; 12],
+            Self::Water(water) => {
+                let [sx, sz] = water.scroll;
+                let [r, g, b, a] = water.tint;
+                [
+                    0.0,
+                    0.0,
+                    0.0,
+                    2.0,
+                    water.distortion,
+                    water.ripple_size,
+                    sx,
+                    sz,
+                    r,
+                    g,
+                    b,
+                    a,
+                ]
+            }
+            Self::Toon(toon) => {
+                let [x, y, z] = toon.light_dir;
+                let [r, g, b, a] = toon.rim_color;
+                [
+                    x,
+                    y,
+                    z,
+                    1.0,
+                    toon.bands.max(1) as f32,
+                    toon.ambient,
+                    toon.rim_width,
+                    0.0,
+                    r,
+                    g,
+                    b,
+                    a,
+                ]
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 struct MeshData {
     instances: Range<u32>,
     submeshes: Vec<SubmeshData>,
+    // Indices (relative to `instances.start`) of instances freed by remove_instance
+    free_instances: Vec<u32>,
+    // Indices (relative to `instances.start`) of instances hidden by set_instance_visible
+    hidden: Vec<u32>,
+    // Indices (relative to `instances.start`) of instances selected by set_instance_selected
+    selected: Vec<u32>,
+}
+
+/// A stable handle to one instance of a mesh, returned by
+/// [`MeshRenderer::add_instance`] or [`FlatRenderer::add_instance`].
+/// The instance keeps its index until it's removed, even as other
+/// instances come and go.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct InstanceHandle {
+    mesh: usize,
+    index: usize,
+}
+
+impl InstanceHandle {
+    /// The mesh number of the instance within its mesh group.
+    pub fn mesh(&self) -> usize {
+        self.mesh
+    }
+    /// The index of the instance in e.g. [`MeshRenderer::get_meshes`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
 }
 /// The range of indices and base vertex for a single submesh.
 #[derive(Debug)]
@@ -119,6 +732,30 @@ impl Transform3D {
         scale: 0.0,
         rotation: [0.0; 4],
     };
+    /// Linearly interpolates between this transform (at `t=0.0`) and
+    /// `other` (at `t=1.0`).  Rotations take the shortest way around.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        // q and -q are the same rotation; blend towards the nearer one
+        let dot: f32 = self
+            .rotation
+            .iter()
+            .zip(other.rotation)
+            .map(|(a, b)| a * b)
+            .sum();
+        let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+        let mut rotation: [f32; 4] =
+            std::array::from_fn(|i| lerp(self.rotation[i], other.rotation[i] * sign));
+        let len = rotation.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if len > 0.0 {
+            rotation.iter_mut().for_each(|c| *c /= len);
+        }
+        Self {
+            translation: std::array::from_fn(|i| lerp(self.translation[i], other.translation[i])),
+            scale: lerp(self.scale, other.scale),
+            rotation,
+        }
+    }
 }
 
 /// A 3D perspective camera positioned at some point and rotated in some orientation (a quaternion).
@@ -133,6 +770,91 @@ pub struct Camera3D {
     pub fov: f32,
 }
 
+impl Camera3D {
+    /// The camera seeing the scene mirrored about `plane`, left and
+    /// right swapped: what it sees at `(x, y)` on the screen shows
+    /// in the mirror at `(-x, y)`.
+    pub fn mirrored(&self, plane: ReflectionPlane) -> Self {
+        let n = ultraviolet::Vec3::from(plane.normal).normalized();
+        let reflect = |v: ultraviolet::Vec3| v - n * (2.0 * v.dot(n));
+        let position = ultraviolet::Vec3::from(self.translation);
+        let rot = ultraviolet::Rotor3::from_quaternion_array(self.rotation).into_matrix();
+        // Reflecting every axis flips handedness, so flip x back
+        let rot = ultraviolet::Mat3::new(
+            -reflect(rot.cols[0]),
+            reflect(rot.cols[1]),
+            reflect(rot.cols[2]),
+        );
+        Self {
+            translation: (position - n * (2.0 * (position.dot(n) - plane.distance))).into(),
+            rotation: rot.into_rotor3().normalized().into_quaternion_array(),
+            ..*self
+        }
+    }
+    /// The ray from the camera through a position on a surface of
+    /// the given size (in physical pixels with the origin at the top
+    /// left), e.g. for picking objects under the mouse.
+    pub fn screen_ray(&self, screen: [f32; 2], surface_size: [f32; 2]) -> Ray {
+        let ndc_x = screen[0] / surface_size[0] * 2.0 - 1.0;
+        let ndc_y = 1.0 - screen[1] / surface_size[1] * 2.0;
+        let half_h = (self.fov / 2.0).tan();
+        let dir = ultraviolet::Vec3::new(ndc_x * half_h * self.aspect, ndc_y * half_h, -1.0);
+        let rot = ultraviolet::Rotor3::from_quaternion_array(self.rotation);
+        Ray {
+            origin: self.translation,
+            dir: (rot * dir).normalized().into(),
+        }
+    }
+    /// Where a world space position appears on a surface of the
+    /// given size (in physical pixels with the origin at the top
+    /// left), or `None` if it's behind the camera.
+    pub fn world_to_screen(&self, world: [f32; 3], surface_size: [f32; 2]) -> Option<[f32; 2]> {
+        let rot = ultraviolet::Rotor3::from_quaternion_array(self.rotation).reversed();
+        let view =
+            rot * (ultraviolet::Vec3::from(world) - ultraviolet::Vec3::from(self.translation));
+        if view.z >= 0.0 {
+            return None;
+        }
+        let half_h = (self.fov / 2.0).tan();
+        let ndc_x = view.x / (-view.z * half_h * self.aspect);
+        let ndc_y = view.y / (-view.z * half_h);
+        Some([
+            (ndc_x + 1.0) / 2.0 * surface_size[0],
+            (1.0 - ndc_y) / 2.0 * surface_size[1],
+        ])
+    }
+    /// The ray from the camera through a position in a rendered image
+    /// of the given size (see [`crate::Renderer::render_size`]).
+    pub fn render_ray(&self, px: RenderPx, render_size: [f32; 2]) -> Ray {
+        self.screen_ray(px.0, render_size)
+    }
+    /// Where a world space position appears in a rendered image of
+    /// the given size, or `None` if it's behind the camera.
+    pub fn world_to_render(&self, world: [f32; 3], render_size: [f32; 2]) -> Option<RenderPx> {
+        self.world_to_screen(world, render_size).map(RenderPx)
+    }
+}
+
+/// A ray in world space, e.g. from [`Camera3D::screen_ray`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ray {
+    /// Where the ray starts
+    pub origin: [f32; 3],
+    /// The ray's direction, of length 1
+    pub dir: [f32; 3],
+}
+
+impl Ray {
+    /// The point `t` units along the ray.
+    pub fn at(&self, t: f32) -> [f32; 3] {
+        [
+            self.origin[0] + self.dir[0] * t,
+            self.origin[1] + self.dir[1] * t,
+            self.origin[2] + self.dir[2] * t,
+        ]
+    }
+}
+
 impl MeshRenderer {
     /// Creates a new `MeshRenderer` meant to draw into the given color target state with the given depth texture format..
     pub fn new(
@@ -143,7 +865,7 @@ impl MeshRenderer {
         let bind_group_layout =
             gpu.device()
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
+                    label: Some("meshes:texture_bgl"),
                     // It needs the first entry for the texture and the second for the sampler.
                     // This is like defining a type signature.
                     entries: &[
@@ -174,6 +896,39 @@ impl MeshRenderer {
                             // No count
                             count: None,
                         },
+                        // The color grade binding
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // The outline binding
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // The shading binding
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 4,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
                     ],
                 });
         let vertex_layout = wgpu::VertexBufferLayout {
@@ -196,9 +951,24 @@ impl MeshRenderer {
         };
         let data = MeshRendererInner::new(
             gpu,
+            "meshes",
             wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("static_meshes.wgsl"))),
-            "vs_main",
+            [
+                "vs_main",
+                "vs_nonuniform_main",
+                "vs_material_main",
+                "vs_nonuniform_material_main",
+            ],
+            // Material overrides don't affect outlines, so those
+            // pipelines ignore the material stream
+            [
+                "vs_outline_main",
+                "vs_outline_nonuniform_main",
+                "vs_outline_main",
+                "vs_outline_nonuniform_main",
+            ],
             "fs_main",
+            "fs_water",
             bind_group_layout,
             vertex_layout,
             color_target,
@@ -211,6 +981,10 @@ impl MeshRenderer {
     pub fn set_camera(&mut self, gpu: &crate::WGPU, camera: Camera3D) {
         self.data.set_camera(gpu, camera)
     }
+    /// Returns the camera used for all mesh groups.
+    pub fn camera(&self) -> Camera3D {
+        self.data.camera
+    }
     /// Add a mesh group with the given array texture.  All meshes in
     /// the group pull from the same vertex buffer, and each submesh
     /// is defined in terms of a range of indices within that buffer.
@@ -241,27 +1015,20 @@ impl MeshRenderer {
             },
             ..Default::default()
         });
-        let sampler_mesh = gpu
-            .device()
-            .create_sampler(&wgpu::SamplerDescriptor::default());
-        let bind_group = gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &self.data.bind_group_layout,
-            entries: &[
-                // One for the texture, one for the sampler
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view_mesh),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler_mesh),
-                },
-            ],
+        let sampler_mesh = gpu.device().create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("meshes:sampler"),
+            ..Default::default()
         });
 
-        self.data
-            .add_mesh_group(gpu, bind_group, vertices, indices, mesh_info)
+        self.data.add_mesh_group(
+            gpu,
+            GroupBinding::Texture(view_mesh, sampler_mesh),
+            Some(crate::resources::TextureInfo::new(texture, None)),
+            vertices,
+            indices,
+            mesh_info,
+            |vtx| vtx.position,
+        )
     }
     /// Change the number of instances of the given mesh of the given mesh group.
     pub fn resize_group_mesh(
@@ -273,6 +1040,153 @@ impl MeshRenderer {
     ) -> usize {
         self.data.resize_group_mesh(gpu, which, mesh_idx, len)
     }
+    #[cfg(feature = "debug")]
+    pub(crate) fn write_gltf(&self, writer: &mut crate::export::GltfWriter) {
+        self.data
+            .write_gltf(writer, |vtx, _| crate::export::ExportVertex {
+                position: vtx.position,
+                uv: Some([vtx.uv_which[0], vtx.uv_which[1]]),
+                color: None,
+            });
+    }
+    /// Adds an instance of the given mesh with the given transform,
+    /// reusing the slot of a removed instance if there is one, and
+    /// uploads it.  When there are no free slots, the mesh's
+    /// instance count is doubled and the new instances are hidden
+    /// until they're added.
+    pub fn add_instance(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        mesh_number: usize,
+        trf: Transform3D,
+    ) -> InstanceHandle {
+        self.data.add_instance(gpu, which, mesh_number, trf)
+    }
+    /// Hides the instance with the given handle and frees its slot
+    /// for a later [`Self::add_instance`].  The handle must not be used again.
+    pub fn remove_instance(&mut self, gpu: &crate::WGPU, which: MeshGroup, handle: InstanceHandle) {
+        self.data.remove_instance(gpu, which, handle)
+    }
+    /// Makes the given mesh group smooth out fixed simulation steps:
+    /// it remembers each instance's transform as of the previous step
+    /// (see [`Self::step_interpolation`]) and uploads transforms
+    /// blended between the previous and current steps by the alpha
+    /// given to [`Self::interpolate`].  Turning this off goes back to
+    /// uploading the current transforms as they are.
+    pub fn set_interpolated(&mut self, which: MeshGroup, interpolated: bool) {
+        self.data.set_interpolated(which, interpolated)
+    }
+    /// Returns whether the given mesh group is interpolated, see
+    /// [`Self::set_interpolated`].
+    pub fn interpolated(&self, which: MeshGroup) -> bool {
+        self.data.interpolated(which)
+    }
+    /// Remembers every interpolated group's current instance
+    /// transforms as the previous ones; call this at the start of
+    /// each simulation step.
+    pub fn step_interpolation(&mut self) {
+        self.data.step_interpolation()
+    }
+    /// Moves instance `index` of the given mesh straight to its
+    /// current transform without interpolating from its old one,
+    /// e.g. when an object teleports.  Added instances start out
+    /// snapped.
+    pub fn snap_instance(&mut self, which: MeshGroup, mesh_number: usize, index: usize) {
+        self.data.snap_instance(which, mesh_number, index)
+    }
+    /// Uploads every interpolated group's instances blended between
+    /// the previous and current simulation steps by `alpha` (between
+    /// 0 and 1), e.g. [`crate::clock::Clock::alpha`].  Call this once
+    /// per rendered frame.
+    pub fn interpolate(&mut self, gpu: &crate::WGPU, alpha: f32) {
+        self.data.interpolate(gpu, alpha)
+    }
+    /// Shows or hides instance `index` of the given mesh and uploads
+    /// it, without moving any instances or changing the count.  A
+    /// hidden instance keeps its transform (which can still be
+    /// changed) but isn't drawn or hit by raycasts.  Adding an
+    /// instance into a removed instance's slot makes it visible.
+    pub fn set_instance_visible(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        mesh_number: usize,
+        index: usize,
+        visible: bool,
+    ) {
+        self.data
+            .set_instance_visible(gpu, which, mesh_number, index, visible)
+    }
+    /// Returns whether instance `index` of the given mesh is visible,
+    /// see [`Self::set_instance_visible`].
+    pub fn instance_visible(&self, which: MeshGroup, mesh_number: usize, index: usize) -> bool {
+        self.data.instance_visible(which, mesh_number, index)
+    }
+    /// Adds instance `index` of the given mesh to the selection drawn
+    /// by [`Self::render_selection_mask`], or removes it.  Removing
+    /// an instance or adding one into its slot deselects it.
+    pub fn set_instance_selected(
+        &mut self,
+        which: MeshGroup,
+        mesh_number: usize,
+        index: usize,
+        selected: bool,
+    ) {
+        self.data
+            .set_instance_selected(which, mesh_number, index, selected)
+    }
+    /// Returns whether instance `index` of the given mesh is
+    /// selected, see [`Self::set_instance_selected`].
+    pub fn instance_selected(&self, which: MeshGroup, mesh_number: usize, index: usize) -> bool {
+        self.data.instance_selected(which, mesh_number, index)
+    }
+    /// Deselects every instance of every mesh group.
+    pub fn clear_selection(&mut self) {
+        self.data.clear_selection()
+    }
+    /// Returns whether any instance is selected.
+    pub fn has_selection(&self) -> bool {
+        self.data.has_selection()
+    }
+    /// Names a group, or removes its name.  The name is included in
+    /// the labels of the group's GPU buffers (other than pooled
+    /// geometry) and bind group, which are remade to carry it, and
+    /// marks its draws with the `debug` feature, so the group is easy
+    /// to find in graphics debuggers and wgpu's errors.
+    pub fn set_group_name(&mut self, gpu: &crate::WGPU, which: MeshGroup, name: Option<&str>) {
+        self.data.set_group_name(gpu, which, name)
+    }
+    /// Returns the given group's name, if it has one.
+    pub fn group_name(&self, which: MeshGroup) -> Option<&str> {
+        self.data.group_name(which)
+    }
+    /// Describes the given mesh group: its texture, how many meshes
+    /// and instances it has, and the size of its geometry.
+    /// Panics if the given mesh group is not populated.
+    pub fn group_info(&self, which: MeshGroup) -> MeshGroupInfo {
+        self.data.group_info(which)
+    }
+    /// Returns the bind group layouts this renderer's pipelines use.
+    pub fn layouts(&self) -> MeshLayouts<'_> {
+        self.data.layouts()
+    }
+    /// Returns the camera bind group, for group 0 of [`MeshLayouts`].
+    pub fn camera_bind_group(&self) -> &wgpu::BindGroup {
+        &self.data.camera_bind_group
+    }
+    /// Returns the given group's bind group, for group 1 of [`MeshLayouts`].
+    pub fn group_bind_group(&self, which: MeshGroup) -> &wgpu::BindGroup {
+        self.data.group_bind_group(which)
+    }
+    // Lists the renderer's GPU buffers and those of every group
+    pub(crate) fn collect_resources(&self, out: &mut Vec<crate::resources::GpuResource>) {
+        self.data.collect_resources(out)
+    }
+    // See MeshRendererInner::group_summaries
+    pub(crate) fn group_summaries(&self) -> Vec<Option<(usize, usize, Option<&str>)>> {
+        self.data.group_summaries()
+    }
     /// Returns how many mesh groups there are.
     pub fn mesh_group_count(&self) -> usize {
         self.data.mesh_group_count()
@@ -293,33 +1207,194 @@ impl MeshRenderer {
     pub fn get_meshes_mut(&mut self, which: MeshGroup, mesh_number: usize) -> &mut [Transform3D] {
         self.data.get_meshes_mut(which, mesh_number)
     }
-    /// Deletes a mesh group, leaving its slot free to be reused.
-    pub fn remove_mesh_group(&mut self, which: MeshGroup) {
-        self.data.remove_mesh_group(which)
+    /// Gets the (mutable) instance transforms of every mesh of every
+    /// populated mesh group at once, indexed by mesh number.  Since
+    /// the slices are disjoint, they can be modified in parallel
+    /// (e.g. from different threads) before being uploaded with
+    /// [`Self::upload_meshes`].
+    pub fn get_groups_mut(&mut self) -> impl Iterator<Item = (MeshGroup, Vec<&mut [Transform3D]>)> {
+        self.data.get_groups_mut()
     }
-    /// Uploads a range of instance data for the given mesh of a given mesh group.
-    pub fn upload_meshes(
+    /// Enables or disables non-uniform scaling for the instances of
+    /// the given mesh group.  When enabled, each instance has a scale
+    /// vector (initially `[1.0, 1.0, 1.0]`) which is multiplied by
+    /// its [`Transform3D::scale`]; see [`Self::get_scales_mut`].
+    pub fn set_nonuniform_scale(&mut self, gpu: &crate::WGPU, which: MeshGroup, enabled: bool) {
+        self.data.set_nonuniform_scale(gpu, which, enabled)
+    }
+    /// Gets the non-uniform scale factors of every instance of the
+    /// given mesh of a mesh group, or `None` if the mesh group does
+    /// not use non-uniform scaling.
+    pub fn get_scales(&self, which: MeshGroup, mesh_number: usize) -> Option<&[[f32; 3]]> {
+        self.data.get_scales(which, mesh_number)
+    }
+    /// Gets the (mutable) non-uniform scale factors of every instance
+    /// of the given mesh of a mesh group.  These are uploaded along
+    /// with the instance transforms.  Panics if the mesh group does
+    /// not use non-uniform scaling.
+    pub fn get_scales_mut(&mut self, which: MeshGroup, mesh_number: usize) -> &mut [[f32; 3]] {
+        self.data.get_scales_mut(which, mesh_number)
+    }
+    /// Sets the [`ColorGrade`] applied to the given mesh group's
+    /// colors before postprocessing.
+    pub fn set_color_grade(&mut self, gpu: &crate::WGPU, which: MeshGroup, grade: ColorGrade) {
+        self.data.set_color_grade(gpu, which, grade)
+    }
+    /// Gets the [`ColorGrade`] of the given mesh group.
+    pub fn color_grade(&self, which: MeshGroup) -> ColorGrade {
+        self.data.color_grade(which)
+    }
+    /// Sets or clears the [`MeshOutline`] drawn around every
+    /// instance of the given mesh group.
+    pub fn set_outline(
         &mut self,
         gpu: &crate::WGPU,
         which: MeshGroup,
-        mesh_number: usize,
-        range: impl std::ops::RangeBounds<usize>,
+        outline: Option<MeshOutline>,
     ) {
-        self.data.upload_meshes(gpu, which, mesh_number, range)
+        self.data.set_outline(gpu, which, outline)
     }
-    /// Uploads instance data for all the meshes of a given mesh group.
-    pub fn upload_meshes_group(&mut self, gpu: &crate::WGPU, which: MeshGroup) {
-        self.data.upload_meshes_group(gpu, which)
+    /// Gets the [`MeshOutline`] of the given mesh group, if it has one.
+    pub fn outline(&self, which: MeshGroup) -> Option<MeshOutline> {
+        self.data.outline(which)
     }
-    /// Renders the given range of mesh groups into the given [`wgpu::RenderPass`].
-    pub fn render<'s, 'pass>(
-        &'s self,
-        rpass: &mut wgpu::RenderPass<'pass>,
-        which: impl std::ops::RangeBounds<usize>,
-    ) where
-        's: 'pass,
+    /// Sets how the given mesh group is lit, e.g. with [`ToonShading`].
+    pub fn set_shading(&mut self, gpu: &crate::WGPU, which: MeshGroup, shading: MeshShading) {
+        self.data.set_shading(gpu, which, shading)
+    }
+    /// Gets the [`MeshShading`] of the given mesh group.
+    pub fn shading(&self, which: MeshGroup) -> MeshShading {
+        self.data.shading(which)
+    }
+    /// Enables or disables per-instance [`MaterialOverride`]s for
+    /// the given mesh group.  When enabled, each instance's override
+    /// starts out as [`MaterialOverride::default`], which has no
+    /// effect; see [`Self::get_material_overrides_mut`].
+    pub fn set_material_overrides(&mut self, gpu: &crate::WGPU, which: MeshGroup, enabled: bool) {
+        self.data.set_material_overrides(gpu, which, enabled)
+    }
+    /// Gets the material overrides of every instance of the given
+    /// mesh of a mesh group, or `None` if the mesh group does not use
+    /// material overrides.
+    pub fn get_material_overrides(
+        &self,
+        which: MeshGroup,
+        mesh_number: usize,
+    ) -> Option<&[MaterialOverride]> {
+        self.data.get_material_overrides(which, mesh_number)
+    }
+    /// Gets the (mutable) material overrides of every instance of
+    /// the given mesh of a mesh group.  These are uploaded along with
+    /// the instance transforms.  Panics if the mesh group does not
+    /// use material overrides.
+    pub fn get_material_overrides_mut(
+        &mut self,
+        which: MeshGroup,
+        mesh_number: usize,
+    ) -> &mut [MaterialOverride] {
+        self.data.get_material_overrides_mut(which, mesh_number)
+    }
+    /// Deletes a mesh group, leaving its slot free to be reused.
+    pub fn remove_mesh_group(&mut self, which: MeshGroup) {
+        self.data.remove_mesh_group(which)
+    }
+    /// Sets whether mesh groups added from now on suballocate their
+    /// vertices and indices from large buffers shared with other
+    /// groups, rather than each getting their own.  This keeps GPU
+    /// memory use steady when many groups are added and removed;
+    /// call [`Self::maintain_pool`] once per frame to compact the
+    /// shared buffers when they get fragmented.
+    pub fn set_pooled(&mut self, pooled: bool) {
+        self.data.set_pooled(pooled)
+    }
+    /// Sets whether mesh groups added from now on keep a hierarchy
+    /// of their triangles in CPU memory, so that [`Self::raycast`]
+    /// hits their exact shapes rather than their bounding boxes.
+    pub fn set_pick_triangles(&mut self, pick_triangles: bool) {
+        self.data.pick_triangles = pick_triangles;
+    }
+    /// Finds the closest instance of any mesh hit by `ray`, e.g.
+    /// from [`Camera3D::screen_ray`], using the instance transforms
+    /// as they are on the CPU.  See [`crate::raycast`] for details.
+    pub fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+        (0..self.data.groups.len())
+            .filter_map(|which| self.data.raycast(MeshGroup(which), ray))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+    /// Like [`Self::raycast`], but only considers the given mesh group.
+    pub fn raycast_group(&self, which: MeshGroup, ray: &Ray) -> Option<RayHit> {
+        self.data.raycast(which, ray)
+    }
+    /// Compacts the shared buffers of pooled mesh groups if no
+    /// groups were added or removed since the last call and they're
+    /// fragmented or mostly empty.
+    pub fn maintain_pool(&mut self, gpu: &crate::WGPU) {
+        self.data.maintain_pool(gpu)
+    }
+    /// Compacts the shared buffers of pooled mesh groups right away.
+    pub fn compact_pool(&mut self, gpu: &crate::WGPU) {
+        self.data.compact_pool(gpu)
+    }
+    /// Uploads a range of instance data for the given mesh of a given mesh group.
+    pub fn upload_meshes(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        mesh_number: usize,
+        range: impl std::ops::RangeBounds<usize>,
+    ) {
+        self.data.upload_meshes(gpu, which, mesh_number, range)
+    }
+    /// Uploads instance data for all the meshes of a given mesh group.
+    pub fn upload_meshes_group(&mut self, gpu: &crate::WGPU, which: MeshGroup) {
+        self.data.upload_meshes_group(gpu, which)
+    }
+    /// Renders the given range of mesh groups into the given [`wgpu::RenderPass`].
+    pub fn render<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        which: impl std::ops::RangeBounds<usize>,
+    ) where
+        's: 'pass,
+    {
+        self.data.render(rpass, which, false)
+    }
+    /// Like [`Self::render`], but leaving out groups with
+    /// [`MeshShading::Water`], for drawing the scene into the
+    /// reflection they show.
+    pub fn render_reflection<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        which: impl std::ops::RangeBounds<usize>,
+    ) where
+        's: 'pass,
     {
-        self.data.render(rpass, which)
+        self.data.render(rpass, which, true)
+    }
+    /// Sets the mirrored scene shown by groups with
+    /// [`MeshShading::Water`], or `None` to show black.  Water
+    /// samples the view at its mirrored screen position, so it
+    /// should be the same size as the target the groups draw into.
+    pub fn set_reflection(&mut self, gpu: &crate::WGPU, view: Option<&wgpu::TextureView>) {
+        self.data.set_reflection(gpu, view)
+    }
+    /// Sets how many seconds water ripples have been scrolling.
+    pub fn set_reflection_time(&mut self, gpu: &crate::WGPU, seconds: f32) {
+        self.data.set_reflection_time(gpu, seconds)
+    }
+    /// Draws the selected instances of the given range of mesh
+    /// groups into a [`wgpu::RenderPass`] with a single
+    /// [`MASK_FORMAT`] color target and no depth attachment, writing
+    /// 1 wherever they cover.  Selected instances are drawn whole,
+    /// even where other geometry hides them.
+    pub fn render_selection_mask<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        which: impl std::ops::RangeBounds<usize>,
+    ) where
+        's: 'pass,
+    {
+        self.data.render_selection_mask(rpass, which)
     }
 }
 
@@ -333,8 +1408,8 @@ impl FlatRenderer {
         let bind_group_layout =
             gpu.device()
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
-                    // It needs the first entry for the texture and the second for the sampler.
+                    label: Some("flats:material_bgl"),
+                    // It needs the first entry for the material colors, then the color grade, outline, and shading from the third on.
                     // This is like defining a type signature.
                     entries: &[
                         // The material binding
@@ -350,6 +1425,39 @@ impl FlatRenderer {
                             },
                             count: None,
                         },
+                        // The color grade binding
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // The outline binding
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // The shading binding
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 4,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
                     ],
                 });
         let vertex_layout = wgpu::VertexBufferLayout {
@@ -366,9 +1474,22 @@ impl FlatRenderer {
         };
         let data = MeshRendererInner::new(
             gpu,
+            "flats",
             wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("static_meshes.wgsl"))),
-            "vs_flat_main",
+            [
+                "vs_flat_main",
+                "vs_flat_nonuniform_main",
+                "vs_flat_material_main",
+                "vs_flat_nonuniform_material_main",
+            ],
+            [
+                "vs_flat_outline_main",
+                "vs_flat_outline_nonuniform_main",
+                "vs_flat_outline_main",
+                "vs_flat_outline_nonuniform_main",
+            ],
             "fs_flat_main",
+            "fs_flat_water",
             bind_group_layout,
             vertex_layout,
             color_target,
@@ -381,6 +1502,10 @@ impl FlatRenderer {
     pub fn set_camera(&mut self, gpu: &crate::WGPU, camera: Camera3D) {
         self.data.set_camera(gpu, camera)
     }
+    /// Returns the camera used for all mesh groups.
+    pub fn camera(&self) -> Camera3D {
+        self.data.camera
+    }
     /// Add a mesh group with the given array of material colors.  All
     /// meshes in the group pull from the same vertex buffer, and each
     /// submesh is defined in terms of a range of indices within that
@@ -402,28 +1527,35 @@ impl FlatRenderer {
             panic!("Can't support >256 materials in one group (got {mat_count})");
         }
         let uniforms = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-            label: Some("flat mesh group"),
+            label: Some("flats:material_colors"),
             size: 4096,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
             mapped_at_creation: false,
         });
         gpu.queue()
             .write_buffer(&uniforms, 0, bytemuck::cast_slice(material_colors));
-        let bind_group = gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &self.data.bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                    buffer: &uniforms,
-                    offset: 0,
-                    size: Some(uniforms.size().try_into().unwrap()),
-                }),
-            }],
-        });
 
-        self.data
-            .add_mesh_group(gpu, bind_group, vertices, indices, mesh_info)
+        let group = self.data.add_mesh_group(
+            gpu,
+            GroupBinding::Materials(uniforms),
+            None,
+            vertices,
+            indices,
+            mesh_info,
+            |vtx| {
+                let [x, y, z, _] = vtx.position_which;
+                [x, y, z]
+            },
+        );
+        #[cfg(feature = "debug")]
+        {
+            self.data.groups[group.0]
+                .as_mut()
+                .unwrap()
+                .cpu_geometry
+                .colors = material_colors.to_vec();
+        }
+        group
     }
     /// Change the number of instances of the given mesh of the given mesh group.
     pub fn resize_group_mesh(
@@ -435,6 +1567,160 @@ impl FlatRenderer {
     ) -> usize {
         self.data.resize_group_mesh(gpu, which, mesh_idx, len)
     }
+    #[cfg(feature = "debug")]
+    pub(crate) fn write_gltf(&self, writer: &mut crate::export::GltfWriter) {
+        self.data.write_gltf(writer, |vtx, colors| {
+            let [x, y, z, which] = vtx.position_which;
+            crate::export::ExportVertex {
+                position: [x, y, z],
+                uv: None,
+                color: Some(
+                    colors
+                        .get(which.to_bits() as usize)
+                        .copied()
+                        .unwrap_or([1.0; 4]),
+                ),
+            }
+        });
+    }
+    /// Adds an instance of the given mesh with the given transform,
+    /// reusing the slot of a removed instance if there is one, and
+    /// uploads it.  When there are no free slots, the mesh's
+    /// instance count is doubled and the new instances are hidden
+    /// until they're added.
+    pub fn add_instance(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        mesh_number: usize,
+        trf: Transform3D,
+    ) -> InstanceHandle {
+        self.data.add_instance(gpu, which, mesh_number, trf)
+    }
+    /// Hides the instance with the given handle and frees its slot
+    /// for a later [`Self::add_instance`].  The handle must not be used again.
+    pub fn remove_instance(&mut self, gpu: &crate::WGPU, which: MeshGroup, handle: InstanceHandle) {
+        self.data.remove_instance(gpu, which, handle)
+    }
+    /// Makes the given mesh group smooth out fixed simulation steps:
+    /// it remembers each instance's transform as of the previous step
+    /// (see [`Self::step_interpolation`]) and uploads transforms
+    /// blended between the previous and current steps by the alpha
+    /// given to [`Self::interpolate`].  Turning this off goes back to
+    /// uploading the current transforms as they are.
+    pub fn set_interpolated(&mut self, which: MeshGroup, interpolated: bool) {
+        self.data.set_interpolated(which, interpolated)
+    }
+    /// Returns whether the given mesh group is interpolated, see
+    /// [`Self::set_interpolated`].
+    pub fn interpolated(&self, which: MeshGroup) -> bool {
+        self.data.interpolated(which)
+    }
+    /// Remembers every interpolated group's current instance
+    /// transforms as the previous ones; call this at the start of
+    /// each simulation step.
+    pub fn step_interpolation(&mut self) {
+        self.data.step_interpolation()
+    }
+    /// Moves instance `index` of the given mesh straight to its
+    /// current transform without interpolating from its old one,
+    /// e.g. when an object teleports.  Added instances start out
+    /// snapped.
+    pub fn snap_instance(&mut self, which: MeshGroup, mesh_number: usize, index: usize) {
+        self.data.snap_instance(which, mesh_number, index)
+    }
+    /// Uploads every interpolated group's instances blended between
+    /// the previous and current simulation steps by `alpha` (between
+    /// 0 and 1), e.g. [`crate::clock::Clock::alpha`].  Call this once
+    /// per rendered frame.
+    pub fn interpolate(&mut self, gpu: &crate::WGPU, alpha: f32) {
+        self.data.interpolate(gpu, alpha)
+    }
+    /// Shows or hides instance `index` of the given mesh and uploads
+    /// it, without moving any instances or changing the count.  A
+    /// hidden instance keeps its transform (which can still be
+    /// changed) but isn't drawn or hit by raycasts.  Adding an
+    /// instance into a removed instance's slot makes it visible.
+    pub fn set_instance_visible(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        mesh_number: usize,
+        index: usize,
+        visible: bool,
+    ) {
+        self.data
+            .set_instance_visible(gpu, which, mesh_number, index, visible)
+    }
+    /// Returns whether instance `index` of the given mesh is visible,
+    /// see [`Self::set_instance_visible`].
+    pub fn instance_visible(&self, which: MeshGroup, mesh_number: usize, index: usize) -> bool {
+        self.data.instance_visible(which, mesh_number, index)
+    }
+    /// Adds instance `index` of the given mesh to the selection drawn
+    /// by [`Self::render_selection_mask`], or removes it.  Removing
+    /// an instance or adding one into its slot deselects it.
+    pub fn set_instance_selected(
+        &mut self,
+        which: MeshGroup,
+        mesh_number: usize,
+        index: usize,
+        selected: bool,
+    ) {
+        self.data
+            .set_instance_selected(which, mesh_number, index, selected)
+    }
+    /// Returns whether instance `index` of the given mesh is
+    /// selected, see [`Self::set_instance_selected`].
+    pub fn instance_selected(&self, which: MeshGroup, mesh_number: usize, index: usize) -> bool {
+        self.data.instance_selected(which, mesh_number, index)
+    }
+    /// Deselects every instance of every mesh group.
+    pub fn clear_selection(&mut self) {
+        self.data.clear_selection()
+    }
+    /// Returns whether any instance is selected.
+    pub fn has_selection(&self) -> bool {
+        self.data.has_selection()
+    }
+    /// Names a group, or removes its name.  The name is included in
+    /// the labels of the group's GPU buffers (other than pooled
+    /// geometry) and bind group, which are remade to carry it, and
+    /// marks its draws with the `debug` feature, so the group is easy
+    /// to find in graphics debuggers and wgpu's errors.
+    pub fn set_group_name(&mut self, gpu: &crate::WGPU, which: MeshGroup, name: Option<&str>) {
+        self.data.set_group_name(gpu, which, name)
+    }
+    /// Returns the given group's name, if it has one.
+    pub fn group_name(&self, which: MeshGroup) -> Option<&str> {
+        self.data.group_name(which)
+    }
+    /// Describes the given mesh group: its texture, how many meshes
+    /// and instances it has, and the size of its geometry.
+    /// Panics if the given mesh group is not populated.
+    pub fn group_info(&self, which: MeshGroup) -> MeshGroupInfo {
+        self.data.group_info(which)
+    }
+    /// Returns the bind group layouts this renderer's pipelines use.
+    pub fn layouts(&self) -> MeshLayouts<'_> {
+        self.data.layouts()
+    }
+    /// Returns the camera bind group, for group 0 of [`MeshLayouts`].
+    pub fn camera_bind_group(&self) -> &wgpu::BindGroup {
+        &self.data.camera_bind_group
+    }
+    /// Returns the given group's bind group, for group 1 of [`MeshLayouts`].
+    pub fn group_bind_group(&self, which: MeshGroup) -> &wgpu::BindGroup {
+        self.data.group_bind_group(which)
+    }
+    // Lists the renderer's GPU buffers and those of every group
+    pub(crate) fn collect_resources(&self, out: &mut Vec<crate::resources::GpuResource>) {
+        self.data.collect_resources(out)
+    }
+    // See MeshRendererInner::group_summaries
+    pub(crate) fn group_summaries(&self) -> Vec<Option<(usize, usize, Option<&str>)>> {
+        self.data.group_summaries()
+    }
     /// Returns how many mesh groups there are.
     pub fn mesh_group_count(&self) -> usize {
         self.data.mesh_group_count()
@@ -455,10 +1741,134 @@ impl FlatRenderer {
     pub fn get_meshes_mut(&mut self, which: MeshGroup, mesh_number: usize) -> &mut [Transform3D] {
         self.data.get_meshes_mut(which, mesh_number)
     }
+    /// Gets the (mutable) instance transforms of every mesh of every
+    /// populated mesh group at once, indexed by mesh number.  Since
+    /// the slices are disjoint, they can be modified in parallel
+    /// (e.g. from different threads) before being uploaded with
+    /// [`Self::upload_meshes`].
+    pub fn get_groups_mut(&mut self) -> impl Iterator<Item = (MeshGroup, Vec<&mut [Transform3D]>)> {
+        self.data.get_groups_mut()
+    }
+    /// Enables or disables non-uniform scaling for the instances of
+    /// the given mesh group.  When enabled, each instance has a scale
+    /// vector (initially `[1.0, 1.0, 1.0]`) which is multiplied by
+    /// its [`Transform3D::scale`]; see [`Self::get_scales_mut`].
+    pub fn set_nonuniform_scale(&mut self, gpu: &crate::WGPU, which: MeshGroup, enabled: bool) {
+        self.data.set_nonuniform_scale(gpu, which, enabled)
+    }
+    /// Gets the non-uniform scale factors of every instance of the
+    /// given mesh of a mesh group, or `None` if the mesh group does
+    /// not use non-uniform scaling.
+    pub fn get_scales(&self, which: MeshGroup, mesh_number: usize) -> Option<&[[f32; 3]]> {
+        self.data.get_scales(which, mesh_number)
+    }
+    /// Gets the (mutable) non-uniform scale factors of every instance
+    /// of the given mesh of a mesh group.  These are uploaded along
+    /// with the instance transforms.  Panics if the mesh group does
+    /// not use non-uniform scaling.
+    pub fn get_scales_mut(&mut self, which: MeshGroup, mesh_number: usize) -> &mut [[f32; 3]] {
+        self.data.get_scales_mut(which, mesh_number)
+    }
+    /// Sets the [`ColorGrade`] applied to the given mesh group's
+    /// colors before postprocessing.
+    pub fn set_color_grade(&mut self, gpu: &crate::WGPU, which: MeshGroup, grade: ColorGrade) {
+        self.data.set_color_grade(gpu, which, grade)
+    }
+    /// Gets the [`ColorGrade`] of the given mesh group.
+    pub fn color_grade(&self, which: MeshGroup) -> ColorGrade {
+        self.data.color_grade(which)
+    }
+    /// Sets or clears the [`MeshOutline`] drawn around every
+    /// instance of the given mesh group.
+    pub fn set_outline(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        outline: Option<MeshOutline>,
+    ) {
+        self.data.set_outline(gpu, which, outline)
+    }
+    /// Gets the [`MeshOutline`] of the given mesh group, if it has one.
+    pub fn outline(&self, which: MeshGroup) -> Option<MeshOutline> {
+        self.data.outline(which)
+    }
+    /// Sets how the given mesh group is lit, e.g. with [`ToonShading`].
+    pub fn set_shading(&mut self, gpu: &crate::WGPU, which: MeshGroup, shading: MeshShading) {
+        self.data.set_shading(gpu, which, shading)
+    }
+    /// Gets the [`MeshShading`] of the given mesh group.
+    pub fn shading(&self, which: MeshGroup) -> MeshShading {
+        self.data.shading(which)
+    }
+    /// Enables or disables per-instance [`MaterialOverride`]s for
+    /// the given mesh group.  When enabled, each instance's override
+    /// starts out as [`MaterialOverride::default`], which has no
+    /// effect; see [`Self::get_material_overrides_mut`].
+    pub fn set_material_overrides(&mut self, gpu: &crate::WGPU, which: MeshGroup, enabled: bool) {
+        self.data.set_material_overrides(gpu, which, enabled)
+    }
+    /// Gets the material overrides of every instance of the given
+    /// mesh of a mesh group, or `None` if the mesh group does not use
+    /// material overrides.
+    pub fn get_material_overrides(
+        &self,
+        which: MeshGroup,
+        mesh_number: usize,
+    ) -> Option<&[MaterialOverride]> {
+        self.data.get_material_overrides(which, mesh_number)
+    }
+    /// Gets the (mutable) material overrides of every instance of
+    /// the given mesh of a mesh group.  These are uploaded along with
+    /// the instance transforms.  Panics if the mesh group does not
+    /// use material overrides.
+    pub fn get_material_overrides_mut(
+        &mut self,
+        which: MeshGroup,
+        mesh_number: usize,
+    ) -> &mut [MaterialOverride] {
+        self.data.get_material_overrides_mut(which, mesh_number)
+    }
     /// Deletes a mesh group, leaving its slot free to be reused.
     pub fn remove_mesh_group(&mut self, which: MeshGroup) {
         self.data.remove_mesh_group(which)
     }
+    /// Sets whether mesh groups added from now on suballocate their
+    /// vertices and indices from large buffers shared with other
+    /// groups, rather than each getting their own.  This keeps GPU
+    /// memory use steady when many groups are added and removed;
+    /// call [`Self::maintain_pool`] once per frame to compact the
+    /// shared buffers when they get fragmented.
+    pub fn set_pooled(&mut self, pooled: bool) {
+        self.data.set_pooled(pooled)
+    }
+    /// Sets whether mesh groups added from now on keep a hierarchy
+    /// of their triangles in CPU memory, so that [`Self::raycast`]
+    /// hits their exact shapes rather than their bounding boxes.
+    pub fn set_pick_triangles(&mut self, pick_triangles: bool) {
+        self.data.pick_triangles = pick_triangles;
+    }
+    /// Finds the closest instance of any mesh hit by `ray`, e.g.
+    /// from [`Camera3D::screen_ray`], using the instance transforms
+    /// as they are on the CPU.  See [`crate::raycast`] for details.
+    pub fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+        (0..self.data.groups.len())
+            .filter_map(|which| self.data.raycast(MeshGroup(which), ray))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+    /// Like [`Self::raycast`], but only considers the given mesh group.
+    pub fn raycast_group(&self, which: MeshGroup, ray: &Ray) -> Option<RayHit> {
+        self.data.raycast(which, ray)
+    }
+    /// Compacts the shared buffers of pooled mesh groups if no
+    /// groups were added or removed since the last call and they're
+    /// fragmented or mostly empty.
+    pub fn maintain_pool(&mut self, gpu: &crate::WGPU) {
+        self.data.maintain_pool(gpu)
+    }
+    /// Compacts the shared buffers of pooled mesh groups right away.
+    pub fn compact_pool(&mut self, gpu: &crate::WGPU) {
+        self.data.compact_pool(gpu)
+    }
     /// Uploads a range of instance data for the given mesh of a given mesh group.
     pub fn upload_meshes(
         &mut self,
@@ -481,7 +1891,44 @@ impl FlatRenderer {
     ) where
         's: 'pass,
     {
-        self.data.render(rpass, which)
+        self.data.render(rpass, which, false)
+    }
+    /// Like [`Self::render`], but leaving out groups with
+    /// [`MeshShading::Water`], for drawing the scene into the
+    /// reflection they show.
+    pub fn render_reflection<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        which: impl std::ops::RangeBounds<usize>,
+    ) where
+        's: 'pass,
+    {
+        self.data.render(rpass, which, true)
+    }
+    /// Sets the mirrored scene shown by groups with
+    /// [`MeshShading::Water`], or `None` to show black.  Water
+    /// samples the view at its mirrored screen position, so it
+    /// should be the same size as the target the groups draw into.
+    pub fn set_reflection(&mut self, gpu: &crate::WGPU, view: Option<&wgpu::TextureView>) {
+        self.data.set_reflection(gpu, view)
+    }
+    /// Sets how many seconds water ripples have been scrolling.
+    pub fn set_reflection_time(&mut self, gpu: &crate::WGPU, seconds: f32) {
+        self.data.set_reflection_time(gpu, seconds)
+    }
+    /// Draws the selected instances of the given range of mesh
+    /// groups into a [`wgpu::RenderPass`] with a single
+    /// [`MASK_FORMAT`] color target and no depth attachment, writing
+    /// 1 wherever they cover.  Selected instances are drawn whole,
+    /// even where other geometry hides them.
+    pub fn render_selection_mask<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        which: impl std::ops::RangeBounds<usize>,
+    ) where
+        's: 'pass,
+    {
+        self.data.render_selection_mask(rpass, which)
     }
 }
 
@@ -489,35 +1936,40 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
     #[allow(clippy::too_many_arguments)]
     fn new(
         gpu: &crate::WGPU,
+        // Prefix for GPU resource labels
+        name: &'static str,
         shader: wgpu::ShaderSource,
-        vs_entry: &str,
+        // For each combination of non-uniform scale and material overrides
+        vs_entries: [&str; 4],
+        // Likewise, for outlines
+        outline_vs_entries: [&str; 4],
         fs_entry: &str,
+        water_fs_entry: &str,
         bind_group_layout: wgpu::BindGroupLayout,
         vertex_layout: wgpu::VertexBufferLayout,
         color_target: wgpu::ColorTargetState,
         depth_format: wgpu::TextureFormat,
     ) -> Self {
-        let shader = gpu
-            .device()
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: shader,
-            });
+        let shader = gpu.create_shader(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{name}:shader")),
+            source: shader,
+        });
         let camera_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: std::mem::size_of::<[f32; 16]>() as u64,
+            label: Some(&format!("{name}:camera_buffer")),
+            // The view-projection matrix and the camera's position
+            size: std::mem::size_of::<[f32; 20]>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
         let camera_bind_group_layout =
             gpu.device()
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
+                    label: Some(&format!("{name}:camera_bgl")),
                     entries: &[wgpu::BindGroupLayoutEntry {
                         // This matches the binding in the shader
                         binding: 0,
-                        // Available in vertex shader
-                        visibility: wgpu::ShaderStages::VERTEX,
+                        // Available in both shaders, for lighting
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                         // It's a uniform buffer
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Uniform,
@@ -529,7 +1981,7 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
                     }],
                 });
         let camera_bind_group = gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
+            label: Some(&format!("{name}:camera_bg")),
             layout: &camera_bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
@@ -539,68 +1991,229 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
         let pipeline_layout =
             gpu.device()
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None,
+                    label: Some(&format!("{name}:pipeline_layout")),
                     bind_group_layouts: &[&camera_bind_group_layout, &bind_group_layout],
                     push_constant_ranges: &[],
                 });
-        let pipeline = gpu
-            .device()
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: vs_entry,
-                    buffers: &[
-                        vertex_layout,
-                        wgpu::VertexBufferLayout {
-                            array_stride: std::mem::size_of::<Transform3D>() as u64,
-                            attributes: &[
-                                // trans_scale
-                                wgpu::VertexAttribute {
-                                    format: wgpu::VertexFormat::Float32x4,
-                                    offset: 0,
-                                    shader_location: 2,
-                                },
-                                // rot
-                                wgpu::VertexAttribute {
-                                    format: wgpu::VertexFormat::Float32x4,
-                                    offset: std::mem::size_of::<f32>() as u64 * 4,
-                                    shader_location: 3,
-                                },
-                            ],
-                            step_mode: wgpu::VertexStepMode::Instance,
+        let reflection_bind_group_layout =
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&format!("{name}:reflection_bgl")),
+                    entries: &[
+                        // The mirrored scene
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                        // The time
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
                         },
                     ],
+                });
+        let water_pipeline_layout =
+            gpu.device()
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some(&format!("{name}:water_pipeline_layout")),
+                    bind_group_layouts: &[
+                        &camera_bind_group_layout,
+                        &bind_group_layout,
+                        &reflection_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        let reflection_time_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{name}:reflection_time")),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let blank_reflection = gpu
+            .create_texture(
+                &[0, 0, 0, 255],
+                wgpu::TextureFormat::Rgba8Unorm,
+                (1, 1),
+                Some(&format!("{name}:blank_reflection")),
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let reflection_bind_group = Self::reflection_bind_group(
+            gpu,
+            name,
+            &reflection_bind_group_layout,
+            &blank_reflection,
+            &reflection_time_buffer,
+        );
+        let instance_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Transform3D>() as u64,
+            attributes: &[
+                // trans_scale
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 2,
                 },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: fs_entry,
-                    targets: &[Some(color_target)],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    ..Default::default()
+                // rot
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: std::mem::size_of::<f32>() as u64 * 4,
+                    shader_location: 3,
                 },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: depth_format,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-            });
+            ],
+            step_mode: wgpu::VertexStepMode::Instance,
+        };
+        let scale_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<[f32; 3]>() as u64,
+            attributes: &[
+                // nonuniform scale
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 4,
+                },
+            ],
+            step_mode: wgpu::VertexStepMode::Instance,
+        };
+        let material_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<MaterialOverride>() as u64,
+            attributes: &[
+                // material_offset and colormod
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Uint32x2,
+                    offset: 0,
+                    shader_location: 5,
+                },
+            ],
+            step_mode: wgpu::VertexStepMode::Instance,
+        };
+        // Selection masks are drawn without depth testing, so
+        // selected instances are outlined even behind other geometry
+        let make_pipeline =
+            |entry_point: &str, buffers: &[wgpu::VertexBufferLayout], kind: PipelineKind| {
+                let mask = kind == PipelineKind::Mask;
+                gpu.device()
+                    .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some(&format!(
+                            "{name}:{}:{entry_point}",
+                            match kind {
+                                PipelineKind::Color => "pipeline",
+                                PipelineKind::Mask => "mask_pipeline",
+                                PipelineKind::Outline => "outline_pipeline",
+                                PipelineKind::Water => "water_pipeline",
+                            }
+                        )),
+                        layout: Some(if kind == PipelineKind::Water {
+                            &water_pipeline_layout
+                        } else {
+                            &pipeline_layout
+                        }),
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point,
+                            buffers,
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: match kind {
+                                PipelineKind::Color => fs_entry,
+                                PipelineKind::Mask => "fs_mask",
+                                PipelineKind::Outline => "fs_outline",
+                                PipelineKind::Water => water_fs_entry,
+                            },
+                            targets: &[Some(if mask {
+                                MASK_FORMAT.into()
+                            } else {
+                                color_target.clone()
+                            })],
+                        }),
+                        // Outlines are inverted hulls, showing only their back faces
+                        primitive: wgpu::PrimitiveState {
+                            topology: wgpu::PrimitiveTopology::TriangleList,
+                            front_face: wgpu::FrontFace::Ccw,
+                            cull_mode: Some(if kind == PipelineKind::Outline {
+                                wgpu::Face::Front
+                            } else {
+                                wgpu::Face::Back
+                            }),
+                            ..Default::default()
+                        },
+                        depth_stencil: (!mask).then(|| wgpu::DepthStencilState {
+                            format: depth_format,
+                            depth_write_enabled: true,
+                            depth_compare: wgpu::CompareFunction::Less,
+                            stencil: wgpu::StencilState::default(),
+                            bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                    })
+            };
+        // Optional instance streams occupy vertex buffer slots 2 and up, in order
+        let buffers = [
+            vec![vertex_layout.clone(), instance_layout.clone()],
+            vec![
+                vertex_layout.clone(),
+                instance_layout.clone(),
+                scale_layout.clone(),
+            ],
+            vec![
+                vertex_layout.clone(),
+                instance_layout.clone(),
+                material_layout.clone(),
+            ],
+            vec![
+                vertex_layout,
+                instance_layout,
+                scale_layout,
+                material_layout,
+            ],
+        ];
+        let pipelines =
+            std::array::from_fn(|i| make_pipeline(vs_entries[i], &buffers[i], PipelineKind::Color));
+        let mask_pipelines =
+            std::array::from_fn(|i| make_pipeline(vs_entries[i], &buffers[i], PipelineKind::Mask));
+        let outline_pipelines = std::array::from_fn(|i| {
+            make_pipeline(outline_vs_entries[i], &buffers[i], PipelineKind::Outline)
+        });
+        let water_pipelines =
+            std::array::from_fn(|i| make_pipeline(vs_entries[i], &buffers[i], PipelineKind::Water));
         let mut ret = Self {
+            name,
             groups: vec![],
             free_groups: vec![],
             bind_group_layout,
+            camera_bind_group_layout,
             camera_bind_group,
             camera_buffer,
-            pipeline,
+            pipelines,
+            mask_pipelines,
+            outline_pipelines,
+            water_pipelines,
+            reflection_bind_group_layout,
+            reflection_bind_group,
+            reflection_time_buffer,
+            blank_reflection,
+            pooled: false,
+            pick_triangles: false,
+            pool: None,
             _vertex_data: PhantomData,
             camera: Camera3D {
                 translation: [0.0; 3],
@@ -617,58 +2230,93 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
 
     fn set_camera(&mut self, gpu: &crate::WGPU, camera: Camera3D) {
         self.camera = camera;
-        let tr = ultraviolet::Vec3::from(camera.translation);
-        let view = (ultraviolet::Mat4::from_translation(tr)
-            * ultraviolet::Rotor3::from_quaternion_array(camera.rotation)
-                .into_matrix()
-                .into_homogeneous())
-        .inversed();
-        let proj = ultraviolet::projection::rh_yup::perspective_wgpu_dx(
-            camera.fov,
-            camera.aspect,
-            camera.near,
-            camera.far,
-        );
-        let mat = proj * view;
+        let mat = ultraviolet::Mat4::from(camera);
+        let [x, y, z] = camera.translation;
         gpu.queue()
             .write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&mat));
+        gpu.queue().write_buffer(
+            &self.camera_buffer,
+            std::mem::size_of::<[f32; 16]>() as u64,
+            bytemuck::bytes_of(&[x, y, z, 1.0]),
+        );
     }
+    #[allow(clippy::too_many_arguments)]
     fn add_mesh_group(
         &mut self,
         gpu: &crate::WGPU,
-        bind_group: wgpu::BindGroup,
+        binding: GroupBinding,
+        texture: Option<crate::resources::TextureInfo>,
         vertices: Vec<Vtx>,
         indices: Vec<u32>,
         mesh_info: Vec<MeshEntry>,
+        position: impl Fn(&Vtx) -> [f32; 3],
     ) -> MeshGroup {
+        let positions: Vec<_> = vertices.iter().map(position).collect();
+        let colliders =
+            MeshCollider::for_group(&positions, &indices, &mesh_info, self.pick_triangles);
         let group_idx = if let Some(idx) = self.free_groups.pop() {
             idx
         } else {
             self.groups.push(None);
             self.groups.len() - 1
         };
-        let vertex_buffer = gpu
-            .device()
-            .create_buffer_init(&wutil::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
-        let index_buffer = gpu
-            .device()
-            .create_buffer_init(&wutil::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&indices),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let geometry = if self.pooled {
+            self.pool_geometry(gpu, bytemuck::cast_slice(&vertices), &indices)
+        } else {
+            GroupGeometry::Dedicated {
+                vertex_buffer: gpu
+                    .device()
+                    .create_buffer_init(&wutil::BufferInitDescriptor {
+                        label: group_label(self.name, "vertex_buffer", group_idx, None).as_deref(),
+                        contents: bytemuck::cast_slice(&vertices),
+                        usage: wgpu::BufferUsages::VERTEX
+                            | wgpu::BufferUsages::COPY_DST
+                            | wgpu::BufferUsages::COPY_SRC,
+                    }),
+                index_buffer: gpu
+                    .device()
+                    .create_buffer_init(&wutil::BufferInitDescriptor {
+                        label: group_label(self.name, "index_buffer", group_idx, None).as_deref(),
+                        contents: bytemuck::cast_slice(&indices),
+                        usage: wgpu::BufferUsages::INDEX
+                            | wgpu::BufferUsages::COPY_DST
+                            | wgpu::BufferUsages::COPY_SRC,
+                    }),
+            }
+        };
         let instance_count: u32 = mesh_info.iter().map(|me| me.instance_count).sum();
         let instance_data = vec![Transform3D::zeroed(); instance_count as usize];
         let instance_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-            label: None,
+            label: group_label(self.name, "instance_buffer", group_idx, None).as_deref(),
             size: instance_count as u64 * std::mem::size_of::<Transform3D>() as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            usage: wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
             mapped_at_creation: false,
         });
+        let label = |kind: &str| group_label(self.name, kind, group_idx, None);
+        let color_grade_buffer = uniform_buffer(
+            gpu,
+            label("color_grade").as_deref(),
+            bytemuck::bytes_of(&ColorGrade::IDENTITY.matrix),
+        );
+        let outline_buffer = uniform_buffer(
+            gpu,
+            label("outline").as_deref(),
+            bytemuck::bytes_of(&MeshOutline::uniform(None)),
+        );
+        let shading_buffer = uniform_buffer(
+            gpu,
+            label("shading").as_deref(),
+            bytemuck::bytes_of(&MeshShading::Unlit.uniform()),
+        );
+        let bind_group = create_group_bind_group(
+            gpu,
+            &self.bind_group_layout,
+            label("group_bg").as_deref(),
+            &binding,
+            [&color_grade_buffer, &outline_buffer, &shading_buffer],
+        );
         let mut next_instance = 0_u32;
         let meshes: Vec<_> = mesh_info
             .into_iter()
@@ -685,20 +2333,140 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
                 MeshData {
                     instances: instance..next_instance,
                     submeshes: me.submeshes,
+                    free_instances: vec![],
+                    hidden: vec![],
+                    selected: vec![],
                 }
             })
             .collect();
         let group = MeshGroupData {
             instance_data,
             instance_buffer,
-            vertex_buffer,
-            index_buffer,
+            geometry,
+            binding,
             bind_group,
             meshes,
+            scales: None,
+            materials: None,
+            colliders,
+            color_grade: ColorGrade::IDENTITY,
+            color_grade_buffer,
+            outline: None,
+            outline_buffer,
+            shading: MeshShading::Unlit,
+            shading_buffer,
+            name: None,
+            texture,
+            interpolation: None,
+            #[cfg(feature = "debug")]
+            cpu_geometry: CpuGeometry {
+                vertices: bytemuck::cast_slice(&vertices).to_vec(),
+                indices,
+                colors: vec![],
+            },
         };
         self.groups[group_idx] = Some(group);
         MeshGroup(group_idx)
     }
+    // Suballocates and uploads a new group's geometry from the pool
+    fn pool_geometry(
+        &mut self,
+        gpu: &crate::WGPU,
+        vertices: &[u8],
+        indices: &[u32],
+    ) -> GroupGeometry {
+        let name = self.name;
+        let pool = self.pool.get_or_insert_with(|| MeshPool {
+            vertices: BufferPool::new(
+                gpu,
+                format!("{name}:vertex_pool"),
+                wgpu::BufferUsages::VERTEX,
+            ),
+            indices: BufferPool::new(gpu, format!("{name}:index_pool"), wgpu::BufferUsages::INDEX),
+            changed: false,
+        });
+        pool.changed = true;
+        let vertex_range = pool.vertices.alloc(
+            gpu,
+            pooled_ranges(&mut self.groups, false),
+            vertices.len() as u64,
+        );
+        let index_range = pool.indices.alloc(
+            gpu,
+            pooled_ranges(&mut self.groups, true),
+            std::mem::size_of_val(indices) as u64,
+        );
+        gpu.queue()
+            .write_buffer(&pool.vertices.buffer, vertex_range.start, vertices);
+        gpu.queue().write_buffer(
+            &pool.indices.buffer,
+            index_range.start,
+            bytemuck::cast_slice(indices),
+        );
+        GroupGeometry::Pooled {
+            vertices: vertex_range,
+            indices: index_range,
+        }
+    }
+    fn set_pooled(&mut self, pooled: bool) {
+        self.pooled = pooled;
+    }
+    fn raycast(&self, which: MeshGroup, ray: &Ray) -> Option<RayHit> {
+        let group = self.groups[which.0].as_ref()?;
+        let mut best: Option<RayHit> = None;
+        for (mesh_idx, (mesh, collider)) in group.meshes.iter().zip(&group.colliders).enumerate() {
+            for idx in mesh.instances.start as usize..mesh.instances.end as usize {
+                if mesh.hidden.contains(&(idx as u32 - mesh.instances.start)) {
+                    continue;
+                }
+                let scale = group.scales.as_ref().map_or([1.0; 3], |s| s.data[idx]);
+                let Some(distance) = collider.raycast(ray, &group.instance_data[idx], scale) else {
+                    continue;
+                };
+                if best.map_or(true, |b| distance < b.distance) {
+                    best = Some(RayHit {
+                        group: which,
+                        mesh: mesh_idx,
+                        instance: idx - mesh.instances.start as usize,
+                        distance,
+                        point: ray.at(distance),
+                    });
+                }
+            }
+        }
+        best
+    }
+    fn compact_pool(&mut self, gpu: &crate::WGPU) {
+        let Some(pool) = self.pool.as_mut() else {
+            return;
+        };
+        for (buffers, indices) in [(&mut pool.vertices, false), (&mut pool.indices, true)] {
+            let live = pooled_ranges(&mut self.groups, indices);
+            let used: u64 = live.iter().map(|r| r.end - r.start).sum();
+            // Leave some room to grow so the next added group doesn't immediately realloc
+            buffers.compact(gpu, live, used * 2);
+        }
+        pool.changed = false;
+    }
+    fn maintain_pool(&mut self, gpu: &crate::WGPU) {
+        let Some(pool) = self.pool.as_mut() else {
+            return;
+        };
+        // Only compact on frames where nothing was added or removed
+        if pool.changed {
+            pool.changed = false;
+            return;
+        }
+        let fragmented = pool
+            .vertices
+            .fragmented(&pooled_ranges(&mut self.groups, false))
+            || pool
+                .indices
+                .fragmented(&pooled_ranges(&mut self.groups, true));
+        if fragmented {
+            self.compact_pool(gpu);
+        }
+    }
     fn resize_group_mesh(
         &mut self,
         gpu: &crate::WGPU,
@@ -737,6 +2505,17 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
             group
                 .instance_data
                 .resize(new_group_len, Transform3D::zeroed());
+            if let Some(scales) = group.scales.as_mut() {
+                scales.data.resize(new_group_len, [1.0; 3]);
+            }
+            if let Some(interp) = group.interpolation.as_mut() {
+                interp.previous.resize(new_group_len, Transform3D::ZERO);
+            }
+            if let Some(materials) = group.materials.as_mut() {
+                materials
+                    .data
+                    .resize(new_group_len, MaterialOverride::default());
+            }
             // move over everything after this mesh
             if let Some(next) = next_mesh {
                 let next = &group.meshes[next];
@@ -745,6 +2524,24 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
                     next.instances.start as usize..old_group_len,
                     new_end as usize,
                 );
+                if let Some(scales) = group.scales.as_mut() {
+                    scales.data.copy_within(
+                        next.instances.start as usize..old_group_len,
+                        new_end as usize,
+                    );
+                }
+                if let Some(interp) = group.interpolation.as_mut() {
+                    interp.previous.copy_within(
+                        next.instances.start as usize..old_group_len,
+                        new_end as usize,
+                    );
+                }
+                if let Some(materials) = group.materials.as_mut() {
+                    materials.data.copy_within(
+                        next.instances.start as usize..old_group_len,
+                        new_end as usize,
+                    );
+                }
                 // update start and end indices for later meshes by diff, the amount that the group got pushed by.
                 let diff = new_end - next.instances.start;
                 for mesh_j in group.meshes[(mesh_idx + 1)..].iter_mut() {
@@ -758,22 +2555,397 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
             // grow instance buffer if needed
             let new_len_bytes = std::mem::size_of::<Transform3D>() * new_group_len;
             if new_len_bytes > group.instance_buffer.size() as usize {
+                trace_event!(
+                    renderer = self.name,
+                    group = which.0,
+                    bytes = new_len_bytes,
+                    "meshes: grew instance buffer"
+                );
                 group.instance_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-                    label: None,
+                    label: group_label(
+                        self.name,
+                        "instance_buffer",
+                        which.0,
+                        group.name.as_deref(),
+                    )
+                    .as_deref(),
                     size: new_len_bytes as u64,
-                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    usage: wgpu::BufferUsages::VERTEX
+                        | wgpu::BufferUsages::COPY_DST
+                        | wgpu::BufferUsages::COPY_SRC,
                     mapped_at_creation: false,
                 });
                 // write immediately since otherwise it will contain garbage
                 gpu.queue().write_buffer(
                     &group.instance_buffer,
                     0,
-                    bytemuck::cast_slice(&group.instance_data),
+                    bytemuck::cast_slice(&group.uploaded_instances(0..new_group_len)),
                 );
+                if let Some(scales) = group.scales.as_mut() {
+                    scales.realloc(gpu);
+                }
+                if let Some(materials) = group.materials.as_mut() {
+                    materials.realloc(gpu);
+                }
             }
         }
         old_len
     }
+    fn add_instance(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        mesh_number: usize,
+        trf: Transform3D,
+    ) -> InstanceHandle {
+        let count = self.mesh_instance_count(which, mesh_number);
+        let mesh = &mut self.groups[which.0].as_mut().unwrap().meshes[mesh_number];
+        // resize_group_mesh may have cut off some free or hidden instances
+        mesh.free_instances.retain(|&idx| (idx as usize) < count);
+        mesh.hidden.retain(|&idx| (idx as usize) < count);
+        let index = match mesh.free_instances.pop() {
+            Some(idx) => idx as usize,
+            None => {
+                // Double the mesh's instances so adding many instances doesn't move them every time
+                let new_count = (count * 2).max(count + 1);
+                self.resize_group_mesh(gpu, which, mesh_number, new_count);
+                let group = self.groups[which.0].as_mut().unwrap();
+                let start = group.meshes[mesh_number].instances.start as usize;
+                group.instance_data[start + count..start + new_count].fill(Transform3D::ZERO);
+                group.meshes[mesh_number]
+                    .free_instances
+                    .extend((count as u32 + 1..new_count as u32).rev());
+                count
+            }
+        };
+        let group = self.groups[which.0].as_mut().unwrap();
+        let mesh = &mut group.meshes[mesh_number];
+        mesh.hidden.retain(|&idx| idx as usize != index);
+        mesh.selected.retain(|&idx| idx as usize != index);
+        let instance = group.meshes[mesh_number].instances.start as usize + index;
+        group.instance_data[instance] = trf;
+        // New instances appear where they're added rather than moving there
+        if let Some(interp) = group.interpolation.as_mut() {
+            interp.previous[instance] = trf;
+        }
+        if let Some(scales) = group.scales.as_mut() {
+            scales.data[instance] = [1.0; 3];
+        }
+        if let Some(materials) = group.materials.as_mut() {
+            materials.data[instance] = MaterialOverride::default();
+        }
+        self.upload_meshes(gpu, which, mesh_number, index..=index);
+        InstanceHandle {
+            mesh: mesh_number,
+            index,
+        }
+    }
+    fn remove_instance(&mut self, gpu: &crate::WGPU, which: MeshGroup, handle: InstanceHandle) {
+        let mesh = &mut self.groups[which.0].as_mut().unwrap().meshes[handle.mesh];
+        debug_assert!(
+            !mesh.free_instances.contains(&(handle.index as u32)),
+            "Instance removed twice"
+        );
+        mesh.free_instances.push(handle.index as u32);
+        mesh.hidden.retain(|&idx| idx as usize != handle.index);
+        mesh.selected.retain(|&idx| idx as usize != handle.index);
+        // A zero scale hides the instance until its slot is reused
+        self.get_meshes_mut(which, handle.mesh)[handle.index] = Transform3D::ZERO;
+        self.snap_instance(which, handle.mesh, handle.index);
+        self.upload_meshes(gpu, which, handle.mesh, handle.index..=handle.index);
+    }
+    fn set_interpolated(&mut self, which: MeshGroup, interpolated: bool) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        group.interpolation = interpolated.then(|| Interpolation {
+            previous: group.instance_data.clone(),
+            alpha: 1.0,
+        });
+    }
+    fn interpolated(&self, which: MeshGroup) -> bool {
+        self.groups[which.0]
+            .as_ref()
+            .unwrap()
+            .interpolation
+            .is_some()
+    }
+    fn step_interpolation(&mut self) {
+        for group in self.groups.iter_mut().flatten() {
+            if let Some(interp) = group.interpolation.as_mut() {
+                interp.previous.copy_from_slice(&group.instance_data);
+            }
+        }
+    }
+    fn snap_instance(&mut self, which: MeshGroup, mesh_number: usize, index: usize) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        let instance = group.meshes[mesh_number].instances.start as usize + index;
+        if let Some(interp) = group.interpolation.as_mut() {
+            interp.previous[instance] = group.instance_data[instance];
+        }
+    }
+    fn interpolate(&mut self, gpu: &crate::WGPU, alpha: f32) {
+        let interpolated: Vec<_> = self
+            .groups
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(idx, group)| {
+                let interp = group.as_mut()?.interpolation.as_mut()?;
+                interp.alpha = alpha.clamp(0.0, 1.0);
+                Some(MeshGroup(idx))
+            })
+            .collect();
+        for which in interpolated {
+            self.upload_meshes_group(gpu, which);
+        }
+    }
+    fn set_instance_visible(
+        &mut self,
+        gpu: &crate::WGPU,
+        which: MeshGroup,
+        mesh_number: usize,
+        index: usize,
+        visible: bool,
+    ) {
+        let count = self.mesh_instance_count(which, mesh_number);
+        assert!(index < count, "Instance {index} out of range");
+        let mesh = &mut self.groups[which.0].as_mut().unwrap().meshes[mesh_number];
+        // resize_group_mesh may have cut off some hidden instances
+        mesh.hidden.retain(|&idx| (idx as usize) < count);
+        if visible != mesh.hidden.contains(&(index as u32)) {
+            return;
+        }
+        if visible {
+            mesh.hidden.retain(|&idx| idx as usize != index);
+        } else {
+            mesh.hidden.push(index as u32);
+        }
+        self.upload_meshes(gpu, which, mesh_number, index..=index);
+    }
+    fn instance_visible(&self, which: MeshGroup, mesh_number: usize, index: usize) -> bool {
+        !self.groups[which.0].as_ref().unwrap().meshes[mesh_number]
+            .hidden
+            .contains(&(index as u32))
+    }
+    fn set_instance_selected(
+        &mut self,
+        which: MeshGroup,
+        mesh_number: usize,
+        index: usize,
+        selected: bool,
+    ) {
+        let count = self.mesh_instance_count(which, mesh_number);
+        assert!(index < count, "Instance {index} out of range");
+        let mesh = &mut self.groups[which.0].as_mut().unwrap().meshes[mesh_number];
+        // resize_group_mesh may have cut off some selected instances
+        mesh.selected.retain(|&idx| (idx as usize) < count);
+        if selected == mesh.selected.contains(&(index as u32)) {
+            return;
+        }
+        if selected {
+            mesh.selected.push(index as u32);
+        } else {
+            mesh.selected.retain(|&idx| idx as usize != index);
+        }
+    }
+    fn instance_selected(&self, which: MeshGroup, mesh_number: usize, index: usize) -> bool {
+        self.groups[which.0].as_ref().unwrap().meshes[mesh_number]
+            .selected
+            .contains(&(index as u32))
+    }
+    fn clear_selection(&mut self) {
+        for group in self.groups.iter_mut().flatten() {
+            for mesh in group.meshes.iter_mut() {
+                mesh.selected.clear();
+            }
+        }
+    }
+    fn has_selection(&self) -> bool {
+        self.groups
+            .iter()
+            .flatten()
+            .flat_map(|group| group.meshes.iter())
+            .any(|mesh| !mesh.selected.is_empty())
+    }
+
+    fn set_group_name(&mut self, gpu: &crate::WGPU, which: MeshGroup, name: Option<&str>) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        group.name = name.map(str::to_string);
+        let name = group.name.as_deref();
+        let label = |kind: &str| group_label(self.name, kind, which.0, name);
+        let mut encoder = crate::relabel_encoder(gpu);
+        group.instance_buffer = crate::relabel_buffer(
+            gpu,
+            &mut encoder,
+            &group.instance_buffer,
+            label("instance_buffer").as_deref(),
+        );
+        // Pooled geometry belongs to the whole renderer
+        if let GroupGeometry::Dedicated {
+            vertex_buffer,
+            index_buffer,
+        } = &mut group.geometry
+        {
+            *vertex_buffer = crate::relabel_buffer(
+                gpu,
+                &mut encoder,
+                vertex_buffer,
+                label("vertex_buffer").as_deref(),
+            );
+            *index_buffer = crate::relabel_buffer(
+                gpu,
+                &mut encoder,
+                index_buffer,
+                label("index_buffer").as_deref(),
+            );
+        }
+        gpu.queue().submit(Some(encoder.finish()));
+        // The rest of the group's data is on the CPU, so its buffers
+        // are filled again rather than copied
+        if let Some(scales) = group.scales.as_mut() {
+            scales.relabel(gpu, label("scales"));
+        }
+        if let Some(materials) = group.materials.as_mut() {
+            materials.relabel(gpu, label("material_overrides"));
+        }
+        group.color_grade_buffer = uniform_buffer(
+            gpu,
+            label("color_grade").as_deref(),
+            bytemuck::bytes_of(&group.color_grade.matrix),
+        );
+        group.outline_buffer = uniform_buffer(
+            gpu,
+            label("outline").as_deref(),
+            bytemuck::bytes_of(&MeshOutline::uniform(group.outline)),
+        );
+        group.shading_buffer = uniform_buffer(
+            gpu,
+            label("shading").as_deref(),
+            bytemuck::bytes_of(&group.shading.uniform()),
+        );
+        group.bind_group = create_group_bind_group(
+            gpu,
+            &self.bind_group_layout,
+            label("group_bg").as_deref(),
+            &group.binding,
+            [
+                &group.color_grade_buffer,
+                &group.outline_buffer,
+                &group.shading_buffer,
+            ],
+        );
+    }
+    fn group_name(&self, which: MeshGroup) -> Option<&str> {
+        self.groups[which.0].as_ref().unwrap().name.as_deref()
+    }
+    fn group_info(&self, which: MeshGroup) -> MeshGroupInfo {
+        let group = self.groups[which.0].as_ref().unwrap();
+        let (vertex_bytes, index_bytes) = match &group.geometry {
+            GroupGeometry::Dedicated {
+                vertex_buffer,
+                index_buffer,
+            } => (vertex_buffer.size(), index_buffer.size()),
+            GroupGeometry::Pooled { vertices, indices } => {
+                (vertices.end - vertices.start, indices.end - indices.start)
+            }
+        };
+        MeshGroupInfo {
+            texture: group.texture,
+            mesh_count: group.meshes.len(),
+            instance_count: group.instance_data.len(),
+            vertex_count: vertex_bytes as usize / std::mem::size_of::<Vtx>(),
+            index_count: index_bytes as usize / std::mem::size_of::<u32>(),
+        }
+    }
+    fn layouts(&self) -> MeshLayouts<'_> {
+        MeshLayouts {
+            camera: &self.camera_bind_group_layout,
+            group: &self.bind_group_layout,
+            reflection: &self.reflection_bind_group_layout,
+        }
+    }
+    fn group_bind_group(&self, which: MeshGroup) -> &wgpu::BindGroup {
+        &self.groups[which.0].as_ref().unwrap().bind_group
+    }
+    // Describes each group slot for a frame graph: how many visible
+    // and selected instances it has and its name, or None for empty
+    // slots
+    fn group_summaries(&self) -> Vec<Option<(usize, usize, Option<&str>)>> {
+        self.groups
+            .iter()
+            .map(|group| {
+                let group = group.as_ref()?;
+                let visible = group
+                    .meshes
+                    .iter()
+                    .map(|mesh| {
+                        // Free and hidden instances are distinct, but
+                        // shrinking may leave stale entries of either
+                        let len = mesh.instances.len() as u32;
+                        let free = mesh.free_instances.iter().filter(|&&i| i < len).count();
+                        let hidden = mesh.hidden.iter().filter(|&&i| i < len).count();
+                        (len as usize).saturating_sub(free + hidden)
+                    })
+                    .sum();
+                let selected = group
+                    .meshes
+                    .iter()
+                    .map(|mesh| {
+                        let len = mesh.instances.len() as u32;
+                        mesh.selected.iter().filter(|&&i| i < len).count()
+                    })
+                    .sum();
+                Some((visible, selected, group.name.as_deref()))
+            })
+            .collect()
+    }
+    fn collect_resources(&self, out: &mut Vec<crate::resources::GpuResource>) {
+        use crate::resources::GpuResource;
+        out.push(GpuResource::buffer(
+            Some(format!("{}:camera_buffer", self.name)),
+            &self.camera_buffer,
+        ));
+        out.push(GpuResource::buffer(
+            Some(format!("{}:reflection_time", self.name)),
+            &self.reflection_time_buffer,
+        ));
+        if let Some(pool) = self.pool.as_ref() {
+            for pool in [&pool.vertices, &pool.indices] {
+                out.push(GpuResource::buffer(Some(pool.label.clone()), &pool.buffer));
+            }
+        }
+        for (which, group) in self.groups.iter().enumerate() {
+            let Some(group) = group else {
+                continue;
+            };
+            let label = |kind: &str| group_label(self.name, kind, which, group.name.as_deref());
+            out.push(GpuResource::buffer(
+                label("instance_buffer"),
+                &group.instance_buffer,
+            ));
+            if let GroupGeometry::Dedicated {
+                vertex_buffer,
+                index_buffer,
+            } = &group.geometry
+            {
+                out.push(GpuResource::buffer(label("vertex_buffer"), vertex_buffer));
+                out.push(GpuResource::buffer(label("index_buffer"), index_buffer));
+            }
+            if let Some(scales) = group.scales.as_ref() {
+                out.push(GpuResource::buffer(scales.label.clone(), &scales.buffer));
+            }
+            if let Some(materials) = group.materials.as_ref() {
+                out.push(GpuResource::buffer(
+                    materials.label.clone(),
+                    &materials.buffer,
+                ));
+            }
+            out.push(GpuResource::buffer(
+                label("color_grade"),
+                &group.color_grade_buffer,
+            ));
+            out.push(GpuResource::buffer(label("outline"), &group.outline_buffer));
+            out.push(GpuResource::buffer(label("shading"), &group.shading_buffer));
+        }
+    }
 
     fn mesh_group_count(&self) -> usize {
         self.groups.len()
@@ -797,10 +2969,236 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
         let range = mesh.instances.clone();
         &mut group.instance_data[range.start as usize..range.end as usize]
     }
+    fn get_groups_mut(&mut self) -> impl Iterator<Item = (MeshGroup, Vec<&mut [Transform3D]>)> {
+        self.groups
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(which, group)| {
+                let group = group.as_mut()?;
+                // meshes' instance ranges are sorted and don't overlap, so split them off in order
+                let mut rest = &mut group.instance_data[..];
+                let mut consumed = 0;
+                let mut meshes = Vec::with_capacity(group.meshes.len());
+                for mesh in group.meshes.iter() {
+                    let start = mesh.instances.start as usize - consumed;
+                    let len = mesh.instances.len();
+                    let (instances, tail) = std::mem::take(&mut rest)[start..].split_at_mut(len);
+                    meshes.push(instances);
+                    rest = tail;
+                    consumed = mesh.instances.end as usize;
+                }
+                Some((MeshGroup(which), meshes))
+            })
+    }
+    fn set_nonuniform_scale(&mut self, gpu: &crate::WGPU, which: MeshGroup, enabled: bool) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        if !enabled {
+            group.scales = None;
+        } else if group.scales.is_none() {
+            group.scales = Some(InstanceStream::new(
+                gpu,
+                group_label(self.name, "scales", which.0, group.name.as_deref()),
+                vec![[1.0; 3]; group.instance_data.len()],
+            ));
+        }
+    }
+    fn get_scales(&self, which: MeshGroup, mesh_number: usize) -> Option<&[[f32; 3]]> {
+        let group = self.groups[which.0].as_ref().unwrap();
+        let range = group.meshes[mesh_number].instances.clone();
+        group
+            .scales
+            .as_ref()
+            .map(|scales| &scales.data[range.start as usize..range.end as usize])
+    }
+    fn get_scales_mut(&mut self, which: MeshGroup, mesh_number: usize) -> &mut [[f32; 3]] {
+        let group = self.groups[which.0].as_mut().unwrap();
+        let range = group.meshes[mesh_number].instances.clone();
+        let scales = group
+            .scales
+            .as_mut()
+            .expect("Mesh group does not use non-uniform scaling");
+        &mut scales.data[range.start as usize..range.end as usize]
+    }
+    // Writes each group with its visible instances
+    #[cfg(feature = "debug")]
+    fn write_gltf(
+        &self,
+        writer: &mut crate::export::GltfWriter,
+        vertex: impl Fn(&Vtx, &[[f32; 4]]) -> crate::export::ExportVertex,
+    ) {
+        for (idx, group) in self.groups.iter().enumerate() {
+            let Some(group) = group else {
+                continue;
+            };
+            let geometry = &group.cpu_geometry;
+            let vertices: Vec<_> = geometry
+                .vertices
+                .chunks_exact(std::mem::size_of::<Vtx>())
+                .map(|bytes| vertex(&bytemuck::pod_read_unaligned(bytes), &geometry.colors))
+                .collect();
+            let meshes: Vec<_> = group
+                .meshes
+                .iter()
+                .map(|mesh| crate::export::ExportMesh {
+                    submeshes: &mesh.submeshes,
+                    instances: (mesh.instances.start as usize..mesh.instances.end as usize)
+                        .filter(|&inst| {
+                            !mesh.hidden.contains(&(inst as u32 - mesh.instances.start))
+                        })
+                        .map(|inst| {
+                            let scale = group.scales.as_ref().map_or([1.0; 3], |s| s.data[inst]);
+                            (
+                                inst - mesh.instances.start as usize,
+                                group.instance_data[inst],
+                                scale,
+                            )
+                        })
+                        // Removed and unused instances have zero scale
+                        .filter(|(_, trf, _)| trf.scale != 0.0)
+                        .collect(),
+                })
+                .collect();
+            writer.add_group(
+                &format!("{} group {idx}", self.name),
+                &vertices,
+                &geometry.indices,
+                &meshes,
+            );
+        }
+    }
+    fn set_color_grade(&mut self, gpu: &crate::WGPU, which: MeshGroup, grade: ColorGrade) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        group.color_grade = grade;
+        gpu.queue().write_buffer(
+            &group.color_grade_buffer,
+            0,
+            bytemuck::bytes_of(&grade.matrix),
+        );
+    }
+    fn color_grade(&self, which: MeshGroup) -> ColorGrade {
+        self.groups[which.0].as_ref().unwrap().color_grade
+    }
+    fn set_outline(&mut self, gpu: &crate::WGPU, which: MeshGroup, outline: Option<MeshOutline>) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        group.outline = outline;
+        gpu.queue().write_buffer(
+            &group.outline_buffer,
+            0,
+            bytemuck::bytes_of(&MeshOutline::uniform(outline)),
+        );
+    }
+    fn outline(&self, which: MeshGroup) -> Option<MeshOutline> {
+        self.groups[which.0].as_ref().unwrap().outline
+    }
+    fn set_shading(&mut self, gpu: &crate::WGPU, which: MeshGroup, shading: MeshShading) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        group.shading = shading;
+        gpu.queue().write_buffer(
+            &group.shading_buffer,
+            0,
+            bytemuck::bytes_of(&shading.uniform()),
+        );
+    }
+    fn shading(&self, which: MeshGroup) -> MeshShading {
+        self.groups[which.0].as_ref().unwrap().shading
+    }
+    fn reflection_bind_group(
+        gpu: &crate::WGPU,
+        name: &str,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        time_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        let sampler = gpu.device().create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(&format!("{name}:reflection_sampler")),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{name}:reflection_bg")),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: time_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+    fn set_reflection(&mut self, gpu: &crate::WGPU, view: Option<&wgpu::TextureView>) {
+        self.reflection_bind_group = Self::reflection_bind_group(
+            gpu,
+            self.name,
+            &self.reflection_bind_group_layout,
+            view.unwrap_or(&self.blank_reflection),
+            &self.reflection_time_buffer,
+        );
+    }
+    fn set_reflection_time(&mut self, gpu: &crate::WGPU, seconds: f32) {
+        gpu.queue().write_buffer(
+            &self.reflection_time_buffer,
+            0,
+            bytemuck::bytes_of(&[seconds, 0.0, 0.0, 0.0]),
+        );
+    }
+    fn set_material_overrides(&mut self, gpu: &crate::WGPU, which: MeshGroup, enabled: bool) {
+        let group = self.groups[which.0].as_mut().unwrap();
+        if !enabled {
+            group.materials = None;
+        } else if group.materials.is_none() {
+            group.materials = Some(InstanceStream::new(
+                gpu,
+                group_label(
+                    self.name,
+                    "material_overrides",
+                    which.0,
+                    group.name.as_deref(),
+                ),
+                vec![MaterialOverride::default(); group.instance_data.len()],
+            ));
+        }
+    }
+    fn get_material_overrides(
+        &self,
+        which: MeshGroup,
+        mesh_number: usize,
+    ) -> Option<&[MaterialOverride]> {
+        let group = self.groups[which.0].as_ref().unwrap();
+        let range = group.meshes[mesh_number].instances.clone();
+        group
+            .materials
+            .as_ref()
+            .map(|materials| &materials.data[range.start as usize..range.end as usize])
+    }
+    fn get_material_overrides_mut(
+        &mut self,
+        which: MeshGroup,
+        mesh_number: usize,
+    ) -> &mut [MaterialOverride] {
+        let group = self.groups[which.0].as_mut().unwrap();
+        let range = group.meshes[mesh_number].instances.clone();
+        let materials = group
+            .materials
+            .as_mut()
+            .expect("Mesh group does not use material overrides");
+        &mut materials.data[range.start as usize..range.end as usize]
+    }
     /// Deletes a mesh group, leaving an empty placeholder.
     fn remove_mesh_group(&mut self, which: MeshGroup) {
-        if self.groups[which.0].is_some() {
-            self.groups[which.0] = None;
+        if let Some(group) = self.groups[which.0].take() {
+            if let (GroupGeometry::Pooled { .. }, Some(pool)) = (group.geometry, self.pool.as_mut())
+            {
+                pool.changed = true;
+            }
             self.free_groups.push(which.0);
         }
     }
@@ -817,59 +3215,177 @@ impl<Vtx: bytemuck::Pod + bytemuck::Zeroable + Copy> MeshRendererInner<Vtx> {
             range,
             mesh.instances.end as usize - mesh.instances.start as usize,
         );
+        trace_span!(
+            "meshes:upload",
+            renderer = self.name,
+            group = which.0,
+            mesh = mesh_number,
+            count = range.len(),
+            bytes = range.len() * std::mem::size_of::<Transform3D>()
+        );
         // offset range by instance_start
+        let instance_range = (mesh.instances.start as usize + range.start)
+            ..(mesh.instances.start as usize + range.end);
         gpu.queue().write_buffer(
             &group.instance_buffer,
-            ((mesh.instances.start as usize + range.start as usize)
-                * std::mem::size_of::<Transform3D>()) as u64,
-            bytemuck::cast_slice(
-                &group.instance_data[(mesh.instances.start as usize + range.start)
-                    ..(mesh.instances.start as usize + range.end)],
-            ),
+            (instance_range.start * std::mem::size_of::<Transform3D>()) as u64,
+            bytemuck::cast_slice(&group.uploaded_instances(instance_range.clone())),
         );
+        if let Some(scales) = group.scales.as_ref() {
+            scales.upload(gpu, instance_range.clone());
+        }
+        if let Some(materials) = group.materials.as_ref() {
+            materials.upload(gpu, instance_range);
+        }
     }
     fn upload_meshes_group(&mut self, gpu: &crate::WGPU, which: MeshGroup) {
         // upload the whole instance buffer
         let group = &self.groups[which.0].as_ref().unwrap();
+        trace_span!(
+            "meshes:upload_group",
+            renderer = self.name,
+            group = which.0,
+            count = group.instance_data.len(),
+            bytes = group.instance_data.len() * std::mem::size_of::<Transform3D>()
+        );
         gpu.queue().write_buffer(
             &group.instance_buffer,
             0,
-            bytemuck::cast_slice(&group.instance_data),
+            bytemuck::cast_slice(&group.uploaded_instances(0..group.instance_data.len())),
         );
+        if let Some(scales) = group.scales.as_ref() {
+            scales.upload(gpu, 0..scales.data.len());
+        }
+        if let Some(materials) = group.materials.as_ref() {
+            materials.upload(gpu, 0..materials.data.len());
+        }
     }
+    // Draws the groups in `which`, leaving out water groups when
+    // drawing into the reflection they show
     fn render<'s, 'pass>(
         &'s self,
         rpass: &mut wgpu::RenderPass<'pass>,
         which: impl std::ops::RangeBounds<usize>,
+        reflection: bool,
     ) where
         's: 'pass,
     {
         if self.groups.is_empty() {
             return;
         }
-        rpass.set_pipeline(&self.pipeline);
         let which = crate::range(which, self.groups.len());
-        // camera
-        rpass.set_bind_group(0, &self.camera_bind_group, &[]);
         for group in self.groups[which].iter().filter_map(|o| o.as_ref()) {
-            rpass.set_bind_group(1, &group.bind_group, &[]);
-            rpass.set_vertex_buffer(0, group.vertex_buffer.slice(..));
-            rpass.set_vertex_buffer(1, group.instance_buffer.slice(..));
-            rpass.set_index_buffer(group.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            for mesh in group.meshes.iter() {
-                if mesh.instances.is_empty() {
-                    continue;
+            let water = matches!(group.shading, MeshShading::Water(_));
+            if water && reflection {
+                continue;
+            }
+            #[cfg(feature = "debug")]
+            if let Some(name) = group.name.as_deref() {
+                rpass.insert_debug_marker(name);
+            }
+            self.bind_group_buffers(rpass, group);
+            let pipeline = if water {
+                rpass.set_bind_group(2, &self.reflection_bind_group, &[]);
+                &self.water_pipelines[group.pipeline_index()]
+            } else {
+                &self.pipelines[group.pipeline_index()]
+            };
+            // The outline's hull is drawn after the group itself
+            // so the depth test rejects most of it early
+            let outline_pipeline = group
+                .outline
+                .map(|_| &self.outline_pipelines[group.pipeline_index()]);
+            for pipeline in std::iter::once(pipeline).chain(outline_pipeline) {
+                rpass.set_pipeline(pipeline);
+                for mesh in group.meshes.iter() {
+                    if mesh.instances.is_empty() {
+                        continue;
+                    }
+                    for submesh in mesh.submeshes.iter() {
+                        rpass.draw_indexed(
+                            submesh.indices.clone(),
+                            submesh.vertex_base,
+                            mesh.instances.clone(),
+                        );
+                    }
                 }
-                for submesh in mesh.submeshes.iter() {
-                    rpass.draw_indexed(
-                        submesh.indices.clone(),
-                        submesh.vertex_base,
-                        mesh.instances.clone(),
-                    );
+            }
+        }
+    }
+    fn render_selection_mask<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        which: impl std::ops::RangeBounds<usize>,
+    ) where
+        's: 'pass,
+    {
+        if self.groups.is_empty() {
+            return;
+        }
+        let which = crate::range(which, self.groups.len());
+        for group in self.groups[which].iter().filter_map(|o| o.as_ref()) {
+            if group.meshes.iter().all(|mesh| mesh.selected.is_empty()) {
+                continue;
+            }
+            rpass.set_pipeline(&self.mask_pipelines[group.pipeline_index()]);
+            self.bind_group_buffers(rpass, group);
+            for mesh in group.meshes.iter() {
+                // Hidden instances have a zero scale, so they draw nothing
+                for &idx in mesh
+                    .selected
+                    .iter()
+                    .filter(|&&idx| idx < mesh.instances.len() as u32)
+                {
+                    let instance = mesh.instances.start + idx;
+                    for submesh in mesh.submeshes.iter() {
+                        rpass.draw_indexed(
+                            submesh.indices.clone(),
+                            submesh.vertex_base,
+                            instance..instance + 1,
+                        );
+                    }
                 }
             }
         }
     }
+    // Sets the group's bind groups and vertex and index buffers
+    fn bind_group_buffers<'s, 'pass>(
+        &'s self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        group: &'s MeshGroupData,
+    ) where
+        's: 'pass,
+    {
+        let mut slot = 2;
+        if let Some(scales) = group.scales.as_ref() {
+            rpass.set_vertex_buffer(slot, scales.buffer.slice(..));
+            slot += 1;
+        }
+        if let Some(materials) = group.materials.as_ref() {
+            rpass.set_vertex_buffer(slot, materials.buffer.slice(..));
+        }
+        // camera
+        rpass.set_bind_group(0, &self.camera_bind_group, &[]);
+        rpass.set_bind_group(1, &group.bind_group, &[]);
+        match &group.geometry {
+            GroupGeometry::Dedicated {
+                vertex_buffer,
+                index_buffer,
+            } => {
+                rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                rpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            }
+            GroupGeometry::Pooled { vertices, indices } => {
+                let pool = self.pool.as_ref().unwrap();
+                rpass.set_vertex_buffer(0, pool.vertices.buffer.slice(vertices.clone()));
+                rpass.set_index_buffer(
+                    pool.indices.buffer.slice(indices.clone()),
+                    wgpu::IndexFormat::Uint32,
+                );
+            }
+        }
+        rpass.set_vertex_buffer(1, group.instance_buffer.slice(..));
+    }
 }
 
 /// An opaque identifier for a mesh group.
@@ -885,6 +3401,21 @@ impl From<usize> for MeshGroup {
         Self(value)
     }
 }
+/// What a mesh group holds, from e.g. [`MeshRenderer::group_info`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshGroupInfo {
+    /// The texture the group draws from, or `None` for flat mesh groups
+    pub texture: Option<crate::resources::TextureInfo>,
+    /// How many meshes are in the group
+    pub mesh_count: usize,
+    /// How many instances the group has room for, across all its meshes
+    pub instance_count: usize,
+    /// How many vertices the group's meshes share
+    pub vertex_count: usize,
+    /// How many indices the group's meshes share
+    pub index_count: usize,
+}
+
 /// An entry in a mesh group, i.e. a 3D model.
 #[derive(Debug)]
 pub struct MeshEntry {
@@ -894,3 +3425,204 @@ pub struct MeshEntry {
     pub submeshes: Vec<SubmeshEntry>,
 }
 pub type SubmeshEntry = SubmeshData;
+
+/// One level of detail of a logical mesh: which mesh of the mesh
+/// group to draw, and how far from the camera it's used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodLevel {
+    /// The mesh number (within the mesh group) for this level
+    pub mesh: usize,
+    /// Instances up to this far from the camera can use this level
+    pub max_distance: f32,
+}
+
+/// Levels of detail for a logical mesh made up of several meshes of a
+/// mesh group, ordered from most to least detailed.  Every frame,
+/// [`Lod::assign`] (or e.g. [`crate::Renderer::mesh_group_apply_lod`])
+/// routes each logical instance to the mesh for its distance from
+/// the camera.  Instances further away than the last level's
+/// `max_distance` aren't drawn at all.
+///
+/// To avoid popping back and forth when an instance hovers around a
+/// switch distance, an instance only changes level once it's
+/// `hysteresis` (a fraction of the switch distance) past it.
+#[derive(Clone, Debug)]
+pub struct Lod {
+    levels: Vec<LodLevel>,
+    hysteresis: f32,
+    // Each logical instance's level as of the last assignment; levels.len() means hidden
+    current: Vec<usize>,
+    // Each level's instances, kept between assignments to reuse their allocations
+    per_level: Vec<Vec<Transform3D>>,
+}
+
+impl Lod {
+    /// Creates a level of detail set from levels sorted by increasing `max_distance`.
+    pub fn new(levels: Vec<LodLevel>, hysteresis: f32) -> Self {
+        assert!(!levels.is_empty(), "Lod needs at least one level");
+        assert!(
+            levels
+                .windows(2)
+                .all(|w| w[0].max_distance <= w[1].max_distance),
+            "Lod levels must be sorted by increasing max_distance"
+        );
+        Self {
+            levels,
+            hysteresis,
+            current: vec![],
+            per_level: vec![],
+        }
+    }
+    /// Returns the levels of detail.
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+    fn level_for(&self, previous: Option<usize>, dist: f32) -> usize {
+        let hidden = self.levels.len();
+        let target = self
+            .levels
+            .iter()
+            .position(|l| dist <= l.max_distance)
+            .unwrap_or(hidden);
+        let Some(previous) = previous else {
+            return target;
+        };
+        // Stay at the previous level while within its (widened) band
+        let near = if previous == 0 {
+            0.0
+        } else {
+            self.levels[previous - 1].max_distance * (1.0 - self.hysteresis)
+        };
+        let far = self
+            .levels
+            .get(previous)
+            .map_or(f32::INFINITY, |l| l.max_distance * (1.0 + self.hysteresis));
+        if (near..=far).contains(&dist) {
+            previous
+        } else {
+            target
+        }
+    }
+    /// Chooses a level of detail for each of `instances` based on its
+    /// distance from `camera_pos`, and calls `write` once per level
+    /// with that level's mesh number and the instances using it.
+    pub fn assign(
+        &mut self,
+        camera_pos: [f32; 3],
+        instances: &[Transform3D],
+        mut write: impl FnMut(usize, &[Transform3D]),
+    ) {
+        // If the instances changed, forget their previous levels
+        let remembered = self.current.len() == instances.len();
+        self.current.resize(instances.len(), 0);
+        self.per_level.resize_with(self.levels.len(), Vec::new);
+        for level_instances in &mut self.per_level {
+            level_instances.clear();
+        }
+        for (idx, inst) in instances.iter().enumerate() {
+            let dist = (0..3)
+                .map(|i| (inst.translation[i] - camera_pos[i]).powi(2))
+                .sum::<f32>()
+                .sqrt();
+            let level = self.level_for(remembered.then(|| self.current[idx]), dist);
+            if let Some(level_instances) = self.per_level.get_mut(level) {
+                level_instances.push(*inst);
+            }
+            self.current[idx] = level;
+        }
+        for (level, level_instances) in self.levels.iter().zip(&self.per_level) {
+            write(level.mesh, level_instances);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lod() -> Lod {
+        Lod::new(
+            vec![
+                LodLevel {
+                    mesh: 3,
+                    max_distance: 10.0,
+                },
+                LodLevel {
+                    mesh: 5,
+                    max_distance: 20.0,
+                },
+            ],
+            0.1,
+        )
+    }
+    // The mesh each instance (placed at distance x along the x axis) was routed to
+    fn assign(lod: &mut Lod, xs: &[f32]) -> Vec<Option<usize>> {
+        let instances: Vec<_> = xs
+            .iter()
+            .map(|&x| Transform3D {
+                translation: [x, 0.0, 0.0],
+                ..Transform3D::ZERO
+            })
+            .collect();
+        let mut meshes = vec![None; xs.len()];
+        let mut writes = vec![];
+        lod.assign([0.0; 3], &instances, |mesh, insts| {
+            writes.push(mesh);
+            for inst in insts {
+                let idx = xs.iter().position(|&x| x == inst.translation[0]).unwrap();
+                meshes[idx] = Some(mesh);
+            }
+        });
+        // Every level is written every time, even if it's empty
+        assert_eq!(writes, [3, 5]);
+        meshes
+    }
+
+    #[test]
+    fn test_lod_initial_levels() {
+        let mut lod = lod();
+        assert_eq!(
+            assign(&mut lod, &[0.0, 10.0, 10.5, 20.0, 20.5]),
+            [Some(3), Some(3), Some(5), Some(5), None]
+        );
+    }
+    #[test]
+    fn test_lod_hysteresis() {
+        let mut lod = lod();
+        // Moving out past the switch distance stays detailed until 10% past it
+        let steps = [
+            (9.0, Some(3)),
+            (10.5, Some(3)),
+            (11.0, Some(3)),
+            (11.5, Some(5)),
+            // And coming back in stays coarse until 10% inside it
+            (9.5, Some(5)),
+            (9.0, Some(5)),
+            (8.9, Some(3)),
+            // Likewise around the draw distance
+            (15.0, Some(5)),
+            (21.5, Some(5)),
+            (22.5, None),
+            (19.0, None),
+            (17.5, Some(5)),
+        ];
+        for (x, mesh) in steps {
+            assert_eq!(assign(&mut lod, &[x]), [mesh], "at {x}");
+        }
+    }
+    #[test]
+    fn test_lod_jumps_and_resets() {
+        let mut lod = lod();
+        assign(&mut lod, &[5.0, 15.0]);
+        // Jumping clear past a band switches straight to the right level
+        assert_eq!(assign(&mut lod, &[30.0, 1.0]), [None, Some(3)]);
+        // Instances just past a switch distance keep their levels...
+        assert_eq!(assign(&mut lod, &[10.5, 9.5]), [Some(5), Some(3)]);
+        assert_eq!(assign(&mut lod, &[9.5, 10.5]), [Some(5), Some(3)]);
+        // ...until the number of instances changes
+        assert_eq!(
+            assign(&mut lod, &[9.5, 10.5, 1.0]),
+            [Some(3), Some(5), Some(3)]
+        );
+    }
+}
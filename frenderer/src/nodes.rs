@@ -0,0 +1,291 @@
+//! Self-contained render nodes for embedding frenderer's renderers
+//! into other engines (e.g. ones built around a render graph)
+//! without a [`crate::Renderer`].  Each node owns one renderer,
+//! declares which resources it reads and writes, and records its
+//! drawing into a caller-supplied encoder and targets.
+
+use crate::colorgeo::ColorGeo;
+use crate::meshes::{Camera3D, FlatRenderer, MeshRenderer};
+use crate::sprites::{Camera2D, SpriteRenderer};
+use crate::WGPU;
+
+/// A resource a [`RenderNode`] reads or writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeSlot {
+    /// A color target view
+    Color,
+    /// A depth target view in [`crate::Renderer::DEPTH_FORMAT`]
+    Depth,
+    /// A 2D camera, set with e.g. [`SpriteNode::set_camera`]
+    Camera2D,
+    /// A 3D camera, set with e.g. [`MeshNode::set_camera`]
+    Camera3D,
+    /// A texture sampled by the node, set with e.g. [`PostNode::set_source`]
+    Texture,
+}
+
+/// The views a [`RenderNode`] draws into, and whether to clear them first.
+pub struct NodeTargets<'a> {
+    /// The color target
+    pub color: &'a wgpu::TextureView,
+    /// What to do with the color target's existing contents
+    pub color_load: wgpu::LoadOp<wgpu::Color>,
+    /// The depth target, for nodes which write [`NodeSlot::Depth`]
+    pub depth: Option<&'a wgpu::TextureView>,
+    /// What to do with the depth target's existing contents
+    pub depth_load: wgpu::LoadOp<f32>,
+}
+
+impl<'a> NodeTargets<'a> {
+    /// Targets which keep their existing contents.
+    pub fn new(color: &'a wgpu::TextureView, depth: Option<&'a wgpu::TextureView>) -> Self {
+        Self {
+            color,
+            color_load: wgpu::LoadOp::Load,
+            depth,
+            depth_load: wgpu::LoadOp::Load,
+        }
+    }
+    /// Clear the color target to `color` and the depth target to
+    /// the far plane before drawing.
+    pub fn cleared(self, color: wgpu::Color) -> Self {
+        Self {
+            color_load: wgpu::LoadOp::Clear(color),
+            depth_load: wgpu::LoadOp::Clear(1.0),
+            ..self
+        }
+    }
+    fn begin_pass<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        label: &str,
+        needs_depth: bool,
+    ) -> wgpu::RenderPass<'e> {
+        let depth = if needs_depth {
+            Some(
+                self.depth
+                    .unwrap_or_else(|| panic!("{label} needs a depth target")),
+            )
+        } else {
+            None
+        };
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.color,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: self.color_load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: depth.map(|view| wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(wgpu::Operations {
+                    load: self.depth_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+/// A renderer that can be run as a step of a larger frame.
+pub trait RenderNode {
+    /// A name for the node, e.g. for debugging a render graph
+    fn name(&self) -> &'static str;
+    /// The resources the node reads
+    fn inputs(&self) -> &'static [NodeSlot];
+    /// The resources the node writes
+    fn outputs(&self) -> &'static [NodeSlot];
+    /// Records the node's drawing into `encoder` in its own render
+    /// pass.  Panics if `targets` lacks a depth view the node needs.
+    fn run(&self, encoder: &mut wgpu::CommandEncoder, targets: &NodeTargets);
+}
+
+/// Draws every sprite group of a [`SpriteRenderer`].
+pub struct SpriteNode {
+    renderer: SpriteRenderer,
+}
+
+impl SpriteNode {
+    /// Creates a node drawing into the given color target and depth format.
+    pub fn new(
+        gpu: &WGPU,
+        color_target: wgpu::ColorTargetState,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            renderer: SpriteRenderer::new(gpu, color_target, depth_format),
+        }
+    }
+    /// The node's renderer, for adding and updating sprite groups.
+    pub fn renderer(&self) -> &SpriteRenderer {
+        &self.renderer
+    }
+    /// The node's renderer, for adding and updating sprite groups.
+    pub fn renderer_mut(&mut self) -> &mut SpriteRenderer {
+        &mut self.renderer
+    }
+    /// Sets the camera of every sprite group.
+    pub fn set_camera(&mut self, gpu: &WGPU, camera: Camera2D) {
+        self.renderer.set_camera_all(gpu, camera);
+    }
+}
+
+impl RenderNode for SpriteNode {
+    fn name(&self) -> &'static str {
+        "frenderer:sprites"
+    }
+    fn inputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Camera2D]
+    }
+    fn outputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Color, NodeSlot::Depth]
+    }
+    fn run(&self, encoder: &mut wgpu::CommandEncoder, targets: &NodeTargets) {
+        let mut rpass = targets.begin_pass(encoder, self.name(), true);
+        self.renderer.render(&mut rpass, ..);
+    }
+}
+
+/// Draws every mesh group of a [`MeshRenderer`].
+pub struct MeshNode {
+    renderer: MeshRenderer,
+}
+
+impl MeshNode {
+    /// Creates a node drawing into the given color target and depth format.
+    pub fn new(
+        gpu: &WGPU,
+        color_target: wgpu::ColorTargetState,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            renderer: MeshRenderer::new(gpu, color_target, depth_format),
+        }
+    }
+    /// The node's renderer, for adding and updating mesh groups.
+    pub fn renderer(&self) -> &MeshRenderer {
+        &self.renderer
+    }
+    /// The node's renderer, for adding and updating mesh groups.
+    pub fn renderer_mut(&mut self) -> &mut MeshRenderer {
+        &mut self.renderer
+    }
+    /// Sets the camera.
+    pub fn set_camera(&mut self, gpu: &WGPU, camera: Camera3D) {
+        self.renderer.set_camera(gpu, camera);
+    }
+}
+
+impl RenderNode for MeshNode {
+    fn name(&self) -> &'static str {
+        "frenderer:meshes"
+    }
+    fn inputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Camera3D]
+    }
+    fn outputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Color, NodeSlot::Depth]
+    }
+    fn run(&self, encoder: &mut wgpu::CommandEncoder, targets: &NodeTargets) {
+        let mut rpass = targets.begin_pass(encoder, self.name(), true);
+        self.renderer.render(&mut rpass, ..);
+    }
+}
+
+/// Draws every mesh group of a [`FlatRenderer`].
+pub struct FlatNode {
+    renderer: FlatRenderer,
+}
+
+impl FlatNode {
+    /// Creates a node drawing into the given color target and depth format.
+    pub fn new(
+        gpu: &WGPU,
+        color_target: wgpu::ColorTargetState,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            renderer: FlatRenderer::new(gpu, color_target, depth_format),
+        }
+    }
+    /// The node's renderer, for adding and updating mesh groups.
+    pub fn renderer(&self) -> &FlatRenderer {
+        &self.renderer
+    }
+    /// The node's renderer, for adding and updating mesh groups.
+    pub fn renderer_mut(&mut self) -> &mut FlatRenderer {
+        &mut self.renderer
+    }
+    /// Sets the camera.
+    pub fn set_camera(&mut self, gpu: &WGPU, camera: Camera3D) {
+        self.renderer.set_camera(gpu, camera);
+    }
+}
+
+impl RenderNode for FlatNode {
+    fn name(&self) -> &'static str {
+        "frenderer:flats"
+    }
+    fn inputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Camera3D]
+    }
+    fn outputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Color, NodeSlot::Depth]
+    }
+    fn run(&self, encoder: &mut wgpu::CommandEncoder, targets: &NodeTargets) {
+        let mut rpass = targets.begin_pass(encoder, self.name(), true);
+        self.renderer.render(&mut rpass, ..);
+    }
+}
+
+/// Applies [`ColorGeo`] postprocessing to a source texture.
+pub struct PostNode {
+    post: ColorGeo,
+}
+
+impl PostNode {
+    /// Creates a node postprocessing `source` through `lut` into the given color target.
+    pub fn new(
+        gpu: &WGPU,
+        source: &wgpu::Texture,
+        lut: &wgpu::Texture,
+        color_target: wgpu::ColorTargetState,
+    ) -> Self {
+        Self {
+            post: ColorGeo::new(gpu, source, lut, color_target),
+        }
+    }
+    /// The node's postprocessor, for changing its parameters.
+    pub fn post(&self) -> &ColorGeo {
+        &self.post
+    }
+    /// The node's postprocessor, for changing its parameters.
+    pub fn post_mut(&mut self) -> &mut ColorGeo {
+        &mut self.post
+    }
+    /// Replaces the texture being postprocessed.
+    pub fn set_source(&mut self, gpu: &WGPU, source: &wgpu::Texture) {
+        self.post.replace_color_texture(gpu, source);
+    }
+}
+
+impl RenderNode for PostNode {
+    fn name(&self) -> &'static str {
+        "frenderer:postprocess"
+    }
+    fn inputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Texture]
+    }
+    fn outputs(&self) -> &'static [NodeSlot] {
+        &[NodeSlot::Color]
+    }
+    fn run(&self, encoder: &mut wgpu::CommandEncoder, targets: &NodeTargets) {
+        let mut rpass = targets.begin_pass(encoder, self.name(), false);
+        self.post.render(&mut rpass);
+    }
+}
@@ -0,0 +1,532 @@
+//! Particle effects described as data, so they can be tweaked
+//! without recompiling.  An [`EffectDesc`] lists emitters, each with
+//! a shape, an emission rate curve and bursts, particle lifetimes and
+//! velocities, and size and color over each particle's life.  With
+//! the `serde` feature, descriptions can be loaded from any format
+//! serde supports (`frapp` can load them as assets).
+//!
+//! A [`ParticleEffect`] is a running instance of a description.
+//! Call [`ParticleEffect::update`] every frame and write its
+//! particles into a sprite group with [`ParticleEffect::write_sprites`],
+//! e.g. through [`crate::SpriteGroupWriter::append_with`].
+
+use crate::rng::TickRng;
+use crate::sprites::{SheetRegion, Transform};
+
+/// A value which changes over time, given as `[time, value]` keys
+/// sorted by time and linearly interpolated between.  Times are
+/// usually fractions from 0.0 to 1.0 of some span, like a particle's
+/// life; before the first key or after the last, the nearest key's
+/// value holds.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Curve {
+    /// The `[time, value]` keys, sorted by time
+    pub keys: Vec<[f32; 2]>,
+}
+
+impl Curve {
+    /// A curve which is always `value`.
+    pub fn constant(value: f32) -> Self {
+        Self {
+            keys: vec![[0.0, value]],
+        }
+    }
+    /// The curve's value at time `t`, or 0.0 if it has no keys.
+    pub fn sample(&self, t: f32) -> f32 {
+        let (weight, a, b) = lerp_keys(&self.keys, t, |k| k[0]);
+        a.map_or(0.0, |a| a[1] + (b.unwrap_or(a)[1] - a[1]) * weight)
+    }
+}
+
+/// A color which changes over time, given as `(time, color)` keys
+/// sorted by time and linearly interpolated between, like a [`Curve`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Gradient {
+    /// The `(time, color)` keys, sorted by time
+    pub keys: Vec<(f32, [u8; 4])>,
+}
+
+impl Gradient {
+    /// A gradient which is always `color`.
+    pub fn constant(color: [u8; 4]) -> Self {
+        Self {
+            keys: vec![(0.0, color)],
+        }
+    }
+    /// The gradient's color at time `t`, or transparent black if it has no keys.
+    pub fn sample(&self, t: f32) -> [u8; 4] {
+        let (weight, a, b) = lerp_keys(&self.keys, t, |k| k.0);
+        a.map_or([0; 4], |a| {
+            let b = b.unwrap_or(a);
+            std::array::from_fn(|i| {
+                (a.1[i] as f32 + (b.1[i] as f32 - a.1[i] as f32) * weight).round() as u8
+            })
+        })
+    }
+}
+
+// The keys on either side of `t` and how far `t` is from the first to the second
+fn lerp_keys<K>(keys: &[K], t: f32, time: impl Fn(&K) -> f32) -> (f32, Option<&K>, Option<&K>) {
+    let after = keys.partition_point(|k| time(k) <= t);
+    match (after.checked_sub(1).map(|i| &keys[i]), keys.get(after)) {
+        (Some(a), Some(b)) => {
+            let span = time(b) - time(a);
+            let weight = if span > 0.0 {
+                (t - time(a)) / span
+            } else {
+                0.0
+            };
+            (weight, Some(a), Some(b))
+        }
+        (Some(a), None) => (0.0, Some(a), None),
+        (None, b) => (0.0, b, None),
+    }
+}
+
+/// The area an emitter spawns particles in, centered on the effect's position.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EmitterShape {
+    /// A single point (the default)
+    #[default]
+    Point,
+    /// Anywhere inside a circle of this radius
+    Circle(f32),
+    /// Anywhere inside a rectangle of this width and height
+    Rect(f32, f32),
+    /// Anywhere on a horizontal line of this length
+    Line(f32),
+}
+
+/// A number of particles spawned all at once.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Burst {
+    /// When to spawn them, in seconds since the emitter (or its loop) started
+    pub time: f32,
+    /// How many to spawn
+    pub count: u32,
+}
+
+/// How one emitter of an [`EffectDesc`] spawns and animates particles.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EmitterDesc {
+    /// The name of the sprite particles are drawn with, looked up
+    /// when a [`ParticleEffect`] is created
+    pub sprite: String,
+    /// Where particles spawn
+    pub shape: EmitterShape,
+    /// How long the emitter emits for, in seconds
+    pub duration: f32,
+    /// Whether the emitter starts over after `duration`
+    pub looping: bool,
+    /// Particles spawned per second over the emitter's duration (from 0.0 to 1.0)
+    pub rate: Curve,
+    /// Groups of particles spawned at particular times
+    pub bursts: Vec<Burst>,
+    /// The least and most seconds a particle lives for
+    pub lifetime: [f32; 2],
+    /// The least and most speed a particle starts with, in world units per second
+    pub speed: [f32; 2],
+    /// The direction particles move in, in radians counterclockwise from the x axis
+    pub direction: f32,
+    /// How far particles' directions may vary either side of `direction`, in radians
+    pub spread: f32,
+    /// Acceleration applied to every particle, in world units per second squared
+    pub gravity: [f32; 2],
+    /// A particle's height in world units over its life (from 0.0
+    /// to 1.0); its width follows the sprite's aspect ratio
+    pub size: Curve,
+    /// The colormod of a particle over its life (from 0.0 to 1.0)
+    pub color: Gradient,
+}
+
+impl Default for EmitterDesc {
+    /// A one-second emitter of ten particles per second, moving up
+    /// at 32 to 64 units per second for a second, 8 units tall.
+    fn default() -> Self {
+        Self {
+            sprite: String::new(),
+            shape: EmitterShape::Point,
+            duration: 1.0,
+            looping: false,
+            rate: Curve::constant(10.0),
+            bursts: vec![],
+            lifetime: [1.0, 1.0],
+            speed: [32.0, 64.0],
+            direction: std::f32::consts::FRAC_PI_2,
+            spread: 0.0,
+            gravity: [0.0, 0.0],
+            size: Curve::constant(8.0),
+            color: Gradient::constant([0; 4]),
+        }
+    }
+}
+
+/// A particle effect made of one or more emitters, see [`ParticleEffect`].
+#[derive(Clone, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EffectDesc {
+    /// The effect's emitters
+    pub emitters: Vec<EmitterDesc>,
+}
+
+struct Emitter {
+    desc: EmitterDesc,
+    region: SheetRegion,
+    // Seconds since the emitter (or its current loop) started
+    time: f32,
+    // Fractional particles owed by the emission rate
+    owed: f32,
+    done: bool,
+}
+
+struct Particle {
+    emitter: usize,
+    pos: [f32; 2],
+    vel: [f32; 2],
+    age: f32,
+    lifetime: f32,
+}
+
+/// A running instance of an [`EffectDesc`].
+pub struct ParticleEffect {
+    /// Where new particles spawn, in world space; particles already
+    /// spawned don't move with it
+    pub position: [f32; 2],
+    /// The depth particles are drawn at
+    pub depth: u16,
+    emitters: Vec<Emitter>,
+    particles: Vec<Particle>,
+    rng: TickRng,
+}
+
+impl ParticleEffect {
+    /// Starts an instance of `desc` at `position`, looking up each
+    /// emitter's sprite by name with `sprite`.  The same `seed` gives
+    /// the same particles.  Fails if a sprite name is unknown.
+    pub fn new(
+        desc: &EffectDesc,
+        position: [f32; 2],
+        depth: u16,
+        seed: u64,
+        sprite: impl Fn(&str) -> Option<SheetRegion>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let emitters = desc
+            .emitters
+            .iter()
+            .map(|desc| {
+                let region = sprite(&desc.sprite)
+                    .ok_or_else(|| format!("Unknown sprite {:?}", desc.sprite))?;
+                Ok(Emitter {
+                    desc: desc.clone(),
+                    region,
+                    time: 0.0,
+                    owed: 0.0,
+                    done: false,
+                })
+            })
+            .collect::<Result<_, Box<dyn std::error::Error>>>()?;
+        Ok(Self {
+            position,
+            depth,
+            emitters,
+            particles: vec![],
+            rng: TickRng::new(seed),
+        })
+    }
+    /// Stops every emitter; particles already spawned live out their lives.
+    pub fn stop(&mut self) {
+        for emitter in self.emitters.iter_mut() {
+            emitter.done = true;
+        }
+    }
+    /// Whether every emitter is done and every particle has died.
+    pub fn is_finished(&self) -> bool {
+        self.particles.is_empty() && self.emitters.iter().all(|e| e.done)
+    }
+    /// Spawns, moves, and ages particles given that `dt` seconds
+    /// have passed since the last update.
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.particles.iter_mut() {
+            let gravity = self.emitters[particle.emitter].desc.gravity;
+            particle.age += dt;
+            for ((vel, pos), accel) in particle
+                .vel
+                .iter_mut()
+                .zip(particle.pos.iter_mut())
+                .zip(gravity)
+            {
+                *vel += accel * dt;
+                *pos += *vel * dt;
+            }
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+        for idx in 0..self.emitters.len() {
+            let count = self.emit(idx, dt);
+            for _ in 0..count {
+                self.spawn(idx);
+            }
+        }
+    }
+    // Advances an emitter's clock, returning how many particles it spawns
+    fn emit(&mut self, idx: usize, dt: f32) -> u32 {
+        let emitter = &mut self.emitters[idx];
+        if emitter.done {
+            return 0;
+        }
+        let duration = emitter.desc.duration.max(f32::EPSILON);
+        let start = emitter.time;
+        let end = start + dt;
+        let rate = emitter.desc.rate.sample(start / duration);
+        emitter.owed += rate.max(0.0) * dt.min(duration - start);
+        let bursts_between = |from: f32, to: f32| -> u32 {
+            emitter
+                .desc
+                .bursts
+                .iter()
+                .filter(|b| b.time >= from && b.time < to)
+                .map(|b| b.count)
+                .sum()
+        };
+        let mut count = bursts_between(start, end.min(duration));
+        if end >= duration {
+            if emitter.desc.looping {
+                // At most one wrap per update
+                emitter.time = (end - duration).min(duration);
+                count += bursts_between(0.0, emitter.time);
+                emitter.owed += rate.max(0.0) * emitter.time;
+            } else {
+                emitter.time = duration;
+                emitter.done = true;
+            }
+        } else {
+            emitter.time = end;
+        }
+        let whole = emitter.owed.floor();
+        emitter.owed -= whole;
+        count + whole as u32
+    }
+    fn spawn(&mut self, emitter: usize) {
+        let desc = &self.emitters[emitter].desc;
+        let rng = &mut self.rng;
+        let offset = match desc.shape {
+            EmitterShape::Point => [0.0, 0.0],
+            EmitterShape::Circle(radius) => {
+                let angle = rng.range_f32(0.0..std::f32::consts::TAU);
+                let dist = radius * rng.next_f32().sqrt();
+                [angle.cos() * dist, angle.sin() * dist]
+            }
+            EmitterShape::Rect(w, h) => [(rng.next_f32() - 0.5) * w, (rng.next_f32() - 0.5) * h],
+            EmitterShape::Line(length) => [(rng.next_f32() - 0.5) * length, 0.0],
+        };
+        let angle = desc.direction + (rng.next_f32() * 2.0 - 1.0) * desc.spread;
+        let speed = lerp(desc.speed, rng.next_f32());
+        let lifetime = lerp(desc.lifetime, rng.next_f32());
+        self.particles.push(Particle {
+            emitter,
+            pos: [self.position[0] + offset[0], self.position[1] + offset[1]],
+            vel: [angle.cos() * speed, angle.sin() * speed],
+            age: 0.0,
+            lifetime,
+        });
+    }
+    /// How many sprites [`ParticleEffect::write_sprites`] needs: one
+    /// per living particle.
+    pub fn sprite_count(&self) -> usize {
+        self.particles.len()
+    }
+    /// Writes a sprite for each living particle into `trfs` and
+    /// `uvs`, returning how many were written.
+    pub fn write_sprites(&self, trfs: &mut [Transform], uvs: &mut [SheetRegion]) -> usize {
+        let mut used = 0;
+        for (particle, (trf, uv)) in self
+            .particles
+            .iter()
+            .zip(trfs.iter_mut().zip(uvs.iter_mut()))
+        {
+            let emitter = &self.emitters[particle.emitter];
+            let life = particle.age / particle.lifetime.max(f32::EPSILON);
+            let region = emitter.region;
+            let h = emitter.desc.size.sample(life).max(0.0);
+            let w = h * region.w.unsigned_abs() as f32 / region.h.unsigned_abs().max(1) as f32;
+            *trf = Transform {
+                w: w as u16,
+                h: h as u16,
+                x: particle.pos[0],
+                y: particle.pos[1],
+                rot: 0.0,
+            };
+            *uv = SheetRegion {
+                depth: self.depth,
+                ..region.with_colormod(emitter.desc.color.sample(life))
+            };
+            used += 1;
+        }
+        used
+    }
+}
+
+fn lerp([lo, hi]: [f32; 2], t: f32) -> f32 {
+    lo + (hi - lo) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(emitter: EmitterDesc) -> ParticleEffect {
+        let desc = EffectDesc {
+            emitters: vec![emitter],
+        };
+        ParticleEffect::new(&desc, [100.0, 50.0], 7, 1, |_| {
+            Some(SheetRegion::rect(0, 0, 16, 8))
+        })
+        .unwrap()
+    }
+    fn sprites(effect: &ParticleEffect) -> (Vec<Transform>, Vec<SheetRegion>) {
+        let mut trfs = vec![Transform::ZERO; effect.sprite_count()];
+        let mut uvs = vec![SheetRegion::ZERO; effect.sprite_count()];
+        assert_eq!(effect.write_sprites(&mut trfs, &mut uvs), trfs.len());
+        (trfs, uvs)
+    }
+    fn bursts(times: &[(f32, u32)]) -> EmitterDesc {
+        EmitterDesc {
+            rate: Curve::constant(0.0),
+            bursts: times
+                .iter()
+                .map(|&(time, count)| Burst { time, count })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_curves() {
+        let curve = Curve {
+            keys: vec![[0.0, 1.0], [0.5, 3.0], [1.0, 2.0]],
+        };
+        assert_eq!(curve.sample(-1.0), 1.0);
+        assert_eq!(curve.sample(0.0), 1.0);
+        assert_eq!(curve.sample(0.25), 2.0);
+        assert_eq!(curve.sample(0.75), 2.5);
+        assert_eq!(curve.sample(1.0), 2.0);
+        assert_eq!(curve.sample(5.0), 2.0);
+        assert_eq!(Curve { keys: vec![] }.sample(0.5), 0.0);
+        let gradient = Gradient {
+            keys: vec![(0.0, [0, 0, 0, 255]), (1.0, [200, 100, 50, 0])],
+        };
+        assert_eq!(gradient.sample(-1.0), [0, 0, 0, 255]);
+        assert_eq!(gradient.sample(0.5), [100, 50, 25, 128]);
+        assert_eq!(gradient.sample(2.0), [200, 100, 50, 0]);
+        assert_eq!(Gradient { keys: vec![] }.sample(0.5), [0; 4]);
+    }
+    #[test]
+    fn test_rate_and_lifetime() {
+        let mut effect = effect(EmitterDesc {
+            rate: Curve::constant(8.0),
+            lifetime: [2.0, 2.0],
+            ..Default::default()
+        });
+        let mut counts = vec![];
+        for _ in 0..14 {
+            effect.update(0.25);
+            counts.push(effect.sprite_count());
+        }
+        // Two particles a step for the emitter's one second, each
+        // living for eight steps
+        assert_eq!(counts, [2, 4, 6, 8, 8, 8, 8, 8, 6, 4, 2, 0, 0, 0]);
+        assert!(effect.is_finished());
+    }
+    #[test]
+    fn test_bursts() {
+        let mut effect = effect(bursts(&[(0.0, 5), (0.5, 3)]));
+        let mut counts = vec![];
+        for _ in 0..4 {
+            effect.update(0.25);
+            counts.push(effect.sprite_count());
+        }
+        assert_eq!(counts, [5, 5, 8, 8]);
+        assert!(!effect.is_finished());
+        effect.update(1.0);
+        assert!(effect.is_finished());
+    }
+    #[test]
+    fn test_looping_and_stop() {
+        let mut effect = effect(EmitterDesc {
+            looping: true,
+            lifetime: [10.0, 10.0],
+            ..bursts(&[(0.0, 1)])
+        });
+        for _ in 0..6 {
+            effect.update(0.5);
+        }
+        assert_eq!(effect.sprite_count(), 3);
+        effect.stop();
+        for _ in 0..6 {
+            effect.update(0.5);
+        }
+        assert_eq!(effect.sprite_count(), 3);
+        assert!(!effect.is_finished());
+        effect.update(10.0);
+        assert!(effect.is_finished());
+    }
+    #[test]
+    fn test_motion_and_appearance() {
+        let mut effect = effect(EmitterDesc {
+            speed: [10.0, 10.0],
+            direction: 0.0,
+            gravity: [0.0, -10.0],
+            size: Curve {
+                keys: vec![[0.0, 8.0], [1.0, 4.0]],
+            },
+            color: Gradient::constant([255, 0, 0, 128]),
+            ..bursts(&[(0.0, 1)])
+        });
+        effect.update(0.5);
+        let (trfs, uvs) = sprites(&effect);
+        assert_eq!(trfs[0].translation(), [100.0, 50.0]);
+        assert_eq!((trfs[0].w, trfs[0].h), (16, 8));
+        assert_eq!(uvs[0].depth, 7);
+        assert_eq!(uvs[0].colormod, [255, 0, 0, 128]);
+        // Particles keep their place when the effect moves
+        effect.position = [0.0, 0.0];
+        effect.update(0.5);
+        let (trfs, _) = sprites(&effect);
+        assert_eq!(trfs[0].translation(), [105.0, 47.5]);
+        assert_eq!((trfs[0].w, trfs[0].h), (12, 6));
+    }
+    #[test]
+    fn test_same_seed_same_particles() {
+        let desc = EmitterDesc {
+            shape: EmitterShape::Circle(20.0),
+            spread: 1.0,
+            lifetime: [0.5, 2.0],
+            ..Default::default()
+        };
+        let (mut a, mut b) = (effect(desc.clone()), effect(desc));
+        for _ in 0..20 {
+            a.update(0.1);
+            b.update(0.1);
+        }
+        let (trfs_a, trfs_b) = (sprites(&a).0, sprites(&b).0);
+        assert!(!trfs_a.is_empty());
+        assert!(trfs_a
+            .iter()
+            .zip(&trfs_b)
+            .all(|(a, b)| a.translation() == b.translation()));
+    }
+    #[test]
+    fn test_unknown_sprite() {
+        let desc = EffectDesc {
+            emitters: vec![EmitterDesc::default()],
+        };
+        assert!(ParticleEffect::new(&desc, [0.0; 2], 0, 1, |_| None).is_err());
+    }
+}
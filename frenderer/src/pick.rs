@@ -0,0 +1,219 @@
+//! CPU-side picking of sprites under a point, e.g. for clicking on
+//! things in 2D.  This needs no readback from the GPU, so it works
+//! the same on every backend including WebGL.
+//!
+//! [`crate::sprites::SpriteRenderer::pick`] finds the topmost sprite
+//! of one group under a world space point, and
+//! [`crate::Renderer::sprite_pick`] finds the topmost sprite of any
+//! group under a surface position like the mouse cursor.  Sprites
+//! are treated as the rotated rectangles their [`Transform`]s
+//! describe, so transparent parts of their images still count.
+//!
+//! Each group keeps a grid of which sprites overlap which cells,
+//! built the first time the group is picked from and rebuilt after
+//! its sprites are accessed mutably, so picking among many sprites
+//! only tests the few near the point.
+
+use crate::sprites::Transform;
+
+/// The sprite found under a point by picking.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SpriteHit {
+    /// The sprite group the sprite is in
+    pub group: usize,
+    /// The sprite's index within its group
+    pub index: usize,
+}
+
+/// Returns whether the rotated rectangle of `trf` contains the
+/// world space `point`.  Hidden sprites (with a zero width or height)
+/// contain nothing.
+pub fn sprite_contains(trf: &Transform, [px, py]: [f32; 2]) -> bool {
+    if trf.w == 0 || trf.h == 0 {
+        return false;
+    }
+    let (dx, dy) = (px - trf.x, py - trf.y);
+    let (sin, cos) = trf.rot.sin_cos();
+    // Rotate the point clockwise into the sprite's unrotated frame
+    let lx = dx * cos + dy * sin;
+    let ly = dy * cos - dx * sin;
+    lx.abs() <= trf.w as f32 * 0.5 && ly.abs() <= trf.h as f32 * 0.5
+}
+
+// The axis-aligned bounds of a sprite's rotated rectangle as
+// [min_x, min_y, max_x, max_y], or None if it's hidden
+fn sprite_bounds(trf: &Transform) -> Option<[f32; 4]> {
+    if trf.w == 0 || trf.h == 0 {
+        return None;
+    }
+    let (sin, cos) = trf.rot.sin_cos();
+    let (hw, hh) = (trf.w as f32 * 0.5, trf.h as f32 * 0.5);
+    let ex = hw * cos.abs() + hh * sin.abs();
+    let ey = hw * sin.abs() + hh * cos.abs();
+    Some([trf.x - ex, trf.y - ey, trf.x + ex, trf.y + ey])
+}
+
+// A uniform grid over a group's sprites, listing for each cell the
+// sprites whose bounds overlap it
+#[derive(Debug)]
+pub(crate) struct PickGrid {
+    origin: [f32; 2],
+    cell: [f32; 2],
+    cols: usize,
+    rows: usize,
+    // Where each cell's sprites start in `sprites`, plus one past the end
+    starts: Vec<u32>,
+    sprites: Vec<u32>,
+}
+
+impl PickGrid {
+    // The most cells along either axis
+    const MAX_CELLS: usize = 256;
+    pub(crate) fn new(trfs: &[Transform]) -> Self {
+        let bounds: Vec<_> = trfs.iter().map(sprite_bounds).collect();
+        let [min_x, min_y, max_x, max_y] = bounds.iter().flatten().fold(
+            [
+                f32::INFINITY,
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+            ],
+            |[a, b, c, d], [x0, y0, x1, y1]| [a.min(*x0), b.min(*y0), c.max(*x1), d.max(*y1)],
+        );
+        let visible = bounds.iter().flatten().count();
+        if visible == 0 {
+            return Self {
+                origin: [0.0; 2],
+                cell: [1.0; 2],
+                cols: 0,
+                rows: 0,
+                starts: vec![0],
+                sprites: vec![],
+            };
+        }
+        // Aim for about one sprite per cell
+        let (w, h) = ((max_x - min_x).max(1.0), (max_y - min_y).max(1.0));
+        let side = (w * h / visible as f32).sqrt();
+        let cols = ((w / side).ceil() as usize).clamp(1, Self::MAX_CELLS);
+        let rows = ((h / side).ceil() as usize).clamp(1, Self::MAX_CELLS);
+        let mut grid = Self {
+            origin: [min_x, min_y],
+            cell: [w / cols as f32, h / rows as f32],
+            cols,
+            rows,
+            starts: vec![0; cols * rows + 1],
+            sprites: vec![],
+        };
+        // Count each cell's sprites, then fill them in
+        for b in bounds.iter().flatten() {
+            for cell in grid.cells_overlapping(b) {
+                grid.starts[cell + 1] += 1;
+            }
+        }
+        for cell in 0..cols * rows {
+            grid.starts[cell + 1] += grid.starts[cell];
+        }
+        grid.sprites = vec![0; grid.starts[cols * rows] as usize];
+        let mut next = grid.starts.clone();
+        for (idx, b) in bounds.iter().enumerate() {
+            let Some(b) = b else { continue };
+            for cell in grid.cells_overlapping(b) {
+                grid.sprites[next[cell] as usize] = idx as u32;
+                next[cell] += 1;
+            }
+        }
+        grid
+    }
+    fn cell_coord(&self, v: f32, axis: usize, count: usize) -> usize {
+        (((v - self.origin[axis]) / self.cell[axis]).floor().max(0.0) as usize).min(count - 1)
+    }
+    fn cells_overlapping(&self, [x0, y0, x1, y1]: &[f32; 4]) -> impl Iterator<Item = usize> {
+        let (c0, c1) = (
+            self.cell_coord(*x0, 0, self.cols),
+            self.cell_coord(*x1, 0, self.cols),
+        );
+        let (r0, r1) = (
+            self.cell_coord(*y0, 1, self.rows),
+            self.cell_coord(*y1, 1, self.rows),
+        );
+        let cols = self.cols;
+        (r0..=r1).flat_map(move |r| (c0..=c1).map(move |c| r * cols + c))
+    }
+    // The sprites whose bounds might contain `point`, in index order
+    pub(crate) fn candidates(&self, [x, y]: [f32; 2]) -> &[u32] {
+        if self.cols == 0
+            || x < self.origin[0]
+            || y < self.origin[1]
+            || x > self.origin[0] + self.cell[0] * self.cols as f32
+            || y > self.origin[1] + self.cell[1] * self.rows as f32
+        {
+            return &[];
+        }
+        let cell = self.cell_coord(y, 1, self.rows) * self.cols + self.cell_coord(x, 0, self.cols);
+        &self.sprites[self.starts[cell] as usize..self.starts[cell + 1] as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprite(x: f32, y: f32, w: u16, h: u16, rot: f32) -> Transform {
+        Transform { w, h, x, y, rot }
+    }
+
+    #[test]
+    fn test_sprite_contains() {
+        let trf = sprite(10.0, 20.0, 8, 4, 0.0);
+        assert!(sprite_contains(&trf, [10.0, 20.0]));
+        assert!(sprite_contains(&trf, [13.9, 21.9]));
+        assert!(!sprite_contains(&trf, [10.0, 22.5]));
+        // A quarter turn swaps the rectangle's width and height
+        let trf = sprite(10.0, 20.0, 8, 4, std::f32::consts::FRAC_PI_2);
+        assert!(sprite_contains(&trf, [10.0, 23.5]));
+        assert!(!sprite_contains(&trf, [13.5, 20.0]));
+        // Hidden sprites contain nothing, not even their centers
+        assert!(!sprite_contains(&sprite(0.0, 0.0, 0, 4, 0.0), [0.0, 0.0]));
+        assert!(!sprite_contains(&sprite(0.0, 0.0, 4, 0, 0.0), [0.0, 0.0]));
+    }
+    #[test]
+    fn test_empty_groups() {
+        let grid = PickGrid::new(&[]);
+        assert!(grid.candidates([0.0, 0.0]).is_empty());
+        let hidden = [sprite(0.0, 0.0, 0, 0, 0.0), sprite(5.0, 5.0, 0, 10, 0.0)];
+        let grid = PickGrid::new(&hidden);
+        assert!(grid.candidates([0.0, 0.0]).is_empty());
+        assert!(grid.candidates([5.0, 5.0]).is_empty());
+    }
+    #[test]
+    fn test_candidates() {
+        let trfs: Vec<_> = (0..100)
+            .map(|i| sprite((i % 10) as f32 * 20.0, (i / 10) as f32 * 20.0, 10, 10, 0.0))
+            .chain([
+                sprite(100.0, 100.0, 0, 0, 0.0),
+                sprite(90.0, 90.0, 30, 30, 0.7),
+            ])
+            .collect();
+        let grid = PickGrid::new(&trfs);
+        for (idx, trf) in trfs.iter().enumerate() {
+            let found = grid.candidates(trf.translation());
+            assert_eq!(
+                found.contains(&(idx as u32)),
+                trf.w != 0,
+                "sprite {idx} at {found:?}"
+            );
+            assert!(found.windows(2).all(|w| w[0] < w[1]));
+        }
+        // Every sprite actually under a point is a candidate
+        for point in [[90.0, 90.0], [100.0, 85.0], [35.0, 144.0]] {
+            let found = grid.candidates(point);
+            for (idx, trf) in trfs.iter().enumerate() {
+                if sprite_contains(trf, point) {
+                    assert!(found.contains(&(idx as u32)), "sprite {idx} at {point:?}");
+                }
+            }
+        }
+        assert!(grid.candidates([-50.0, 0.0]).is_empty());
+        assert!(grid.candidates([0.0, 500.0]).is_empty());
+    }
+}
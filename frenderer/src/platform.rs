@@ -0,0 +1,87 @@
+//! The windowing-library-agnostic parts of frenderer's game loop.
+//! The `winit` feature's [`crate::FrendererEvents`] is built on
+//! these, and games using another windowing library (e.g. SDL2 or
+//! tao) can use them directly: create the renderer's surface with
+//! [`crate::Renderer::create_surface`], translate window events into
+//! [`PlatformEvent`]s for [`crate::Renderer::handle_platform_event`],
+//! and feed input events to an [`crate::input::Input`] via
+//! [`crate::input::Input::press_key`] and friends.
+
+/// Phase in the game event loop
+pub enum EventPhase {
+    /// The game should simulate time forward by the given number of steps and then render.  Typically the caller of `handle_event` or [`crate::Renderer::handle_platform_event`] should respond to this by calling `render` on the [`crate::frenderer::Renderer`].
+    Run(usize),
+    /// The game should terminate as quickly as possible and close the window.
+    Quit,
+    /// There's nothing in particular the game should do right now.
+    Wait,
+}
+
+/// A window event frenderer's game loop cares about, translated
+/// from whatever windowing library is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlatformEvent {
+    /// The window's drawable area changed to this size in physical pixels.
+    Resized {
+        /// The new width
+        width: u32,
+        /// The new height
+        height: u32,
+    },
+    /// The user asked to close the window.
+    CloseRequested,
+    /// It's time to draw a frame, e.g. on vsync or once per trip
+    /// around the application's main loop.  After handling it, check
+    /// [`crate::Renderer::take_redraw_request`] to see whether to
+    /// schedule the next one.
+    RedrawRequested,
+}
+
+impl crate::Renderer {
+    /// Figure out "the right thing to do" for a [`PlatformEvent`],
+    /// resizing the surface or ticking `clock` as needed.  This is
+    /// the windowing-library-agnostic counterpart of
+    /// `FrendererEvents::handle_event`.
+    pub fn handle_platform_event(
+        &mut self,
+        clock: &mut crate::clock::Clock,
+        evt: PlatformEvent,
+    ) -> EventPhase {
+        match evt {
+            PlatformEvent::Resized { width, height } => {
+                self.resize_surface(width, height);
+                EventPhase::Wait
+            }
+            PlatformEvent::CloseRequested => EventPhase::Quit,
+            PlatformEvent::RedrawRequested => EventPhase::Run(clock.tick()),
+        }
+    }
+    /// Returns whether frames are only drawn on demand, see
+    /// [`crate::Renderer::set_render_on_demand`].
+    pub fn render_on_demand(&self) -> bool {
+        self.render_on_demand
+    }
+    /// In render-on-demand mode, the game loop only draws a frame
+    /// when input arrives, the window changes, or the game calls
+    /// [`crate::Renderer::request_redraw`], instead of drawing
+    /// continuously.  This saves power on menus and in tools where
+    /// nothing moves most of the time.  The clock keeps running
+    /// while idle, so the first frame afterwards may simulate up to
+    /// the clock's maximum number of steps.
+    pub fn set_render_on_demand(&mut self, on_demand: bool) {
+        self.render_on_demand = on_demand;
+        self.redraw_requested = true;
+    }
+    /// Asks for another frame to be drawn, e.g. while an animation
+    /// or transition is playing in render-on-demand mode.
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+    /// Returns whether the game loop should schedule another frame,
+    /// clearing any pending [`crate::Renderer::request_redraw`].
+    /// This is always true unless render-on-demand mode is on.  Call
+    /// it once the window's pending events have been handled.
+    pub fn take_redraw_request(&mut self) -> bool {
+        std::mem::take(&mut self.redraw_requested) || !self.render_on_demand
+    }
+}
@@ -0,0 +1,423 @@
+//! CPU-side ray casting against mesh instances, e.g. for clicking on
+//! things in 3D.  Make a ray with
+//! [`crate::meshes::Camera3D::screen_ray`] and pass it to
+//! [`crate::meshes::MeshRenderer::raycast`] or
+//! [`crate::meshes::FlatRenderer::raycast`].  Every mesh's bounding
+//! box is computed when its group is added, so by default rays are
+//! tested against each instance's bounds; after
+//! [`crate::meshes::MeshRenderer::set_pick_triangles`], groups added
+//! from then on also keep a bounding volume hierarchy of their
+//! triangles so that rays hit exactly what's drawn.
+
+use crate::meshes::{MeshEntry, MeshGroup, Ray, Transform3D};
+use ultraviolet::{Rotor3, Vec3};
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates
+    pub min: [f32; 3],
+    /// The corner with the largest coordinates
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// A box containing nothing, which grows to fit whatever it's
+    /// combined with.
+    pub const EMPTY: Self = Self {
+        min: [f32::INFINITY; 3],
+        max: [f32::NEG_INFINITY; 3],
+    };
+    /// The smallest box containing all of `points`.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |bounds, p| {
+            bounds.union(Self { min: p, max: p })
+        })
+    }
+    /// The smallest box containing both boxes.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: std::array::from_fn(|i| self.min[i].min(other.min[i])),
+            max: std::array::from_fn(|i| self.max[i].max(other.max[i])),
+        }
+    }
+    /// Whether the box contains nothing.
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.min[i] > self.max[i])
+    }
+    /// The point in the middle of the box.
+    pub fn center(&self) -> [f32; 3] {
+        std::array::from_fn(|i| (self.min[i] + self.max[i]) / 2.0)
+    }
+    /// How far along `ray` (in multiples of its direction) it enters
+    /// the box, zero if it starts inside, or `None` if it misses.
+    pub fn ray_hit(&self, ray: &Ray) -> Option<f32> {
+        if self.is_empty() {
+            return None;
+        }
+        let (mut near, mut far) = (0.0_f32, f32::INFINITY);
+        for i in 0..3 {
+            let inv = 1.0 / ray.dir[i];
+            let t0 = (self.min[i] - ray.origin[i]) * inv;
+            let t1 = (self.max[i] - ray.origin[i]) * inv;
+            // NaNs (from a ray lying in one of the box's planes) fall
+            // through min/max without changing anything
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+        (near <= far).then_some(near)
+    }
+}
+
+/// Where a ray hit a mesh instance, from e.g.
+/// [`crate::meshes::MeshRenderer::raycast`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// The mesh group that was hit
+    pub group: MeshGroup,
+    /// The mesh number within the group
+    pub mesh: usize,
+    /// The index of the instance within the mesh's instances
+    pub instance: usize,
+    /// How far along the ray the hit is, in multiples of its
+    /// direction (world units for rays from
+    /// [`crate::meshes::Camera3D::screen_ray`])
+    pub distance: f32,
+    /// The world space position of the hit
+    pub point: [f32; 3],
+}
+
+// Triangles per BVH leaf
+const LEAF_SIZE: usize = 4;
+
+// A node of a bounding volume hierarchy; leaves have a nonzero
+// `count` of triangles starting at `first`, while inner nodes'
+// children are at `first` and `first + 1`.
+struct BvhNode {
+    bounds: Aabb,
+    first: u32,
+    count: u32,
+}
+
+// The shape of one mesh in its own space, for hit testing instances
+pub(crate) struct MeshCollider {
+    bounds: Aabb,
+    nodes: Vec<BvhNode>,
+    tris: Vec<[Vec3; 3]>,
+}
+
+impl MeshCollider {
+    // Colliders for each mesh of a group, with triangle hierarchies if `triangles`
+    pub(crate) fn for_group(
+        positions: &[[f32; 3]],
+        indices: &[u32],
+        meshes: &[MeshEntry],
+        triangles: bool,
+    ) -> Vec<Self> {
+        meshes
+            .iter()
+            .map(|mesh| {
+                let tris: Vec<[Vec3; 3]> = mesh
+                    .submeshes
+                    .iter()
+                    .flat_map(|sm| {
+                        indices[sm.indices.start as usize..sm.indices.end as usize]
+                            .chunks_exact(3)
+                            .map(move |tri| {
+                                [tri[0], tri[1], tri[2]].map(|idx| {
+                                    let idx = (idx as i64 + sm.vertex_base as i64) as usize;
+                                    Vec3::from(positions[idx])
+                                })
+                            })
+                    })
+                    .collect();
+                Self::new(tris, triangles)
+            })
+            .collect()
+    }
+    fn new(mut tris: Vec<[Vec3; 3]>, triangles: bool) -> Self {
+        let bounds = Aabb::from_points(tris.iter().flatten().map(|&p| p.into()));
+        if !triangles {
+            return Self {
+                bounds,
+                nodes: vec![],
+                tris: vec![],
+            };
+        }
+        let mut nodes = vec![BvhNode {
+            bounds,
+            first: 0,
+            count: tris.len() as u32,
+        }];
+        build(&mut nodes, &mut tris, 0);
+        Self {
+            bounds,
+            nodes,
+            tris,
+        }
+    }
+    // How far along `ray` it hits an instance with the given
+    // transform and nonuniform scale, if at all
+    pub(crate) fn raycast(&self, ray: &Ray, trf: &Transform3D, scale: [f32; 3]) -> Option<f32> {
+        let scale = Vec3::from(scale) * trf.scale;
+        if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 || self.bounds.is_empty() {
+            // Hidden or removed instances have zero scale
+            return None;
+        }
+        // Bring the ray into the mesh's space, keeping its parameterization
+        let unrotate = Rotor3::from_quaternion_array(trf.rotation).reversed();
+        let origin = unrotate * (Vec3::from(ray.origin) - Vec3::from(trf.translation)) / scale;
+        let dir = unrotate * Vec3::from(ray.dir) / scale;
+        let local = Ray {
+            origin: origin.into(),
+            dir: dir.into(),
+        };
+        let entry = self.bounds.ray_hit(&local)?;
+        if self.nodes.is_empty() {
+            return Some(entry);
+        }
+        let mut best: Option<f32> = None;
+        let mut stack = vec![0_usize];
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            match node.bounds.ray_hit(&local) {
+                Some(t) if best.map_or(true, |b| t < b) => {}
+                _ => continue,
+            }
+            let first = node.first as usize;
+            if node.count == 0 {
+                stack.extend([first, first + 1]);
+                continue;
+            }
+            for tri in &self.tris[first..first + node.count as usize] {
+                if let Some(t) = ray_triangle(origin, dir, tri) {
+                    best = Some(best.map_or(t, |b| b.min(t)));
+                }
+            }
+        }
+        best
+    }
+}
+
+// Splits the node at `idx` (covering its range of `tris`) in half
+// along its longest axis until the leaves are small enough
+fn build(nodes: &mut Vec<BvhNode>, tris: &mut [[Vec3; 3]], idx: usize) {
+    let (first, count) = (nodes[idx].first as usize, nodes[idx].count as usize);
+    if count <= LEAF_SIZE {
+        return;
+    }
+    let centroid = |tri: &[Vec3; 3]| (tri[0] + tri[1] + tri[2]) / 3.0;
+    let range = &mut tris[first..first + count];
+    let spread = Aabb::from_points(range.iter().map(|tri| centroid(tri).into()));
+    let axis = (0..3)
+        .max_by(|&a, &b| {
+            let extent = |i: usize| spread.max[i] - spread.min[i];
+            extent(a).total_cmp(&extent(b))
+        })
+        .unwrap();
+    let half = count / 2;
+    range.select_nth_unstable_by(half, |a, b| centroid(a)[axis].total_cmp(&centroid(b)[axis]));
+    let child = nodes.len();
+    for (start, len) in [(first, half), (first + half, count - half)] {
+        let bounds =
+            Aabb::from_points(tris[start..start + len].iter().flatten().map(|&p| p.into()));
+        nodes.push(BvhNode {
+            bounds,
+            first: start as u32,
+            count: len as u32,
+        });
+    }
+    nodes[idx].first = child as u32;
+    nodes[idx].count = 0;
+    build(nodes, tris, child);
+    build(nodes, tris, child + 1);
+}
+
+// Möller-Trumbore intersection of a ray with either side of a triangle
+fn ray_triangle(origin: Vec3, dir: Vec3, tri: &[Vec3; 3]) -> Option<f32> {
+    let edge1 = tri[1] - tri[0];
+    let edge2 = tri[2] - tri[0];
+    let p = dir.cross(edge2);
+    let det = edge1.dot(p);
+    // The determinant scales with the edges and direction, so compare
+    // it relative to them to keep tiny triangles hittable
+    if det.abs() <= f32::EPSILON * edge1.mag() * edge2.mag() * dir.mag() {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - tri[0];
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = dir.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    (t >= 0.0).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::TickRng;
+
+    fn identity() -> Transform3D {
+        Transform3D {
+            translation: [0.0; 3],
+            scale: 1.0,
+            rotation: Rotor3::identity().into_quaternion_array(),
+        }
+    }
+    fn ray(origin: [f32; 3], dir: [f32; 3]) -> Ray {
+        Ray { origin, dir }
+    }
+    // The twelve triangles of a unit cube centered on the origin
+    fn cube() -> Vec<[Vec3; 3]> {
+        let corner = |i: usize| {
+            Vec3::new(
+                (i & 1) as f32 - 0.5,
+                ((i >> 1) & 1) as f32 - 0.5,
+                ((i >> 2) & 1) as f32 - 0.5,
+            )
+        };
+        let faces = [
+            [0, 1, 3, 2],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 3, 7, 6],
+            [0, 2, 6, 4],
+            [1, 3, 7, 5],
+        ];
+        faces
+            .iter()
+            .flat_map(|&[a, b, c, d]| {
+                [
+                    [corner(a), corner(b), corner(c)],
+                    [corner(a), corner(c), corner(d)],
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cube_hit_and_miss() {
+        for triangles in [false, true] {
+            let cube = MeshCollider::new(cube(), triangles);
+            let trf = identity();
+            let hit = cube.raycast(&ray([0.0, 0.2, -5.0], [0.0, 0.0, 1.0]), &trf, [1.0; 3]);
+            assert_eq!(hit, Some(4.5));
+            assert_eq!(
+                cube.raycast(&ray([2.0, 0.0, -5.0], [0.0, 0.0, 1.0]), &trf, [1.0; 3]),
+                None
+            );
+            assert_eq!(
+                cube.raycast(&ray([0.0, 0.0, -5.0], [0.0, 0.0, -1.0]), &trf, [1.0; 3]),
+                None
+            );
+            // Hidden instances are never hit
+            assert_eq!(
+                cube.raycast(&ray([0.0, 0.0, -5.0], [0.0, 0.0, 1.0]), &trf, [0.0; 3]),
+                None
+            );
+        }
+        // Rays starting inside hit the far side of the triangles
+        let cube = MeshCollider::new(cube(), true);
+        let hit = cube.raycast(&ray([0.0; 3], [1.0, 0.0, 0.0]), &identity(), [1.0; 3]);
+        assert_eq!(hit, Some(0.5));
+    }
+    #[test]
+    fn test_bvh_matches_brute_force() {
+        let mut rng = TickRng::new(7);
+        let mut point = || {
+            Vec3::new(
+                rng.range_f32(-10.0..10.0),
+                rng.range_f32(-10.0..10.0),
+                rng.range_f32(-10.0..10.0),
+            )
+        };
+        let tris: Vec<_> = (0..300)
+            .map(|_| {
+                let center = point();
+                [center, center + point() * 0.1, center + point() * 0.1]
+            })
+            .collect();
+        let collider = MeshCollider::new(tris.clone(), true);
+        assert!(collider.nodes.len() > 1);
+        for _ in 0..200 {
+            let origin = point() * 2.0;
+            let dir = (point() - origin).normalized();
+            let expected = tris
+                .iter()
+                .filter_map(|tri| ray_triangle(origin, dir, tri))
+                .min_by(f32::total_cmp);
+            let found = collider.raycast(&ray(origin.into(), dir.into()), &identity(), [1.0; 3]);
+            assert_eq!(found, expected);
+        }
+    }
+    #[test]
+    fn test_transformed_instance() {
+        let cube = MeshCollider::new(cube(), true);
+        // Scaled by 2 overall and 3 along z, so it's 6 deep
+        let trf = Transform3D {
+            translation: [10.0, 0.0, 0.0],
+            scale: 2.0,
+            ..identity()
+        };
+        let hit = cube.raycast(
+            &ray([10.0, 0.0, -10.0], [0.0, 0.0, 1.0]),
+            &trf,
+            [1.0, 1.0, 3.0],
+        );
+        assert!((hit.unwrap() - 7.0).abs() < 1e-5);
+        assert_eq!(
+            cube.raycast(&ray([0.0, 0.0, -10.0], [0.0, 0.0, 1.0]), &trf, [1.0; 3]),
+            None
+        );
+        // Turned 45 degrees about y, a vertical edge faces the ray
+        let trf = Transform3D {
+            rotation: Rotor3::from_rotation_xz(std::f32::consts::FRAC_PI_4).into_quaternion_array(),
+            ..trf
+        };
+        let hit = cube.raycast(&ray([10.0, 0.0, -10.0], [0.0, 0.0, 1.0]), &trf, [1.0; 3]);
+        assert!((hit.unwrap() - (10.0 - std::f32::consts::SQRT_2)).abs() < 1e-4);
+        // Just past where the unrotated cube would end, the rotated one is still there
+        let hit = cube.raycast(&ray([11.1, 0.0, -10.0], [0.0, 0.0, 1.0]), &trf, [1.0; 3]);
+        assert!(hit.is_some());
+    }
+    #[test]
+    fn test_small_triangles() {
+        let size = 1e-4;
+        let tri = [
+            Vec3::zero(),
+            Vec3::new(size, 0.0, 0.0),
+            Vec3::new(0.0, size, 0.0),
+        ];
+        let origin = Vec3::new(size * 0.25, size * 0.25, -1.0);
+        let hit = ray_triangle(origin, Vec3::unit_z(), &tri);
+        assert!((hit.unwrap() - 1.0).abs() < 1e-6);
+        // Rays in the triangle's plane still miss
+        assert_eq!(ray_triangle(origin, Vec3::unit_x(), &tri), None);
+        let collider = MeshCollider::new(vec![tri], true);
+        let hit = collider.raycast(&ray(origin.into(), [0.0, 0.0, 1.0]), &identity(), [1.0; 3]);
+        assert!(hit.is_some());
+    }
+    #[test]
+    fn test_aabb_ray_hit() {
+        let bounds = Aabb::from_points([[-1.0, -1.0, -1.0], [1.0, 2.0, 1.0]]);
+        assert_eq!(bounds.center(), [0.0, 0.5, 0.0]);
+        assert_eq!(
+            bounds.ray_hit(&ray([0.0, 0.0, -3.0], [0.0, 0.0, 1.0])),
+            Some(2.0)
+        );
+        assert_eq!(bounds.ray_hit(&ray([0.0; 3], [0.0, 1.0, 0.0])), Some(0.0));
+        assert_eq!(
+            bounds.ray_hit(&ray([3.0, 0.0, -3.0], [0.0, 0.0, 1.0])),
+            None
+        );
+        assert!(Aabb::EMPTY.is_empty());
+        assert_eq!(Aabb::EMPTY.ray_hit(&ray([0.0; 3], [1.0, 0.0, 0.0])), None);
+    }
+}
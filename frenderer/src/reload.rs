@@ -0,0 +1,214 @@
+//! Finding the parts of a spritesheet which changed between two
+//! versions of it, so that hot-reloading an atlas only re-uploads
+//! what was edited instead of every layer of the array texture.
+//!
+//! [`diff_layers`] compares the old and new images of each layer in
+//! [`DIFF_TILE`]-pixel square tiles and merges neighboring changed
+//! tiles into a few [`DirtyRect`]s.
+//! [`crate::WGPU::write_texture_rects`] then uploads just those
+//! rectangles, and [`crate::Renderer::reload_array_texture`] does
+//! both.  Textures keep their size and format across a reload; if
+//! those change, create a new texture instead.
+
+/// The width and height in pixels of the tiles images are compared
+/// in.  Smaller tiles find tighter rectangles but make more of them.
+pub const DIFF_TILE: u32 = 32;
+
+/// A rectangle of pixels which differ between two versions of one
+/// layer of an image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DirtyRect {
+    /// The array texture layer the rectangle is in
+    pub layer: u32,
+    /// The left edge of the rectangle in pixels
+    pub x: u32,
+    /// The top edge of the rectangle in pixels
+    pub y: u32,
+    /// The width of the rectangle in pixels
+    pub width: u32,
+    /// The height of the rectangle in pixels
+    pub height: u32,
+}
+
+/// Finds the rectangles which changed between `old` and `new`, two
+/// versions of one layer of an image of the given size with
+/// `bytes_per_pixel` bytes per pixel.  The rectangles don't overlap
+/// and cover every changed pixel (along with some unchanged ones
+/// near them).
+pub fn diff_layer(
+    old: &[u8],
+    new: &[u8],
+    (width, height): (u32, u32),
+    bytes_per_pixel: u32,
+    layer: u32,
+) -> Vec<DirtyRect> {
+    let row_bytes = (width * bytes_per_pixel) as usize;
+    assert_eq!(
+        old.len(),
+        row_bytes * height as usize,
+        "Old image size doesn't match dimensions"
+    );
+    assert_eq!(
+        new.len(),
+        row_bytes * height as usize,
+        "New image size doesn't match dimensions"
+    );
+    let tiles_x = width.div_ceil(DIFF_TILE);
+    let tiles_y = height.div_ceil(DIFF_TILE);
+    let tile_changed = |tx: u32, ty: u32| {
+        let x0 = (tx * DIFF_TILE * bytes_per_pixel) as usize;
+        let x1 = (((tx + 1) * DIFF_TILE).min(width) * bytes_per_pixel) as usize;
+        (ty * DIFF_TILE..((ty + 1) * DIFF_TILE).min(height)).any(|y| {
+            let row = y as usize * row_bytes;
+            old[row + x0..row + x1] != new[row + x0..row + x1]
+        })
+    };
+    // Runs of changed tiles in each row of tiles, as tile ranges;
+    // runs with the same extent in consecutive rows grow downwards
+    let mut open: Vec<(std::ops::Range<u32>, u32)> = vec![];
+    let mut rects = vec![];
+    let to_rect = |(cols, top): (std::ops::Range<u32>, u32), bottom: u32| {
+        let x = cols.start * DIFF_TILE;
+        let y = top * DIFF_TILE;
+        DirtyRect {
+            layer,
+            x,
+            y,
+            width: (cols.end * DIFF_TILE).min(width) - x,
+            height: (bottom * DIFF_TILE).min(height) - y,
+        }
+    };
+    for ty in 0..tiles_y {
+        let mut runs = vec![];
+        let mut tx = 0;
+        while tx < tiles_x {
+            if tile_changed(tx, ty) {
+                let start = tx;
+                while tx < tiles_x && tile_changed(tx, ty) {
+                    tx += 1;
+                }
+                runs.push(start..tx);
+            }
+            tx += 1;
+        }
+        let mut still_open = vec![];
+        for (cols, top) in open.drain(..) {
+            if let Some(idx) = runs.iter().position(|run| *run == cols) {
+                runs.remove(idx);
+                still_open.push((cols, top));
+            } else {
+                rects.push(to_rect((cols, top), ty));
+            }
+        }
+        still_open.extend(runs.into_iter().map(|run| (run, ty)));
+        open = still_open;
+    }
+    rects.extend(open.into_iter().map(|run| to_rect(run, tiles_y)));
+    rects
+}
+
+/// Finds the rectangles which changed in each layer between `old`
+/// and `new`, see [`diff_layer`].  Panics if they don't have the same
+/// number of layers.
+pub fn diff_layers(
+    old: &[&[u8]],
+    new: &[&[u8]],
+    size: (u32, u32),
+    bytes_per_pixel: u32,
+) -> Vec<DirtyRect> {
+    assert_eq!(
+        old.len(),
+        new.len(),
+        "Can't diff images with different numbers of layers"
+    );
+    old.iter()
+        .zip(new.iter())
+        .enumerate()
+        .flat_map(|(layer, (old, new))| diff_layer(old, new, size, bytes_per_pixel, layer as u32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: (u32, u32) = (100, 70);
+
+    fn image() -> Vec<u8> {
+        (0..SIZE.0 * SIZE.1 * 4).map(|i| (i % 251) as u8).collect()
+    }
+    fn with_changes(pixels: &[[u32; 2]]) -> Vec<u8> {
+        let mut img = image();
+        for &[x, y] in pixels {
+            img[((y * SIZE.0 + x) * 4 + 1) as usize] ^= 0xff;
+        }
+        img
+    }
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> DirtyRect {
+        DirtyRect {
+            layer: 0,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    fn sorted(mut rects: Vec<DirtyRect>) -> Vec<DirtyRect> {
+        rects.sort_by_key(|r| (r.layer, r.y, r.x));
+        rects
+    }
+
+    #[test]
+    fn test_identical() {
+        assert!(diff_layer(&image(), &image(), SIZE, 4, 0).is_empty());
+        assert!(diff_layer(&[], &[], (0, 0), 4, 0).is_empty());
+    }
+    #[test]
+    fn test_single_pixel() {
+        let rects = diff_layer(&image(), &with_changes(&[[40, 10]]), SIZE, 4, 0);
+        assert_eq!(rects, [rect(32, 0, 32, 32)]);
+    }
+    #[test]
+    fn test_separate_regions() {
+        let rects = diff_layer(&image(), &with_changes(&[[5, 5], [80, 60]]), SIZE, 4, 0);
+        assert_eq!(sorted(rects), [rect(0, 0, 32, 32), rect(64, 32, 32, 32)]);
+        // Neighboring tiles merge into one rectangle
+        let changes = [[5, 5], [40, 5], [5, 40], [40, 40]];
+        let rects = diff_layer(&image(), &with_changes(&changes), SIZE, 4, 0);
+        assert_eq!(rects, [rect(0, 0, 64, 64)]);
+        // Runs of different widths stay apart and don't overlap
+        let changes = [[5, 5], [40, 5], [5, 40]];
+        let rects = diff_layer(&image(), &with_changes(&changes), SIZE, 4, 0);
+        assert_eq!(sorted(rects), [rect(0, 0, 64, 32), rect(0, 32, 32, 32)]);
+    }
+    #[test]
+    fn test_last_row_and_column() {
+        let rects = diff_layer(&image(), &with_changes(&[[99, 69]]), SIZE, 4, 0);
+        assert_eq!(rects, [rect(96, 64, 4, 6)]);
+        let rects = diff_layer(&image(), &with_changes(&[[99, 0], [0, 69]]), SIZE, 4, 0);
+        assert_eq!(sorted(rects), [rect(96, 0, 4, 32), rect(0, 64, 32, 6)]);
+    }
+    #[test]
+    fn test_layers() {
+        let (old, new) = (image(), with_changes(&[[50, 50]]));
+        let rects = diff_layers(&[&old, &old, &old], &[&old, &new, &old], SIZE, 4);
+        assert_eq!(
+            rects,
+            [DirtyRect {
+                layer: 1,
+                ..rect(32, 32, 32, 32)
+            }]
+        );
+    }
+    #[test]
+    #[should_panic]
+    fn test_mismatched_layer_counts() {
+        let img = image();
+        diff_layers(&[&img, &img], &[&img], SIZE, 4);
+    }
+    #[test]
+    #[should_panic]
+    fn test_mismatched_sizes() {
+        diff_layer(&image(), &image()[4..], SIZE, 4, 0);
+    }
+}
@@ -0,0 +1,133 @@
+//! A listing of the GPU buffers and textures frenderer keeps alive,
+//! for tracking down leaks and memory use.  See
+//! [`crate::Renderer::live_resources`] and
+//! [`crate::Renderer::resource_report`].
+//!
+//! Each resource is listed with the label it was created with, so
+//! the same names show up in graphics debuggers like RenderDoc and in
+//! wgpu's validation errors.  Groups named with e.g.
+//! [`crate::sprites::SpriteRenderer::set_group_name`] include their
+//! names in their resources' labels.
+//!
+//! [`TextureInfo`] describes the texture a sprite or mesh group
+//! draws from, e.g. from [`crate::Renderer::sprite_group_texture_info`].
+
+/// What sort of GPU resource a [`GpuResource`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// A [`wgpu::Buffer`]
+    Buffer,
+    /// A [`wgpu::Texture`]
+    Texture,
+}
+
+/// One live GPU resource.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GpuResource {
+    /// Whether it's a buffer or a texture
+    pub kind: ResourceKind,
+    /// The label it was created with
+    pub label: String,
+    /// Its size in bytes, counting every mip level and array layer
+    /// of textures
+    pub bytes: u64,
+}
+
+impl GpuResource {
+    pub(crate) fn buffer(label: Option<String>, buffer: &wgpu::Buffer) -> Self {
+        Self {
+            kind: ResourceKind::Buffer,
+            label: label.unwrap_or_default(),
+            bytes: buffer.size(),
+        }
+    }
+    pub(crate) fn texture(label: Option<String>, texture: &wgpu::Texture) -> Self {
+        Self {
+            kind: ResourceKind::Texture,
+            label: label.unwrap_or_default(),
+            bytes: texture_bytes(texture),
+        }
+    }
+}
+
+impl std::fmt::Display for GpuResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ResourceKind::Buffer => "buffer",
+            ResourceKind::Texture => "texture",
+        };
+        write!(f, "{kind:<8} {:>12} B  {}", self.bytes, self.label)
+    }
+}
+
+/// The approximate size of a texture in bytes, summing every mip
+/// level and array layer.  Compressed formats count whole blocks.
+pub fn texture_bytes(texture: &wgpu::Texture) -> u64 {
+    let format = texture.format();
+    let (block_w, block_h) = format.block_dimensions();
+    // Depth-stencil formats have no single block size; count both aspects
+    let block_bytes = format.block_copy_size(None).unwrap_or_else(|| {
+        [
+            wgpu::TextureAspect::DepthOnly,
+            wgpu::TextureAspect::StencilOnly,
+        ]
+        .into_iter()
+        .filter_map(|aspect| format.block_copy_size(Some(aspect)))
+        .sum()
+    }) as u64;
+    let size = texture.size();
+    let layers = match texture.dimension() {
+        wgpu::TextureDimension::D3 => 1,
+        _ => size.depth_or_array_layers as u64,
+    };
+    (0..texture.mip_level_count())
+        .map(|mip| {
+            let mip_size = size.mip_level_size(mip, texture.dimension());
+            let blocks_w = mip_size.width.div_ceil(block_w) as u64;
+            let blocks_h = mip_size.height.div_ceil(block_h) as u64;
+            let depth = match texture.dimension() {
+                wgpu::TextureDimension::D3 => mip_size.depth_or_array_layers as u64,
+                _ => 1,
+            };
+            blocks_w * blocks_h * depth * block_bytes
+        })
+        .sum::<u64>()
+        * layers
+        * texture.sample_count() as u64
+}
+
+/// The size, layer count, and format of the texture a sprite or mesh
+/// group draws from, as it was when the group was created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureInfo {
+    /// The width in texels
+    pub width: u32,
+    /// The height in texels
+    pub height: u32,
+    /// How many array layers the group can draw from
+    pub layers: u32,
+    /// The format the group samples the texture as
+    pub format: wgpu::TextureFormat,
+}
+
+impl TextureInfo {
+    /// Describes `texture` as seen through a view made with `view`,
+    /// or through the whole texture if there's no view.
+    pub fn new(texture: &wgpu::Texture, view: Option<&wgpu::TextureViewDescriptor>) -> Self {
+        let layers = texture.depth_or_array_layers().max(1);
+        let (layers, format) = match view {
+            Some(view) => (
+                view.array_layer_count
+                    .unwrap_or(layers.saturating_sub(view.base_array_layer)),
+                view.format.unwrap_or(texture.format()),
+            ),
+            None => (layers, texture.format()),
+        };
+        Self {
+            width: texture.width(),
+            height: texture.height(),
+            layers,
+            format,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! A small seedable random number generator for game logic which
+//! needs to be reproducible, e.g. for replays or lockstep
+//! networking.  Unlike `rand::thread_rng`, a [`TickRng`]'s output
+//! depends only on its seed and the simulation tick it's been set to,
+//! so two runs fed the same inputs make the same random choices.
+//!
+//! Call [`TickRng::set_tick`] at the start of every simulation step
+//! (e.g. with [`crate::clock::Clock::steps`]), or let `frapp` do it
+//! for you.  Because each tick's numbers are derived from the seed
+//! and tick alone, a tick which draws more or fewer numbers than
+//! usual doesn't disturb the ticks after it.  Use [`TickRng::stream`]
+//! to give subsystems their own sequences, so adding a random call
+//! to one doesn't change the numbers another sees.
+//!
+//! With the `serde` feature, a [`TickRng`] can be saved along with
+//! the rest of the game state.
+
+/// A deterministic random number generator tied to the simulation tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TickRng {
+    seed: u64,
+    tick: u64,
+    stream: u64,
+    state: u64,
+}
+
+// SplitMix64's increment and output mixing function
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl TickRng {
+    /// Creates a generator with the given seed, at tick 0.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self {
+            seed,
+            tick: 0,
+            stream: 0,
+            state: 0,
+        };
+        rng.set_tick(0);
+        rng
+    }
+    /// The seed this generator was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// The tick this generator was last set to.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+    /// Restarts the sequence for the given simulation tick.
+    pub fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+        self.state = mix(self.seed ^ mix(tick.wrapping_add(mix(self.stream))));
+    }
+    /// Moves on to the next simulation tick.
+    pub fn next_tick(&mut self) {
+        self.set_tick(self.tick.wrapping_add(1));
+    }
+    /// A generator for a separate sequence of numbers, e.g. for one
+    /// subsystem, at the same seed and tick.  The same `id` always
+    /// gives the same sequence.
+    pub fn stream(&self, id: u64) -> Self {
+        let mut rng = Self {
+            stream: mix(self.stream ^ id.wrapping_add(GOLDEN_GAMMA)),
+            ..*self
+        };
+        rng.set_tick(self.tick);
+        rng
+    }
+    /// A uniformly random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+    /// A uniformly random `u32`.
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    /// A uniformly random `f32` in `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+    /// A random `f32` in the given range.
+    pub fn range_f32(&mut self, range: std::ops::Range<f32>) -> f32 {
+        range.start + self.next_f32() * (range.end - range.start)
+    }
+    /// A random `i32` in the given range, which must not be empty.
+    pub fn range_i32(&mut self, range: std::ops::Range<i32>) -> i32 {
+        assert!(range.start < range.end, "Empty range {range:?}");
+        let span = range.end.abs_diff(range.start) as u64;
+        range.start.wrapping_add(self.below(span) as i32)
+    }
+    /// A random index less than `len`, which must not be zero.
+    pub fn index(&mut self, len: usize) -> usize {
+        assert!(len > 0, "Can't pick an index of an empty collection");
+        self.below(len as u64) as usize
+    }
+    /// Returns true with the given probability.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+    /// A random element of `items`, or `None` if it's empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.index(items.len())])
+    }
+    /// Shuffles `items` in place.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.index(i + 1));
+        }
+    }
+    // A uniformly random number below `bound`, without modulo bias
+    fn below(&mut self, bound: u64) -> u64 {
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let wide = self.next_u64() as u128 * bound as u128;
+            if (wide as u64) >= threshold {
+                return (wide >> 64) as u64;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn draws(rng: &mut TickRng) -> Vec<u64> {
+        (0..8).map(|_| rng.next_u64()).collect()
+    }
+    #[test]
+    fn test_same_seed_and_tick_repeat() {
+        let (mut a, mut b) = (TickRng::new(7), TickRng::new(7));
+        a.set_tick(42);
+        b.set_tick(42);
+        assert_eq!(draws(&mut a), draws(&mut b));
+        // Restarting a tick replays it no matter how much was drawn
+        a.set_tick(42);
+        b.set_tick(42);
+        assert_eq!(draws(&mut a), draws(&mut b));
+        assert_ne!(draws(&mut TickRng::new(8)), draws(&mut TickRng::new(7)));
+    }
+    #[test]
+    fn test_ticks_are_independent() {
+        let mut a = TickRng::new(3);
+        a.next_u64();
+        a.next_tick();
+        let mut b = TickRng::new(3);
+        (0..100).for_each(|_| {
+            b.next_u64();
+        });
+        b.next_tick();
+        assert_eq!(draws(&mut a), draws(&mut b));
+    }
+    #[test]
+    fn test_streams() {
+        let rng = TickRng::new(11);
+        assert_eq!(draws(&mut rng.stream(1)), draws(&mut rng.stream(1)));
+        assert_ne!(draws(&mut rng.stream(1)), draws(&mut rng.stream(2)));
+        let mut main = rng;
+        assert_ne!(draws(&mut rng.stream(1)), draws(&mut main));
+    }
+    #[test]
+    fn test_ranges() {
+        let mut rng = TickRng::new(5);
+        for _ in 0..1000 {
+            let f = rng.next_f32();
+            assert!((0.0..1.0).contains(&f));
+            assert!((-3..4).contains(&rng.range_i32(-3..4)));
+            assert!(rng.index(5) < 5);
+        }
+        // The widest range doesn't overflow
+        rng.range_i32(i32::MIN..i32::MAX);
+        assert_eq!(rng.range_i32(9..10), 9);
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+    }
+    #[test]
+    fn test_choose_and_shuffle() {
+        let mut rng = TickRng::new(1);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+        assert_eq!(rng.choose(&[4]), Some(&4));
+        let mut items: Vec<_> = (0..32).collect();
+        rng.shuffle(&mut items);
+        let mut sorted = items.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..32).collect::<Vec<_>>());
+        rng.shuffle::<u8>(&mut []);
+    }
+    #[test]
+    #[should_panic]
+    fn test_empty_range_panics() {
+        TickRng::new(0).range_i32(3..3);
+    }
+}
@@ -0,0 +1,65 @@
+//! PNG encoding for captured frames and one-call screenshot saving
+//! and copying (with the `screenshot` feature).
+//! [`Renderer::save_screenshot`] and [`Renderer::copy_screenshot`]
+//! are handy to bind to a debug key in your game loop.
+
+use crate::clipboard::Clipboard;
+use crate::{CapturedFrame, Renderer};
+use image::ImageEncoder;
+use std::path::{Path, PathBuf};
+
+impl CapturedFrame {
+    /// Encodes this frame as a PNG image.
+    pub fn encode_png(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png).write_image(
+            &self.rgba,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        )?;
+        Ok(png)
+    }
+    /// Encodes this frame as a PNG image and writes it to the given path.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.encode_png()?)?;
+        Ok(())
+    }
+    /// Puts this frame on the system clipboard as an image, returning
+    /// whether that worked.  Needs the `clipboard` feature on native
+    /// platforms and isn't supported on the web.
+    pub fn copy_to_clipboard(&self, clipboard: &mut Clipboard) -> bool {
+        clipboard.set_image(self.width, self.height, &self.rgba)
+    }
+}
+
+impl Renderer {
+    /// Captures the most recently rendered frame (see
+    /// [`Renderer::capture_frame`]) and saves it as a PNG in the
+    /// given directory with a timestamped filename, returning the
+    /// path of the new file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_screenshot(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+        let path = dir.as_ref().join(format!(
+            "screenshot-{}-{:03}.png",
+            now.as_secs(),
+            now.subsec_millis()
+        ));
+        self.capture_frame()?.save_png(&path)?;
+        Ok(path)
+    }
+    /// Captures the most recently rendered frame (see
+    /// [`Renderer::capture_frame`]) and puts it on the system
+    /// clipboard, returning whether both worked.  Needs the
+    /// `clipboard` feature.  [`crate::input::Input::copy_image`] does
+    /// the same with the input's clipboard.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn copy_screenshot(&self, clipboard: &mut Clipboard) -> bool {
+        self.capture_frame()
+            .is_ok_and(|frame| frame.copy_to_clipboard(clipboard))
+    }
+}
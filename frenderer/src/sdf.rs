@@ -0,0 +1,173 @@
+//! Signed distance fields of spritesheets' alpha channels, for soft
+//! drop shadows, glows, and outlines drawn by
+//! [`crate::sprites::SpriteRenderer::set_sdf_effect`] without
+//! pre-baked shadow art.
+//!
+//! [`alpha_sdf`] turns an RGBA image into one byte per pixel: 128 at
+//! the edge of the opaque parts (alpha of at least one half), rising
+//! to 255 `spread` pixels inside them and falling to 0 `spread`
+//! pixels outside.  Upload every layer of a spritesheet this way as
+//! an `R8Unorm` array texture the same size as the sheet (see
+//! [`crate::Renderer::create_sdf_texture`]).
+//!
+//! Distances are measured across the whole sheet, so leave at least
+//! `spread` pixels of transparent padding around each sprite to keep
+//! neighbors' shapes out of each other's effects.
+
+/// Computes the signed distance field of an RGBA8 image's alpha
+/// channel, one byte per pixel; see the [module docs](self).
+pub fn alpha_sdf(rgba: &[u8], width: u32, height: u32, spread: u32) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    assert_eq!(rgba.len(), w * h * 4, "Image size doesn't match dimensions");
+    let inside: Vec<bool> = rgba.chunks_exact(4).map(|px| px[3] >= 128).collect();
+    // Squared distances to the nearest inside pixel, and to the nearest outside one
+    let to_inside = squared_distances(&inside, w, h, true);
+    let to_outside = squared_distances(&inside, w, h, false);
+    let spread = spread.max(1) as f32;
+    inside
+        .iter()
+        .zip(to_inside.iter().zip(to_outside.iter()))
+        .map(|(&is_inside, (&d_in, &d_out))| {
+            // The edge lies half a pixel beyond the last pixel on either side
+            let dist = if is_inside {
+                -(d_out.sqrt() - 0.5)
+            } else {
+                d_in.sqrt() - 0.5
+            };
+            ((0.5 - dist / (2.0 * spread)).clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+// The squared distance from each pixel to the nearest pixel whose
+// insideness is `target`, by Felzenszwalb and Huttenlocher's
+// separable distance transform
+fn squared_distances(inside: &[bool], w: usize, h: usize, target: bool) -> Vec<f32> {
+    const FAR: f32 = 1e20;
+    let mut grid: Vec<f32> = inside
+        .iter()
+        .map(|&px| if px == target { 0.0 } else { FAR })
+        .collect();
+    let mut line = vec![0.0; w.max(h)];
+    let mut out = vec![0.0; w.max(h)];
+    for x in 0..w {
+        for y in 0..h {
+            line[y] = grid[y * w + x];
+        }
+        transform_line(&line[..h], &mut out[..h]);
+        for y in 0..h {
+            grid[y * w + x] = out[y];
+        }
+    }
+    for y in 0..h {
+        transform_line(&grid[y * w..(y + 1) * w], &mut out[..w]);
+        grid[y * w..(y + 1) * w].copy_from_slice(&out[..w]);
+    }
+    grid
+}
+
+// The 1D squared distance transform of `f` into `out`: the lower
+// envelope of the parabolas rooted at each sample
+fn transform_line(f: &[f32], out: &mut [f32]) {
+    let n = f.len();
+    if n == 0 {
+        return;
+    }
+    let mut roots = vec![0usize; n];
+    let mut bounds = vec![0.0f32; n + 1];
+    let mut k = 0;
+    bounds[0] = f32::NEG_INFINITY;
+    bounds[1] = f32::INFINITY;
+    for q in 1..n {
+        let intersect = |r: usize| {
+            ((f[q] + (q * q) as f32) - (f[r] + (r * r) as f32)) / (2.0 * (q as f32 - r as f32))
+        };
+        let mut s = intersect(roots[k]);
+        while s <= bounds[k] {
+            k -= 1;
+            s = intersect(roots[k]);
+        }
+        k += 1;
+        roots[k] = q;
+        bounds[k] = s;
+        bounds[k + 1] = f32::INFINITY;
+    }
+    k = 0;
+    for (q, out) in out.iter_mut().enumerate() {
+        while bounds[k + 1] < q as f32 {
+            k += 1;
+        }
+        let d = q as f32 - roots[k] as f32;
+        *out = d * d + f[roots[k]];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(w: usize, h: usize, opaque: impl Fn(usize, usize) -> bool) -> Vec<u8> {
+        (0..w * h)
+            .flat_map(|i| {
+                let alpha = if opaque(i % w, i / w) { 255 } else { 0 };
+                [255, 255, 255, alpha]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_uniform_images() {
+        let clear = alpha_sdf(&image(5, 3, |_, _| false), 5, 3, 4);
+        assert!(clear.iter().all(|&d| d == 0));
+        let solid = alpha_sdf(&image(5, 3, |_, _| true), 5, 3, 4);
+        assert!(solid.iter().all(|&d| d == 255));
+        assert!(alpha_sdf(&[], 0, 0, 4).is_empty());
+    }
+    #[test]
+    fn test_single_pixel() {
+        let sdf = alpha_sdf(&image(9, 9, |x, y| x == 4 && y == 4), 9, 9, 4);
+        let at = |x: usize, y: usize| sdf[y * 9 + x];
+        // Half a pixel inside the edge, then falling off with distance
+        assert_eq!(at(4, 4), 143);
+        assert_eq!(at(5, 4), 112);
+        assert_eq!(at(4, 3), 112);
+        assert_eq!(at(5, 5), 98);
+        assert_eq!(at(6, 4), 80);
+        assert_eq!(at(8, 4), 16);
+        assert_eq!(at(0, 0), 0);
+        // The falloff is the same in every direction
+        for (x, y) in [(3, 4), (4, 5), (2, 4), (4, 6), (0, 4), (4, 8), (3, 3)] {
+            assert_eq!(at(x, y), at(8 - x, 8 - y));
+        }
+    }
+    #[test]
+    fn test_edge() {
+        let sdf = alpha_sdf(&image(8, 2, |x, _| x < 4), 8, 2, 3);
+        // The two pixels beside the edge straddle 128 evenly
+        assert_eq!(sdf[3] as u32 + sdf[4] as u32, 255);
+        assert!(sdf[3] > 128 && sdf[4] < 128);
+        assert!(sdf[..8].windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(sdf[..8], sdf[8..]);
+        // Half-transparent pixels count as inside
+        let mut rgba = image(2, 1, |_, _| false);
+        rgba[3] = 128;
+        assert!(alpha_sdf(&rgba, 2, 1, 1)[0] > 128);
+    }
+    #[test]
+    fn test_matches_brute_force() {
+        let (w, h) = (7, 5);
+        let inside: Vec<bool> = (0..w * h).map(|i| (i * 7 + i / 3) % 5 == 0).collect();
+        let distances = squared_distances(&inside, w, h, true);
+        for (i, &d) in distances.iter().enumerate() {
+            let (x, y) = ((i % w) as f32, (i / w) as f32);
+            let nearest = (0..w * h)
+                .filter(|&j| inside[j])
+                .map(|j| {
+                    let (dx, dy) = ((j % w) as f32 - x, (j / w) as f32 - y);
+                    dx * dx + dy * dy
+                })
+                .fold(f32::INFINITY, f32::min);
+            assert_eq!(d, nearest, "pixel {i}");
+        }
+    }
+}
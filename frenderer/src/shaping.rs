@@ -0,0 +1,334 @@
+//! Shaped text from TrueType and OpenType fonts, for scripts a
+//! [`BitFont`] can't draw: right-to-left languages like Arabic and
+//! Hebrew, scripts whose letters join or change shape, and combining
+//! marks.  Needs the `shaping` feature.
+//!
+//! A [`TtfFont`] splits a line into runs of the same direction with
+//! the Unicode bidirectional algorithm, shapes each run with
+//! rustybuzz, and lays the glyphs out from left to right.  Glyphs are
+//! rasterized the first time they're drawn into a region of a
+//! spritesheet set aside for the font, like a [`BitFont`]'s region,
+//! so shaped text is drawn with ordinary sprites alongside other
+//! text.  After drawing, call [`TtfFont::upload`] to copy newly
+//! rasterized glyphs to the spritesheet.
+//!
+//! Sprites are alpha tested, so glyph edges are hard rather than
+//! antialiased; rasterize at about the size text will be drawn.
+//!
+//! [`BitFont`]: crate::bitfont::BitFont
+
+use std::collections::HashMap;
+
+use ab_glyph::{Font, FontVec, PxScale};
+
+use crate::sprites::{SheetRegion, Transform};
+use crate::WGPU;
+
+/// A glyph of a [`ShapedText`], positioned in font units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShapedGlyph {
+    /// The glyph's index in the font
+    pub glyph_id: u16,
+    /// The byte offset in the shaped text of the first character
+    /// this glyph draws
+    pub cluster: u32,
+    /// How far the pen moves right after this glyph
+    pub x_advance: i32,
+    /// How far the glyph is moved right of the pen
+    pub x_offset: i32,
+    /// How far the glyph is moved up from the baseline
+    pub y_offset: i32,
+    /// Whether the glyph has an outline, i.e. isn't whitespace
+    pub visible: bool,
+}
+
+/// A line of text shaped by [`TtfFont::shape`], with its glyphs in
+/// left-to-right visual order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShapedText {
+    glyphs: Vec<ShapedGlyph>,
+}
+
+impl ShapedText {
+    /// The shaped glyphs in left-to-right order.
+    pub fn glyphs(&self) -> &[ShapedGlyph] {
+        &self.glyphs
+    }
+    /// How many sprites drawing this text uses: one per visible glyph.
+    pub fn sprite_count(&self) -> usize {
+        self.glyphs.iter().filter(|g| g.visible).count()
+    }
+}
+
+// Where a rasterized glyph is in the font's region, and where it's
+// drawn relative to the pen on the baseline, in rasterized pixels
+#[derive(Clone, Copy, Debug)]
+struct CachedGlyph {
+    x: u16,
+    y: u16,
+    w: u16,
+    h: u16,
+    left: f32,
+    top: f32,
+}
+
+/// A TrueType or OpenType font which shapes and draws text as
+/// sprites; see the module documentation.
+pub struct TtfFont {
+    font: FontVec,
+    region: SheetRegion,
+    pixel_height: f32,
+    // Rasterized glyphs, or None for ones with no outline or no room
+    glyphs: HashMap<u16, Option<CachedGlyph>>,
+    // The region's RGBA pixels, and whether they changed since the last upload
+    pixels: Vec<u8>,
+    dirty: bool,
+    // The shelf glyphs are being packed into
+    shelf_x: u16,
+    shelf_y: u16,
+    shelf_h: u16,
+    warned_full: bool,
+}
+
+impl TtfFont {
+    /// Loads a font from the bytes of a `.ttf` or `.otf` file.
+    /// Glyphs are rasterized `pixel_height` pixels from the highest
+    /// ascender to the lowest descender into `region`, a part of an
+    /// `Rgba8` spritesheet set aside for them; its colormod is used
+    /// for the text.
+    pub fn new(
+        data: Vec<u8>,
+        region: SheetRegion,
+        pixel_height: f32,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let font = FontVec::try_from_vec(data)?;
+        if rustybuzz::Face::from_slice(font.as_slice(), 0).is_none() {
+            return Err("Font can't be shaped".into());
+        }
+        assert!(pixel_height > 0.0, "Glyphs need a positive pixel height");
+        let (w, h) = (region.w.unsigned_abs(), region.h.unsigned_abs());
+        Ok(Self {
+            font,
+            region,
+            pixel_height,
+            glyphs: HashMap::new(),
+            pixels: vec![0; w as usize * h as usize * 4],
+            dirty: true,
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_h: 0,
+            warned_full: false,
+        })
+    }
+    /// The region of the spritesheet the font's glyphs are rasterized into.
+    pub fn region(&self) -> SheetRegion {
+        self.region
+    }
+    /// Sets the colormod of text drawn from now on.
+    pub fn set_colormod(&mut self, cmod: [u8; 4]) {
+        self.region.colormod = cmod;
+    }
+    /// Splits `text` into runs by direction, shapes each run, and
+    /// returns the glyphs in left-to-right order.  `text` is treated
+    /// as one line; its base direction comes from its first strong
+    /// character.
+    pub fn shape(&self, text: &str) -> ShapedText {
+        // Parsing the face is cheap next to shaping, and saves keeping
+        // a second borrow of the font data around
+        let face = rustybuzz::Face::from_slice(self.font.as_slice(), 0)
+            .expect("Font was checked in TtfFont::new");
+        let mut glyphs = vec![];
+        let bidi = unicode_bidi::BidiInfo::new(text, None);
+        for para in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(para, para.range.clone());
+            for run in runs {
+                let mut buffer = rustybuzz::UnicodeBuffer::new();
+                buffer.push_str(&text[run.clone()]);
+                buffer.set_direction(if levels[run.start].is_rtl() {
+                    rustybuzz::Direction::RightToLeft
+                } else {
+                    rustybuzz::Direction::LeftToRight
+                });
+                buffer.guess_segment_properties();
+                // Glyphs come out in visual order even for right-to-left runs
+                let shaped = rustybuzz::shape(&face, &[], buffer);
+                for (info, pos) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+                    let glyph_id = info.glyph_id as u16;
+                    glyphs.push(ShapedGlyph {
+                        glyph_id,
+                        cluster: run.start as u32 + info.cluster,
+                        x_advance: pos.x_advance,
+                        x_offset: pos.x_offset,
+                        y_offset: pos.y_offset,
+                        visible: face
+                            .glyph_bounding_box(rustybuzz::ttf_parser::GlyphId(glyph_id))
+                            .is_some(),
+                    });
+                }
+            }
+        }
+        ShapedText { glyphs }
+    }
+    /// Like [`crate::bitfont::BitFont::draw_text`], but shapes `text`
+    /// first; `char_height` is the distance from the font's highest
+    /// ascender to its lowest descender.  Uses
+    /// [`ShapedText::sprite_count`] sprites, which can differ from
+    /// the number of characters, so shape the text first with
+    /// [`TtfFont::shape`] to find out how many sprites are needed.
+    /// Returns the bottom right corner of the rendered line and how
+    /// many sprites were used.
+    pub fn draw_text(
+        &mut self,
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+        text: &str,
+        screen_pos: [f32; 2],
+        depth: u16,
+        char_height: f32,
+    ) -> ([f32; 2], usize) {
+        let shaped = self.shape(text);
+        self.draw_shaped(trfs, uvs, &shaped, screen_pos, depth, char_height)
+    }
+    /// Like [`TtfFont::draw_text`], but with text shaped earlier.
+    pub fn draw_shaped(
+        &mut self,
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+        shaped: &ShapedText,
+        screen_pos: [f32; 2],
+        depth: u16,
+        char_height: f32,
+    ) -> ([f32; 2], usize) {
+        let count = shaped.sprite_count();
+        trfs[0..count].fill(Transform::ZERO);
+        uvs[0..count].fill(SheetRegion::ZERO);
+        let units = self.font.height_unscaled();
+        // Font units to screen units, and rasterized pixels to screen units
+        let scale = char_height / units;
+        let raster = char_height / self.pixel_height;
+        let [mut x, top] = screen_pos;
+        let baseline = top - self.font.ascent_unscaled() * scale;
+        let mut used = 0;
+        for glyph in &shaped.glyphs {
+            if glyph.visible {
+                if let Some(cached) = self.rasterize(glyph.glyph_id) {
+                    let (w, h) = (cached.w as f32 * raster, cached.h as f32 * raster);
+                    let left = x + glyph.x_offset as f32 * scale + cached.left * raster;
+                    let glyph_top = baseline + glyph.y_offset as f32 * scale - cached.top * raster;
+                    trfs[used] = Transform {
+                        w: w as u16,
+                        h: h as u16,
+                        x: left + w / 2.0,
+                        y: glyph_top - h / 2.0,
+                        rot: 0.0,
+                    };
+                    uvs[used] = SheetRegion::new(
+                        self.region.sheet,
+                        self.region.x + cached.x,
+                        self.region.y + cached.y,
+                        depth,
+                        cached.w as i16,
+                        cached.h as i16,
+                    )
+                    .with_colormod(self.region.colormod);
+                }
+                // Glyphs which didn't fit leave an empty sprite
+                used += 1;
+            }
+            x += glyph.x_advance as f32 * scale;
+        }
+        ([x, top - char_height], used)
+    }
+    /// Copies glyphs rasterized since the last upload into the font's
+    /// region of `texture`, the spritesheet it was made for.
+    pub fn upload(&mut self, gpu: &WGPU, texture: &wgpu::Texture) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let (w, h) = (
+            self.region.w.unsigned_abs() as u32,
+            self.region.h.unsigned_abs() as u32,
+        );
+        gpu.queue().write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: self.region.x as u32,
+                    y: self.region.y as u32,
+                    z: self.region.sheet as u32,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(w * 4),
+                rows_per_image: Some(h),
+            },
+            wgpu::Extent3d {
+                width: w,
+                height: h,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    // Finds or rasterizes a glyph, returning None if it has no
+    // outline or there's no room left for it
+    fn rasterize(&mut self, glyph_id: u16) -> Option<CachedGlyph> {
+        if let Some(cached) = self.glyphs.get(&glyph_id) {
+            return *cached;
+        }
+        let cached = self.pack(glyph_id);
+        self.glyphs.insert(glyph_id, cached);
+        cached
+    }
+    fn pack(&mut self, glyph_id: u16) -> Option<CachedGlyph> {
+        let glyph = ab_glyph::GlyphId(glyph_id)
+            .with_scale_and_position(PxScale::from(self.pixel_height), ab_glyph::point(0.0, 0.0));
+        let outlined = self.font.outline_glyph(glyph)?;
+        let bounds = outlined.px_bounds();
+        let (w, h) = (bounds.width() as u16, bounds.height() as u16);
+        let (region_w, region_h) = (self.region.w.unsigned_abs(), self.region.h.unsigned_abs());
+        // Pack glyphs in rows with a pixel between them so they don't bleed together
+        if self.shelf_x + w > region_w {
+            self.shelf_x = 0;
+            self.shelf_y += self.shelf_h + 1;
+            self.shelf_h = 0;
+        }
+        if self.shelf_x + w > region_w || self.shelf_y + h > region_h {
+            if !self.warned_full {
+                log::warn!("TtfFont region is full; some glyphs won't be drawn");
+                self.warned_full = true;
+            }
+            return None;
+        }
+        let (x, y) = (self.shelf_x, self.shelf_y);
+        self.shelf_x += w + 1;
+        self.shelf_h = self.shelf_h.max(h);
+        let stride = region_w as usize * 4;
+        let pixels = &mut self.pixels;
+        outlined.draw(|gx, gy, coverage| {
+            let (gx, gy) = (gx as usize + x as usize, gy as usize + y as usize);
+            if gx < region_w as usize && gy < region_h as usize {
+                let idx = gy * stride + gx * 4;
+                pixels[idx..idx + 4].copy_from_slice(&[
+                    255,
+                    255,
+                    255,
+                    (coverage.clamp(0.0, 1.0) * 255.0) as u8,
+                ]);
+            }
+        });
+        self.dirty = true;
+        Some(CachedGlyph {
+            x,
+            y,
+            w,
+            h,
+            left: bounds.min.x,
+            top: bounds.min.y,
+        })
+    }
+}
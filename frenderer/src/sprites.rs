@@ -2,11 +2,22 @@
 //! be independently translated; each layer can have several
 //! spritesheets and numerous sprites.  For efficiency, it's best to
 //! minimize the number of groups.
+//!
+//! Sprite groups can also use 256-color indexed spritesheets with a
+//! palette which can be swapped or cycled at runtime (see
+//! [`SpriteRenderer::add_indexed_sprite_group`]).
+//!
+//! Very large groups of static decoration, like tile layers, can be
+//! stored as half-size [`CompactSprite`]s instead (see
+//! [`SpriteRenderer::add_compact_sprite_group`]).
 
 use std::{borrow::Cow, ops::Range};
 
+use crate::colorgeo::ColorGrade;
+use crate::units::{RenderPx, WorldPos};
 use crate::WGPU;
 use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
 
 /// A SheetRegion defines the visual appearance of a sprite: which spritesheet (of an array of spritesheets), its pixel region within the spritesheet, and its visual depth (larger meaning further away).
 #[repr(C)]
@@ -85,6 +96,33 @@ impl SheetRegion {
     pub const fn with_colormod(self, colormod: [u8; 4]) -> Self {
         Self { colormod, ..self }
     }
+    /// The [`SheetRegion`] covering all of layer `layer` of
+    /// `texture`, e.g. one drawn into with
+    /// [`crate::Renderer::render_to_layer`].
+    pub fn layer(texture: &wgpu::Texture, layer: u16, depth: u16) -> Self {
+        Self::new(
+            layer,
+            0,
+            0,
+            depth,
+            texture.width() as i16,
+            texture.height() as i16,
+        )
+    }
+    /// Splits this region into a grid of `cols` by `rows` equal cells
+    /// and returns the cell at column `col` and row `row` (counting
+    /// from the top left), e.g. to bake several imposters onto one
+    /// layer with [`crate::Renderer::render_to_region`].
+    pub fn grid_cell(self, [cols, rows]: [u16; 2], [col, row]: [u16; 2]) -> Self {
+        let (w, h) = (self.w / cols as i16, self.h / rows as i16);
+        Self {
+            x: (self.x as i32 + w as i32 * col as i32) as u16,
+            y: (self.y as i32 + h as i32 * row as i32) as u16,
+            w,
+            h,
+            ..self
+        }
+    }
 }
 
 /// A Transform describes a location, an extent, and a rotation in 2D
@@ -124,6 +162,365 @@ impl Transform {
     pub fn scale(&self) -> [f32; 2] {
         [self.w as f32, self.h as f32]
     }
+    /// Returns the Transform's translation as a world space position.
+    pub fn world_pos(&self) -> WorldPos {
+        WorldPos([self.x, self.y])
+    }
+    /// Moves the Transform to the given world space position.
+    pub fn set_world_pos(&mut self, WorldPos([x, y]): WorldPos) {
+        self.x = x;
+        self.y = y;
+    }
+    /// Linearly interpolates between this transform (at `t=0.0`) and
+    /// `other` (at `t=1.0`).  Rotations take the shortest way around.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let tau = std::f32::consts::TAU;
+        let drot =
+            (other.rot - self.rot + std::f32::consts::PI).rem_euclid(tau) - std::f32::consts::PI;
+        Self {
+            w: lerp(self.w as f32, other.w as f32).round() as u16,
+            h: lerp(self.h as f32, other.h as f32).round() as u16,
+            x: lerp(self.x, other.x),
+            y: lerp(self.y, other.y),
+            rot: self.rot + drot * t,
+        }
+    }
+}
+
+/// Keeps the previous and current simulated [`Transform`]s of a
+/// sprite group so that rendering can smoothly interpolate between
+/// fixed simulation steps.  Call [`TransformHistory::step`] before
+/// each simulation step, modify [`TransformHistory::current_mut`]
+/// during it, and then before rendering write the interpolated
+/// transforms into the sprite group with
+/// [`TransformHistory::write_interpolated`] using
+/// [`crate::clock::Clock::alpha`].
+#[derive(Clone, Debug, Default)]
+pub struct TransformHistory {
+    prev: Vec<Transform>,
+    current: Vec<Transform>,
+}
+
+impl TransformHistory {
+    /// Creates a history whose previous and current transforms are both `transforms`.
+    pub fn new(transforms: Vec<Transform>) -> Self {
+        Self {
+            prev: transforms.clone(),
+            current: transforms,
+        }
+    }
+    /// Returns how many transforms are tracked.
+    pub fn len(&self) -> usize {
+        self.current.len()
+    }
+    /// Returns true if no transforms are tracked.
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty()
+    }
+    /// Changes the number of tracked transforms; new ones are zeroed.
+    pub fn resize(&mut self, len: usize) {
+        self.prev.resize(len, Transform::ZERO);
+        self.current.resize(len, Transform::ZERO);
+    }
+    /// Remembers the current transforms as the previous ones; call
+    /// this at the start of each simulation step.
+    pub fn step(&mut self) {
+        self.prev.copy_from_slice(&self.current);
+    }
+    /// Moves the transform at `idx` to `trf` without interpolating
+    /// from its old position, e.g. when an object teleports or spawns.
+    pub fn snap(&mut self, idx: usize, trf: Transform) {
+        self.prev[idx] = trf;
+        self.current[idx] = trf;
+    }
+    /// Returns the transforms as of the most recent simulation step.
+    pub fn current(&self) -> &[Transform] {
+        &self.current
+    }
+    /// Returns the (mutable) transforms for the current simulation step.
+    pub fn current_mut(&mut self) -> &mut [Transform] {
+        &mut self.current
+    }
+    /// Returns the transforms as of the simulation step before the most recent one.
+    pub fn previous(&self) -> &[Transform] {
+        &self.prev
+    }
+    /// Writes transforms interpolated between the previous and
+    /// current steps by `alpha` (between 0 and 1) into `out`, which
+    /// is typically obtained from [`crate::Renderer::sprites_mut`].
+    /// Only the first `out.len()` transforms are written.
+    pub fn write_interpolated(&self, alpha: f32, out: &mut [Transform]) {
+        for ((out, prev), current) in out
+            .iter_mut()
+            .zip(self.prev.iter())
+            .zip(self.current.iter())
+        {
+            *out = prev.lerp(current, alpha);
+        }
+    }
+}
+
+/// A fixed-size sprite group whose slots are handed out and taken
+/// back one at a time, for things like projectiles which come and go
+/// constantly.  The group is never resized: spawning takes a free
+/// slot and despawning hides its sprite with a zeroed [`Transform`],
+/// both in constant time.
+///
+/// Changed slots are uploaded together by [`SpritePool::upload`]
+/// (or automatically through [`crate::Renderer::sprite_pool_spawn`]
+/// and friends).  Don't resize the pool's group by other means.
+#[derive(Clone, Debug)]
+pub struct SpritePool {
+    group: usize,
+    // Free slots, with the next one to hand out at the end
+    free: Vec<usize>,
+    live: Vec<bool>,
+    dirty: Option<Range<usize>>,
+}
+
+impl SpritePool {
+    /// Creates a sprite group with room for `capacity` sprites, all
+    /// hidden, and a pool handing out its slots.
+    pub fn new(
+        sprites: &mut SpriteRenderer,
+        gpu: &WGPU,
+        tex: &wgpu::Texture,
+        capacity: usize,
+        camera: Camera2D,
+    ) -> Self {
+        let group = sprites.add_sprite_group(
+            gpu,
+            tex,
+            vec![Transform::ZERO; capacity],
+            vec![SheetRegion::ZERO; capacity],
+            camera,
+        );
+        Self {
+            group,
+            free: (0..capacity).rev().collect(),
+            live: vec![false; capacity],
+            dirty: None,
+        }
+    }
+    /// The sprite group the pool's sprites are in
+    pub fn group(&self) -> usize {
+        self.group
+    }
+    /// The most sprites the pool can hold at once
+    pub fn capacity(&self) -> usize {
+        self.live.len()
+    }
+    /// The number of sprites currently spawned
+    pub fn len(&self) -> usize {
+        self.capacity() - self.free.len()
+    }
+    /// Whether no sprites are spawned
+    pub fn is_empty(&self) -> bool {
+        self.free.len() == self.capacity()
+    }
+    /// Whether every slot is taken
+    pub fn is_full(&self) -> bool {
+        self.free.is_empty()
+    }
+    /// Whether the given slot holds a spawned sprite
+    pub fn is_live(&self, slot: usize) -> bool {
+        self.live.get(slot).copied().unwrap_or(false)
+    }
+    /// The slots holding spawned sprites, in order
+    pub fn live_slots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.live
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, live)| live.then_some(slot))
+    }
+    /// Shows a sprite in a free slot and returns the slot, or returns
+    /// `None` if the pool is full.
+    pub fn spawn(
+        &mut self,
+        sprites: &mut SpriteRenderer,
+        trf: Transform,
+        uv: SheetRegion,
+    ) -> Option<usize> {
+        let slot = self.free.pop()?;
+        self.live[slot] = true;
+        self.write(sprites, slot, trf, uv);
+        Some(slot)
+    }
+    /// Hides the sprite in the given slot and frees the slot, returning
+    /// whether it held a spawned sprite.
+    pub fn despawn(&mut self, sprites: &mut SpriteRenderer, slot: usize) -> bool {
+        if !self.is_live(slot) {
+            return false;
+        }
+        self.live[slot] = false;
+        self.free.push(slot);
+        let uv = sprites.get_sprites(self.group).1[slot];
+        self.write(sprites, slot, Transform::ZERO, uv);
+        true
+    }
+    /// Despawns every sprite.
+    pub fn clear(&mut self, sprites: &mut SpriteRenderer) {
+        for slot in 0..self.capacity() {
+            self.despawn(sprites, slot);
+        }
+    }
+    /// Changes the sprite in a spawned slot.
+    /// Panics if the slot doesn't hold a spawned sprite.
+    pub fn set(
+        &mut self,
+        sprites: &mut SpriteRenderer,
+        slot: usize,
+        trf: Transform,
+        uv: SheetRegion,
+    ) {
+        assert!(self.is_live(slot), "Sprite pool slot {slot} isn't spawned");
+        self.write(sprites, slot, trf, uv);
+    }
+    /// Returns and forgets the range of slots changed since the last
+    /// call, for uploading them.
+    pub fn take_dirty(&mut self) -> Option<Range<usize>> {
+        self.dirty.take()
+    }
+    /// Uploads the slots changed since the last upload.
+    pub fn upload(&mut self, sprites: &mut SpriteRenderer, gpu: &WGPU) {
+        if let Some(range) = self.take_dirty() {
+            sprites.upload_sprites(gpu, self.group, range);
+        }
+    }
+    fn write(
+        &mut self,
+        sprites: &mut SpriteRenderer,
+        slot: usize,
+        trf: Transform,
+        uv: SheetRegion,
+    ) {
+        let (trfs, uvs) = sprites.get_sprites_mut(self.group);
+        trfs[slot] = trf;
+        uvs[slot] = uv;
+        self.dirty = Some(match self.dirty.take() {
+            Some(range) => range.start.min(slot)..range.end.max(slot + 1),
+            None => slot..slot + 1,
+        });
+    }
+}
+
+/// Trail parameters for a sprite group.  When a group has a trail,
+/// each sprite is followed by `copies` translucent "ghost" copies
+/// spaced evenly between its current transform and the transform it
+/// had when it was previously uploaded.  The nearest ghost is drawn
+/// with opacity `alpha`, and each further ghost's opacity is
+/// multiplied by `falloff`.
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, Debug, PartialEq)]
+pub struct Trail {
+    /// How many ghost copies to draw behind each sprite
+    pub copies: u32,
+    /// The opacity of the ghost nearest to the sprite
+    pub alpha: f32,
+    /// The factor by which each successive ghost's opacity is multiplied
+    pub falloff: f32,
+    _padding: u32,
+}
+
+impl Trail {
+    /// Create a new [`Trail`] with the given number of ghost copies, initial opacity, and opacity falloff.
+    pub const fn new(copies: u32, alpha: f32, falloff: f32) -> Self {
+        Self {
+            copies,
+            alpha,
+            falloff,
+            _padding: 0,
+        }
+    }
+}
+
+/// Soft effects drawn behind a sprite group's sprites using a signed
+/// distance field of their spritesheet's alpha channel (see
+/// [`crate::sdf`] and [`SpriteRenderer::set_sdf_effect`]): a drop
+/// shadow, a glow, and an outline, each left out if its color is
+/// fully transparent or its size is zero.  Sizes are in sheet pixels
+/// and can't usefully exceed the `spread` the field was made with.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SdfEffect {
+    /// The spread the distance field was generated with
+    pub spread: f32,
+    /// How far the shadow is offset from the sprite, in world units
+    pub shadow_offset: [f32; 2],
+    /// How far the shadow's edge is blurred
+    pub shadow_softness: f32,
+    /// The shadow's color, with its opacity in alpha
+    pub shadow_color: [f32; 4],
+    /// How far the glow reaches past the sprite's edge
+    pub glow_radius: f32,
+    /// The glow's color at the sprite's edge
+    pub glow_color: [f32; 4],
+    /// The outline's width
+    pub outline_width: f32,
+    /// The outline's color
+    pub outline_color: [f32; 4],
+}
+
+impl SdfEffect {
+    /// An effect drawing nothing, for a distance field generated
+    /// with the given spread.
+    pub fn new(spread: f32) -> Self {
+        Self {
+            spread,
+            ..Self::default()
+        }
+    }
+    /// Adds a drop shadow offset by `offset` world units with edges
+    /// blurred over `softness` sheet pixels.
+    pub fn with_shadow(self, offset: [f32; 2], softness: f32, color: [f32; 4]) -> Self {
+        Self {
+            shadow_offset: offset,
+            shadow_softness: softness,
+            shadow_color: color,
+            ..self
+        }
+    }
+    /// Adds a glow fading out over `radius` sheet pixels.
+    pub fn with_glow(self, radius: f32, color: [f32; 4]) -> Self {
+        Self {
+            glow_radius: radius,
+            glow_color: color,
+            ..self
+        }
+    }
+    /// Adds an outline `width` sheet pixels wide.
+    pub fn with_outline(self, width: f32, color: [f32; 4]) -> Self {
+        Self {
+            outline_width: width,
+            outline_color: color,
+            ..self
+        }
+    }
+    fn uniform(&self) -> SdfUniform {
+        SdfUniform {
+            shadow_color: self.shadow_color,
+            glow_color: self.glow_color,
+            outline_color: self.outline_color,
+            shadow: [
+                self.shadow_offset[0],
+                self.shadow_offset[1],
+                self.shadow_softness,
+                self.spread.max(1.0),
+            ],
+            sizes: [self.glow_radius, self.outline_width, 0.0, 0.0],
+        }
+    }
+}
+
+// SdfEffect as the shader sees it
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod)]
+struct SdfUniform {
+    shadow_color: [f32; 4],
+    glow_color: [f32; 4],
+    outline_color: [f32; 4],
+    shadow: [f32; 4],
+    sizes: [f32; 4],
 }
 
 /// Camera2D is a transform for a sprite layer, defining a scale
@@ -137,6 +534,334 @@ pub struct Camera2D {
     pub screen_size: [f32; 2],
 }
 
+impl Camera2D {
+    /// Convert a position on a surface of the given size (in
+    /// physical pixels with the origin at the top left, like mouse
+    /// and touch positions) into world space.
+    pub fn screen_to_world(&self, screen: [f32; 2], surface_size: [f32; 2]) -> [f32; 2] {
+        [
+            self.screen_pos[0] + screen[0] / surface_size[0] * self.screen_size[0],
+            self.screen_pos[1] + (1.0 - screen[1] / surface_size[1]) * self.screen_size[1],
+        ]
+    }
+    /// Convert a world space position into a position on a surface
+    /// of the given size (in physical pixels with the origin at the
+    /// top left).
+    pub fn world_to_screen(&self, world: [f32; 2], surface_size: [f32; 2]) -> [f32; 2] {
+        [
+            (world[0] - self.screen_pos[0]) / self.screen_size[0] * surface_size[0],
+            (1.0 - (world[1] - self.screen_pos[1]) / self.screen_size[1]) * surface_size[1],
+        ]
+    }
+    /// Convert a position in a rendered image of the given size
+    /// (see [`crate::Renderer::render_size`]) drawn with this camera
+    /// into world space.
+    pub fn render_to_world(&self, px: RenderPx, render_size: [f32; 2]) -> WorldPos {
+        WorldPos(self.screen_to_world(px.0, render_size))
+    }
+    /// Convert a world space position into a position in a rendered
+    /// image of the given size drawn with this camera.
+    pub fn world_to_render(&self, pos: WorldPos, render_size: [f32; 2]) -> RenderPx {
+        RenderPx(self.world_to_screen(pos.0, render_size))
+    }
+    /// Blends from this camera towards `next` by `alpha` (between 0
+    /// and 1), e.g. from the previous simulation step's camera to the
+    /// current one using [`crate::clock::Clock::alpha`].
+    pub fn interpolate(&self, next: &Camera2D, alpha: f32) -> Camera2D {
+        let lerp =
+            |a: [f32; 2], b: [f32; 2]| [a[0] + (b[0] - a[0]) * alpha, a[1] + (b[1] - a[1]) * alpha];
+        Camera2D {
+            screen_pos: lerp(self.screen_pos, next.screen_pos),
+            screen_size: lerp(self.screen_size, next.screen_size),
+        }
+    }
+}
+
+/// How a sprite group interacts with the depth buffer it shares with
+/// other sprite groups and meshes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DepthMode {
+    /// Whether sprites are hidden behind things drawn closer to the screen
+    pub test: bool,
+    /// Whether sprites hide things drawn further from the screen after them
+    pub write: bool,
+}
+
+impl DepthMode {
+    /// Depth tested and written, for sprites in the game world.  This is the default.
+    pub const WORLD: Self = Self {
+        test: true,
+        write: true,
+    };
+    /// Neither depth tested nor written, so sprites are drawn on top
+    /// of everything drawn before them (e.g. UI).  Within the group,
+    /// later sprites are drawn over earlier ones regardless of depth.
+    pub const OVERLAY: Self = Self {
+        test: false,
+        write: false,
+    };
+    // Every mode, in index order
+    const ALL: [Self; 4] = [
+        Self::OVERLAY,
+        Self {
+            test: true,
+            write: false,
+        },
+        Self {
+            test: false,
+            write: true,
+        },
+        Self::WORLD,
+    ];
+    fn index(self) -> usize {
+        self.test as usize | (self.write as usize) << 1
+    }
+    fn depth_stencil_state(self, format: wgpu::TextureFormat) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: self.write,
+            depth_compare: if self.test {
+                wgpu::CompareFunction::Less
+            } else {
+                wgpu::CompareFunction::Always
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
+
+impl Default for DepthMode {
+    fn default() -> Self {
+        Self::WORLD
+    }
+}
+
+/// Derives each sprite's depth from its position, so that e.g. in a
+/// top-down game a character standing above a tree is drawn behind it
+/// and one standing below is drawn in front, without sorting sprites
+/// on the CPU.  The offset is computed in the shader from the
+/// sprite's bottom edge along `axis`, relative to the camera, and
+/// added to its [`SheetRegion::depth`].  Sprites must use a
+/// [`DepthMode`] with depth testing for this to have an effect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct YSort {
+    /// The world space direction in which sprites get further away
+    pub axis: [f32; 2],
+    /// How much [`SheetRegion::depth`] increases per world space unit along `axis`
+    pub scale: f32,
+}
+
+impl Default for YSort {
+    /// Sprites one world unit higher up are one depth unit further back.
+    fn default() -> Self {
+        Self {
+            axis: [0.0, 1.0],
+            scale: 1.0,
+        }
+    }
+}
+
+impl YSort {
+    // The camera uniform's sort parameters; all zero disables sorting
+    fn uniform(y_sort: Option<Self>) -> [f32; 4] {
+        y_sort.map_or([0.0; 4], |ys| [ys.axis[0], ys.axis[1], ys.scale, 0.0])
+    }
+}
+
+/// Makes a sprite group's depths distances from a 3D camera, so
+/// that sprites and meshes sharing the depth buffer hide each other
+/// correctly (e.g. billboards or 2D characters in a 3D scene).  Each
+/// sprite is drawn as if it were [`SheetRegion::depth`] times
+/// `units_per_depth` world units in front of a camera with the given
+/// near and far planes; see [`SpriteRenderer::set_world_depth`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldDepth {
+    /// The near plane distance of the 3D camera
+    pub near: f32,
+    /// The far plane distance of the 3D camera
+    pub far: f32,
+    /// How many world units of distance each unit of [`SheetRegion::depth`] represents
+    pub units_per_depth: f32,
+}
+
+impl WorldDepth {
+    /// Matches the projection of the given mesh camera.
+    pub fn from_camera(camera: &crate::meshes::Camera3D, units_per_depth: f32) -> Self {
+        Self {
+            near: camera.near,
+            far: camera.far,
+            units_per_depth,
+        }
+    }
+    // The camera uniform's world depth parameters; all zero keeps depths as layers
+    fn uniform(world_depth: Option<Self>) -> [f32; 4] {
+        world_depth.map_or([0.0; 4], |wd| [wd.near, wd.far, wd.units_per_depth, 1.0])
+    }
+}
+
+/// How a sprite group samples its texture; see
+/// [`SpriteRenderer::set_sampler`].  Sheet regions may reach past the
+/// edges of their texture layer, and the address modes decide what
+/// shows up there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SpriteSampler {
+    /// Addressing outside the layer horizontally and vertically
+    pub address_mode: [wgpu::AddressMode; 2],
+    /// Filtering when sprites are drawn larger or smaller than their regions
+    pub filter: wgpu::FilterMode,
+}
+
+impl SpriteSampler {
+    /// Nearest-neighbor filtering, clamped to the layer's edges (the default)
+    pub const CLAMP: Self = Self {
+        address_mode: [wgpu::AddressMode::ClampToEdge; 2],
+        filter: wgpu::FilterMode::Nearest,
+    };
+    /// Nearest-neighbor filtering, repeating the layer in every direction
+    pub const REPEAT: Self = Self {
+        address_mode: [wgpu::AddressMode::Repeat; 2],
+        filter: wgpu::FilterMode::Nearest,
+    };
+    /// Produce a new [`SpriteSampler`] with the same addressing but
+    /// the given filter, e.g. `Linear` for smoothly scaled sprites.
+    pub const fn with_filter(self, filter: wgpu::FilterMode) -> Self {
+        Self { filter, ..self }
+    }
+    fn descriptor(self) -> wgpu::SamplerDescriptor<'static> {
+        wgpu::SamplerDescriptor {
+            label: Some("sprites:sampler"),
+            address_mode_u: self.address_mode[0],
+            address_mode_v: self.address_mode[1],
+            mag_filter: self.filter,
+            min_filter: self.filter,
+            ..Default::default()
+        }
+    }
+}
+
+/// A scrolling background made of one sprite covering a camera's
+/// whole view, whose sheet region repeats a texture layer through a
+/// [`SpriteSampler::REPEAT`] sampler instead of needing a sprite per
+/// tile.  The whole layer is repeated, so it should hold just the
+/// background's tile.  See
+/// [`crate::Renderer::sprite_group_add_tiled_background`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TiledBackground {
+    /// The texture layer to repeat
+    pub layer: u16,
+    /// The depth to draw at, as in [`SheetRegion::depth`]
+    pub depth: u16,
+    /// How many world units wide and tall each texel is drawn
+    pub scale: f32,
+    /// How far the texture has scrolled, in texels to the right and
+    /// down the layer; e.g. the camera's position divided by
+    /// `scale` (with y negated), times a parallax factor
+    pub scroll: [f32; 2],
+}
+
+impl Default for TiledBackground {
+    /// Layer 0 at depth 0, one world unit per texel, unscrolled
+    fn default() -> Self {
+        Self {
+            layer: 0,
+            depth: 0,
+            scale: 1.0,
+            scroll: [0.0; 2],
+        }
+    }
+}
+
+impl TiledBackground {
+    /// Returns the sprite covering `camera`'s view with the background
+    /// scrolled into place, for a layer `tile_size` texels wide and
+    /// tall.  Whole texels of scrolling move the sheet region and
+    /// fractions move the sprite, so scrolling stays smooth.
+    pub fn sprite(&self, camera: &Camera2D, tile_size: [u32; 2]) -> (Transform, SheetRegion) {
+        let scale = self.scale.max(f32::EPSILON);
+        let [sx, sy] =
+            [0, 1].map(|axis| self.scroll[axis].rem_euclid(tile_size[axis].max(1) as f32));
+        // One extra texel each way covers the fractional offset
+        let [tw, th] = [0, 1].map(|axis| (camera.screen_size[axis] / scale).ceil() + 1.0);
+        let (w, h) = (tw * scale, th * scale);
+        let left = camera.screen_pos[0] - sx.fract() * scale;
+        let top = camera.screen_pos[1] + camera.screen_size[1] + sy.fract() * scale;
+        (
+            Transform {
+                w: w.round() as u16,
+                h: h.round() as u16,
+                x: left + w / 2.0,
+                y: top - h / 2.0,
+                rot: 0.0,
+            },
+            SheetRegion::new(
+                self.layer, sx as u16, sy as u16, self.depth, tw as i16, th as i16,
+            ),
+        )
+    }
+}
+
+/// A flipbook animation whose frames are consecutive layers of an
+/// array texture rather than regions of one spritesheet.  It only
+/// changes a [`SheetRegion`]'s [`SheetRegion::sheet`], keeping its
+/// rectangle, depth, and color modulation, so every frame should be
+/// drawn at the same place in its layer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerAnimation {
+    /// The layer holding the first frame
+    pub first_layer: u16,
+    /// How many frames there are, one per layer
+    pub frames: u16,
+    /// How many frames are shown per second
+    pub fps: f32,
+    /// Whether to start over after the last frame instead of holding it
+    pub looping: bool,
+}
+
+impl LayerAnimation {
+    /// A looping animation over `frames` layers starting at `first_layer`.
+    pub fn new(first_layer: u16, frames: u16, fps: f32) -> Self {
+        Self {
+            first_layer,
+            frames,
+            fps,
+            looping: true,
+        }
+    }
+    /// Produce a new [`LayerAnimation`] which does or doesn't loop.
+    pub fn with_looping(self, looping: bool) -> Self {
+        Self { looping, ..self }
+    }
+    /// How many seconds one pass through the frames takes.
+    pub fn duration(&self) -> f32 {
+        self.frames as f32 / self.fps
+    }
+    /// The layer shown `time` seconds into the animation.
+    pub fn layer(&self, time: f32) -> u16 {
+        let frames = self.frames.max(1);
+        let frame = (time.max(0.0) * self.fps) as u32;
+        let frame = if self.looping {
+            frame % frames as u32
+        } else {
+            frame.min(frames as u32 - 1)
+        };
+        self.first_layer + frame as u16
+    }
+    /// `region` on the layer shown `time` seconds into the animation.
+    pub fn apply(&self, region: SheetRegion, time: f32) -> SheetRegion {
+        region.with_sheet(self.layer(time))
+    }
+    /// Moves each of `regions` to the layer shown at the matching
+    /// entry of `times`, e.g. every sprite of a group playing the
+    /// same animation with different start times.  Extra regions or
+    /// times are left alone.
+    pub fn apply_all(&self, regions: &mut [SheetRegion], times: &[f32]) {
+        for (region, &time) in regions.iter_mut().zip(times) {
+            region.sheet = self.layer(time);
+        }
+    }
+}
+
 struct SpriteGroup {
     world_buffer: wgpu::Buffer,
     sheet_buffer: wgpu::Buffer,
@@ -144,96 +869,720 @@ struct SpriteGroup {
     sheet_regions: Vec<SheetRegion>,
     camera: Camera2D,
     camera_buffer: wgpu::Buffer,
+    // Whether camera was staged but not yet uploaded, see stage_camera
+    camera_staged: bool,
+    // The view tex_bind_group samples, kept to remake it when renaming
+    tex_view: wgpu::TextureView,
+    texture: crate::resources::TextureInfo,
     tex_bind_group: wgpu::BindGroup,
     sprite_bind_group: wgpu::BindGroup,
+    // A name to include in the group's resource labels, see set_group_name
+    name: Option<String>,
+    trail: Option<TrailData>,
+    sdf: Option<SdfData>,
+    palette: Option<PaletteData>,
+    depth_mode: DepthMode,
+    y_sort: Option<YSort>,
+    world_depth: Option<WorldDepth>,
+    shapes: Vec<SpriteShape>,
+    pixel_perfect: bool,
+    color_grade: ColorGrade,
+    sampler: SpriteSampler,
+    // Which of SpriteRenderer::shader_variants draws this group, if not the default shader
+    shader: Option<usize>,
+    reclaimable: bool,
+    // How many reclaim passes in a row found this group underused
+    underused_frames: u32,
+    // How many frames in a row found this group mostly empty, see SparseWarning
+    sparse_frames: u32,
+    // A compact group's sprites, which live in sheet_buffer; its
+    // world_transforms and sheet_regions stay empty
+    compact: Option<CompactData>,
+    // Built on demand by SpriteRenderer::pick and cleared whenever
+    // world_transforms might change
+    pick_grid: std::sync::OnceLock<crate::pick::PickGrid>,
 }
 
-/// SpriteRenderer hosts a number of sprite groups.  Each group has a
-/// specified spritesheet texture array, parallel vectors of
-/// [`Transform`]s and [`SheetRegion`]s, and a [`Camera2D`] to define
-/// its transform.  All groups render into the same depth
-/// buffer, so their outputs are interleaved.
-pub struct SpriteRenderer {
-    pipeline: wgpu::RenderPipeline,
-    sprite_bind_group_layout: wgpu::BindGroupLayout,
-    texture_bind_group_layout: wgpu::BindGroupLayout,
-    groups: Vec<Option<SpriteGroup>>,
-    free_groups: Vec<usize>,
-    use_storage: bool,
+impl SpriteGroup {
+    // How many sprites the GPU buffers have room for
+    fn capacity(&self) -> usize {
+        self.sheet_buffer.size() as usize / std::mem::size_of::<SheetRegion>()
+    }
+    fn len(&self) -> usize {
+        self.compact
+            .as_ref()
+            .map_or(self.world_transforms.len(), |compact| compact.sprites.len())
+    }
+    // A sprite's depth as the shader computes it, before any WorldDepth mapping
+    fn sprite_depth(&self, index: usize) -> f32 {
+        let depth = self.sheet_regions[index].depth as f32;
+        let Some(ys) = self.y_sort else {
+            return depth;
+        };
+        let trf = &self.world_transforms[index];
+        let center = [
+            trf.x - self.camera.screen_pos[0],
+            trf.y - self.camera.screen_pos[1],
+        ];
+        let feet = center[0] * ys.axis[0] + center[1] * ys.axis[1]
+            - 0.5 * (trf.w as f32 * ys.axis[0].abs() + trf.h as f32 * ys.axis[1].abs());
+        depth + feet * ys.scale
+    }
 }
 
-impl SpriteRenderer {
-    /// Create a new [`SpriteRenderer`] meant to draw into the given color target and with the given depth texture format.
-    pub fn new(
-        gpu: &WGPU,
-        color_target: wgpu::ColorTargetState,
-        depth_format: wgpu::TextureFormat,
-    ) -> Self {
-        let shader = gpu
-            .device()
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("sprites.wgsl"))),
-            });
+/// When [`SpriteRenderer::reclaim`] should shrink a sprite group's
+/// buffers to fit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReclaimPolicy {
+    /// A group is underused when it holds fewer than this fraction
+    /// of the sprites its buffers have room for.
+    pub threshold: f32,
+    /// How many consecutive reclaim passes a group must be underused
+    /// before it's shrunk.
+    pub frames: u32,
+}
 
-        let texture_bind_group_layout =
-            gpu.device()
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
-                    // It needs the first entry for the texture and the second for the sampler.
-                    // This is like defining a type signature.
-                    entries: &[
-                        // The texture binding
-                        wgpu::BindGroupLayoutEntry {
-                            // This matches the binding in the shader
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                            // It's a texture binding
-                            ty: wgpu::BindingType::Texture {
-                                // We can use it with float samplers
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                                // It's being used as a 2D texture
-                                view_dimension: wgpu::TextureViewDimension::D2Array,
-                                // This is not a multisampled texture
-                                multisampled: false,
-                            },
-                            count: None,
-                        },
-                        // The sampler binding
-                        wgpu::BindGroupLayoutEntry {
-                            // This matches the binding in the shader
-                            binding: 1,
-                            // Only available in the fragment shader
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            // It's a sampler
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            // No count
-                            count: None,
-                        },
-                    ],
-                });
-        // The camera binding
-        let camera_layout_entry = wgpu::BindGroupLayoutEntry {
-            // This matches the binding in the shader
-            binding: 0,
-            // Available in vertex shader
-            visibility: wgpu::ShaderStages::VERTEX,
-            // It's a buffer
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            // No count, not a buffer array binding
-            count: None,
-        };
-        let use_storage = gpu.supports_storage();
-        let sprite_bind_group_layout = if use_storage {
-            gpu.device()
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
-                    entries: &[
-                        camera_layout_entry,
+impl Default for ReclaimPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            frames: 300,
+        }
+    }
+}
+
+/// How full a sprite group is, from [`SpriteRenderer::group_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpriteGroupStats {
+    /// Sprites with a nonzero size and sheet region, which are
+    /// actually drawn
+    pub visible: usize,
+    /// The group's size, all of which is uploaded and drawn
+    pub allocated: usize,
+    /// How many sprites the group's GPU buffers have room for
+    pub capacity: usize,
+}
+
+impl SpriteGroupStats {
+    /// The fraction of allocated sprites which are visible, or 1.0 for
+    /// an empty group.
+    pub fn occupancy(&self) -> f32 {
+        if self.allocated == 0 {
+            1.0
+        } else {
+            self.visible as f32 / self.allocated as f32
+        }
+    }
+}
+
+/// When [`SpriteRenderer::warn_sparse_groups`] should log a warning
+/// about a sprite group which is mostly hidden sprites, e.g. because
+/// it was grown to the next power of two and never shrunk.  Every
+/// hidden sprite is still uploaded and drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SparseWarning {
+    /// A group is sparse when fewer than this fraction of its sprites
+    /// are visible.
+    pub threshold: f32,
+    /// How many consecutive frames a group must be sparse before
+    /// it's reported.
+    pub frames: u32,
+    /// Groups smaller than this are never reported.
+    pub min_size: usize,
+}
+
+impl Default for SparseWarning {
+    fn default() -> Self {
+        Self {
+            threshold: 0.25,
+            frames: 600,
+            min_size: 64,
+        }
+    }
+}
+
+/// A sprite of a compact sprite group, in 16 bytes rather than the
+/// 32 of a [`Transform`] and [`SheetRegion`].  Its position is
+/// quantized to steps of its group's [`CompactLayout::quantum`], and
+/// it has no rotation; its sheet, depth, and color modulation are
+/// shared by the whole group.  Make one with [`CompactLayout::compact`].
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, Debug, Default, PartialEq, Eq)]
+pub struct CompactSprite {
+    /// The x coordinate of the center, in quanta from the layout's origin
+    pub x: u16,
+    /// The y coordinate of the center, in quanta from the layout's origin
+    pub y: u16,
+    /// The width in world units, like [`Transform::w`]
+    pub w: u16,
+    /// The height in world units, like [`Transform::h`]
+    pub h: u16,
+    /// Like [`SheetRegion::x`]
+    pub sheet_x: u16,
+    /// Like [`SheetRegion::y`]
+    pub sheet_y: u16,
+    /// Like [`SheetRegion::w`]; may be negative
+    pub sheet_w: i16,
+    /// Like [`SheetRegion::h`]; may be negative
+    pub sheet_h: i16,
+}
+
+/// What a compact sprite group's sprites share: how their quantized
+/// positions map into the world, and the sheet, depth, and color
+/// modulation they're all drawn with.  See
+/// [`SpriteRenderer::add_compact_sprite_group`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompactLayout {
+    /// The world position of quantized position `(0, 0)`
+    pub origin: [f32; 2],
+    /// World units per step of quantized position, so the group
+    /// spans `65535.0 * quantum` units on each axis.  Must be positive.
+    pub quantum: f32,
+    /// Which array texture layer every sprite uses
+    pub sheet: u16,
+    /// The depth of every sprite, like [`SheetRegion::depth`]
+    pub depth: u16,
+    /// The color modulation of every sprite, like [`SheetRegion::colormod`]
+    pub colormod: [u8; 4],
+}
+
+impl Default for CompactLayout {
+    /// Whole world units from the origin, sheet 0, depth 0, no color modulation.
+    fn default() -> Self {
+        Self {
+            origin: [0.0, 0.0],
+            quantum: 1.0,
+            sheet: 0,
+            depth: 0,
+            colormod: [0; 4],
+        }
+    }
+}
+
+impl CompactLayout {
+    /// Packs a sprite into a [`CompactSprite`], rounding its position
+    /// to the nearest quantum within the layout's range.  Its
+    /// rotation and its region's sheet, depth, and color modulation
+    /// are dropped in favor of the layout's.
+    pub fn compact(&self, trf: &Transform, region: &SheetRegion) -> CompactSprite {
+        let quantize = |v: f32, origin: f32| {
+            ((v - origin) / self.quantum)
+                .round()
+                .clamp(0.0, u16::MAX as f32) as u16
+        };
+        CompactSprite {
+            x: quantize(trf.x, self.origin[0]),
+            y: quantize(trf.y, self.origin[1]),
+            w: trf.w,
+            h: trf.h,
+            sheet_x: region.x,
+            sheet_y: region.y,
+            sheet_w: region.w,
+            sheet_h: region.h,
+        }
+    }
+    /// The world position of a compact sprite's center.
+    pub fn position(&self, sprite: &CompactSprite) -> [f32; 2] {
+        [
+            self.origin[0] + sprite.x as f32 * self.quantum,
+            self.origin[1] + sprite.y as f32 * self.quantum,
+        ]
+    }
+}
+
+// A compact group's layout as laid out after the color grade in its uniform buffer
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod)]
+struct CompactUniform {
+    origin: [f32; 2],
+    quantum: f32,
+    _padding: f32,
+    // Packed like a SheetRegion's first eight bytes
+    sheet: u16,
+    depth: u16,
+    colormod: [u8; 4],
+    _padding2: [u32; 2],
+}
+
+impl From<CompactLayout> for CompactUniform {
+    fn from(layout: CompactLayout) -> Self {
+        Self {
+            origin: layout.origin,
+            quantum: layout.quantum,
+            _padding: 0.0,
+            sheet: layout.sheet,
+            depth: layout.depth,
+            colormod: layout.colormod,
+            _padding2: [0; 2],
+        }
+    }
+}
+
+struct CompactData {
+    sprites: Vec<CompactSprite>,
+    layout: CompactLayout,
+}
+
+/// A convex outline of the visible part of a [`SheetRegion`].  Sprites
+/// showing that region are drawn as this polygon instead of a full
+/// quad, which saves fill rate on large, mostly transparent sprites
+/// like particles and effects.  See [`SpriteRenderer::set_sprite_shapes`].
+#[derive(Clone, Debug)]
+pub struct SpriteShape {
+    // The region with flips undone; colormod and depth are ignored
+    region: SheetRegion,
+    // Counterclockwise corners in sprite space, from -0.5 to 0.5 with y up
+    points: Vec<[f32; 2]>,
+}
+
+impl SpriteShape {
+    /// The most corners a shape can have
+    pub const MAX_POINTS: usize = 8;
+    /// A shape for `region` with the given corners, in pixels from
+    /// the region's top left corner.  The corners must form a convex
+    /// polygon (in either winding order) within the region, with 3 to
+    /// [`SpriteShape::MAX_POINTS`] corners.  Sprites showing a
+    /// flipped version of `region` use a flipped version of the shape.
+    pub fn new(
+        region: SheetRegion,
+        corners: &[[f32; 2]],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !(3..=Self::MAX_POINTS).contains(&corners.len()) {
+            return Err(format!(
+                "Sprite shapes need 3 to {} corners, got {}",
+                Self::MAX_POINTS,
+                corners.len()
+            )
+            .into());
+        }
+        let region = Self::unflipped(region);
+        let (w, h) = (region.w as f32, region.h as f32);
+        if w == 0.0 || h == 0.0 {
+            return Err("Sprite shapes need a region with nonzero size".into());
+        }
+        let mut points: Vec<[f32; 2]> = corners
+            .iter()
+            .map(|&[x, y]| [x / w - 0.5, 0.5 - y / h])
+            .collect();
+        if points
+            .iter()
+            .any(|p| p.iter().any(|c| !(-0.5 - 1e-4..=0.5 + 1e-4).contains(c)))
+        {
+            return Err(format!("Sprite shape corners {corners:?} leave the region").into());
+        }
+        let n = points.len();
+        let turns: Vec<f32> = (0..n)
+            .map(|i| {
+                let [a, b, c] = [points[i], points[(i + 1) % n], points[(i + 2) % n]];
+                (b[0] - a[0]) * (c[1] - b[1]) - (b[1] - a[1]) * (c[0] - b[0])
+            })
+            .collect();
+        let area: f32 = (0..n)
+            .map(|i| {
+                let [a, b] = [points[i], points[(i + 1) % n]];
+                a[0] * b[1] - b[0] * a[1]
+            })
+            .sum();
+        if area.abs() < 1e-6 || turns.iter().any(|t| t * area < -1e-6) {
+            return Err(format!("Sprite shape corners {corners:?} aren't convex").into());
+        }
+        // Sprites are drawn with back faces culled
+        if area < 0.0 {
+            points.reverse();
+        }
+        Ok(Self { region, points })
+    }
+    /// The tightest octagon (with horizontal, vertical, and diagonal
+    /// sides) around the pixels of `region` with at least the given
+    /// alpha, in an RGBA8 spritesheet image `width` pixels wide such
+    /// as the data passed to [`crate::Renderer::create_array_texture`].
+    /// Returns `None` if no pixels are that opaque.
+    pub fn from_alpha(
+        region: SheetRegion,
+        image: &[u8],
+        width: u32,
+        threshold: u8,
+    ) -> Option<Self> {
+        let canonical = Self::unflipped(region);
+        // Bounds of x, y, x+y, and x-y over the corners of opaque pixels
+        let mut lo = [f32::INFINITY; 4];
+        let mut hi = [f32::NEG_INFINITY; 4];
+        for py in 0..canonical.h as u32 {
+            for px in 0..canonical.w as u32 {
+                let idx = ((canonical.y as u32 + py) * width + canonical.x as u32 + px) as usize;
+                if image[idx * 4 + 3] < threshold {
+                    continue;
+                }
+                let (x, y) = (px as f32, py as f32);
+                for (i, (low, high)) in [
+                    (x, x + 1.0),
+                    (y, y + 1.0),
+                    (x + y, x + y + 2.0),
+                    (x - y - 1.0, x - y + 1.0),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    lo[i] = lo[i].min(low);
+                    hi[i] = hi[i].max(high);
+                }
+            }
+        }
+        if lo[0] > hi[0] {
+            return None;
+        }
+        // Cut the corners off the bounding rectangle along the diagonals
+        let mut corners = vec![
+            [lo[0], lo[1]],
+            [hi[0], lo[1]],
+            [hi[0], hi[1]],
+            [lo[0], hi[1]],
+        ];
+        for (normal, limit) in [
+            ([-1.0, -1.0], -lo[2]),
+            ([1.0, 1.0], hi[2]),
+            ([-1.0, 1.0], -lo[3]),
+            ([1.0, -1.0], hi[3]),
+        ] {
+            corners = clip_polygon(&corners, normal, limit);
+        }
+        corners.dedup_by(|a, b| (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4);
+        if corners.len() > 1 && corners[0] == corners[corners.len() - 1] {
+            corners.pop();
+        }
+        Self::new(region, &corners).ok()
+    }
+    /// The region this shape is for, with any flips undone
+    pub fn region(&self) -> SheetRegion {
+        self.region
+    }
+    /// The shape's corners in counterclockwise order, in sprite space
+    /// from -0.5 to 0.5 with y pointing up
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+    /// The fraction of the region's area the shape covers
+    pub fn coverage(&self) -> f32 {
+        let n = self.points.len();
+        (0..n)
+            .map(|i| {
+                let [a, b] = [self.points[i], self.points[(i + 1) % n]];
+                a[0] * b[1] - b[0] * a[1]
+            })
+            .sum::<f32>()
+            / 2.0
+    }
+    fn unflipped(region: SheetRegion) -> SheetRegion {
+        let mut region = region;
+        if region.w < 0 {
+            region = region.flip_horizontal();
+        }
+        if region.h < 0 {
+            region = region.flip_vertical();
+        }
+        SheetRegion::new(region.sheet, region.x, region.y, 0, region.w, region.h)
+    }
+}
+
+// The part of a convex polygon where dot(normal, p) <= limit
+fn clip_polygon(points: &[[f32; 2]], normal: [f32; 2], limit: f32) -> Vec<[f32; 2]> {
+    let dist = |p: [f32; 2]| normal[0] * p[0] + normal[1] * p[1] - limit;
+    let mut out = Vec::with_capacity(points.len() + 1);
+    for (i, &a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let (da, db) = (dist(a), dist(b));
+        if da <= 0.0 {
+            out.push(a);
+        }
+        if (da < 0.0) != (db < 0.0) && da != db {
+            let t = da / (da - db);
+            out.push([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+        }
+    }
+    out
+}
+
+// The sprite shapes of a group as laid out after the camera in its uniform buffer
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod)]
+struct ShapeUniform {
+    // The number of shapes, 1 if the group is pixel-perfect, then padding
+    info: [u32; 4],
+    // Each shape's unflipped region: x, y, w, h
+    regions: [[f32; 4]; SpriteRenderer::MAX_SPRITE_SHAPES],
+    // Each shape's sheet and number of corners, then padding
+    sheets: [[u32; 4]; SpriteRenderer::MAX_SPRITE_SHAPES],
+    // Each shape's corners, two per entry
+    points: [[f32; 4]; SpriteRenderer::MAX_SPRITE_SHAPES * SpriteShape::MAX_POINTS / 2],
+}
+
+impl ShapeUniform {
+    fn new(shapes: &[SpriteShape], pixel_perfect: bool) -> Self {
+        let mut uniform = Self::zeroed();
+        uniform.info = [shapes.len() as u32, pixel_perfect as u32, 0, 0];
+        for (i, shape) in shapes.iter().enumerate() {
+            let r = shape.region;
+            uniform.regions[i] = [r.x as f32, r.y as f32, r.w as f32, r.h as f32];
+            uniform.sheets[i] = [r.sheet as u32, shape.points.len() as u32, 0, 0];
+            for (k, p) in shape.points.iter().enumerate() {
+                let entry = &mut uniform.points[i * SpriteShape::MAX_POINTS / 2 + k / 2];
+                entry[(k % 2) * 2..(k % 2) * 2 + 2].copy_from_slice(p);
+            }
+        }
+        uniform
+    }
+}
+
+/// A 256-color palette of sRGB colors for indexed-color sprite groups.
+pub type Palette = [[u8; 4]; 256];
+
+fn palette_from_slice(colors: &[[u8; 4]]) -> Palette {
+    assert!(colors.len() <= 256, "Palettes can have at most 256 colors");
+    let mut palette = [[0; 4]; 256];
+    palette[..colors.len()].copy_from_slice(colors);
+    palette
+}
+
+struct PaletteData {
+    colors: Palette,
+    texture: wgpu::Texture,
+}
+
+struct SdfData {
+    effect: SdfEffect,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+struct TrailData {
+    trail: Trail,
+    trail_buffer: wgpu::Buffer,
+    // The transforms as of the upload before the most recent one
+    prev_buffer: wgpu::Buffer,
+    // A copy of the transforms as of the most recent upload
+    uploaded_transforms: Vec<Transform>,
+    bind_group: wgpu::BindGroup,
+}
+
+// A shader variant registered with
+// SpriteRenderer::register_shader_variant, whose pipelines are made
+// the first time a group uses it
+struct ShaderVariant {
+    name: String,
+    source: String,
+    entry_point: String,
+    pipelines: Option<VariantPipelines>,
+}
+
+// A shader variant's pipelines for regular and compact groups, one per DepthMode
+struct VariantPipelines {
+    plain: [wgpu::RenderPipeline; 4],
+    compact: [wgpu::RenderPipeline; 4],
+}
+
+// Makes one sprite pipeline per DepthMode
+#[allow(clippy::too_many_arguments)]
+fn depth_mode_pipelines(
+    gpu: &WGPU,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    vertex_entry: &str,
+    buffers: &[wgpu::VertexBufferLayout],
+    fragment_entry: &str,
+    color_target: &wgpu::ColorTargetState,
+    depth_format: wgpu::TextureFormat,
+) -> [wgpu::RenderPipeline; 4] {
+    DepthMode::ALL.map(|depth| {
+        gpu.device()
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module,
+                    entry_point: vertex_entry,
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module,
+                    entry_point: fragment_entry,
+                    targets: &[Some(color_target.clone())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(depth.depth_stencil_state(depth_format)),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+    })
+}
+
+/// The bind group layouts of a [`SpriteRenderer`]'s pipelines, from
+/// [`SpriteRenderer::layouts`].  Custom pipelines can use them to
+/// bind sprite groups' bind groups (see
+/// [`SpriteRenderer::group_bind_groups`]) or to make their own bind
+/// groups which frenderer's pipelines accept, without copying the
+/// layouts' definitions.
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteLayouts<'a> {
+    /// Group 0: the camera uniform at binding 0 and, if storage
+    /// buffers are supported, the [`Transform`] and [`SheetRegion`]
+    /// storage buffers at bindings 1 and 2
+    pub sprite: &'a wgpu::BindGroupLayout,
+    /// Group 1: a filterable float `D2Array` texture at binding 0
+    /// and a filtering sampler at binding 1
+    pub texture: &'a wgpu::BindGroupLayout,
+    /// Group 1 of indexed-color groups: a uint `D2Array` texture of
+    /// palette indices at binding 0 and a non-filterable float 2D
+    /// palette texture at binding 1
+    pub indexed_texture: &'a wgpu::BindGroupLayout,
+    /// Group 2 of trails: the trail parameters uniform at binding 0
+    /// and, if storage buffers are supported, the trail points
+    /// storage buffer at binding 1
+    pub trail: &'a wgpu::BindGroupLayout,
+    /// Group 2 of SDF effects: the effect parameters uniform at
+    /// binding 0, a filterable float `D2Array` distance field texture
+    /// at binding 1, and a filtering sampler at binding 2
+    pub sdf: &'a wgpu::BindGroupLayout,
+}
+
+/// SpriteRenderer hosts a number of sprite groups.  Each group has a
+/// specified spritesheet texture array, parallel vectors of
+/// [`Transform`]s and [`SheetRegion`]s, and a [`Camera2D`] to define
+/// its transform.  All groups render into the same depth
+/// buffer, so their outputs are interleaved.
+pub struct SpriteRenderer {
+    // One pipeline of each kind per DepthMode (or per depth test setting for trails)
+    pipelines: [wgpu::RenderPipeline; 4],
+    trail_pipelines: [wgpu::RenderPipeline; 2],
+    sdf_pipelines: [wgpu::RenderPipeline; 2],
+    indexed_pipelines: [wgpu::RenderPipeline; 4],
+    compact_pipelines: [wgpu::RenderPipeline; 4],
+    // Kept to make shader variants' pipelines on demand
+    pipeline_layout: wgpu::PipelineLayout,
+    color_target: wgpu::ColorTargetState,
+    depth_format: wgpu::TextureFormat,
+    shader_variants: Vec<ShaderVariant>,
+    sprite_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    indexed_texture_bind_group_layout: wgpu::BindGroupLayout,
+    trail_bind_group_layout: wgpu::BindGroupLayout,
+    sdf_bind_group_layout: wgpu::BindGroupLayout,
+    sdf_sampler: wgpu::Sampler,
+    groups: Vec<Option<SpriteGroup>>,
+    free_groups: Vec<usize>,
+    use_storage: bool,
+}
+
+impl SpriteRenderer {
+    /// The most [`SpriteShape`]s a sprite group can have
+    pub const MAX_SPRITE_SHAPES: usize = 16;
+    /// The name of the built-in sprite shader, which groups use
+    /// unless given another with [`SpriteRenderer::set_shader_variant`]
+    pub const DEFAULT_SHADER: &'static str = "default";
+    const WORLD_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Transform>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[wgpu::VertexAttribute {
+            // This is a fun little trick, we
+            // lie and say it's four floats.
+            // In the shader the first float
+            // is cast bitwise to a u32 and
+            // then the w and h are masked out
+            // and casted back to f32.
+            format: wgpu::VertexFormat::Float32x4,
+            offset: 0,
+            shader_location: 0,
+        }],
+    };
+    const SHEET_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<SheetRegion>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Uint32x4,
+            offset: 0,
+            shader_location: 1,
+        }],
+    };
+    // Where each group's color grade goes in its camera buffer, after
+    // the camera, y-sort and world depth parameters, and shapes
+    const COLOR_GRADE_OFFSET: u64 =
+        (std::mem::size_of::<Camera2D>() + 32 + std::mem::size_of::<ShapeUniform>()) as u64;
+    // Where a compact group's layout goes, after the color grade
+    const COMPACT_OFFSET: u64 = Self::COLOR_GRADE_OFFSET + std::mem::size_of::<[f32; 16]>() as u64;
+    /// Create a new [`SpriteRenderer`] meant to draw into the given color target and with the given depth texture format.
+    pub fn new(
+        gpu: &WGPU,
+        color_target: wgpu::ColorTargetState,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = gpu.create_shader(wgpu::ShaderModuleDescriptor {
+            label: Some("sprites:shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("sprites.wgsl"))),
+        });
+
+        let texture_bind_group_layout =
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sprites:texture_bgl"),
+                    // It needs the first entry for the texture and the second for the sampler.
+                    // This is like defining a type signature.
+                    entries: &[
+                        // The texture binding
+                        wgpu::BindGroupLayoutEntry {
+                            // This matches the binding in the shader
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                            // It's a texture binding
+                            ty: wgpu::BindingType::Texture {
+                                // We can use it with float samplers
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                // It's being used as a 2D texture
+                                view_dimension: wgpu::TextureViewDimension::D2Array,
+                                // This is not a multisampled texture
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        // The sampler binding
+                        wgpu::BindGroupLayoutEntry {
+                            // This matches the binding in the shader
+                            binding: 1,
+                            // Only available in the fragment shader
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            // It's a sampler
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            // No count
+                            count: None,
+                        },
+                    ],
+                });
+        // The camera binding
+        let camera_layout_entry = wgpu::BindGroupLayoutEntry {
+            // This matches the binding in the shader
+            binding: 0,
+            // Available in vertex shader, and in the fragment shader for the color grade
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            // It's a buffer
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            // No count, not a buffer array binding
+            count: None,
+        };
+        let use_storage = gpu.supports_storage();
+        let sprite_bind_group_layout = if use_storage {
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sprites:sprite_bgl"),
+                    entries: &[
+                        camera_layout_entry,
                         wgpu::BindGroupLayoutEntry {
                             // This matches the binding in the shader
                             binding: 1,
@@ -267,305 +1616,1669 @@ impl SpriteRenderer {
         } else {
             gpu.device()
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
+                    label: Some("sprites:sprite_bgl"),
                     entries: &[camera_layout_entry],
                 })
         };
-        let pipeline_layout =
-            gpu.device()
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None,
-                    bind_group_layouts: &[&sprite_bind_group_layout, &texture_bind_group_layout],
-                    push_constant_ranges: &[],
+        let pipeline_layout =
+            gpu.device()
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sprites:pipeline_layout"),
+                    bind_group_layouts: &[&sprite_bind_group_layout, &texture_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        assert_eq!(std::mem::size_of::<Transform>(), 4 * 4);
+        assert_eq!(std::mem::size_of::<SheetRegion>(), 4 * 4);
+        assert_eq!(std::mem::size_of::<CompactSprite>(), 4 * 4);
+        let world_layout = Self::WORLD_LAYOUT;
+        let sheet_layout = Self::SHEET_LAYOUT;
+        // The previous transforms are laid out just like the current ones
+        let prev_world_layout = wgpu::VertexBufferLayout {
+            attributes: &[wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 2,
+            }],
+            ..world_layout.clone()
+        };
+        // Compact sprites are stored where sheet regions would be
+        let compact_vbuf_layouts = [sheet_layout.clone()];
+        let vbuf_layouts = [world_layout.clone(), sheet_layout.clone()];
+        let trail_vbuf_layouts = [world_layout, sheet_layout, prev_world_layout];
+        let sdf_vbuf_layouts = vbuf_layouts.clone();
+        let pipelines = depth_mode_pipelines(
+            gpu,
+            "sprites:pipeline",
+            &pipeline_layout,
+            &shader,
+            if use_storage {
+                "vs_storage_main"
+            } else {
+                "vs_vbuf_main"
+            },
+            if use_storage { &[] } else { &vbuf_layouts },
+            "fs_main",
+            &color_target,
+            depth_format,
+        );
+        let compact_pipelines = depth_mode_pipelines(
+            gpu,
+            "sprites:compact_pipeline",
+            &pipeline_layout,
+            &shader,
+            if use_storage {
+                "vs_storage_compact_main"
+            } else {
+                "vs_vbuf_compact_main"
+            },
+            if use_storage {
+                &[]
+            } else {
+                &compact_vbuf_layouts
+            },
+            "fs_main",
+            &color_target,
+            depth_format,
+        );
+
+        // Indexed-color groups bind a spritesheet of palette indices
+        // and a palette texture in place of the texture and sampler.
+        let indexed_texture_bind_group_layout =
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sprites:indexed_texture_bgl"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Uint,
+                                view_dimension: wgpu::TextureViewDimension::D2Array,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let indexed_pipeline_layout =
+            gpu.device()
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sprites:indexed_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &sprite_bind_group_layout,
+                        &indexed_texture_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        let indexed_pipelines = depth_mode_pipelines(
+            gpu,
+            "sprites:indexed_pipeline",
+            &indexed_pipeline_layout,
+            &shader,
+            if use_storage {
+                "vs_storage_indexed_main"
+            } else {
+                "vs_vbuf_indexed_main"
+            },
+            if use_storage { &[] } else { &vbuf_layouts },
+            "fs_indexed_main",
+            &color_target,
+            depth_format,
+        );
+
+        // Trails get their own bind group with the trail parameters
+        // and (when storage buffers are available) the previous
+        // transforms; otherwise the previous transforms are a third
+        // vertex buffer.
+        let trail_params_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let trail_bind_group_layout = if use_storage {
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sprites:trail_bgl"),
+                    entries: &[
+                        trail_params_entry,
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                })
+        } else {
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sprites:trail_bgl"),
+                    entries: &[trail_params_entry],
+                })
+        };
+        let trail_pipeline_layout =
+            gpu.device()
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sprites:trail_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &sprite_bind_group_layout,
+                        &texture_bind_group_layout,
+                        &trail_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        // Ghosts are depth tested (if their group is) but don't write
+        // depth, so they never hide the sprites they're following.
+        let trail_pipelines = [false, true].map(|test| {
+            gpu.device()
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("sprites:trail_pipeline"),
+                    layout: Some(&trail_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: if use_storage {
+                            "vs_storage_trail_main"
+                        } else {
+                            "vs_vbuf_trail_main"
+                        },
+                        buffers: if use_storage {
+                            &[]
+                        } else {
+                            &trail_vbuf_layouts
+                        },
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_trail_main",
+                        targets: &[Some(color_target.clone())],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(
+                        DepthMode { test, write: false }.depth_stencil_state(depth_format),
+                    ),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+        });
+
+        // SDF effects get their own bind group with the effect
+        // parameters and the distance field, sampled smoothly
+        let sdf_bind_group_layout =
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sprites:sdf_bgl"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2Array,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                });
+        let sdf_sampler = gpu.device().create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sprites:sdf_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let sdf_pipeline_layout =
+            gpu.device()
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sprites:sdf_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &sprite_bind_group_layout,
+                        &texture_bind_group_layout,
+                        &sdf_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        // Like ghosts, effects are depth tested (if their group is)
+        // but don't write depth, and are drawn before the sprites
+        let sdf_pipelines = [false, true].map(|test| {
+            gpu.device()
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("sprites:sdf_pipeline"),
+                    layout: Some(&sdf_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: if use_storage {
+                            "vs_storage_sdf_main"
+                        } else {
+                            "vs_vbuf_sdf_main"
+                        },
+                        buffers: if use_storage { &[] } else { &sdf_vbuf_layouts },
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_sdf_main",
+                        targets: &[Some(color_target.clone())],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(
+                        DepthMode { test, write: false }.depth_stencil_state(depth_format),
+                    ),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+        });
+
+        Self {
+            pipelines,
+            trail_pipelines,
+            sdf_pipelines,
+            indexed_pipelines,
+            compact_pipelines,
+            pipeline_layout,
+            color_target,
+            depth_format,
+            shader_variants: Vec::new(),
+            use_storage,
+            free_groups: Vec::new(),
+            groups: Vec::with_capacity(4),
+            sprite_bind_group_layout,
+            texture_bind_group_layout,
+            indexed_texture_bind_group_layout,
+            trail_bind_group_layout,
+            sdf_bind_group_layout,
+            sdf_sampler,
+        }
+    }
+    /// Create a new sprite group sized to fit `world_transforms` and
+    /// `sheet_regions`, which should be the same length.  Returns the
+    /// sprite group index corresponding to this group.
+    pub fn add_sprite_group(
+        &mut self,
+        gpu: &WGPU,
+        tex: &wgpu::Texture,
+        world_transforms: Vec<Transform>,
+        sheet_regions: Vec<SheetRegion>,
+        camera: Camera2D,
+    ) -> usize {
+        self.add_group(
+            gpu,
+            tex,
+            None,
+            None,
+            world_transforms,
+            sheet_regions,
+            camera,
+        )
+    }
+    /// Like [`SpriteRenderer::add_sprite_group`], but the group
+    /// samples `tex` through a view made with `view` instead of one
+    /// covering every layer, e.g. to pick a range of layers or mip
+    /// levels, or to read an sRGB texture as raw data with a
+    /// non-sRGB format (which must be among the texture's
+    /// `view_formats`).  The view's dimension is always a 2D array.
+    pub fn add_sprite_group_with_view(
+        &mut self,
+        gpu: &WGPU,
+        tex: &wgpu::Texture,
+        view: &wgpu::TextureViewDescriptor,
+        world_transforms: Vec<Transform>,
+        sheet_regions: Vec<SheetRegion>,
+        camera: Camera2D,
+    ) -> usize {
+        self.add_group(
+            gpu,
+            tex,
+            Some(view),
+            None,
+            world_transforms,
+            sheet_regions,
+            camera,
+        )
+    }
+    /// Create a new indexed-color sprite group sized to fit
+    /// `world_transforms` and `sheet_regions`, which should be the
+    /// same length.  `tex` must be an [`wgpu::TextureFormat::R8Uint`]
+    /// array texture whose texels are indices into `palette`; palette
+    /// colors are sRGB, and colors with low alpha are transparent.
+    /// Returns the sprite group index corresponding to this group.
+    pub fn add_indexed_sprite_group(
+        &mut self,
+        gpu: &WGPU,
+        tex: &wgpu::Texture,
+        palette: &[[u8; 4]],
+        world_transforms: Vec<Transform>,
+        sheet_regions: Vec<SheetRegion>,
+        camera: Camera2D,
+    ) -> usize {
+        assert_eq!(
+            tex.format(),
+            wgpu::TextureFormat::R8Uint,
+            "Indexed sprite groups need an R8Uint texture"
+        );
+        let colors = palette_from_slice(palette);
+        let texture = gpu.device().create_texture_with_data(
+            gpu.queue(),
+            &wgpu::TextureDescriptor {
+                label: Some("sprites:palette"),
+                size: wgpu::Extent3d {
+                    width: 256,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&colors),
+        );
+        self.add_group(
+            gpu,
+            tex,
+            None,
+            Some((colors, texture)),
+            world_transforms,
+            sheet_regions,
+            camera,
+        )
+    }
+    /// Create a new compact sprite group holding `sprites`, which
+    /// takes half the memory and upload bandwidth of a regular group
+    /// of the same size.  This suits very large groups of static,
+    /// unrotated decoration like tile layers; see [`CompactSprite`]
+    /// for what's given up.  Compact groups have no [`Transform`]s or
+    /// [`SheetRegion`]s, so access their sprites with
+    /// [`SpriteRenderer::get_compact_sprites_mut`] rather than
+    /// [`SpriteRenderer::get_sprites_mut`]; they can't have trails.
+    /// Returns the sprite group index corresponding to this group.
+    pub fn add_compact_sprite_group(
+        &mut self,
+        gpu: &WGPU,
+        tex: &wgpu::Texture,
+        sprites: Vec<CompactSprite>,
+        layout: CompactLayout,
+        camera: Camera2D,
+    ) -> usize {
+        // Start with room for one sprite so no binding is empty, then
+        // make room for the compact sprites
+        let which = self.add_group(
+            gpu,
+            tex,
+            None,
+            None,
+            vec![Transform::ZERO],
+            vec![SheetRegion::ZERO],
+            camera,
+        );
+        let group = self.groups[which].as_mut().unwrap();
+        group.world_transforms.clear();
+        group.sheet_regions.clear();
+        let capacity = sprites.len().max(1);
+        group.compact = Some(CompactData { sprites, layout });
+        self.realloc_group(gpu, which, capacity);
+        self.set_compact_layout(gpu, which, layout);
+        which
+    }
+    /// Changes how a compact sprite group's quantized positions map
+    /// into the world and what sheet, depth, and color modulation its
+    /// sprites share.  Uploads to the GPU.
+    /// Panics if the given sprite group is not a populated compact group.
+    pub fn set_compact_layout(&mut self, gpu: &WGPU, which: usize, layout: CompactLayout) {
+        let group = self.groups[which].as_mut().unwrap();
+        group
+            .compact
+            .as_mut()
+            .expect("Sprite group is not compact")
+            .layout = layout;
+        gpu.queue().write_buffer(
+            &group.camera_buffer,
+            Self::COMPACT_OFFSET,
+            bytemuck::bytes_of(&CompactUniform::from(layout)),
+        );
+    }
+    /// Returns the [`CompactLayout`] of the given sprite group, or
+    /// `None` if it isn't compact.
+    /// Panics if the given sprite group is not populated.
+    pub fn compact_layout(&self, which: usize) -> Option<CompactLayout> {
+        self.groups[which]
+            .as_ref()
+            .unwrap()
+            .compact
+            .as_ref()
+            .map(|compact| compact.layout)
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn add_group(
+        &mut self,
+        gpu: &WGPU,
+        tex: &wgpu::Texture,
+        view: Option<&wgpu::TextureViewDescriptor>,
+        palette: Option<(Palette, wgpu::Texture)>,
+        world_transforms: Vec<Transform>,
+        sheet_regions: Vec<SheetRegion>,
+        camera: Camera2D,
+    ) -> usize {
+        if gpu.is_gl() && (tex.depth_or_array_layers() == 1 || tex.depth_or_array_layers() == 6) {
+            panic!("Array textures with 1 or 6 layers aren't supported in webgl or other GL backends {:?}", tex);
+        }
+        let group_idx = if let Some(idx) = self.free_groups.pop() {
+            idx
+        } else {
+            self.groups.push(None);
+            self.groups.len() - 1
+        };
+        let view_sprite = tex.create_view(&match view {
+            Some(view) => wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                ..view.clone()
+            },
+            None => wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                base_array_layer: 0,
+                array_layer_count: match tex.depth_or_array_layers() {
+                    0 => Some(1),
+                    layers => Some(layers),
+                },
+                ..Default::default()
+            },
+        });
+        let tex_bind_group = Self::texture_bind_group(
+            gpu,
+            if palette.is_some() {
+                &self.indexed_texture_bind_group_layout
+            } else {
+                &self.texture_bind_group_layout
+            },
+            &view_sprite,
+            palette.as_ref().map(|(_, texture)| texture),
+            SpriteSampler::default(),
+            group_idx,
+            None,
+        );
+        let buffer_world = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: crate::group_label("sprites:world_buffer", group_idx, None).as_deref(),
+            size: world_transforms.len() as u64 * std::mem::size_of::<Transform>() as u64,
+            usage: if self.use_storage {
+                wgpu::BufferUsages::STORAGE
+            } else {
+                wgpu::BufferUsages::VERTEX
+            } | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let buffer_sheet = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: crate::group_label("sprites:sheet_buffer", group_idx, None).as_deref(),
+            size: sheet_regions.len() as u64 * std::mem::size_of::<SheetRegion>() as u64,
+            usage: if self.use_storage {
+                wgpu::BufferUsages::STORAGE
+            } else {
+                wgpu::BufferUsages::VERTEX
+            } | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let camera_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: crate::group_label("sprites:camera_buffer", group_idx, None).as_deref(),
+            // The camera followed by the y-sort and world depth
+            // parameters, the shapes, the color grade, and any compact layout
+            size: Self::COMPACT_OFFSET + std::mem::size_of::<CompactUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let sprite_bind_group = Self::sprite_bind_group(
+            gpu,
+            &self.sprite_bind_group_layout,
+            self.use_storage,
+            crate::group_label("sprites:sprite_bg", group_idx, None).as_deref(),
+            &camera_buffer,
+            &buffer_world,
+            &buffer_sheet,
+        );
+        gpu.queue()
+            .write_buffer(&buffer_world, 0, bytemuck::cast_slice(&world_transforms));
+        gpu.queue()
+            .write_buffer(&buffer_sheet, 0, bytemuck::cast_slice(&sheet_regions));
+        gpu.queue()
+            .write_buffer(&camera_buffer, 0, bytemuck::bytes_of(&camera));
+        gpu.queue().write_buffer(
+            &camera_buffer,
+            std::mem::size_of::<Camera2D>() as u64,
+            bytemuck::bytes_of(&YSort::uniform(None)),
+        );
+        gpu.queue().write_buffer(
+            &camera_buffer,
+            std::mem::size_of::<Camera2D>() as u64 + 16,
+            bytemuck::bytes_of(&WorldDepth::uniform(None)),
+        );
+        gpu.queue().write_buffer(
+            &camera_buffer,
+            Self::COLOR_GRADE_OFFSET,
+            bytemuck::bytes_of(&ColorGrade::IDENTITY.matrix),
+        );
+        self.groups[group_idx] = Some(SpriteGroup {
+            world_buffer: buffer_world,
+            sheet_buffer: buffer_sheet,
+            world_transforms,
+            sheet_regions,
+            tex_view: view_sprite,
+            texture: crate::resources::TextureInfo::new(tex, view),
+            tex_bind_group,
+            sprite_bind_group,
+            name: None,
+            camera,
+            camera_buffer,
+            camera_staged: false,
+            trail: None,
+            sdf: None,
+            palette: palette.map(|(colors, texture)| PaletteData { colors, texture }),
+            depth_mode: DepthMode::default(),
+            y_sort: None,
+            world_depth: None,
+            shapes: vec![],
+            pixel_perfect: false,
+            color_grade: ColorGrade::IDENTITY,
+            sampler: SpriteSampler::default(),
+            shader: None,
+            reclaimable: true,
+            underused_frames: 0,
+            sparse_frames: 0,
+            compact: None,
+            pick_grid: std::sync::OnceLock::new(),
+        });
+        group_idx
+    }
+    /// Returns the number of sprite groups (including placeholders for removed groups).
+    pub fn sprite_group_count(&self) -> usize {
+        self.groups.len()
+    }
+    /// Returns whether the given sprite group slot holds a group,
+    /// i.e. it exists and hasn't been removed.
+    pub fn sprite_group_populated(&self, which: usize) -> bool {
+        self.groups.get(which).is_some_and(|group| group.is_some())
+    }
+    /// Deletes a sprite group, leaving an empty group slot behind (this might get recycled later).
+    pub fn remove_sprite_group(&mut self, which: usize) {
+        if self.groups[which].is_some() {
+            self.groups[which] = None;
+            self.free_groups.push(which);
+        }
+    }
+    /// Reports the size of the given sprite group.  Panics if the given sprite group is not populated.
+    pub fn sprite_group_size(&self, which: usize) -> usize {
+        self.groups[which].as_ref().unwrap().len()
+    }
+    /// Describes the texture the given sprite group draws from.
+    /// Panics if the given sprite group is not populated.
+    pub fn texture_info(&self, which: usize) -> crate::resources::TextureInfo {
+        self.groups[which].as_ref().unwrap().texture
+    }
+    /// Makes the given sprite group draw from `tex` instead, viewed
+    /// through all of its layers, e.g. after the texture it drew
+    /// from was re-created at a new size.  Sheet regions are left
+    /// as they are.  Panics if the given sprite group is not
+    /// populated or is indexed.
+    pub fn replace_texture(&mut self, gpu: &WGPU, which: usize, tex: &wgpu::Texture) {
+        if gpu.is_gl() && (tex.depth_or_array_layers() == 1 || tex.depth_or_array_layers() == 6) {
+            panic!("Array textures with 1 or 6 layers aren't supported in webgl or other GL backends {:?}", tex);
+        }
+        let group = self.groups[which].as_mut().unwrap();
+        assert!(
+            group.palette.is_none(),
+            "Can't replace an indexed sprite group's texture"
+        );
+        group.tex_view = tex.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            array_layer_count: Some(tex.depth_or_array_layers()),
+            ..Default::default()
+        });
+        group.texture = crate::resources::TextureInfo::new(tex, None);
+        group.tex_bind_group = Self::texture_bind_group(
+            gpu,
+            &self.texture_bind_group_layout,
+            &group.tex_view,
+            None,
+            group.sampler,
+            which,
+            group.name.as_deref(),
+        );
+    }
+    /// Resizes a sprite group.  If the new size is smaller, this is
+    /// very cheap; if it's larger than it's ever been before, it
+    /// might involve reallocating the [`Vec<Transform>`],
+    /// [`Vec<SheetRegion>`], or the GPU buffer used to draw sprites,
+    /// so it could be expensive.  If this happens, the buffer will
+    /// also be uploaded to prevent garbage data from being used in
+    /// the shader.  To avoid redundant work, resize upwards as few
+    /// times as possible.
+    ///
+    /// Panics if the given sprite group is not populated.
+    pub fn resize_sprite_group(&mut self, gpu: &WGPU, which: usize, len: usize) -> usize {
+        let group = &mut self.groups[which].as_mut().unwrap();
+        let old_len = group.len();
+        if old_len == len {
+            return old_len;
+        }
+        // shrink or grow sprite vecs
+        if let Some(compact) = group.compact.as_mut() {
+            compact.sprites.resize(len, CompactSprite::zeroed());
+        } else {
+            assert_eq!(old_len, group.sheet_regions.len());
+            group.world_transforms.resize(len, Transform::zeroed());
+            group.sheet_regions.resize(len, SheetRegion::zeroed());
+            group.pick_grid.take();
+        }
+        if let Some(trail) = group.trail.as_mut() {
+            trail.uploaded_transforms.resize(len, Transform::zeroed());
+        }
+        // realloc buffer if needed
+        if len > group.capacity() {
+            self.realloc_group(gpu, which, len);
+        }
+        old_len
+    }
+    /// Shrinks a sprite group's GPU buffers (and CPU-side vectors) to
+    /// fit its current size, returning the memory left over from
+    /// when it was larger.  Like growing a group, this reallocates and
+    /// re-uploads its buffers, so it could be expensive.
+    ///
+    /// Panics if the given sprite group is not populated.
+    pub fn shrink_sprite_group_to_fit(&mut self, gpu: &WGPU, which: usize) {
+        let group = self.groups[which].as_mut().unwrap();
+        group.underused_frames = 0;
+        group.world_transforms.shrink_to_fit();
+        group.sheet_regions.shrink_to_fit();
+        if let Some(compact) = group.compact.as_mut() {
+            compact.sprites.shrink_to_fit();
+        }
+        if let Some(trail) = group.trail.as_mut() {
+            trail.uploaded_transforms.shrink_to_fit();
+        }
+        // Keep room for one sprite, since bindings can't be empty
+        let len = group.len().max(1);
+        if len < group.capacity() {
+            self.realloc_group(gpu, which, len);
+        }
+    }
+    /// Sets whether [`SpriteRenderer::reclaim`] may shrink a specific
+    /// sprite group.  Opt latency-sensitive groups out of reclamation
+    /// so that growing them again never needs a reallocation.  Groups
+    /// are reclaimable by default, but nothing is reclaimed unless
+    /// [`SpriteRenderer::reclaim`] is called (e.g. by
+    /// [`crate::Renderer::set_sprite_reclaim_policy`]).
+    ///
+    /// Panics if the given sprite group is not populated.
+    pub fn set_reclaimable(&mut self, which: usize, reclaimable: bool) {
+        let group = self.groups[which].as_mut().unwrap();
+        group.reclaimable = reclaimable;
+        group.underused_frames = 0;
+    }
+    /// Call once per frame to shrink reclaimable sprite groups whose
+    /// size has stayed below `policy`'s threshold for long enough.
+    pub fn reclaim(&mut self, gpu: &WGPU, policy: ReclaimPolicy) {
+        for which in 0..self.groups.len() {
+            let Some(group) = self.groups[which].as_mut() else {
+                continue;
+            };
+            if !group.reclaimable {
+                continue;
+            }
+            let used = group.len() as f32;
+            if used >= group.capacity() as f32 * policy.threshold {
+                group.underused_frames = 0;
+                continue;
+            }
+            group.underused_frames += 1;
+            if group.underused_frames >= policy.frames {
+                self.shrink_sprite_group_to_fit(gpu, which);
+            }
+        }
+    }
+    /// Counts the given sprite group's visible, allocated, and
+    /// GPU-side sprites.  This looks at every sprite in the group.
+    ///
+    /// Panics if the given sprite group is not populated.
+    pub fn group_stats(&self, which: usize) -> SpriteGroupStats {
+        let group = self.groups[which].as_ref().unwrap();
+        let visible = match group.compact.as_ref() {
+            Some(compact) => compact
+                .sprites
+                .iter()
+                .filter(|s| s.w != 0 && s.h != 0 && s.sheet_w != 0 && s.sheet_h != 0)
+                .count(),
+            None => group
+                .world_transforms
+                .iter()
+                .zip(&group.sheet_regions)
+                .filter(|(trf, uv)| trf.w != 0 && trf.h != 0 && uv.w != 0 && uv.h != 0)
+                .count(),
+        };
+        SpriteGroupStats {
+            visible,
+            allocated: group.len(),
+            capacity: group.capacity(),
+        }
+    }
+    /// Call once per frame to log a warning about each sprite group
+    /// which has stayed sparse for `warning.frames` frames in a row.
+    /// Each group is reported once until it fills up again.  This
+    /// looks at every sprite of every group, so it's meant for
+    /// development builds.
+    pub fn warn_sparse_groups(&mut self, warning: SparseWarning) {
+        for which in 0..self.groups.len() {
+            let Some(group) = self.groups[which].as_ref() else {
+                continue;
+            };
+            if group.len() < warning.min_size {
+                continue;
+            }
+            let stats = self.group_stats(which);
+            let group = self.groups[which].as_mut().unwrap();
+            if stats.occupancy() >= warning.threshold {
+                group.sparse_frames = 0;
+                continue;
+            }
+            group.sparse_frames += 1;
+            if group.sparse_frames == warning.frames {
+                log::warn!(
+                    "Sprite group {which} has drawn only {} of {} sprites (capacity {}) for {} frames; consider resizing it",
+                    stats.visible,
+                    stats.allocated,
+                    stats.capacity,
+                    warning.frames
+                );
+            }
+        }
+    }
+    /// Names a sprite group, or removes its name.  The name is
+    /// included in the labels of the group's GPU buffers and bind
+    /// groups, which are remade to carry it, and marks its draws
+    /// with the `debug` feature, so the group is easy to find in
+    /// graphics debuggers and wgpu's errors.
+    /// Panics if the given sprite group is not populated.
+    pub fn set_group_name(&mut self, gpu: &WGPU, which: usize, name: Option<&str>) {
+        let group = self.groups[which].as_mut().unwrap();
+        group.name = name.map(str::to_string);
+        let name = group.name.as_deref();
+        let mut encoder = crate::relabel_encoder(gpu);
+        group.world_buffer = crate::relabel_buffer(
+            gpu,
+            &mut encoder,
+            &group.world_buffer,
+            crate::group_label("sprites:world_buffer", which, name).as_deref(),
+        );
+        group.sheet_buffer = crate::relabel_buffer(
+            gpu,
+            &mut encoder,
+            &group.sheet_buffer,
+            crate::group_label("sprites:sheet_buffer", which, name).as_deref(),
+        );
+        group.camera_buffer = crate::relabel_buffer(
+            gpu,
+            &mut encoder,
+            &group.camera_buffer,
+            crate::group_label("sprites:camera_buffer", which, name).as_deref(),
+        );
+        gpu.queue().submit(Some(encoder.finish()));
+        group.sprite_bind_group = Self::sprite_bind_group(
+            gpu,
+            &self.sprite_bind_group_layout,
+            self.use_storage,
+            crate::group_label("sprites:sprite_bg", which, name).as_deref(),
+            &group.camera_buffer,
+            &group.world_buffer,
+            &group.sheet_buffer,
+        );
+        group.tex_bind_group = Self::texture_bind_group(
+            gpu,
+            if group.palette.is_some() {
+                &self.indexed_texture_bind_group_layout
+            } else {
+                &self.texture_bind_group_layout
+            },
+            &group.tex_view,
+            group.palette.as_ref().map(|palette| &palette.texture),
+            group.sampler,
+            which,
+            name,
+        );
+    }
+    /// Returns the given sprite group's name, if it has one.
+    /// Panics if the given sprite group is not populated.
+    pub fn group_name(&self, which: usize) -> Option<&str> {
+        self.groups[which].as_ref().unwrap().name.as_deref()
+    }
+    /// Returns the bind group layouts this renderer's pipelines use.
+    pub fn layouts(&self) -> SpriteLayouts<'_> {
+        SpriteLayouts {
+            sprite: &self.sprite_bind_group_layout,
+            texture: &self.texture_bind_group_layout,
+            indexed_texture: &self.indexed_texture_bind_group_layout,
+            trail: &self.trail_bind_group_layout,
+            sdf: &self.sdf_bind_group_layout,
+        }
+    }
+    /// Returns the given sprite group's bind groups for groups 0 and
+    /// 1 of [`SpriteLayouts`]: its camera (and sprites, with storage
+    /// buffers), and its texture.  Indexed-color groups' texture bind
+    /// groups use [`SpriteLayouts::indexed_texture`].
+    /// Panics if the given sprite group is not populated.
+    pub fn group_bind_groups(&self, which: usize) -> (&wgpu::BindGroup, &wgpu::BindGroup) {
+        let group = self.groups[which].as_ref().unwrap();
+        (&group.sprite_bind_group, &group.tex_bind_group)
+    }
+    // A group's world transform and sheet region buffers, for
+    // compute passes writing its sprites directly; None if they
+    // aren't storage buffers or the group is compact
+    pub(crate) fn storage_buffers(&self, which: usize) -> Option<(&wgpu::Buffer, &wgpu::Buffer)> {
+        let group = self.groups[which].as_ref().unwrap();
+        (self.use_storage && group.compact.is_none())
+            .then_some((&group.world_buffer, &group.sheet_buffer))
+    }
+    // Describes each group slot for a frame graph: how many sprites
+    // it draws and its name, or None for empty slots
+    pub(crate) fn group_summaries(&self) -> Vec<Option<(usize, Option<&str>)>> {
+        self.groups
+            .iter()
+            .map(|group| {
+                let group = group.as_ref()?;
+                Some((group.len(), group.name.as_deref()))
+            })
+            .collect()
+    }
+    // Lists the GPU buffers and textures of every sprite group
+    pub(crate) fn collect_resources(&self, out: &mut Vec<crate::resources::GpuResource>) {
+        use crate::resources::GpuResource;
+        for (which, group) in self.groups.iter().enumerate() {
+            let Some(group) = group else {
+                continue;
+            };
+            let name = group.name.as_deref();
+            for (kind, buffer) in [
+                ("sprites:world_buffer", &group.world_buffer),
+                ("sprites:sheet_buffer", &group.sheet_buffer),
+                ("sprites:camera_buffer", &group.camera_buffer),
+            ] {
+                out.push(GpuResource::buffer(
+                    crate::group_label(kind, which, name).map(Cow::into_owned),
+                    buffer,
+                ));
+            }
+            if let Some(trail) = group.trail.as_ref() {
+                out.push(GpuResource::buffer(
+                    Some("sprites:trail_buffer".to_string()),
+                    &trail.trail_buffer,
+                ));
+                out.push(GpuResource::buffer(
+                    Some("sprites:prev_world_buffer".to_string()),
+                    &trail.prev_buffer,
+                ));
+            }
+            if let Some(sdf) = group.sdf.as_ref() {
+                out.push(GpuResource::buffer(
+                    Some("sprites:sdf_buffer".to_string()),
+                    &sdf.buffer,
+                ));
+            }
+            if let Some(palette) = group.palette.as_ref() {
+                out.push(GpuResource::texture(
+                    Some("sprites:palette".to_string()),
+                    &palette.texture,
+                ));
+            }
+        }
+    }
+    // The bind group a sprite group's texture (and palette, for
+    // indexed groups) is sampled through
+    fn texture_bind_group(
+        gpu: &WGPU,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        palette: Option<&wgpu::Texture>,
+        sampler: SpriteSampler,
+        which: usize,
+        name: Option<&str>,
+    ) -> wgpu::BindGroup {
+        if let Some(palette_texture) = palette {
+            let view_palette = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                label: crate::group_label("sprites:indexed_texture_bg", which, name).as_deref(),
+                layout,
+                entries: &[
+                    // One for the index texture, one for the palette
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&view_palette),
+                    },
+                ],
+            })
+        } else {
+            let sampler_sprite = gpu.device().create_sampler(&sampler.descriptor());
+            gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                label: crate::group_label("sprites:texture_bg", which, name).as_deref(),
+                layout,
+                entries: &[
+                    // One for the texture, one for the sampler
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler_sprite),
+                    },
+                ],
+            })
+        }
+    }
+    // The bind group of a sprite group's camera and, when using
+    // storage buffers, its sprites
+    fn sprite_bind_group(
+        gpu: &WGPU,
+        layout: &wgpu::BindGroupLayout,
+        use_storage: bool,
+        label: Option<&str>,
+        camera_buffer: &wgpu::Buffer,
+        world_buffer: &wgpu::Buffer,
+        sheet_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        if use_storage {
+            gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                label,
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: camera_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: world_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: sheet_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        } else {
+            gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                label,
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                }],
+            })
+        }
+    }
+    // Reallocate a group's buffers with room for `capacity` sprites
+    // and upload its contents, remaking sprite_bind_group if using
+    // storage buffers
+    fn realloc_group(&mut self, gpu: &WGPU, which: usize, capacity: usize) {
+        let group = self.groups[which].as_mut().unwrap();
+        let new_size = capacity * std::mem::size_of::<Transform>();
+        trace_event!(
+            group = which,
+            capacity,
+            bytes = new_size,
+            "sprites: reallocated group buffers"
+        );
+        // Compact groups keep their sprites in the sheet buffer and
+        // only need a placeholder world buffer
+        let world_size = if group.compact.is_some() {
+            std::mem::size_of::<Transform>()
+        } else {
+            new_size
+        };
+        group.world_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: crate::group_label("sprites:world_buffer", which, group.name.as_deref())
+                .as_deref(),
+            size: world_size as u64,
+            usage: if self.use_storage {
+                wgpu::BufferUsages::STORAGE
+            } else {
+                wgpu::BufferUsages::VERTEX
+            } | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        group.sheet_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: crate::group_label("sprites:sheet_buffer", which, group.name.as_deref())
+                .as_deref(),
+            size: new_size as u64,
+            usage: if self.use_storage {
+                wgpu::BufferUsages::STORAGE
+            } else {
+                wgpu::BufferUsages::VERTEX
+            } | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        if self.use_storage {
+            group.sprite_bind_group = Self::sprite_bind_group(
+                gpu,
+                &self.sprite_bind_group_layout,
+                self.use_storage,
+                crate::group_label("sprites:sprite_bg", which, group.name.as_deref()).as_deref(),
+                &group.camera_buffer,
+                &group.world_buffer,
+                &group.sheet_buffer,
+            );
+        }
+        if let Some(compact) = group.compact.as_ref() {
+            gpu.queue().write_buffer(
+                &group.sheet_buffer,
+                0,
+                bytemuck::cast_slice(&compact.sprites),
+            );
+        } else {
+            gpu.queue().write_buffer(
+                &group.world_buffer,
+                0,
+                bytemuck::cast_slice(&group.world_transforms),
+            );
+            gpu.queue().write_buffer(
+                &group.sheet_buffer,
+                0,
+                bytemuck::cast_slice(&group.sheet_regions),
+            );
+        }
+        if let Some(trail) = group.trail.take() {
+            group.trail = Some(Self::create_trail_data(
+                gpu,
+                &self.trail_bind_group_layout,
+                self.use_storage,
+                &group.world_transforms,
+                capacity,
+                trail.trail,
+            ));
+        }
+    }
+    /// Sets how a specific sprite group interacts with the depth buffer.
+    /// Panics if the given sprite group is not populated.
+    pub fn set_depth_mode(&mut self, which: usize, mode: DepthMode) {
+        self.groups[which].as_mut().unwrap().depth_mode = mode;
+    }
+    /// Gets the [`DepthMode`] of a specific sprite group.
+    /// Panics if the given sprite group is not populated.
+    pub fn depth_mode(&self, which: usize) -> DepthMode {
+        self.groups[which].as_ref().unwrap().depth_mode
+    }
+    /// Registers a named variant of the sprite shader which sprite
+    /// groups can then select with
+    /// [`SpriteRenderer::set_shader_variant`], e.g. a dissolve or
+    /// palette swap effect.
+    ///
+    /// `source` is WGSL appended to frenderer's own sprite shader, so
+    /// it can use that shader's bindings, its `VertexOutput` struct,
+    /// and helpers like `sprite_uv` and `graded`.  It must define a
+    /// fragment shader named `entry_point` taking a `VertexOutput`
+    /// and returning one color.  The variant's pipelines are only
+    /// made once a group uses it.
+    ///
+    /// Registering a name again replaces that variant, and groups
+    /// using it switch to the new source.  Panics if `name` is
+    /// [`SpriteRenderer::DEFAULT_SHADER`], or, once its pipelines are
+    /// made, with the compiler's error if `source` doesn't compile.
+    pub fn register_shader_variant(
+        &mut self,
+        gpu: &WGPU,
+        name: &str,
+        source: &str,
+        entry_point: &str,
+    ) {
+        assert_ne!(
+            name,
+            Self::DEFAULT_SHADER,
+            "The default sprite shader can't be replaced"
+        );
+        let variant = ShaderVariant {
+            name: name.to_string(),
+            source: source.to_string(),
+            entry_point: entry_point.to_string(),
+            pipelines: None,
+        };
+        let idx = match self.shader_variants.iter().position(|v| v.name == name) {
+            Some(idx) => {
+                self.shader_variants[idx] = variant;
+                idx
+            }
+            None => {
+                self.shader_variants.push(variant);
+                self.shader_variants.len() - 1
+            }
+        };
+        if self.groups.iter().flatten().any(|g| g.shader == Some(idx)) {
+            self.make_variant_pipelines(gpu, idx);
+        }
+    }
+    /// Returns the names of the registered shader variants, not
+    /// including [`SpriteRenderer::DEFAULT_SHADER`].
+    pub fn shader_variants(&self) -> impl Iterator<Item = &str> + '_ {
+        self.shader_variants.iter().map(|v| v.name.as_str())
+    }
+    /// Draws a specific sprite group with the named shader variant
+    /// (see [`SpriteRenderer::register_shader_variant`]), or with the
+    /// built-in shader if `name` is [`SpriteRenderer::DEFAULT_SHADER`].
+    /// Panics if the given sprite group is not populated or is an
+    /// indexed-color group, or if no variant has that name.
+    pub fn set_shader_variant(&mut self, gpu: &WGPU, which: usize, name: &str) {
+        let shader = if name == Self::DEFAULT_SHADER {
+            None
+        } else {
+            let idx = self
+                .shader_variants
+                .iter()
+                .position(|v| v.name == name)
+                .unwrap_or_else(|| panic!("No sprite shader variant named {name:?}"));
+            if self.shader_variants[idx].pipelines.is_none() {
+                self.make_variant_pipelines(gpu, idx);
+            }
+            Some(idx)
+        };
+        let group = self.groups[which].as_mut().unwrap();
+        assert!(
+            group.palette.is_none() || shader.is_none(),
+            "Indexed-color sprite groups can't use shader variants"
+        );
+        group.shader = shader;
+    }
+    /// Returns the name of the shader variant a specific sprite group
+    /// is drawn with.
+    /// Panics if the given sprite group is not populated.
+    pub fn shader_variant(&self, which: usize) -> &str {
+        match self.groups[which].as_ref().unwrap().shader {
+            Some(idx) => &self.shader_variants[idx].name,
+            None => Self::DEFAULT_SHADER,
+        }
+    }
+    fn make_variant_pipelines(&mut self, gpu: &WGPU, idx: usize) {
+        let variant = &self.shader_variants[idx];
+        let module = gpu.create_shader(wgpu::ShaderModuleDescriptor {
+            label: Some("sprites:variant_shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!(
+                "{}\n{}",
+                include_str!("sprites.wgsl"),
+                variant.source
+            ))),
+        });
+        let use_storage = self.use_storage;
+        let plain = depth_mode_pipelines(
+            gpu,
+            "sprites:variant_pipeline",
+            &self.pipeline_layout,
+            &module,
+            if use_storage {
+                "vs_storage_main"
+            } else {
+                "vs_vbuf_main"
+            },
+            if use_storage {
+                &[]
+            } else {
+                &[Self::WORLD_LAYOUT, Self::SHEET_LAYOUT]
+            },
+            &variant.entry_point,
+            &self.color_target,
+            self.depth_format,
+        );
+        let compact = depth_mode_pipelines(
+            gpu,
+            "sprites:variant_compact_pipeline",
+            &self.pipeline_layout,
+            &module,
+            if use_storage {
+                "vs_storage_compact_main"
+            } else {
+                "vs_vbuf_compact_main"
+            },
+            if use_storage {
+                &[]
+            } else {
+                &[Self::SHEET_LAYOUT]
+            },
+            &variant.entry_point,
+            &self.color_target,
+            self.depth_format,
+        );
+        self.shader_variants[idx].pipelines = Some(VariantPipelines { plain, compact });
+    }
+    /// Sets or clears the [`YSort`] of a specific sprite group.
+    /// Panics if the given sprite group is not populated.
+    pub fn set_y_sort(&mut self, gpu: &WGPU, which: usize, y_sort: Option<YSort>) {
+        let group = self.groups[which].as_mut().unwrap();
+        group.y_sort = y_sort;
+        gpu.queue().write_buffer(
+            &group.camera_buffer,
+            std::mem::size_of::<Camera2D>() as u64,
+            bytemuck::bytes_of(&YSort::uniform(y_sort)),
+        );
+    }
+    /// Gets the [`YSort`] of a specific sprite group, if any.
+    /// Panics if the given sprite group is not populated.
+    pub fn y_sort(&self, which: usize) -> Option<YSort> {
+        self.groups[which].as_ref().unwrap().y_sort
+    }
+    /// Sets or clears the [`WorldDepth`] of a specific sprite group.
+    /// Set it again if the 3D camera's near or far planes change.
+    /// Panics if the given sprite group is not populated.
+    pub fn set_world_depth(&mut self, gpu: &WGPU, which: usize, world_depth: Option<WorldDepth>) {
+        let group = self.groups[which].as_mut().unwrap();
+        group.world_depth = world_depth;
+        gpu.queue().write_buffer(
+            &group.camera_buffer,
+            std::mem::size_of::<Camera2D>() as u64 + 16,
+            bytemuck::bytes_of(&WorldDepth::uniform(world_depth)),
+        );
+    }
+    /// Gets the [`WorldDepth`] of a specific sprite group, if any.
+    /// Panics if the given sprite group is not populated.
+    pub fn world_depth(&self, which: usize) -> Option<WorldDepth> {
+        self.groups[which].as_ref().unwrap().world_depth
+    }
+    /// Sets the [`SpriteShape`]s of a specific sprite group: sprites
+    /// showing one of the shapes' regions are drawn as that shape,
+    /// and the rest as quads.  Each sprite then costs three times as
+    /// many vertices, so this pays off for large sprites rather than
+    /// many small ones.  Panics if there are more than
+    /// [`SpriteRenderer::MAX_SPRITE_SHAPES`] shapes or the given
+    /// sprite group is not populated.
+    pub fn set_sprite_shapes(&mut self, gpu: &WGPU, which: usize, shapes: Vec<SpriteShape>) {
+        assert!(
+            shapes.len() <= Self::MAX_SPRITE_SHAPES,
+            "A sprite group can have at most {} shapes",
+            Self::MAX_SPRITE_SHAPES
+        );
+        let group = self.groups[which].as_mut().unwrap();
+        gpu.queue().write_buffer(
+            &group.camera_buffer,
+            std::mem::size_of::<Camera2D>() as u64 + 32,
+            bytemuck::bytes_of(&ShapeUniform::new(&shapes, group.pixel_perfect)),
+        );
+        group.shapes = shapes;
+    }
+    /// Gets the [`SpriteShape`]s of a specific sprite group.
+    /// Panics if the given sprite group is not populated.
+    pub fn sprite_shapes(&self, which: usize) -> &[SpriteShape] {
+        &self.groups[which].as_ref().unwrap().shapes
+    }
+    /// Turns pixel-perfect drawing on or off for a specific sprite
+    /// group, for pixel art drawn with one world unit per pixel
+    /// (i.e. with a [`Camera2D`] whose `screen_size` is the render
+    /// size).  Sprites' unrotated edges are snapped to whole pixels
+    /// relative to the camera, and texture lookups are kept at least
+    /// half a texel inside each sprite's region, so sprites between
+    /// pixels don't shimmer or crack and neighboring frames of the
+    /// spritesheet never bleed in.  Rotated sprites are snapped by
+    /// their centers but can't otherwise line up with the pixel grid.
+    /// Panics if the given sprite group is not populated.
+    pub fn set_pixel_perfect(&mut self, gpu: &WGPU, which: usize, pixel_perfect: bool) {
+        let group = self.groups[which].as_mut().unwrap();
+        group.pixel_perfect = pixel_perfect;
+        // The flag follows the shape count at the start of the shape uniform
+        gpu.queue().write_buffer(
+            &group.camera_buffer,
+            std::mem::size_of::<Camera2D>() as u64 + 32 + 4,
+            bytemuck::bytes_of(&(pixel_perfect as u32)),
+        );
+    }
+    /// Gets whether a specific sprite group is drawn pixel-perfectly.
+    /// Panics if the given sprite group is not populated.
+    pub fn pixel_perfect(&self, which: usize) -> bool {
+        self.groups[which].as_ref().unwrap().pixel_perfect
+    }
+    /// Sets how a specific sprite group samples its texture, e.g.
+    /// [`SpriteSampler::REPEAT`] for regions that tile their layer.
+    /// Panics if the given sprite group is not populated or is
+    /// indexed, since indexed groups read texels without a sampler.
+    pub fn set_sampler(&mut self, gpu: &WGPU, which: usize, sampler: SpriteSampler) {
+        let group = self.groups[which].as_mut().unwrap();
+        assert!(
+            group.palette.is_none(),
+            "Indexed sprite groups don't use a sampler"
+        );
+        group.sampler = sampler;
+        group.tex_bind_group = Self::texture_bind_group(
+            gpu,
+            &self.texture_bind_group_layout,
+            &group.tex_view,
+            None,
+            sampler,
+            which,
+            group.name.as_deref(),
+        );
+    }
+    /// Gets how a specific sprite group samples its texture.
+    /// Panics if the given sprite group is not populated.
+    pub fn sampler(&self, which: usize) -> SpriteSampler {
+        self.groups[which].as_ref().unwrap().sampler
+    }
+    /// Sets the [`ColorGrade`] applied to a specific sprite group's
+    /// colors before postprocessing.
+    /// Panics if the given sprite group is not populated.
+    pub fn set_color_grade(&mut self, gpu: &WGPU, which: usize, grade: ColorGrade) {
+        let group = self.groups[which].as_mut().unwrap();
+        group.color_grade = grade;
+        gpu.queue().write_buffer(
+            &group.camera_buffer,
+            Self::COLOR_GRADE_OFFSET,
+            bytemuck::bytes_of(&grade.matrix),
+        );
+    }
+    /// Gets the [`ColorGrade`] of a specific sprite group.
+    /// Panics if the given sprite group is not populated.
+    pub fn color_grade(&self, which: usize) -> ColorGrade {
+        self.groups[which].as_ref().unwrap().color_grade
+    }
+    /// Sets or clears the [`Trail`] for a specific sprite group.
+    /// Ghost copies are drawn between each sprite's two most recently
+    /// uploaded transforms, so sprites which are not uploaded every
+    /// frame will leave a lingering trail behind.
+    /// Panics if the given sprite group is not populated or is an
+    /// indexed-color or compact group.
+    pub fn set_trail(&mut self, gpu: &WGPU, which: usize, trail: Option<Trail>) {
+        let group = self.groups[which].as_mut().unwrap();
+        assert!(
+            trail.is_none() || group.palette.is_none(),
+            "Trails aren't supported for indexed-color sprite groups"
+        );
+        assert!(
+            trail.is_none() || group.compact.is_none(),
+            "Trails aren't supported for compact sprite groups"
+        );
+        group.trail = match (group.trail.take(), trail) {
+            (_, None) => None,
+            (Some(mut data), Some(trail)) => {
+                data.trail = trail;
+                gpu.queue()
+                    .write_buffer(&data.trail_buffer, 0, bytemuck::bytes_of(&trail));
+                Some(data)
+            }
+            (None, Some(trail)) => Some(Self::create_trail_data(
+                gpu,
+                &self.trail_bind_group_layout,
+                self.use_storage,
+                &group.world_transforms,
+                group.capacity(),
+                trail,
+            )),
+        };
+    }
+    /// Returns the [`Trail`] of the given sprite group, if it has one.
+    /// Panics if the given sprite group is not populated.
+    pub fn trail(&self, which: usize) -> Option<Trail> {
+        self.groups[which]
+            .as_ref()
+            .unwrap()
+            .trail
+            .as_ref()
+            .map(|t| t.trail)
+    }
+    /// Sets or clears the [`SdfEffect`] drawn behind a specific sprite
+    /// group's sprites, using `sdf`, a distance field of the group's
+    /// spritesheet made with [`crate::sdf::alpha_sdf`] (an `R8Unorm`
+    /// array texture with the same size and layers as the sheet).
+    /// Effects are drawn on a quad grown past each sprite by the
+    /// spread and shadow offset, so they can spill out of its region.
+    /// Panics if the given sprite group is not populated or is an
+    /// indexed-color or compact group.
+    pub fn set_sdf_effect(
+        &mut self,
+        gpu: &WGPU,
+        which: usize,
+        effect: Option<(SdfEffect, &wgpu::Texture)>,
+    ) {
+        let group = self.groups[which].as_mut().unwrap();
+        assert!(
+            effect.is_none() || group.palette.is_none(),
+            "SDF effects aren't supported for indexed-color sprite groups"
+        );
+        assert!(
+            effect.is_none() || group.compact.is_none(),
+            "SDF effects aren't supported for compact sprite groups"
+        );
+        group.sdf = effect.map(|(effect, sdf)| {
+            let buffer = gpu
+                .device()
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("sprites:sdf_buffer"),
+                    contents: bytemuck::bytes_of(&effect.uniform()),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 });
-
-        assert_eq!(std::mem::size_of::<Transform>(), 4 * 4);
-        assert_eq!(std::mem::size_of::<SheetRegion>(), 4 * 4);
-        let pipeline = gpu
-            .device()
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: if use_storage {
-                        "vs_storage_main"
-                    } else {
-                        "vs_vbuf_main"
+            let view = sdf.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                ..Default::default()
+            });
+            let bind_group = gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("sprites:sdf_bg"),
+                layout: &self.sdf_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
                     },
-                    buffers: if use_storage {
-                        &[]
-                    } else {
-                        &[
-                            wgpu::VertexBufferLayout {
-                                array_stride: std::mem::size_of::<Transform>() as u64,
-                                step_mode: wgpu::VertexStepMode::Instance,
-                                attributes: &[wgpu::VertexAttribute {
-                                    // This is a fun little trick, we
-                                    // lie and say it's four floats.
-                                    // In the shader the first float
-                                    // is cast bitwise to a u32 and
-                                    // then the w and h are masked out
-                                    // and casted back to f32.
-                                    format: wgpu::VertexFormat::Float32x4,
-                                    offset: 0,
-                                    shader_location: 0,
-                                }],
-                            },
-                            wgpu::VertexBufferLayout {
-                                array_stride: std::mem::size_of::<SheetRegion>() as u64,
-                                step_mode: wgpu::VertexStepMode::Instance,
-                                attributes: &[wgpu::VertexAttribute {
-                                    format: wgpu::VertexFormat::Uint32x4,
-                                    offset: 0,
-                                    shader_location: 1,
-                                }],
-                            },
-                        ]
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&view),
                     },
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(color_target)],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: depth_format,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&self.sdf_sampler),
+                    },
+                ],
             });
-
-        Self {
-            pipeline,
-            use_storage,
-            free_groups: Vec::new(),
-            groups: Vec::with_capacity(4),
-            sprite_bind_group_layout,
-            texture_bind_group_layout,
-        }
+            SdfData {
+                effect,
+                buffer,
+                bind_group,
+            }
+        });
     }
-    /// Create a new sprite group sized to fit `world_transforms` and
-    /// `sheet_regions`, which should be the same length.  Returns the
-    /// sprite group index corresponding to this group.
-    pub fn add_sprite_group(
+    /// Changes the [`SdfEffect`] of a sprite group which already has
+    /// one, keeping its distance field.  Panics if the given sprite
+    /// group is not populated or has no SDF effect.
+    pub fn update_sdf_effect(&mut self, gpu: &WGPU, which: usize, effect: SdfEffect) {
+        let sdf = self.groups[which]
+            .as_mut()
+            .unwrap()
+            .sdf
+            .as_mut()
+            .expect("Sprite group has no SDF effect");
+        sdf.effect = effect;
+        gpu.queue()
+            .write_buffer(&sdf.buffer, 0, bytemuck::bytes_of(&effect.uniform()));
+    }
+    /// Returns the [`SdfEffect`] of the given sprite group, if it has one.
+    /// Panics if the given sprite group is not populated.
+    pub fn sdf_effect(&self, which: usize) -> Option<SdfEffect> {
+        self.groups[which]
+            .as_ref()
+            .unwrap()
+            .sdf
+            .as_ref()
+            .map(|sdf| sdf.effect)
+    }
+    /// Replaces the palette of an indexed-color sprite group.  If
+    /// `palette` has fewer than 256 colors, the remaining entries are
+    /// transparent.  Panics if the given sprite group is not populated
+    /// or not an indexed-color group.
+    pub fn set_palette(&mut self, gpu: &WGPU, which: usize, palette: &[[u8; 4]]) {
+        let data = Self::palette_data_mut(&mut self.groups, which);
+        data.colors = palette_from_slice(palette);
+        Self::upload_palette(gpu, data);
+    }
+    /// Returns the palette of the given sprite group, or `None` if it
+    /// isn't an indexed-color group.  Panics if the given sprite group
+    /// is not populated.
+    pub fn palette(&self, which: usize) -> Option<&Palette> {
+        self.groups[which]
+            .as_ref()
+            .unwrap()
+            .palette
+            .as_ref()
+            .map(|data| &data.colors)
+    }
+    /// Rotates the palette entries in `range` of an indexed-color
+    /// sprite group by `amount` places (towards higher indices if
+    /// positive), wrapping around within the range.  Calling this
+    /// periodically produces classic palette cycling animations like
+    /// flowing water or flickering fire.  Panics if the given sprite
+    /// group is not populated or not an indexed-color group.
+    pub fn cycle_palette_range(
         &mut self,
         gpu: &WGPU,
-        tex: &wgpu::Texture,
-        world_transforms: Vec<Transform>,
-        sheet_regions: Vec<SheetRegion>,
-        camera: Camera2D,
-    ) -> usize {
-        if gpu.is_gl() && (tex.depth_or_array_layers() == 1 || tex.depth_or_array_layers() == 6) {
-            panic!("Array textures with 1 or 6 layers aren't supported in webgl or other GL backends {:?}", tex);
+        which: usize,
+        range: Range<usize>,
+        amount: isize,
+    ) {
+        let data = Self::palette_data_mut(&mut self.groups, which);
+        let colors = &mut data.colors[range];
+        if colors.is_empty() {
+            return;
         }
-        let group_idx = if let Some(idx) = self.free_groups.pop() {
-            idx
-        } else {
-            self.groups.push(None);
-            self.groups.len() - 1
-        };
-        let view_sprite = tex.create_view(&wgpu::TextureViewDescriptor {
-            dimension: Some(wgpu::TextureViewDimension::D2Array),
-            base_array_layer: 0,
-            array_layer_count: match tex.depth_or_array_layers() {
-                0 => Some(1),
-                layers => Some(layers),
+        let amount = amount.rem_euclid(colors.len() as isize) as usize;
+        colors.rotate_right(amount);
+        Self::upload_palette(gpu, data);
+    }
+    fn palette_data_mut(groups: &mut [Option<SpriteGroup>], which: usize) -> &mut PaletteData {
+        groups[which]
+            .as_mut()
+            .unwrap()
+            .palette
+            .as_mut()
+            .expect("Sprite group does not use indexed color")
+    }
+    fn upload_palette(gpu: &WGPU, data: &PaletteData) {
+        gpu.queue().write_texture(
+            data.texture.as_image_copy(),
+            bytemuck::cast_slice(&data.colors),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(256 * 4),
+                rows_per_image: Some(1),
             },
-            ..Default::default()
-        });
-        let sampler_sprite = gpu
-            .device()
-            .create_sampler(&wgpu::SamplerDescriptor::default());
-        let tex_bind_group = gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &self.texture_bind_group_layout,
-            entries: &[
-                // One for the texture, one for the sampler
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view_sprite),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler_sprite),
-                },
-            ],
-        });
-        let buffer_world = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: world_transforms.len() as u64 * std::mem::size_of::<Transform>() as u64,
-            usage: if self.use_storage {
-                wgpu::BufferUsages::STORAGE
-            } else {
-                wgpu::BufferUsages::VERTEX
-            } | wgpu::BufferUsages::COPY_DST,
+            data.texture.size(),
+        );
+    }
+    fn create_trail_data(
+        gpu: &WGPU,
+        layout: &wgpu::BindGroupLayout,
+        use_storage: bool,
+        world_transforms: &[Transform],
+        capacity: usize,
+        trail: Trail,
+    ) -> TrailData {
+        let trail_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sprites:trail_buffer"),
+            size: std::mem::size_of::<Trail>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
-        let buffer_sheet = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: sheet_regions.len() as u64 * std::mem::size_of::<SheetRegion>() as u64,
-            usage: if self.use_storage {
+        let prev_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sprites:prev_world_buffer"),
+            // Sized like the world buffer, since groups grow within
+            // their capacity without reallocating (and bindings can't
+            // be empty)
+            size: (capacity.max(1) * std::mem::size_of::<Transform>()) as u64,
+            usage: if use_storage {
                 wgpu::BufferUsages::STORAGE
             } else {
                 wgpu::BufferUsages::VERTEX
-            } | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        let camera_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: std::mem::size_of::<Camera2D>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            } | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
             mapped_at_creation: false,
         });
-        let sprite_bind_group = if self.use_storage {
+        let bind_group = if use_storage {
             gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
-                label: None,
-                layout: &self.sprite_bind_group_layout,
+                label: Some("sprites:trail_bg"),
+                layout,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: camera_buffer.as_entire_binding(),
+                        resource: trail_buffer.as_entire_binding(),
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
-                        resource: buffer_world.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: buffer_sheet.as_entire_binding(),
+                        resource: prev_buffer.as_entire_binding(),
                     },
                 ],
             })
         } else {
             gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
-                label: None,
-                layout: &self.sprite_bind_group_layout,
+                label: Some("sprites:trail_bg"),
+                layout,
                 entries: &[wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: camera_buffer.as_entire_binding(),
+                    resource: trail_buffer.as_entire_binding(),
                 }],
             })
         };
         gpu.queue()
-            .write_buffer(&buffer_world, 0, bytemuck::cast_slice(&world_transforms));
-        gpu.queue()
-            .write_buffer(&buffer_sheet, 0, bytemuck::cast_slice(&sheet_regions));
+            .write_buffer(&trail_buffer, 0, bytemuck::bytes_of(&trail));
+        // Start with no motion: the previous transforms are the current ones
         gpu.queue()
-            .write_buffer(&camera_buffer, 0, bytemuck::bytes_of(&camera));
-        self.groups[group_idx] = Some(SpriteGroup {
-            world_buffer: buffer_world,
-            sheet_buffer: buffer_sheet,
-            world_transforms,
-            sheet_regions,
-            tex_bind_group,
-            sprite_bind_group,
-            camera,
-            camera_buffer,
-        });
-        group_idx
-    }
-    /// Returns the number of sprite groups (including placeholders for removed groups).
-    pub fn sprite_group_count(&self) -> usize {
-        self.groups.len()
-    }
-    /// Deletes a sprite group, leaving an empty group slot behind (this might get recycled later).
-    pub fn remove_sprite_group(&mut self, which: usize) {
-        if self.groups[which].is_some() {
-            self.groups[which] = None;
-            self.free_groups.push(which);
-        }
-    }
-    /// Reports the size of the given sprite group.  Panics if the given sprite group is not populated.
-    pub fn sprite_group_size(&self, which: usize) -> usize {
-        self.groups[which].as_ref().unwrap().world_transforms.len()
-    }
-    /// Resizes a sprite group.  If the new size is smaller, this is
-    /// very cheap; if it's larger than it's ever been before, it
-    /// might involve reallocating the [`Vec<Transform>`],
-    /// [`Vec<SheetRegion>`], or the GPU buffer used to draw sprites,
-    /// so it could be expensive.  If this happens, the buffer will
-    /// also be uploaded to prevent garbage data from being used in
-    /// the shader.  To avoid redundant work, resize upwards as few
-    /// times as possible.
-    ///
-    /// Panics if the given sprite group is not populated.
-    pub fn resize_sprite_group(&mut self, gpu: &WGPU, which: usize, len: usize) -> usize {
-        let group = &mut self.groups[which].as_mut().unwrap();
-        let old_len = group.world_transforms.len();
-        if old_len == len {
-            return old_len;
+            .write_buffer(&prev_buffer, 0, bytemuck::cast_slice(world_transforms));
+        TrailData {
+            trail,
+            trail_buffer,
+            prev_buffer,
+            uploaded_transforms: world_transforms.to_vec(),
+            bind_group,
         }
-        assert_eq!(old_len, group.sheet_regions.len());
-        // shrink or grow sprite vecs
-        group.world_transforms.resize(len, Transform::zeroed());
-        group.sheet_regions.resize(len, SheetRegion::zeroed());
-        // realloc buffer if needed, remake sprite_bind_group if using storage buffers
-        let new_size = len * std::mem::size_of::<Transform>();
-        if new_size > group.world_buffer.size() as usize {
-            group.world_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-                label: None,
-                size: new_size as u64,
-                usage: if self.use_storage {
-                    wgpu::BufferUsages::STORAGE
-                } else {
-                    wgpu::BufferUsages::VERTEX
-                } | wgpu::BufferUsages::COPY_DST,
-                mapped_at_creation: false,
-            });
-            group.sheet_buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
-                label: None,
-                size: new_size as u64,
-                usage: if self.use_storage {
-                    wgpu::BufferUsages::STORAGE
-                } else {
-                    wgpu::BufferUsages::VERTEX
-                } | wgpu::BufferUsages::COPY_DST,
-                mapped_at_creation: false,
-            });
-            if self.use_storage {
-                group.sprite_bind_group =
-                    gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: None,
-                        layout: &self.sprite_bind_group_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: group.camera_buffer.as_entire_binding(),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: group.world_buffer.as_entire_binding(),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 2,
-                                resource: group.sheet_buffer.as_entire_binding(),
-                            },
-                        ],
-                    });
-            };
-            gpu.queue().write_buffer(
-                &group.world_buffer,
-                0,
-                bytemuck::cast_slice(&group.world_transforms),
-            );
-            gpu.queue().write_buffer(
-                &group.sheet_buffer,
-                0,
-                bytemuck::cast_slice(&group.sheet_regions),
-            );
-        }
-        old_len
     }
     /// Set the given camera transform on all sprite groups.  Uploads to the GPU.
     pub fn set_camera_all(&mut self, gpu: &WGPU, camera: Camera2D) {
@@ -580,21 +3293,86 @@ impl SpriteRenderer {
     pub fn set_camera(&mut self, gpu: &WGPU, which: usize, camera: Camera2D) {
         let sg = &mut self.groups[which].as_mut().unwrap();
         sg.camera = camera;
+        sg.camera_staged = false;
         gpu.queue()
             .write_buffer(&sg.camera_buffer, 0, bytemuck::bytes_of(&sg.camera));
     }
+    /// Set the given camera transform on a specific sprite group
+    /// without uploading it yet.  [`SpriteRenderer::upload_cameras`]
+    /// uploads every staged camera, so staging a group's camera
+    /// several times before then costs only one write.
+    /// Panics if the given sprite group is not populated.
+    pub fn stage_camera(&mut self, which: usize, camera: Camera2D) {
+        let sg = &mut self.groups[which].as_mut().unwrap();
+        sg.camera = camera;
+        sg.camera_staged = true;
+    }
+    /// Uploads the cameras staged with [`SpriteRenderer::stage_camera`].
+    pub fn upload_cameras(&mut self, gpu: &WGPU) {
+        for sg in self.groups.iter_mut().flatten() {
+            if std::mem::take(&mut sg.camera_staged) {
+                gpu.queue()
+                    .write_buffer(&sg.camera_buffer, 0, bytemuck::bytes_of(&sg.camera));
+            }
+        }
+    }
+    /// Set a specific sprite group's camera to one blended between
+    /// `prev` and `current` by `alpha`, see [`Camera2D::interpolate`].
+    /// Call this every rendered frame when the camera follows a
+    /// fixed-step simulation, so it moves smoothly at any frame rate.
+    pub fn set_camera_interpolated(
+        &mut self,
+        gpu: &WGPU,
+        which: usize,
+        prev: Camera2D,
+        current: Camera2D,
+        alpha: f32,
+    ) {
+        self.set_camera(gpu, which, prev.interpolate(&current, alpha))
+    }
+    /// Returns the camera of a specific sprite group, or `None` if
+    /// the group has been removed.
+    pub fn camera(&self, which: usize) -> Option<Camera2D> {
+        self.groups[which].as_ref().map(|sg| sg.camera)
+    }
     /// Send a range of stored sprite data for a particular group to the GPU.
     /// You must call this yourself after modifying sprite data.
     /// Panics if the given sprite group is not populated.
     pub fn upload_sprites(&mut self, gpu: &WGPU, which: usize, range: Range<usize>) {
         let range = crate::range(range, self.sprite_group_size(which));
+        trace_span!(
+            "sprites:upload",
+            group = which,
+            count = range.len(),
+            bytes = range.len()
+                * (std::mem::size_of::<Transform>() + std::mem::size_of::<SheetRegion>())
+        );
         self.upload_world_transforms(gpu, which, range.clone());
         self.upload_sheet_regions(gpu, which, range);
     }
     /// Upload only position changes to the GPU.
     /// Panics if the given sprite group is not populated.
+    /// For compact groups, this uploads their sprites.
     pub fn upload_world_transforms(&mut self, gpu: &WGPU, which: usize, range: Range<usize>) {
-        let group = self.groups[which].as_ref().unwrap();
+        let group = self.groups[which].as_mut().unwrap();
+        if let Some(compact) = group.compact.as_ref() {
+            gpu.queue().write_buffer(
+                &group.sheet_buffer,
+                (range.start * std::mem::size_of::<CompactSprite>()) as u64,
+                bytemuck::cast_slice(&compact.sprites[range]),
+            );
+            return;
+        }
+        if let Some(trail) = group.trail.as_mut() {
+            // What was uploaded last time becomes the previous transforms
+            gpu.queue().write_buffer(
+                &trail.prev_buffer,
+                (range.start * std::mem::size_of::<Transform>()) as u64,
+                bytemuck::cast_slice(&trail.uploaded_transforms[range.clone()]),
+            );
+            trail.uploaded_transforms[range.clone()]
+                .copy_from_slice(&group.world_transforms[range.clone()]);
+        }
         gpu.queue().write_buffer(
             &group.world_buffer,
             (range.start * std::mem::size_of::<Transform>()) as u64,
@@ -603,14 +3381,49 @@ impl SpriteRenderer {
     }
     /// Upload only visual changes to the GPU.
     /// Panics if the given sprite group is not populated.
+    /// This does nothing for compact groups, whose sprites are
+    /// uploaded by [`SpriteRenderer::upload_world_transforms`].
     pub fn upload_sheet_regions(&mut self, gpu: &WGPU, which: usize, range: Range<usize>) {
         let group = self.groups[which].as_ref().unwrap();
+        if group.compact.is_some() {
+            return;
+        }
         gpu.queue().write_buffer(
             &group.sheet_buffer,
             (range.start * std::mem::size_of::<SheetRegion>()) as u64,
             bytemuck::cast_slice(&group.sheet_regions[range]),
         );
     }
+    /// Returns the index of the topmost sprite of a specific sprite
+    /// group whose rectangle contains the world space point `world`,
+    /// see [`crate::pick`].  With depth testing, that's the nearest
+    /// sprite (the first of several at the same depth); otherwise it's
+    /// the last one drawn.  Compact groups are never picked.
+    /// Panics if the given sprite group is not populated.
+    pub fn pick(&self, which: usize, world: [f32; 2]) -> Option<usize> {
+        self.pick_with_depth(which, world).map(|(index, _)| index)
+    }
+    // Like pick, but also gives the sprite's depth
+    pub(crate) fn pick_with_depth(&self, which: usize, world: [f32; 2]) -> Option<(usize, f32)> {
+        let group = self.groups[which].as_ref().unwrap();
+        if group.compact.is_some() {
+            return None;
+        }
+        let grid = group
+            .pick_grid
+            .get_or_init(|| crate::pick::PickGrid::new(&group.world_transforms));
+        let hits = grid
+            .candidates(world)
+            .iter()
+            .map(|&index| index as usize)
+            .filter(|&index| crate::pick::sprite_contains(&group.world_transforms[index], world))
+            .map(|index| (index, group.sprite_depth(index)));
+        if group.depth_mode.test {
+            hits.reduce(|best, hit| if hit.1 < best.1 { hit } else { best })
+        } else {
+            hits.last()
+        }
+    }
     /// Get a read-only slice of a specified sprite group's world transforms and texture regions.
     /// Panics if the given sprite group is not populated.
     pub fn get_sprites(&self, which: usize) -> (&[Transform], &[SheetRegion]) {
@@ -618,11 +3431,55 @@ impl SpriteRenderer {
         (&group.world_transforms, &group.sheet_regions)
     }
     /// Get a mutable slice of a specified sprite group's world transforms and texture regions.
+    /// These are empty for compact groups.
     /// Panics if the given sprite group is not populated.
     pub fn get_sprites_mut(&mut self, which: usize) -> (&mut [Transform], &mut [SheetRegion]) {
         let group = self.groups[which].as_mut().unwrap();
+        group.pick_grid.take();
         (&mut group.world_transforms, &mut group.sheet_regions)
     }
+    /// Get a read-only slice of a compact sprite group's sprites.
+    /// Panics if the given sprite group is not a populated compact group.
+    pub fn get_compact_sprites(&self, which: usize) -> &[CompactSprite] {
+        let group = self.groups[which].as_ref().unwrap();
+        &group
+            .compact
+            .as_ref()
+            .expect("Sprite group is not compact")
+            .sprites
+    }
+    /// Get a mutable slice of a compact sprite group's sprites, to be
+    /// uploaded with [`SpriteRenderer::upload_sprites`].
+    /// Panics if the given sprite group is not a populated compact group.
+    pub fn get_compact_sprites_mut(&mut self, which: usize) -> &mut [CompactSprite] {
+        let group = self.groups[which].as_mut().unwrap();
+        &mut group
+            .compact
+            .as_mut()
+            .expect("Sprite group is not compact")
+            .sprites
+    }
+    /// Get mutable slices of every populated sprite group's world
+    /// transforms and texture regions at once, along with each
+    /// group's index.  Since the slices are disjoint, they can be
+    /// modified in parallel (e.g. from different threads) before
+    /// being uploaded with [`SpriteRenderer::upload_sprites`].
+    pub fn get_groups_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (usize, &mut [Transform], &mut [SheetRegion])> {
+        self.groups
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(which, group)| {
+                let group = group.as_mut()?;
+                group.pick_grid.take();
+                Some((
+                    which,
+                    &mut group.world_transforms[..],
+                    &mut group.sheet_regions[..],
+                ))
+            })
+    }
     /// Render the given range of sprite groups into the given pass.
     pub fn render<'s, 'pass>(
         &'s self,
@@ -634,24 +3491,156 @@ impl SpriteRenderer {
         if self.groups.is_empty() {
             return;
         }
-        rpass.set_pipeline(&self.pipeline);
         let which = crate::range(which, self.groups.len());
         for group in self.groups[which].iter().filter_map(|o| o.as_ref()) {
-            if group.world_transforms.is_empty() {
-                continue;
-            }
+            self.draw_group(rpass, group);
+        }
+    }
+    /// Render a single sprite group into the given pass, e.g. to
+    /// interleave sprite groups with other renderers' draws.  Does
+    /// nothing if the group is not populated.
+    pub fn render_group<'s, 'pass>(&'s self, rpass: &mut wgpu::RenderPass<'pass>, which: usize)
+    where
+        's: 'pass,
+    {
+        if let Some(group) = self.groups.get(which).and_then(|o| o.as_ref()) {
+            self.draw_group(rpass, group);
+        }
+    }
+    /// Render the given sprite groups into the given pass in the
+    /// order listed, skipping any which are not populated.
+    pub fn render_groups<'s, 'pass>(&'s self, rpass: &mut wgpu::RenderPass<'pass>, which: &[usize])
+    where
+        's: 'pass,
+    {
+        for &which in which {
+            self.render_group(rpass, which);
+        }
+    }
+    // Issues the draws for one sprite group
+    fn draw_group<'s, 'pass>(&'s self, rpass: &mut wgpu::RenderPass<'pass>, group: &'s SpriteGroup)
+    where
+        's: 'pass,
+    {
+        if group.len() == 0 {
+            return;
+        }
+        #[cfg(feature = "debug")]
+        if let Some(name) = group.name.as_deref() {
+            rpass.insert_debug_marker(name);
+        }
+        if let Some(sdf) = group.sdf.as_ref() {
+            rpass.set_pipeline(&self.sdf_pipelines[group.depth_mode.test as usize]);
             if !self.use_storage {
                 rpass.set_vertex_buffer(0, group.world_buffer.slice(..));
                 rpass.set_vertex_buffer(1, group.sheet_buffer.slice(..));
             }
             rpass.set_bind_group(0, &group.sprite_bind_group, &[]);
             rpass.set_bind_group(1, &group.tex_bind_group, &[]);
-            // draw two triangles per sprite, and sprites-many sprites.
-            // this uses instanced drawing, but it would also be okay
-            // to draw 6 * sprites.len() vertices and use modular arithmetic
-            // to figure out which sprite we're drawing.
-            assert_eq!(group.world_transforms.len(), group.sheet_regions.len());
-            rpass.draw(0..6, 0..group.world_transforms.len() as u32);
+            rpass.set_bind_group(2, &sdf.bind_group, &[]);
+            rpass.draw(0..6, 0..group.len() as u32);
+        }
+        let depth = group.depth_mode.index();
+        let variant = group
+            .shader
+            .and_then(|idx| self.shader_variants[idx].pipelines.as_ref());
+        if group.compact.is_some() {
+            rpass.set_pipeline(&variant.map_or(&self.compact_pipelines, |v| &v.compact)[depth]);
+        } else if group.palette.is_some() {
+            rpass.set_pipeline(&self.indexed_pipelines[depth]);
+        } else {
+            rpass.set_pipeline(&variant.map_or(&self.pipelines, |v| &v.plain)[depth]);
+        }
+        if !self.use_storage {
+            if group.compact.is_some() {
+                rpass.set_vertex_buffer(0, group.sheet_buffer.slice(..));
+            } else {
+                rpass.set_vertex_buffer(0, group.world_buffer.slice(..));
+                rpass.set_vertex_buffer(1, group.sheet_buffer.slice(..));
+            }
+        }
+        rpass.set_bind_group(0, &group.sprite_bind_group, &[]);
+        rpass.set_bind_group(1, &group.tex_bind_group, &[]);
+        // draw two triangles per sprite, and sprites-many sprites.
+        // this uses instanced drawing, but it would also be okay
+        // to draw 6 * sprites.len() vertices and use modular arithmetic
+        // to figure out which sprite we're drawing.
+        // Shaped sprites are fans of up to MAX_POINTS - 2 triangles
+        assert_eq!(group.world_transforms.len(), group.sheet_regions.len());
+        let vertices = if group.shapes.is_empty() {
+            6
+        } else {
+            3 * (SpriteShape::MAX_POINTS as u32 - 2)
+        };
+        rpass.draw(0..vertices, 0..group.len() as u32);
+        if let Some(trail) = group.trail.as_ref() {
+            if trail.trail.copies == 0 {
+                return;
+            }
+            rpass.set_pipeline(&self.trail_pipelines[group.depth_mode.test as usize]);
+            if !self.use_storage {
+                rpass.set_vertex_buffer(2, trail.prev_buffer.slice(..));
+            }
+            rpass.set_bind_group(2, &trail.bind_group, &[]);
+            // Each instance draws all of its ghosts, six vertices apiece.
+            rpass.draw(
+                0..(6 * trail.trail.copies),
+                0..group.world_transforms.len() as u32,
+            );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_animation_loops() {
+        let anim = LayerAnimation::new(4, 3, 10.0);
+        assert_eq!(anim.duration(), 0.3);
+        let layers: Vec<_> = [0.0, 0.05, 0.15, 0.25, 0.35, 0.45]
+            .iter()
+            .map(|&t| anim.layer(t))
+            .collect();
+        assert_eq!(layers, [4, 4, 5, 6, 4, 5]);
+        let held = anim.with_looping(false);
+        assert_eq!(held.layer(0.15), 5);
+        assert_eq!(held.layer(0.25), 6);
+        assert_eq!(held.layer(100.0), 6);
+    }
+    #[test]
+    fn test_layer_animation_edge_cases() {
+        for anim in [
+            LayerAnimation::new(2, 5, 12.0),
+            LayerAnimation::new(2, 5, 12.0).with_looping(false),
+        ] {
+            assert_eq!(anim.layer(-1.0), 2);
+            assert_eq!(anim.layer(f32::NAN), 2);
+        }
+        // No frames or no speed just shows the first layer
+        assert_eq!(LayerAnimation::new(7, 0, 12.0).layer(3.0), 7);
+        assert_eq!(
+            LayerAnimation::new(7, 0, 12.0)
+                .with_looping(false)
+                .layer(3.0),
+            7
+        );
+        assert_eq!(LayerAnimation::new(7, 4, 0.0).layer(3.0), 7);
+    }
+    #[test]
+    fn test_layer_animation_apply() {
+        let anim = LayerAnimation::new(1, 4, 2.0);
+        let region = SheetRegion::new(0, 8, 16, 3, 32, 32);
+        let moved = anim.apply(region, 1.0);
+        assert_eq!(moved.sheet, 3);
+        assert_eq!((moved.x, moved.y, moved.depth), (8, 16, 3));
+        let mut regions = [region; 3];
+        anim.apply_all(&mut regions, &[0.0, 0.5]);
+        let sheets: Vec<_> = regions.iter().map(|r| r.sheet).collect();
+        assert_eq!(sheets, [1, 2, 0]);
+        // Extra times are ignored too
+        anim.apply_all(&mut regions[..1], &[1.5, 2.0, 3.0]);
+        assert_eq!(regions[0].sheet, 4);
+    }
+}
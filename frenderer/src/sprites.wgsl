@@ -12,6 +12,74 @@ var<private> VERTICES:array<vec2<f32>,6> = array<vec2<f32>,6>(
 struct Camera {
     screen_pos: vec2<f32>,
     screen_size: vec2<f32>,
+    // Y-sort axis, then depth units per world unit (zero when y-sorting is off)
+    sort: vec4<f32>,
+    // 3D camera near and far planes, world units per depth unit, and whether world depth is on
+    world_depth: vec4<f32>,
+    // The number of sprite shapes, 1 if the group is pixel-perfect, then padding
+    shape_info: vec4<u32>,
+    // Each shape's unflipped region: x, y, w, h
+    shape_regions: array<vec4<f32>, 16>,
+    // Each shape's sheet and number of corners
+    shape_sheets: array<vec4<u32>, 16>,
+    // Each shape's eight corners, two per entry
+    shape_points: array<vec4<f32>, 64>,
+    // The group's color grade, applied to RGB colors
+    grade: mat4x4<f32>,
+    // A compact group's origin and quantum, then padding
+    compact_origin: vec4<f32>,
+    // A compact group's sheet and depth (packed like UVData), colormod, then padding
+    compact_region: vec4<u32>,
+}
+
+// The corner of a sprite (from -0.5 to 0.5) for this vertex: a quad
+// corner if the sprite's region has no shape, or a corner of the
+// shape's triangle fan otherwise, with unused triangles collapsed
+fn sprite_corner(uvs:UVData, vertex:u32) -> vec2<f32> {
+  let count = camera.shape_info.x;
+  if (count == 0u) {
+    return VERTICES[vertex];
+  }
+  let xy = unpack2x16unorm(uvs.xy)*65535.0;
+  let wh = unpack2x16snorm(uvs.wh)*32767.0;
+  let flipped = wh < vec2(0.0);
+  let region = vec4(select(xy, xy + wh, flipped), abs(wh));
+  let sheet = uvs.sheet_depth & 0x0000FFFFu;
+  for (var i = 0u; i < count; i++) {
+    if (camera.shape_sheets[i].x != sheet || any(camera.shape_regions[i] != region)) {
+      continue;
+    }
+    let points = camera.shape_sheets[i].y;
+    let tri = vertex / 3u;
+    let corner = vertex % 3u;
+    if (tri + 2u >= points) {
+      return vec2(0.0);
+    }
+    var idx = select(tri + corner, 0u, corner == 0u);
+    // Flipping one axis mirrors the shape, so walk it backwards to keep it counterclockwise
+    if (flipped.x != flipped.y) {
+      idx = (points - idx) % points;
+    }
+    let pair = camera.shape_points[i * 4u + idx / 2u];
+    let point = select(pair.xy, pair.zw, idx % 2u == 1u);
+    return select(point, -point, flipped);
+  }
+  if (vertex < 6u) {
+    return VERTICES[vertex];
+  }
+  return vec2(0.0);
+}
+
+// Convert a sprite's depth (in SheetRegion depth units) to a depth buffer value
+fn depth_value(depth:f32) -> f32 {
+  if (camera.world_depth.w == 0.0) {
+    return clamp(depth / 65535.0, 0.0, 1.0);
+  }
+  // Match the mesh renderers' perspective projection
+  let near = camera.world_depth.x;
+  let far = camera.world_depth.y;
+  let dist = max(depth * camera.world_depth.z, near);
+  return clamp(far / (far - near) * (1.0 - near / dist), 0.0, 1.0);
 }
 
 struct UVData {
@@ -32,7 +100,10 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
     @location(1) @interpolate(flat) tex_index: u32,
-    @location(2) colormod: vec4<f32>
+    @location(2) colormod: vec4<f32>,
+    @location(3) alpha: f32,
+    // The range texture coordinates are clamped to: min xy, then max xy
+    @location(4) @interpolate(flat) uv_bounds: vec4<f32>
 }
 
 fn u32_to_vec4(in:u32) -> vec4<f32> {
@@ -43,7 +114,7 @@ fn u32_to_vec4(in:u32) -> vec4<f32> {
   return vec4(f32(r)/255.0, f32(g)/255.0, f32(b)/255.0, f32(a)/255.0);
 }
 
-fn sprite_to_vert(trf:vec4<f32>, uvs:UVData, norm_vert:vec2<f32>) -> VertexOutput {
+fn sprite_to_vert(trf:vec4<f32>, uvs:UVData, norm_vert:vec2<f32>, tex_size:vec2<u32>) -> VertexOutput {
   let center:vec2<f32> = trf.yz;
   let size_bits:u32 = bitcast<u32>(trf.x);
   let size:vec2<f32> = vec2(f32(size_bits & 0x0000FFFFu),
@@ -51,7 +122,6 @@ fn sprite_to_vert(trf:vec4<f32>, uvs:UVData, norm_vert:vec2<f32>) -> VertexOutpu
                             );
   let tex_layer = uvs.sheet_depth & 0x0000FFFFu;
   let tex_depth = (uvs.sheet_depth & 0xFFFF0000u) >> 16u;
-  let tex_size:vec2<u32> = textureDimensions(t_diffuse);
   let rot:f32 = trf.w;
   let sinrot:f32 = sin(rot);
   let cosrot:f32 = cos(rot);
@@ -62,8 +132,13 @@ fn sprite_to_vert(trf:vec4<f32>, uvs:UVData, norm_vert:vec2<f32>) -> VertexOutpu
                      scaled.x*sinrot+scaled.y*cosrot
                      );
   // now translate by trf (center, size):
-  let world_pos = (center) + rotated;
-  let camera_pos = world_pos - camera.screen_pos;
+  let pixel_perfect = camera.shape_info.y != 0u;
+  var camera_center = center - camera.screen_pos;
+  if (pixel_perfect) {
+    // Put the unrotated bottom left corner, and so every edge, on a whole pixel
+    camera_center = floor(camera_center - size * 0.5 + 0.5) + size * 0.5;
+  }
+  let camera_pos = camera_center + rotated;
   let box_pos = camera_pos / (camera.screen_size*0.5);
   let ndc_pos = vec4(box_pos.xy, 0.0, 1.0) - vec4(1.0, 1.0, 0.0, 0.0);
   let tex_uvxy:vec2<f32> = unpack2x16unorm(uvs.xy)*65535.0;
@@ -71,9 +146,18 @@ fn sprite_to_vert(trf:vec4<f32>, uvs:UVData, norm_vert:vec2<f32>) -> VertexOutpu
   let tex_corner = vec2(tex_uvxy.x / f32(tex_size.x), tex_uvxy.y / f32(tex_size.y));
   let tex_uv_size = vec2(tex_uvwh.x / f32(tex_size.x), tex_uvwh.y / f32(tex_size.y));
   let norm_uv = vec2(norm_vert.x+0.5, 1.0-(norm_vert.y+0.5));
+  // Pixel-perfect sprites never sample within half a texel of their region's edges
+  let half_texel = select(vec2(0.0), 0.5 / vec2<f32>(tex_size), pixel_perfect);
+  let uv_min = min(tex_corner, tex_corner + tex_uv_size) + half_texel;
+  let uv_max = max(tex_corner, tex_corner + tex_uv_size) - half_texel;
+  // Otherwise the bounds are loose enough for regions repeating their layer many times
+  let uv_bounds = select(vec4(-65536.0, -65536.0, 65536.0, 65536.0), vec4(uv_min, max(uv_min, uv_max)), pixel_perfect);
   let colormod = u32_to_vec4(uvs.colormod);
+  // Y-sorting pushes sprites back by how far their bottom edge is along the sort axis
+  let feet = dot(center - camera.screen_pos, camera.sort.xy) - 0.5 * dot(size, abs(camera.sort.xy));
+  let depth = depth_value(f32(tex_depth) + feet * camera.sort.z);
   // Larger y = smaller depth = closer to screen
-  return VertexOutput(ndc_pos+vec4(0.0, 0.0, f32(tex_depth)/65535.0, 0.0), tex_corner + norm_uv*tex_uv_size, tex_layer, colormod);
+  return VertexOutput(ndc_pos+vec4(0.0, 0.0, depth, 0.0), tex_corner + norm_uv*tex_uv_size, tex_layer, colormod, 1.0, uv_bounds);
 }
 
 @vertex
@@ -81,7 +165,7 @@ fn vs_storage_main(@builtin(vertex_index) in_vertex_index: u32, @builtin(instanc
   // We'll just look up the vertex data in those constant arrays
   let trf = s_world[sprite_index];
   let uvs = s_sheet[sprite_index];
-  return sprite_to_vert(trf, uvs, VERTICES[in_vertex_index]);
+  return sprite_to_vert(trf, uvs, sprite_corner(uvs, in_vertex_index), textureDimensions(t_diffuse));
 }
 
 @vertex
@@ -90,14 +174,178 @@ fn vs_storage_noinstance_main(@builtin(vertex_index) in_vertex_index: u32) -> Ve
     let vertex_index:u32 = in_vertex_index % u32(6);
     let trf = s_world[sprite_index];
     let uvs = s_sheet[sprite_index];
-    return sprite_to_vert(trf, uvs, VERTICES[vertex_index]);
+    return sprite_to_vert(trf, uvs, VERTICES[vertex_index], textureDimensions(t_diffuse));
 }
 
 @vertex
 fn vs_vbuf_main(@builtin(vertex_index) in_vertex_index: u32, @location(0) trf:vec4<f32>, @location(1) sheet_region:vec4<u32>) -> VertexOutput {
-  return sprite_to_vert(trf, UVData(sheet_region.x, sheet_region.y, sheet_region.z, sheet_region.w), VERTICES[in_vertex_index]);
+  let uvs = UVData(sheet_region.x, sheet_region.y, sheet_region.z, sheet_region.w);
+  return sprite_to_vert(trf, uvs, sprite_corner(uvs, in_vertex_index), textureDimensions(t_diffuse));
+}
+
+// Compact sprites (see CompactSprite) are a quantized center, a size,
+// and a region's xy and wh; the rest comes from the group's layout
+fn compact_to_vert(sprite:vec4<u32>, vertex:u32) -> VertexOutput {
+  let quantized = vec2(f32(sprite.x & 0x0000FFFFu), f32(sprite.x >> 16u));
+  let center = camera.compact_origin.xy + quantized * camera.compact_origin.z;
+  let trf = vec4(bitcast<f32>(sprite.y), center, 0.0);
+  let uvs = UVData(camera.compact_region.x, sprite.z, sprite.w, camera.compact_region.y);
+  return sprite_to_vert(trf, uvs, sprite_corner(uvs, vertex), textureDimensions(t_diffuse));
 }
 
+// Compact groups keep their sprites where sheet regions would go
+@vertex
+fn vs_storage_compact_main(@builtin(vertex_index) in_vertex_index: u32, @builtin(instance_index) sprite_index:u32) -> VertexOutput {
+  let sprite = s_sheet[sprite_index];
+  return compact_to_vert(vec4(sprite.sheet_depth, sprite.xy, sprite.wh, sprite.colormod), in_vertex_index);
+}
+
+@vertex
+fn vs_vbuf_compact_main(@builtin(vertex_index) in_vertex_index: u32, @location(1) sprite:vec4<u32>) -> VertexOutput {
+  return compact_to_vert(sprite, in_vertex_index);
+}
+
+// Indexed-color sprites use the same vertex logic, but their
+// spritesheet holds palette indices instead of colors.
+@vertex
+fn vs_storage_indexed_main(@builtin(vertex_index) in_vertex_index: u32, @builtin(instance_index) sprite_index:u32) -> VertexOutput {
+  let trf = s_world[sprite_index];
+  let uvs = s_sheet[sprite_index];
+  return sprite_to_vert(trf, uvs, sprite_corner(uvs, in_vertex_index), textureDimensions(t_indexed));
+}
+
+@vertex
+fn vs_vbuf_indexed_main(@builtin(vertex_index) in_vertex_index: u32, @location(0) trf:vec4<f32>, @location(1) sheet_region:vec4<u32>) -> VertexOutput {
+  let uvs = UVData(sheet_region.x, sheet_region.y, sheet_region.z, sheet_region.w);
+  return sprite_to_vert(trf, uvs, sprite_corner(uvs, in_vertex_index), textureDimensions(t_indexed));
+}
+
+struct Trail {
+    copies: u32,
+    alpha: f32,
+    falloff: f32,
+    _padding: u32,
+}
+
+@group(2) @binding(0)
+var<uniform> trail: Trail;
+@group(2) @binding(1)
+var<storage, read> s_world_prev: array<vec4<f32>>;
+
+// Each sprite instance draws all its ghosts: vertex 6*k..6*(k+1) belongs to ghost k+1.
+fn trail_to_vert(trf:vec4<f32>, prev:vec4<f32>, uvs:UVData, in_vertex_index:u32) -> VertexOutput {
+  let ghost:u32 = in_vertex_index / 6u + 1u;
+  let t:f32 = f32(ghost) / f32(trail.copies + 1u);
+  // Keep the current size bits, but move and rotate back towards the previous transform
+  let ghost_trf = vec4(trf.x, mix(trf.yzw, prev.yzw, t));
+  var out = sprite_to_vert(ghost_trf, uvs, VERTICES[in_vertex_index % 6u], textureDimensions(t_diffuse));
+  out.alpha = trail.alpha * pow(trail.falloff, f32(ghost - 1u));
+  return out;
+}
+
+@vertex
+fn vs_storage_trail_main(@builtin(vertex_index) in_vertex_index: u32, @builtin(instance_index) sprite_index:u32) -> VertexOutput {
+  return trail_to_vert(s_world[sprite_index], s_world_prev[sprite_index], s_sheet[sprite_index], in_vertex_index);
+}
+
+@vertex
+fn vs_vbuf_trail_main(@builtin(vertex_index) in_vertex_index: u32, @location(0) trf:vec4<f32>, @location(1) sheet_region:vec4<u32>, @location(2) prev:vec4<f32>) -> VertexOutput {
+  return trail_to_vert(trf, prev, UVData(sheet_region.x, sheet_region.y, sheet_region.z, sheet_region.w), in_vertex_index);
+}
+
+struct SdfEffect {
+    shadow_color: vec4<f32>,
+    glow_color: vec4<f32>,
+    outline_color: vec4<f32>,
+    // Shadow offset in world units, shadow softness and SDF spread in sheet pixels
+    shadow: vec4<f32>,
+    // Glow radius and outline width in sheet pixels, then padding
+    sizes: vec4<f32>,
+}
+
+@group(2) @binding(0)
+var<uniform> sdf_effect: SdfEffect;
+@group(2) @binding(1)
+var t_sdf: texture_2d_array<f32>;
+@group(2) @binding(2)
+var s_sdf: sampler;
+
+struct SdfVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) tex_index: u32,
+    // How far the shadow is shifted in texture coordinates
+    @location(2) @interpolate(flat) shadow_uv: vec2<f32>,
+}
+
+// Effects are drawn on a quad grown by the SDF's spread plus the
+// shadow's offset, in sheet pixels, on every side of the sprite
+fn sdf_to_vert(trf:vec4<f32>, uvs:UVData, vertex:u32) -> SdfVertexOutput {
+  let size_bits:u32 = bitcast<u32>(trf.x);
+  let size = vec2(f32(size_bits & 0x0000FFFFu), f32(size_bits >> 16u));
+  let tex_size = vec2<f32>(textureDimensions(t_diffuse));
+  let region = unpack2x16snorm(uvs.wh)*32767.0;
+  let region_px = max(abs(region), vec2(1.0));
+  // The shadow offset in the sprite's unrotated frame, then in sheet pixels
+  let rot = trf.w;
+  let offset = sdf_effect.shadow.xy;
+  let local = vec2(offset.x*cos(rot) + offset.y*sin(rot), -offset.x*sin(rot) + offset.y*cos(rot));
+  let offset_px = local / max(size, vec2(1.0)) * region_px;
+  let margin = sdf_effect.shadow.w + length(offset_px);
+  let grow = 1.0 + 2.0 * margin / region_px;
+  let base = sprite_to_vert(trf, uvs, VERTICES[vertex] * grow, textureDimensions(t_diffuse));
+  // Texture coordinates run down the sheet while local y runs up
+  let shadow_uv = vec2(offset_px.x, -offset_px.y) * sign(region) / tex_size;
+  return SdfVertexOutput(base.clip_position, base.tex_coords, base.tex_index, shadow_uv);
+}
+
+@vertex
+fn vs_storage_sdf_main(@builtin(vertex_index) in_vertex_index: u32, @builtin(instance_index) sprite_index:u32) -> SdfVertexOutput {
+  return sdf_to_vert(s_world[sprite_index], s_sheet[sprite_index], in_vertex_index);
+}
+
+@vertex
+fn vs_vbuf_sdf_main(@builtin(vertex_index) in_vertex_index: u32, @location(0) trf:vec4<f32>, @location(1) sheet_region:vec4<u32>) -> SdfVertexOutput {
+  return sdf_to_vert(trf, UVData(sheet_region.x, sheet_region.y, sheet_region.z, sheet_region.w), in_vertex_index);
+}
+
+// The distance in sheet pixels from the sprite's edge, negative inside
+fn sdf_distance(uv:vec2<f32>, layer:u32) -> f32 {
+  let value = textureSampleLevel(t_sdf, s_sdf, uv, layer, 0.0).r;
+  return (0.5 - value) * 2.0 * sdf_effect.shadow.w;
+}
+
+// Premultiplied `top` over premultiplied `bottom`
+fn over(top:vec4<f32>, bottom:vec4<f32>) -> vec4<f32> {
+  return top + bottom * (1.0 - top.a);
+}
+
+// Coverage of a shape whose edge is `dist` pixels away, softened over `soft` pixels
+fn coverage(dist:f32, soft:f32) -> f32 {
+  let s = max(soft, 0.5);
+  return 1.0 - smoothstep(-s, s, dist);
+}
+
+@fragment
+fn fs_sdf_main(in:SdfVertexOutput) -> @location(0) vec4<f32> {
+  let dist = sdf_distance(in.tex_coords, in.tex_index);
+  let shadow_dist = sdf_distance(in.tex_coords - in.shadow_uv, in.tex_index);
+  var color = vec4(0.0);
+  let shadow = sdf_effect.shadow_color.a * coverage(shadow_dist, sdf_effect.shadow.z);
+  color = over(vec4(sdf_effect.shadow_color.rgb * shadow, shadow), color);
+  let glow_radius = sdf_effect.sizes.x;
+  if (glow_radius > 0.0) {
+    let glow = sdf_effect.glow_color.a * (1.0 - smoothstep(0.0, glow_radius, max(dist, 0.0)));
+    color = over(vec4(sdf_effect.glow_color.rgb * glow, glow), color);
+  }
+  let width = sdf_effect.sizes.y;
+  if (width > 0.0) {
+    let outline = sdf_effect.outline_color.a * coverage(dist - width, 0.5);
+    color = over(vec4(sdf_effect.outline_color.rgb * outline, outline), color);
+  }
+  if (color.a < 0.004) { discard; }
+  return color;
+}
 
 // Now our fragment shader needs two "global" inputs to be bound:
 // A texture...
@@ -108,13 +356,50 @@ var t_diffuse: texture_2d_array<f32>;
 var s_diffuse: sampler;
 // Both are in the same binding group here since they go together naturally.
 
+// The texture coordinates to sample, kept within the sprite's bounds
+fn sprite_uv(in:VertexOutput) -> vec2<f32> {
+    return clamp(in.tex_coords, in.uv_bounds.xy, in.uv_bounds.zw);
+}
+
+// Apply the group's color grade to a final color
+fn graded(color:vec3<f32>) -> vec3<f32> {
+    return (camera.grade * vec4(color, 1.0)).xyz;
+}
+
 // Our fragment shader takes an interpolated `VertexOutput` as input now
 @fragment
 fn fs_main(in:VertexOutput) -> @location(0) vec4<f32> {
     // And we use the tex coords from the vertex output to sample from the texture.
-    let color:vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords, in.tex_index);
+    let color:vec4<f32> = textureSample(t_diffuse, s_diffuse, sprite_uv(in), in.tex_index);
     if color.w < 0.05 { discard; }
     // mod color by in.colormod
     let out_color = mix(color.xyz, in.colormod.xyz, in.colormod.w);
-    return vec4<f32>(out_color.xyz, 1.0);
+    return vec4<f32>(graded(out_color), 1.0);
+}
+
+@fragment
+fn fs_trail_main(in:VertexOutput) -> @location(0) vec4<f32> {
+    let color:vec4<f32> = textureSample(t_diffuse, s_diffuse, sprite_uv(in), in.tex_index);
+    if color.w < 0.05 { discard; }
+    let out_color = mix(color.xyz, in.colormod.xyz, in.colormod.w);
+    // Premultiplied, like the blend state of the target
+    return vec4<f32>(graded(out_color) * in.alpha, in.alpha);
+}
+
+// Indexed-color sprite groups bind a spritesheet of palette indices
+// and a 256x1 palette in place of the texture and sampler.
+@group(1) @binding(0)
+var t_indexed: texture_2d_array<u32>;
+@group(1) @binding(1)
+var t_palette: texture_2d<f32>;
+
+@fragment
+fn fs_indexed_main(in:VertexOutput) -> @location(0) vec4<f32> {
+    let dims = vec2<f32>(textureDimensions(t_indexed));
+    let texel = vec2<i32>(clamp(sprite_uv(in) * dims, vec2(0.0), dims - 1.0));
+    let index = textureLoad(t_indexed, texel, in.tex_index, 0).r;
+    let color:vec4<f32> = textureLoad(t_palette, vec2(index, 0u), 0);
+    if color.w < 0.05 { discard; }
+    let out_color = mix(color.xyz, in.colormod.xyz, in.colormod.w);
+    return vec4<f32>(graded(out_color), 1.0);
 }
@@ -1,5 +1,10 @@
+struct Camera {
+  projview: mat4x4<f32>,
+  // xyz: the camera's position in world space
+  eye: vec4<f32>,
+}
 @group(0) @binding(0)
-var<uniform> projview: mat4x4<f32>;
+var<uniform> camera: Camera;
 
 struct VertexInput {
   @location(0) position: vec3<f32>,
@@ -14,38 +19,130 @@ struct InstanceInput {
   @location(3) rot: vec4<f32>,
 }
 
+struct InstanceScaleInput {
+  @location(4) scale: vec3<f32>,
+}
+
+struct InstanceMaterialInput {
+  // material offset, packed RGBA colormod
+  @location(5) offset_colormod: vec2<u32>,
+}
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
     @location(1) @interpolate(flat) tex_index: u32,
+    @location(2) @interpolate(flat) colormod: vec4<f32>,
+    @location(3) world_position: vec3<f32>,
 }
 
 struct FlatVertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) @interpolate(flat) mat_index: u32,
+    @location(1) @interpolate(flat) colormod: vec4<f32>,
+    @location(2) world_position: vec3<f32>,
 }
 
-@vertex
-fn vs_main(vtx:VertexInput, inst:InstanceInput) -> VertexOutput {
+fn mesh_vert(vtx:VertexInput, model:mat4x4<f32>, offset:u32, colormod:vec4<f32>) -> VertexOutput {
   var out:VertexOutput;
-  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w);
   let transformed = model * vec4(vtx.position,1.0);
-  out.clip_position = projview * transformed;
+  out.clip_position = camera.projview * transformed;
+  out.world_position = transformed.xyz;
   out.tex_coords = vtx.uv_which.xy;
-  out.tex_index = bitcast<u32>(vtx.uv_which.z);
+  out.tex_index = bitcast<u32>(vtx.uv_which.z) + offset;
+  out.colormod = colormod;
   return out;
 }
-@vertex
-fn vs_flat_main(vtx:FlatVertexInput, inst:InstanceInput) -> FlatVertexOutput {
+fn flat_vert(vtx:FlatVertexInput, model:mat4x4<f32>, offset:u32, colormod:vec4<f32>) -> FlatVertexOutput {
   var out:FlatVertexOutput;
-  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w);
   let transformed = model * vec4(vtx.position_which.xyz,1.0);
-  out.clip_position = projview * transformed;
-  out.mat_index = bitcast<u32>(vtx.position_which.w);
+  out.clip_position = camera.projview * transformed;
+  out.world_position = transformed.xyz;
+  out.mat_index = min(bitcast<u32>(vtx.position_which.w) + offset, 255u);
+  out.colormod = colormod;
   return out;
 }
 
-fn mat_from_trs(t:vec3<f32>, r:vec4<f32>, s:f32) -> mat4x4<f32> {
+@vertex
+fn vs_main(vtx:VertexInput, inst:InstanceInput) -> VertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, vec3(inst.translate_scale.w));
+  return mesh_vert(vtx, model, 0u, vec4(0.0));
+}
+@vertex
+fn vs_nonuniform_main(vtx:VertexInput, inst:InstanceInput, scale:InstanceScaleInput) -> VertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w * scale.scale);
+  return mesh_vert(vtx, model, 0u, vec4(0.0));
+}
+@vertex
+fn vs_material_main(vtx:VertexInput, inst:InstanceInput, mat:InstanceMaterialInput) -> VertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, vec3(inst.translate_scale.w));
+  return mesh_vert(vtx, model, mat.offset_colormod.x, unpack4x8unorm(mat.offset_colormod.y));
+}
+@vertex
+fn vs_nonuniform_material_main(vtx:VertexInput, inst:InstanceInput, scale:InstanceScaleInput, mat:InstanceMaterialInput) -> VertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w * scale.scale);
+  return mesh_vert(vtx, model, mat.offset_colormod.x, unpack4x8unorm(mat.offset_colormod.y));
+}
+@vertex
+fn vs_flat_main(vtx:FlatVertexInput, inst:InstanceInput) -> FlatVertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, vec3(inst.translate_scale.w));
+  return flat_vert(vtx, model, 0u, vec4(0.0));
+}
+@vertex
+fn vs_flat_nonuniform_main(vtx:FlatVertexInput, inst:InstanceInput, scale:InstanceScaleInput) -> FlatVertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w * scale.scale);
+  return flat_vert(vtx, model, 0u, vec4(0.0));
+}
+@vertex
+fn vs_flat_material_main(vtx:FlatVertexInput, inst:InstanceInput, mat:InstanceMaterialInput) -> FlatVertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, vec3(inst.translate_scale.w));
+  return flat_vert(vtx, model, mat.offset_colormod.x, unpack4x8unorm(mat.offset_colormod.y));
+}
+@vertex
+fn vs_flat_nonuniform_material_main(vtx:FlatVertexInput, inst:InstanceInput, scale:InstanceScaleInput, mat:InstanceMaterialInput) -> FlatVertexOutput {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w * scale.scale);
+  return flat_vert(vtx, model, mat.offset_colormod.x, unpack4x8unorm(mat.offset_colormod.y));
+}
+
+// Each group (textured or flat) can also have an inverted-hull outline
+struct Outline {
+  color: vec4<f32>,
+  // x: how far vertices are pushed outward, in world units
+  thickness: vec4<f32>,
+}
+@group(1) @binding(3)
+var<uniform> outline: Outline;
+
+// Vertices don't have normals, so outlines push each vertex
+// directly away from its mesh's origin instead
+fn outline_vert(position:vec3<f32>, model:mat4x4<f32>) -> vec4<f32> {
+  let dir = (model * vec4(position, 0.0)).xyz;
+  let len = length(dir);
+  let push = select(vec3(0.0), dir / max(len, 0.0001) * outline.thickness.x, len > 0.0001);
+  return camera.projview * (model * vec4(position, 1.0) + vec4(push, 0.0));
+}
+@vertex
+fn vs_outline_main(vtx:VertexInput, inst:InstanceInput) -> @builtin(position) vec4<f32> {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, vec3(inst.translate_scale.w));
+  return outline_vert(vtx.position, model);
+}
+@vertex
+fn vs_outline_nonuniform_main(vtx:VertexInput, inst:InstanceInput, scale:InstanceScaleInput) -> @builtin(position) vec4<f32> {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w * scale.scale);
+  return outline_vert(vtx.position, model);
+}
+@vertex
+fn vs_flat_outline_main(vtx:FlatVertexInput, inst:InstanceInput) -> @builtin(position) vec4<f32> {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, vec3(inst.translate_scale.w));
+  return outline_vert(vtx.position_which.xyz, model);
+}
+@vertex
+fn vs_flat_outline_nonuniform_main(vtx:FlatVertexInput, inst:InstanceInput, scale:InstanceScaleInput) -> @builtin(position) vec4<f32> {
+  let model = mat_from_trs(inst.translate_scale.xyz, inst.rot, inst.translate_scale.w * scale.scale);
+  return outline_vert(vtx.position_which.xyz, model);
+}
+
+fn mat_from_trs(t:vec3<f32>, r:vec4<f32>, s:vec3<f32>) -> mat4x4<f32> {
   let trans =
     mat4x4<f32>
     (
@@ -74,9 +171,9 @@ fn mat_from_trs(t:vec3<f32>, r:vec4<f32>, s:f32) -> mat4x4<f32> {
   let scale =
     mat4x4<f32>
     (
-     vec4<f32>(  s,0.0,0.0,0.0),
-     vec4<f32>(0.0,  s,0.0,0.0),
-     vec4<f32>(0.0,0.0,  s,0.0),
+     vec4<f32>(s.x,0.0,0.0,0.0),
+     vec4<f32>(0.0,s.y,0.0,0.0),
+     vec4<f32>(0.0,0.0,s.z,0.0),
      vec4<f32>(0.0,0.0,0.0,1.0),
      );
   return trans*rot*scale;
@@ -91,13 +188,55 @@ var t_diffuse: texture_2d_array<f32>;
 var s_diffuse: sampler;
 // Both are in the same binding group here since they go together naturally.
 
+// Each group (textured or flat) also has a color grade for its RGB colors.
+@group(1) @binding(2)
+var<uniform> grade: mat4x4<f32>;
+
+fn graded(color:vec3<f32>) -> vec3<f32> {
+    return (grade * vec4(color, 1.0)).xyz;
+}
+
+// Each group can also be lit
+struct Shading {
+  // xyz: the direction light travels; w: 0 for unlit, 1 for toon, 2 for water
+  light_dir_mode: vec4<f32>,
+  // For toon shading, x: how many bands, y: the darkest band's brightness, z: the rim width
+  // For water, x: the distortion, y: the ripple size, zw: the ripples' scrolling
+  params: vec4<f32>,
+  // The rim color, or the water's tint
+  color: vec4<f32>,
+}
+@group(1) @binding(4)
+var<uniform> shading: Shading;
+
+fn shaded(color:vec3<f32>, world_position:vec3<f32>) -> vec3<f32> {
+    // Vertices don't have normals, so use each triangle's face
+    // normal, turned towards the camera (back faces are culled)
+    let to_eye = normalize(camera.eye.xyz - world_position);
+    var normal = normalize(cross(dpdx(world_position), dpdy(world_position)));
+    normal = select(normal, -normal, dot(normal, to_eye) < 0.0);
+    if (u32(shading.light_dir_mode.w) != 1u) {
+        return color;
+    }
+    let bands = max(shading.params.x, 1.0);
+    let ambient = shading.params.y;
+    let diffuse = max(dot(normal, -normalize(shading.light_dir_mode.xyz)), 0.0);
+    // Snap to the band below, with the brightest band fully lit
+    let level = select(min(floor(diffuse * bands), bands - 1.0) / max(bands - 1.0, 1.0), 1.0, bands == 1.0);
+    let lit = color * mix(ambient, 1.0, level);
+    // A hard-edged rim where the surface turns away from the camera
+    let rim = step(1.0 - shading.params.z, 1.0 - max(dot(normal, to_eye), 0.0));
+    return mix(lit, shading.color.rgb, rim * shading.color.a);
+}
+
 // Our fragment shader takes an interpolated `VertexOutput` as input now
 @fragment
 fn fs_main(in:VertexOutput) -> @location(0) vec4<f32> {
     // And we use the tex coords from the vertex output to sample from the texture.
     let color:vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords, in.tex_index);
     // if color.w < 0.2 { discard; }
-    return color;
+    let lit = shaded(mix(color.rgb, in.colormod.rgb, in.colormod.a), in.world_position);
+    return vec4(graded(lit), color.a);
 }
 
 // Now our fragment shader needs a global uniform of colors.
@@ -110,5 +249,46 @@ var<uniform> mat_diffuse: array<vec4<f32>, 256>;
 fn fs_flat_main(in:FlatVertexOutput) -> @location(0) vec4<f32> {
     // And we use the tex coords from the vertex output to sample from the texture
     let color:vec4<f32> = mat_diffuse[in.mat_index];
-    return color;
+    let lit = shaded(mix(color.rgb, in.colormod.rgb, in.colormod.a), in.world_position);
+    return vec4(graded(lit), color.a);
+}
+
+// Selected instances cover the selection mask, whatever their material
+@fragment
+fn fs_mask() -> @location(0) vec4<f32> {
+    return vec4(1.0);
+}
+
+// Outline hulls are a flat color, whatever their material
+@fragment
+fn fs_outline() -> @location(0) vec4<f32> {
+    return outline.color;
+}
+
+// Water groups also see the scene mirrored about the water's plane,
+// drawn left and right swapped
+@group(2) @binding(0)
+var t_reflection: texture_2d<f32>;
+@group(2) @binding(1)
+var s_reflection: sampler;
+// x: seconds elapsed, for scrolling ripples
+@group(2) @binding(2)
+var<uniform> reflection_time: vec4<f32>;
+
+fn water(frag:vec4<f32>, world_position:vec3<f32>) -> vec4<f32> {
+    let ripple_size = max(shading.params.y, 0.0001);
+    let p = (world_position.xz - shading.params.zw * reflection_time.x) / ripple_size;
+    let offset = vec2(sin(p.x + p.y * 0.7), cos(p.y - p.x * 0.6)) * shading.params.x;
+    let size = vec2<f32>(textureDimensions(t_reflection));
+    let uv = vec2(1.0 - frag.x / size.x, frag.y / size.y) + offset;
+    let reflected = textureSample(t_reflection, s_reflection, uv).rgb;
+    return vec4(graded(mix(reflected, shading.color.rgb, shading.color.a)), 1.0);
+}
+@fragment
+fn fs_water(in:VertexOutput) -> @location(0) vec4<f32> {
+    return water(in.clip_position, in.world_position);
+}
+@fragment
+fn fs_flat_water(in:FlatVertexOutput) -> @location(0) vec4<f32> {
+    return water(in.clip_position, in.world_position);
 }
@@ -0,0 +1,388 @@
+//! Streaming of spritesheet layers for large worlds.  A
+//! [`TextureStreamer`] owns an array texture sized to a memory budget
+//! and fills its layers with chunks (e.g. map regions) loaded on
+//! demand by a [`ChunkSource`].  When the texture is full, the
+//! least-recently-drawn chunks are evicted to make room.
+//!
+//! Each frame, call [`TextureStreamer::request`] for every chunk
+//! you'd like to draw; chunks which are resident report their layer,
+//! to be used as the [`crate::sprites::SheetRegion::sheet`] of sprites
+//! in a group whose spritesheet is [`TextureStreamer::texture`].
+//! Then call [`TextureStreamer::update`], which uploads finished loads,
+//! starts new ones in priority order, and returns [`StreamEvent`]s so
+//! the game can e.g. create or remove whole sprite groups as their
+//! chunks come and go.  Loaded chunks which don't fit yet wait for a
+//! layer to free up as long as they're still requested, and chunks
+//! which fail to load are retried less and less often.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::WGPU;
+
+/// The texels of a loaded chunk (tightly packed rows in the
+/// streamer's texture format), or why it couldn't be loaded.
+pub type LoadResult = Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Loads chunks for a [`TextureStreamer`], possibly asynchronously.
+pub trait ChunkSource<K> {
+    /// Start loading the chunk named by `key`.
+    fn request(&mut self, key: K);
+    /// Return a chunk whose load has finished, if any.
+    fn poll(&mut self) -> Option<(K, LoadResult)>;
+}
+
+/// A [`ChunkSource`] which calls a loading function (e.g. reading
+/// and decoding an image file) on a background thread per request.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ThreadedSource<K> {
+    load: std::sync::Arc<dyn Fn(&K) -> LoadResult + Send + Sync>,
+    sender: std::sync::mpsc::Sender<(K, LoadResult)>,
+    receiver: std::sync::mpsc::Receiver<(K, LoadResult)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<K> ThreadedSource<K> {
+    /// Creates a source which loads chunks with `load`.
+    pub fn new(load: impl Fn(&K) -> LoadResult + Send + Sync + 'static) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        Self {
+            load: std::sync::Arc::new(load),
+            sender,
+            receiver,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<K: Send + 'static> ChunkSource<K> for ThreadedSource<K> {
+    fn request(&mut self, key: K) {
+        let load = std::sync::Arc::clone(&self.load);
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let result = load(&key);
+            // The streamer may have been dropped in the meantime
+            let _ = sender.send((key, result));
+        });
+    }
+    fn poll(&mut self) -> Option<(K, LoadResult)> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Something that happened to a chunk during [`TextureStreamer::update`].
+#[derive(Debug)]
+pub enum StreamEvent<K> {
+    /// The chunk was uploaded into this layer.
+    Loaded {
+        /// The chunk
+        key: K,
+        /// Its layer in the streamer's texture
+        layer: u16,
+    },
+    /// The chunk was evicted from this layer to make room for another.
+    Evicted {
+        /// The chunk
+        key: K,
+        /// The layer it used to occupy
+        layer: u16,
+    },
+    /// The chunk couldn't be loaded.
+    Failed {
+        /// The chunk
+        key: K,
+        /// Why it failed
+        error: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+// Frames to wait before retrying a failed chunk, doubling with each
+// further failure up to the maximum
+const FIRST_RETRY_FRAMES: u64 = 30;
+const MAX_RETRY_FRAMES: u64 = FIRST_RETRY_FRAMES * 64;
+
+struct Failure {
+    count: u32,
+    // The first frame on which the chunk may be requested again
+    retry_frame: u64,
+}
+
+struct Resident<K> {
+    key: K,
+    // The frame on which this chunk was last requested
+    last_used: u64,
+}
+
+/// Streams chunks into the layers of an array texture, evicting
+/// the least recently drawn ones when the texture is full.
+pub struct TextureStreamer<K, S> {
+    source: S,
+    texture: wgpu::Texture,
+    layer_size: (u32, u32),
+    layers: Vec<Option<Resident<K>>>,
+    resident: HashMap<K, usize>,
+    // This frame's requests for chunks which aren't resident, with their priorities
+    wanted: HashMap<K, f32>,
+    in_flight: HashSet<K>,
+    // Chunks which loaded while there was no layer for them
+    pending: HashMap<K, Vec<u8>>,
+    failures: HashMap<K, Failure>,
+    pinned: HashSet<K>,
+    max_in_flight: usize,
+    frame: u64,
+}
+
+impl<K: Clone + Eq + Hash, S: ChunkSource<K>> TextureStreamer<K, S> {
+    /// Creates a streamer whose texture holds as many `width` by
+    /// `height` layers of `format` as fit in `budget_bytes` (but at
+    /// least one).
+    pub fn new(
+        gpu: &WGPU,
+        source: S,
+        format: wgpu::TextureFormat,
+        (width, height): (u32, u32),
+        budget_bytes: u64,
+    ) -> Self {
+        let layer_bytes = (format.block_copy_size(None).unwrap_or(4) * width * height) as u64;
+        let layer_count = (budget_bytes / layer_bytes).clamp(1, u16::MAX as u64) as u32;
+        let texture = gpu.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("streaming:texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                // Same workaround as Renderer::create_array_texture: GL treats these layer counts specially
+                depth_or_array_layers: match layer_count {
+                    1 | 6 if gpu.is_gl() => layer_count + 1,
+                    n => n,
+                },
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        Self {
+            source,
+            texture,
+            layer_size: (width, height),
+            layers: (0..layer_count).map(|_| None).collect(),
+            resident: HashMap::new(),
+            wanted: HashMap::new(),
+            in_flight: HashSet::new(),
+            pending: HashMap::new(),
+            failures: HashMap::new(),
+            pinned: HashSet::new(),
+            max_in_flight: 4,
+            frame: 0,
+        }
+    }
+    /// The array texture chunks are streamed into.
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+    /// How many chunks can be resident at once.
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+    /// The chunk source.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+    /// Set how many chunks may be loading at once (4 by default).
+    pub fn set_max_in_flight(&mut self, max: usize) {
+        self.max_in_flight = max;
+    }
+    /// Ask to draw the chunk `key` this frame.  If it's resident,
+    /// this returns its layer and marks it as recently drawn;
+    /// otherwise it will be loaded, with higher `priority` chunks
+    /// loaded first.
+    pub fn request(&mut self, key: &K, priority: f32) -> Option<u16> {
+        if let Some(&layer) = self.resident.get(key) {
+            self.layers[layer].as_mut().unwrap().last_used = self.frame;
+            return Some(layer as u16);
+        }
+        let wanted = self.wanted.entry(key.clone()).or_insert(priority);
+        *wanted = wanted.max(priority);
+        None
+    }
+    /// The layer of a resident chunk, without marking it as drawn.
+    pub fn layer(&self, key: &K) -> Option<u16> {
+        self.resident.get(key).map(|&layer| layer as u16)
+    }
+    /// Keep a chunk from being evicted, e.g. because it's part of
+    /// the UI or the player's home base.
+    pub fn pin(&mut self, key: K) {
+        self.pinned.insert(key);
+    }
+    /// Allow a pinned chunk to be evicted again.
+    pub fn unpin(&mut self, key: &K) {
+        self.pinned.remove(key);
+    }
+    /// Whether loading the chunk `key` failed and it's waiting to be
+    /// retried.
+    pub fn is_failed(&self, key: &K) -> bool {
+        self.failures
+            .get(key)
+            .is_some_and(|failure| failure.retry_frame > self.frame)
+    }
+    /// Forget that loading the chunk `key` failed, so that it's
+    /// loaded on its next request (e.g. once its file exists).
+    pub fn retry(&mut self, key: &K) {
+        self.failures.remove(key);
+    }
+    /// Uploads chunks which have finished loading, evicting the
+    /// least recently drawn chunks (other than pinned chunks or those
+    /// requested this frame) if there's no room, then starts loading
+    /// this frame's requested chunks in priority order.  Loaded
+    /// chunks which still don't fit are kept until a layer frees up,
+    /// as long as they're requested each frame.  Chunks which failed
+    /// to load aren't requested again for a while, waiting longer
+    /// after each failure.  Call once per frame after making requests.
+    pub fn update(&mut self, gpu: &WGPU) -> Vec<StreamEvent<K>> {
+        let mut events = vec![];
+        while let Some((key, result)) = self.source.poll() {
+            self.in_flight.remove(&key);
+            match result.and_then(|data| self.check_size(data)) {
+                Ok(data) => {
+                    self.failures.remove(&key);
+                    self.pending.insert(key, data);
+                }
+                Err(error) => {
+                    let failure = self.failures.entry(key.clone()).or_insert(Failure {
+                        count: 0,
+                        retry_frame: 0,
+                    });
+                    failure.count += 1;
+                    let delay = FIRST_RETRY_FRAMES
+                        .saturating_mul(1 << (failure.count - 1).min(16))
+                        .min(MAX_RETRY_FRAMES);
+                    failure.retry_frame = self.frame + delay;
+                    events.push(StreamEvent::Failed { key, error });
+                }
+            }
+        }
+        let mut wanted: Vec<_> = self.wanted.drain().collect();
+        wanted.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        // Upload the most wanted chunks first, then any which loaded
+        // but aren't requested anymore while there's room
+        let mut pending = std::mem::take(&mut self.pending);
+        let order: Vec<K> = wanted
+            .iter()
+            .map(|(key, _)| key.clone())
+            .filter(|key| pending.contains_key(key))
+            .chain(
+                pending
+                    .keys()
+                    .filter(|key| !self.wanted_in(&wanted, key))
+                    .cloned(),
+            )
+            .collect();
+        for key in order {
+            let data = pending.remove(&key).unwrap();
+            if let Err(data) = self.upload(gpu, key.clone(), data, &mut events) {
+                // Only keep waiting for room while the chunk is still wanted
+                if self.wanted_in(&wanted, &key) {
+                    self.pending.insert(key, data);
+                }
+            }
+        }
+        for (key, _priority) in wanted {
+            if self.in_flight.len() >= self.max_in_flight {
+                break;
+            }
+            let waiting = self.in_flight.contains(&key)
+                || self.resident.contains_key(&key)
+                || self.pending.contains_key(&key)
+                || self.is_failed(&key);
+            if !waiting {
+                self.in_flight.insert(key.clone());
+                self.source.request(key);
+            }
+        }
+        self.frame += 1;
+        events
+    }
+    fn wanted_in(&self, wanted: &[(K, f32)], key: &K) -> bool {
+        wanted.iter().any(|(wanted, _)| wanted == key)
+    }
+    fn check_size(&self, data: Vec<u8>) -> LoadResult {
+        let (width, height) = self.layer_size;
+        let expected = self.texture.format().block_copy_size(None).unwrap_or(4) * width * height;
+        if data.len() == expected as usize {
+            Ok(data)
+        } else {
+            Err(format!("Chunk has {} bytes, expected {expected}", data.len()).into())
+        }
+    }
+    // Uploads a loaded chunk, or gives its data back if every layer is in use
+    fn upload(
+        &mut self,
+        gpu: &WGPU,
+        key: K,
+        data: Vec<u8>,
+        events: &mut Vec<StreamEvent<K>>,
+    ) -> Result<(), Vec<u8>> {
+        if self.resident.contains_key(&key) {
+            return Ok(());
+        }
+        let free = self.layers.iter().position(Option::is_none);
+        let Some(layer) = free.or_else(|| self.evict(events)) else {
+            return Err(data);
+        };
+        let (width, height) = self.layer_size;
+        gpu.queue().write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer as u32,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(
+                    self.texture.format().block_copy_size(None).unwrap_or(4) * width,
+                ),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.resident.insert(key.clone(), layer);
+        self.layers[layer] = Some(Resident {
+            key: key.clone(),
+            last_used: self.frame,
+        });
+        events.push(StreamEvent::Loaded {
+            key,
+            layer: layer as u16,
+        });
+        Ok(())
+    }
+    // Free the least recently drawn evictable layer, if any
+    fn evict(&mut self, events: &mut Vec<StreamEvent<K>>) -> Option<usize> {
+        let (layer, _) = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, res)| Some((idx, res.as_ref()?)))
+            .filter(|(_, res)| res.last_used < self.frame && !self.pinned.contains(&res.key))
+            .min_by_key(|(_, res)| res.last_used)?;
+        let evicted = self.layers[layer].take().unwrap();
+        self.resident.remove(&evicted.key);
+        events.push(StreamEvent::Evicted {
+            key: evicted.key,
+            layer: layer as u16,
+        });
+        Some(layer)
+    }
+}
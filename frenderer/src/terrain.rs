@@ -0,0 +1,618 @@
+//! Heightmapped ground for 3D scenes.
+//!
+//! A [`TerrainRenderer`] builds a grid mesh from a [`Heightmap`]
+//! (e.g. the pixels of an 8- or 16-bit grayscale image), split into
+//! square chunks whose bounding boxes are tested against the camera
+//! so that chunks out of view aren't drawn.  Its surface blends up to
+//! four layers of an array texture, tiled across the world, by the
+//! channels of a weight map stretched over the whole terrain.
+//!
+//! The terrain uses the same [`Camera3D`] and depth buffer as mesh
+//! groups, so meshes stand on it and hide behind its hills.  The
+//! [`crate::Renderer`] draws one terrain, set with
+//! [`crate::Renderer::terrain_set`], before its mesh groups, and keeps
+//! it looking through the textured mesh camera.
+
+use crate::meshes::Camera3D;
+use crate::raycast::Aabb;
+use std::{borrow::Cow, ops::Range};
+use wgpu::util::{self as wutil, DeviceExt};
+
+/// A grid of heights from 0.0 to 1.0, row by row along the z axis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    depth: u32,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// A heightmap from 8-bit samples, e.g. the pixels of an R8 or
+    /// grayscale image, where 255 is the highest.  Panics unless
+    /// there are `width * depth` samples and both are at least 2.
+    pub fn from_r8(width: u32, depth: u32, samples: &[u8]) -> Self {
+        Self::new(
+            width,
+            depth,
+            samples.iter().map(|&h| h as f32 / u8::MAX as f32).collect(),
+        )
+    }
+    /// A heightmap from 16-bit samples, e.g. the pixels of an R16 or
+    /// 16-bit grayscale image, where 65535 is the highest.  Panics
+    /// unless there are `width * depth` samples and both are at
+    /// least 2.
+    pub fn from_r16(width: u32, depth: u32, samples: &[u16]) -> Self {
+        Self::new(
+            width,
+            depth,
+            samples
+                .iter()
+                .map(|&h| h as f32 / u16::MAX as f32)
+                .collect(),
+        )
+    }
+    /// A heightmap from heights between 0.0 and 1.0.  Panics unless
+    /// there are `width * depth` heights and both are at least 2.
+    pub fn new(width: u32, depth: u32, heights: Vec<f32>) -> Self {
+        assert!(
+            width >= 2 && depth >= 2,
+            "Heightmaps need at least 2x2 samples (got {width}x{depth})"
+        );
+        assert_eq!(
+            heights.len(),
+            (width * depth) as usize,
+            "A {width}x{depth} heightmap needs {} samples",
+            width * depth
+        );
+        Self {
+            width,
+            depth,
+            heights,
+        }
+    }
+    /// How many samples there are along the x axis.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// How many samples there are along the z axis.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+    /// The height of a sample, clamping coordinates to the grid.
+    pub fn get(&self, x: i64, z: i64) -> f32 {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let z = z.clamp(0, self.depth as i64 - 1) as usize;
+        self.heights[z * self.width as usize + x]
+    }
+    // The world height of the surface drawn from this heightmap with
+    // `settings` at a world x and z position, see TerrainRenderer::height_at
+    fn surface_height(&self, settings: &TerrainSettings, x: f32, z: f32) -> Option<f32> {
+        let TerrainSettings {
+            origin,
+            size,
+            height,
+            ..
+        } = *settings;
+        let gx = (x - origin[0]) / size[0] * (self.width - 1) as f32;
+        let gz = (z - origin[2]) / size[1] * (self.depth - 1) as f32;
+        if !(0.0..=(self.width - 1) as f32).contains(&gx)
+            || !(0.0..=(self.depth - 1) as f32).contains(&gz)
+        {
+            return None;
+        }
+        let (x0, z0) = (gx.floor() as i64, gz.floor() as i64);
+        let (fx, fz) = (gx.fract(), gz.fract());
+        let h = |dx, dz| self.get(x0 + dx, z0 + dz);
+        // Match the triangles the grid is drawn with
+        let sample = if fx + fz <= 1.0 {
+            h(0, 0) + (h(1, 0) - h(0, 0)) * fx + (h(0, 1) - h(0, 0)) * fz
+        } else {
+            h(1, 1) + (h(0, 1) - h(1, 1)) * (1.0 - fx) + (h(1, 0) - h(1, 1)) * (1.0 - fz)
+        };
+        Some(origin[1] + sample * height)
+    }
+}
+
+/// Where a [`TerrainRenderer`]'s grid lies in the world and how it's
+/// textured and lit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainSettings {
+    /// The world position of the corner at the heightmap's first
+    /// sample, at height 0.0
+    pub origin: [f32; 3],
+    /// How far the terrain extends along the x and z axes
+    pub size: [f32; 2],
+    /// The world height of a 1.0 in the heightmap
+    pub height: f32,
+    /// How many grid cells along each side a chunk covers
+    pub chunk_cells: u32,
+    /// How many world units each repeat of the splat textures covers
+    pub texture_scale: f32,
+    /// The direction sunlight travels, in world space
+    pub light_dir: [f32; 3],
+    /// The brightness of faces turned away from the light, from 0.0
+    /// (black) to 1.0 (unlit)
+    pub ambient: f32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            origin: [-32.0, 0.0, -32.0],
+            size: [64.0, 64.0],
+            height: 8.0,
+            chunk_cells: 32,
+            texture_scale: 4.0,
+            light_dir: [-0.5, -1.0, -0.3],
+            ambient: 0.4,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+struct TerrainVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    // Across the whole terrain from 0.0 to 1.0, for the weight map
+    uv: [f32; 2],
+}
+
+struct Chunk {
+    indices: Range<u32>,
+    bounds: Aabb,
+}
+
+/// Draws a heightmapped, texture-splatted terrain; see the module
+/// documentation.
+pub struct TerrainRenderer {
+    heightmap: Heightmap,
+    settings: TerrainSettings,
+    pipeline: wgpu::RenderPipeline,
+    camera: Camera3D,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    chunks: Vec<Chunk>,
+    // Whether each chunk is in view of the camera
+    visible: Vec<bool>,
+}
+
+impl TerrainRenderer {
+    /// Builds a terrain from a heightmap, meant to draw into the given
+    /// color target state with the given depth texture format.
+    /// `textures` is an array texture with up to four layers, and
+    /// each of the red, green, blue, and alpha channels of the 2D
+    /// `weights` texture is how much of the layer with that index
+    /// shows at that point of the terrain.  On GL backends `textures`
+    /// needs at least two layers.
+    pub fn new(
+        gpu: &crate::WGPU,
+        color_target: wgpu::ColorTargetState,
+        depth_format: wgpu::TextureFormat,
+        heightmap: Heightmap,
+        settings: TerrainSettings,
+        textures: &wgpu::Texture,
+        weights: &wgpu::Texture,
+    ) -> Self {
+        if gpu.is_gl()
+            && (textures.depth_or_array_layers() == 1 || textures.depth_or_array_layers() == 6)
+        {
+            panic!("Array textures with 1 or 6 layers aren't supported in webgl or other GL backends {:?}", textures);
+        }
+        let device = gpu.device();
+        let shader = gpu.create_shader(wgpu::ShaderModuleDescriptor {
+            label: Some("terrain:shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("terrain.wgsl"))),
+        });
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("terrain:camera_buffer"),
+            size: std::mem::size_of::<[f32; 16]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("terrain:camera_bgl"),
+                entries: &[uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+            });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("terrain:camera_bg"),
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+        let texture_entry = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("terrain:texture_bgl"),
+            entries: &[
+                // The splat textures and their repeating sampler
+                texture_entry(0, wgpu::TextureViewDimension::D2Array),
+                sampler_entry(1),
+                // The weight map and its clamping sampler
+                texture_entry(2, wgpu::TextureViewDimension::D2),
+                sampler_entry(3),
+                // The texture scale and lighting
+                uniform_entry(4, wgpu::ShaderStages::FRAGMENT),
+            ],
+        });
+        let textures_view = textures.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let weights_view = weights.create_view(&wgpu::TextureViewDescriptor::default());
+        let repeat_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("terrain:texture_sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let clamp_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("terrain:weight_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let [lx, ly, lz] = settings.light_dir;
+        let uniform_buffer = device.create_buffer_init(&wutil::BufferInitDescriptor {
+            label: Some("terrain:uniforms"),
+            contents: bytemuck::bytes_of(&[
+                lx,
+                ly,
+                lz,
+                settings.ambient,
+                settings.texture_scale,
+                0.0,
+                0.0,
+                0.0,
+            ]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("terrain:texture_bg"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&textures_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&repeat_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&weights_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&clamp_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("terrain:pipeline_layout"),
+            bind_group_layouts: &[&camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("terrain:pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<TerrainVertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(color_target)],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let (vertices, indices, chunks) = build_grid(&heightmap, &settings);
+        let vertex_buffer = device.create_buffer_init(&wutil::BufferInitDescriptor {
+            label: Some("terrain:vertex_buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wutil::BufferInitDescriptor {
+            label: Some("terrain:index_buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let visible = vec![true; chunks.len()];
+        let mut ret = Self {
+            heightmap,
+            settings,
+            pipeline,
+            camera: Camera3D {
+                translation: [0.0; 3],
+                near: 0.1,
+                far: 100.0,
+                rotation: ultraviolet::Rotor3::identity().into_quaternion_array(),
+                aspect: 4.0 / 3.0,
+                fov: std::f32::consts::FRAC_PI_2,
+            },
+            camera_buffer,
+            camera_bind_group,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer,
+            index_buffer,
+            chunks,
+            visible,
+        };
+        ret.set_camera(gpu, ret.camera);
+        ret
+    }
+    /// Sets the camera the terrain is drawn through, and finds which
+    /// chunks it can see.
+    pub fn set_camera(&mut self, gpu: &crate::WGPU, camera: Camera3D) {
+        self.camera = camera;
+        let mat = ultraviolet::Mat4::from(camera);
+        gpu.queue()
+            .write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&mat));
+        let planes = frustum_planes(&mat);
+        for (visible, chunk) in self.visible.iter_mut().zip(self.chunks.iter()) {
+            *visible = in_frustum(&planes, &chunk.bounds);
+        }
+    }
+    /// Returns the camera the terrain is drawn through.
+    pub fn camera(&self) -> Camera3D {
+        self.camera
+    }
+    /// Returns the heightmap the terrain was built from.
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+    /// Returns the settings the terrain was built with.
+    pub fn settings(&self) -> TerrainSettings {
+        self.settings
+    }
+    /// The world height of the terrain's surface at a world x and z
+    /// position, interpolated between samples, or `None` if the
+    /// position is off the terrain.  Handy for putting things on the
+    /// ground.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        self.heightmap.surface_height(&self.settings, x, z)
+    }
+    /// Returns how many chunks the terrain is split into.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+    /// Returns the world space bounding box of the given chunk.
+    pub fn chunk_bounds(&self, chunk: usize) -> Aabb {
+        self.chunks[chunk].bounds
+    }
+    /// Returns whether the given chunk was in view of the camera as
+    /// of the last [`TerrainRenderer::set_camera`].
+    pub fn is_chunk_visible(&self, chunk: usize) -> bool {
+        self.visible[chunk]
+    }
+    /// Returns how many chunks are in view of the camera.
+    pub fn visible_chunk_count(&self) -> usize {
+        self.visible.iter().filter(|v| **v).count()
+    }
+    pub(crate) fn collect_resources(&self, out: &mut Vec<crate::resources::GpuResource>) {
+        use crate::resources::GpuResource;
+        for (label, buffer) in [
+            ("terrain:camera_buffer", &self.camera_buffer),
+            ("terrain:uniforms", &self.uniform_buffer),
+            ("terrain:vertex_buffer", &self.vertex_buffer),
+            ("terrain:index_buffer", &self.index_buffer),
+        ] {
+            out.push(GpuResource::buffer(Some(label.to_string()), buffer));
+        }
+    }
+    /// Renders the chunks in view of the camera into the given
+    /// [`wgpu::RenderPass`].
+    pub fn render<'s, 'pass>(&'s self, rpass: &mut wgpu::RenderPass<'pass>)
+    where
+        's: 'pass,
+    {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.camera_bind_group, &[]);
+        rpass.set_bind_group(1, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for (chunk, _) in self
+            .chunks
+            .iter()
+            .zip(self.visible.iter())
+            .filter(|(_, visible)| **visible)
+        {
+            rpass.draw_indexed(chunk.indices.clone(), 0, 0..1);
+        }
+    }
+}
+
+// One vertex per heightmap sample, and each chunk's triangles in a
+// contiguous run of indices
+fn build_grid(
+    heightmap: &Heightmap,
+    settings: &TerrainSettings,
+) -> (Vec<TerrainVertex>, Vec<u32>, Vec<Chunk>) {
+    let (w, d) = (heightmap.width, heightmap.depth);
+    let cell = [
+        settings.size[0] / (w - 1) as f32,
+        settings.size[1] / (d - 1) as f32,
+    ];
+    let mut vertices = Vec::with_capacity((w * d) as usize);
+    for z in 0..d as i64 {
+        for x in 0..w as i64 {
+            let h = |x, z| heightmap.get(x, z) * settings.height;
+            // Central differences, one-sided at the edges
+            let dx = (h(x + 1, z) - h(x - 1, z))
+                / (((x + 1).min(w as i64 - 1) - (x - 1).max(0)) as f32 * cell[0]);
+            let dz = (h(x, z + 1) - h(x, z - 1))
+                / (((z + 1).min(d as i64 - 1) - (z - 1).max(0)) as f32 * cell[1]);
+            let normal = ultraviolet::Vec3::new(-dx, 1.0, -dz).normalized();
+            vertices.push(TerrainVertex {
+                position: [
+                    settings.origin[0] + x as f32 * cell[0],
+                    settings.origin[1] + h(x, z),
+                    settings.origin[2] + z as f32 * cell[1],
+                ],
+                normal: normal.into(),
+                uv: [x as f32 / (w - 1) as f32, z as f32 / (d - 1) as f32],
+            });
+        }
+    }
+    let chunk_cells = settings.chunk_cells.max(1);
+    let mut indices = Vec::with_capacity(((w - 1) * (d - 1) * 6) as usize);
+    let mut chunks = vec![];
+    for cz in (0..d - 1).step_by(chunk_cells as usize) {
+        for cx in (0..w - 1).step_by(chunk_cells as usize) {
+            let start = indices.len() as u32;
+            let mut bounds = Aabb::EMPTY;
+            for z in cz..(cz + chunk_cells).min(d - 1) {
+                for x in cx..(cx + chunk_cells).min(w - 1) {
+                    let a = z * w + x;
+                    let (b, c, e) = (a + 1, a + w, a + w + 1);
+                    // Counterclockwise seen from above
+                    indices.extend_from_slice(&[a, c, b, b, c, e]);
+                    for v in [a, b, c, e] {
+                        let p = vertices[v as usize].position;
+                        bounds = bounds.union(Aabb { min: p, max: p });
+                    }
+                }
+            }
+            chunks.push(Chunk {
+                indices: start..indices.len() as u32,
+                bounds,
+            });
+        }
+    }
+    (vertices, indices, chunks)
+}
+
+// The view frustum's six planes as (normal, distance) with the
+// normals pointing inward, from a view-projection matrix with depth
+// from 0 to 1
+fn frustum_planes(mat: &ultraviolet::Mat4) -> [ultraviolet::Vec4; 6] {
+    let row = |i: usize| {
+        ultraviolet::Vec4::new(
+            mat.cols[0][i],
+            mat.cols[1][i],
+            mat.cols[2][i],
+            mat.cols[3][i],
+        )
+    };
+    let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+    [w + x, w - x, w + y, w - y, z, w - z]
+}
+
+// Whether any of the box might be inside the frustum: it's outside
+// if its corner furthest along some plane's normal is behind it
+fn in_frustum(planes: &[ultraviolet::Vec4; 6], bounds: &Aabb) -> bool {
+    planes.iter().all(|plane| {
+        let corner: [f32; 3] = std::array::from_fn(|i| {
+            if plane[i] >= 0.0 {
+                bounds.max[i]
+            } else {
+                bounds.min[i]
+            }
+        });
+        plane.x * corner[0] + plane.y * corner[1] + plane.z * corner[2] + plane.w >= 0.0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    // A 3x3 heightmap rising along x, stretched over 0..10 on both axes
+    fn ramp() -> (Heightmap, TerrainSettings) {
+        let heights = (0..9).map(|i| (i % 3) as f32 / 2.0).collect();
+        let settings = TerrainSettings {
+            origin: [0.0, 1.0, 0.0],
+            size: [10.0, 10.0],
+            height: 4.0,
+            ..Default::default()
+        };
+        (Heightmap::new(3, 3, heights), settings)
+    }
+    #[test]
+    fn test_height_at_samples() {
+        let (map, settings) = ramp();
+        assert_eq!(map.surface_height(&settings, 0.0, 0.0), Some(1.0));
+        assert_eq!(map.surface_height(&settings, 5.0, 5.0), Some(3.0));
+        assert_eq!(map.surface_height(&settings, 10.0, 10.0), Some(5.0));
+        // Between samples the surface is interpolated
+        let mid = map.surface_height(&settings, 2.5, 7.5).unwrap();
+        assert!((mid - 2.0).abs() < 1e-5, "{mid}");
+    }
+    #[test]
+    fn test_height_at_out_of_range() {
+        let (map, settings) = ramp();
+        for (x, z) in [
+            (-0.01, 5.0),
+            (5.0, -0.01),
+            (10.01, 5.0),
+            (5.0, 10.01),
+            (f32::NAN, 5.0),
+            (5.0, f32::INFINITY),
+        ] {
+            assert_eq!(map.surface_height(&settings, x, z), None, "{x}, {z}");
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn test_heightmap_too_small() {
+        Heightmap::new(1, 2, vec![0.0; 2]);
+    }
+}
@@ -0,0 +1,60 @@
+@group(0) @binding(0)
+var<uniform> projview: mat4x4<f32>;
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) normal: vec3<f32>,
+  @location(2) uv: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(vtx:VertexInput) -> VertexOutput {
+  var out:VertexOutput;
+  out.clip_position = projview * vec4(vtx.position, 1.0);
+  out.world_position = vtx.position;
+  out.normal = vtx.normal;
+  out.uv = vtx.uv;
+  return out;
+}
+
+// Up to four texture layers, tiled across the world
+@group(1) @binding(0)
+var t_layers: texture_2d_array<f32>;
+@group(1) @binding(1)
+var s_layers: sampler;
+// Each channel weights the layer with the same index
+@group(1) @binding(2)
+var t_weights: texture_2d<f32>;
+@group(1) @binding(3)
+var s_weights: sampler;
+
+struct Terrain {
+  // xyz: the direction light travels; w: the brightness of unlit faces
+  light_dir_ambient: vec4<f32>,
+  // x: how many world units one repeat of the layers covers
+  texture_scale: vec4<f32>,
+}
+@group(1) @binding(4)
+var<uniform> terrain: Terrain;
+
+@fragment
+fn fs_main(in:VertexOutput) -> @location(0) vec4<f32> {
+    let uv = in.world_position.xz / max(terrain.texture_scale.x, 0.0001);
+    let weights = textureSample(t_weights, s_weights, in.uv);
+    let total = max(weights.x + weights.y + weights.z + weights.w, 0.0001);
+    let color =
+        textureSample(t_layers, s_layers, uv, 0) * weights.x +
+        textureSample(t_layers, s_layers, uv, 1) * weights.y +
+        textureSample(t_layers, s_layers, uv, 2) * weights.z +
+        textureSample(t_layers, s_layers, uv, 3) * weights.w;
+    let light = max(dot(normalize(in.normal), -normalize(terrain.light_dir_ambient.xyz)), 0.0);
+    let ambient = terrain.light_dir_ambient.w;
+    return vec4((color.rgb / total) * mix(ambient, 1.0, light), 1.0);
+}
@@ -0,0 +1,227 @@
+//! Caches static strings drawn with a [`BitFont`] into a texture, so
+//! long texts like dialog can be drawn as one sprite per string
+//! rather than one sprite per character.  Each string lives in its
+//! own layer ("slot") of the cache's array texture; it's rendered
+//! once and only redrawn when its text or character height changes.
+//!
+//! To use the cache, add a sprite group whose spritesheet is
+//! [`TextCache::texture`] and write the [`Transform`] and
+//! [`SheetRegion`] returned by [`TextCache::draw_text`] into it.
+
+use crate::bitfont::BitFont;
+use crate::sprites::{Camera2D, SheetRegion, SpriteRenderer, Transform};
+use crate::WGPU;
+
+struct CachedText {
+    text: String,
+    char_height: f32,
+    region: SheetRegion,
+}
+
+/// A texture of pre-rendered strings, drawn with a [`BitFont`].
+pub struct TextCache {
+    font: BitFont,
+    sprites: SpriteRenderer,
+    texture: wgpu::Texture,
+    depth_view: wgpu::TextureView,
+    slot_size: (u32, u32),
+    slots: Vec<Option<CachedText>>,
+}
+
+impl TextCache {
+    /// The format of the cache's texture.
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+    /// Creates a cache with room for `slots` strings, each of which
+    /// can be up to `width` by `height` pixels.  `font_texture` is
+    /// the spritesheet containing `font`'s region.
+    pub fn new(
+        gpu: &WGPU,
+        font: BitFont,
+        font_texture: &wgpu::Texture,
+        (width, height): (u32, u32),
+        slots: usize,
+    ) -> Self {
+        assert!(slots > 0, "Text cache needs at least one slot");
+        let layers = match slots {
+            // Same workaround as Renderer::create_array_texture: GL treats these layer counts specially
+            1 | 6 if gpu.is_gl() => slots + 1,
+            _ => slots,
+        };
+        let texture = gpu.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("textcache:texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_texture = gpu.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("textcache:depth_texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: crate::Renderer::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut sprites = SpriteRenderer::new(
+            gpu,
+            wgpu::ColorTargetState {
+                format: Self::FORMAT,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            crate::Renderer::DEPTH_FORMAT,
+        );
+        sprites.add_sprite_group(
+            gpu,
+            font_texture,
+            vec![Transform::ZERO],
+            vec![SheetRegion::ZERO],
+            Camera2D {
+                screen_pos: [0.0, 0.0],
+                screen_size: [width as f32, height as f32],
+            },
+        );
+        Self {
+            font,
+            sprites,
+            texture,
+            depth_view,
+            slot_size: (width, height),
+            slots: (0..slots).map(|_| None).collect(),
+        }
+    }
+    /// The cache's array texture, with one layer per slot.
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+    /// How many strings the cache can hold.
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+    /// The maximum size in pixels of each cached string.
+    pub fn slot_size(&self) -> (u32, u32) {
+        self.slot_size
+    }
+    /// Forgets the string in `slot`, so it will be redrawn next time.
+    pub fn invalidate(&mut self, slot: usize) {
+        self.slots[slot] = None;
+    }
+    /// Like [`BitFont::draw_text`], but produces a single sprite
+    /// showing `text` in the given slot.  The text is only rendered
+    /// into the cache if `text` or `char_height` differ from the
+    /// last call for this slot.  Parts of the text which don't fit
+    /// in the slot are cut off.  The given position is the top-left
+    /// corner of the sprite.
+    pub fn draw_text(
+        &mut self,
+        gpu: &WGPU,
+        slot: usize,
+        text: &str,
+        screen_pos: [f32; 2],
+        depth: u16,
+        char_height: f32,
+    ) -> (Transform, SheetRegion) {
+        let region = match &self.slots[slot] {
+            Some(cached) if cached.text == text && cached.char_height == char_height => {
+                cached.region
+            }
+            _ => {
+                let region = self.render_slot(gpu, slot, text, char_height);
+                self.slots[slot] = Some(CachedText {
+                    text: text.to_string(),
+                    char_height,
+                    region,
+                });
+                region
+            }
+        };
+        let (w, h) = (region.w as f32, region.h as f32);
+        (
+            Transform {
+                w: region.w as u16,
+                h: region.h as u16,
+                x: screen_pos[0] + w / 2.0,
+                y: screen_pos[1] - h / 2.0,
+                rot: 0.0,
+            },
+            SheetRegion { depth, ..region },
+        )
+    }
+    fn render_slot(
+        &mut self,
+        gpu: &WGPU,
+        slot: usize,
+        text: &str,
+        char_height: f32,
+    ) -> SheetRegion {
+        let (width, height) = self.slot_size;
+        self.sprites.resize_sprite_group(gpu, 0, text.len());
+        let (trfs, uvs) = self.sprites.get_sprites_mut(0);
+        let (_corner, used) =
+            self.font
+                .draw_text(trfs, uvs, text, [0.0, height as f32], 0, char_height);
+        let text_w = trfs[..used]
+            .iter()
+            .map(|t| t.x + t.w as f32 / 2.0)
+            .fold(0.0, f32::max);
+        self.sprites.upload_sprites(gpu, 0, 0..text.len());
+        let view = self.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("textcache:slot_view"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: slot as u32,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+        let mut encoder = gpu
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("textcache:encoder"),
+            });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("textcache:pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                ..Default::default()
+            });
+            self.sprites.render(&mut rpass, 0..1);
+        }
+        gpu.queue().submit(Some(encoder.finish()));
+        SheetRegion::new(
+            slot as u16,
+            0,
+            0,
+            0,
+            text_w.ceil().min(width as f32) as i16,
+            char_height.ceil().min(height as f32) as i16,
+        )
+    }
+}
@@ -0,0 +1,293 @@
+//! A tilemap drawing helper built for use with [`crate::sprites::SpriteRenderer`],
+//! supporting square, hexagonal, and isometric grids.
+//!
+//! Tile coordinates are `[column, row]`, with columns increasing to
+//! the right and rows increasing upwards in world space (matching
+//! [`Camera2D`]'s y-up world).  Use [`Camera2D::screen_to_world`]
+//! and [`Camera2D::world_to_screen`] together with
+//! [`TileGrid::world_to_tile`] and [`TileGrid::tile_to_world`] to go
+//! between tiles and e.g. mouse positions.
+
+use crate::sprites::{Camera2D, SheetRegion, Transform};
+
+/// How tiles are arranged in a [`TileGrid`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileLayout {
+    /// A plain rectangular grid.
+    Square,
+    /// Hexagons with a point at the top, in rows spaced 3/4 of a
+    /// tile apart; odd rows are shifted right by half a tile.
+    HexPointyTop,
+    /// Hexagons with a flat top, in columns spaced 3/4 of a tile
+    /// apart; odd columns are shifted up by half a tile.
+    HexFlatTop,
+    /// Diamonds in a rotated grid: columns run up and to the right,
+    /// rows run up and to the left.
+    Isometric,
+    /// Diamonds in rows spaced half a tile apart; odd rows are
+    /// shifted right by half a tile, so the map fills a rectangle.
+    IsometricStaggered,
+}
+
+/// The geometry of a tile grid: its layout, tile size, and where
+/// the center of tile `[0, 0]` is in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileGrid {
+    /// How tiles are arranged
+    pub layout: TileLayout,
+    /// The width of a tile's sprite in world space
+    pub tile_w: f32,
+    /// The height of a tile's sprite in world space
+    pub tile_h: f32,
+    /// The world space center of tile `[0, 0]`
+    pub origin: [f32; 2],
+}
+
+impl TileGrid {
+    /// The world space center of the given tile.
+    pub fn tile_to_world(&self, [col, row]: [i32; 2]) -> [f32; 2] {
+        let (w, h) = (self.tile_w, self.tile_h);
+        let (c, r) = (col as f32, row as f32);
+        let (odd_col, odd_row) = ((col & 1) as f32, (row & 1) as f32);
+        let [x, y] = match self.layout {
+            TileLayout::Square => [c * w, r * h],
+            TileLayout::HexPointyTop => [(c + odd_row * 0.5) * w, r * h * 0.75],
+            TileLayout::HexFlatTop => [c * w * 0.75, (r + odd_col * 0.5) * h],
+            TileLayout::Isometric => [(c - r) * w / 2.0, (c + r) * h / 2.0],
+            TileLayout::IsometricStaggered => [(c + odd_row * 0.5) * w, r * h / 2.0],
+        };
+        [self.origin[0] + x, self.origin[1] + y]
+    }
+    /// The tile containing the given world space position.
+    pub fn world_to_tile(&self, world: [f32; 2]) -> [i32; 2] {
+        let (w, h) = (self.tile_w, self.tile_h);
+        let (x, y) = (world[0] - self.origin[0], world[1] - self.origin[1]);
+        match self.layout {
+            TileLayout::Square => [(x / w).round() as i32, (y / h).round() as i32],
+            TileLayout::HexPointyTop => {
+                // Fractional axial coordinates, then back to offset rows
+                let r = y / (h * 0.75);
+                let (q, r) = hex_round(x / w - r / 2.0, r);
+                [q + (r - (r & 1)) / 2, r]
+            }
+            TileLayout::HexFlatTop => {
+                let q = x / (w * 0.75);
+                let (q, r) = hex_round(q, y / h - q / 2.0);
+                [q, r + (q - (q & 1)) / 2]
+            }
+            TileLayout::Isometric => iso_round(x / w, y / h),
+            TileLayout::IsometricStaggered => {
+                // Staggered tiles sit on the same lattice as diamond tiles
+                let [c, r] = iso_round(x / w, y / h);
+                [(c - r).div_euclid(2), c + r]
+            }
+        }
+    }
+    /// The tile under a position on a surface of the given size
+    /// (e.g. the mouse position), as seen through `camera`.
+    pub fn screen_to_tile(
+        &self,
+        camera: &Camera2D,
+        screen: [f32; 2],
+        surface_size: [f32; 2],
+    ) -> [i32; 2] {
+        self.world_to_tile(camera.screen_to_world(screen, surface_size))
+    }
+    /// Where the given tile's center appears on a surface of the
+    /// given size, as seen through `camera`.
+    pub fn tile_to_screen(
+        &self,
+        camera: &Camera2D,
+        tile: [i32; 2],
+        surface_size: [f32; 2],
+    ) -> [f32; 2] {
+        camera.world_to_screen(self.tile_to_world(tile), surface_size)
+    }
+    // How far back the tile is drawn, so tiles lower on screen
+    // overlap the ones behind them
+    fn depth_rank(&self, [col, row]: [i32; 2]) -> i32 {
+        match self.layout {
+            TileLayout::Square => 0,
+            TileLayout::HexPointyTop | TileLayout::IsometricStaggered => row,
+            TileLayout::HexFlatTop => row * 2 + (col & 1),
+            TileLayout::Isometric => col + row,
+        }
+    }
+}
+
+// Round fractional axial hex coordinates to the nearest hex
+fn hex_round(q: f32, r: f32) -> (i32, i32) {
+    let s = -q - r;
+    let (rq, rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        ((-rr - rs) as i32, rr as i32)
+    } else if dr > ds {
+        (rq as i32, (-rq - rs) as i32)
+    } else {
+        (rq as i32, rr as i32)
+    }
+}
+
+// Round a position measured in tile sizes to the nearest diamond tile
+fn iso_round(x: f32, y: f32) -> [i32; 2] {
+    // Rotate into the grid's axes, where diamonds become unit squares
+    [(x + y).round() as i32, (y - x).round() as i32]
+}
+
+/// A rectangular map of tiles drawn as sprites on a [`TileGrid`].
+/// Tiles are indexed by `[column, row]` from `[0, 0]` up to the
+/// map's width and height.
+#[derive(Clone, Debug)]
+pub struct Tilemap {
+    /// The grid tiles are laid out on
+    pub grid: TileGrid,
+    width: usize,
+    height: usize,
+    tiles: Vec<Option<SheetRegion>>,
+}
+
+impl Tilemap {
+    /// Creates an empty tilemap with the given size in tiles.
+    pub fn new(grid: TileGrid, width: usize, height: usize) -> Self {
+        Self {
+            grid,
+            width,
+            height,
+            tiles: vec![None; width * height],
+        }
+    }
+    /// The map's width and height in tiles.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    /// The tile at the given position, if it's in bounds and not empty.
+    pub fn get(&self, [col, row]: [i32; 2]) -> Option<SheetRegion> {
+        self.index(col, row).and_then(|idx| self.tiles[idx])
+    }
+    /// Set or clear the tile at the given position.  Panics if the
+    /// position is out of bounds.
+    pub fn set(&mut self, [col, row]: [i32; 2], tile: Option<SheetRegion>) {
+        let idx = self.index(col, row).expect("Tile position out of bounds");
+        self.tiles[idx] = tile;
+    }
+    fn index(&self, col: i32, row: i32) -> Option<usize> {
+        let (col, row) = (usize::try_from(col).ok()?, usize::try_from(row).ok()?);
+        (col < self.width && row < self.height).then_some(row * self.width + col)
+    }
+    /// How many sprites [`Tilemap::write_sprites`] needs: one per
+    /// non-empty tile.
+    pub fn sprite_count(&self) -> usize {
+        self.tiles.iter().filter(|t| t.is_some()).count()
+    }
+    /// Writes a sprite for each non-empty tile into `trfs` and `uvs`,
+    /// returning how many were written.  Each tile's depth is pushed
+    /// back by its row (and column, for some layouts) so that tiles
+    /// taller than the grid spacing, as are common in hexagonal and
+    /// isometric maps, overlap the tiles behind them.
+    pub fn write_sprites(&self, trfs: &mut [Transform], uvs: &mut [SheetRegion]) -> usize {
+        let tiles = self.tiles.iter().enumerate().filter_map(|(idx, tile)| {
+            let pos = [(idx % self.width) as i32, (idx / self.width) as i32];
+            tile.map(|region| (pos, region))
+        });
+        let mut used = 0;
+        for ((pos, region), (trf, uv)) in tiles.zip(trfs.iter_mut().zip(uvs.iter_mut())) {
+            let [x, y] = self.grid.tile_to_world(pos);
+            *trf = Transform {
+                w: self.grid.tile_w as u16,
+                h: self.grid.tile_h as u16,
+                x,
+                y,
+                rot: 0.0,
+            };
+            let rank = self.grid.depth_rank(pos).clamp(0, u16::MAX as i32) as u16;
+            *uv = SheetRegion {
+                depth: region.depth.saturating_add(rank),
+                ..region
+            };
+            used += 1;
+        }
+        used
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUTS: [TileLayout; 5] = [
+        TileLayout::Square,
+        TileLayout::HexPointyTop,
+        TileLayout::HexFlatTop,
+        TileLayout::Isometric,
+        TileLayout::IsometricStaggered,
+    ];
+
+    fn grid(layout: TileLayout) -> TileGrid {
+        TileGrid {
+            layout,
+            tile_w: 32.0,
+            tile_h: 24.0,
+            origin: [-100.0, 50.0],
+        }
+    }
+
+    #[test]
+    fn test_world_to_tile_round_trip() {
+        for layout in LAYOUTS {
+            let grid = grid(layout);
+            for row in -6..6 {
+                for col in -6..6 {
+                    let [x, y] = grid.tile_to_world([col, row]);
+                    assert_eq!(grid.world_to_tile([x, y]), [col, row], "{layout:?}");
+                    // Points a little off center are still in the same tile
+                    assert_eq!(
+                        grid.world_to_tile([x + 2.0, y - 2.0]),
+                        [col, row],
+                        "{layout:?}"
+                    );
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_get_out_of_bounds() {
+        let mut map = Tilemap::new(grid(TileLayout::Square), 3, 2);
+        map.set([2, 1], Some(SheetRegion::rect(0, 0, 32, 24)));
+        assert!(map.get([2, 1]).is_some());
+        assert!(map.get([0, 0]).is_none());
+        assert!(map.get([3, 1]).is_none());
+        assert!(map.get([2, 2]).is_none());
+        assert!(map.get([-1, 0]).is_none());
+    }
+    #[test]
+    fn test_write_sprites() {
+        let mut map = Tilemap::new(grid(TileLayout::Isometric), 3, 3);
+        let region = SheetRegion::new(0, 0, 0, 10, 32, 24);
+        map.set([0, 0], Some(region));
+        map.set([2, 1], Some(region));
+        assert_eq!(map.sprite_count(), 2);
+        let mut trfs = [Transform::ZERO; 4];
+        let mut uvs = [SheetRegion::ZERO; 4];
+        assert_eq!(map.write_sprites(&mut trfs, &mut uvs), 2);
+        assert_eq!(trfs[1].translation(), map.grid.tile_to_world([2, 1]));
+        assert_eq!((trfs[1].w, trfs[1].h), (32, 24));
+        // Tiles further back are drawn deeper
+        assert_eq!(uvs[0].depth, 10);
+        assert_eq!(uvs[1].depth, 13);
+        // Writing stops when the output runs out
+        assert_eq!(map.write_sprites(&mut trfs[..1], &mut uvs[..1]), 1);
+    }
+    #[test]
+    fn test_empty_map() {
+        let map = Tilemap::new(grid(TileLayout::HexFlatTop), 0, 0);
+        assert_eq!(map.sprite_count(), 0);
+        assert!(map.get([0, 0]).is_none());
+        assert_eq!(map.write_sprites(&mut [], &mut []), 0);
+    }
+    #[test]
+    #[should_panic]
+    fn test_set_out_of_bounds() {
+        Tilemap::new(grid(TileLayout::Square), 2, 2).set([2, 0], None);
+    }
+}
@@ -0,0 +1,711 @@
+//! A minimal retained layout tree for menus and HUDs, built from
+//! [`NineSlice`] panels, [`BitFont`] text, and plain images.
+//!
+//! A [`UiTree`] holds [`UiNode`]s, each sized in pixels, as a
+//! percentage of its parent, or to fit its contents, and arranged by
+//! its parent: anchored in place, or stacked vertically or
+//! horizontally.  Whenever the render size might have changed (or
+//! just every frame), call [`UiTree::layout`] with
+//! [`crate::Renderer::render_size`] to recompute every node's
+//! [`UiRect`], then write the tree's sprites into a sprite group,
+//! e.g. with
+//! `writer.append_with(tree.sprite_count(), |trfs, uvs| tree.write_sprites(trfs, uvs, 0))`
+//! on a [`crate::SpriteGroupWriter`].
+//!
+//! Rectangles are in render pixels with the origin at the bottom
+//! left, so draw the group with a camera covering the render size
+//! (`screen_pos: [0.0, 0.0], screen_size: render_size`) and
+//! [`crate::sprites::DepthMode::OVERLAY`], which draws children over
+//! their parents.  [`UiTree::node_at`] finds the node under e.g. the
+//! mouse for handling clicks.
+
+use crate::bitfont::BitFont;
+use crate::nineslice::NineSlice;
+use crate::sprites::{SheetRegion, Transform};
+
+/// How big a [`UiNode`] is along one axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    /// A fixed size in pixels
+    Px(f32),
+    /// A percentage (from 0.0 to 100.0) of the parent's size inside its padding
+    Percent(f32),
+    /// Just big enough for the node's content and children, plus padding
+    Auto,
+}
+
+/// Where along one axis a [`UiNode`] sits within the space its parent gives it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    /// The left or top
+    #[default]
+    Start,
+    /// The middle
+    Center,
+    /// The right or bottom
+    End,
+}
+
+impl Align {
+    fn fraction(self) -> f32 {
+        match self {
+            Align::Start => 0.0,
+            Align::Center => 0.5,
+            Align::End => 1.0,
+        }
+    }
+}
+
+/// Where a [`UiNode`] is placed within its parent.  In stacks only
+/// the axis across the stack is used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Anchor {
+    /// The horizontal alignment
+    pub x: Align,
+    /// The vertical alignment
+    pub y: Align,
+}
+
+impl Anchor {
+    /// In the top left corner (the default)
+    pub const TOP_LEFT: Self = Self::new(Align::Start, Align::Start);
+    /// Centered along the top edge
+    pub const TOP: Self = Self::new(Align::Center, Align::Start);
+    /// In the top right corner
+    pub const TOP_RIGHT: Self = Self::new(Align::End, Align::Start);
+    /// Centered along the left edge
+    pub const LEFT: Self = Self::new(Align::Start, Align::Center);
+    /// In the middle
+    pub const CENTER: Self = Self::new(Align::Center, Align::Center);
+    /// Centered along the right edge
+    pub const RIGHT: Self = Self::new(Align::End, Align::Center);
+    /// In the bottom left corner
+    pub const BOTTOM_LEFT: Self = Self::new(Align::Start, Align::End);
+    /// Centered along the bottom edge
+    pub const BOTTOM: Self = Self::new(Align::Center, Align::End);
+    /// In the bottom right corner
+    pub const BOTTOM_RIGHT: Self = Self::new(Align::End, Align::End);
+    /// An anchor with the given horizontal and vertical alignments.
+    pub const fn new(x: Align, y: Align) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Space kept clear inside a [`UiNode`]'s edges, in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Padding {
+    /// Space inside the left edge
+    pub left: f32,
+    /// Space inside the right edge
+    pub right: f32,
+    /// Space inside the top edge
+    pub top: f32,
+    /// Space inside the bottom edge
+    pub bottom: f32,
+}
+
+impl Padding {
+    /// The same padding on every side.
+    pub const fn all(pad: f32) -> Self {
+        Self::symmetric(pad, pad)
+    }
+    /// `horizontal` padding on the left and right and `vertical` on the top and bottom.
+    pub const fn symmetric(horizontal: f32, vertical: f32) -> Self {
+        Self {
+            left: horizontal,
+            right: horizontal,
+            top: vertical,
+            bottom: vertical,
+        }
+    }
+}
+
+/// How a [`UiNode`] arranges its children.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Layout {
+    /// Each child is placed by its own [`Anchor`], possibly overlapping the others
+    #[default]
+    Overlay,
+    /// Children are stacked from top to bottom with `gap` pixels between them
+    Vertical { gap: f32 },
+    /// Children are stacked from left to right with `gap` pixels between them
+    Horizontal { gap: f32 },
+}
+
+/// What a [`UiNode`] draws.
+#[derive(Clone, Debug, Default)]
+pub enum UiContent {
+    /// Nothing; the node only arranges its children
+    #[default]
+    Empty,
+    /// A nine-slice box filling the node
+    Panel(NineSlice),
+    /// A single sprite filling the node; its natural size is the region's size
+    Image(SheetRegion),
+    /// A line of text in the top left corner inside the node's padding
+    Text {
+        /// The font to draw with
+        font: BitFont,
+        /// The text to draw
+        text: String,
+        /// The height of each character in pixels
+        char_height: f32,
+    },
+}
+
+/// A rectangle in render pixels, from its bottom left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UiRect {
+    /// The left edge
+    pub x: f32,
+    /// The bottom edge
+    pub y: f32,
+    /// The width
+    pub w: f32,
+    /// The height
+    pub h: f32,
+}
+
+impl UiRect {
+    /// Whether `point` is inside the rectangle.
+    pub fn contains(&self, [x, y]: [f32; 2]) -> bool {
+        x >= self.x && x <= self.x + self.w && y >= self.y && y <= self.y + self.h
+    }
+}
+
+/// One element of a [`UiTree`].
+#[derive(Clone, Debug)]
+pub struct UiNode {
+    /// How wide the node is
+    pub width: Size,
+    /// How tall the node is
+    pub height: Size,
+    /// Space kept clear around the node's content and children
+    pub padding: Padding,
+    /// Where the node sits within its parent
+    pub anchor: Anchor,
+    /// Moves the node from where its parent puts it, in pixels (positive y is up)
+    pub offset: [f32; 2],
+    /// How the node arranges its children
+    pub layout: Layout,
+    /// What the node draws
+    pub content: UiContent,
+    /// Hidden nodes and their children take up no space and draw nothing
+    pub visible: bool,
+}
+
+impl UiNode {
+    /// A visible, automatically sized node drawing `content`.
+    pub fn new(content: UiContent) -> Self {
+        Self {
+            width: Size::Auto,
+            height: Size::Auto,
+            padding: Padding::default(),
+            anchor: Anchor::default(),
+            offset: [0.0; 2],
+            layout: Layout::default(),
+            content,
+            visible: true,
+        }
+    }
+    /// Produce a new [`UiNode`] with the given width and height.
+    pub fn with_size(self, width: Size, height: Size) -> Self {
+        Self {
+            width,
+            height,
+            ..self
+        }
+    }
+    /// Produce a new [`UiNode`] with the given padding.
+    pub fn with_padding(self, padding: Padding) -> Self {
+        Self { padding, ..self }
+    }
+    /// Produce a new [`UiNode`] placed by the given anchor.
+    pub fn with_anchor(self, anchor: Anchor) -> Self {
+        Self { anchor, ..self }
+    }
+    /// Produce a new [`UiNode`] moved by the given offset.
+    pub fn with_offset(self, offset: [f32; 2]) -> Self {
+        Self { offset, ..self }
+    }
+    /// Produce a new [`UiNode`] arranging its children with the given layout.
+    pub fn with_layout(self, layout: Layout) -> Self {
+        Self { layout, ..self }
+    }
+    // The size of the node's content alone
+    fn content_size(&self) -> [f32; 2] {
+        match &self.content {
+            UiContent::Empty | UiContent::Panel(_) => [0.0, 0.0],
+            UiContent::Image(region) => [
+                region.w.unsigned_abs() as f32,
+                region.h.unsigned_abs() as f32,
+            ],
+            UiContent::Text {
+                font,
+                text,
+                char_height,
+            } => {
+                let (char_w, char_h) = font.char_size();
+                let char_width = char_w as f32 / char_h as f32 * char_height;
+                // BitFont::draw_text advances twice for whitespace
+                let advances: usize = text
+                    .chars()
+                    .map(|c| if c.is_whitespace() { 2 } else { 1 })
+                    .sum();
+                [advances as f32 * char_width, *char_height]
+            }
+        }
+    }
+    fn sprite_count(&self, rect: &UiRect) -> usize {
+        match &self.content {
+            UiContent::Empty => 0,
+            UiContent::Panel(panel) => panel.sprite_count(rect.w, rect.h),
+            UiContent::Image(_) => 1,
+            UiContent::Text { text, .. } => text.len(),
+        }
+    }
+}
+
+/// A node's place in a [`UiTree`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UiId(usize);
+
+struct UiEntry {
+    node: UiNode,
+    parent: Option<UiId>,
+    children: Vec<UiId>,
+    rect: UiRect,
+    // The node's automatic size, from the last layout
+    measured: [f32; 2],
+}
+
+/// A tree of [`UiNode`]s laid out to fill the render area; see the
+/// [module docs](self).
+pub struct UiTree {
+    entries: Vec<Option<UiEntry>>,
+    free: Vec<usize>,
+}
+
+impl Default for UiTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UiTree {
+    /// The root node, which always fills the render area and anchors its children
+    pub const ROOT: UiId = UiId(0);
+    /// Creates a tree with only the root node.
+    pub fn new() -> Self {
+        Self {
+            entries: vec![Some(UiEntry {
+                node: UiNode::new(UiContent::Empty),
+                parent: None,
+                children: vec![],
+                rect: UiRect::default(),
+                measured: [0.0; 2],
+            })],
+            free: vec![],
+        }
+    }
+    fn entry(&self, id: UiId) -> &UiEntry {
+        self.entries[id.0].as_ref().expect("UI node was removed")
+    }
+    fn entry_mut(&mut self, id: UiId) -> &mut UiEntry {
+        self.entries[id.0].as_mut().expect("UI node was removed")
+    }
+    /// Adds `node` as the last child of `parent`, returning its id.
+    /// Panics if `parent` has been removed.
+    pub fn add(&mut self, parent: UiId, node: UiNode) -> UiId {
+        let entry = UiEntry {
+            node,
+            parent: Some(parent),
+            children: vec![],
+            rect: UiRect::default(),
+            measured: [0.0; 2],
+        };
+        let id = match self.free.pop() {
+            Some(idx) => {
+                self.entries[idx] = Some(entry);
+                UiId(idx)
+            }
+            None => {
+                self.entries.push(Some(entry));
+                UiId(self.entries.len() - 1)
+            }
+        };
+        self.entry_mut(parent).children.push(id);
+        id
+    }
+    /// Removes a node and all of its children.  Panics if `id` is
+    /// [`UiTree::ROOT`] or has already been removed.
+    pub fn remove(&mut self, id: UiId) {
+        assert_ne!(id, Self::ROOT, "Can't remove the root UI node");
+        let parent = self.entry(id).parent.unwrap();
+        self.entry_mut(parent).children.retain(|&child| child != id);
+        let mut doomed = vec![id];
+        while let Some(id) = doomed.pop() {
+            let entry = self.entries[id.0].take().unwrap();
+            doomed.extend(entry.children);
+            self.free.push(id.0);
+        }
+    }
+    /// The given node.  Panics if it has been removed.
+    pub fn node(&self, id: UiId) -> &UiNode {
+        &self.entry(id).node
+    }
+    /// The given node, for changing; the change shows up after the
+    /// next [`UiTree::layout`].  Panics if it has been removed.
+    pub fn node_mut(&mut self, id: UiId) -> &mut UiNode {
+        &mut self.entry_mut(id).node
+    }
+    /// The given node's parent, or `None` for the root.
+    pub fn parent(&self, id: UiId) -> Option<UiId> {
+        self.entry(id).parent
+    }
+    /// The given node's children, in drawing order.
+    pub fn children(&self, id: UiId) -> &[UiId] {
+        &self.entry(id).children
+    }
+    /// Where the given node was put by the last [`UiTree::layout`].
+    pub fn rect(&self, id: UiId) -> UiRect {
+        self.entry(id).rect
+    }
+    /// Recomputes every node's rectangle to fill a render area of the given size.
+    pub fn layout(&mut self, (width, height): (u32, u32)) {
+        self.measure(Self::ROOT);
+        self.place(
+            Self::ROOT,
+            UiRect {
+                x: 0.0,
+                y: 0.0,
+                w: width as f32,
+                h: height as f32,
+            },
+        );
+    }
+    fn visible_children(&self, id: UiId) -> Vec<UiId> {
+        self.entry(id)
+            .children
+            .iter()
+            .copied()
+            .filter(|&child| self.entry(child).node.visible)
+            .collect()
+    }
+    // Computes automatic sizes from the leaves up; children sized by
+    // percentage don't count towards their parents' automatic sizes
+    fn measure(&mut self, id: UiId) -> [f32; 2] {
+        let children: Vec<[f32; 2]> = self
+            .visible_children(id)
+            .into_iter()
+            .map(|child| {
+                let measured = self.measure(child);
+                let node = &self.entry(child).node;
+                let fixed = |size: Size, auto: f32| match size {
+                    Size::Px(px) => px,
+                    Size::Percent(_) => 0.0,
+                    Size::Auto => auto,
+                };
+                [
+                    fixed(node.width, measured[0]),
+                    fixed(node.height, measured[1]),
+                ]
+            })
+            .collect();
+        let node = &self.entry(id).node;
+        let gaps = |gap: f32| gap * children.len().saturating_sub(1) as f32;
+        let max = |axis: usize| children.iter().map(|c| c[axis]).fold(0.0, f32::max);
+        let sum = |axis: usize| children.iter().map(|c| c[axis]).sum::<f32>();
+        let inner = match node.layout {
+            Layout::Overlay => [max(0), max(1)],
+            Layout::Vertical { gap } => [max(0), sum(1) + gaps(gap)],
+            Layout::Horizontal { gap } => [sum(0) + gaps(gap), max(1)],
+        };
+        let content = node.content_size();
+        let pad = node.padding;
+        let measured = [
+            content[0].max(inner[0]) + pad.left + pad.right,
+            content[1].max(inner[1]) + pad.top + pad.bottom,
+        ];
+        self.entry_mut(id).measured = measured;
+        measured
+    }
+    fn place(&mut self, id: UiId, rect: UiRect) {
+        self.entry_mut(id).rect = rect;
+        let node = &self.entry(id).node;
+        let pad = node.padding;
+        let layout = node.layout;
+        let inner = UiRect {
+            x: rect.x + pad.left,
+            y: rect.y + pad.bottom,
+            w: (rect.w - pad.left - pad.right).max(0.0),
+            h: (rect.h - pad.top - pad.bottom).max(0.0),
+        };
+        let resolve = |size: Size, space: f32, auto: f32| match size {
+            Size::Px(px) => px,
+            Size::Percent(pct) => space * pct / 100.0,
+            Size::Auto => auto,
+        };
+        // Where a child of the given size sits across the space, top or left first
+        let align_x = |align: Align, w: f32| inner.x + align.fraction() * (inner.w - w);
+        let align_y = |align: Align, h: f32| inner.y + (1.0 - align.fraction()) * (inner.h - h);
+        let mut cursor = match layout {
+            Layout::Horizontal { .. } => inner.x,
+            _ => inner.y + inner.h,
+        };
+        for child in self.visible_children(id) {
+            let entry = self.entry(child);
+            let node = &entry.node;
+            let w = resolve(node.width, inner.w, entry.measured[0]);
+            let h = resolve(node.height, inner.h, entry.measured[1]);
+            let (x, y) = match layout {
+                Layout::Overlay => (align_x(node.anchor.x, w), align_y(node.anchor.y, h)),
+                Layout::Vertical { gap } => {
+                    let y = cursor - h;
+                    cursor = y - gap;
+                    (align_x(node.anchor.x, w), y)
+                }
+                Layout::Horizontal { gap } => {
+                    let x = cursor;
+                    cursor = x + w + gap;
+                    (x, align_y(node.anchor.y, h))
+                }
+            };
+            let [dx, dy] = node.offset;
+            self.place(
+                child,
+                UiRect {
+                    x: x + dx,
+                    y: y + dy,
+                    w,
+                    h,
+                },
+            );
+        }
+    }
+    /// Returns the topmost visible node (other than the root) whose
+    /// rectangle contains `point`, in render pixels from the bottom
+    /// left.  Walk up with [`UiTree::parent`] to find e.g. the button
+    /// a label belongs to.
+    pub fn node_at(&self, point: [f32; 2]) -> Option<UiId> {
+        self.hit(Self::ROOT, point).filter(|&id| id != Self::ROOT)
+    }
+    fn hit(&self, id: UiId, point: [f32; 2]) -> Option<UiId> {
+        let entry = self.entry(id);
+        // Later children draw over earlier ones, and over their parents
+        entry
+            .children
+            .iter()
+            .rev()
+            .filter(|&&child| self.entry(child).node.visible)
+            .find_map(|&child| self.hit(child, point))
+            .or_else(|| entry.rect.contains(point).then_some(id))
+    }
+    /// Returns how many sprites [`UiTree::write_sprites`] needs room
+    /// for with the current layout.
+    pub fn sprite_count(&self) -> usize {
+        self.entries
+            .iter()
+            .flatten()
+            .filter(|entry| self.shown(entry))
+            .map(|entry| entry.node.sprite_count(&entry.rect))
+            .sum()
+    }
+    // Whether a node and all its ancestors are visible
+    fn shown(&self, entry: &UiEntry) -> bool {
+        entry.node.visible
+            && entry
+                .parent
+                .map_or(true, |parent| self.shown(self.entry(parent)))
+    }
+    /// Writes the sprites of every visible node into `trfs` and
+    /// `uvs`, parents before their children, and returns how many
+    /// were written.  Panels and images are drawn at their regions'
+    /// depths plus `depth`; text is drawn at `depth`.  The slices must
+    /// have room for [`UiTree::sprite_count`] sprites.
+    pub fn write_sprites(
+        &self,
+        trfs: &mut [Transform],
+        uvs: &mut [SheetRegion],
+        depth: u16,
+    ) -> usize {
+        let mut used = 0;
+        let mut stack = vec![Self::ROOT];
+        while let Some(id) = stack.pop() {
+            let entry = self.entry(id);
+            if !entry.node.visible {
+                continue;
+            }
+            let rect = entry.rect;
+            let (trfs, uvs) = (&mut trfs[used..], &mut uvs[used..]);
+            used += match &entry.node.content {
+                UiContent::Empty => 0,
+                UiContent::Panel(panel) => {
+                    panel.draw(trfs, uvs, rect.x, rect.y, rect.w, rect.h, depth)
+                }
+                UiContent::Image(region) => {
+                    trfs[0] = Transform {
+                        w: rect.w as u16,
+                        h: rect.h as u16,
+                        x: rect.x + rect.w / 2.0,
+                        y: rect.y + rect.h / 2.0,
+                        rot: 0.0,
+                    };
+                    uvs[0] = region.with_depth(region.depth + depth);
+                    1
+                }
+                UiContent::Text {
+                    font,
+                    text,
+                    char_height,
+                } => {
+                    let pad = entry.node.padding;
+                    let top_left = [rect.x + pad.left, rect.y + rect.h - pad.top];
+                    font.draw_text(trfs, uvs, text, top_left, depth, *char_height)
+                        .1
+                }
+            };
+            // Pushed in reverse so the first child is drawn first
+            stack.extend(entry.children.iter().rev());
+        }
+        used
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(w: i16, h: i16) -> UiNode {
+        UiNode::new(UiContent::Image(SheetRegion::rect(0, 0, w, h)))
+    }
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> UiRect {
+        UiRect { x, y, w, h }
+    }
+
+    #[test]
+    fn test_overlay_layout() {
+        let mut tree = UiTree::new();
+        let fixed = tree.add(
+            UiTree::ROOT,
+            image(4, 4).with_size(Size::Px(50.0), Size::Px(20.0)),
+        );
+        let percent = tree.add(
+            UiTree::ROOT,
+            image(4, 4)
+                .with_size(Size::Percent(50.0), Size::Percent(25.0))
+                .with_anchor(Anchor::BOTTOM_RIGHT),
+        );
+        let auto = tree.add(
+            UiTree::ROOT,
+            image(16, 8)
+                .with_padding(Padding::all(2.0))
+                .with_anchor(Anchor::CENTER)
+                .with_offset([3.0, -2.0]),
+        );
+        tree.layout((200, 100));
+        assert_eq!(tree.rect(UiTree::ROOT), rect(0.0, 0.0, 200.0, 100.0));
+        assert_eq!(tree.rect(fixed), rect(0.0, 80.0, 50.0, 20.0));
+        assert_eq!(tree.rect(percent), rect(100.0, 0.0, 100.0, 25.0));
+        assert_eq!(tree.rect(auto), rect(93.0, 42.0, 20.0, 12.0));
+        // Layout follows the render size
+        tree.layout((100, 40));
+        assert_eq!(tree.rect(fixed), rect(0.0, 20.0, 50.0, 20.0));
+        assert_eq!(tree.rect(percent), rect(50.0, 0.0, 50.0, 10.0));
+    }
+    #[test]
+    fn test_stacks() {
+        let mut tree = UiTree::new();
+        let column = tree.add(
+            UiTree::ROOT,
+            UiNode::new(UiContent::Empty)
+                .with_padding(Padding::all(4.0))
+                .with_layout(Layout::Vertical { gap: 2.0 }),
+        );
+        let first = tree.add(column, image(10, 5).with_anchor(Anchor::RIGHT));
+        let hidden = tree.add(column, image(30, 30));
+        tree.node_mut(hidden).visible = false;
+        let second = tree.add(column, image(20, 6));
+        // A percentage sized child doesn't count towards an automatic size
+        let filler = tree.add(
+            column,
+            image(4, 4).with_size(Size::Percent(100.0), Size::Px(1.0)),
+        );
+        tree.layout((100, 100));
+        assert_eq!(tree.rect(column), rect(0.0, 76.0, 28.0, 24.0));
+        assert_eq!(tree.rect(first), rect(14.0, 91.0, 10.0, 5.0));
+        assert_eq!(tree.rect(second), rect(4.0, 83.0, 20.0, 6.0));
+        assert_eq!(tree.rect(filler), rect(4.0, 80.0, 20.0, 1.0));
+        assert_eq!(tree.rect(hidden), UiRect::default());
+
+        let row = tree.add(
+            UiTree::ROOT,
+            UiNode::new(UiContent::Empty)
+                .with_anchor(Anchor::BOTTOM_LEFT)
+                .with_layout(Layout::Horizontal { gap: 3.0 }),
+        );
+        let left = tree.add(row, image(10, 5));
+        let right = tree.add(row, image(20, 6).with_anchor(Anchor::BOTTOM));
+        tree.layout((100, 100));
+        assert_eq!(tree.rect(row), rect(0.0, 0.0, 33.0, 6.0));
+        assert_eq!(tree.rect(left), rect(0.0, 1.0, 10.0, 5.0));
+        assert_eq!(tree.rect(right), rect(13.0, 0.0, 20.0, 6.0));
+    }
+    #[test]
+    fn test_node_at() {
+        let mut tree = UiTree::new();
+        let square = |size: f32| image(4, 4).with_size(Size::Px(size), Size::Px(size));
+        let panel = tree.add(UiTree::ROOT, square(50.0));
+        let inner = tree.add(panel, square(10.0));
+        let over = tree.add(UiTree::ROOT, square(20.0));
+        tree.layout((100, 100));
+        // Later siblings are on top of earlier ones and their children
+        assert_eq!(tree.node_at([5.0, 95.0]), Some(over));
+        assert_eq!(tree.node_at([30.0, 60.0]), Some(panel));
+        // The root itself is never hit
+        assert_eq!(tree.node_at([80.0, 20.0]), None);
+        tree.node_mut(over).visible = false;
+        assert_eq!(tree.node_at([5.0, 95.0]), Some(inner));
+        // Children of hidden nodes can't be hit either
+        tree.node_mut(panel).visible = false;
+        assert_eq!(tree.node_at([5.0, 95.0]), None);
+    }
+    #[test]
+    fn test_remove_reuses_slots() {
+        let mut tree = UiTree::new();
+        let a = tree.add(UiTree::ROOT, image(4, 4));
+        let b = tree.add(a, image(4, 4));
+        let c = tree.add(UiTree::ROOT, image(4, 4));
+        tree.layout((10, 10));
+        assert_eq!(tree.sprite_count(), 3);
+        tree.remove(a);
+        assert_eq!(tree.children(UiTree::ROOT), [c]);
+        assert_eq!(tree.sprite_count(), 1);
+        let d = tree.add(c, image(4, 4));
+        let e = tree.add(UiTree::ROOT, image(4, 4));
+        let mut reused = [d, e];
+        reused.sort_by_key(|id| id.0);
+        assert_eq!(reused, [a, b]);
+        assert_eq!(tree.entries.len(), 4);
+        assert_eq!(tree.children(UiTree::ROOT), [c, e]);
+        assert_eq!(tree.parent(d), Some(c));
+        assert!(tree.children(d).is_empty());
+        tree.layout((10, 10));
+        assert_eq!(tree.sprite_count(), 3);
+        // A fresh slot is used once the free ones run out
+        assert_eq!(tree.add(d, image(4, 4)), UiId(4));
+    }
+    #[test]
+    #[should_panic]
+    fn test_remove_root() {
+        UiTree::new().remove(UiTree::ROOT);
+    }
+    #[test]
+    #[should_panic]
+    fn test_removed_node() {
+        let mut tree = UiTree::new();
+        let id = tree.add(UiTree::ROOT, image(4, 4));
+        tree.remove(id);
+        tree.node(id);
+    }
+}
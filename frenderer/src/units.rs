@@ -0,0 +1,93 @@
+//! Typed 2D positions for the three coordinate spaces frenderer
+//! works in, so that APIs say which space they expect and
+//! conversions between them go through one place:
+//!
+//! - [`SurfacePx`]: physical pixels of the window's surface, with
+//!   the origin at the top left and y pointing down (mouse and touch
+//!   positions);
+//! - [`RenderPx`]: pixels of the rendered image (see
+//!   [`crate::Renderer::render_size`]), also with the origin at the
+//!   top left and y pointing down;
+//! - [`WorldPos`]: a [`crate::sprites::Camera2D`]'s world space, with
+//!   y pointing up, in which [`crate::sprites::Transform`]s are
+//!   positioned.
+//!
+//! [`crate::Renderer::surface_px_to_render`] and
+//! [`crate::Renderer::render_px_to_surface`] account for the scale
+//! mode and postprocessing transform, while
+//! [`crate::sprites::Camera2D::render_to_world`] and
+//! [`crate::sprites::Camera2D::world_to_render`] account for the
+//! camera.  [`crate::Renderer::surface_px_to_world`] and
+//! [`crate::Renderer::world_to_surface_px`] do both at once.
+//!
+//! Each type wraps a plain `[f32; 2]` and converts to and from one
+//! with [`From`], so they can be introduced gradually.
+
+macro_rules! position_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+        pub struct $name(pub [f32; 2]);
+
+        impl $name {
+            /// The position `(x, y)`.
+            pub const fn new(x: f32, y: f32) -> Self {
+                Self([x, y])
+            }
+            /// The horizontal coordinate.
+            pub fn x(self) -> f32 {
+                self.0[0]
+            }
+            /// The vertical coordinate.
+            pub fn y(self) -> f32 {
+                self.0[1]
+            }
+        }
+
+        impl From<[f32; 2]> for $name {
+            fn from(pos: [f32; 2]) -> Self {
+                Self(pos)
+            }
+        }
+
+        impl From<$name> for [f32; 2] {
+            fn from(pos: $name) -> Self {
+                pos.0
+            }
+        }
+
+        impl std::ops::Add<[f32; 2]> for $name {
+            type Output = Self;
+            /// Offsets the position within the same space.
+            fn add(self, [dx, dy]: [f32; 2]) -> Self {
+                Self([self.0[0] + dx, self.0[1] + dy])
+            }
+        }
+
+        impl std::ops::Sub for $name {
+            type Output = [f32; 2];
+            /// The offset between two positions in the same space.
+            fn sub(self, other: Self) -> [f32; 2] {
+                [self.0[0] - other.0[0], self.0[1] - other.0[1]]
+            }
+        }
+    };
+}
+
+position_type!(
+    /// A position in world space as seen by a
+    /// [`crate::sprites::Camera2D`], with y pointing up.
+    WorldPos
+);
+position_type!(
+    /// A position in the rendered image in pixels, with the origin at
+    /// its top left and y pointing down.
+    RenderPx
+);
+position_type!(
+    /// A position on the surface in physical pixels, with the origin
+    /// at its top left and y pointing down, like mouse and touch
+    /// positions.
+    SurfacePx
+);
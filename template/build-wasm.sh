@@ -0,0 +1,23 @@
+#!/bin/sh
+# Builds the game for the web into dist/, ready to serve as static
+# files (e.g. `python3 -m http.server -d dist`) or upload from CI.
+# Pass --webgl to target WebGL instead of WebGPU.
+set -eu
+
+features=""
+if [ "${1:-}" = "--webgl" ]; then
+    features="--features webgl"
+fi
+
+rustup target add wasm32-unknown-unknown
+[ -f Cargo.lock ] || cargo generate-lockfile
+# wasm-bindgen's CLI has to match the library version the game links
+bindgen_version=$(grep -A1 '^name = "wasm-bindgen"$' Cargo.lock | sed -n 's/^version = "\(.*\)"$/\1/p')
+if [ "$(wasm-bindgen --version 2>/dev/null)" != "wasm-bindgen $bindgen_version" ]; then
+    cargo install --locked wasm-bindgen-cli --version "$bindgen_version"
+fi
+
+cargo build --release --target wasm32-unknown-unknown $features
+wasm-bindgen --target web --no-typescript --out-dir dist \
+    "target/wasm32-unknown-unknown/release/{{crate_name}}.wasm"
+cp web/index.html dist/index.html
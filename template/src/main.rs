@@ -0,0 +1,75 @@
+use frapp::assets_manager::asset::Png;
+use frapp::frenderer::input::{Input, Key};
+use frapp::frenderer::sprites::{Camera2D, SheetRegion, Transform};
+use frapp::frenderer::*;
+use frapp::*;
+
+const W: f32 = 320.0;
+const H: f32 = 240.0;
+const SPEED: f32 = 2.0;
+
+struct Game {
+    #[allow(dead_code)]
+    assets: AssetCache,
+    player: [f32; 2],
+}
+
+impl App for Game {
+    type Renderer = Immediate;
+    const DT: f32 = 1.0 / 60.0;
+    fn new(renderer: &mut Self::Renderer, assets: AssetCache) -> Self {
+        // Loads content/player.png; native builds read the content
+        // folder at runtime and web builds embed it
+        let player_img = assets
+            .load::<Png>("player")
+            .expect("Couldn't load player image")
+            .read()
+            .0
+            .to_rgba8();
+        let player_tex = renderer.create_array_texture(
+            &[&player_img],
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            player_img.dimensions(),
+            Some("player.png"),
+        );
+        renderer.sprite_group_add(
+            &player_tex,
+            1,
+            Camera2D {
+                screen_pos: [0.0, 0.0],
+                screen_size: [W, H],
+            },
+        );
+        Self {
+            assets,
+            player: [W / 2.0, H / 2.0],
+        }
+    }
+    fn update(&mut self, _renderer: &mut Self::Renderer, input: &Input) {
+        self.player[0] += input.key_axis(Key::ArrowLeft, Key::ArrowRight) * SPEED;
+        self.player[1] += input.key_axis(Key::ArrowDown, Key::ArrowUp) * SPEED;
+        self.player[0] = self.player[0].clamp(0.0, W);
+        self.player[1] = self.player[1].clamp(0.0, H);
+    }
+    fn render(&mut self, renderer: &mut Self::Renderer, _dt: f32, _input: &Input) {
+        let [x, y] = self.player;
+        renderer.draw_sprite(
+            0,
+            Transform {
+                x,
+                y,
+                w: 32,
+                h: 32,
+                rot: 0.0,
+            },
+            SheetRegion::new(0, 0, 0, 0, 16, 16),
+        );
+    }
+}
+
+fn main() {
+    app!(Game, "content").run(
+        WindowBuilder::new().with_title("{{window_title}}"),
+        Some((W as u32, H as u32)),
+    );
+}